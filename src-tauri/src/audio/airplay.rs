@@ -0,0 +1,608 @@
+//! AirPlay output: finds AirPlay speakers over mDNS and streams the engine's processed
+//! (post-DSP) output to them over RAOP, i.e. RTSP session setup followed by RTP packets
+//! of uncompressed ALAC at 44.1 kHz stereo.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use super::engine::SharedOutput;
+use super::mdns;
+use super::output::{OutputBackend, OutputFormat, OutputSampleKind, RenderCallback, StreamGuard};
+
+const RAOP_SERVICE: &str = "_raop._tcp.local";
+/// How long discovery listens for mDNS answers.
+pub const DISCOVERY_WINDOW: Duration = Duration::from_secs(3);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RTSP_TIMEOUT: Duration = Duration::from_secs(10);
+/// RAOP receivers only accept 44.1 kHz stereo; the engine resamples to it.
+const SAMPLE_RATE: u32 = 44_100;
+const CHANNELS: u16 = 2;
+/// Frames per RTP packet, as announced in the ALAC `fmtp` line.
+const FRAMES_PER_PACKET: usize = 352;
+/// Audio is sent this far ahead of real time so network jitter does not cause dropouts.
+const SEND_AHEAD: Duration = Duration::from_millis(250);
+/// Playback delay announced in sync packets, on top of the receiver's own buffering.
+const LATENCY_FRAMES: u32 = 11_025;
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+/// Read timeout of the timing responder, i.e. how quickly it notices a closed session.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Seconds between the NTP epoch (1900) and the Unix epoch.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// An AirPlay speaker (AirPort Express, HomePod, AirPlay-enabled receivers).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AirPlayDevice {
+    /// The device's MAC address, which prefixes its RAOP instance name.
+    pub id: String,
+    pub name: String,
+    pub model: Option<String>,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Browses mDNS for `_raop._tcp` speakers answering within `window`. Devices that only
+/// accept encrypted audio are left out, since the sender streams in the clear.
+pub fn discover(window: Duration) -> Result<Vec<AirPlayDevice>, String> {
+    let mut devices = mdns::browse(RAOP_SERVICE, window)?
+        .into_iter()
+        .filter(|service| {
+            service
+                .txt
+                .get("et")
+                .is_none_or(|types| types.split(',').any(|kind| kind.trim() == "0"))
+        })
+        .map(|service| {
+            let (id, name) = service
+                .label()
+                .split_once('@')
+                .map(|(id, name)| (id.to_string(), name.to_string()))
+                .unwrap_or_else(|| (service.label().to_string(), service.label().to_string()));
+            AirPlayDevice {
+                id,
+                name,
+                model: service.txt.get("am").cloned(),
+                host: service.host.to_string(),
+                port: service.port,
+            }
+        })
+        .collect::<Vec<_>>();
+    devices.dedup_by(|a, b| a.id == b.id);
+    devices.sort_by_key(|device| device.name.to_lowercase());
+    Ok(devices)
+}
+
+/// Writes big-endian bit fields, as the ALAC bitstream expects.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: usize) {
+        for shift in (0..count).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if (value >> shift) & 1 == 1 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+}
+
+/// Encodes interleaved stereo samples as one uncompressed ALAC frame: a channel-pair
+/// element flagged "not compressed", the raw 16-bit samples, then the end tag.
+fn encode_alac_frame(samples: &[f32]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.write(1, 3); // channel pair element
+    writer.write(0, 4); // element instance tag
+    writer.write(0, 12); // unused
+    writer.write(0, 1); // no explicit frame size
+    writer.write(0, 2); // no shifted bytes
+    writer.write(1, 1); // not compressed
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write(value as u16 as u32, 16);
+    }
+    writer.write(7, 3); // end of frame
+    writer.bytes
+}
+
+/// Current wall clock as a 64-bit NTP timestamp.
+fn ntp_now() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let fraction = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    ((now.as_secs() + NTP_UNIX_OFFSET) << 32) | fraction
+}
+
+/// Splits an RTSP `Transport` header into its `key=value` parameters.
+fn parse_transport(header: &str) -> HashMap<String, String> {
+    header
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+fn random_u32() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    (nanos as u32) ^ ((nanos >> 32) as u32).rotate_left(13) ^ std::process::id()
+}
+
+/// One RTSP control connection. Requests are answered in order on the same socket.
+struct RtspClient {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    url: String,
+    cseq: u32,
+    session: Option<String>,
+    client_instance: String,
+}
+
+impl RtspClient {
+    fn connect(address: SocketAddr) -> Result<Self, String> {
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+            .map_err(|e| format!("Failed to connect to AirPlay device {address}: {e}"))?;
+        stream
+            .set_read_timeout(Some(RTSP_TIMEOUT))
+            .map_err(|e| format!("Failed to configure AirPlay connection: {e}"))?;
+        let local = stream
+            .local_addr()
+            .map_err(|e| format!("Failed to read local address: {e}"))?;
+        let reader = BufReader::new(
+            stream
+                .try_clone()
+                .map_err(|e| format!("Failed to clone AirPlay connection: {e}"))?,
+        );
+        let client_instance = format!("{:08X}{:08X}", random_u32(), random_u32());
+        Ok(Self {
+            stream,
+            reader,
+            url: format!("rtsp://{}/{}", local.ip(), random_u32()),
+            cseq: 0,
+            session: None,
+            client_instance,
+        })
+    }
+
+    fn local_ip(&self) -> Result<IpAddr, String> {
+        self.stream
+            .local_addr()
+            .map(|addr| addr.ip())
+            .map_err(|e| format!("Failed to read local address: {e}"))
+    }
+
+    /// Sends `method` and returns the response headers (names lowercased), failing on
+    /// anything but `200 OK`.
+    fn request(
+        &mut self,
+        method: &str,
+        headers: &[(&str, String)],
+        body: Option<(&str, &[u8])>,
+    ) -> Result<HashMap<String, String>, String> {
+        self.cseq += 1;
+        let mut message = format!(
+            "{method} {} RTSP/1.0\r\nCSeq: {}\r\nUser-Agent: PowerPlayer/0.1\r\nClient-Instance: {}\r\nDACP-ID: {}\r\n",
+            self.url, self.cseq, self.client_instance, self.client_instance
+        );
+        if let Some(session) = &self.session {
+            message.push_str(&format!("Session: {session}\r\n"));
+        }
+        for (name, value) in headers {
+            message.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if let Some((content_type, body)) = body {
+            message.push_str(&format!(
+                "Content-Type: {content_type}\r\nContent-Length: {}\r\n",
+                body.len()
+            ));
+        }
+        message.push_str("\r\n");
+        let mut bytes = message.into_bytes();
+        if let Some((_, body)) = body {
+            bytes.extend_from_slice(body);
+        }
+        self.stream
+            .write_all(&bytes)
+            .map_err(|e| format!("AirPlay {method} failed: {e}"))?;
+
+        let mut status = String::new();
+        self.reader
+            .read_line(&mut status)
+            .map_err(|e| format!("AirPlay {method} got no answer: {e}"))?;
+        let mut response = HashMap::new();
+        loop {
+            let mut line = String::new();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| format!("AirPlay {method} answer was cut short: {e}"))?;
+            if read == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                response.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+        let length = response
+            .get("content-length")
+            .and_then(|length| length.parse::<u64>().ok())
+            .unwrap_or(0);
+        io::copy(&mut (&mut self.reader).take(length), &mut io::sink())
+            .map_err(|e| format!("AirPlay {method} answer was cut short: {e}"))?;
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(format!(
+                "AirPlay device rejected {method}: {}",
+                status.trim()
+            ));
+        }
+        Ok(response)
+    }
+}
+
+/// The render callback installed by the engine. The generation tells a stale guard
+/// (from a previous track) not to clear its successor's callback.
+type RenderSlot = Mutex<Option<(u64, RenderCallback)>>;
+
+struct SessionShared {
+    device_name: String,
+    render: RenderSlot,
+    generation: AtomicU64,
+    stop: AtomicBool,
+}
+
+/// Ports the receiver gave us in `SETUP`, plus our sockets for each channel.
+struct RtpChannels {
+    audio: UdpSocket,
+    control: UdpSocket,
+    control_peer: SocketAddr,
+    timing: UdpSocket,
+}
+
+/// An open RAOP session: the RTSP connection plus the sender and timing threads.
+struct RaopSession {
+    device: AirPlayDevice,
+    rtsp: RtspClient,
+    shared: Arc<SessionShared>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl RaopSession {
+    fn open(device: AirPlayDevice) -> Result<Self, String> {
+        let host: IpAddr = device
+            .host
+            .parse()
+            .map_err(|_| format!("Invalid AirPlay device address: {}", device.host))?;
+        let mut rtsp = RtspClient::connect(SocketAddr::new(host, device.port))?;
+        let local_ip = rtsp.local_ip()?;
+        let bind = |purpose: &str| {
+            UdpSocket::bind(SocketAddr::new(local_ip, 0))
+                .map_err(|e| format!("Failed to open AirPlay {purpose} socket: {e}"))
+        };
+        let audio = bind("audio")?;
+        let control = bind("control")?;
+        let timing = bind("timing")?;
+        let port_of = |socket: &UdpSocket| {
+            socket
+                .local_addr()
+                .map(|addr| addr.port())
+                .map_err(|e| format!("Failed to read AirPlay socket port: {e}"))
+        };
+
+        let (ip_kind, local, remote) = match (local_ip, host) {
+            (IpAddr::V4(local), IpAddr::V4(remote)) => {
+                ("IP4", local.to_string(), remote.to_string())
+            }
+            (local, remote) => ("IP6", local.to_string(), remote.to_string()),
+        };
+        let sdp = format!(
+            "v=0\r\no=iTunes {} 0 IN {ip_kind} {local}\r\ns=iTunes\r\nc=IN {ip_kind} {remote}\r\nt=0 0\r\nm=audio 0 RTP/AVP 96\r\na=rtpmap:96 AppleLossless\r\na=fmtp:96 {FRAMES_PER_PACKET} 0 16 40 10 14 {CHANNELS} 255 0 0 {SAMPLE_RATE}\r\n",
+            random_u32()
+        );
+        rtsp.request("ANNOUNCE", &[], Some(("application/sdp", sdp.as_bytes())))?;
+        let setup = rtsp.request(
+            "SETUP",
+            &[(
+                "Transport",
+                format!(
+                    "RTP/AVP/UDP;unicast;interleaved=0-1;mode=record;control_port={};timing_port={}",
+                    port_of(&control)?,
+                    port_of(&timing)?
+                ),
+            )],
+            None,
+        )?;
+        rtsp.session = Some(
+            setup
+                .get("session")
+                .and_then(|session| session.split(';').next())
+                .ok_or_else(|| "AirPlay device did not open a session".to_string())?
+                .to_string(),
+        );
+        let transport = parse_transport(setup.get("transport").map_or("", String::as_str));
+        let port = |key: &str| {
+            transport
+                .get(key)
+                .and_then(|port| port.parse::<u16>().ok())
+                .ok_or_else(|| format!("AirPlay device did not assign a {key}"))
+        };
+        audio
+            .connect(SocketAddr::new(host, port("server_port")?))
+            .map_err(|e| format!("Failed to reach AirPlay audio port: {e}"))?;
+        let channels = RtpChannels {
+            audio,
+            control,
+            control_peer: SocketAddr::new(host, port("control_port")?),
+            timing,
+        };
+
+        let sequence = random_u32() as u16;
+        let rtp_time = random_u32();
+        rtsp.request(
+            "RECORD",
+            &[
+                ("Range", "npt=0-".to_string()),
+                ("RTP-Info", format!("seq={sequence};rtptime={rtp_time}")),
+            ],
+            None,
+        )?;
+
+        let shared = Arc::new(SessionShared {
+            device_name: format!("AirPlay: {}", device.name),
+            render: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            stop: AtomicBool::new(false),
+        });
+        let timing = channels
+            .timing
+            .try_clone()
+            .map_err(|e| format!("Failed to clone AirPlay timing socket: {e}"))?;
+        let timing_shared = Arc::clone(&shared);
+        let sender_shared = Arc::clone(&shared);
+        let threads = vec![
+            thread::spawn(move || answer_timing_requests(timing, &timing_shared)),
+            thread::spawn(move || send_audio(channels, &sender_shared, sequence, rtp_time)),
+        ];
+        Ok(Self {
+            device,
+            rtsp,
+            shared,
+            threads,
+        })
+    }
+
+    fn close(mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+        if let Err(err) = self.rtsp.request("TEARDOWN", &[], None) {
+            eprintln!("AirPlay teardown failed: {err}");
+        }
+    }
+}
+
+/// Replies to the receiver's NTP-style timing requests so it can sync its clock to ours.
+fn answer_timing_requests(socket: UdpSocket, shared: &SessionShared) {
+    if socket.set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        return;
+    }
+    let mut buffer = [0_u8; 128];
+    while !shared.stop.load(Ordering::SeqCst) {
+        let Ok((read, from)) = socket.recv_from(&mut buffer) else {
+            continue;
+        };
+        if read < 32 || buffer[1] & 0x7F != 0x52 {
+            continue;
+        }
+        let received = ntp_now();
+        let mut reply = [0_u8; 32];
+        reply[..4].copy_from_slice(&[0x80, 0xD3, 0x00, 0x07]);
+        // Origin time is the request's transmit time.
+        reply[8..16].copy_from_slice(&buffer[24..32]);
+        reply[16..24].copy_from_slice(&received.to_be_bytes());
+        reply[24..32].copy_from_slice(&ntp_now().to_be_bytes());
+        let _ = socket.send_to(&reply, from);
+    }
+}
+
+/// Pulls packets from the engine's render callback (or silence while none is installed)
+/// and sends them paced to real time, with a sync packet every second.
+fn send_audio(channels: RtpChannels, shared: &SessionShared, mut sequence: u16, mut rtp_time: u32) {
+    let ssrc = random_u32();
+    let mut samples = vec![0.0_f32; FRAMES_PER_PACKET * CHANNELS as usize];
+    let started = Instant::now();
+    let mut sent_frames = 0_u64;
+    let mut last_sync: Option<Instant> = None;
+    while !shared.stop.load(Ordering::SeqCst) {
+        let due = started + Duration::from_secs_f64(sent_frames as f64 / SAMPLE_RATE as f64);
+        let now = Instant::now();
+        if due > now + SEND_AHEAD {
+            thread::sleep((due - now - SEND_AHEAD).min(POLL_INTERVAL));
+            continue;
+        }
+
+        if last_sync.is_none_or(|last| last.elapsed() >= SYNC_INTERVAL) {
+            let mut sync = [0_u8; 20];
+            sync[0] = if last_sync.is_none() { 0x90 } else { 0x80 };
+            sync[1..4].copy_from_slice(&[0xD4, 0x00, 0x07]);
+            sync[4..8].copy_from_slice(&rtp_time.wrapping_sub(LATENCY_FRAMES).to_be_bytes());
+            sync[8..16].copy_from_slice(&ntp_now().to_be_bytes());
+            sync[16..20].copy_from_slice(&rtp_time.to_be_bytes());
+            let _ = channels.control.send_to(&sync, channels.control_peer);
+            last_sync = Some(Instant::now());
+        }
+
+        match shared.render.lock() {
+            Ok(mut render) => match render.as_mut() {
+                Some((_, callback)) => callback(&mut samples),
+                None => samples.fill(0.0),
+            },
+            Err(_) => samples.fill(0.0),
+        }
+        let mut packet = Vec::with_capacity(12 + samples.len() * 2 + 4);
+        packet.push(0x80);
+        packet.push(if sent_frames == 0 { 0xE0 } else { 0x60 });
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        packet.extend_from_slice(&rtp_time.to_be_bytes());
+        packet.extend_from_slice(&ssrc.to_be_bytes());
+        packet.extend_from_slice(&encode_alac_frame(&samples));
+        if let Err(err) = channels.audio.send(&packet) {
+            eprintln!("AirPlay audio packet failed: {err}");
+        }
+        sequence = sequence.wrapping_add(1);
+        rtp_time = rtp_time.wrapping_add(FRAMES_PER_PACKET as u32);
+        sent_frames += FRAMES_PER_PACKET as u64;
+    }
+}
+
+/// Engine output that feeds an AirPlay session instead of a local device.
+struct RaopBackend {
+    shared: Arc<SessionShared>,
+}
+
+/// Uninstalls the render callback when the engine drops the stream.
+struct RenderGuard {
+    shared: Arc<SessionShared>,
+    generation: u64,
+}
+
+impl Drop for RenderGuard {
+    fn drop(&mut self) {
+        if let Ok(mut render) = self.shared.render.lock() {
+            if render
+                .as_ref()
+                .is_some_and(|(generation, _)| *generation == self.generation)
+            {
+                render.take();
+            }
+        }
+    }
+}
+
+impl OutputBackend for RaopBackend {
+    fn device_name(&self) -> String {
+        self.shared.device_name.clone()
+    }
+
+    fn negotiate(&self, sample_rate: u32, _channels: u16) -> Result<OutputFormat, String> {
+        Ok(OutputFormat {
+            sample_rate: SAMPLE_RATE,
+            channels: CHANNELS,
+            sample_kind: OutputSampleKind::F32,
+            exact_rate: sample_rate == SAMPLE_RATE,
+        })
+    }
+
+    fn start(&self, _format: &OutputFormat, render: RenderCallback) -> Result<StreamGuard, String> {
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *self
+            .shared
+            .render
+            .lock()
+            .map_err(|_| "AirPlay render lock poisoned".to_string())? = Some((generation, render));
+        Ok(Box::new(RenderGuard {
+            shared: Arc::clone(&self.shared),
+            generation,
+        }))
+    }
+}
+
+/// The selected AirPlay speaker, if any. Unlike DLNA and Cast, the speaker receives the
+/// engine's rendered audio, so the engine keeps playing and only its output changes.
+#[derive(Default)]
+pub struct AirPlayOutput {
+    devices: Mutex<Vec<AirPlayDevice>>,
+    session: Mutex<Option<RaopSession>>,
+}
+
+impl AirPlayOutput {
+    /// Runs discovery and remembers the result for `connect`.
+    pub fn refresh_devices(&self) -> Result<Vec<AirPlayDevice>, String> {
+        let devices = discover(DISCOVERY_WINDOW)?;
+        *self
+            .devices
+            .lock()
+            .map_err(|_| "AirPlay device list lock poisoned".to_string())? = devices.clone();
+        Ok(devices)
+    }
+
+    pub fn active(&self) -> Option<AirPlayDevice> {
+        let session = self.session.lock().ok()?;
+        session.as_ref().map(|session| session.device.clone())
+    }
+
+    /// Opens a session with the device with `id`, replacing any previous one, and returns
+    /// the output the engine should render into.
+    pub fn connect(&self, id: &str) -> Result<(AirPlayDevice, SharedOutput), String> {
+        self.disconnect();
+        let device = self
+            .devices
+            .lock()
+            .map_err(|_| "AirPlay device list lock poisoned".to_string())?
+            .iter()
+            .find(|device| device.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown AirPlay device: {id}; list devices first"))?;
+        let session = RaopSession::open(device.clone())?;
+        let backend: SharedOutput = Arc::new(RaopBackend {
+            shared: Arc::clone(&session.shared),
+        });
+        *self
+            .session
+            .lock()
+            .map_err(|_| "AirPlay session lock poisoned".to_string())? = Some(session);
+        Ok((device, backend))
+    }
+
+    /// Tears down the active session, if any.
+    pub fn disconnect(&self) {
+        if let Some(previous) = self
+            .session
+            .lock()
+            .ok()
+            .and_then(|mut session| session.take())
+        {
+            previous.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_alac_frame, parse_transport, FRAMES_PER_PACKET};
+
+    #[test]
+    fn encodes_uncompressed_alac_frames() {
+        let frame = encode_alac_frame(&[0.0; FRAMES_PER_PACKET * 2]);
+        assert_eq!(frame.len(), 1412);
+        assert_eq!(frame[0], 0x20);
+        assert_eq!(frame[1410], 0x01);
+        assert_eq!(frame[1411], 0xC0);
+
+        let frame = encode_alac_frame(&[1.0, -1.0]);
+        // 23 header bits, then 0x7FFF and 0x8001 starting on the last bit of byte 2.
+        assert_eq!(&frame[2..8], &[0x02, 0xFF, 0xFF, 0x00, 0x03, 0xC0]);
+
+        let transport = parse_transport(
+            "RTP/AVP/UDP;unicast;mode=record;server_port=6000;control_port=6001;timing_port=6002",
+        );
+        assert_eq!(transport["server_port"], "6000");
+        assert_eq!(transport["timing_port"], "6002");
+    }
+}
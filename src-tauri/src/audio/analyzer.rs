@@ -1,6 +1,27 @@
+use rusty_chromaprint::{Configuration, FingerprintCompressor, Fingerprinter};
 use std::path::Path;
 
 use super::decoder;
+use super::dsp::beat::{BeatDetector, ONSET_FRAME_SIZE};
+use super::dsp::filters::BiquadFilter;
+
+/// Onset frames analysed per second when estimating a track's tempo.
+const BPM_FRAMES_PER_SECOND: usize = 100;
+/// The detector only keeps a few seconds of history, so the tempo is sampled once
+/// per segment and the median is reported for the whole track.
+const BPM_SEGMENT_SECONDS: usize = 8;
+
+/// Seconds of audio averaged into each loudness value (EBU R128 short-term window).
+const LOUDNESS_WINDOW_SECONDS: usize = 3;
+/// Loudness reported for silence; matches the R128 absolute gate.
+const LOUDNESS_FLOOR_LUFS: f32 = -70.0;
+
+/// AcoustID identifies recordings by the fingerprint of their first two minutes.
+const FINGERPRINT_SECONDS: usize = 120;
+
+/// Resolution of the cached RMS envelope; smaller requests are derived from it with
+/// [`downsample_waveform`] instead of decoding the file again.
+pub const WAVEFORM_CACHE_POINTS: usize = 2048;
 
 pub fn extract_waveform(path: &Path, points: usize) -> Result<Vec<f32>, String> {
     if points == 0 {
@@ -10,6 +31,213 @@ pub fn extract_waveform(path: &Path, points: usize) -> Result<Vec<f32>, String>
     Ok(compute_waveform(&decoded.samples, decoded.channels as usize, points))
 }
 
+/// Decodes `path` and estimates its tempo in BPM. Returns `None` for tracks without
+/// a detectable pulse (silence, ambient material, very short files).
+pub fn analyze_bpm(path: &Path) -> Result<Option<f32>, String> {
+    let decoded = decoder::decode_file(path)?;
+    Ok(estimate_bpm(
+        &decoded.samples,
+        decoded.channels as usize,
+        decoded.sample_rate,
+    ))
+}
+
+/// Decodes `path` and returns its short-term loudness curve: one LUFS value per second
+/// of audio, each measured over a 3 s window centred on that second.
+pub fn analyze_loudness_profile(path: &Path) -> Result<Vec<f32>, String> {
+    let decoded = decoder::decode_file(path)?;
+    Ok(compute_loudness_profile(
+        &decoded.samples,
+        decoded.channels as usize,
+        decoded.sample_rate,
+    ))
+}
+
+/// Chromaprint fingerprint of a track, as AcoustID expects it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioFingerprint {
+    /// Compressed fingerprint, URL-safe base64 without padding.
+    pub fingerprint: String,
+    /// Length of the whole track, not just the fingerprinted part.
+    pub duration_seconds: u32,
+}
+
+/// Decodes `path` and computes its Chromaprint fingerprint from the first two minutes.
+/// Returns `None` for tracks too short or quiet to fingerprint.
+pub fn analyze_fingerprint(path: &Path) -> Result<Option<AudioFingerprint>, String> {
+    let decoded = decoder::decode_file(path)?;
+    let channels = decoded.channels as usize;
+    if channels == 0 || decoded.sample_rate == 0 {
+        return Ok(None);
+    }
+    let frames = decoded.samples.len() / channels;
+    let duration_seconds = (frames / decoded.sample_rate as usize) as u32;
+    Ok(
+        compute_fingerprint(&decoded.samples, channels, decoded.sample_rate)?.map(|fingerprint| {
+            AudioFingerprint {
+                fingerprint,
+                duration_seconds,
+            }
+        }),
+    )
+}
+
+fn compute_fingerprint(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+) -> Result<Option<String>, String> {
+    let config = Configuration::preset_test2();
+    let mut printer = Fingerprinter::new(&config);
+    printer
+        .start(sample_rate, channels as u32)
+        .map_err(|e| format!("Failed to start fingerprinting: {e:?}"))?;
+    let end = samples
+        .len()
+        .min(FINGERPRINT_SECONDS * sample_rate as usize * channels);
+    let pcm = samples[..end - end % channels]
+        .iter()
+        .map(|sample| (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16)
+        .collect::<Vec<_>>();
+    printer.consume(&pcm);
+    printer.finish();
+    let fingerprint = printer.fingerprint();
+    if fingerprint.is_empty() {
+        return Ok(None);
+    }
+    let compressed = FingerprintCompressor::from(&config).compress(fingerprint);
+    Ok(Some(base64_url(&compressed)))
+}
+
+/// URL-safe base64 without padding, the encoding of Chromaprint's `fpcalc`.
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+fn compute_loudness_profile(samples: &[f32], channels: usize, sample_rate: u32) -> Vec<f32> {
+    if channels == 0 || sample_rate == 0 {
+        return Vec::new();
+    }
+    // BS.1770 K-weighting: high-frequency shelf followed by the RLB high-pass.
+    let rate = sample_rate as f32;
+    let mut weighting = (0..channels)
+        .map(|_| {
+            let mut shelf = BiquadFilter::new();
+            shelf.set_high_shelf(rate, 1_681.97, 4.0, 1.0);
+            let mut high_pass = BiquadFilter::new();
+            high_pass.set_high_pass(rate, 38.13, 0.5);
+            (shelf, high_pass)
+        })
+        .collect::<Vec<_>>();
+
+    let second_energy = samples
+        .chunks(sample_rate as usize * channels)
+        .map(|second| {
+            let energy = second
+                .chunks_exact(channels)
+                .map(|frame| {
+                    frame
+                        .iter()
+                        .zip(weighting.iter_mut())
+                        .map(|(sample, (shelf, high_pass))| {
+                            let weighted = high_pass.process_sample(shelf.process_sample(*sample));
+                            weighted * weighted
+                        })
+                        .sum::<f32>()
+                })
+                .sum::<f32>();
+            (energy, second.len() / channels)
+        })
+        .collect::<Vec<_>>();
+
+    let half_window = LOUDNESS_WINDOW_SECONDS / 2;
+    (0..second_energy.len())
+        .map(|second| {
+            let window = &second_energy[second.saturating_sub(half_window)
+                ..(second + half_window + 1).min(second_energy.len())];
+            let energy = window.iter().map(|(energy, _)| energy).sum::<f32>();
+            let frames = window
+                .iter()
+                .map(|(_, frames)| frames)
+                .sum::<usize>()
+                .max(1);
+            let mean_square = energy / frames as f32;
+            if mean_square <= f32::MIN_POSITIVE {
+                return LOUDNESS_FLOOR_LUFS;
+            }
+            (-0.691 + 10.0 * mean_square.log10()).max(LOUDNESS_FLOOR_LUFS)
+        })
+        .collect()
+}
+
+fn estimate_bpm(samples: &[f32], channels: usize, sample_rate: u32) -> Option<f32> {
+    if channels == 0 || sample_rate == 0 {
+        return None;
+    }
+    let mono = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect::<Vec<_>>();
+    let hop = (sample_rate as usize / BPM_FRAMES_PER_SECOND).max(1);
+    let mut detector = BeatDetector::new(sample_rate as f32 / hop as f32);
+    let segment_frames = BPM_SEGMENT_SECONDS * BPM_FRAMES_PER_SECOND;
+
+    let mut estimates = Vec::new();
+    let mut frames = 0usize;
+    let mut end = ONSET_FRAME_SIZE;
+    while end <= mono.len() {
+        detector.process_window(&mono[end - ONSET_FRAME_SIZE..end]);
+        frames += 1;
+        if frames.is_multiple_of(segment_frames) {
+            estimates.extend(detector.estimated_tempo());
+        }
+        end += hop;
+    }
+    if !frames.is_multiple_of(segment_frames) {
+        estimates.extend(detector.estimated_tempo());
+    }
+    if estimates.is_empty() {
+        return None;
+    }
+
+    estimates.sort_by(|a, b| a.total_cmp(b));
+    let median = estimates[estimates.len() / 2];
+    Some((median * 10.0).round() / 10.0)
+}
+
+/// Reduces a normalized RMS envelope to `points` values (RMS of each bucket) and
+/// normalizes the result again so the loudest bar stays at 1.0.
+pub fn downsample_waveform(envelope: &[f32], points: usize) -> Vec<f32> {
+    if points == 0 || envelope.is_empty() {
+        return vec![0.0; points];
+    }
+    let mut out = (0..points)
+        .map(|i| {
+            let start = i * envelope.len() / points;
+            let end = ((i + 1) * envelope.len() / points).max(start + 1);
+            let bucket = &envelope[start.min(envelope.len() - 1)..end.min(envelope.len())];
+            (bucket.iter().map(|v| v * v).sum::<f32>() / bucket.len() as f32).sqrt()
+        })
+        .collect::<Vec<_>>();
+    let max = out.iter().copied().fold(0.0_f32, f32::max);
+    if max > 0.0 {
+        for value in &mut out {
+            *value /= max;
+        }
+    }
+    out
+}
+
 fn compute_waveform(samples: &[f32], channels: usize, points: usize) -> Vec<f32> {
     if points == 0 {
         return Vec::new();
@@ -64,7 +292,10 @@ fn compute_waveform(samples: &[f32], channels: usize, points: usize) -> Vec<f32>
 
 #[cfg(test)]
 mod tests {
-    use super::compute_waveform;
+    use super::{
+        base64_url, compute_fingerprint, compute_loudness_profile, compute_waveform,
+        downsample_waveform, estimate_bpm,
+    };
 
     #[test]
     fn waveform_has_requested_points_and_is_normalized() {
@@ -75,9 +306,104 @@ mod tests {
         assert!((max - 1.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn downsampled_waveform_matches_direct_computation() {
+        let samples = (0..4_096)
+            .map(|i| (i as f32 / 512.0).sin() * (i as f32 / 4_096.0))
+            .collect::<Vec<_>>();
+        let direct = compute_waveform(&samples, 1, 16);
+        let derived = downsample_waveform(&compute_waveform(&samples, 1, 256), 16);
+        assert_eq!(derived.len(), 16);
+        for (a, b) in direct.iter().zip(&derived) {
+            assert!((a - b).abs() < 1e-3, "{a} vs {b}");
+        }
+        assert_eq!(downsample_waveform(&[], 3), vec![0.0; 3]);
+    }
+
     #[test]
     fn waveform_handles_empty_input() {
         let out = compute_waveform(&[], 2, 5);
         assert_eq!(out, vec![0.0; 5]);
     }
+
+    #[test]
+    fn fingerprints_are_stable_and_base64_encoded() {
+        assert_eq!(base64_url(b"hello"), "aGVsbG8");
+        assert_eq!(base64_url(&[0xfb, 0xff]), "-_8");
+
+        let sample_rate = 11_025_usize;
+        // A melody of half-second notes, in stereo.
+        let samples = (0..sample_rate * 10)
+            .flat_map(|i| {
+                let note = 220.0 * 2f32.powf((i * 2 / sample_rate % 12) as f32 / 12.0);
+                let value =
+                    0.5 * (i as f32 * note * std::f32::consts::TAU / sample_rate as f32).sin();
+                [value, value]
+            })
+            .collect::<Vec<_>>();
+        let fingerprint = compute_fingerprint(&samples, 2, sample_rate as u32)
+            .expect("fingerprint")
+            .expect("long enough");
+        assert!(fingerprint
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_')));
+        assert_eq!(
+            compute_fingerprint(&samples, 2, sample_rate as u32).expect("fingerprint"),
+            Some(fingerprint)
+        );
+        assert_eq!(
+            compute_fingerprint(&samples[..100], 2, sample_rate as u32).expect("fingerprint"),
+            None
+        );
+    }
+
+    #[test]
+    fn bpm_is_estimated_from_click_track() {
+        let sample_rate = 44_100_usize;
+        let period = sample_rate / 2; // 120 BPM
+        let samples = (0..sample_rate * 20)
+            .flat_map(|i| {
+                let offset = i % period;
+                let value = if offset < 1_000 {
+                    (offset as f32 * 0.7).sin() * (-(offset as f32) / 200.0).exp()
+                } else {
+                    0.0
+                };
+                [value, value]
+            })
+            .collect::<Vec<_>>();
+
+        let bpm = estimate_bpm(&samples, 2, sample_rate as u32).expect("bpm should be found");
+        assert!((bpm - 120.0).abs() < 4.0, "expected ~120 BPM, got {bpm}");
+    }
+
+    #[test]
+    fn bpm_is_none_for_silence() {
+        assert_eq!(estimate_bpm(&vec![0.0; 44_100 * 10], 1, 44_100), None);
+    }
+
+    #[test]
+    fn loudness_profile_has_one_value_per_second() {
+        let rate = 48_000;
+        // 4 s of a -6 dBFS 1 kHz sine, then 4 s of silence.
+        let samples = (0..rate * 8)
+            .map(|i| {
+                if i < rate * 4 {
+                    0.5 * (2.0 * std::f32::consts::PI * 1_000.0 * i as f32 / rate as f32).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        let profile = compute_loudness_profile(&samples, 1, rate as u32);
+        assert_eq!(profile.len(), 8);
+        // A full-scale 1 kHz sine reads -3.01 LUFS; -6 dB of amplitude gives about -9.
+        assert!(
+            (profile[1] + 9.0).abs() < 1.0,
+            "expected ~-9 LUFS, got {}",
+            profile[1]
+        );
+        assert!(profile[4] < profile[2]);
+        assert_eq!(profile[6], -70.0);
+    }
 }
@@ -0,0 +1,289 @@
+//! Monkey's Audio support (`.ape`). Symphonia has neither an APE demuxer nor a
+//! decoder, so frames are located through the file's seek table here and decoded
+//! with `ape-decoder`. Files older than version 3.95 are not supported.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use ape_decoder::{format::ApeFileInfo, FrameDecoder};
+use symphonia::core::{
+    audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec},
+    codecs::{
+        CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult,
+        CODEC_TYPE_MONKEYS_AUDIO,
+    },
+    errors::{
+        decode_error, end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind,
+    },
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::MediaSourceStream,
+    meta::{Metadata, MetadataLog},
+    probe::{Descriptor, Instantiate, QueryDescriptor},
+    support_codec, support_format,
+    units::TimeBase,
+};
+
+/// The oldest format revision `FrameDecoder` handles.
+const MIN_VERSION: u16 = 3950;
+
+pub struct ApeReader {
+    reader: MediaSourceStream,
+    info: ApeFileInfo,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    next_frame: u32,
+}
+
+impl ApeReader {
+    /// Packets carry the frame's bit alignment (0..=3 bytes into the first word) in their
+    /// first byte, followed by the word-aligned frame bytes `FrameDecoder` reads.
+    fn read_frame(&mut self, frame: u32) -> Result<Packet> {
+        let alignment = (self.info.seek_byte(frame) - self.info.seek_byte(0)) % 4;
+        // The bit reader may look one word past the frame.
+        let length = self.info.frame_byte_count(frame) + alignment + 4;
+        self.reader
+            .seek(SeekFrom::Start(self.info.seek_byte(frame) - alignment))?;
+        let mut data = vec![alignment as u8];
+        (&mut self.reader).take(length).read_to_end(&mut data)?;
+        let blocks_per_frame = u64::from(self.info.header.blocks_per_frame);
+        Ok(Packet::new_from_boxed_slice(
+            0,
+            u64::from(frame) * blocks_per_frame,
+            u64::from(self.info.frame_block_count(frame)),
+            data.into_boxed_slice(),
+        ))
+    }
+}
+
+impl QueryDescriptor for ApeReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "ape",
+            "Monkey's Audio",
+            &["ape"],
+            &["audio/ape", "audio/x-ape"],
+            &[b"MAC "]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for ApeReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let Ok(info) = ape_decoder::format::parse(&mut source) else {
+            return decode_error("ape: invalid file header");
+        };
+        let version = info.descriptor.version;
+        if version < MIN_VERSION {
+            return unsupported_error("ape: files older than version 3.95 are not supported");
+        }
+        let header = &info.header;
+        let Some(channels) = Channels::from_bits((1_u32 << header.channels.min(31)) - 1) else {
+            return unsupported_error("ape: unsupported channel count");
+        };
+        // The decoder needs the encoder's version and compression level.
+        let mut extra_data = version.to_le_bytes().to_vec();
+        extra_data.extend_from_slice(&header.compression_level.to_le_bytes());
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_MONKEYS_AUDIO)
+            .with_sample_rate(header.sample_rate)
+            .with_time_base(TimeBase::new(1, header.sample_rate))
+            .with_channels(channels)
+            .with_bits_per_sample(u32::from(header.bits_per_sample))
+            .with_max_frames_per_packet(u64::from(header.blocks_per_frame))
+            .with_n_frames(info.total_blocks.max(0) as u64)
+            .with_extra_data(extra_data.into_boxed_slice());
+        Ok(Self {
+            reader: source,
+            info,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            next_frame: 0,
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => match self.tracks[0].codec_params.time_base {
+                Some(time_base) => time_base.calc_timestamp(time),
+                None => return seek_error(SeekErrorKind::Unseekable),
+            },
+        };
+        let blocks_per_frame = u64::from(self.info.header.blocks_per_frame).max(1);
+        let frame = required_ts / blocks_per_frame;
+        if frame >= u64::from(self.info.header.total_frames) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+        self.next_frame = frame as u32;
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts,
+            actual_ts: frame * blocks_per_frame,
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        if self.next_frame >= self.info.header.total_frames {
+            return end_of_stream_error();
+        }
+        let packet = self.read_frame(self.next_frame)?;
+        self.next_frame += 1;
+        Ok(packet)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+pub struct ApeDecoder {
+    params: CodecParameters,
+    frames: FrameDecoder,
+    buf: AudioBuffer<f32>,
+    bits_per_sample: u32,
+}
+
+impl Decoder for ApeDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (Some(sample_rate), Some(channels), Some(bits_per_sample), Some(extra_data)) = (
+            params.sample_rate,
+            params.channels,
+            params.bits_per_sample,
+            params.extra_data.as_deref(),
+        ) else {
+            return unsupported_error("ape: incomplete codec parameters");
+        };
+        let [v0, v1, c0, c1, ..] = *extra_data else {
+            return unsupported_error("ape: missing version and compression level");
+        };
+        let Ok(frames) = FrameDecoder::new(
+            u16::from_le_bytes([v0, v1]),
+            channels.count() as u16,
+            bits_per_sample as u16,
+            u16::from_le_bytes([c0, c1]),
+        ) else {
+            return unsupported_error("ape: unsupported stream parameters");
+        };
+        Ok(Self {
+            params: params.clone(),
+            frames,
+            buf: AudioBuffer::new(
+                params.max_frames_per_packet.unwrap_or(0),
+                SignalSpec::new(sample_rate, channels),
+            ),
+            bits_per_sample,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(
+            CODEC_TYPE_MONKEYS_AUDIO,
+            "ape",
+            "Monkey's Audio"
+        )]
+    }
+
+    fn reset(&mut self) {
+        // Every frame restarts the predictors, so nothing carries over between packets.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let Some((alignment, data)) = packet.buf().split_first() else {
+            return decode_error("ape: empty packet");
+        };
+        let frames = packet.dur() as usize;
+        let Ok(pcm) = self
+            .frames
+            .decode_frame(data, u32::from(*alignment), frames)
+        else {
+            return decode_error("ape: corrupt frame");
+        };
+        let spec = *self.buf.spec();
+        if frames > self.buf.capacity() {
+            self.buf = AudioBuffer::new(frames as u64, spec);
+        }
+
+        // Decoded frames are little-endian WAV PCM: unsigned 8-bit or signed wider samples.
+        let width = (self.bits_per_sample / 8) as usize;
+        let channels = spec.channels.count();
+        let scale = 1.0 / (1_u64 << (self.bits_per_sample - 1)) as f32;
+        self.buf.render_reserved(Some(frames));
+        for channel in 0..channels {
+            let samples = pcm.chunks_exact(width).skip(channel).step_by(channels);
+            for (out, bytes) in self.buf.chan_mut(channel).iter_mut().zip(samples) {
+                let sample = match *bytes {
+                    [byte] => i32::from(byte) - 128,
+                    [b0, b1] => i32::from(i16::from_le_bytes([b0, b1])),
+                    [b0, b1, b2] => i32::from_le_bytes([0, b0, b1, b2]) >> 8,
+                    [b0, b1, b2, b3] => i32::from_le_bytes([b0, b1, b2, b3]),
+                    _ => 0,
+                };
+                *out = sample as f32 * scale;
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::decoder::decode_file;
+    use crate::test_support::unique_temp_path;
+    use oxideav_ape::{encode_pcm, CompressionLevel, EncoderConfig};
+
+    #[test]
+    fn stereo_file_decodes_frame_by_frame() {
+        let left = (0..5000)
+            .map(|n| ((n as f32 * 0.05).sin() * 12_000.0) as i32)
+            .collect::<Vec<_>>();
+        let right = left.iter().map(|sample| -sample / 3).collect::<Vec<_>>();
+        let mut config = EncoderConfig::new(CompressionLevel::Normal, 2, 44_100, 16);
+        config.blocks_per_frame = 2048;
+        let file = encode_pcm(config, &[left.clone(), right.clone()]).expect("pcm should encode");
+        let path = unique_temp_path("stereo.ape");
+        std::fs::write(&path, file).expect("ape should be written");
+
+        let track = decode_file(&path).expect("ape should decode");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!((track.sample_rate, track.channels), (44_100, 2));
+        let expected = left
+            .iter()
+            .zip(&right)
+            .flat_map(|(l, r)| [*l as f32 / 32_768.0, *r as f32 / 32_768.0])
+            .collect::<Vec<_>>();
+        assert_eq!(track.samples, expected);
+    }
+}
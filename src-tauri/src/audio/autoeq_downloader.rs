@@ -0,0 +1,129 @@
+use crate::audio::dsp::autoeq::{self, AutoEqEntry, AutoEqProfile};
+use crate::library::art_cache::prune_flat_cache_dir;
+use crate::library::network_usage::{self, NetworkProvider};
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Root of the AutoEq results; a mirror with the same layout can be swapped in here.
+const AUTOEQ_RESULTS_URL: &str =
+    "https://raw.githubusercontent.com/jaakkopasanen/AutoEq/master/results/";
+const AUTOEQ_INDEX_FILE: &str = "INDEX.md";
+/// The catalog changes rarely, so a downloaded index is reused for a week.
+const AUTOEQ_INDEX_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const AUTOEQ_PROFILE_CACHE_MAX_FILES: usize = 256;
+
+/// Catalog entries matching `query`, best first.
+pub fn search_profiles(query: &str, limit: usize) -> Result<Vec<AutoEqEntry>, String> {
+    let entries = load_index()?;
+    Ok(autoeq::search(&entries, query, limit)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// Resolves `model` (optionally from one measurement `source`) in the catalog and
+/// returns its parametric profile, downloading it on first use.
+pub fn fetch_profile(
+    model: &str,
+    source: Option<&str>,
+) -> Result<(AutoEqEntry, AutoEqProfile), String> {
+    let entries = load_index()?
+        .into_iter()
+        .filter(|entry| source.is_none_or(|source| entry.source == source))
+        .collect::<Vec<_>>();
+    let entry = autoeq::search(&entries, model, 1)
+        .into_iter()
+        .next()
+        .cloned()
+        .ok_or_else(|| format!("No AutoEQ profile found for model: {model}"))?;
+
+    let cache_path = profile_cache_dir().join(format!("{}.txt", cache_key(&entry.path)));
+    if let Ok(text) = fs::read_to_string(&cache_path) {
+        if let Ok(profile) = autoeq::parse_parametric_eq(&text) {
+            return Ok((entry, profile));
+        }
+    }
+
+    let directory = entry.path.trim_start_matches("./").trim_end_matches('/');
+    let file_name = directory.rsplit('/').next().unwrap_or(directory);
+    let text = download(&format!(
+        "{AUTOEQ_RESULTS_URL}{directory}/{file_name}%20ParametricEQ.txt"
+    ))?;
+    let profile = autoeq::parse_parametric_eq(&text)?;
+    let _ = fs::create_dir_all(profile_cache_dir());
+    prune_flat_cache_dir(&profile_cache_dir(), AUTOEQ_PROFILE_CACHE_MAX_FILES);
+    fs::write(&cache_path, text)
+        .map_err(|e| format!("Failed to cache AutoEQ profile {}: {e}", entry.name))?;
+    Ok((entry, profile))
+}
+
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir()
+        .join("powerplayer")
+        .join("autoeq_cache")
+}
+
+fn profile_cache_dir() -> PathBuf {
+    cache_dir().join("profiles")
+}
+
+/// The cached index while it is fresh; otherwise a new download, falling back to the
+/// stale copy when offline.
+fn load_index() -> Result<Vec<AutoEqEntry>, String> {
+    let index_path = cache_dir().join(AUTOEQ_INDEX_FILE);
+    let cached = fs::read_to_string(&index_path).ok();
+    let fresh = fs::metadata(&index_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < AUTOEQ_INDEX_MAX_AGE);
+    if let (true, Some(markdown)) = (fresh, &cached) {
+        return Ok(autoeq::parse_index(markdown));
+    }
+
+    match download(&format!("{AUTOEQ_RESULTS_URL}{AUTOEQ_INDEX_FILE}")) {
+        Ok(markdown) => {
+            let entries = autoeq::parse_index(&markdown);
+            if entries.is_empty() {
+                return Err("AutoEQ catalog index has no entries".to_string());
+            }
+            let _ = fs::create_dir_all(cache_dir());
+            let _ = fs::write(&index_path, markdown);
+            Ok(entries)
+        }
+        Err(err) => match cached {
+            Some(markdown) => Ok(autoeq::parse_index(&markdown)),
+            None => Err(err),
+        },
+    }
+}
+
+fn download(url: &str) -> Result<String, String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent("PowerPlayer/0.1")
+        .build()
+        .map_err(|e| format!("Failed to create AutoEQ client: {e}"))?;
+    let response = client
+        .get(url)
+        .send()
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .map_err(|e| format!("Failed to read {url}: {e}"))?;
+    network_usage::record(NetworkProvider::AutoEq, body.len() as u64);
+    if !status.is_success() {
+        return Err(format!("Failed to download {url}: HTTP {status}"));
+    }
+    Ok(body)
+}
+
+fn cache_key(path: &str) -> String {
+    let mut hash = Sha256::new();
+    hash.update(path.as_bytes());
+    format!("{:x}", hash.finalize())
+}
@@ -0,0 +1,748 @@
+//! Chromecast output: finds Cast devices over mDNS, opens a CASTV2 session (protobuf
+//! frames over TLS on port 8009), launches the Default Media Receiver and has it play the
+//! track published by `audio::media_server`.
+
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme, StreamOwned};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{mdns, media_server};
+
+const CAST_SERVICE: &str = "_googlecast._tcp.local";
+/// How long discovery listens for mDNS answers.
+pub const DISCOVERY_WINDOW: Duration = Duration::from_secs(3);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// The receiver app must come up within this long after `LAUNCH`.
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Receivers drop senders that stay silent for more than a few seconds.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// Socket read timeout, i.e. how quickly queued commands are sent out.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Google's Default Media Receiver, which plays any URL it is given.
+const DEFAULT_MEDIA_RECEIVER: &str = "CC1AD845";
+const SENDER_ID: &str = "sender-0";
+const RECEIVER_ID: &str = "receiver-0";
+
+const NS_CONNECTION: &str = "urn:x-cast:com.google.cast.tp.connection";
+const NS_HEARTBEAT: &str = "urn:x-cast:com.google.cast.tp.heartbeat";
+const NS_RECEIVER: &str = "urn:x-cast:com.google.cast.receiver";
+const NS_MEDIA: &str = "urn:x-cast:com.google.cast.media";
+
+/// A Chromecast or Google/Nest speaker.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CastDevice {
+    pub id: String,
+    pub name: String,
+    pub model: Option<String>,
+    pub host: String,
+    pub port: u16,
+}
+
+/// What the receiver last reported, kept in sync with changes made on the device itself
+/// (e.g. pausing from a phone).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CastStatus {
+    pub device_id: String,
+    pub connected: bool,
+    /// `IDLE`, `BUFFERING`, `PLAYING` or `PAUSED`, as reported by the receiver.
+    pub player_state: String,
+    pub position_seconds: f64,
+    pub duration_seconds: Option<f64>,
+    pub volume: f32,
+    pub muted: bool,
+}
+
+pub type StatusCallback = Arc<dyn Fn(CastStatus) + Send + Sync>;
+
+/// Browses mDNS for `_googlecast._tcp` devices answering within `window`.
+pub fn discover(window: Duration) -> Result<Vec<CastDevice>, String> {
+    let mut devices = mdns::browse(CAST_SERVICE, window)?
+        .into_iter()
+        .map(|service| CastDevice {
+            id: service
+                .txt
+                .get("id")
+                .cloned()
+                .unwrap_or_else(|| service.instance.clone()),
+            name: service
+                .txt
+                .get("fn")
+                .cloned()
+                .unwrap_or_else(|| service.label().to_string()),
+            model: service.txt.get("md").cloned(),
+            host: service.host.to_string(),
+            port: service.port,
+        })
+        .collect::<Vec<_>>();
+    devices.dedup_by(|a, b| a.id == b.id);
+    devices.sort_by_key(|device| device.name.to_lowercase());
+    Ok(devices)
+}
+
+/// A `CastMessage` protobuf with a UTF-8 payload, length-prefixed for the wire.
+fn encode_frame(source: &str, destination: &str, namespace: &str, payload: &str) -> Vec<u8> {
+    fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+    fn put_string(out: &mut Vec<u8>, tag: u8, value: &str) {
+        out.push(tag);
+        put_varint(out, value.len() as u64);
+        out.extend_from_slice(value.as_bytes());
+    }
+    let mut message = Vec::new();
+    message.extend_from_slice(&[0x08, 0]); // protocol_version = CASTV2_1_0
+    put_string(&mut message, 0x12, source);
+    put_string(&mut message, 0x1a, destination);
+    put_string(&mut message, 0x22, namespace);
+    message.extend_from_slice(&[0x28, 0]); // payload_type = STRING
+    put_string(&mut message, 0x32, payload);
+
+    let mut frame = (message.len() as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(&message);
+    frame
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct CastMessage {
+    source: String,
+    namespace: String,
+    payload: String,
+}
+
+/// Splits the next complete frame off `buffer`. Binary payloads are ignored.
+fn take_frame(buffer: &mut Vec<u8>) -> Option<CastMessage> {
+    let length = u32::from_be_bytes(buffer.get(..4)?.try_into().ok()?) as usize;
+    if buffer.len() < 4 + length {
+        return None;
+    }
+    let body = buffer[4..4 + length].to_vec();
+    buffer.drain(..4 + length);
+
+    let read_varint = |offset: &mut usize| -> Option<u64> {
+        let mut value = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *body.get(*offset)?;
+            *offset += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    };
+    let mut message = CastMessage::default();
+    let mut offset = 0;
+    while offset < body.len() {
+        let Some(key) = read_varint(&mut offset) else {
+            break;
+        };
+        match key & 0x7 {
+            0 => {
+                read_varint(&mut offset);
+            }
+            2 => {
+                let Some(length) = read_varint(&mut offset) else {
+                    break;
+                };
+                let end = (offset + length as usize).min(body.len());
+                let value = String::from_utf8_lossy(&body[offset..end]).into_owned();
+                match key >> 3 {
+                    2 => message.source = value,
+                    4 => message.namespace = value,
+                    6 => message.payload = value,
+                    _ => {}
+                }
+                offset = end;
+            }
+            _ => break,
+        }
+    }
+    Some(message)
+}
+
+/// Cast devices present self-signed certificates; the session is local-network only.
+#[derive(Debug)]
+struct AcceptDeviceCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptDeviceCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+fn connect_tls(device: &CastDevice) -> Result<TlsStream, String> {
+    let ip: IpAddr = device
+        .host
+        .parse()
+        .map_err(|e| format!("Invalid Cast device address {}: {e}", device.host))?;
+    let socket = TcpStream::connect_timeout(&SocketAddr::new(ip, device.port), CONNECT_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to {}: {e}", device.name))?;
+    socket
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| format!("Failed to configure Cast socket: {e}"))?;
+    let provider = Arc::new(ring::default_provider());
+    let config = ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Failed to configure TLS: {e}"))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptDeviceCertificate(provider)))
+        .with_no_client_auth();
+    let connection = ClientConnection::new(Arc::new(config), ServerName::IpAddress(ip.into()))
+        .map_err(|e| format!("Failed to start TLS with {}: {e}", device.name))?;
+    Ok(StreamOwned::new(connection, socket))
+}
+
+struct Outgoing {
+    destination: String,
+    namespace: &'static str,
+    payload: Value,
+}
+
+#[derive(Default)]
+struct SessionState {
+    transport_id: Option<String>,
+    session_id: Option<String>,
+    media_session_id: Option<i64>,
+    status: CastStatus,
+}
+
+/// One connection to a receiver. Commands are queued to a thread that owns the TLS
+/// stream, answers heartbeats and applies status updates.
+struct CastSession {
+    device: CastDevice,
+    outgoing: Sender<Outgoing>,
+    state: Arc<Mutex<SessionState>>,
+    next_request: AtomicI64,
+}
+
+impl CastSession {
+    fn open(device: CastDevice, on_status: StatusCallback) -> Result<Self, String> {
+        let stream = connect_tls(&device)?;
+        let (outgoing, queued) = mpsc::channel();
+        let state = Arc::new(Mutex::new(SessionState {
+            status: CastStatus {
+                device_id: device.id.clone(),
+                connected: true,
+                player_state: "IDLE".to_string(),
+                ..CastStatus::default()
+            },
+            ..SessionState::default()
+        }));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || run_session(stream, queued, shared, on_status));
+
+        let session = Self {
+            device,
+            outgoing,
+            state,
+            next_request: AtomicI64::new(1),
+        };
+        session.send(RECEIVER_ID, NS_CONNECTION, json!({ "type": "CONNECT" }))?;
+        session.request(
+            RECEIVER_ID,
+            NS_RECEIVER,
+            json!({ "type": "LAUNCH", "appId": DEFAULT_MEDIA_RECEIVER }),
+        )?;
+        let started = Instant::now();
+        let transport_id = loop {
+            if let Some(transport_id) = session.with_state(|state| state.transport_id.clone())? {
+                break transport_id;
+            }
+            if !session.is_connected() || started.elapsed() > LAUNCH_TIMEOUT {
+                return Err(format!(
+                    "{} did not start the media receiver",
+                    session.device.name
+                ));
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+        session.send(&transport_id, NS_CONNECTION, json!({ "type": "CONNECT" }))?;
+        Ok(session)
+    }
+
+    fn with_state<T>(&self, read: impl FnOnce(&SessionState) -> T) -> Result<T, String> {
+        self.state
+            .lock()
+            .map(|state| read(&state))
+            .map_err(|_| "Cast session lock poisoned".to_string())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.with_state(|state| state.status.connected)
+            .unwrap_or(false)
+    }
+
+    fn send(
+        &self,
+        destination: &str,
+        namespace: &'static str,
+        payload: Value,
+    ) -> Result<(), String> {
+        self.outgoing
+            .send(Outgoing {
+                destination: destination.to_string(),
+                namespace,
+                payload,
+            })
+            .map_err(|_| format!("Connection to {} was closed", self.device.name))
+    }
+
+    fn request(
+        &self,
+        destination: &str,
+        namespace: &'static str,
+        mut payload: Value,
+    ) -> Result<(), String> {
+        payload["requestId"] = json!(self.next_request.fetch_add(1, Ordering::SeqCst));
+        self.send(destination, namespace, payload)
+    }
+
+    fn transport_id(&self) -> Result<String, String> {
+        self.with_state(|state| state.transport_id.clone())?
+            .ok_or_else(|| format!("{} has no media receiver running", self.device.name))
+    }
+
+    fn media_command(&self, mut payload: Value) -> Result<(), String> {
+        let media_session_id = self
+            .with_state(|state| state.media_session_id)?
+            .ok_or_else(|| format!("Nothing is loaded on {}", self.device.name))?;
+        payload["mediaSessionId"] = json!(media_session_id);
+        self.request(&self.transport_id()?, NS_MEDIA, payload)
+    }
+
+    fn load(
+        &self,
+        url: &str,
+        content_type: &str,
+        title: &str,
+        autoplay: bool,
+        start_seconds: f64,
+    ) -> Result<(), String> {
+        self.request(
+            &self.transport_id()?,
+            NS_MEDIA,
+            json!({
+                "type": "LOAD",
+                "autoplay": autoplay,
+                "currentTime": start_seconds.max(0.0),
+                "media": {
+                    "contentId": url,
+                    "contentType": content_type,
+                    "streamType": "BUFFERED",
+                    "metadata": { "metadataType": 3, "title": title },
+                },
+            }),
+        )
+    }
+
+    /// Stops the receiver app and closes the connection.
+    fn close(&self) {
+        if let Ok(Some(session_id)) = self.with_state(|state| state.session_id.clone()) {
+            let _ = self.request(
+                RECEIVER_ID,
+                NS_RECEIVER,
+                json!({ "type": "STOP", "sessionId": session_id }),
+            );
+        }
+        let _ = self.send(RECEIVER_ID, NS_CONNECTION, json!({ "type": "CLOSE" }));
+    }
+}
+
+fn run_session(
+    mut stream: TlsStream,
+    queued: Receiver<Outgoing>,
+    state: Arc<Mutex<SessionState>>,
+    on_status: StatusCallback,
+) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0_u8; 4096];
+    let mut last_ping = Instant::now();
+    let result: Result<(), String> = (|| loop {
+        loop {
+            match queued.try_recv() {
+                Ok(message) => write_message(&mut stream, &message)?,
+                Err(TryRecvError::Empty) => break,
+                // The session was dropped; the queued CLOSE has already been sent.
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        if last_ping.elapsed() >= HEARTBEAT_INTERVAL {
+            write_message(
+                &mut stream,
+                &Outgoing {
+                    destination: RECEIVER_ID.to_string(),
+                    namespace: NS_HEARTBEAT,
+                    payload: json!({ "type": "PING" }),
+                },
+            )?;
+            last_ping = Instant::now();
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err("closed by the device".to_string()),
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(err) => return Err(err.to_string()),
+        }
+        while let Some(message) = take_frame(&mut buffer) {
+            let Ok(payload) = serde_json::from_str::<Value>(&message.payload) else {
+                continue;
+            };
+            if message.namespace == NS_HEARTBEAT && payload["type"] == "PING" {
+                write_message(
+                    &mut stream,
+                    &Outgoing {
+                        destination: message.source.clone(),
+                        namespace: NS_HEARTBEAT,
+                        payload: json!({ "type": "PONG" }),
+                    },
+                )?;
+                continue;
+            }
+            let mut state = state
+                .lock()
+                .map_err(|_| "session lock poisoned".to_string())?;
+            let before = state.status.clone();
+            let still_open = apply_message(&mut state, &message, &payload);
+            if state.status != before {
+                on_status(state.status.clone());
+            }
+            if !still_open {
+                return Err("the receiver app was closed".to_string());
+            }
+        }
+    })();
+    if let Err(err) = result {
+        eprintln!("Cast session ended: {err}");
+    }
+    if let Ok(mut state) = state.lock() {
+        state.status.connected = false;
+        on_status(state.status.clone());
+    }
+}
+
+fn write_message(stream: &mut TlsStream, message: &Outgoing) -> Result<(), String> {
+    let frame = encode_frame(
+        SENDER_ID,
+        &message.destination,
+        message.namespace,
+        &message.payload.to_string(),
+    );
+    stream
+        .write_all(&frame)
+        .and_then(|_| stream.flush())
+        .map_err(|e| e.to_string())
+}
+
+/// Applies a receiver or media status message. Returns false once our receiver app is
+/// gone (stopped from another sender, or replaced by a different app).
+fn apply_message(state: &mut SessionState, message: &CastMessage, payload: &Value) -> bool {
+    match (message.namespace.as_str(), payload["type"].as_str()) {
+        (NS_RECEIVER, Some("RECEIVER_STATUS")) => {
+            let status = &payload["status"];
+            if let Some(level) = status["volume"]["level"].as_f64() {
+                state.status.volume = level as f32;
+            }
+            if let Some(muted) = status["volume"]["muted"].as_bool() {
+                state.status.muted = muted;
+            }
+            let app = status["applications"].as_array().and_then(|apps| {
+                apps.iter()
+                    .find(|app| app["appId"] == DEFAULT_MEDIA_RECEIVER)
+            });
+            match app {
+                Some(app) => {
+                    state.transport_id = app["transportId"].as_str().map(str::to_string);
+                    state.session_id = app["sessionId"].as_str().map(str::to_string);
+                }
+                None if state.transport_id.is_some() => return false,
+                None => {}
+            }
+        }
+        (NS_MEDIA, Some("MEDIA_STATUS")) => {
+            let Some(media) = payload["status"]
+                .as_array()
+                .and_then(|status| status.first())
+            else {
+                state.media_session_id = None;
+                state.status.player_state = "IDLE".to_string();
+                return true;
+            };
+            state.media_session_id = media["mediaSessionId"].as_i64().or(state.media_session_id);
+            if let Some(player_state) = media["playerState"].as_str() {
+                state.status.player_state = player_state.to_string();
+            }
+            if let Some(position) = media["currentTime"].as_f64() {
+                state.status.position_seconds = position;
+            }
+            if let Some(duration) = media["media"]["duration"].as_f64() {
+                state.status.duration_seconds = Some(duration);
+            }
+        }
+        (NS_CONNECTION, Some("CLOSE")) => return false,
+        _ => {}
+    }
+    true
+}
+
+#[derive(Clone)]
+struct CurrentTrack {
+    path: PathBuf,
+    title: String,
+}
+
+/// The selected Cast device (if any) and the track it should play. While a device is
+/// active, transport commands and volume go to it instead of the local output.
+#[derive(Default)]
+pub struct CastOutput {
+    devices: Mutex<Vec<CastDevice>>,
+    session: Mutex<Option<CastSession>>,
+    track: Mutex<Option<CurrentTrack>>,
+}
+
+impl CastOutput {
+    /// Runs discovery and remembers the result for `select`.
+    pub fn refresh_devices(&self) -> Result<Vec<CastDevice>, String> {
+        let devices = discover(DISCOVERY_WINDOW)?;
+        *self
+            .devices
+            .lock()
+            .map_err(|_| "Cast device list lock poisoned".to_string())? = devices.clone();
+        Ok(devices)
+    }
+
+    /// True while a session is open; a device that disconnects on its own stops being
+    /// the active output.
+    pub fn is_active(&self) -> bool {
+        self.session
+            .lock()
+            .ok()
+            .is_some_and(|session| session.as_ref().is_some_and(CastSession::is_connected))
+    }
+
+    pub fn status(&self) -> Option<CastStatus> {
+        let session = self.session.lock().ok()?;
+        session
+            .as_ref()?
+            .with_state(|state| state.status.clone())
+            .ok()
+    }
+
+    /// Connects to the device with `id` and hands it the current track from
+    /// `position_seconds`, playing when `play` is set. `None` disconnects and returns to
+    /// the local output.
+    pub fn select(
+        &self,
+        id: Option<&str>,
+        play: bool,
+        position_seconds: f64,
+        on_status: StatusCallback,
+    ) -> Result<Option<CastDevice>, String> {
+        self.disconnect();
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        let device = self
+            .devices
+            .lock()
+            .map_err(|_| "Cast device list lock poisoned".to_string())?
+            .iter()
+            .find(|device| device.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown Cast device: {id}; list devices first"))?;
+        let opened = CastSession::open(device.clone(), on_status)?;
+        let track = self.track.lock().ok().and_then(|track| track.clone());
+        if let Some(track) = track {
+            load_track(&opened, &track, play, position_seconds)?;
+        }
+        *self
+            .session
+            .lock()
+            .map_err(|_| "Cast session lock poisoned".to_string())? = Some(opened);
+        Ok(Some(device))
+    }
+
+    /// Stops playback on the active device, if any, and closes the session.
+    pub fn disconnect(&self) {
+        if let Some(previous) = self
+            .session
+            .lock()
+            .ok()
+            .and_then(|mut session| session.take())
+        {
+            previous.close();
+        }
+    }
+
+    /// Records the track the player moved to and, with a device active, loads it there.
+    pub fn set_track(&self, path: &Path, title: &str) -> Result<(), String> {
+        let track = CurrentTrack {
+            path: path.to_path_buf(),
+            title: title.to_string(),
+        };
+        *self
+            .track
+            .lock()
+            .map_err(|_| "Cast track lock poisoned".to_string())? = Some(track.clone());
+        self.on_active(|session| load_track(session, &track, false, 0.0))
+            .or_else(|err| if self.is_active() { Err(err) } else { Ok(()) })
+    }
+
+    pub fn play(&self) -> Result<(), String> {
+        self.on_active(|session| session.media_command(json!({ "type": "PLAY" })))
+    }
+
+    pub fn pause(&self) -> Result<(), String> {
+        self.on_active(|session| session.media_command(json!({ "type": "PAUSE" })))
+    }
+
+    pub fn seek(&self, seconds: f64) -> Result<(), String> {
+        self.on_active(|session| {
+            session.media_command(json!({ "type": "SEEK", "currentTime": seconds.max(0.0) }))
+        })
+    }
+
+    pub fn set_volume(&self, volume: f32) -> Result<(), String> {
+        self.on_active(|session| {
+            session.request(
+                RECEIVER_ID,
+                NS_RECEIVER,
+                json!({ "type": "SET_VOLUME", "volume": { "level": volume.clamp(0.0, 1.0) } }),
+            )
+        })
+    }
+
+    fn on_active(
+        &self,
+        action: impl FnOnce(&CastSession) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| "Cast session lock poisoned".to_string())?;
+        match session.as_ref().filter(|session| session.is_connected()) {
+            Some(session) => action(session),
+            None => Err("No Cast device is selected".to_string()),
+        }
+    }
+}
+
+fn load_track(
+    session: &CastSession,
+    track: &CurrentTrack,
+    play: bool,
+    position_seconds: f64,
+) -> Result<(), String> {
+    let ip: IpAddr = session
+        .device
+        .host
+        .parse()
+        .map_err(|e| format!("Invalid Cast device address {}: {e}", session.device.host))?;
+    let media = media_server::publish(&track.path, SocketAddr::new(ip, session.device.port))?;
+    session.load(
+        &media.url,
+        media.content_type,
+        &track.title,
+        play,
+        position_seconds,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_frame, take_frame, CastMessage};
+
+    #[test]
+    fn cast_frames_round_trip() {
+        let mut buffer = encode_frame(
+            "receiver-0",
+            "sender-0",
+            "urn:x-cast:com.google.cast.tp.heartbeat",
+            r#"{"type":"PING"}"#,
+        );
+        let second = encode_frame(
+            "web-1",
+            "sender-0",
+            "urn:x-cast:com.google.cast.media",
+            "{}",
+        );
+        buffer.extend_from_slice(&second[..6]);
+
+        assert_eq!(
+            take_frame(&mut buffer),
+            Some(CastMessage {
+                source: "receiver-0".to_string(),
+                namespace: "urn:x-cast:com.google.cast.tp.heartbeat".to_string(),
+                payload: r#"{"type":"PING"}"#.to_string(),
+            })
+        );
+        // The second frame is incomplete and stays buffered.
+        assert_eq!(take_frame(&mut buffer), None);
+        buffer.extend_from_slice(&second[6..]);
+        assert_eq!(
+            take_frame(&mut buffer).map(|message| message.source),
+            Some("web-1".to_string())
+        );
+        assert!(buffer.is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+//! Chapter markers of MP4-family files (`.m4b` audiobooks, `.m4a`, `.mp4`). Symphonia
+//! does not expose them, so the `moov` box is walked here. Both layouts in the wild
+//! are read: a QuickTime chapter text track (`tref/chap`, written by iTunes and most
+//! audiobook tools) and the Nero `udta/chpl` list.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// `moov` holds only sample tables and tags; anything larger is not a real file.
+const MAX_MOOV_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_CHAPTERS: usize = 10_000;
+/// Nero chapter start times are in 100 ns units.
+const NERO_TIMESCALE: f64 = 10_000_000.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+}
+
+pub fn is_mp4_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["m4b", "m4a", "mp4"]
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Chapters in start order; empty for files without chapter markers or outside the
+/// MP4 family. The last chapter ends at the movie duration.
+pub fn read_chapters(path: &Path) -> Result<Vec<Chapter>, String> {
+    if !is_mp4_path(path) {
+        return Ok(Vec::new());
+    }
+    let mut file =
+        File::open(path).map_err(|e| format!("Cannot open file {}: {e}", path.display()))?;
+    let Some(moov) = read_moov(&mut file)
+        .map_err(|e| format!("Cannot read MP4 boxes of {}: {e}", path.display()))?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut starts = quicktime_chapters(&moov, &mut file)
+        .map_err(|e| format!("Cannot read chapter track of {}: {e}", path.display()))?;
+    if starts.is_empty() {
+        starts = nero_chapters(&moov);
+    }
+    starts.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let duration = movie_duration(&moov);
+    let ends = starts
+        .iter()
+        .skip(1)
+        .map(|(start, _)| *start)
+        .chain(std::iter::once(duration.unwrap_or(0.0)))
+        .collect::<Vec<_>>();
+    Ok(starts
+        .into_iter()
+        .zip(ends)
+        .map(|((start_seconds, title), end)| Chapter {
+            title,
+            start_seconds,
+            end_seconds: end.max(start_seconds),
+        })
+        .collect())
+}
+
+/// Finds the top-level `moov` box by hopping over the others (`mdat` can be gigabytes).
+fn read_moov(file: &mut File) -> std::io::Result<Option<Vec<u8>>> {
+    let file_len = file.metadata()?.len();
+    let mut offset = 0_u64;
+    while offset + 8 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0_u8; 8];
+        file.read_exact(&mut header)?;
+        let (header_len, size) =
+            match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+                0 => (8, file_len - offset),
+                1 => {
+                    let mut large = [0_u8; 8];
+                    file.read_exact(&mut large)?;
+                    (16, u64::from_be_bytes(large))
+                }
+                size => (8, size as u64),
+            };
+        if size < header_len {
+            break;
+        }
+        if &header[4..8] == b"moov" {
+            let body_len = (size - header_len).min(file_len - offset - header_len);
+            if body_len > MAX_MOOV_BYTES {
+                return Ok(None);
+            }
+            let mut body = vec![0_u8; body_len as usize];
+            file.read_exact(&mut body)?;
+            return Ok(Some(body));
+        }
+        offset = offset.saturating_add(size);
+    }
+    Ok(None)
+}
+
+/// Child boxes of a container body as `(type, body)`; stops at the first malformed one.
+fn child_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+    while let (Some(size), Some(kind)) = (be_u32(data, offset), data.get(offset + 4..offset + 8)) {
+        let (header_len, size) = match size {
+            0 => (8, data.len() - offset),
+            1 => match be_u64(data, offset + 8) {
+                Some(large) => (16, large as usize),
+                None => break,
+            },
+            size => (8, size as usize),
+        };
+        if size < header_len {
+            break;
+        }
+        let Some(body) = data.get(offset + header_len..offset.saturating_add(size)) else {
+            break;
+        };
+        boxes.push(([kind[0], kind[1], kind[2], kind[3]], body));
+        offset += size;
+    }
+    boxes
+}
+
+fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    let (first, rest) = path.split_first()?;
+    let body = child_boxes(data)
+        .into_iter()
+        .find(|(kind, _)| kind == *first)?
+        .1;
+    if rest.is_empty() {
+        Some(body)
+    } else {
+        find_box(body, rest)
+    }
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// `(timescale, duration)` of a version 0/1 `mvhd` or `mdhd` box.
+fn header_timing(header: &[u8]) -> Option<(u32, u64)> {
+    match header.first()? {
+        1 => Some((be_u32(header, 20)?, be_u64(header, 24)?)),
+        _ => Some((be_u32(header, 12)?, be_u32(header, 16)? as u64)),
+    }
+}
+
+fn movie_duration(moov: &[u8]) -> Option<f64> {
+    let (timescale, duration) = header_timing(find_box(moov, &[b"mvhd"])?)?;
+    (timescale > 0).then(|| duration as f64 / timescale as f64)
+}
+
+fn nero_chapters(moov: &[u8]) -> Vec<(f64, String)> {
+    let Some(chpl) = find_box(moov, &[b"udta", b"chpl"]) else {
+        return Vec::new();
+    };
+    // Version 1 adds four bytes of unknown purpose before the count.
+    let mut offset = if chpl.first() == Some(&1) { 8 } else { 4 };
+    let count = chpl.get(offset).copied().unwrap_or(0) as usize;
+    offset += 1;
+    let mut chapters = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (Some(start), Some(&title_len)) = (be_u64(chpl, offset), chpl.get(offset + 8)) else {
+            break;
+        };
+        let Some(title) = chpl.get(offset + 9..offset + 9 + title_len as usize) else {
+            break;
+        };
+        chapters.push((
+            start as f64 / NERO_TIMESCALE,
+            String::from_utf8_lossy(title).into_owned(),
+        ));
+        offset += 9 + title_len as usize;
+    }
+    chapters
+}
+
+/// Reads the text samples of the track referenced by another track's `tref/chap`.
+fn quicktime_chapters(moov: &[u8], file: &mut File) -> std::io::Result<Vec<(f64, String)>> {
+    let tracks = child_boxes(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, trak)| trak)
+        .collect::<Vec<_>>();
+    let track_id = |trak: &[u8]| {
+        let tkhd = find_box(trak, &[b"tkhd"])?;
+        be_u32(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 })
+    };
+    let chapter_ids = tracks
+        .iter()
+        .filter_map(|trak| find_box(trak, &[b"tref", b"chap"]))
+        .flat_map(|chap| (0..chap.len() / 4).filter_map(|index| be_u32(chap, index * 4)))
+        .collect::<Vec<_>>();
+    let Some(chapter_track) = tracks
+        .iter()
+        .find(|trak| track_id(trak).is_some_and(|id| chapter_ids.contains(&id)))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let Some((timescale, _)) = find_box(chapter_track, &[b"mdia", b"mdhd"]).and_then(header_timing)
+    else {
+        return Ok(Vec::new());
+    };
+    let Some(stbl) = find_box(chapter_track, &[b"mdia", b"minf", b"stbl"]) else {
+        return Ok(Vec::new());
+    };
+    if timescale == 0 {
+        return Ok(Vec::new());
+    }
+
+    let samples = sample_locations(stbl);
+    let mut chapters = Vec::with_capacity(samples.len());
+    for (start, offset, size) in samples {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut sample = vec![0_u8; size.min(u16::MAX as u32 + 2) as usize];
+        file.read_exact(&mut sample)?;
+        chapters.push((start as f64 / timescale as f64, text_sample(&sample)));
+    }
+    Ok(chapters)
+}
+
+/// `(start time, file offset, size)` of each sample described by a sample table.
+fn sample_locations(stbl: &[u8]) -> Vec<(u64, u64, u32)> {
+    let table = |kind: &[u8; 4]| find_box(stbl, &[kind]);
+    let entry_count = |table: &[u8]| be_u32(table, 4).unwrap_or(0) as usize;
+
+    let sizes = table(b"stsz")
+        .map(|stsz| {
+            let fixed = be_u32(stsz, 4).unwrap_or(0);
+            let count = (be_u32(stsz, 8).unwrap_or(0) as usize).min(MAX_CHAPTERS);
+            (0..count)
+                .map_while(|index| match fixed {
+                    0 => be_u32(stsz, 12 + index * 4),
+                    fixed => Some(fixed),
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let chunk_offsets = if let Some(stco) = table(b"stco") {
+        (0..entry_count(stco).min(MAX_CHAPTERS))
+            .map_while(|index| be_u32(stco, 8 + index * 4).map(u64::from))
+            .collect::<Vec<_>>()
+    } else if let Some(co64) = table(b"co64") {
+        (0..entry_count(co64).min(MAX_CHAPTERS))
+            .map_while(|index| be_u64(co64, 8 + index * 8))
+            .collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+
+    // (first chunk, samples per chunk), chunks numbered from 1.
+    let chunk_runs = table(b"stsc")
+        .map(|stsc| {
+            (0..entry_count(stsc).min(MAX_CHAPTERS))
+                .map_while(|index| {
+                    Some((
+                        be_u32(stsc, 8 + index * 12)?,
+                        be_u32(stsc, 12 + index * 12)?,
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut offsets = Vec::with_capacity(sizes.len());
+    for (chunk_index, chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk_number = chunk_index as u32 + 1;
+        let per_chunk = chunk_runs
+            .iter()
+            .rev()
+            .find(|(first, _)| *first <= chunk_number)
+            .map_or(0, |(_, count)| *count);
+        let mut offset = *chunk_offset;
+        for _ in 0..per_chunk {
+            let Some(size) = sizes.get(offsets.len()) else {
+                break;
+            };
+            offsets.push(offset);
+            offset += *size as u64;
+        }
+    }
+
+    let mut starts = Vec::with_capacity(offsets.len());
+    if let Some(stts) = table(b"stts") {
+        let mut time = 0_u64;
+        for index in 0..entry_count(stts) {
+            let (Some(count), Some(delta)) =
+                (be_u32(stts, 8 + index * 8), be_u32(stts, 12 + index * 8))
+            else {
+                break;
+            };
+            for _ in 0..count {
+                if starts.len() == offsets.len() {
+                    break;
+                }
+                starts.push(time);
+                time += delta as u64;
+            }
+        }
+    }
+
+    starts
+        .into_iter()
+        .zip(offsets)
+        .zip(sizes)
+        .map(|((start, offset), size)| (start, offset, size))
+        .collect()
+}
+
+/// A text sample is a 16-bit length followed by UTF-8, or UTF-16 with a BOM.
+fn text_sample(sample: &[u8]) -> String {
+    let len = sample
+        .get(..2)
+        .map_or(0, |len| u16::from_be_bytes([len[0], len[1]]) as usize);
+    let text = sample.get(2..2 + len).unwrap_or_default();
+    let utf16 = |bytes: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units = bytes
+            .chunks_exact(2)
+            .map(|pair| decode([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    };
+    match text {
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        _ => String::from_utf8_lossy(text).into_owned(),
+    }
+    .trim()
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_chapters, Chapter};
+    use crate::test_support::unique_temp_path;
+    use std::path::PathBuf;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn full_box(kind: &[u8; 4], fields: &[u32]) -> Vec<u8> {
+        let mut body = vec![0_u8; 4]; // version 0, no flags
+        for field in fields {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+        mp4_box(kind, &body)
+    }
+
+    /// mvhd v0: creation, modification, timescale, duration.
+    fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+        full_box(b"mvhd", &[0, 0, timescale, duration])
+    }
+
+    fn temp_m4b(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = unique_temp_path(&format!("chapters-{name}.m4b"));
+        std::fs::write(&path, bytes).expect("test file should be written");
+        path
+    }
+
+    #[test]
+    fn reads_nero_chapter_list() {
+        let mut chpl = vec![0_u8; 4];
+        chpl.push(2);
+        for (start, title) in [(0_u64, "Opening"), (600_000_000, "Part Two")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+        let moov = [
+            mvhd(1_000, 90_000),
+            mp4_box(b"udta", &mp4_box(b"chpl", &chpl)),
+        ]
+        .concat();
+        let file = [mp4_box(b"ftyp", b"M4B "), mp4_box(b"moov", &moov)].concat();
+        let path = temp_m4b("nero", &file);
+
+        let chapters = read_chapters(&path).expect("chapters should parse");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    title: "Opening".to_string(),
+                    start_seconds: 0.0,
+                    end_seconds: 60.0
+                },
+                Chapter {
+                    title: "Part Two".to_string(),
+                    start_seconds: 60.0,
+                    end_seconds: 90.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_quicktime_chapter_track() {
+        // Chapter text samples sit in `mdat` right after `ftyp`.
+        let ftyp = mp4_box(b"ftyp", b"M4A ");
+        let samples = [
+            b"\x00\x05Intro".to_vec(),
+            b"\x00\x0a\xfe\xff\x00E\x00n\x00d\x00!".to_vec(),
+        ];
+        let mdat = mp4_box(b"mdat", &samples.concat());
+        let first_sample = (ftyp.len() + 8) as u32;
+
+        let audio_trak = mp4_box(
+            b"trak",
+            &[
+                full_box(b"tkhd", &[0, 0, 1]),
+                mp4_box(b"tref", &mp4_box(b"chap", &2_u32.to_be_bytes())),
+            ]
+            .concat(),
+        );
+        let stbl = [
+            full_box(b"stts", &[2, 1, 30, 1, 45]),
+            full_box(b"stsc", &[1, 1, 2, 1]),
+            full_box(b"stsz", &[0, 2, 7, 12]),
+            full_box(b"stco", &[1, first_sample]),
+        ]
+        .concat();
+        let text_trak = mp4_box(
+            b"trak",
+            &[
+                full_box(b"tkhd", &[0, 0, 2]),
+                mp4_box(
+                    b"mdia",
+                    &[
+                        full_box(b"mdhd", &[0, 0, 1, 75]),
+                        mp4_box(b"minf", &mp4_box(b"stbl", &stbl)),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        );
+        let moov = mp4_box(b"moov", &[mvhd(10, 750), audio_trak, text_trak].concat());
+        let path = temp_m4b("quicktime", &[ftyp, mdat, moov].concat());
+
+        let chapters = read_chapters(&path).expect("chapters should parse");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            chapters
+                .iter()
+                .map(|chapter| (
+                    chapter.title.as_str(),
+                    chapter.start_seconds,
+                    chapter.end_seconds
+                ))
+                .collect::<Vec<_>>(),
+            vec![("Intro", 0.0, 30.0), ("End!", 30.0, 75.0)]
+        );
+        assert!(read_chapters(&PathBuf::from("/music/song.flac"))
+            .expect("non-mp4 files have no chapters")
+            .is_empty());
+    }
+}
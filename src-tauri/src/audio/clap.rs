@@ -0,0 +1,1032 @@
+//! Minimal CLAP host. Loads `.clap` modules through their C ABI, instantiates audio
+//! effects and runs them on stereo blocks. Only what an insert effect needs is
+//! implemented: no GUIs, note events or host extensions, and extra ports such as
+//! sidechains are fed silence.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use libloading::Library;
+use walkdir::WalkDir;
+
+use super::plugin_host::{
+    EffectPlugin, PluginClassInfo, PluginFormat, PluginParameter, PluginState,
+};
+
+const PLUGIN_FACTORY_ID: &CStr = c"clap.plugin-factory";
+const EXT_AUDIO_PORTS: &CStr = c"clap.audio-ports";
+const EXT_PARAMS: &CStr = c"clap.params";
+const EXT_STATE: &CStr = c"clap.state";
+
+const PROCESS_ERROR: i32 = 0;
+const CORE_EVENT_SPACE_ID: u16 = 0;
+const EVENT_PARAM_VALUE: u16 = 5;
+const PARAM_IS_STEPPED: u32 = 1 << 0;
+const PARAM_IS_HIDDEN: u32 = 1 << 4;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ClapVersion {
+    major: u32,
+    minor: u32,
+    revision: u32,
+}
+
+const CLAP_VERSION: ClapVersion = ClapVersion {
+    major: 1,
+    minor: 2,
+    revision: 0,
+};
+
+#[repr(C)]
+struct PluginEntry {
+    clap_version: ClapVersion,
+    init: unsafe extern "C" fn(*const c_char) -> bool,
+    deinit: unsafe extern "C" fn(),
+    get_factory: unsafe extern "C" fn(*const c_char) -> *const c_void,
+}
+
+#[repr(C)]
+struct PluginFactory {
+    get_plugin_count: unsafe extern "C" fn(*const PluginFactory) -> u32,
+    get_plugin_descriptor:
+        unsafe extern "C" fn(*const PluginFactory, u32) -> *const PluginDescriptor,
+    create_plugin:
+        unsafe extern "C" fn(*const PluginFactory, *const Host, *const c_char) -> *const Plugin,
+}
+
+#[repr(C)]
+struct PluginDescriptor {
+    clap_version: ClapVersion,
+    id: *const c_char,
+    name: *const c_char,
+    vendor: *const c_char,
+    url: *const c_char,
+    manual_url: *const c_char,
+    support_url: *const c_char,
+    version: *const c_char,
+    description: *const c_char,
+    /// Null-terminated list such as `audio-effect`, `equalizer`.
+    features: *const *const c_char,
+}
+
+#[repr(C)]
+struct Host {
+    clap_version: ClapVersion,
+    host_data: *mut c_void,
+    name: *const c_char,
+    vendor: *const c_char,
+    url: *const c_char,
+    version: *const c_char,
+    get_extension: unsafe extern "C" fn(*const Host, *const c_char) -> *const c_void,
+    request_restart: unsafe extern "C" fn(*const Host),
+    request_process: unsafe extern "C" fn(*const Host),
+    request_callback: unsafe extern "C" fn(*const Host),
+}
+
+#[repr(C)]
+struct Plugin {
+    desc: *const PluginDescriptor,
+    plugin_data: *mut c_void,
+    init: unsafe extern "C" fn(*const Plugin) -> bool,
+    destroy: unsafe extern "C" fn(*const Plugin),
+    activate: unsafe extern "C" fn(*const Plugin, f64, u32, u32) -> bool,
+    deactivate: unsafe extern "C" fn(*const Plugin),
+    start_processing: unsafe extern "C" fn(*const Plugin) -> bool,
+    stop_processing: unsafe extern "C" fn(*const Plugin),
+    reset: unsafe extern "C" fn(*const Plugin),
+    process: unsafe extern "C" fn(*const Plugin, *const Process) -> i32,
+    get_extension: unsafe extern "C" fn(*const Plugin, *const c_char) -> *const c_void,
+    on_main_thread: unsafe extern "C" fn(*const Plugin),
+}
+
+#[repr(C)]
+struct Process {
+    steady_time: i64,
+    frames_count: u32,
+    transport: *const c_void,
+    audio_inputs: *const AudioBuffer,
+    audio_outputs: *mut AudioBuffer,
+    audio_inputs_count: u32,
+    audio_outputs_count: u32,
+    in_events: *const InputEvents,
+    out_events: *const OutputEvents,
+}
+
+#[repr(C)]
+struct AudioBuffer {
+    data32: *mut *mut f32,
+    data64: *mut *mut f64,
+    channel_count: u32,
+    latency: u32,
+    constant_mask: u64,
+}
+
+#[repr(C)]
+struct InputEvents {
+    ctx: *mut c_void,
+    size: unsafe extern "C" fn(*const InputEvents) -> u32,
+    get: unsafe extern "C" fn(*const InputEvents, u32) -> *const EventHeader,
+}
+
+#[repr(C)]
+struct OutputEvents {
+    ctx: *mut c_void,
+    try_push: unsafe extern "C" fn(*const OutputEvents, *const EventHeader) -> bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct EventHeader {
+    size: u32,
+    time: u32,
+    space_id: u16,
+    event_type: u16,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ParamValueEvent {
+    header: EventHeader,
+    param_id: u32,
+    cookie: *mut c_void,
+    note_id: i32,
+    port_index: i16,
+    channel: i16,
+    key: i16,
+    value: f64,
+}
+
+#[repr(C)]
+struct PluginAudioPorts {
+    count: unsafe extern "C" fn(*const Plugin, bool) -> u32,
+    get: unsafe extern "C" fn(*const Plugin, u32, bool, *mut AudioPortInfo) -> bool,
+}
+
+#[repr(C)]
+struct AudioPortInfo {
+    id: u32,
+    name: [c_char; 256],
+    flags: u32,
+    channel_count: u32,
+    port_type: *const c_char,
+    in_place_pair: u32,
+}
+
+#[repr(C)]
+struct PluginParams {
+    count: unsafe extern "C" fn(*const Plugin) -> u32,
+    get_info: unsafe extern "C" fn(*const Plugin, u32, *mut ParamInfo) -> bool,
+    get_value: unsafe extern "C" fn(*const Plugin, u32, *mut f64) -> bool,
+    value_to_text: unsafe extern "C" fn(*const Plugin, u32, f64, *mut c_char, u32) -> bool,
+    text_to_value: unsafe extern "C" fn(*const Plugin, u32, *const c_char, *mut f64) -> bool,
+    flush: unsafe extern "C" fn(*const Plugin, *const InputEvents, *const OutputEvents),
+}
+
+#[repr(C)]
+struct ParamInfo {
+    id: u32,
+    flags: u32,
+    cookie: *mut c_void,
+    name: [c_char; 256],
+    module: [c_char; 1024],
+    min_value: f64,
+    max_value: f64,
+    default_value: f64,
+}
+
+#[repr(C)]
+struct PluginStateExt {
+    save: unsafe extern "C" fn(*const Plugin, *const OutputStream) -> bool,
+    load: unsafe extern "C" fn(*const Plugin, *const InputStream) -> bool,
+}
+
+#[repr(C)]
+struct OutputStream {
+    ctx: *mut c_void,
+    write: unsafe extern "C" fn(*const OutputStream, *const c_void, u64) -> i64,
+}
+
+#[repr(C)]
+struct InputStream {
+    ctx: *mut c_void,
+    read: unsafe extern "C" fn(*const InputStream, *mut c_void, u64) -> i64,
+}
+
+/// The host handed to every plugin. It offers no extensions and ignores requests;
+/// plugins have to cope with both.
+struct StaticHost(Host);
+
+// SAFETY: the host only points at static strings and stateless functions.
+unsafe impl Sync for StaticHost {}
+
+static HOST: StaticHost = StaticHost(Host {
+    clap_version: CLAP_VERSION,
+    host_data: ptr::null_mut(),
+    name: c"PowerPlayer".as_ptr(),
+    vendor: c"PowerPlayer".as_ptr(),
+    url: c"".as_ptr(),
+    version: c"1.0".as_ptr(),
+    get_extension: host_get_extension,
+    request_restart: host_request,
+    request_process: host_request,
+    request_callback: host_request,
+});
+
+unsafe extern "C" fn host_get_extension(_host: *const Host, _id: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const Host) {}
+
+/// Parameter changes handed to the plugin in one call. Plugins only see `list`, which
+/// the callbacks cast back to the whole struct.
+#[repr(C)]
+struct EventList<'a> {
+    list: InputEvents,
+    events: &'a [ParamValueEvent],
+}
+
+impl<'a> EventList<'a> {
+    fn new(events: &'a [ParamValueEvent]) -> Self {
+        Self {
+            list: InputEvents {
+                ctx: ptr::null_mut(),
+                size: events_size,
+                get: events_get,
+            },
+            events,
+        }
+    }
+}
+
+unsafe extern "C" fn events_size(list: *const InputEvents) -> u32 {
+    let list = &*list.cast::<EventList>();
+    list.events.len() as u32
+}
+
+unsafe extern "C" fn events_get(list: *const InputEvents, index: u32) -> *const EventHeader {
+    let list = &*list.cast::<EventList>();
+    list.events
+        .get(index as usize)
+        .map_or(ptr::null(), |event| ptr::addr_of!(event.header))
+}
+
+/// Events the plugin sends back (such as gestures from its own GUI) are dropped.
+unsafe extern "C" fn events_try_push(
+    _list: *const OutputEvents,
+    _event: *const EventHeader,
+) -> bool {
+    true
+}
+
+const OUTPUT_EVENTS: OutputEvents = OutputEvents {
+    ctx: ptr::null_mut(),
+    try_push: events_try_push,
+};
+
+/// `ctx` points at the `Vec<u8>` being written.
+unsafe extern "C" fn stream_write(
+    stream: *const OutputStream,
+    buffer: *const c_void,
+    size: u64,
+) -> i64 {
+    let data = &mut *((*stream).ctx as *mut Vec<u8>);
+    data.extend_from_slice(std::slice::from_raw_parts(
+        buffer.cast::<u8>(),
+        size as usize,
+    ));
+    size as i64
+}
+
+/// `ctx` points at a `Cursor<&[u8]>` over the saved state.
+unsafe extern "C" fn stream_read(
+    stream: *const InputStream,
+    buffer: *mut c_void,
+    size: u64,
+) -> i64 {
+    let cursor = &mut *((*stream).ctx as *mut Cursor<&[u8]>);
+    let target = std::slice::from_raw_parts_mut(buffer.cast::<u8>(), size as usize);
+    cursor.read(target).map_or(-1, |read| read as i64)
+}
+
+/// A loaded `.clap` module. CLAP wants `init` and `deinit` called once per load of the
+/// binary, so modules are shared between every plugin instance and scan that uses them.
+struct Module {
+    entry: *const PluginEntry,
+    path: PathBuf,
+    _library: Library,
+}
+
+// SAFETY: the entry functions are thread-safe per the CLAP spec and the module is
+// otherwise immutable.
+unsafe impl Send for Module {}
+unsafe impl Sync for Module {}
+
+fn loaded_modules() -> &'static Mutex<HashMap<PathBuf, Weak<Module>>> {
+    static MODULES: OnceLock<Mutex<HashMap<PathBuf, Weak<Module>>>> = OnceLock::new();
+    MODULES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+impl Module {
+    fn open(path: &Path) -> Result<Arc<Self>, String> {
+        let mut modules = loaded_modules()
+            .lock()
+            .map_err(|_| "CLAP module registry lock poisoned".to_string())?;
+        if let Some(module) = modules.get(path).and_then(Weak::upgrade) {
+            return Ok(module);
+        }
+        let binary = module_binary(path)?;
+        let c_path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| format!("Invalid CLAP plugin path: {}", path.display()))?;
+        // SAFETY: loading a plugin runs its initializers; `clap_entry` is the exported
+        // entry struct every CLAP module provides.
+        let module = unsafe {
+            let library = Library::new(&binary)
+                .map_err(|e| format!("Failed to load CLAP module {}: {e}", path.display()))?;
+            let entry = *library
+                .get::<*const PluginEntry>(b"clap_entry\0")
+                .map_err(|e| format!("{} is not a CLAP module: {e}", path.display()))?;
+            if entry.is_null() || (*entry).clap_version.major < 1 {
+                return Err(format!(
+                    "CLAP module {} uses an unsupported CLAP version",
+                    path.display()
+                ));
+            }
+            if !((*entry).init)(c_path.as_ptr()) {
+                return Err(format!(
+                    "CLAP module {} failed to initialize",
+                    path.display()
+                ));
+            }
+            Arc::new(Self {
+                entry,
+                path: path.to_path_buf(),
+                _library: library,
+            })
+        };
+        modules.retain(|_, module| module.strong_count() > 0);
+        modules.insert(path.to_path_buf(), Arc::downgrade(&module));
+        Ok(module)
+    }
+
+    fn factory(&self) -> Result<&PluginFactory, String> {
+        // SAFETY: the entry stays valid while the library is loaded.
+        let factory = unsafe { ((*self.entry).get_factory)(PLUGIN_FACTORY_ID.as_ptr()) };
+        if factory.is_null() {
+            return Err(format!(
+                "CLAP module {} has no plugin factory",
+                self.path.display()
+            ));
+        }
+        // SAFETY: the factory lives as long as the module.
+        Ok(unsafe { &*factory.cast::<PluginFactory>() })
+    }
+
+    /// `(id, name, features)` of every plugin in the module.
+    fn plugins(&self) -> Result<Vec<(String, String, Vec<String>)>, String> {
+        let factory = self.factory()?;
+        // SAFETY: indices come from `get_plugin_count`; descriptors live as long as the
+        // module and their strings are null-terminated.
+        unsafe {
+            Ok((0..(factory.get_plugin_count)(factory))
+                .filter_map(|index| {
+                    let desc = (factory.get_plugin_descriptor)(factory, index);
+                    if desc.is_null() || (*desc).id.is_null() {
+                        return None;
+                    }
+                    let mut features = Vec::new();
+                    let mut feature = (*desc).features;
+                    while !feature.is_null() && !(*feature).is_null() {
+                        features.push(c_str(*feature));
+                        feature = feature.add(1);
+                    }
+                    Some((c_str((*desc).id), c_str((*desc).name), features))
+                })
+                .collect())
+        }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        // SAFETY: every plugin from this module has been destroyed by now.
+        unsafe { ((*self.entry).deinit)() };
+    }
+}
+
+/// Path of the shared library for a `.clap` plugin: the file itself, or the binary
+/// inside the bundle on macOS.
+fn module_binary(path: &Path) -> Result<PathBuf, String> {
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let stem = path
+        .file_stem()
+        .ok_or_else(|| format!("Invalid CLAP plugin path: {}", path.display()))?;
+    let binary = path.join("Contents").join("MacOS").join(stem);
+    if cfg!(target_os = "macos") && binary.is_file() {
+        return Ok(binary);
+    }
+    Err(format!(
+        "CLAP plugin {} has no binary for this platform",
+        path.display()
+    ))
+}
+
+/// Standard CLAP install folders for this platform, after any listed in `CLAP_PATH`.
+pub fn default_search_dirs() -> Vec<PathBuf> {
+    let mut dirs = std::env::var_os("CLAP_PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if cfg!(target_os = "windows") {
+        if let Some(common) = std::env::var_os("CommonProgramFiles") {
+            dirs.push(PathBuf::from(common).join("CLAP"));
+        }
+        if let Some(local) = dirs::data_local_dir() {
+            dirs.push(local.join("Programs").join("Common").join("CLAP"));
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home.join("Library/Audio/Plug-Ins/CLAP"));
+        }
+        dirs.push(PathBuf::from("/Library/Audio/Plug-Ins/CLAP"));
+    } else {
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home.join(".clap"));
+        }
+        dirs.push(PathBuf::from("/usr/lib/clap"));
+    }
+    dirs
+}
+
+/// Effects in every `.clap` module under `dirs`. Modules that fail to load are
+/// skipped, as are instruments and note effects since they take no audio input.
+pub fn scan(dirs: &[PathBuf]) -> Vec<PluginClassInfo> {
+    let mut modules = Vec::new();
+    for dir in dirs {
+        let mut entries = WalkDir::new(dir).follow_links(true).into_iter();
+        while let Some(entry) = entries.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let is_module = entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("clap"));
+            if !is_module {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                entries.skip_current_dir();
+            }
+            modules.push(entry.into_path());
+        }
+    }
+
+    let mut found = Vec::new();
+    for path in modules {
+        let plugins = Module::open(&path).and_then(|module| module.plugins());
+        let plugins = match plugins {
+            Ok(plugins) => plugins,
+            Err(err) => {
+                eprintln!("Skipping CLAP plugin: {err}");
+                continue;
+            }
+        };
+        for (id, name, features) in plugins {
+            if !is_audio_effect(&features) {
+                continue;
+            }
+            found.push(PluginClassInfo {
+                format: PluginFormat::Clap,
+                path: path.to_string_lossy().to_string(),
+                class_id: id,
+                name,
+                categories: features.join("|"),
+            });
+        }
+    }
+    found
+}
+
+fn is_audio_effect(features: &[String]) -> bool {
+    let has = |feature: &str| features.iter().any(|f| f == feature);
+    has("audio-effect") || !(has("instrument") || has("note-effect"))
+}
+
+/// Scratch channels for an audio port the rack has no signal for.
+struct SparePort {
+    _channels: Vec<Vec<f32>>,
+    pointers: Vec<*mut f32>,
+}
+
+impl SparePort {
+    fn new(channel_count: u32, frames: usize) -> Self {
+        let mut channels = vec![vec![0.0; frames]; channel_count as usize];
+        let pointers = channels
+            .iter_mut()
+            .map(|channel| channel.as_mut_ptr())
+            .collect();
+        Self {
+            _channels: channels,
+            pointers,
+        }
+    }
+}
+
+/// One instantiated stereo effect, set up for blocks of up to `max_block` frames.
+pub struct ClapPlugin {
+    name: String,
+    path: String,
+    id: String,
+    plugin: *const Plugin,
+    params: *const PluginParams,
+    state: *const PluginStateExt,
+    /// One buffer per port; the main ports get the rack's channels at every `process`.
+    inputs: Vec<AudioBuffer>,
+    outputs: Vec<AudioBuffer>,
+    spare_ports: Vec<SparePort>,
+    main_input: [*mut f32; 2],
+    main_output: [*mut f32; 2],
+    sample_rate: f32,
+    max_block: usize,
+    active: bool,
+    processing: bool,
+    steady_time: i64,
+    // Declared last so the module is released after the plugin is destroyed.
+    _module: Arc<Module>,
+}
+
+// SAFETY: the plugin is only reached through `&mut self` or `&self` while the DSP chain
+// mutex is held, so it is never used from two threads at once.
+unsafe impl Send for ClapPlugin {}
+
+impl ClapPlugin {
+    pub fn load(path: &str, id: &str, sample_rate: f32, max_block: usize) -> Result<Self, String> {
+        let module = Module::open(Path::new(path))?;
+        let name = module
+            .plugins()?
+            .into_iter()
+            .find(|(plugin_id, ..)| plugin_id == id)
+            .map(|(_, name, _)| name)
+            .ok_or_else(|| format!("CLAP module {path} has no plugin {id}"))?;
+        let c_id = CString::new(id).map_err(|_| format!("Invalid CLAP plugin ID: {id}"))?;
+        let factory = module.factory()?;
+        // SAFETY: the host is static and the ID is null-terminated.
+        let plugin = unsafe { (factory.create_plugin)(factory, &HOST.0, c_id.as_ptr()) };
+        if plugin.is_null() {
+            return Err(format!("Failed to create CLAP plugin {name}"));
+        }
+        // SAFETY: `init` is the first call a new plugin gets; on failure it must still be
+        // destroyed.
+        if !unsafe { ((*plugin).init)(plugin) } {
+            unsafe { ((*plugin).destroy)(plugin) };
+            return Err(format!("CLAP plugin {name} failed to initialize"));
+        }
+
+        let mut clap = Self {
+            name,
+            path: path.to_string(),
+            id: id.to_string(),
+            plugin,
+            params: extension(plugin, EXT_PARAMS),
+            state: extension(plugin, EXT_STATE),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            spare_ports: Vec::new(),
+            main_input: [ptr::null_mut(); 2],
+            main_output: [ptr::null_mut(); 2],
+            sample_rate: sample_rate.max(8_000.0),
+            max_block: max_block.max(1),
+            active: false,
+            processing: false,
+            steady_time: 0,
+            _module: module,
+        };
+        clap.setup_ports()?;
+        clap.activate()?;
+        Ok(clap)
+    }
+
+    /// Requires stereo main ports and prepares silent buffers for any others.
+    fn setup_ports(&mut self) -> Result<(), String> {
+        let ports = extension::<PluginAudioPorts>(self.plugin, EXT_AUDIO_PORTS);
+        if ports.is_null() {
+            return Err(format!("CLAP plugin {} has no audio ports", self.name));
+        }
+        for is_input in [true, false] {
+            // SAFETY: indices come from `count` and `info` matches the CLAP struct.
+            let channel_counts = unsafe {
+                (0..((*ports).count)(self.plugin, is_input))
+                    .map(|index| {
+                        let mut info: AudioPortInfo = std::mem::zeroed();
+                        if ((*ports).get)(self.plugin, index, is_input, &mut info) {
+                            info.channel_count
+                        } else {
+                            0
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            };
+            if channel_counts.first() != Some(&2) {
+                return Err(format!(
+                    "CLAP plugin {} is not an effect with stereo input and output",
+                    self.name
+                ));
+            }
+            let mut buffers = Vec::with_capacity(channel_counts.len());
+            for (index, channel_count) in channel_counts.into_iter().enumerate() {
+                let data32 = if index == 0 {
+                    ptr::null_mut()
+                } else {
+                    let mut spare = SparePort::new(channel_count, self.max_block);
+                    let data32 = spare.pointers.as_mut_ptr();
+                    self.spare_ports.push(spare);
+                    data32
+                };
+                buffers.push(AudioBuffer {
+                    data32,
+                    data64: ptr::null_mut(),
+                    channel_count,
+                    latency: 0,
+                    constant_mask: 0,
+                });
+            }
+            if is_input {
+                self.inputs = buffers;
+            } else {
+                self.outputs = buffers;
+            }
+        }
+        Ok(())
+    }
+
+    fn activate(&mut self) -> Result<(), String> {
+        // SAFETY: plain call on a live plugin.
+        let activated = unsafe {
+            ((*self.plugin).activate)(
+                self.plugin,
+                f64::from(self.sample_rate),
+                1,
+                self.max_block as u32,
+            )
+        };
+        if !activated {
+            return Err(format!(
+                "CLAP plugin {} failed to activate at {} Hz",
+                self.name, self.sample_rate
+            ));
+        }
+        self.active = true;
+        Ok(())
+    }
+
+    fn deactivate(&mut self) {
+        if !self.active {
+            return;
+        }
+        // SAFETY: balances `start_processing` and `activate`.
+        unsafe {
+            if self.processing {
+                ((*self.plugin).stop_processing)(self.plugin);
+            }
+            ((*self.plugin).deactivate)(self.plugin);
+        }
+        self.processing = false;
+        self.active = false;
+    }
+
+    /// Runs the plugin on `frames` frames of the given main channels. Returns `false`
+    /// when the plugin would not start processing or reported an error.
+    fn run_block(&mut self, frames: usize, input: [*mut f32; 2], output: [*mut f32; 2]) -> bool {
+        // SAFETY: plain call on an active plugin.
+        if !self.processing && unsafe { ((*self.plugin).start_processing)(self.plugin) } {
+            self.processing = true;
+        }
+        if !self.processing {
+            return false;
+        }
+        self.main_input = input;
+        self.main_output = output;
+        self.inputs[0].data32 = self.main_input.as_mut_ptr();
+        self.outputs[0].data32 = self.main_output.as_mut_ptr();
+        let in_events = EventList::new(&[]);
+        let process = Process {
+            steady_time: self.steady_time,
+            frames_count: frames as u32,
+            transport: ptr::null(),
+            audio_inputs: self.inputs.as_ptr(),
+            audio_outputs: self.outputs.as_mut_ptr(),
+            audio_inputs_count: self.inputs.len() as u32,
+            audio_outputs_count: self.outputs.len() as u32,
+            in_events: &in_events.list,
+            out_events: &OUTPUT_EVENTS,
+        };
+        // SAFETY: every buffer and event list referenced by `process` outlives the call.
+        let status = unsafe { ((*self.plugin).process)(self.plugin, &process) };
+        self.steady_time += frames as i64;
+        status != PROCESS_ERROR
+    }
+
+    /// Maps a plain parameter value into 0.0–1.0 for the parameter's range.
+    fn normalize(info: &ParamInfo, value: f64) -> f64 {
+        let range = info.max_value - info.min_value;
+        if range > 0.0 {
+            ((value - info.min_value) / range).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn param_info(&self, id: u32) -> Option<ParamInfo> {
+        let params = self.params;
+        if params.is_null() {
+            return None;
+        }
+        // SAFETY: indices come from `count` and `info` matches the CLAP struct.
+        unsafe {
+            (0..((*params).count)(self.plugin)).find_map(|index| {
+                let mut info: ParamInfo = std::mem::zeroed();
+                (((*params).get_info)(self.plugin, index, &mut info) && info.id == id)
+                    .then_some(info)
+            })
+        }
+    }
+}
+
+impl EffectPlugin for ClapPlugin {
+    fn format(&self) -> PluginFormat {
+        PluginFormat::Clap
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn class_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Values are reported normalized, like VST3's, whatever range the plugin uses.
+    fn parameters(&self) -> Vec<PluginParameter> {
+        let params = self.params;
+        if params.is_null() {
+            return Vec::new();
+        }
+        // SAFETY: indices come from `count` and the buffers match the CLAP structs.
+        unsafe {
+            (0..((*params).count)(self.plugin))
+                .filter_map(|index| {
+                    let mut info: ParamInfo = std::mem::zeroed();
+                    if !((*params).get_info)(self.plugin, index, &mut info)
+                        || info.flags & PARAM_IS_HIDDEN != 0
+                    {
+                        return None;
+                    }
+                    let mut value = info.default_value;
+                    ((*params).get_value)(self.plugin, info.id, &mut value);
+                    let mut display = [0 as c_char; 128];
+                    if !((*params).value_to_text)(
+                        self.plugin,
+                        info.id,
+                        value,
+                        display.as_mut_ptr(),
+                        display.len() as u32,
+                    ) {
+                        display[0] = 0;
+                    }
+                    let step_count = if info.flags & PARAM_IS_STEPPED != 0 {
+                        (info.max_value - info.min_value).round() as i32
+                    } else {
+                        0
+                    };
+                    Some(PluginParameter {
+                        id: info.id,
+                        title: c_string(&info.name),
+                        units: String::new(),
+                        value: Self::normalize(&info, value),
+                        default_value: Self::normalize(&info, info.default_value),
+                        step_count,
+                        display: c_string(&display),
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// Applied right away through `flush`, so the change also lands in the next saved
+    /// state while playback is paused.
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err("CLAP parameter value must be finite".to_string());
+        }
+        let info = self
+            .param_info(id)
+            .ok_or_else(|| format!("CLAP plugin {} has no parameter {id}", self.name))?;
+        let mut plain = info.min_value + value.clamp(0.0, 1.0) * (info.max_value - info.min_value);
+        if info.flags & PARAM_IS_STEPPED != 0 {
+            plain = plain.round();
+        }
+        let event = ParamValueEvent {
+            header: EventHeader {
+                size: std::mem::size_of::<ParamValueEvent>() as u32,
+                time: 0,
+                space_id: CORE_EVENT_SPACE_ID,
+                event_type: EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id: id,
+            cookie: info.cookie,
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value: plain,
+        };
+        let events = EventList::new(std::slice::from_ref(&event));
+        // SAFETY: `param_info` found the params extension; the event list outlives the
+        // call and nothing else runs on the plugin while `&mut self` is held.
+        unsafe { ((*self.params).flush)(self.plugin, &events.list, &OUTPUT_EVENTS) };
+        Ok(())
+    }
+
+    fn state(&self) -> Result<PluginState, String> {
+        if self.state.is_null() {
+            return Ok(PluginState::default());
+        }
+        let mut data = Vec::new();
+        let stream = OutputStream {
+            ctx: ptr::from_mut(&mut data).cast(),
+            write: stream_write,
+        };
+        // SAFETY: the stream and its buffer outlive the call.
+        if !unsafe { ((*self.state).save)(self.plugin, &stream) } {
+            return Err(format!("Failed to read state of CLAP plugin {}", self.name));
+        }
+        Ok(PluginState {
+            component: data,
+            controller: Vec::new(),
+        })
+    }
+
+    fn restore_state(&mut self, state: &PluginState) -> Result<(), String> {
+        if self.state.is_null() || state.component.is_empty() {
+            return Ok(());
+        }
+        let mut cursor = Cursor::new(state.component.as_slice());
+        let stream = InputStream {
+            ctx: ptr::from_mut(&mut cursor).cast(),
+            read: stream_read,
+        };
+        // SAFETY: the stream and its cursor outlive the call.
+        if !unsafe { ((*self.state).load)(self.plugin, &stream) } {
+            return Err(format!(
+                "CLAP plugin {} rejected its saved state",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), String> {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() <= f32::EPSILON {
+            return Ok(());
+        }
+        self.deactivate();
+        self.sample_rate = sr;
+        self.activate()
+    }
+
+    fn process(&mut self, input: [&mut [f32]; 2], output: [&mut [f32]; 2]) {
+        let frames = input[0]
+            .len()
+            .min(input[1].len())
+            .min(output[0].len())
+            .min(output[1].len());
+        let [in_left, in_right] = input;
+        let [out_left, out_right] = output;
+        let processed = self.active
+            && frames <= self.max_block
+            && self.run_block(
+                frames,
+                [in_left.as_mut_ptr(), in_right.as_mut_ptr()],
+                [out_left.as_mut_ptr(), out_right.as_mut_ptr()],
+            );
+        if !processed {
+            out_left[..frames].copy_from_slice(&in_left[..frames]);
+            out_right[..frames].copy_from_slice(&in_right[..frames]);
+        }
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        // SAFETY: balances `create_plugin`; nothing uses the plugin afterwards.
+        unsafe { ((*self.plugin).destroy)(self.plugin) };
+    }
+}
+
+fn extension<T>(plugin: *const Plugin, id: &CStr) -> *const T {
+    // SAFETY: `get_extension` may be called any time after `init`.
+    unsafe { ((*plugin).get_extension)(plugin, id.as_ptr()).cast() }
+}
+
+fn c_str(text: *const c_char) -> String {
+    if text.is_null() {
+        return String::new();
+    }
+    // SAFETY: callers pass null-terminated strings owned by the plugin.
+    unsafe { CStr::from_ptr(text) }
+        .to_string_lossy()
+        .trim()
+        .to_string()
+}
+
+fn c_string(chars: &[c_char]) -> String {
+    let bytes = chars.iter().map(|c| *c as u8).collect::<Vec<_>>();
+    CStr::from_bytes_until_nul(&bytes)
+        .map(|text| text.to_string_lossy().trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_audio_effect, stream_read, stream_write, EventHeader, EventList, InputStream,
+        OutputStream, ParamValueEvent, EVENT_PARAM_VALUE,
+    };
+    use std::io::Cursor;
+    use std::ptr;
+
+    #[test]
+    fn host_objects_follow_the_clap_abi() {
+        // The param value event is 56 bytes with the value last.
+        assert_eq!(std::mem::size_of::<ParamValueEvent>(), 56);
+        assert_eq!(std::mem::offset_of!(ParamValueEvent, value), 48);
+
+        let event = ParamValueEvent {
+            header: EventHeader {
+                size: 56,
+                time: 0,
+                space_id: 0,
+                event_type: EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id: 7,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value: 0.5,
+        };
+        let events = EventList::new(std::slice::from_ref(&event));
+        let list = &events.list;
+        unsafe {
+            assert_eq!((list.size)(list), 1);
+            let header = (list.get)(list, 0);
+            assert_eq!((*header.cast::<ParamValueEvent>()).param_id, 7);
+            assert!((list.get)(list, 1).is_null());
+        }
+
+        // Plugins see the streams only through their function pointers.
+        let mut data: Vec<u8> = Vec::new();
+        let output = OutputStream {
+            ctx: ptr::from_mut(&mut data).cast(),
+            write: stream_write,
+        };
+        let payload = b"plugin state";
+        unsafe { assert_eq!((output.write)(&output, payload.as_ptr().cast(), 12), 12) };
+        assert_eq!(data, payload);
+
+        let mut cursor = Cursor::new(data.as_slice());
+        let input = InputStream {
+            ctx: ptr::from_mut(&mut cursor).cast(),
+            read: stream_read,
+        };
+        let mut back = [0_u8; 8];
+        unsafe {
+            assert_eq!((input.read)(&input, back.as_mut_ptr().cast(), 8), 8);
+            assert_eq!((input.read)(&input, back.as_mut_ptr().cast(), 8), 4);
+            assert_eq!((input.read)(&input, back.as_mut_ptr().cast(), 8), 0);
+        }
+        assert_eq!(&back[..4], b"tate");
+
+        let features = |list: &[&str]| list.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert!(is_audio_effect(&features(&["audio-effect", "reverb"])));
+        assert!(is_audio_effect(&features(&["equalizer"])));
+        assert!(!is_audio_effect(&features(&["instrument", "synthesizer"])));
+    }
+}
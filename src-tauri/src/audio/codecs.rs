@@ -0,0 +1,36 @@
+//! Symphonia's probe and codec registry, extended with the formats its bundled build
+//! cannot play: Opus, WavPack and Monkey's Audio. Everything that opens audio through
+//! Symphonia uses these instead of `symphonia::default`.
+
+use std::sync::OnceLock;
+
+use symphonia::core::{codecs::CodecRegistry, probe::Probe};
+
+use super::{
+    ape::{ApeDecoder, ApeReader},
+    opus::OpusDecoder,
+    wavpack::{WavPackDecoder, WavPackReader},
+};
+
+pub fn get_codecs() -> &'static CodecRegistry {
+    static CODECS: OnceLock<CodecRegistry> = OnceLock::new();
+    CODECS.get_or_init(|| {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<OpusDecoder>();
+        registry.register_all::<WavPackDecoder>();
+        registry.register_all::<ApeDecoder>();
+        registry
+    })
+}
+
+pub fn get_probe() -> &'static Probe {
+    static PROBE: OnceLock<Probe> = OnceLock::new();
+    PROBE.get_or_init(|| {
+        let mut probe = Probe::default();
+        symphonia::default::register_enabled_formats(&mut probe);
+        probe.register_all::<WavPackReader>();
+        probe.register_all::<ApeReader>();
+        probe
+    })
+}
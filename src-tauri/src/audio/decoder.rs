@@ -1,10 +1,15 @@
 use std::{
     fs::File,
     io::{Cursor, Read, Seek},
+    ops::Range,
     path::Path,
 };
 
 use memmap2::MmapOptions;
+
+use super::codecs;
+use super::dsd::{decode_dsd_file, is_dsd_path, read_dsd_metadata};
+use crate::library::cue::{resolve_virtual_track, CueTrack};
 use rubato::{FftFixedInOut, Resampler};
 
 use symphonia::core::{
@@ -34,8 +39,15 @@ pub struct CoverArt {
 pub struct TrackMetadata {
     pub artist: Option<String>,
     pub title: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    /// Release year, from the first four digits of the date tag.
+    pub year: Option<u32>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
     pub cover_art: Option<CoverArt>,
     pub duration_seconds: Option<f32>,
+    pub sample_rate: Option<u32>,
 }
 
 const MMAP_THRESHOLD_BYTES: u64 = 50 * 1024 * 1024;
@@ -92,6 +104,12 @@ fn open_media_source(path: &Path) -> Result<(TrackMediaSource, bool), String> {
 }
 
 pub fn read_track_metadata(path: &Path) -> Result<TrackMetadata, String> {
+    if let Some(track) = resolve_virtual_track(path) {
+        return read_cue_track_metadata(&track?);
+    }
+    if is_dsd_path(path) {
+        return read_dsd_metadata(path);
+    }
     let (source, _) = open_media_source(path)?;
     let mss = MediaSourceStream::new(Box::new(source), Default::default());
 
@@ -100,7 +118,7 @@ pub fn read_track_metadata(path: &Path) -> Result<TrackMetadata, String> {
         hint.with_extension(ext);
     }
 
-    let mut probed = symphonia::default::get_probe()
+    let mut probed = codecs::get_probe()
         .format(
             &hint,
             mss,
@@ -115,8 +133,14 @@ pub fn read_track_metadata(path: &Path) -> Result<TrackMetadata, String> {
             .file_stem()
             .and_then(|stem| stem.to_str())
             .map(std::string::ToString::to_string),
+        album: None,
+        genre: None,
+        year: None,
+        track_number: None,
+        disc_number: None,
         cover_art: None,
         duration_seconds: None,
+        sample_rate: None,
     };
 
     if let Some(pre_metadata) = probed.metadata.get() {
@@ -131,6 +155,7 @@ pub fn read_track_metadata(path: &Path) -> Result<TrackMetadata, String> {
     }
 
     if let Some(track) = format.default_track() {
+        metadata.sample_rate = track.codec_params.sample_rate;
         if let (Some(sample_rate), Some(n_frames)) =
             (track.codec_params.sample_rate, track.codec_params.n_frames)
         {
@@ -161,6 +186,33 @@ fn apply_metadata_revision(revision: &MetadataRevision, metadata: &mut TrackMeta
         if metadata.title.is_none() && matches!(tag.std_key, Some(StandardTagKey::TrackTitle)) {
             metadata.title = Some(tag.value.to_string());
         }
+
+        if metadata.genre.is_none() && matches!(tag.std_key, Some(StandardTagKey::Genre)) {
+            metadata.genre = Some(tag.value.to_string());
+        }
+
+        if metadata.year.is_none()
+            && matches!(
+                tag.std_key,
+                Some(
+                    StandardTagKey::Date
+                        | StandardTagKey::ReleaseDate
+                        | StandardTagKey::OriginalDate
+                )
+            )
+        {
+            metadata.year = parse_year(&tag.value.to_string());
+        }
+
+        match tag.std_key {
+            Some(StandardTagKey::TrackNumber) if metadata.track_number.is_none() => {
+                metadata.track_number = parse_position(&tag.value.to_string());
+            }
+            Some(StandardTagKey::DiscNumber) if metadata.disc_number.is_none() => {
+                metadata.disc_number = parse_position(&tag.value.to_string());
+            }
+            _ => {}
+        }
     }
 
     if metadata.cover_art.is_none() {
@@ -173,7 +225,31 @@ fn apply_metadata_revision(revision: &MetadataRevision, metadata: &mut TrackMeta
     }
 }
 
+/// Year at the start of a date tag (`1999`, `1999-04-12`, `1999/04`).
+fn parse_year(date: &str) -> Option<u32> {
+    date.trim().get(..4)?.parse().ok().filter(|year| *year > 0)
+}
+
+/// Track or disc number from a tag such as `3` or `3/12`.
+fn parse_position(value: &str) -> Option<u32> {
+    value
+        .split('/')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|number| *number > 0)
+}
+
+/// Decodes a whole file, or just the song of a cue sheet virtual track path
+/// (see `library::cue`), so playback and analysis treat both the same way.
 pub fn decode_file(path: &Path) -> Result<DecodedTrack, String> {
+    if let Some(track) = resolve_virtual_track(path) {
+        return decode_cue_track(&track?);
+    }
+    if is_dsd_path(path) {
+        return decode_dsd_file(path);
+    }
     let (source, _) = open_media_source(path)?;
     let mss = MediaSourceStream::new(Box::new(source), Default::default());
 
@@ -182,7 +258,7 @@ pub fn decode_file(path: &Path) -> Result<DecodedTrack, String> {
         hint.with_extension(ext);
     }
 
-    let probed = symphonia::default::get_probe()
+    let probed = codecs::get_probe()
         .format(
             &hint,
             mss,
@@ -196,7 +272,7 @@ pub fn decode_file(path: &Path) -> Result<DecodedTrack, String> {
         .default_track()
         .ok_or_else(|| "No default audio track found".to_string())?;
 
-    let mut decoder = symphonia::default::get_codecs()
+    let mut decoder = codecs::get_codecs()
         .make(&track.codec_params, &DecoderOptions::default())
         .map_err(|e| format!("Decoder creation failed: {e}"))?;
 
@@ -229,6 +305,13 @@ pub fn decode_file(path: &Path) -> Result<DecodedTrack, String> {
 
         let spec = *decoded.spec();
         let duration = decoded.capacity() as u64;
+        // WavPack and APE packets vary in length, so the buffer may need to grow.
+        if sample_buffer
+            .as_ref()
+            .is_some_and(|buffer| buffer.capacity() < decoded.frames() * spec.channels.count())
+        {
+            sample_buffer = None;
+        }
         let buffer = sample_buffer.get_or_insert_with(|| SampleBuffer::<f32>::new(duration, spec));
         buffer.copy_interleaved_ref(decoded);
         samples.extend_from_slice(buffer.samples());
@@ -241,6 +324,102 @@ pub fn decode_file(path: &Path) -> Result<DecodedTrack, String> {
     })
 }
 
+/// Cue sheet tags take precedence over the tags of the file holding the whole album.
+fn read_cue_track_metadata(track: &CueTrack) -> Result<TrackMetadata, String> {
+    let mut metadata = read_track_metadata(&track.file)?;
+    let end_seconds = track
+        .end_seconds()
+        .or_else(|| metadata.duration_seconds.map(f64::from));
+    metadata.duration_seconds =
+        end_seconds.map(|end| (end - track.start_seconds()).max(0.0) as f32);
+    metadata.title = track.title.clone().or(metadata.title);
+    metadata.artist = track.performer.clone().or(metadata.artist);
+    metadata.album = track.album.clone().or(metadata.album);
+    metadata.genre = track.genre.clone().or(metadata.genre);
+    metadata.track_number = Some(track.number);
+    Ok(metadata)
+}
+
+fn decode_cue_track(track: &CueTrack) -> Result<DecodedTrack, String> {
+    let mut decoded = decode_file(&track.file)?;
+    let channels = decoded.channels.max(1) as usize;
+    let range = track.frame_range(decoded.sample_rate, decoded.samples.len() / channels);
+    decoded.samples.truncate(range.end * channels);
+    decoded.samples.drain(..range.start * channels);
+    Ok(decoded)
+}
+
+/// -60 dBFS: below the noise floor of virtually any master, above typical dither.
+const SILENCE_THRESHOLD: f32 = 0.001;
+/// Audio kept around the detected edges so soft attacks and fade tails are not clipped.
+const SILENCE_PADDING_MS: usize = 20;
+
+/// Returns the frame range between the first and last audible frames of an interleaved
+/// buffer, padded by `SILENCE_PADDING_MS`. Fully silent buffers return the whole range.
+pub fn audible_frame_range(samples: &[f32], channels: usize, sample_rate: u32) -> Range<usize> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let is_audible = |frame: &[f32]| frame.iter().any(|sample| sample.abs() > SILENCE_THRESHOLD);
+    let Some(first) = samples.chunks_exact(channels).position(is_audible) else {
+        return 0..frames;
+    };
+    let last = samples
+        .chunks_exact(channels)
+        .rposition(is_audible)
+        .unwrap_or(first);
+    let padding = sample_rate as usize * SILENCE_PADDING_MS / 1000;
+    first.saturating_sub(padding)..(last + 1 + padding).min(frames)
+}
+
+/// Returns the frame ranges where every sample stays below `threshold_db` (dBFS) for at
+/// least `min_seconds`. Each range is shrunk by `SILENCE_PADDING_MS` on the sides that
+/// border audio, so skipping it keeps a short natural tail and lead-in.
+pub fn silent_gaps(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    threshold_db: f32,
+    min_seconds: f32,
+) -> Vec<Range<usize>> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let threshold = 10.0_f32.powf(threshold_db / 20.0);
+    let min_frames = ((min_seconds.max(0.0) * sample_rate as f32) as usize).max(1);
+    let padding = sample_rate as usize * SILENCE_PADDING_MS / 1000;
+
+    let mut gaps = Vec::new();
+    let mut push_gap = |start: usize, end: usize| {
+        if end - start < min_frames {
+            return;
+        }
+        let start = if start == 0 { 0 } else { start + padding };
+        let end = if end == frames {
+            frames
+        } else {
+            end.saturating_sub(padding)
+        };
+        if start < end {
+            gaps.push(start..end);
+        }
+    };
+    let mut silence_start = None;
+    for (frame, samples) in samples.chunks_exact(channels).enumerate() {
+        let silent = samples.iter().all(|sample| sample.abs() <= threshold);
+        match (silent, silence_start) {
+            (true, None) => silence_start = Some(frame),
+            (false, Some(start)) => {
+                push_gap(start, frame);
+                silence_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = silence_start {
+        push_gap(start, frames);
+    }
+    gaps
+}
+
 /// Minimal-cost linear interpolation resampler used only when device and track sample-rates differ.
 /// It is intentionally simple for low-latency startup and predictable memory behavior, but quality is
 /// lower than dedicated sinc-based resamplers; this is acceptable here as a fallback path.
@@ -320,7 +499,40 @@ pub fn resample_hq(
 
 #[cfg(test)]
 mod tests {
-    use super::{resample_linear, should_use_mmap};
+    use super::{
+        audible_frame_range, decode_file, parse_position, parse_year, read_track_metadata,
+        resample_linear, should_use_mmap, silent_gaps,
+    };
+    use crate::test_support::unique_temp_path;
+
+    /// Minimal 16-bit big-endian PCM AIFF: FORM/AIFF with COMM + SSND chunks.
+    fn aiff_bytes(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let frames = (samples.len() / channels as usize) as u32;
+        // 80-bit IEEE extended sample rate.
+        let exponent = 31 - sample_rate.leading_zeros();
+        let mantissa = (sample_rate as u64) << (63 - exponent);
+        let mut comm = Vec::new();
+        comm.extend_from_slice(&channels.to_be_bytes());
+        comm.extend_from_slice(&frames.to_be_bytes());
+        comm.extend_from_slice(&16_u16.to_be_bytes());
+        comm.extend_from_slice(&(16_383 + exponent as u16).to_be_bytes());
+        comm.extend_from_slice(&mantissa.to_be_bytes());
+        let mut ssnd = vec![0_u8; 8];
+        for sample in samples {
+            ssnd.extend_from_slice(&sample.to_be_bytes());
+        }
+
+        let mut body = b"AIFF".to_vec();
+        for (id, chunk) in [(b"COMM", comm), (b"SSND", ssnd)] {
+            body.extend_from_slice(id);
+            body.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            body.extend_from_slice(&chunk);
+        }
+        let mut file = b"FORM".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
 
     #[test]
     fn resample_changes_frame_count() {
@@ -334,4 +546,98 @@ mod tests {
         assert!(!should_use_mmap((50 * 1024 * 1024) - 1));
         assert!(should_use_mmap((50 * 1024 * 1024) + 1));
     }
+
+    #[test]
+    fn audible_range_skips_leading_and_trailing_silence() {
+        // 1 kHz rate: padding is 20 frames.
+        let mut mono = vec![0.0_f32; 1_000];
+        mono[300] = 0.5;
+        mono[600] = -0.5;
+        mono[900] = 0.0005; // below the threshold
+        assert_eq!(audible_frame_range(&mono, 1, 1_000), 280..621);
+
+        let stereo = [0.0, 0.0, 0.0, 0.2, 0.0, 0.0];
+        assert_eq!(audible_frame_range(&stereo, 2, 0), 1..2);
+    }
+
+    #[test]
+    fn silent_buffer_keeps_full_range() {
+        assert_eq!(audible_frame_range(&[0.0; 10], 2, 48_000), 0..5);
+    }
+
+    #[test]
+    fn silent_gaps_finds_long_interior_silence() {
+        // 1 kHz rate: padding is 20 frames, minimum gap 1 s = 1_000 frames.
+        let mut mono = vec![0.5_f32; 5_000];
+        mono[1_000..2_500].fill(0.0);
+        mono[3_000..3_400].fill(0.0); // too short to skip
+        mono[4_000..].fill(0.0);
+        assert_eq!(
+            silent_gaps(&mono, 1, 1_000, -60.0, 1.0),
+            vec![1_020..2_480, 4_020..5_000]
+        );
+    }
+
+    #[test]
+    fn silent_gaps_respects_threshold() {
+        let quiet = vec![0.01_f32; 4_000]; // -40 dBFS
+        assert!(silent_gaps(&quiet, 2, 1_000, -60.0, 1.0).is_empty());
+        assert_eq!(silent_gaps(&quiet, 2, 1_000, -30.0, 1.0), vec![0..2_000]);
+    }
+
+    #[test]
+    fn years_are_read_from_date_tags() {
+        assert_eq!(parse_year("1999"), Some(1999));
+        assert_eq!(parse_year(" 2004-05-17T00:00:00Z"), Some(2004));
+        assert_eq!(parse_year("12/05/2004"), None);
+        assert_eq!(parse_year("98"), None);
+        assert_eq!(parse_position("3/12"), Some(3));
+        assert_eq!(parse_position(" 07 "), Some(7));
+        assert_eq!(parse_position("0"), None);
+    }
+
+    #[test]
+    fn decodes_aiff_files() {
+        let path = unique_temp_path("decoder.aiff");
+        let samples = [0_i16, 0, 16_384, -16_384, -32_768, 32_767];
+        std::fs::write(&path, aiff_bytes(44_100, 2, &samples)).expect("aiff should be written");
+
+        let track = decode_file(&path).expect("aiff should decode");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(track.sample_rate, 44_100);
+        assert_eq!(track.channels, 2);
+        assert_eq!(track.samples.len(), samples.len());
+        assert!((track.samples[2] - 0.5).abs() < 1e-4);
+        assert!((track.samples[3] + 0.5).abs() < 1e-4);
+        assert!((track.samples[4] + 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn cue_virtual_tracks_decode_only_their_song() {
+        let dir = unique_temp_path("cue");
+        std::fs::create_dir_all(&dir).expect("test dir should be created");
+        // 7.5 kHz: 100 sample frames per CUE frame, one second of audio.
+        let samples = (0..7_500).map(|i| i as i16).collect::<Vec<_>>();
+        std::fs::write(dir.join("album.aiff"), aiff_bytes(7_500, 1, &samples))
+            .expect("aiff should be written");
+        std::fs::write(
+            dir.join("album.cue"),
+            "FILE \"album.aiff\" AIFF\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Second\"\n    INDEX 01 00:00:30\n",
+        )
+        .expect("cue should be written");
+
+        let first = decode_file(&dir.join("album.cue#01")).expect("first song should decode");
+        let second = decode_file(&dir.join("album.cue#02")).expect("second song should decode");
+        let metadata =
+            read_track_metadata(&dir.join("album.cue#02")).expect("metadata should be read");
+        let missing = decode_file(&dir.join("album.cue#03"));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(first.samples.len(), 3_000);
+        assert_eq!(second.samples.len(), 4_500);
+        assert!((second.samples[0] - 3_000.0 / 32_768.0).abs() < 1e-6);
+        assert_eq!(metadata.title.as_deref(), Some("Second"));
+        assert!((metadata.duration_seconds.expect("duration") - 0.6).abs() < 1e-3);
+        assert!(missing.is_err());
+    }
 }
@@ -0,0 +1,465 @@
+//! UPnP/DLNA renderer output: finds MediaRenderer devices on the LAN over SSDP, serves
+//! the current track to them through `audio::media_server` and drives playback
+//! through their AVTransport service.
+
+use std::collections::HashSet;
+use std::io;
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::blocking::Client;
+use reqwest::Url;
+use serde::Serialize;
+
+use super::media_server;
+
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const MEDIA_RENDERER_TARGET: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+/// How long discovery listens for SSDP replies; renderers answer within `MX` seconds.
+pub const DISCOVERY_WINDOW: Duration = Duration::from_secs(3);
+/// Renderers are on the LAN, so anything slower than this is treated as gone.
+const CONTROL_TIMEOUT_SECS: u64 = 3;
+
+/// A MediaRenderer with an AVTransport service.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DlnaRenderer {
+    /// The device UDN (`uuid:...`), stable across restarts.
+    pub id: String,
+    pub name: String,
+    pub location: String,
+    pub control_url: String,
+    pub service_type: String,
+}
+
+/// Sends an SSDP M-SEARCH for media renderers and resolves every device that answers
+/// within `window`. Devices whose description cannot be fetched are skipped.
+pub fn discover(window: Duration) -> Result<Vec<DlnaRenderer>, String> {
+    let socket =
+        UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to open SSDP socket: {e}"))?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDR}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {MEDIA_RENDERER_TARGET}\r\n\r\n",
+        window.as_secs().max(1)
+    );
+    // SSDP runs over UDP, so the search is repeated once in case the first is lost.
+    for _ in 0..2 {
+        socket
+            .send_to(search.as_bytes(), SSDP_ADDR)
+            .map_err(|e| format!("Failed to send SSDP search: {e}"))?;
+    }
+
+    let deadline = Instant::now() + window;
+    let mut locations = Vec::new();
+    let mut buffer = [0_u8; 2048];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        if remaining.is_zero() {
+            break;
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(|e| format!("Failed to configure SSDP socket: {e}"))?;
+        match socket.recv_from(&mut buffer) {
+            Ok((read, _)) => {
+                if let Some(location) =
+                    parse_ssdp_location(&String::from_utf8_lossy(&buffer[..read]))
+                {
+                    if !locations.contains(&location) {
+                        locations.push(location);
+                    }
+                }
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break
+            }
+            Err(err) => return Err(format!("Failed to read SSDP reply: {err}")),
+        }
+    }
+
+    let client = control_client()?;
+    let mut seen = HashSet::new();
+    let mut renderers = Vec::new();
+    for location in locations {
+        let description = match client
+            .get(&location)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+        {
+            Ok(description) => description,
+            Err(err) => {
+                eprintln!("Failed to fetch renderer description {location}: {err}");
+                continue;
+            }
+        };
+        if let Some(renderer) = parse_description(&description, &location) {
+            // Multi-homed devices answer once per interface.
+            if seen.insert(renderer.id.clone()) {
+                renderers.push(renderer);
+            }
+        }
+    }
+    renderers.sort_by_key(|renderer| renderer.name.to_lowercase());
+    Ok(renderers)
+}
+
+fn parse_ssdp_location(response: &str) -> Option<String> {
+    let mut lines = response.lines();
+    let status = lines.next()?;
+    if !status.starts_with("HTTP/1.1 200") && !status.starts_with("NOTIFY") {
+        return None;
+    }
+    lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Reads the device description; `None` unless it offers an AVTransport service.
+fn parse_description(xml: &str, location: &str) -> Option<DlnaRenderer> {
+    let base = xml_text(xml, "URLBase")
+        .and_then(|base| Url::parse(&base).ok())
+        .or_else(|| Url::parse(location).ok())?;
+    let service = xml_elements(xml, "service").into_iter().find(|service| {
+        xml_text(service, "serviceType").is_some_and(|kind| kind.contains(":AVTransport:"))
+    })?;
+    let control_url = base.join(&xml_text(service, "controlURL")?).ok()?;
+    Some(DlnaRenderer {
+        id: xml_text(xml, "UDN").unwrap_or_else(|| location.to_string()),
+        name: xml_text(xml, "friendlyName").unwrap_or_else(|| "DLNA renderer".to_string()),
+        location: location.to_string(),
+        control_url: control_url.to_string(),
+        service_type: xml_text(service, "serviceType")?,
+    })
+}
+
+/// Inner text of every `<tag>` (any namespace prefix). Enough for UPnP descriptions and
+/// SOAP replies, which never nest an element inside one of the same name.
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        let local = name.rsplit(':').next().unwrap_or(name);
+        if local != tag {
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        if rest[..tag_end].ends_with('/') {
+            found.push("");
+            continue;
+        }
+        let body = &rest[tag_end + 1..];
+        let Some(close) = body.find(&format!("</{name}>")) else {
+            break;
+        };
+        found.push(&body[..close]);
+        rest = &body[close..];
+    }
+    found
+}
+
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    xml_elements(xml, tag)
+        .first()
+        .map(|text| xml_unescape(text.trim()))
+        .filter(|text| !text.is_empty())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn control_client() -> Result<Client, String> {
+    Client::builder()
+        .timeout(Duration::from_secs(CONTROL_TIMEOUT_SECS))
+        .user_agent("PowerPlayer/0.1 UPnP/1.0")
+        .build()
+        .map_err(|e| format!("Failed to build UPnP HTTP client: {e}"))
+}
+
+/// Invokes an AVTransport action on instance 0.
+fn send_action(
+    renderer: &DlnaRenderer,
+    action: &str,
+    arguments: &[(&str, &str)],
+) -> Result<(), String> {
+    let arguments = arguments
+        .iter()
+        .map(|(name, value)| format!("<{name}>{}</{name}>", xml_escape(value)))
+        .collect::<String>();
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service}\"><InstanceID>0</InstanceID>{arguments}</u:{action}></s:Body>\
+         </s:Envelope>",
+        service = renderer.service_type,
+    );
+    let response = control_client()?
+        .post(&renderer.control_url)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header(
+            "SOAPACTION",
+            format!("\"{}#{action}\"", renderer.service_type),
+        )
+        .body(body)
+        .send()
+        .map_err(|e| format!("{} did not answer {action}: {e}", renderer.name))?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    let detail = response
+        .text()
+        .ok()
+        .and_then(|fault| xml_text(&fault, "errorDescription"))
+        .unwrap_or_else(|| status.to_string());
+    Err(format!("{} rejected {action}: {detail}", renderer.name))
+}
+
+/// `hh:mm:ss`, the only time format every renderer accepts for `Seek`.
+fn format_upnp_time(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
+#[derive(Clone)]
+struct CurrentTrack {
+    path: PathBuf,
+    title: String,
+}
+
+/// The selected renderer (if any) and the track it should play. While a renderer is
+/// active, transport commands go to it instead of the local output.
+#[derive(Default)]
+pub struct DlnaOutput {
+    renderers: Mutex<Vec<DlnaRenderer>>,
+    active: Mutex<Option<DlnaRenderer>>,
+    track: Mutex<Option<CurrentTrack>>,
+    /// Whether the last transport command left the renderer playing.
+    playing: AtomicBool,
+}
+
+impl DlnaOutput {
+    /// Runs discovery and remembers the result for `select`.
+    pub fn refresh_renderers(&self) -> Result<Vec<DlnaRenderer>, String> {
+        let renderers = discover(DISCOVERY_WINDOW)?;
+        *self
+            .renderers
+            .lock()
+            .map_err(|_| "Renderer list lock poisoned".to_string())? = renderers.clone();
+        Ok(renderers)
+    }
+
+    pub fn active(&self) -> Option<DlnaRenderer> {
+        self.active.lock().ok()?.clone()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::SeqCst)
+    }
+
+    /// Makes the renderer with `id` the active output and hands it the current track,
+    /// starting playback there when `play` is set. `None` returns to the local output.
+    pub fn select(&self, id: Option<&str>, play: bool) -> Result<Option<DlnaRenderer>, String> {
+        let Some(id) = id else {
+            self.playing.store(false, Ordering::SeqCst);
+            if let Some(previous) = self
+                .active
+                .lock()
+                .map_err(|_| "Active renderer lock poisoned".to_string())?
+                .take()
+            {
+                // The renderer may already be off; local playback takes over regardless.
+                if let Err(err) = send_action(&previous, "Stop", &[]) {
+                    eprintln!("Failed to stop {}: {err}", previous.name);
+                }
+            }
+            return Ok(None);
+        };
+        let renderer = self
+            .renderers
+            .lock()
+            .map_err(|_| "Renderer list lock poisoned".to_string())?
+            .iter()
+            .find(|renderer| renderer.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown renderer: {id}; list renderers first"))?;
+        *self
+            .active
+            .lock()
+            .map_err(|_| "Active renderer lock poisoned".to_string())? = Some(renderer.clone());
+        if self.track.lock().ok().is_some_and(|track| track.is_some()) {
+            self.push_current(play)?;
+        }
+        Ok(Some(renderer))
+    }
+
+    /// Records the track the player moved to and, with a renderer active, sends it there.
+    pub fn set_track(&self, path: &Path, title: &str) -> Result<(), String> {
+        *self
+            .track
+            .lock()
+            .map_err(|_| "DLNA track lock poisoned".to_string())? = Some(CurrentTrack {
+            path: path.to_path_buf(),
+            title: title.to_string(),
+        });
+        if self.active().is_some() {
+            self.push_current(false)?;
+        }
+        Ok(())
+    }
+
+    pub fn play(&self) -> Result<(), String> {
+        self.on_active(|renderer| send_action(renderer, "Play", &[("Speed", "1")]))?;
+        self.playing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn pause(&self) -> Result<(), String> {
+        self.on_active(|renderer| send_action(renderer, "Pause", &[]))?;
+        self.playing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn seek(&self, seconds: f64) -> Result<(), String> {
+        self.on_active(|renderer| {
+            send_action(
+                renderer,
+                "Seek",
+                &[("Unit", "REL_TIME"), ("Target", &format_upnp_time(seconds))],
+            )
+        })
+    }
+
+    fn on_active(
+        &self,
+        action: impl FnOnce(&DlnaRenderer) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let renderer = self
+            .active()
+            .ok_or_else(|| "No DLNA renderer is selected".to_string())?;
+        action(&renderer)
+    }
+
+    fn push_current(&self, play: bool) -> Result<(), String> {
+        let renderer = self
+            .active()
+            .ok_or_else(|| "No DLNA renderer is selected".to_string())?;
+        let track = self
+            .track
+            .lock()
+            .map_err(|_| "DLNA track lock poisoned".to_string())?
+            .clone()
+            .ok_or_else(|| "No track to send to the renderer".to_string())?;
+
+        let control = Url::parse(&renderer.control_url)
+            .map_err(|e| format!("Invalid renderer control URL: {e}"))?;
+        let peer = control
+            .socket_addrs(|| Some(80))
+            .ok()
+            .and_then(|addrs| addrs.into_iter().next())
+            .ok_or_else(|| format!("Cannot resolve renderer {}", renderer.name))?;
+        let media = media_server::publish(&track.path, peer)?;
+        let (uri, content_type) = (media.url, media.content_type);
+        let didl = format!(
+            "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">\
+             <item id=\"0\" parentID=\"-1\" restricted=\"1\"><dc:title>{}</dc:title><upnp:class>object.item.audioItem.musicTrack</upnp:class>\
+             <res protocolInfo=\"http-get:*:{content_type}:*\">{}</res></item></DIDL-Lite>",
+            xml_escape(&track.title),
+            xml_escape(&uri),
+        );
+        send_action(
+            &renderer,
+            "SetAVTransportURI",
+            &[("CurrentURI", &uri), ("CurrentURIMetaData", &didl)],
+        )?;
+        if play {
+            send_action(&renderer, "Play", &[("Speed", "1")])?;
+        }
+        self.playing.store(play, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_upnp_time, parse_description, parse_ssdp_location};
+
+    #[test]
+    fn parses_ssdp_replies_and_renderer_descriptions() {
+        let reply = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLocation: http://192.168.1.20:49152/description.xml\r\nST: urn:schemas-upnp-org:device:MediaRenderer:1\r\n\r\n";
+        assert_eq!(
+            parse_ssdp_location(reply).as_deref(),
+            Some("http://192.168.1.20:49152/description.xml")
+        );
+        assert_eq!(parse_ssdp_location("M-SEARCH * HTTP/1.1\r\n\r\n"), None);
+
+        let description = r#"<?xml version="1.0"?>
+            <root xmlns="urn:schemas-upnp-org:device-1-0"><device>
+              <friendlyName>Living Room &amp; Kitchen</friendlyName>
+              <UDN>uuid:5f9ec1b3-ed59-4dc6</UDN>
+              <serviceList>
+                <service><serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+                  <controlURL>/rc/control</controlURL></service>
+                <service><serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+                  <controlURL>upnp/av/control</controlURL></service>
+              </serviceList>
+            </device></root>"#;
+        let renderer = parse_description(description, "http://192.168.1.20:49152/dev/desc.xml")
+            .expect("AVTransport renderer");
+        assert_eq!(renderer.name, "Living Room & Kitchen");
+        assert_eq!(renderer.id, "uuid:5f9ec1b3-ed59-4dc6");
+        assert_eq!(
+            renderer.control_url,
+            "http://192.168.1.20:49152/dev/upnp/av/control"
+        );
+        assert_eq!(
+            renderer.service_type,
+            "urn:schemas-upnp-org:service:AVTransport:1"
+        );
+
+        let no_transport = description.replace("AVTransport", "ConnectionManager");
+        assert_eq!(
+            parse_description(&no_transport, "http://192.168.1.20/"),
+            None
+        );
+    }
+
+    #[test]
+    fn formats_seek_targets_as_hours_minutes_seconds() {
+        assert_eq!(format_upnp_time(3725.9), "01:02:05");
+        assert_eq!(format_upnp_time(-4.0), "00:00:00");
+    }
+}
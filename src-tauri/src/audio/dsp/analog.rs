@@ -0,0 +1,257 @@
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Drive into the saturator at full intensity.
+const MAX_DRIVE: f32 = 4.0;
+/// The tape path always runs this much behind, so wow can swing both ways.
+const BASE_DELAY_MS: f32 = 2.0;
+/// Slow pitch drift of an off-center record or a stretched belt...
+const WOW_HZ: f32 = 0.55;
+const WOW_DEPTH_MS: f32 = 1.0;
+/// ...and the faster capstan flutter on top of it.
+const FLUTTER_HZ: f32 = 6.3;
+const FLUTTER_DEPTH_MS: f32 = 0.06;
+/// Pops per second at full crackle.
+const MAX_CRACKLE_RATE: f32 = 12.0;
+const CRACKLE_DECAY_MS: f32 = 0.4;
+const MAX_CRACKLE_LEVEL: f32 = 0.08;
+/// How long switching the node on or off crossfades.
+const TOGGLE_RAMP_MS: f32 = 30.0;
+
+/// Everything the user controls on the analog character node. Intensities run from
+/// 0.0 (none) to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalogSettings {
+    pub enabled: bool,
+    pub saturation: f32,
+    pub wow_flutter: f32,
+    pub crackle: f32,
+}
+
+impl Default for AnalogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            saturation: 0.5,
+            wow_flutter: 0.3,
+            crackle: 0.0,
+        }
+    }
+}
+
+/// Vinyl/tape coloration: soft saturation that rounds off peaks, wow and flutter from
+/// a modulated delay, and optional crackle. Toggling crossfades.
+pub struct AnalogNode {
+    enabled: AtomicBool,
+    saturation_bits: AtomicU32,
+    wow_flutter_bits: AtomicU32,
+    crackle_bits: AtomicU32,
+    sample_rate: f32,
+    delay: [Vec<f32>; 2],
+    write_pos: usize,
+    wow_phase: f32,
+    flutter_phase: f32,
+    crackle_env: f32,
+    crackle_level: [f32; 2],
+    crackle_decay: f32,
+    rng: u32,
+    /// 0 (bypassed) to 1 (fully applied).
+    mix: f32,
+    mix_step: f32,
+}
+
+impl AnalogNode {
+    pub fn new(sample_rate: f32) -> Self {
+        let defaults = AnalogSettings::default();
+        let mut node = Self {
+            enabled: AtomicBool::new(defaults.enabled),
+            saturation_bits: AtomicU32::new(defaults.saturation.to_bits()),
+            wow_flutter_bits: AtomicU32::new(defaults.wow_flutter.to_bits()),
+            crackle_bits: AtomicU32::new(defaults.crackle.to_bits()),
+            sample_rate: 0.0,
+            delay: [Vec::new(), Vec::new()],
+            write_pos: 0,
+            wow_phase: 0.0,
+            flutter_phase: 0.0,
+            crackle_env: 0.0,
+            crackle_level: [0.0; 2],
+            crackle_decay: 0.0,
+            rng: 0x9E37_79B9,
+            mix: 0.0,
+            mix_step: 0.0,
+        };
+        node.set_sample_rate(sample_rate);
+        node
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_saturation(&self, amount: f32) {
+        self.saturation_bits
+            .store(amount.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_wow_flutter(&self, amount: f32) {
+        self.wow_flutter_bits
+            .store(amount.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_crackle(&self, amount: f32) {
+        self.crackle_bits
+            .store(amount.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn settings(&self) -> AnalogSettings {
+        AnalogSettings {
+            enabled: self.is_enabled(),
+            saturation: f32::from_bits(self.saturation_bits.load(Ordering::Relaxed)),
+            wow_flutter: f32::from_bits(self.wow_flutter_bits.load(Ordering::Relaxed)),
+            crackle: f32::from_bits(self.crackle_bits.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() <= f32::EPSILON {
+            return;
+        }
+        self.sample_rate = sr;
+        let max_delay_ms = BASE_DELAY_MS + WOW_DEPTH_MS + FLUTTER_DEPTH_MS;
+        let len = (max_delay_ms * 0.001 * sr).ceil() as usize + 2;
+        self.delay = [vec![0.0; len], vec![0.0; len]];
+        self.write_pos = 0;
+        self.crackle_decay = (-1.0 / (CRACKLE_DECAY_MS * 0.001 * sr)).exp();
+        self.mix_step = 1.0 / (TOGGLE_RAMP_MS * 0.001 * sr);
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let target_mix = if self.enabled.load(Ordering::Relaxed) {
+            1.0
+        } else {
+            0.0
+        };
+        if self.mix == 0.0 && target_mix == 0.0 {
+            return (left, right);
+        }
+        self.mix = if target_mix > self.mix {
+            (self.mix + self.mix_step).min(target_mix)
+        } else {
+            (self.mix - self.mix_step).max(target_mix)
+        };
+        let settings = self.settings();
+
+        let (wet_left, wet_right) = self.wobble(left, right, settings.wow_flutter);
+        let crackle = self.crackle(settings.crackle);
+        let wet_left = saturate(wet_left, settings.saturation) + crackle * self.crackle_level[0];
+        let wet_right = saturate(wet_right, settings.saturation) + crackle * self.crackle_level[1];
+        (
+            left + (wet_left - left) * self.mix,
+            right + (wet_right - right) * self.mix,
+        )
+    }
+
+    /// Reads both channels back through the delay line at a slowly modulated position.
+    fn wobble(&mut self, left: f32, right: f32, amount: f32) -> (f32, f32) {
+        let len = self.delay[0].len();
+        self.delay[0][self.write_pos] = left;
+        self.delay[1][self.write_pos] = right;
+
+        let rate = self.sample_rate;
+        self.wow_phase = (self.wow_phase + TAU * WOW_HZ / rate) % TAU;
+        self.flutter_phase = (self.flutter_phase + TAU * FLUTTER_HZ / rate) % TAU;
+        let delay_ms = BASE_DELAY_MS
+            + amount
+                * (WOW_DEPTH_MS * self.wow_phase.sin()
+                    + FLUTTER_DEPTH_MS * self.flutter_phase.sin());
+        let delay = (delay_ms * 0.001 * rate).clamp(1.0, (len - 2) as f32);
+
+        let read = self.write_pos as f32 - delay + len as f32;
+        let index = read.floor() as usize % len;
+        let next = (index + 1) % len;
+        let frac = read.fract();
+        let tap = |line: &[f32]| line[index] + (line[next] - line[index]) * frac;
+        let out = (tap(&self.delay[0]), tap(&self.delay[1]));
+        self.write_pos = (self.write_pos + 1) % len;
+        out
+    }
+
+    /// Decaying noise bursts started at random, at a rate set by `amount`.
+    fn crackle(&mut self, amount: f32) -> f32 {
+        if amount > 0.0 && self.crackle_env < 1.0e-3 {
+            let chance = amount * MAX_CRACKLE_RATE / self.sample_rate;
+            if self.next_random() < chance {
+                self.crackle_env = 1.0;
+                // Mostly faint ticks with the occasional louder pop, a little off
+                // center.
+                let level = amount * MAX_CRACKLE_LEVEL * self.next_random().powi(3);
+                let pan = self.next_random();
+                self.crackle_level = [level * (1.0 - 0.5 * pan), level * (0.5 + 0.5 * pan)];
+            }
+        }
+        if self.crackle_env < 1.0e-3 {
+            return 0.0;
+        }
+        let sample = (2.0 * self.next_random() - 1.0) * self.crackle_env;
+        self.crackle_env *= self.crackle_decay;
+        sample
+    }
+
+    /// Uniform in 0.0..1.0 (xorshift32).
+    fn next_random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Soft clipper with unity gain for small signals, blended in by `amount`. At full
+/// intensity the output never exceeds `1 / MAX_DRIVE`, so loud passages are rounded
+/// off rather than clipped.
+fn saturate(sample: f32, amount: f32) -> f32 {
+    let clipped = (sample * MAX_DRIVE).tanh() / MAX_DRIVE;
+    sample + (clipped - sample) * amount
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnalogNode;
+
+    #[test]
+    fn analog_node_colors_only_when_enabled() {
+        let mut node = AnalogNode::new(48_000.0);
+        assert_eq!(node.process_stereo_frame(0.7, -0.3), (0.7, -0.3));
+
+        node.set_enabled(true);
+        node.set_wow_flutter(0.0);
+        node.set_saturation(1.0);
+        let mut peak = 0.0_f32;
+        for n in 0..48_000 {
+            let sample = 0.9 * (std::f32::consts::TAU * 100.0 * n as f32 / 48_000.0).sin();
+            let (left, _) = node.process_stereo_frame(sample, sample);
+            if n > 4_800 {
+                peak = peak.max(left.abs());
+            }
+        }
+        assert!(peak < 0.26, "peaks should be rounded off: {peak}");
+
+        // Silence stays silent without crackle, and picks up pops with it.
+        let mut node = AnalogNode::new(48_000.0);
+        node.set_enabled(true);
+        let silent = (0..48_000).all(|_| node.process_stereo_frame(0.0, 0.0) == (0.0, 0.0));
+        assert!(silent);
+        node.set_crackle(1.0);
+        let pops = (0..48_000)
+            .filter(|_| node.process_stereo_frame(0.0, 0.0).0.abs() > 1.0e-4)
+            .count();
+        assert!(pops > 0);
+    }
+}
@@ -1,54 +1,207 @@
-#[derive(Clone, Copy)]
-pub struct EqBandConfig {
-    pub frequency: f32,
-    pub gain_db: f32,
-    pub q_factor: f32,
+//! Parsing and search for the AutoEq results catalog (`results/INDEX.md`) and its
+//! `ParametricEQ.txt` profiles, in the EqualizerAPO format AutoEq publishes.
+
+use super::filters::{EqBandSettings, FilterType};
+
+/// Q used for shelves that are given without one.
+const DEFAULT_SHELF_Q: f32 = 0.707;
+
+/// One measured headphone in the catalog. The same model can appear once per
+/// measurement source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoEqEntry {
+    pub name: String,
+    /// Who measured it, e.g. `oratory1990` or `crinacle on GRAS 43AG-7`.
+    pub source: String,
+    /// Directory of the profile relative to the results root, URL-encoded as in the index.
+    pub path: String,
 }
 
-const TEN_BAND_FREQUENCIES: [f32; 10] = [
-    31.0, 62.0, 125.0, 250.0, 500.0, 1_000.0, 2_000.0, 4_000.0, 8_000.0, 16_000.0,
-];
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoEqProfile {
+    /// Gain AutoEq recommends ahead of the filters to avoid clipping.
+    pub preamp_db: f32,
+    pub bands: Vec<EqBandSettings>,
+}
 
-const FLAT_GAINS: [f32; 10] = [0.0; 10];
-// Baseline 10-band compensation derived from AutoEQ-style Harman-target correction profile.
-const SONY_WH_1000XM4_GAINS: [f32; 10] = [-2.4, -1.6, -0.8, 0.2, 1.1, 1.8, 2.2, 1.5, 0.4, -0.9];
+/// Reads the `- [Name](./path) by source` lines of the results index.
+pub fn parse_index(markdown: &str) -> Vec<AutoEqEntry> {
+    markdown
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("- [")?;
+            let (name, rest) = rest.split_once("](")?;
+            let (path, source) = rest.rsplit_once(") by ")?;
+            Some(AutoEqEntry {
+                name: name.trim().to_string(),
+                source: source.trim().to_string(),
+                path: path.trim().to_string(),
+            })
+        })
+        .filter(|entry| !entry.name.is_empty() && !entry.path.is_empty())
+        .collect()
+}
 
-fn build_profile(gains: [f32; 10]) -> Vec<EqBandConfig> {
-    TEN_BAND_FREQUENCIES
+/// Entries whose name contains every word of `query`, ignoring case and punctuation so
+/// `wh1000xm4` finds `Sony WH-1000XM4`. Exact names rank first, then names starting
+/// with the query, then shorter names; ties keep catalog order.
+pub fn search<'a>(entries: &'a [AutoEqEntry], query: &str, limit: usize) -> Vec<&'a AutoEqEntry> {
+    let words = query
+        .split_whitespace()
+        .map(compact)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let query = words.concat();
+    let mut matches = entries
         .iter()
-        .zip(gains.iter())
-        .map(|(frequency, gain_db)| EqBandConfig {
-            frequency: *frequency,
-            gain_db: *gain_db,
-            q_factor: 1.0,
+        .filter_map(|entry| {
+            let name = compact(&entry.name);
+            if !words.iter().all(|word| name.contains(word.as_str())) {
+                return None;
+            }
+            let rank = if name == query {
+                0
+            } else if name.starts_with(&query) {
+                1
+            } else {
+                2
+            };
+            Some((rank, name.len(), entry))
         })
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|(rank, length, _)| (*rank, *length));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, entry)| entry)
         .collect()
 }
 
-pub fn profile_for_model(model: &str) -> Option<Vec<EqBandConfig>> {
-    let normalized = model.trim().to_lowercase();
-    if normalized.contains("sony wh-1000xm4") || normalized.contains("wh1000xm4") {
-        return Some(build_profile(SONY_WH_1000XM4_GAINS));
+/// Parses a `ParametricEQ.txt` profile, or any filter list in the same format such as
+/// a REW "EQ filter export". Filters switched `OFF` or left unset (`None`) are skipped.
+pub fn parse_parametric_eq(text: &str) -> Result<AutoEqProfile, String> {
+    let mut preamp_db = 0.0;
+    let mut bands = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("Preamp:") {
+            preamp_db = parse_number(value.trim().trim_end_matches("dB"), line)?;
+            continue;
+        }
+        let Some((label, filter)) = line.split_once(':') else {
+            continue;
+        };
+        if !label.starts_with("Filter") {
+            continue;
+        }
+        let tokens = filter.split_whitespace().collect::<Vec<_>>();
+        if tokens.first() != Some(&"ON") {
+            continue;
+        }
+        let filter_type = match tokens.get(1) {
+            Some(&"None") => continue,
+            Some(&("PK" | "PEQ")) => FilterType::Peaking,
+            Some(&("LS" | "LSC")) => FilterType::LowShelf,
+            Some(&("HS" | "HSC")) => FilterType::HighShelf,
+            Some(&("LP" | "LPQ")) => FilterType::LowPass,
+            Some(&("HP" | "HPQ")) => FilterType::HighPass,
+            _ => return Err(format!("Unsupported AutoEQ filter: {line}")),
+        };
+        let (mut frequency, mut gain_db, mut q_factor) = (None, 0.0, None);
+        // Values follow their key; units after them are ignored.
+        for pair in tokens[2..].windows(2) {
+            match pair[0] {
+                "Fc" => frequency = Some(parse_number(pair[1], line)?),
+                "Gain" => gain_db = parse_number(pair[1], line)?,
+                "Q" => q_factor = Some(parse_number(pair[1], line)?),
+                _ => {}
+            }
+        }
+        let q_factor = match (q_factor, filter_type) {
+            (Some(q_factor), _) => q_factor,
+            (None, FilterType::LowShelf | FilterType::HighShelf) => DEFAULT_SHELF_Q,
+            (None, _) => return Err(format!("AutoEQ filter has no Q: {line}")),
+        };
+        bands.push(EqBandSettings {
+            frequency: frequency.ok_or_else(|| format!("AutoEQ filter has no Fc: {line}"))?,
+            gain_db,
+            q_factor,
+            filter_type,
+        });
     }
-    if normalized == "flat" || normalized == "harman target" {
-        return Some(build_profile(FLAT_GAINS));
+    if bands.is_empty() {
+        return Err("AutoEQ profile has no active filters".to_string());
     }
-    None
+    Ok(AutoEqProfile { preamp_db, bands })
+}
+
+fn parse_number(value: &str, line: &str) -> Result<f32, String> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("Invalid number in AutoEQ profile line: {line}"))
+}
+
+fn compact(text: &str) -> String {
+    text.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{profile_for_model, TEN_BAND_FREQUENCIES};
+    use super::{parse_index, parse_parametric_eq, search};
+    use crate::audio::dsp::filters::FilterType;
+
+    const INDEX: &str = "# Headphones\n\n\
+        - [Sony WH-1000XM4](./oratory1990/over-ear/Sony%20WH-1000XM4) by oratory1990\n\
+        - [Sony WH-1000XM4 (ANC off)](./crinacle/GRAS%2043AG-7%20over-ear/Sony%20WH-1000XM4%20(ANC%20off)) by crinacle on GRAS 43AG-7\n\
+        - [Sennheiser HD 650](./oratory1990/over-ear/Sennheiser%20HD%20650) by oratory1990\n";
 
     #[test]
-    fn known_profile_returns_ten_bands() {
-        let profile = profile_for_model("Sony WH-1000XM4").expect("profile should exist");
-        assert_eq!(profile.len(), 10);
-        assert_eq!(profile[0].frequency, TEN_BAND_FREQUENCIES[0]);
+    fn index_search_ignores_punctuation_and_ranks_exact_names_first() {
+        let entries = parse_index(INDEX);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].name, "Sony WH-1000XM4 (ANC off)");
+        assert_eq!(entries[1].source, "crinacle on GRAS 43AG-7");
+        assert_eq!(
+            entries[1].path,
+            "./crinacle/GRAS%2043AG-7%20over-ear/Sony%20WH-1000XM4%20(ANC%20off)"
+        );
+
+        let found = search(&entries, "wh1000xm4", 10);
+        assert_eq!(found.len(), 2);
+        let found = search(&entries, "sony wh-1000xm4", 10);
+        assert_eq!(found[0].source, "oratory1990");
+        assert_eq!(search(&entries, "hd650", 10)[0].name, "Sennheiser HD 650");
+        assert!(search(&entries, "  ", 10).is_empty());
+        assert!(search(&entries, "Unknown Model", 10).is_empty());
     }
 
     #[test]
-    fn unknown_profile_returns_none() {
-        assert!(profile_for_model("Unknown Model").is_none());
+    fn parametric_profiles_keep_filter_types_and_skip_disabled_filters() {
+        let profile = parse_parametric_eq(
+            "Preamp: -6.4 dB\n\
+             Filter 1: ON LSC Fc 105 Hz Gain 6.1 dB Q 0.70\n\
+             Filter 2: ON PK Fc 2727 Hz Gain -4.6 dB Q 2.58\n\
+             Filter 3: OFF PK Fc 5000 Hz Gain 3.0 dB Q 1.00\n\
+             Filter 4: ON HS Fc 10000 Hz Gain 2.0 dB\n",
+        )
+        .expect("profile should parse");
+        assert_eq!(profile.preamp_db, -6.4);
+        assert_eq!(profile.bands.len(), 3);
+        assert_eq!(profile.bands[0].filter_type, FilterType::LowShelf);
+        assert_eq!(profile.bands[1].frequency, 2_727.0);
+        assert_eq!(profile.bands[1].gain_db, -4.6);
+        assert_eq!(profile.bands[1].q_factor, 2.58);
+        assert_eq!(profile.bands[2].filter_type, FilterType::HighShelf);
+
+        assert!(parse_parametric_eq("Preamp: -1 dB\n").is_err());
+        assert!(parse_parametric_eq("Filter 1: ON PK Fc abc Hz Gain 1 dB Q 1").is_err());
     }
 }
@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use super::upmix::{layout, Crossover, Speaker};
+
+/// Crossover frequencies offered for small satellite speakers.
+const MIN_CROSSOVER_HZ: f32 = 60.0;
+const MAX_CROSSOVER_HZ: f32 = 120.0;
+
+/// How bass is split between the main speakers and the subwoofer on outputs with an
+/// LFE channel.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BassManagementSettings {
+    pub enabled: bool,
+    /// Where the mains are high-passed and the sub takes over, 60–120 Hz.
+    pub crossover_hz: f32,
+    /// Gain of the subwoofer channel, -20 to +10 dB.
+    pub sub_level_db: f32,
+}
+
+impl Default for BassManagementSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            crossover_hz: 80.0,
+            sub_level_db: 0.0,
+        }
+    }
+}
+
+impl BassManagementSettings {
+    /// Clamps every field into its supported range.
+    pub fn clamped(self) -> Self {
+        Self {
+            enabled: self.enabled,
+            crossover_hz: self.crossover_hz.clamp(MIN_CROSSOVER_HZ, MAX_CROSSOVER_HZ),
+            sub_level_db: self.sub_level_db.clamp(-20.0, 10.0),
+        }
+    }
+}
+
+/// Bass management for multichannel outputs: every main channel is split by a
+/// 4th-order Linkwitz-Riley crossover, its highs stay on the speaker and its lows are
+/// summed with the LFE into the subwoofer channel. Runs on whole output frames, after
+/// the DSP chain; outputs without an LFE channel pass through.
+pub struct BassManager {
+    enabled: AtomicBool,
+    crossover_bits: AtomicU32,
+    sub_level_bits: AtomicU32,
+    sample_rate: f32,
+    /// Speaker of each channel of the last frame processed.
+    speakers: Vec<Speaker>,
+    /// Low and high half of each channel's crossover.
+    filters: Vec<(Crossover, Crossover)>,
+    /// Crossover and sub level the filters and `sub_gain` were built for.
+    designed: Option<(u32, u32)>,
+    sub_gain: f32,
+}
+
+impl BassManager {
+    pub fn new(sample_rate: f32) -> Self {
+        let defaults = BassManagementSettings::default();
+        Self {
+            enabled: AtomicBool::new(defaults.enabled),
+            crossover_bits: AtomicU32::new(defaults.crossover_hz.to_bits()),
+            sub_level_bits: AtomicU32::new(defaults.sub_level_db.to_bits()),
+            sample_rate: sample_rate.max(8_000.0),
+            speakers: Vec::new(),
+            filters: Vec::new(),
+            designed: None,
+            sub_gain: 1.0,
+        }
+    }
+
+    pub fn set_settings(&self, settings: BassManagementSettings) {
+        let settings = settings.clamped();
+        self.crossover_bits
+            .store(settings.crossover_hz.to_bits(), Ordering::Relaxed);
+        self.sub_level_bits
+            .store(settings.sub_level_db.to_bits(), Ordering::Relaxed);
+        self.enabled.store(settings.enabled, Ordering::SeqCst);
+    }
+
+    pub fn settings(&self) -> BassManagementSettings {
+        BassManagementSettings {
+            enabled: self.is_enabled(),
+            crossover_hz: f32::from_bits(self.crossover_bits.load(Ordering::Relaxed)),
+            sub_level_db: f32::from_bits(self.sub_level_bits.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() > f32::EPSILON {
+            self.sample_rate = sr;
+            self.designed = None;
+        }
+    }
+
+    /// Redistributes the bass of one interleaved output frame. The filters are rebuilt
+    /// when the channel count, crossover or sub level changes.
+    pub fn process_frame(&mut self, frame: &mut [f32]) {
+        if !self.is_enabled() || frame.len() <= 2 {
+            return;
+        }
+        if frame.len() != self.speakers.len() {
+            self.speakers = layout(frame.len());
+            self.designed = None;
+        }
+        let Some(lfe) = self.speakers.iter().position(|s| *s == Speaker::Lfe) else {
+            return;
+        };
+        let wanted = (
+            self.crossover_bits.load(Ordering::Relaxed),
+            self.sub_level_bits.load(Ordering::Relaxed),
+        );
+        if self.designed != Some(wanted) {
+            self.rebuild(wanted);
+        }
+
+        let mut bass = frame[lfe];
+        for ((sample, speaker), (low, high)) in
+            frame.iter_mut().zip(&self.speakers).zip(&mut self.filters)
+        {
+            if matches!(speaker, Speaker::Lfe | Speaker::Unused) {
+                continue;
+            }
+            bass += low.process(*sample);
+            *sample = high.process(*sample);
+        }
+        frame[lfe] = bass * self.sub_gain;
+    }
+
+    fn rebuild(&mut self, (crossover_bits, sub_level_bits): (u32, u32)) {
+        let crossover_hz = f32::from_bits(crossover_bits);
+        let sr = self.sample_rate;
+        self.filters = self
+            .speakers
+            .iter()
+            .map(|_| {
+                (
+                    Crossover::low_pass(sr, crossover_hz),
+                    Crossover::high_pass(sr, crossover_hz),
+                )
+            })
+            .collect();
+        self.sub_gain = 10.0_f32.powf(f32::from_bits(sub_level_bits) / 20.0);
+        self.designed = Some((crossover_bits, sub_level_bits));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BassManagementSettings, BassManager};
+
+    #[test]
+    fn bass_moves_from_the_mains_to_the_sub() {
+        let mut manager = BassManager::new(48_000.0);
+        let settings = BassManagementSettings {
+            enabled: true,
+            crossover_hz: 200.0,
+            sub_level_db: -6.0,
+        };
+        manager.set_settings(settings);
+        assert_eq!(manager.settings().crossover_hz, 120.0);
+
+        // 5.1 with a 30 Hz tone and a 2 kHz tone on the front left only.
+        let peaks = |manager: &mut BassManager, frequency: f32| {
+            let mut peaks = [0.0_f32; 6];
+            for n in 0..48_000 {
+                let phase = 2.0 * std::f32::consts::PI * frequency * n as f32 / 48_000.0;
+                let mut frame = [0.5 * phase.sin(), 0.0, 0.0, 0.0, 0.0, 0.0];
+                manager.process_frame(&mut frame);
+                if n > 24_000 {
+                    for (peak, sample) in peaks.iter_mut().zip(frame) {
+                        *peak = peak.max(sample.abs());
+                    }
+                }
+            }
+            peaks
+        };
+        let low = peaks(&mut manager, 30.0);
+        assert!(low[0] < 0.05, "mains should be high-passed: {}", low[0]);
+        assert!((low[3] - 0.25).abs() < 0.02, "sub at -6 dB: {}", low[3]);
+        let high = peaks(&mut manager, 2_000.0);
+        assert!(high[0] > 0.49 && high[3] < 0.01, "{high:?}");
+
+        // Stereo, and 5.1 with bass management off, pass through untouched.
+        let mut stereo = [0.3, -0.2];
+        manager.process_frame(&mut stereo);
+        assert_eq!(stereo, [0.3, -0.2]);
+        manager.set_settings(BassManagementSettings::default());
+        let mut frame = [0.3, -0.2, 0.1, 0.4, 0.0, 0.0];
+        manager.process_frame(&mut frame);
+        assert_eq!(frame, [0.3, -0.2, 0.1, 0.4, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+/// Analysis window used for each onset frame. 1024 samples keep kick/snare
+/// transients sharp while still resolving the low-frequency content.
+pub const ONSET_FRAME_SIZE: usize = 1024;
+/// Seconds of onset history kept for the adaptive threshold and tempo estimate.
+const HISTORY_SECONDS: f32 = 8.0;
+/// Seconds of history used for the local (adaptive) threshold.
+const THRESHOLD_WINDOW_SECONDS: f32 = 1.0;
+/// Multiplier applied to the local standard deviation when thresholding flux.
+const THRESHOLD_SENSITIVITY: f32 = 1.5;
+/// Minimum spacing between two beats (~300 BPM).
+const MIN_BEAT_INTERVAL_SECONDS: f32 = 0.2;
+const MIN_TEMPO_BPM: f32 = 60.0;
+const MAX_TEMPO_BPM: f32 = 200.0;
+
+/// A detected beat/onset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeatEvent {
+    /// 0.0..1.0 — how far the onset strength exceeded the adaptive threshold.
+    pub confidence: f32,
+    /// Current tempo estimate, available once enough history is collected.
+    pub tempo_bpm: Option<f32>,
+}
+
+/// Spectral-flux onset detector with autocorrelation tempo estimation.
+///
+/// The detector is fed one mono window per frame (e.g. the newest samples of the
+/// visualizer window every poll, or consecutive hops of a decoded file) and
+/// compares its magnitude spectrum with the previous frame. Positive spectral
+/// differences (flux) above a moving `mean + k·σ` threshold are reported as beats.
+pub struct BeatDetector {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    frames_per_second: f32,
+    previous_magnitudes: Vec<f32>,
+    flux_history: VecDeque<f32>,
+    history_len: usize,
+    threshold_len: usize,
+    min_beat_frames: usize,
+    frames_since_beat: usize,
+}
+
+impl BeatDetector {
+    /// `frames_per_second` is the rate at which [`process_window`](Self::process_window) is called.
+    pub fn new(frames_per_second: f32) -> Self {
+        let fps = frames_per_second.max(1.0);
+        let mut planner = FftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(ONSET_FRAME_SIZE);
+        let window = (0..ONSET_FRAME_SIZE)
+            .map(|i| {
+                0.5 * (1.0
+                    - (2.0 * std::f32::consts::PI * i as f32 / (ONSET_FRAME_SIZE - 1) as f32).cos())
+            })
+            .collect();
+        let min_beat_frames = (MIN_BEAT_INTERVAL_SECONDS * fps).ceil() as usize;
+        Self {
+            fft,
+            window,
+            frames_per_second: fps,
+            previous_magnitudes: Vec::new(),
+            flux_history: VecDeque::new(),
+            history_len: (HISTORY_SECONDS * fps).ceil() as usize,
+            threshold_len: (THRESHOLD_WINDOW_SECONDS * fps).ceil().max(2.0) as usize,
+            min_beat_frames,
+            frames_since_beat: min_beat_frames,
+        }
+    }
+
+    /// Clears all history, e.g. after a seek or track change.
+    pub fn reset(&mut self) {
+        self.previous_magnitudes.clear();
+        self.flux_history.clear();
+        self.frames_since_beat = self.min_beat_frames;
+    }
+
+    /// Analyses the newest `ONSET_FRAME_SIZE` samples of `mono` and returns a beat
+    /// event when an onset is detected in this frame.
+    pub fn process_window(&mut self, mono: &[f32]) -> Option<BeatEvent> {
+        let flux = self.spectral_flux(mono)?;
+        let (mean, std_dev) = self.local_statistics();
+        self.push_flux(flux);
+        self.frames_since_beat = self.frames_since_beat.saturating_add(1);
+
+        if self.flux_history.len() < self.threshold_len {
+            return None;
+        }
+        let threshold = mean + THRESHOLD_SENSITIVITY * std_dev;
+        if flux <= threshold
+            || flux <= f32::EPSILON
+            || self.frames_since_beat < self.min_beat_frames
+        {
+            return None;
+        }
+
+        self.frames_since_beat = 0;
+        let confidence = if threshold > f32::EPSILON {
+            ((flux - threshold) / threshold).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        Some(BeatEvent {
+            confidence,
+            tempo_bpm: self.estimated_tempo(),
+        })
+    }
+
+    /// Estimates the tempo from the autocorrelation of the onset-strength history.
+    /// Returns `None` until at least a few seconds of history are available.
+    pub fn estimated_tempo(&self) -> Option<f32> {
+        let min_lag = (60.0 * self.frames_per_second / MAX_TEMPO_BPM)
+            .floor()
+            .max(1.0) as usize;
+        let max_lag = (60.0 * self.frames_per_second / MIN_TEMPO_BPM).ceil() as usize;
+        if self.flux_history.len() < max_lag * 2 {
+            return None;
+        }
+
+        let mean = self.flux_history.iter().sum::<f32>() / self.flux_history.len() as f32;
+        let centered = self
+            .flux_history
+            .iter()
+            .map(|value| value - mean)
+            .collect::<Vec<_>>();
+        let energy = centered.iter().map(|v| v * v).sum::<f32>();
+        if energy <= f32::EPSILON {
+            return None;
+        }
+
+        // The biased estimate (normalised by the full length) slightly favours shorter
+        // lags, so a perfectly periodic pulse resolves to its tempo rather than half of it.
+        let mut best_lag = 0;
+        let mut best_score = 0.0_f32;
+        for lag in min_lag..=max_lag {
+            let score = centered
+                .iter()
+                .zip(centered.iter().skip(lag))
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / centered.len() as f32;
+            if score > best_score {
+                best_score = score;
+                best_lag = lag;
+            }
+        }
+        if best_lag == 0 {
+            return None;
+        }
+        Some(60.0 * self.frames_per_second / best_lag as f32)
+    }
+
+    fn spectral_flux(&mut self, mono: &[f32]) -> Option<f32> {
+        if mono.len() < ONSET_FRAME_SIZE {
+            return None;
+        }
+        let start = mono.len() - ONSET_FRAME_SIZE;
+        let mut buffer = mono[start..]
+            .iter()
+            .zip(self.window.iter())
+            .map(|(sample, w)| Complex::new(sample * w, 0.0))
+            .collect::<Vec<_>>();
+        self.fft.process(&mut buffer);
+
+        let magnitudes = buffer[..ONSET_FRAME_SIZE / 2]
+            .iter()
+            .map(|c| (1.0 + c.norm()).ln())
+            .collect::<Vec<_>>();
+        let flux = if self.previous_magnitudes.len() == magnitudes.len() {
+            magnitudes
+                .iter()
+                .zip(self.previous_magnitudes.iter())
+                .map(|(current, previous)| (current - previous).max(0.0))
+                .sum::<f32>()
+                / magnitudes.len() as f32
+        } else {
+            0.0
+        };
+        self.previous_magnitudes = magnitudes;
+        Some(flux)
+    }
+
+    fn local_statistics(&self) -> (f32, f32) {
+        let recent = self
+            .flux_history
+            .iter()
+            .rev()
+            .take(self.threshold_len)
+            .copied()
+            .collect::<Vec<_>>();
+        if recent.is_empty() {
+            return (0.0, 0.0);
+        }
+        let mean = recent.iter().sum::<f32>() / recent.len() as f32;
+        let variance =
+            recent.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / recent.len() as f32;
+        (mean, variance.sqrt())
+    }
+
+    fn push_flux(&mut self, flux: f32) {
+        self.flux_history.push_back(flux);
+        while self.flux_history.len() > self.history_len {
+            self.flux_history.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: usize = 44_100;
+    const HOP: usize = 441; // 100 frames per second
+
+    /// Synthesises a click track: short decaying noise bursts every `period` samples.
+    fn click_track(bpm: f32, seconds: f32) -> Vec<f32> {
+        let total = (seconds * SAMPLE_RATE as f32) as usize;
+        let period = (60.0 / bpm * SAMPLE_RATE as f32) as usize;
+        let mut state = 12_345_u32;
+        (0..total)
+            .map(|i| {
+                let offset = i % period;
+                if offset < 2_000 {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    let noise = (state >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0;
+                    noise * (-(offset as f32) / 300.0).exp()
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn run_detector(samples: &[f32]) -> (BeatDetector, Vec<BeatEvent>) {
+        let mut detector = BeatDetector::new((SAMPLE_RATE / HOP) as f32);
+        let mut events = Vec::new();
+        let mut end = ONSET_FRAME_SIZE;
+        while end <= samples.len() {
+            if let Some(event) = detector.process_window(&samples[..end]) {
+                events.push(event);
+            }
+            end += HOP;
+        }
+        (detector, events)
+    }
+
+    #[test]
+    fn silence_produces_no_beats() {
+        let (_, events) = run_detector(&vec![0.0; SAMPLE_RATE * 4]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn short_window_is_ignored() {
+        let mut detector = BeatDetector::new(50.0);
+        assert!(detector.process_window(&[0.5; 16]).is_none());
+    }
+
+    #[test]
+    fn click_track_yields_beats_and_tempo() {
+        let samples = click_track(120.0, 12.0);
+        let (detector, events) = run_detector(&samples);
+        // 12 s at 120 BPM = 24 clicks; the first second only warms up the threshold.
+        assert!(
+            events.len() >= 15,
+            "expected regular beats, got {}",
+            events.len()
+        );
+        assert!(events.iter().all(|e| (0.0..=1.0).contains(&e.confidence)));
+
+        let tempo = detector
+            .estimated_tempo()
+            .expect("tempo should be estimated");
+        assert!(
+            (tempo - 120.0).abs() < 4.0,
+            "expected ~120 BPM, got {tempo}"
+        );
+    }
+
+    #[test]
+    fn reset_clears_history() {
+        let samples = click_track(100.0, 6.0);
+        let (mut detector, _) = run_detector(&samples);
+        detector.reset();
+        assert!(detector.estimated_tempo().is_none());
+    }
+}
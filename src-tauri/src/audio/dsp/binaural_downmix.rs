@@ -0,0 +1,248 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::sync::Arc;
+
+use super::filters::BiquadFilter;
+use super::sofa::{HrirSet, MAX_HRIR_LENGTH};
+use super::spatial::{convolve, SPEED_OF_SOUND};
+
+/// Approximate head radius for the Woodworth ITD, in metres.
+const HEAD_RADIUS: f32 = 0.0875;
+/// Center and surround channels are mixed in 3 dB down, as in the ITU-R BS.775 stereo
+/// downmix.
+const CENTER_GAIN: f32 = FRAC_1_SQRT_2;
+const SURROUND_GAIN: f32 = FRAC_1_SQRT_2;
+/// The LFE carries +10 dB of in-band gain on playback; bass is not localized, so it
+/// goes to both ears unprocessed.
+const LFE_GAIN: f32 = 0.5;
+
+/// Azimuth of each channel of a multichannel source in degrees (SOFA convention: 0°
+/// ahead, positive to the left), in WAVE order; `None` is the LFE.
+fn channel_azimuths(channels: usize) -> Option<&'static [Option<f32>]> {
+    let azimuths: &[Option<f32>] = match channels {
+        3 => &[Some(30.0), Some(-30.0), Some(0.0)],
+        4 => &[Some(30.0), Some(-30.0), Some(110.0), Some(-110.0)],
+        5 => &[
+            Some(30.0),
+            Some(-30.0),
+            Some(0.0),
+            Some(110.0),
+            Some(-110.0),
+        ],
+        6 => &[
+            Some(30.0),
+            Some(-30.0),
+            Some(0.0),
+            None,
+            Some(110.0),
+            Some(-110.0),
+        ],
+        // 6.1: back center, then the side pair.
+        7 => &[
+            Some(30.0),
+            Some(-30.0),
+            Some(0.0),
+            None,
+            Some(180.0),
+            Some(90.0),
+            Some(-90.0),
+        ],
+        8 => &[
+            Some(30.0),
+            Some(-30.0),
+            Some(0.0),
+            None,
+            Some(150.0),
+            Some(-150.0),
+            Some(90.0),
+            Some(-90.0),
+        ],
+        _ => return None,
+    };
+    Some(azimuths)
+}
+
+/// One source channel played from a virtual speaker around the listener's head.
+struct VirtualSpeaker {
+    gain: f32,
+    /// Measured responses towards the speaker, when an HRTF set is loaded.
+    hrir: Option<(Vec<f32>, Vec<f32>)>,
+    /// Otherwise the ear delays and levels of the spherical-head model, with the far
+    /// ear low-passed by the head's shadow.
+    delay: [usize; 2],
+    level: [f32; 2],
+    shadow: [BiquadFilter; 2],
+}
+
+impl VirtualSpeaker {
+    fn new(azimuth: f32, gain: f32, sample_rate: f32, hrtf: Option<&HrirSet>) -> Self {
+        // Radians off the median plane, folded to the front half like the spatial room.
+        let off_center = azimuth.to_radians().sin().abs().asin();
+        let itd = (HEAD_RADIUS / SPEED_OF_SOUND) * (off_center.sin() + off_center);
+        let far_delay = ((itd * sample_rate).round() as usize).min(MAX_HRIR_LENGTH - 1);
+        let far_level = 10.0_f32.powf(-6.0 * off_center.sin() / 20.0);
+        let shadow_cutoff = (20_000.0 - 12_000.0 * off_center.sin()).clamp(2_000.0, 20_000.0);
+        let mut near = BiquadFilter::new();
+        near.set_low_pass(sample_rate, 20_000.0, FRAC_1_SQRT_2);
+        let mut far = BiquadFilter::new();
+        far.set_low_pass(sample_rate, shadow_cutoff, FRAC_1_SQRT_2);
+
+        // Speakers on the left (positive azimuth) reach the left ear first.
+        let left_near = azimuth >= 0.0;
+        let (delay, level, shadow) = if left_near {
+            ([0, far_delay], [1.0, far_level], [near, far])
+        } else {
+            ([far_delay, 0], [far_level, 1.0], [far, near])
+        };
+        Self {
+            gain,
+            hrir: hrtf.map(|set| set.interpolate(azimuth, 0.0, sample_rate)),
+            delay,
+            level,
+            shadow,
+        }
+    }
+}
+
+/// Folds a multichannel source to headphone stereo by playing each channel through a
+/// virtual speaker at its standard position: convolved with the loaded HRTF, or
+/// rendered with the spatial room's ITD/ILD head model when there is none. Filter and
+/// delay state carries over between calls, so a stream can be fed in packets.
+pub struct BinauralDownmixer {
+    sample_rate: f32,
+    hrtf: Option<Arc<HrirSet>>,
+    out_channels: usize,
+    /// Layout the speakers were built for; they are rebuilt when it changes.
+    in_channels: usize,
+    speakers: Vec<Option<VirtualSpeaker>>,
+    /// Each channel's input history, stored twice over so the newest
+    /// `MAX_HRIR_LENGTH` samples are always contiguous.
+    history: Vec<Vec<f32>>,
+    position: usize,
+}
+
+impl BinauralDownmixer {
+    pub fn new(sample_rate: f32, out_channels: usize, hrtf: Option<Arc<HrirSet>>) -> Self {
+        Self {
+            sample_rate: sample_rate.max(8_000.0),
+            hrtf,
+            out_channels,
+            in_channels: 0,
+            speakers: Vec::new(),
+            history: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Downmixes interleaved `input` to stereo, or returns `None` when this downmixer
+    /// does not apply: the output is not stereo, or the source is not a 3 to 8 channel
+    /// layout.
+    pub fn downmix(&mut self, input: &[f32], in_channels: usize) -> Option<Vec<f32>> {
+        if self.out_channels != 2 {
+            return None;
+        }
+        let azimuths = channel_azimuths(in_channels)?;
+        if in_channels != self.in_channels {
+            self.configure(azimuths);
+        }
+
+        let mut out = Vec::with_capacity(input.len() / in_channels * 2);
+        for frame in input.chunks_exact(in_channels) {
+            self.position = (self.position + 1) % MAX_HRIR_LENGTH;
+            let newest = self.position + MAX_HRIR_LENGTH;
+            let mut ears = [0.0_f32; 2];
+            for ((speaker, history), sample) in
+                self.speakers.iter_mut().zip(&mut self.history).zip(frame)
+            {
+                let Some(speaker) = speaker else {
+                    ears.iter_mut().for_each(|ear| *ear += LFE_GAIN * sample);
+                    continue;
+                };
+                let sample = sample * speaker.gain;
+                history[self.position] = sample;
+                history[newest] = sample;
+                match &speaker.hrir {
+                    Some((left, right)) => {
+                        ears[0] += convolve(left, history, newest);
+                        ears[1] += convolve(right, history, newest);
+                    }
+                    None => {
+                        for (ear, out) in ears.iter_mut().enumerate() {
+                            let delayed = history[newest - speaker.delay[ear]];
+                            *out +=
+                                speaker.shadow[ear].process_sample(delayed) * speaker.level[ear];
+                        }
+                    }
+                }
+            }
+            out.extend_from_slice(&ears);
+        }
+        Some(out)
+    }
+
+    fn configure(&mut self, azimuths: &[Option<f32>]) {
+        let hrtf = self.hrtf.as_deref();
+        self.speakers = azimuths
+            .iter()
+            .enumerate()
+            .map(|(channel, azimuth)| {
+                azimuth.map(|azimuth| {
+                    let gain = match channel {
+                        0 | 1 => 1.0,
+                        _ if azimuth == 0.0 => CENTER_GAIN,
+                        _ => SURROUND_GAIN,
+                    };
+                    VirtualSpeaker::new(azimuth, gain, self.sample_rate, hrtf)
+                })
+            })
+            .collect();
+        self.history = vec![vec![0.0; MAX_HRIR_LENGTH * 2]; azimuths.len()];
+        self.in_channels = azimuths.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BinauralDownmixer;
+
+    fn energy(samples: &[f32], ear: usize) -> f32 {
+        samples.iter().skip(ear).step_by(2).map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn surround_channels_reach_both_ears_from_their_side() {
+        // A burst on the left surround of a 5.1 source.
+        let frames = 2_048;
+        let mut input = vec![0.0_f32; frames * 6];
+        for frame in 0..frames {
+            input[frame * 6 + 4] = (frame as f32 * 0.05).sin() * 0.5;
+        }
+        let mut downmixer = BinauralDownmixer::new(48_000.0, 2, None);
+        let out = downmixer.downmix(&input, 6).expect("5.1 to stereo");
+        assert_eq!(out.len(), frames * 2);
+        let (left, right) = (energy(&out, 0), energy(&out, 1));
+        assert!(left > right && right > 0.1 * left, "{left} vs {right}");
+
+        // The far ear hears it later, not just quieter.
+        let onset = |ear: usize| {
+            out.iter()
+                .skip(ear)
+                .step_by(2)
+                .position(|sample| sample.abs() > 1e-6)
+        };
+        assert!(onset(0) < onset(1));
+
+        // The LFE goes to both ears alike.
+        let mut lfe = vec![0.0_f32; 6];
+        lfe[3] = 0.4;
+        let out = BinauralDownmixer::new(48_000.0, 2, None)
+            .downmix(&lfe, 6)
+            .expect("5.1 to stereo");
+        assert_eq!(out, [0.2, 0.2]);
+
+        // Stereo sources and multichannel outputs are left to the channel copy.
+        assert!(downmixer.downmix(&[0.1, 0.2], 2).is_none());
+        assert!(BinauralDownmixer::new(48_000.0, 6, None)
+            .downmix(&input, 6)
+            .is_none());
+    }
+}
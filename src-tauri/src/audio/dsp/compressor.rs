@@ -0,0 +1,353 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use super::filters::BiquadFilter;
+
+pub const COMPRESSOR_BANDS: usize = 4;
+const DEFAULT_CROSSOVERS: [f32; COMPRESSOR_BANDS - 1] = [120.0, 1_000.0, 6_000.0];
+const MIN_CROSSOVER_HZ: f32 = 20.0;
+const MAX_CROSSOVER_HZ: f32 = 20_000.0;
+const KNEE_DB: f32 = 6.0;
+const ATTACK_MS: f32 = 10.0;
+const RELEASE_MS: f32 = 150.0;
+
+/// Threshold and ratio of one compressor band.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompressorBandSettings {
+    pub threshold_db: f32,
+    pub ratio: f32,
+}
+
+impl Default for CompressorBandSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: 0.0,
+            ratio: 1.0,
+        }
+    }
+}
+
+/// Everything the user controls on the compressor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompressorSettings {
+    pub enabled: bool,
+    pub crossovers: [f32; COMPRESSOR_BANDS - 1],
+    pub bands: Vec<CompressorBandSettings>,
+}
+
+struct BandControl {
+    threshold_bits: AtomicU32,
+    ratio_bits: AtomicU32,
+    /// Current reduction in dB, published by the audio thread for metering.
+    reduction_bits: AtomicU32,
+}
+
+impl BandControl {
+    fn new() -> Self {
+        let defaults = CompressorBandSettings::default();
+        Self {
+            threshold_bits: AtomicU32::new(defaults.threshold_db.to_bits()),
+            ratio_bits: AtomicU32::new(defaults.ratio.to_bits()),
+            reduction_bits: AtomicU32::new(0.0_f32.to_bits()),
+        }
+    }
+
+    fn settings(&self) -> CompressorBandSettings {
+        CompressorBandSettings {
+            threshold_db: f32::from_bits(self.threshold_bits.load(Ordering::Relaxed)),
+            ratio: f32::from_bits(self.ratio_bits.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// 4th-order Linkwitz-Riley filter: two cascaded Butterworth biquads, so the low and
+/// high outputs of one crossover sum to an all-pass.
+#[derive(Default)]
+struct LinkwitzRiley {
+    stages: [BiquadFilter; 2],
+}
+
+impl LinkwitzRiley {
+    fn set_low_pass(&mut self, sample_rate: f32, frequency: f32) {
+        for stage in &mut self.stages {
+            stage.set_low_pass(sample_rate, frequency, FRAC_1_SQRT_2);
+        }
+    }
+
+    fn set_high_pass(&mut self, sample_rate: f32, frequency: f32) {
+        for stage in &mut self.stages {
+            stage.set_high_pass(sample_rate, frequency, FRAC_1_SQRT_2);
+        }
+    }
+
+    fn process_sample(&mut self, sample: f32) -> f32 {
+        let sample = self.stages[0].process_sample(sample);
+        self.stages[1].process_sample(sample)
+    }
+}
+
+/// Splits one channel into four bands. Each lower band also passes through the
+/// all-pass of every crossover above it, so the bands sum back with a flat magnitude.
+#[derive(Default)]
+struct ChannelCrossover {
+    low: [LinkwitzRiley; COMPRESSOR_BANDS - 1],
+    high: [LinkwitzRiley; COMPRESSOR_BANDS - 1],
+    /// Band 0 at crossovers 1 and 2, band 1 at crossover 2.
+    all_pass: [BiquadFilter; 3],
+}
+
+impl ChannelCrossover {
+    fn set_crossovers(&mut self, sample_rate: f32, crossovers: [f32; COMPRESSOR_BANDS - 1]) {
+        for (index, frequency) in crossovers.into_iter().enumerate() {
+            self.low[index].set_low_pass(sample_rate, frequency);
+            self.high[index].set_high_pass(sample_rate, frequency);
+        }
+        self.all_pass[0].set_all_pass(sample_rate, crossovers[1], FRAC_1_SQRT_2);
+        self.all_pass[1].set_all_pass(sample_rate, crossovers[2], FRAC_1_SQRT_2);
+        self.all_pass[2].set_all_pass(sample_rate, crossovers[2], FRAC_1_SQRT_2);
+    }
+
+    fn split(&mut self, sample: f32) -> [f32; COMPRESSOR_BANDS] {
+        let band0 = self.low[0].process_sample(sample);
+        let rest = self.high[0].process_sample(sample);
+        let band1 = self.low[1].process_sample(rest);
+        let rest = self.high[1].process_sample(rest);
+        let band2 = self.low[2].process_sample(rest);
+        let band3 = self.high[2].process_sample(rest);
+        let band0 = self.all_pass[0].process_sample(band0);
+        [
+            self.all_pass[1].process_sample(band0),
+            self.all_pass[2].process_sample(band1),
+            band2,
+            band3,
+        ]
+    }
+}
+
+/// Four-band compressor for taming one region of a recording (boomy bass, harsh
+/// treble) without touching the EQ. Each band is compressed on the louder of its two
+/// channels so the stereo image stays put. Bypassed while disabled.
+pub struct MultibandCompressorNode {
+    enabled: AtomicBool,
+    crossover_bits: [AtomicU32; COMPRESSOR_BANDS - 1],
+    bands: [BandControl; COMPRESSOR_BANDS],
+    needs_update: AtomicBool,
+    sample_rate: f32,
+    was_enabled: bool,
+    channels: [ChannelCrossover; 2],
+    reduction_db: [f32; COMPRESSOR_BANDS],
+    attack_coeff: f32,
+    release_coeff: f32,
+}
+
+impl MultibandCompressorNode {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut node = Self {
+            enabled: AtomicBool::new(false),
+            crossover_bits: DEFAULT_CROSSOVERS.map(|frequency| AtomicU32::new(frequency.to_bits())),
+            bands: std::array::from_fn(|_| BandControl::new()),
+            needs_update: AtomicBool::new(true),
+            sample_rate: sample_rate.max(8_000.0),
+            was_enabled: false,
+            channels: Default::default(),
+            reduction_db: [0.0; COMPRESSOR_BANDS],
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+        };
+        node.recalculate();
+        node
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Threshold is clamped to -60..0 dBFS and ratio to 1:1..20:1.
+    pub fn set_band(&self, index: usize, threshold_db: f32, ratio: f32) -> Result<(), String> {
+        let Some(band) = self.bands.get(index) else {
+            return Err(format!(
+                "Compressor band index out of range: {index} (valid: 0 to {})",
+                COMPRESSOR_BANDS - 1
+            ));
+        };
+        if !threshold_db.is_finite() || !ratio.is_finite() {
+            return Err("Compressor threshold and ratio must be finite".to_string());
+        }
+        band.threshold_bits
+            .store(threshold_db.clamp(-60.0, 0.0).to_bits(), Ordering::SeqCst);
+        band.ratio_bits
+            .store(ratio.clamp(1.0, 20.0).to_bits(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn settings(&self) -> CompressorSettings {
+        CompressorSettings {
+            enabled: self.is_enabled(),
+            crossovers: self.crossovers(),
+            bands: self.bands.iter().map(BandControl::settings).collect(),
+        }
+    }
+
+    /// Current gain reduction of each band in dB (0 when idle).
+    pub fn gain_reduction_db(&self) -> Vec<f32> {
+        self.bands
+            .iter()
+            .map(|band| f32::from_bits(band.reduction_bits.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Crossover frequencies between the bands, ascending, within 20 Hz..20 kHz.
+    pub fn set_crossovers(&self, crossovers: [f32; COMPRESSOR_BANDS - 1]) -> Result<(), String> {
+        let in_range = crossovers
+            .iter()
+            .all(|frequency| (MIN_CROSSOVER_HZ..=MAX_CROSSOVER_HZ).contains(frequency));
+        if !in_range || crossovers.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!(
+                "Compressor crossovers must ascend within {MIN_CROSSOVER_HZ}-{MAX_CROSSOVER_HZ} Hz"
+            ));
+        }
+        for (bits, frequency) in self.crossover_bits.iter().zip(crossovers) {
+            bits.store(frequency.to_bits(), Ordering::SeqCst);
+        }
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn crossovers(&self) -> [f32; COMPRESSOR_BANDS - 1] {
+        std::array::from_fn(|index| {
+            f32::from_bits(self.crossover_bits[index].load(Ordering::Relaxed))
+        })
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() > f32::EPSILON {
+            self.sample_rate = sr;
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            if self.was_enabled {
+                self.was_enabled = false;
+                for band in &self.bands {
+                    band.reduction_bits
+                        .store(0.0_f32.to_bits(), Ordering::Relaxed);
+                }
+            }
+            return (left, right);
+        }
+        if !self.was_enabled {
+            // Start from silence rather than filter state left over from before bypass.
+            self.was_enabled = true;
+            self.channels = Default::default();
+            self.reduction_db = [0.0; COMPRESSOR_BANDS];
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+        if self.needs_update.swap(false, Ordering::SeqCst) {
+            self.recalculate();
+        }
+
+        let left_bands = self.channels[0].split(left);
+        let right_bands = self.channels[1].split(right);
+        let (mut out_left, mut out_right) = (0.0, 0.0);
+        for (index, band) in self.bands.iter().enumerate() {
+            let settings = band.settings();
+            let peak = left_bands[index].abs().max(right_bands[index].abs());
+            let level_db = 20.0 * peak.max(1.0e-9).log10();
+            let target = gain_reduction_db(level_db, settings.threshold_db, settings.ratio);
+            let current = self.reduction_db[index];
+            let coeff = if target > current {
+                self.attack_coeff
+            } else {
+                self.release_coeff
+            };
+            let reduction = target + (current - target) * coeff;
+            self.reduction_db[index] = reduction;
+            band.reduction_bits
+                .store(reduction.to_bits(), Ordering::Relaxed);
+
+            let gain = 10.0_f32.powf(-reduction / 20.0);
+            out_left += left_bands[index] * gain;
+            out_right += right_bands[index] * gain;
+        }
+        (out_left, out_right)
+    }
+
+    fn recalculate(&mut self) {
+        let crossovers = self.crossovers();
+        for channel in &mut self.channels {
+            channel.set_crossovers(self.sample_rate, crossovers);
+        }
+        self.attack_coeff = (-1.0 / (ATTACK_MS * 0.001 * self.sample_rate)).exp();
+        self.release_coeff = (-1.0 / (RELEASE_MS * 0.001 * self.sample_rate)).exp();
+    }
+}
+
+/// Static curve with a soft knee of `KNEE_DB` centred on the threshold.
+fn gain_reduction_db(level_db: f32, threshold_db: f32, ratio: f32) -> f32 {
+    let over = level_db - threshold_db;
+    let slope = 1.0 - 1.0 / ratio;
+    if 2.0 * over <= -KNEE_DB {
+        0.0
+    } else if 2.0 * over < KNEE_DB {
+        slope * (over + KNEE_DB / 2.0).powi(2) / (2.0 * KNEE_DB)
+    } else {
+        slope * over
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultibandCompressorNode;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// RMS of the output over the last half of one second of a sine at `frequency`.
+    fn output_rms(node: &mut MultibandCompressorNode, frequency: f32, amplitude: f32) -> f32 {
+        let total = SAMPLE_RATE as usize;
+        let mut sum = 0.0;
+        for n in 0..total {
+            let phase = 2.0 * std::f32::consts::PI * frequency * n as f32 / SAMPLE_RATE;
+            let (left, _) = node.process_stereo_frame(amplitude * phase.sin(), 0.0);
+            if n >= total / 2 {
+                sum += left * left;
+            }
+        }
+        (sum / (total / 2) as f32).sqrt()
+    }
+
+    #[test]
+    fn bands_sum_flat_and_compress_only_their_own_range() {
+        let mut node = MultibandCompressorNode::new(SAMPLE_RATE);
+        assert_eq!(node.process_stereo_frame(0.3, -0.2), (0.3, -0.2));
+        let sine_rms = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+
+        // Enabled at 1:1 the crossover only shifts phase.
+        node.set_enabled(true);
+        for frequency in [60.0, 120.0, 1_000.0, 6_000.0, 12_000.0] {
+            let rms = output_rms(&mut node, frequency, 0.5);
+            assert!((rms / sine_rms - 1.0).abs() < 0.02, "{frequency} Hz: {rms}");
+        }
+
+        node.set_band(0, -30.0, 8.0).expect("band 0");
+        let bass = output_rms(&mut node, 50.0, 0.5);
+        assert!(bass < sine_rms * 0.25, "bass should be compressed: {bass}");
+        assert!(node.gain_reduction_db()[0] > 15.0);
+        let mids = output_rms(&mut node, 2_500.0, 0.5);
+        assert!((mids / sine_rms - 1.0).abs() < 0.02, "mids: {mids}");
+
+        assert!(node.set_band(4, -10.0, 2.0).is_err());
+        assert!(node.set_crossovers([500.0, 200.0, 8_000.0]).is_err());
+        node.set_crossovers([200.0, 2_000.0, 8_000.0])
+            .expect("crossovers");
+        assert_eq!(node.crossovers(), [200.0, 2_000.0, 8_000.0]);
+    }
+}
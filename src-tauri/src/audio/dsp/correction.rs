@@ -0,0 +1,285 @@
+//! Measured room or headphone corrections imported from filter files: REW "EQ filter
+//! export" and AutoEq/EqualizerAPO parametric lists, or miniDSP biquad coefficients.
+
+use serde::Serialize;
+
+use super::autoeq::parse_parametric_eq;
+use super::filters::{BiquadFilter, EqBandSettings};
+
+/// More filters than any of the supported exporters writes.
+const MAX_CORRECTION_FILTERS: usize = 64;
+
+/// One filter of an imported correction.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CorrectionFilter {
+    /// A parametric band, designed for whatever rate the output runs at.
+    Band(EqBandSettings),
+    /// Raw coefficients (`a0 = 1`, denominator `1 + a1·z⁻¹ + a2·z⁻²`) designed at
+    /// `sample_rate`; other rates get a bilinear-transform conversion, which is exact
+    /// at low frequencies and drifts slightly near the lower Nyquist frequency.
+    Biquad {
+        b0: f32,
+        b1: f32,
+        b2: f32,
+        a1: f32,
+        a2: f32,
+        sample_rate: f32,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RoomCorrection {
+    /// Gain ahead of the filters, as recommended by the file to avoid clipping.
+    pub preamp_db: f32,
+    pub filters: Vec<CorrectionFilter>,
+}
+
+/// Parses a correction file. miniDSP biquad files are recognized by their `biquadN`
+/// sections and need the `sample_rate` they were designed for; anything else is read
+/// as a parametric filter list.
+pub fn parse_correction(text: &str, sample_rate: Option<f32>) -> Result<RoomCorrection, String> {
+    let correction = if text.to_ascii_lowercase().contains("biquad") {
+        let sample_rate = sample_rate
+            .filter(|rate| rate.is_finite() && *rate >= 8_000.0)
+            .ok_or_else(|| {
+                "miniDSP biquad files need the sample rate they were designed for".to_string()
+            })?;
+        RoomCorrection {
+            preamp_db: 0.0,
+            filters: parse_biquads(text, sample_rate)?,
+        }
+    } else {
+        let profile = parse_parametric_eq(text)?;
+        RoomCorrection {
+            preamp_db: profile.preamp_db,
+            filters: profile
+                .bands
+                .into_iter()
+                .map(CorrectionFilter::Band)
+                .collect(),
+        }
+    };
+    if correction.filters.len() > MAX_CORRECTION_FILTERS {
+        return Err(format!(
+            "Correction has {} filters; at most {MAX_CORRECTION_FILTERS} are supported",
+            correction.filters.len()
+        ));
+    }
+    Ok(correction)
+}
+
+/// Reads `biquad1, b0=…, b1=…, b2=…, a1=…, a2=…, biquad2, …`. miniDSP adds the
+/// feedback terms rather than subtracting them, so `a1` and `a2` are negated.
+fn parse_biquads(text: &str, sample_rate: f32) -> Result<Vec<CorrectionFilter>, String> {
+    let mut sections: Vec<[Option<f32>; 5]> = Vec::new();
+    let tokens = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        if token.to_ascii_lowercase().starts_with("biquad") {
+            sections.push([None; 5]);
+            continue;
+        }
+        let (key, value) = token
+            .split_once('=')
+            .ok_or_else(|| format!("Unexpected text in biquad file: {token}"))?;
+        let index = match key.trim().to_ascii_lowercase().as_str() {
+            "b0" => 0,
+            "b1" => 1,
+            "b2" => 2,
+            "a1" => 3,
+            "a2" => 4,
+            _ => return Err(format!("Unknown biquad coefficient: {token}")),
+        };
+        let value = value
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("Invalid biquad coefficient: {token}"))?;
+        let section = sections
+            .last_mut()
+            .ok_or_else(|| format!("Coefficient outside a biquad section: {token}"))?;
+        section[index] = Some(value);
+    }
+    if sections.is_empty() {
+        return Err("Biquad file has no filters".to_string());
+    }
+    sections
+        .into_iter()
+        .enumerate()
+        .map(|(index, section)| {
+            let [Some(b0), Some(b1), Some(b2), Some(a1), Some(a2)] = section else {
+                return Err(format!("biquad{} is missing coefficients", index + 1));
+            };
+            let (a1, a2) = (-a1, -a2);
+            // Inside the stability triangle, or the filter would blow up.
+            if a2.abs() >= 1.0 || a1.abs() >= 1.0 + a2 {
+                return Err(format!("biquad{} is unstable", index + 1));
+            }
+            Ok(CorrectionFilter::Biquad {
+                b0,
+                b1,
+                b2,
+                a1,
+                a2,
+                sample_rate,
+            })
+        })
+        .collect()
+}
+
+/// Moves a biquad designed at `from` Hz to `to` Hz by undoing its bilinear transform
+/// and applying it again at the new rate.
+fn convert_biquad(coefficients: [f32; 5], from: f32, to: f32) -> [f32; 5] {
+    let [b0, b1, b2, a1, a2] = coefficients.map(f64::from);
+    let ratio = f64::from(to) / f64::from(from);
+    // Analog prototype coefficients of s², s and 1, with s in units of the old rate.
+    let analog = |c0: f64, c1: f64, c2: f64| (c0 - c1 + c2, 2.0 * (c0 - c2), c0 + c1 + c2);
+    let digital = |(s2, s1, s0): (f64, f64, f64)| {
+        let s2 = s2 * ratio * ratio;
+        let s1 = s1 * ratio;
+        (s2 + s1 + s0, 2.0 * (s0 - s2), s2 - s1 + s0)
+    };
+    let (n0, n1, n2) = digital(analog(b0, b1, b2));
+    let (d0, d1, d2) = digital(analog(1.0, a1, a2));
+    [n0 / d0, n1 / d0, n2 / d0, d1 / d0, d2 / d0].map(|value| value as f32)
+}
+
+/// The correction stage: a gain and a cascade of biquads per channel. Replacing the
+/// correction or changing the rate rebuilds the filters.
+pub struct RoomCorrectionNode {
+    correction: RoomCorrection,
+    gain: f32,
+    filters: Vec<[BiquadFilter; 2]>,
+    sample_rate: f32,
+}
+
+impl RoomCorrectionNode {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            correction: RoomCorrection::default(),
+            gain: 1.0,
+            filters: Vec::new(),
+            sample_rate: sample_rate.max(8_000.0),
+        }
+    }
+
+    pub fn set_correction(&mut self, correction: RoomCorrection) {
+        self.correction = correction;
+        self.rebuild();
+    }
+
+    pub fn correction(&self) -> &RoomCorrection {
+        &self.correction
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() > f32::EPSILON {
+            self.sample_rate = sr;
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let sr = self.sample_rate;
+        self.gain = 10.0_f32.powf(self.correction.preamp_db.clamp(-24.0, 6.0) / 20.0);
+        self.filters = self
+            .correction
+            .filters
+            .iter()
+            .map(|filter| {
+                let mut stage = [BiquadFilter::new(), BiquadFilter::new()];
+                for channel in &mut stage {
+                    match *filter {
+                        CorrectionFilter::Band(band) => channel.set_band(sr, &band),
+                        CorrectionFilter::Biquad {
+                            b0,
+                            b1,
+                            b2,
+                            a1,
+                            a2,
+                            sample_rate,
+                        } => channel.set_coefficients(convert_biquad(
+                            [b0, b1, b2, a1, a2],
+                            sample_rate,
+                            sr,
+                        )),
+                    }
+                }
+                stage
+            })
+            .collect();
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if self.filters.is_empty() && self.gain == 1.0 {
+            return (left, right);
+        }
+        let (mut left, mut right) = (left * self.gain, right * self.gain);
+        for [left_filter, right_filter] in &mut self.filters {
+            left = left_filter.process_sample(left);
+            right = right_filter.process_sample(right);
+        }
+        (left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert_biquad, parse_correction, CorrectionFilter};
+    use crate::audio::dsp::filters::FilterType;
+
+    /// Magnitude in dB of a biquad at `frequency`.
+    fn response_db([b0, b1, b2, a1, a2]: [f32; 5], sample_rate: f32, frequency: f32) -> f32 {
+        let w = 2.0 * std::f32::consts::PI * frequency / sample_rate;
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let num = (b0 + b1 * c1 + b2 * c2).hypot(b1 * s1 + b2 * s2);
+        let den = (1.0 + a1 * c1 + a2 * c2).hypot(a1 * s1 + a2 * s2);
+        20.0 * (num / den).log10()
+    }
+
+    #[test]
+    fn rew_and_minidsp_files_become_correction_filters() {
+        let rew = "Filter Settings file\n\n\
+            Room EQ V5.20\n\
+            Dated: 16-Oct-2026 10:00:00\n\n\
+            Equaliser: Generic\n\
+            Filter  1: ON  PK       Fc    63.50 Hz  Gain  -8.00 dB  Q  4.000\n\
+            Filter  2: ON  LS       Fc   120.0 Hz  Gain   3.00 dB\n\
+            Filter  3: OFF None\n";
+        let correction = parse_correction(rew, None).expect("REW export should parse");
+        assert_eq!(correction.filters.len(), 2);
+        let CorrectionFilter::Band(band) = correction.filters[0] else {
+            panic!("REW filters are parametric bands");
+        };
+        assert_eq!((band.frequency, band.gain_db), (63.5, -8.0));
+        assert_eq!(band.filter_type, FilterType::Peaking);
+
+        // A -6 dB peak at 1 kHz, Q 1, designed at 48 kHz, in miniDSP's sign convention.
+        let minidsp = "biquad1,\nb0=0.9578975,\nb1=-1.8155229,\nb2=0.8732915,\n\
+                       a1=1.8155229,\na2=-0.8311890,\n";
+        assert!(parse_correction(minidsp, None).is_err());
+        let correction = parse_correction(minidsp, Some(48_000.0)).expect("biquads parse");
+        let CorrectionFilter::Biquad {
+            b0, b1, b2, a1, a2, ..
+        } = correction.filters[0]
+        else {
+            panic!("miniDSP filters stay biquads");
+        };
+        let coefficients = [b0, b1, b2, a1, a2];
+        assert!((response_db(coefficients, 48_000.0, 1_000.0) + 6.0).abs() < 0.1);
+
+        // Converted to 96 kHz, the peak stays at 1 kHz with the same depth.
+        let converted = convert_biquad(coefficients, 48_000.0, 96_000.0);
+        assert!((response_db(converted, 96_000.0, 1_000.0) + 6.0).abs() < 0.1);
+        assert!(response_db(converted, 96_000.0, 100.0).abs() < 0.2);
+
+        assert!(parse_correction("biquad1, b0=1, b1=0, b2=0, a1=0", Some(48_000.0)).is_err());
+        assert!(
+            parse_correction("biquad1, b0=1, b1=0, b2=0, a1=0, a2=1.5", Some(48_000.0)).is_err()
+        );
+    }
+}
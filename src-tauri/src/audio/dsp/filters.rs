@@ -1,9 +1,34 @@
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use super::plugin_rack::PluginPosition;
 
 const EQ_BANDS_MIN: usize = 10;
 const EQ_BANDS_MAX: usize = 15;
 
-#[derive(Clone, Copy)]
+/// ISO 266 third-octave centers, 20 Hz to 20 kHz.
+pub const GRAPHIC_EQ_FREQUENCIES: [f32; 31] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0,
+    500.0, 630.0, 800.0, 1_000.0, 1_250.0, 1_600.0, 2_000.0, 2_500.0, 3_150.0, 4_000.0, 5_000.0,
+    6_300.0, 8_000.0, 10_000.0, 12_500.0, 16_000.0, 20_000.0,
+];
+/// Bandwidth of one third of an octave.
+const GRAPHIC_EQ_Q: f32 = 4.318;
+const GRAPHIC_EQ_MAX_GAIN_DB: f32 = 12.0;
+
+/// Which equalizer the user EQ stage runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EqMode {
+    /// The parametric user EQ (10 bands with free frequency, gain, Q and type).
+    Parametric,
+    /// The 31-band graphic EQ with fixed centers and gain-only sliders.
+    Graphic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FilterType {
     Peaking,
     LowShelf,
@@ -12,6 +37,36 @@ pub enum FilterType {
     LowPass,
 }
 
+impl FilterType {
+    const ALL: [FilterType; 5] = [
+        FilterType::Peaking,
+        FilterType::LowShelf,
+        FilterType::HighShelf,
+        FilterType::HighPass,
+        FilterType::LowPass,
+    ];
+
+    fn from_index(index: u8) -> Self {
+        Self::ALL
+            .get(index as usize)
+            .copied()
+            .unwrap_or(FilterType::Peaking)
+    }
+
+    fn index(self) -> u8 {
+        Self::ALL.iter().position(|t| *t == self).unwrap_or(0) as u8
+    }
+}
+
+/// Full settings of one EQ band, as stored in EQ presets and AutoEQ profiles.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EqBandSettings {
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub q_factor: f32,
+    pub filter_type: FilterType,
+}
+
 #[derive(Clone, Copy)]
 struct BiquadCoefficients {
     b0: f32,
@@ -74,38 +129,25 @@ impl BiquadFilter {
     pub fn set_low_pass(&mut self, sample_rate: f32, frequency: f32, q_factor: f32) {
         self.coeffs = low_pass_coefficients(sample_rate, frequency, q_factor);
     }
-}
 
-impl Default for BiquadFilter {
-    fn default() -> Self {
-        Self::new()
+    pub fn set_all_pass(&mut self, sample_rate: f32, frequency: f32, q_factor: f32) {
+        self.coeffs = all_pass_coefficients(sample_rate, frequency, q_factor);
     }
-}
-
-pub struct SoftLimiter {
-    threshold: f32,
-}
 
-impl SoftLimiter {
-    pub fn new() -> Self {
-        Self {
-            threshold: db_to_gain(-0.1),
-        }
+    /// Designs the filter for one EQ band, whatever its type.
+    pub fn set_band(&mut self, sample_rate: f32, band: &EqBandSettings) {
+        self.coeffs = settings_coefficients(sample_rate, band);
     }
 
-    pub fn process_sample(&self, sample: f32) -> f32 {
-        let abs = sample.abs();
-        if abs <= self.threshold {
-            return sample;
-        }
-
-        let over = abs - self.threshold;
-        let compressed = self.threshold + over / (1.0 + over / (1.0 - self.threshold));
-        compressed.min(1.0).copysign(sample)
+    /// Loads raw coefficients `[b0, b1, b2, a1, a2]`, normalized to `a0 = 1` with the
+    /// denominator `1 + a1·z⁻¹ + a2·z⁻²`.
+    pub fn set_coefficients(&mut self, coefficients: [f32; 5]) {
+        let [b0, b1, b2, a1, a2] = coefficients;
+        self.coeffs = BiquadCoefficients { b0, b1, b2, a1, a2 };
     }
 }
 
-impl Default for SoftLimiter {
+impl Default for BiquadFilter {
     fn default() -> Self {
         Self::new()
     }
@@ -141,55 +183,200 @@ impl Default for StereoWidener {
     }
 }
 
+/// Chain stages that can be bypassed on their own, in signal order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DspNode {
+    Tone,
+    AutoEq,
+    /// The imported room correction.
+    Correction,
+    /// The user EQ stage, whichever EQ mode it runs in.
+    UserEq,
+    Expansion,
+    Spatial,
+    Reverb,
+    Limiter,
+}
+
+impl DspNode {
+    pub const ALL: [DspNode; 8] = [
+        DspNode::Tone,
+        DspNode::AutoEq,
+        DspNode::Correction,
+        DspNode::UserEq,
+        DspNode::Expansion,
+        DspNode::Spatial,
+        DspNode::Reverb,
+        DspNode::Limiter,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << Self::ALL.iter().position(|node| *node == self).unwrap_or(0)
+    }
+}
+
 pub struct DspChain {
     tone: super::tone::ToneNode,
+    loudness: super::loudness::LoudnessNode,
     auto_eq: ParametricEQ,
+    correction: super::correction::RoomCorrectionNode,
     user_eq: ParametricEQ,
+    graphic_eq: GraphicEQ,
+    linear_phase: super::linear_phase::LinearPhaseEQ,
+    compressor: super::compressor::MultibandCompressorNode,
+    night_mode: super::night_mode::NightModeNode,
+    analog: super::analog::AnalogNode,
     balance: super::tone::BalanceNode,
     expansion: super::tone::StereoExpansionNode,
     spatial: super::spatial::SpatialRoomNode,
     reverb: super::reverb::ReverbNode,
-    limiter: SoftLimiter,
+    limiter: super::limiter::LookaheadLimiter,
+    plugins: super::plugin_rack::PluginRack,
+    bass_management: super::bass_management::BassManager,
+    /// One `DspNode::bit` per bypassed node.
+    bypassed: AtomicU8,
 }
 
 impl DspChain {
     pub fn new(sample_rate: f32) -> Self {
         Self {
             tone: super::tone::ToneNode::new(sample_rate),
+            loudness: super::loudness::LoudnessNode::new(sample_rate),
             auto_eq: ParametricEQ::new(10, sample_rate),
+            correction: super::correction::RoomCorrectionNode::new(sample_rate),
             user_eq: ParametricEQ::new(10, sample_rate),
+            graphic_eq: GraphicEQ::new(sample_rate),
+            linear_phase: super::linear_phase::LinearPhaseEQ::new(),
+            compressor: super::compressor::MultibandCompressorNode::new(sample_rate),
+            night_mode: super::night_mode::NightModeNode::new(sample_rate),
+            analog: super::analog::AnalogNode::new(sample_rate),
             balance: super::tone::BalanceNode::new(),
             expansion: super::tone::StereoExpansionNode::new(sample_rate),
             spatial: super::spatial::SpatialRoomNode::new(sample_rate),
             reverb: super::reverb::ReverbNode::new(sample_rate),
-            limiter: SoftLimiter::new(),
+            limiter: super::limiter::LookaheadLimiter::new(sample_rate),
+            plugins: super::plugin_rack::PluginRack::new(sample_rate),
+            bass_management: super::bass_management::BassManager::new(sample_rate),
+            bypassed: AtomicU8::new(0),
         }
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.tone.set_sample_rate(sample_rate);
+        self.loudness.set_sample_rate(sample_rate);
         self.auto_eq.set_sample_rate(sample_rate);
+        self.correction.set_sample_rate(sample_rate);
         self.user_eq.set_sample_rate(sample_rate);
+        self.graphic_eq.set_sample_rate(sample_rate);
+        self.compressor.set_sample_rate(sample_rate);
+        self.night_mode.set_sample_rate(sample_rate);
+        self.analog.set_sample_rate(sample_rate);
         self.expansion.set_sample_rate(sample_rate);
         self.spatial.set_sample_rate(sample_rate);
         self.reverb.set_sample_rate(sample_rate);
+        self.limiter.set_sample_rate(sample_rate);
+        self.plugins.set_sample_rate(sample_rate);
+        self.bass_management.set_sample_rate(sample_rate);
     }
 
-    /// Order: PreAmp → Tone → AutoEQ → UserEQ → Balance → StereoExpansion → Spatial → Reverb → Limiter
+    /// Order: PreAmp → Tone → Loudness → AutoEQ → Correction → UserEQ → Compressor → NightMode → Analog → Balance → StereoExpansion → Spatial → Reverb → Limiter,
+    /// with plugin inserts at the four `PluginPosition`s. In graphic EQ mode the
+    /// GraphicEQ takes the place of UserEQ; with linear phase on, either runs as a
+    /// `LinearPhaseEQ` instead of biquads. Bypassed `DspNode`s are skipped.
     pub fn process_stereo_frame(&mut self, left: f32, right: f32, preamp_db: f32) -> (f32, f32) {
+        let bypassed = self.bypassed.load(Ordering::Relaxed);
+        let active = |node: DspNode| bypassed & node.bit() == 0;
         let preamp = db_to_gain(preamp_db);
         let (left, right) = (left * preamp, right * preamp);
-        let (left, right) = self.tone.process_stereo_frame(left, right);
-        let (left, right) = self.auto_eq.process_stereo_frame(left, right);
-        let (left, right) = self.user_eq.process_stereo_frame(left, right);
+        let (left, right) = if active(DspNode::Tone) {
+            self.tone.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        };
+        let (left, right) = self.loudness.process_stereo_frame(left, right);
+        let (left, right) = self
+            .plugins
+            .process_stereo_frame(PluginPosition::PreEq, left, right);
+        let (left, right) = if active(DspNode::AutoEq) {
+            self.auto_eq.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        };
+        let (left, right) = if active(DspNode::Correction) {
+            self.correction.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        };
+        let graphic = self.graphic_eq.is_enabled();
+        let (left, right) = if !active(DspNode::UserEq) {
+            (left, right)
+        } else if self.linear_phase.is_enabled() {
+            let eq = if graphic {
+                self.graphic_eq.as_parametric()
+            } else {
+                &self.user_eq
+            };
+            self.linear_phase
+                .process_stereo_frame(left, right, eq, graphic)
+        } else if graphic {
+            self.graphic_eq.process_stereo_frame(left, right)
+        } else {
+            self.user_eq.process_stereo_frame(left, right)
+        };
+        let (left, right) = self
+            .plugins
+            .process_stereo_frame(PluginPosition::PostEq, left, right);
+        let (left, right) = self.compressor.process_stereo_frame(left, right);
+        let (left, right) = self.night_mode.process_stereo_frame(left, right);
+        let (left, right) = self.analog.process_stereo_frame(left, right);
+        let (left, right) =
+            self.plugins
+                .process_stereo_frame(PluginPosition::PostDynamics, left, right);
         let (left, right) = self.balance.process_stereo_frame(left, right);
-        let (left, right) = self.expansion.process_stereo_frame(left, right);
-        let (left, right) = self.spatial.process_stereo_frame(left, right);
-        let (left, right) = self.reverb.process_stereo_frame(left, right);
-        (
-            self.limiter.process_sample(left),
-            self.limiter.process_sample(right),
-        )
+        let (left, right) = if active(DspNode::Expansion) {
+            self.expansion.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        };
+        let (left, right) = if active(DspNode::Spatial) {
+            self.spatial.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        };
+        let (left, right) = if active(DspNode::Reverb) {
+            self.reverb.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        };
+        let (left, right) =
+            self.plugins
+                .process_stereo_frame(PluginPosition::PreLimiter, left, right);
+        if active(DspNode::Limiter) {
+            self.limiter.process_stereo_frame(left, right)
+        } else {
+            (left, right)
+        }
+    }
+
+    /// Bass management on a whole interleaved output frame, once `process_stereo_frame`
+    /// has filled its front pair.
+    pub fn process_output_frame(&mut self, frame: &mut [f32]) {
+        self.bass_management.process_frame(frame);
+    }
+
+    /// Takes `node` out of the signal path, or puts it back, without touching its
+    /// settings.
+    pub fn set_node_bypassed(&self, node: DspNode, bypassed: bool) {
+        if bypassed {
+            self.bypassed.fetch_or(node.bit(), Ordering::SeqCst);
+        } else {
+            self.bypassed.fetch_and(!node.bit(), Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_node_bypassed(&self, node: DspNode) -> bool {
+        self.bypassed.load(Ordering::Relaxed) & node.bit() != 0
     }
 
     pub fn update_user_eq_band(
@@ -207,21 +394,89 @@ impl DspChain {
         self.user_eq.get_bands()
     }
 
+    pub fn user_eq_settings(&self) -> Vec<EqBandSettings> {
+        self.user_eq
+            .get_bands()
+            .into_iter()
+            .zip(self.user_eq.get_band_types())
+            .map(
+                |((frequency, gain_db, q_factor), filter_type)| EqBandSettings {
+                    frequency,
+                    gain_db,
+                    q_factor,
+                    filter_type,
+                },
+            )
+            .collect()
+    }
+
+    /// Applies `bands` in order; user EQ bands past the end of `bands` are flattened.
+    pub fn apply_user_eq_settings(&self, bands: &[EqBandSettings]) -> Result<(), String> {
+        self.user_eq.apply_settings(bands)
+    }
+
+    /// Response of the user EQ stage: the graphic EQ in graphic mode, otherwise the
+    /// parametric one.
     pub fn user_eq_response(&self, num_points: usize) -> Vec<(f32, f32)> {
-        self.user_eq.compute_frequency_response(num_points)
+        match self.eq_mode() {
+            EqMode::Graphic => self.graphic_eq.compute_frequency_response(num_points),
+            EqMode::Parametric => self.user_eq.compute_frequency_response(num_points),
+        }
     }
 
-    pub fn set_autoeq_profile(&self, profile: &[(f32, f32, f32)]) -> Result<(), String> {
-        for (idx, (freq, gain_db, q)) in profile.iter().copied().enumerate() {
-            self.auto_eq.update_band(idx, freq, gain_db, q)?;
+    pub fn set_eq_mode(&self, mode: EqMode) {
+        self.graphic_eq.set_enabled(mode == EqMode::Graphic);
+    }
+
+    pub fn eq_mode(&self) -> EqMode {
+        if self.graphic_eq.is_enabled() {
+            EqMode::Graphic
+        } else {
+            EqMode::Parametric
         }
-        Ok(())
+    }
+
+    pub fn graphic_eq(&self) -> &GraphicEQ {
+        &self.graphic_eq
+    }
+
+    pub fn linear_phase(&self) -> &super::linear_phase::LinearPhaseEQ {
+        &self.linear_phase
+    }
+
+    /// Replaces the AutoEQ compensation; an empty profile turns it off.
+    pub fn set_autoeq_profile(&self, profile: &[EqBandSettings]) -> Result<(), String> {
+        self.auto_eq.apply_settings(profile)
+    }
+
+    pub fn correction(&self) -> &super::correction::RoomCorrectionNode {
+        &self.correction
+    }
+
+    pub fn correction_mut(&mut self) -> &mut super::correction::RoomCorrectionNode {
+        &mut self.correction
     }
 
     pub fn tone(&self) -> &super::tone::ToneNode {
         &self.tone
     }
 
+    pub fn loudness(&self) -> &super::loudness::LoudnessNode {
+        &self.loudness
+    }
+
+    pub fn compressor(&self) -> &super::compressor::MultibandCompressorNode {
+        &self.compressor
+    }
+
+    pub fn night_mode(&self) -> &super::night_mode::NightModeNode {
+        &self.night_mode
+    }
+
+    pub fn analog(&self) -> &super::analog::AnalogNode {
+        &self.analog
+    }
+
     pub fn balance(&self) -> &super::tone::BalanceNode {
         &self.balance
     }
@@ -234,6 +489,14 @@ impl DspChain {
         &self.reverb
     }
 
+    pub fn limiter(&self) -> &super::limiter::LookaheadLimiter {
+        &self.limiter
+    }
+
+    pub fn bass_management(&self) -> &super::bass_management::BassManager {
+        &self.bass_management
+    }
+
     pub fn spatial(&self) -> &super::spatial::SpatialRoomNode {
         &self.spatial
     }
@@ -241,10 +504,27 @@ impl DspChain {
     pub fn spatial_mut(&mut self) -> &mut super::spatial::SpatialRoomNode {
         &mut self.spatial
     }
+
+    /// Output `channel` of the spatial room when it is rendered to speakers; the front
+    /// pair comes out of `process_stereo_frame` as usual.
+    pub fn spatial_speaker_sample(&self, channel: usize) -> Option<f32> {
+        if self.is_node_bypassed(DspNode::Spatial) {
+            return None;
+        }
+        self.spatial.speaker_sample(channel)
+    }
+
+    pub fn plugins(&self) -> &super::plugin_rack::PluginRack {
+        &self.plugins
+    }
+
+    pub fn plugins_mut(&mut self) -> &mut super::plugin_rack::PluginRack {
+        &mut self.plugins
+    }
 }
 
 struct EqBand {
-    filter_type: FilterType,
+    filter_type: AtomicU8,
     frequency_bits: AtomicU32,
     gain_db_bits: AtomicU32,
     q_factor_bits: AtomicU32,
@@ -253,13 +533,17 @@ struct EqBand {
 impl EqBand {
     fn new(filter_type: FilterType, frequency: f32, gain_db: f32, q_factor: f32) -> Self {
         Self {
-            filter_type,
+            filter_type: AtomicU8::new(filter_type.index()),
             frequency_bits: AtomicU32::new(frequency.to_bits()),
             gain_db_bits: AtomicU32::new(gain_db.to_bits()),
             q_factor_bits: AtomicU32::new(q_factor.to_bits()),
         }
     }
 
+    fn filter_type(&self) -> FilterType {
+        FilterType::from_index(self.filter_type.load(Ordering::Relaxed))
+    }
+
     fn frequency(&self) -> f32 {
         f32::from_bits(self.frequency_bits.load(Ordering::Relaxed))
     }
@@ -291,27 +575,35 @@ pub struct ParametricEQ {
     left_filters: Vec<BiquadFilter>,
     right_filters: Vec<BiquadFilter>,
     needs_recalculation: AtomicBool,
+    /// Bumped on every change, so other stages designed from this EQ can follow it.
+    revision: AtomicU32,
 }
 
 impl ParametricEQ {
     pub fn new(bands: usize, sample_rate: f32) -> Self {
         let band_count = bands.clamp(EQ_BANDS_MIN, EQ_BANDS_MAX);
-        let mut eq_bands = Vec::with_capacity(band_count);
-        for index in 0..band_count {
-            eq_bands.push(EqBand::new(
-                FilterType::Peaking,
-                default_band_frequency(index, band_count),
-                0.0,
-                1.0,
-            ));
-        }
+        let eq_bands = (0..band_count)
+            .map(|index| {
+                EqBand::new(
+                    FilterType::Peaking,
+                    default_band_frequency(index, band_count),
+                    0.0,
+                    1.0,
+                )
+            })
+            .collect();
+        Self::with_bands(eq_bands, sample_rate)
+    }
 
+    fn with_bands(bands: Vec<EqBand>, sample_rate: f32) -> Self {
+        let band_count = bands.len();
         let mut eq = Self {
             sample_rate: sample_rate.max(8_000.0),
-            bands: eq_bands,
+            bands,
             left_filters: (0..band_count).map(|_| BiquadFilter::new()).collect(),
             right_filters: (0..band_count).map(|_| BiquadFilter::new()).collect(),
             needs_recalculation: AtomicBool::new(true),
+            revision: AtomicU32::new(0),
         };
         eq.recalculate_if_needed();
         eq
@@ -321,7 +613,7 @@ impl ParametricEQ {
         let sanitized = sample_rate.max(8_000.0);
         if (sanitized - self.sample_rate).abs() > f32::EPSILON {
             self.sample_rate = sanitized;
-            self.needs_recalculation.store(true, Ordering::SeqCst);
+            self.mark_changed();
         }
     }
 
@@ -344,7 +636,43 @@ impl ParametricEQ {
         let q_factor = sanitize_q(q_factor);
 
         if band.update(frequency, gain_db, q_factor) {
-            self.needs_recalculation.store(true, Ordering::SeqCst);
+            self.mark_changed();
+        }
+        Ok(())
+    }
+
+    pub fn set_band_type(&self, index: usize, filter_type: FilterType) -> Result<(), String> {
+        let Some(band) = self.bands.get(index) else {
+            return Err(format!(
+                "Band index out of range: {index} (valid: 0 to {})",
+                self.bands.len().saturating_sub(1),
+            ));
+        };
+        if band.filter_type.swap(filter_type.index(), Ordering::SeqCst) != filter_type.index() {
+            self.mark_changed();
+        }
+        Ok(())
+    }
+
+    /// Applies `bands` in order; bands past the end of `bands` become flat peaking
+    /// filters at their current frequency and Q.
+    pub fn apply_settings(&self, bands: &[EqBandSettings]) -> Result<(), String> {
+        if bands.len() > self.bands.len() {
+            return Err(format!(
+                "{} EQ bands given; the equalizer has {}",
+                bands.len(),
+                self.bands.len()
+            ));
+        }
+        for (index, current) in self.bands.iter().enumerate() {
+            let band = bands.get(index).copied().unwrap_or(EqBandSettings {
+                frequency: current.frequency(),
+                gain_db: 0.0,
+                q_factor: current.q_factor(),
+                filter_type: FilterType::Peaking,
+            });
+            self.set_band_type(index, band.filter_type)?;
+            self.update_band(index, band.frequency, band.gain_db, band.q_factor)?;
         }
         Ok(())
     }
@@ -367,31 +695,56 @@ impl ParametricEQ {
             return;
         }
 
-        for (index, band) in self.bands.iter().enumerate() {
-            let frequency = sanitize_frequency(band.frequency(), self.sample_rate);
-            let gain_db = band.gain_db().clamp(-24.0, 24.0);
-            let q_factor = sanitize_q(band.q_factor());
-            let coeffs = match band.filter_type {
-                FilterType::Peaking => {
-                    peaking_coefficients(self.sample_rate, frequency, gain_db, q_factor)
-                }
-                FilterType::LowShelf => {
-                    low_shelf_coefficients(self.sample_rate, frequency, gain_db, q_factor)
-                }
-                FilterType::HighShelf => {
-                    high_shelf_coefficients(self.sample_rate, frequency, gain_db, q_factor)
-                }
-                FilterType::HighPass => {
-                    high_pass_coefficients(self.sample_rate, frequency, q_factor)
-                }
-                FilterType::LowPass => low_pass_coefficients(self.sample_rate, frequency, q_factor),
-            };
-
+        for index in 0..self.bands.len() {
+            let coeffs = self.band_coefficients(&self.bands[index]);
             self.left_filters[index].coeffs = coeffs;
             self.right_filters[index].coeffs = coeffs;
         }
     }
 
+    fn mark_changed(&self) {
+        self.needs_recalculation.store(true, Ordering::SeqCst);
+        self.revision.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    fn band_coefficients(&self, band: &EqBand) -> BiquadCoefficients {
+        settings_coefficients(
+            self.sample_rate,
+            &EqBandSettings {
+                frequency: band.frequency(),
+                gain_db: band.gain_db(),
+                q_factor: band.q_factor(),
+                filter_type: band.filter_type(),
+            },
+        )
+    }
+
+    /// Combined linear magnitude of all bands at `bins` evenly spaced frequencies from
+    /// 0 Hz to Nyquist, for stages that design their own filter from this EQ.
+    pub fn linear_magnitudes(&self, bins: usize) -> Vec<f32> {
+        let coeffs: Vec<_> = self
+            .bands
+            .iter()
+            .map(|band| self.band_coefficients(band))
+            .collect();
+        let step = std::f32::consts::PI / bins.saturating_sub(1).max(1) as f32;
+        (0..bins)
+            .map(|bin| {
+                let w = bin as f32 * step;
+                let (cos_w, cos_2w) = (w.cos() as f64, (2.0 * w).cos() as f64);
+                coeffs
+                    .iter()
+                    .map(|coeffs| magnitude_squared(coeffs, cos_w, cos_2w))
+                    .product::<f64>()
+                    .sqrt() as f32
+            })
+            .collect()
+    }
+
     /// Returns band parameters as Vec of (frequency, gain_db, q_factor) tuples.
     pub fn get_bands(&self) -> Vec<(f32, f32, f32)> {
         self.bands
@@ -400,6 +753,10 @@ impl ParametricEQ {
             .collect()
     }
 
+    pub fn get_band_types(&self) -> Vec<FilterType> {
+        self.bands.iter().map(EqBand::filter_type).collect()
+    }
+
     /// Computes the combined magnitude response (dB) at logarithmically spaced frequencies.
     /// Returns Vec of (frequency_hz, magnitude_db) pairs.
     pub fn compute_frequency_response(&self, num_points: usize) -> Vec<(f32, f32)> {
@@ -418,36 +775,8 @@ impl ParametricEQ {
             let mut total_mag_sq: f64 = 1.0;
 
             for band in &self.bands {
-                let band_freq = sanitize_frequency(band.frequency(), self.sample_rate);
-                let gain_db = band.gain_db().clamp(-24.0, 24.0);
-                let q = sanitize_q(band.q_factor());
-                let coeffs = match band.filter_type {
-                    FilterType::Peaking => {
-                        peaking_coefficients(self.sample_rate, band_freq, gain_db, q)
-                    }
-                    FilterType::LowShelf => {
-                        low_shelf_coefficients(self.sample_rate, band_freq, gain_db, q)
-                    }
-                    FilterType::HighShelf => {
-                        high_shelf_coefficients(self.sample_rate, band_freq, gain_db, q)
-                    }
-                    FilterType::HighPass => high_pass_coefficients(self.sample_rate, band_freq, q),
-                    FilterType::LowPass => low_pass_coefficients(self.sample_rate, band_freq, q),
-                };
-
-                // |H(e^jw)|^2 = (b0^2 + b1^2 + b2^2 + 2*(b0*b1+b1*b2)*cos(w) + 2*b0*b2*cos(2w))
-                //              / (1    + a1^2 + a2^2 + 2*(a1+a1*a2)*cos(w)     + 2*a2*cos(2w))
-                let num = (coeffs.b0 * coeffs.b0 + coeffs.b1 * coeffs.b1 + coeffs.b2 * coeffs.b2)
-                    as f64
-                    + 2.0 * (coeffs.b0 * coeffs.b1 + coeffs.b1 * coeffs.b2) as f64 * cos_w as f64
-                    + 2.0 * (coeffs.b0 * coeffs.b2) as f64 * cos_2w as f64;
-                let den = (1.0 + coeffs.a1 * coeffs.a1 + coeffs.a2 * coeffs.a2) as f64
-                    + 2.0 * (coeffs.a1 + coeffs.a1 * coeffs.a2) as f64 * cos_w as f64
-                    + 2.0 * coeffs.a2 as f64 * cos_2w as f64;
-
-                if den.abs() > 1e-12 {
-                    total_mag_sq *= num / den;
-                }
+                let coeffs = self.band_coefficients(band);
+                total_mag_sq *= magnitude_squared(&coeffs, cos_w as f64, cos_2w as f64);
             }
 
             let mag_db = 10.0 * total_mag_sq.max(1e-12).log10();
@@ -464,6 +793,102 @@ impl Default for ParametricEQ {
     }
 }
 
+/// 31-band graphic equalizer: peaking filters at the ISO third-octave centers, of
+/// which only the gain can be changed. Runs in place of the parametric user EQ while
+/// enabled.
+pub struct GraphicEQ {
+    enabled: AtomicBool,
+    eq: ParametricEQ,
+}
+
+impl GraphicEQ {
+    pub fn new(sample_rate: f32) -> Self {
+        let bands = GRAPHIC_EQ_FREQUENCIES
+            .iter()
+            .map(|&frequency| EqBand::new(FilterType::Peaking, frequency, 0.0, GRAPHIC_EQ_Q))
+            .collect();
+        Self {
+            enabled: AtomicBool::new(false),
+            eq: ParametricEQ::with_bands(bands, sample_rate),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.eq.set_sample_rate(sample_rate);
+    }
+
+    /// Sets the gain of band `index` (see `GRAPHIC_EQ_FREQUENCIES`), clamped to ±12 dB.
+    pub fn set_gain(&self, index: usize, gain_db: f32) -> Result<(), String> {
+        let frequency = GRAPHIC_EQ_FREQUENCIES.get(index).copied().ok_or_else(|| {
+            format!(
+                "Graphic EQ band out of range: {index} (valid: 0 to {})",
+                GRAPHIC_EQ_FREQUENCIES.len() - 1
+            )
+        })?;
+        let gain_db = gain_db.clamp(-GRAPHIC_EQ_MAX_GAIN_DB, GRAPHIC_EQ_MAX_GAIN_DB);
+        self.eq.update_band(index, frequency, gain_db, GRAPHIC_EQ_Q)
+    }
+
+    /// Gains of all bands in dB, lowest frequency first.
+    pub fn gains(&self) -> Vec<f32> {
+        self.eq
+            .get_bands()
+            .into_iter()
+            .map(|(_, gain, _)| gain)
+            .collect()
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.eq.process_stereo_frame(left, right)
+    }
+
+    pub fn compute_frequency_response(&self, num_points: usize) -> Vec<(f32, f32)> {
+        self.eq.compute_frequency_response(num_points)
+    }
+
+    /// The underlying peaking filters, e.g. to design a linear-phase version from.
+    pub fn as_parametric(&self) -> &ParametricEQ {
+        &self.eq
+    }
+}
+
+fn settings_coefficients(sample_rate: f32, band: &EqBandSettings) -> BiquadCoefficients {
+    let frequency = sanitize_frequency(band.frequency, sample_rate);
+    let gain_db = band.gain_db.clamp(-24.0, 24.0);
+    let q_factor = sanitize_q(band.q_factor);
+    match band.filter_type {
+        FilterType::Peaking => peaking_coefficients(sample_rate, frequency, gain_db, q_factor),
+        FilterType::LowShelf => low_shelf_coefficients(sample_rate, frequency, gain_db, q_factor),
+        FilterType::HighShelf => high_shelf_coefficients(sample_rate, frequency, gain_db, q_factor),
+        FilterType::HighPass => high_pass_coefficients(sample_rate, frequency, q_factor),
+        FilterType::LowPass => low_pass_coefficients(sample_rate, frequency, q_factor),
+    }
+}
+
+/// |H(e^jw)|^2 = (b0^2 + b1^2 + b2^2 + 2*(b0*b1+b1*b2)*cos(w) + 2*b0*b2*cos(2w))
+///              / (1    + a1^2 + a2^2 + 2*(a1+a1*a2)*cos(w)     + 2*a2*cos(2w))
+fn magnitude_squared(coeffs: &BiquadCoefficients, cos_w: f64, cos_2w: f64) -> f64 {
+    let num = (coeffs.b0 * coeffs.b0 + coeffs.b1 * coeffs.b1 + coeffs.b2 * coeffs.b2) as f64
+        + 2.0 * (coeffs.b0 * coeffs.b1 + coeffs.b1 * coeffs.b2) as f64 * cos_w
+        + 2.0 * (coeffs.b0 * coeffs.b2) as f64 * cos_2w;
+    let den = (1.0 + coeffs.a1 * coeffs.a1 + coeffs.a2 * coeffs.a2) as f64
+        + 2.0 * (coeffs.a1 + coeffs.a1 * coeffs.a2) as f64 * cos_w
+        + 2.0 * coeffs.a2 as f64 * cos_2w;
+    if den.abs() > 1e-12 {
+        num / den
+    } else {
+        1.0
+    }
+}
+
 fn default_band_frequency(index: usize, total: usize) -> f32 {
     let min_hz = 32.0_f32;
     let max_hz = 16_000.0_f32;
@@ -474,12 +899,12 @@ fn default_band_frequency(index: usize, total: usize) -> f32 {
     min_hz * (max_hz / min_hz).powf(ratio)
 }
 
-fn sanitize_frequency(frequency: f32, sample_rate: f32) -> f32 {
+pub(super) fn sanitize_frequency(frequency: f32, sample_rate: f32) -> f32 {
     let nyquist = (sample_rate * 0.5) - 1.0;
     frequency.clamp(10.0, nyquist.max(10.0))
 }
 
-fn sanitize_q(q_factor: f32) -> f32 {
+pub(super) fn sanitize_q(q_factor: f32) -> f32 {
     q_factor.clamp(0.1, 18.0)
 }
 
@@ -602,9 +1027,27 @@ fn low_pass_coefficients(sample_rate: f32, frequency: f32, q_factor: f32) -> Biq
     )
 }
 
+fn all_pass_coefficients(sample_rate: f32, frequency: f32, q_factor: f32) -> BiquadCoefficients {
+    let w0 = 2.0 * std::f32::consts::PI * sanitize_frequency(frequency, sample_rate) / sample_rate;
+    let alpha = w0.sin() / (2.0 * sanitize_q(q_factor));
+    let cos_w0 = w0.cos();
+
+    normalize(
+        1.0 - alpha,
+        -2.0 * cos_w0,
+        1.0 + alpha,
+        1.0 + alpha,
+        -2.0 * cos_w0,
+        1.0 - alpha,
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{BiquadFilter, ParametricEQ, SoftLimiter, StereoWidener};
+    use super::{
+        BiquadFilter, DspChain, DspNode, GraphicEQ, ParametricEQ, StereoWidener,
+        GRAPHIC_EQ_FREQUENCIES,
+    };
 
     #[test]
     fn biquad_stays_finite_after_configuration() {
@@ -633,13 +1076,6 @@ mod tests {
             .load(std::sync::atomic::Ordering::SeqCst));
     }
 
-    #[test]
-    fn soft_limiter_caps_extreme_levels() {
-        let limiter = SoftLimiter::new();
-        assert!(limiter.process_sample(2.0) <= 1.0);
-        assert!(limiter.process_sample(-2.0) >= -1.0);
-    }
-
     #[test]
     fn flat_eq_response_is_near_zero_db() {
         let eq = ParametricEQ::new(10, 48_000.0);
@@ -682,6 +1118,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn graphic_eq_has_31_gain_only_bands() {
+        let eq = GraphicEQ::new(48_000.0);
+        assert_eq!(eq.gains(), vec![0.0; 31]);
+        eq.set_gain(17, 20.0).expect("1 kHz band");
+        assert!(eq.set_gain(31, 3.0).is_err());
+        let gains = eq.gains();
+        assert_eq!(GRAPHIC_EQ_FREQUENCIES[17], 1_000.0);
+        assert_eq!(gains[17], 12.0);
+
+        // A third-octave band barely touches the next band's center.
+        let response = eq.compute_frequency_response(512);
+        let at = |hz: f32| {
+            response
+                .iter()
+                .min_by(|a, b| (a.0 - hz).abs().total_cmp(&(b.0 - hz).abs()))
+                .map(|(_, db)| *db)
+                .unwrap()
+        };
+        assert!(at(1_000.0) > 11.0);
+        assert!(at(1_600.0) < 4.0);
+    }
+
+    #[test]
+    fn bypassed_nodes_leave_the_signal_alone() {
+        let mut chain = DspChain::new(48_000.0);
+        chain.tone().set_bass(12.0);
+        let run = |chain: &mut DspChain| {
+            (0..4_800)
+                .map(|n| {
+                    let sample = 0.1 * (std::f32::consts::TAU * 60.0 * n as f32 / 48_000.0).sin();
+                    chain.process_stereo_frame(sample, sample, 0.0).0.abs()
+                })
+                .fold(0.0_f32, f32::max)
+        };
+        assert!(run(&mut chain) > 0.2);
+
+        chain.set_node_bypassed(DspNode::Tone, true);
+        assert!(chain.is_node_bypassed(DspNode::Tone));
+        assert!(!chain.is_node_bypassed(DspNode::Limiter));
+        assert!(run(&mut chain) < 0.11);
+        chain.set_node_bypassed(DspNode::Tone, false);
+        assert!(DspNode::ALL
+            .iter()
+            .all(|node| !chain.is_node_bypassed(*node)));
+    }
+
     #[test]
     fn stereo_widener_increases_channel_difference() {
         let widener = StereoWidener::new();
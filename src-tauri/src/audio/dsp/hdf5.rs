@@ -0,0 +1,1042 @@
+//! Read-only subset of HDF5, enough to pull numeric datasets out of SOFA files (which are
+//! netCDF-4, i.e. HDF5 underneath). Covers superblocks v0–v3, v1/v2 object headers,
+//! symbol-table groups and link-message groups (compact or fractal-heap backed), and
+//! compact, contiguous or B-tree chunked layouts with the deflate and shuffle filters.
+
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::ZlibDecoder;
+
+const SIGNATURE: &[u8; 8] = b"\x89HDF\r\n\x1a\n";
+const UNDEFINED_ADDRESS: u64 = u64::MAX;
+
+const MSG_DATASPACE: u16 = 0x0001;
+const MSG_LINK_INFO: u16 = 0x0002;
+const MSG_DATATYPE: u16 = 0x0003;
+const MSG_LINK: u16 = 0x0006;
+const MSG_LAYOUT: u16 = 0x0008;
+const MSG_FILTER_PIPELINE: u16 = 0x000B;
+const MSG_ATTRIBUTE: u16 = 0x000C;
+const MSG_CONTINUATION: u16 = 0x0010;
+const MSG_SYMBOL_TABLE: u16 = 0x0011;
+
+const FILTER_DEFLATE: u16 = 1;
+const FILTER_SHUFFLE: u16 = 2;
+const FILTER_FLETCHER32: u16 = 3;
+
+/// A dataset read into memory, converted to `f64` in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dataset {
+    pub shape: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+/// Bounds-checked little-endian reader over a byte slice.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(count)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| "HDF5 structure runs past the end of the file".to_string())?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), String> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn uint(&mut self, size: usize) -> Result<u64, String> {
+        let bytes = self.bytes(size)?;
+        if size > 8 {
+            return Err(format!("Unsupported HDF5 integer width: {size}"));
+        }
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0_u64, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        self.uint(1).map(|value| value as u8)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        self.uint(2).map(|value| value as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.uint(4).map(|value| value as u32)
+    }
+
+    fn expect(&mut self, signature: &[u8]) -> Result<(), String> {
+        if self.bytes(signature.len())? == signature {
+            Ok(())
+        } else {
+            Err(format!(
+                "Missing HDF5 {} signature",
+                String::from_utf8_lossy(signature)
+            ))
+        }
+    }
+}
+
+/// One header message: its type and raw payload.
+struct Message<'a> {
+    kind: u16,
+    data: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NumberKind {
+    Unsigned,
+    Signed,
+    Float,
+    /// Fixed-length string (only meaningful for attributes).
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Datatype {
+    kind: NumberKind,
+    size: usize,
+    big_endian: bool,
+}
+
+enum Layout<'a> {
+    Compact(&'a [u8]),
+    Contiguous {
+        address: u64,
+        size: u64,
+    },
+    /// v1 B-tree indexed chunks; `chunk` excludes the trailing element-size dimension.
+    ChunkedBTree {
+        address: u64,
+        chunk: Vec<usize>,
+    },
+    SingleChunk {
+        address: u64,
+        size: Option<u64>,
+        chunk: Vec<usize>,
+    },
+}
+
+pub struct Hdf5File {
+    data: Vec<u8>,
+    /// Where the superblock sits; all addresses are relative to it.
+    base: u64,
+    offset_size: usize,
+    length_size: usize,
+    root: u64,
+}
+
+impl Hdf5File {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let data =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::from_bytes(data)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        // The superblock may follow a user block of 512, 1024, 2048… bytes.
+        let mut base = 0_usize;
+        while data.get(base..base + 8) != Some(SIGNATURE.as_slice()) {
+            base = if base == 0 { 512 } else { base * 2 };
+            if base >= data.len() {
+                return Err("Not an HDF5 file".to_string());
+            }
+        }
+        let mut cursor = Cursor::new(&data, base + 8);
+        let version = cursor.u8()?;
+        let (offset_size, length_size, root) = match version {
+            0 | 1 => {
+                cursor.skip(4)?;
+                let offset_size = cursor.u8()? as usize;
+                let length_size = cursor.u8()? as usize;
+                cursor.skip(1 + 4 + 4)?;
+                if version == 1 {
+                    cursor.skip(4)?;
+                }
+                // Base, free-space, end-of-file and driver addresses, then the root
+                // group's symbol table entry (link name offset first).
+                cursor.skip(offset_size * 5)?;
+                (offset_size, length_size, cursor.uint(offset_size)?)
+            }
+            2 | 3 => {
+                let offset_size = cursor.u8()? as usize;
+                let length_size = cursor.u8()? as usize;
+                cursor.skip(1 + offset_size * 3)?;
+                (offset_size, length_size, cursor.uint(offset_size)?)
+            }
+            other => return Err(format!("Unsupported HDF5 superblock version {other}")),
+        };
+        if !matches!(offset_size, 2 | 4 | 8) || !matches!(length_size, 2 | 4 | 8) {
+            return Err("Unsupported HDF5 offset or length size".to_string());
+        }
+        Ok(Self {
+            data,
+            base: base as u64,
+            offset_size,
+            length_size,
+            root,
+        })
+    }
+
+    /// Reads the dataset `name` from the root group.
+    pub fn dataset(&self, name: &str) -> Result<Dataset, String> {
+        let address = self.find(name)?;
+        let messages = self.messages(address)?;
+        let shape = match find_message(&messages, MSG_DATASPACE) {
+            Some(data) => self.parse_dataspace(data)?,
+            None => return Err(format!("{name} has no dataspace")),
+        };
+        let datatype = find_message(&messages, MSG_DATATYPE)
+            .ok_or_else(|| format!("{name} has no datatype"))
+            .and_then(parse_datatype)?;
+        let layout = find_message(&messages, MSG_LAYOUT)
+            .ok_or_else(|| format!("{name} has no data layout"))
+            .and_then(|data| self.parse_layout(data))?;
+        let filters = match find_message(&messages, MSG_FILTER_PIPELINE) {
+            Some(data) => parse_filters(data)?,
+            None => Vec::new(),
+        };
+
+        let count = shape.iter().product::<usize>();
+        let raw = match layout {
+            Layout::Compact(bytes) => bytes.to_vec(),
+            Layout::Contiguous { address, size } => {
+                if address == UNDEFINED_ADDRESS {
+                    vec![0; count * datatype.size]
+                } else {
+                    self.slice(address, size as usize)?.to_vec()
+                }
+            }
+            Layout::ChunkedBTree { address, chunk } => {
+                let mut raw = vec![0_u8; count * datatype.size];
+                if address != UNDEFINED_ADDRESS {
+                    self.read_chunk_tree(
+                        address,
+                        &shape,
+                        &chunk,
+                        datatype.size,
+                        &filters,
+                        &mut raw,
+                    )?;
+                }
+                raw
+            }
+            Layout::SingleChunk {
+                address,
+                size,
+                chunk,
+            } => {
+                let mut raw = vec![0_u8; count * datatype.size];
+                if address != UNDEFINED_ADDRESS {
+                    let stored_size =
+                        size.unwrap_or((chunk.iter().product::<usize>() * datatype.size) as u64);
+                    let stored = self.slice(address, stored_size as usize)?;
+                    let decoded = apply_filters(stored, &filters, datatype.size, 0)?;
+                    copy_chunk(
+                        &decoded,
+                        &vec![0; shape.len()],
+                        &chunk,
+                        &shape,
+                        datatype.size,
+                        &mut raw,
+                    );
+                }
+                raw
+            }
+        };
+        if raw.len() < count * datatype.size {
+            return Err(format!("{name} holds less data than its shape"));
+        }
+        let values = raw
+            .chunks_exact(datatype.size)
+            .take(count)
+            .map(|element| decode_number(element, datatype))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Dataset { shape, values })
+    }
+
+    /// A fixed-length string attribute of the root-group object `object` (e.g. a
+    /// variable's `Type`). Dense attribute storage is not read.
+    pub fn string_attribute(&self, object: &str, attribute: &str) -> Option<String> {
+        let address = self.find(object).ok()?;
+        let messages = self.messages(address).ok()?;
+        messages
+            .iter()
+            .filter(|message| message.kind == MSG_ATTRIBUTE)
+            .find_map(|message| {
+                let (name, datatype, data) = self.parse_attribute(message.data).ok()?;
+                (name == attribute && datatype.kind == NumberKind::Text).then(|| {
+                    String::from_utf8_lossy(data)
+                        .trim_end_matches(['\0', ' '])
+                        .to_string()
+                })
+            })
+    }
+
+    fn slice(&self, address: u64, size: usize) -> Result<&[u8], String> {
+        let start = (self.base + address) as usize;
+        self.data
+            .get(start..start.saturating_add(size))
+            .ok_or_else(|| "HDF5 data runs past the end of the file".to_string())
+    }
+
+    fn cursor(&self, address: u64) -> Result<Cursor<'_>, String> {
+        let start = self.base.saturating_add(address) as usize;
+        if start >= self.data.len() {
+            return Err("HDF5 address points past the end of the file".to_string());
+        }
+        Ok(Cursor::new(&self.data, start))
+    }
+
+    fn offset(&self, cursor: &mut Cursor) -> Result<u64, String> {
+        let value = cursor.uint(self.offset_size)?;
+        let undefined = if self.offset_size == 8 {
+            u64::MAX
+        } else {
+            (1_u64 << (self.offset_size * 8)) - 1
+        };
+        Ok(if value == undefined {
+            UNDEFINED_ADDRESS
+        } else {
+            value
+        })
+    }
+
+    fn length(&self, cursor: &mut Cursor) -> Result<u64, String> {
+        cursor.uint(self.length_size)
+    }
+
+    /// Address of the object header linked as `name` in the root group.
+    fn find(&self, name: &str) -> Result<u64, String> {
+        self.root_links()?
+            .into_iter()
+            .find(|(link, _)| link == name)
+            .map(|(_, address)| address)
+            .ok_or_else(|| format!("SOFA file has no {name} variable"))
+    }
+
+    fn root_links(&self) -> Result<Vec<(String, u64)>, String> {
+        let messages = self.messages(self.root)?;
+        let mut links = Vec::new();
+        for message in &messages {
+            match message.kind {
+                MSG_SYMBOL_TABLE => {
+                    let mut cursor = Cursor::new(message.data, 0);
+                    let tree = self.offset(&mut cursor)?;
+                    let heap = self.offset(&mut cursor)?;
+                    self.symbol_table_links(tree, heap, &mut links, 0)?;
+                }
+                MSG_LINK => {
+                    if let Some(link) = self.parse_link(message.data)? {
+                        links.push(link);
+                    }
+                }
+                MSG_LINK_INFO => {
+                    let mut cursor = Cursor::new(message.data, 0);
+                    cursor.skip(1)?;
+                    if cursor.u8()? & 1 != 0 {
+                        cursor.skip(8)?;
+                    }
+                    let heap = self.offset(&mut cursor)?;
+                    if heap != UNDEFINED_ADDRESS {
+                        for object in self.fractal_heap_objects(heap)? {
+                            if let Some(link) = self.parse_link(object)? {
+                                links.push(link);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(links)
+    }
+
+    /// Walks an old-style group: a v1 B-tree of symbol table nodes naming objects in a
+    /// local heap.
+    fn symbol_table_links(
+        &self,
+        tree: u64,
+        heap: u64,
+        links: &mut Vec<(String, u64)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > 32 {
+            return Err("HDF5 group tree is too deep".to_string());
+        }
+        let names = {
+            let mut cursor = self.cursor(heap)?;
+            cursor.expect(b"HEAP")?;
+            cursor.skip(4)?;
+            self.length(&mut cursor)?;
+            self.length(&mut cursor)?;
+            self.offset(&mut cursor)?
+        };
+        let mut cursor = self.cursor(tree)?;
+        cursor.expect(b"TREE")?;
+        cursor.skip(1)?;
+        let level = cursor.u8()?;
+        let entries = cursor.u16()? as usize;
+        cursor.skip(self.offset_size * 2)?;
+        for _ in 0..entries {
+            self.length(&mut cursor)?;
+            let child = self.offset(&mut cursor)?;
+            if level > 0 {
+                self.symbol_table_links(child, heap, links, depth + 1)?;
+                continue;
+            }
+            let mut node = self.cursor(child)?;
+            node.expect(b"SNOD")?;
+            node.skip(2)?;
+            let symbols = node.u16()?;
+            for _ in 0..symbols {
+                let name_offset = self.length(&mut node)?;
+                let object = self.offset(&mut node)?;
+                node.skip(4 + 4 + 16)?;
+                let name = self.cursor(names + name_offset)?;
+                let raw = &name.data[name.pos..];
+                let end = raw.iter().position(|byte| *byte == 0).unwrap_or(raw.len());
+                links.push((String::from_utf8_lossy(&raw[..end]).into_owned(), object));
+            }
+        }
+        Ok(())
+    }
+
+    /// Hard links only; soft and external links are ignored.
+    fn parse_link(&self, data: &[u8]) -> Result<Option<(String, u64)>, String> {
+        let mut cursor = Cursor::new(data, 0);
+        if cursor.u8()? != 1 {
+            return Err("Unsupported HDF5 link message version".to_string());
+        }
+        let flags = cursor.u8()?;
+        let link_type = if flags & 0x08 != 0 { cursor.u8()? } else { 0 };
+        if flags & 0x04 != 0 {
+            cursor.skip(8)?;
+        }
+        if flags & 0x10 != 0 {
+            cursor.skip(1)?;
+        }
+        let name_length = cursor.uint(1 << (flags & 0x03))? as usize;
+        let name = String::from_utf8_lossy(cursor.bytes(name_length)?).into_owned();
+        if link_type != 0 {
+            return Ok(None);
+        }
+        Ok(Some((name, self.offset(&mut cursor)?)))
+    }
+
+    /// Every managed object in a fractal heap, read back to back from its direct blocks
+    /// until the heap's object count is reached. Only used for link messages, which are
+    /// self-delimiting.
+    fn fractal_heap_objects(&self, address: u64) -> Result<Vec<&[u8]>, String> {
+        let mut cursor = self.cursor(address)?;
+        cursor.expect(b"FRHP")?;
+        cursor.skip(1)?;
+        let _heap_id_length = cursor.u16()?;
+        let filters_length = cursor.u16()?;
+        let flags = cursor.u8()?;
+        cursor.skip(4)?;
+        self.length(&mut cursor)?;
+        self.offset(&mut cursor)?;
+        self.length(&mut cursor)?;
+        self.offset(&mut cursor)?;
+        // Managed space, allocated space, iterator offset, then the managed object count;
+        // the huge and tiny object statistics follow.
+        cursor.skip(self.length_size * 3)?;
+        let managed_objects = self.length(&mut cursor)? as usize;
+        cursor.skip(self.length_size * 4)?;
+        let table_width = cursor.u16()? as usize;
+        let start_block = self.length(&mut cursor)?;
+        let max_direct_block = self.length(&mut cursor)?;
+        let max_heap_bits = cursor.u16()? as usize;
+        cursor.skip(2)?;
+        let root = self.offset(&mut cursor)?;
+        let root_rows = cursor.u16()? as usize;
+        if filters_length != 0 {
+            return Err("Filtered HDF5 fractal heaps are not supported".to_string());
+        }
+        let heap = FractalHeap {
+            table_width,
+            start_block,
+            max_direct_block,
+            block_offset_size: max_heap_bits.div_ceil(8),
+            checksummed: flags & 0x02 != 0,
+        };
+
+        let mut blocks = Vec::new();
+        if root == UNDEFINED_ADDRESS {
+            return Ok(Vec::new());
+        }
+        if root_rows == 0 {
+            blocks.push((root, start_block));
+        } else {
+            self.indirect_blocks(&heap, root, root_rows, &mut blocks, 0)?;
+        }
+
+        let mut objects = Vec::new();
+        for (block, size) in blocks {
+            let mut cursor = self.cursor(block)?;
+            cursor.expect(b"FHDB")?;
+            cursor.skip(1 + self.offset_size + heap.block_offset_size)?;
+            if heap.checksummed {
+                cursor.skip(4)?;
+            }
+            let end = ((self.base + block) as usize + size as usize).min(self.data.len());
+            let mut pos = cursor.pos;
+            // Objects are packed from the block start.
+            while objects.len() < managed_objects && pos < end && self.data[pos] == 1 {
+                let length = link_length(&self.data[pos..end], self.offset_size)?;
+                objects.push(&self.data[pos..pos + length]);
+                pos += length;
+            }
+        }
+        Ok(objects)
+    }
+
+    fn indirect_blocks(
+        &self,
+        heap: &FractalHeap,
+        address: u64,
+        rows: usize,
+        blocks: &mut Vec<(u64, u64)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > 16 {
+            return Err("HDF5 fractal heap is too deep".to_string());
+        }
+        let mut cursor = self.cursor(address)?;
+        cursor.expect(b"FHIB")?;
+        cursor.skip(1 + self.offset_size + heap.block_offset_size)?;
+        for row in 0..rows {
+            let size = heap.row_block_size(row);
+            for _ in 0..heap.table_width {
+                let child = self.offset(&mut cursor)?;
+                if child == UNDEFINED_ADDRESS {
+                    continue;
+                }
+                if size <= heap.max_direct_block {
+                    blocks.push((child, size));
+                } else {
+                    let child_rows = heap.rows_for_indirect(size);
+                    self.indirect_blocks(heap, child, child_rows, blocks, depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Object header messages (v1 or v2 header), following continuation blocks.
+    fn messages(&self, address: u64) -> Result<Vec<Message<'_>>, String> {
+        let mut messages = Vec::new();
+        let mut cursor = self.cursor(address)?;
+        let mut pending: Vec<(u64, u64)> = Vec::new();
+        if cursor.data.get(cursor.pos..cursor.pos + 4) == Some(b"OHDR".as_slice()) {
+            cursor.skip(4)?;
+            if cursor.u8()? != 2 {
+                return Err("Unsupported HDF5 object header version".to_string());
+            }
+            let flags = cursor.u8()?;
+            if flags & 0x20 != 0 {
+                cursor.skip(16)?;
+            }
+            if flags & 0x10 != 0 {
+                cursor.skip(4)?;
+            }
+            let size = cursor.uint(1 << (flags & 0x03))? as usize;
+            let start = cursor.pos;
+            self.v2_messages(
+                &cursor.data[start..start + size.min(cursor.data.len() - start)],
+                flags,
+                &mut messages,
+                &mut pending,
+            )?;
+            let mut hops = 0;
+            while let Some((block, length)) = pending.pop() {
+                hops += 1;
+                if hops > 256 {
+                    return Err("Too many HDF5 header continuations".to_string());
+                }
+                let chunk = self.slice(block, length as usize)?;
+                if chunk.get(..4) != Some(b"OCHK".as_slice()) || chunk.len() < 8 {
+                    return Err("Missing HDF5 OCHK signature".to_string());
+                }
+                self.v2_messages(
+                    &chunk[4..chunk.len() - 4],
+                    flags,
+                    &mut messages,
+                    &mut pending,
+                )?;
+            }
+        } else {
+            if cursor.u8()? != 1 {
+                return Err("Unsupported HDF5 object header version".to_string());
+            }
+            cursor.skip(1)?;
+            let count = cursor.u16()? as usize;
+            cursor.skip(4)?;
+            let size = cursor.u32()? as usize;
+            // The v1 prefix is padded to 16 bytes.
+            cursor.skip(4)?;
+            let start = cursor.pos;
+            pending.push(((start as u64) - self.base, size as u64));
+            let mut hops = 0;
+            while let Some((block, length)) = pending.pop() {
+                hops += 1;
+                if hops > 256 || messages.len() > count {
+                    break;
+                }
+                let chunk = self.slice(block, length as usize)?;
+                let mut inner = Cursor::new(chunk, 0);
+                while inner.pos + 8 <= chunk.len() {
+                    let kind = inner.u16()?;
+                    let size = inner.u16()? as usize;
+                    inner.skip(4)?;
+                    let data = inner.bytes(size)?;
+                    if kind == MSG_CONTINUATION {
+                        pending.push(self.continuation(data)?);
+                    }
+                    messages.push(Message { kind, data });
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    fn v2_messages<'a>(
+        &self,
+        chunk: &'a [u8],
+        flags: u8,
+        messages: &mut Vec<Message<'a>>,
+        pending: &mut Vec<(u64, u64)>,
+    ) -> Result<(), String> {
+        let header = if flags & 0x04 != 0 { 6 } else { 4 };
+        let mut cursor = Cursor::new(chunk, 0);
+        // Anything shorter than a message header at the end is a gap.
+        while cursor.pos + header <= chunk.len() {
+            let kind = cursor.u8()? as u16;
+            let size = cursor.u16()? as usize;
+            cursor.skip(header - 3)?;
+            let data = cursor.bytes(size)?;
+            if kind == MSG_CONTINUATION {
+                pending.push(self.continuation(data)?);
+            }
+            messages.push(Message { kind, data });
+        }
+        Ok(())
+    }
+
+    fn continuation(&self, data: &[u8]) -> Result<(u64, u64), String> {
+        let mut cursor = Cursor::new(data, 0);
+        Ok((self.offset(&mut cursor)?, self.length(&mut cursor)?))
+    }
+
+    fn parse_dataspace(&self, data: &[u8]) -> Result<Vec<usize>, String> {
+        let mut cursor = Cursor::new(data, 0);
+        let version = cursor.u8()?;
+        let rank = cursor.u8()? as usize;
+        cursor.skip(1)?;
+        match version {
+            1 => cursor.skip(5)?,
+            2 => cursor.skip(1)?,
+            other => return Err(format!("Unsupported HDF5 dataspace version {other}")),
+        }
+        (0..rank)
+            .map(|_| self.length(&mut cursor).map(|dim| dim as usize))
+            .collect()
+    }
+
+    fn parse_layout<'a>(&self, data: &'a [u8]) -> Result<Layout<'a>, String> {
+        let mut cursor = Cursor::new(data, 0);
+        let version = cursor.u8()?;
+        if !matches!(version, 3 | 4) {
+            return Err(format!("Unsupported HDF5 layout version {version}"));
+        }
+        match cursor.u8()? {
+            0 => {
+                let size = cursor.u16()? as usize;
+                Ok(Layout::Compact(cursor.bytes(size)?))
+            }
+            1 => Ok(Layout::Contiguous {
+                address: self.offset(&mut cursor)?,
+                size: self.length(&mut cursor)?,
+            }),
+            2 if version == 3 => {
+                let rank = cursor.u8()? as usize;
+                let address = self.offset(&mut cursor)?;
+                let mut chunk = (0..rank)
+                    .map(|_| cursor.u32().map(|dim| dim as usize))
+                    .collect::<Result<Vec<_>, _>>()?;
+                chunk.pop();
+                Ok(Layout::ChunkedBTree { address, chunk })
+            }
+            2 => {
+                let flags = cursor.u8()?;
+                let rank = cursor.u8()? as usize;
+                let width = cursor.u8()? as usize;
+                let mut chunk = (0..rank)
+                    .map(|_| cursor.uint(width).map(|dim| dim as usize))
+                    .collect::<Result<Vec<_>, _>>()?;
+                chunk.pop();
+                match cursor.u8()? {
+                    1 => {
+                        let size = if flags & 0x02 != 0 {
+                            let size = self.length(&mut cursor)?;
+                            cursor.skip(4)?;
+                            Some(size)
+                        } else {
+                            None
+                        };
+                        Ok(Layout::SingleChunk {
+                            address: self.offset(&mut cursor)?,
+                            size,
+                            chunk,
+                        })
+                    }
+                    other => Err(format!("Unsupported HDF5 chunk index type {other}")),
+                }
+            }
+            other => Err(format!("Unsupported HDF5 layout class {other}")),
+        }
+    }
+
+    fn parse_attribute<'a>(&self, data: &'a [u8]) -> Result<(String, Datatype, &'a [u8]), String> {
+        let mut cursor = Cursor::new(data, 0);
+        let version = cursor.u8()?;
+        cursor.skip(1)?;
+        let name_size = cursor.u16()? as usize;
+        let datatype_size = cursor.u16()? as usize;
+        let dataspace_size = cursor.u16()? as usize;
+        if version == 3 {
+            cursor.skip(1)?;
+        }
+        let padded = |size: usize| {
+            if version == 1 {
+                size.div_ceil(8) * 8
+            } else {
+                size
+            }
+        };
+        let name = cursor.bytes(padded(name_size))?;
+        let name = String::from_utf8_lossy(&name[..name_size])
+            .trim_end_matches('\0')
+            .to_string();
+        let datatype = parse_datatype(cursor.bytes(padded(datatype_size))?)?;
+        let shape = self.parse_dataspace(cursor.bytes(padded(dataspace_size))?)?;
+        let length = shape.iter().product::<usize>() * datatype.size;
+        Ok((name, datatype, cursor.bytes(length)?))
+    }
+
+    fn read_chunk_tree(
+        &self,
+        address: u64,
+        shape: &[usize],
+        chunk: &[usize],
+        element_size: usize,
+        filters: &[Filter],
+        out: &mut [u8],
+    ) -> Result<(), String> {
+        let mut pending = vec![(address, 0_usize)];
+        while let Some((node, depth)) = pending.pop() {
+            if depth > 32 {
+                return Err("HDF5 chunk tree is too deep".to_string());
+            }
+            let mut cursor = self.cursor(node)?;
+            cursor.expect(b"TREE")?;
+            if cursor.u8()? != 1 {
+                return Err("Unexpected HDF5 B-tree node type".to_string());
+            }
+            let level = cursor.u8()?;
+            let entries = cursor.u16()? as usize;
+            cursor.skip(self.offset_size * 2)?;
+            for _ in 0..entries {
+                let size = cursor.u32()? as usize;
+                let filter_mask = cursor.u32()?;
+                let offsets = (0..=chunk.len())
+                    .map(|_| cursor.uint(8).map(|offset| offset as usize))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let child = self.offset(&mut cursor)?;
+                if level > 0 {
+                    pending.push((child, depth + 1));
+                    continue;
+                }
+                let stored = self.slice(child, size)?;
+                let decoded = apply_filters(stored, filters, element_size, filter_mask)?;
+                copy_chunk(
+                    &decoded,
+                    &offsets[..chunk.len()],
+                    chunk,
+                    shape,
+                    element_size,
+                    out,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+struct FractalHeap {
+    table_width: usize,
+    start_block: u64,
+    max_direct_block: u64,
+    block_offset_size: usize,
+    checksummed: bool,
+}
+
+impl FractalHeap {
+    fn row_block_size(&self, row: usize) -> u64 {
+        if row == 0 {
+            self.start_block
+        } else {
+            self.start_block << (row - 1)
+        }
+    }
+
+    /// Rows in an indirect block whose blocks total `size` bytes.
+    fn rows_for_indirect(&self, size: u64) -> usize {
+        let per_row = self.start_block * self.table_width as u64;
+        (size / per_row.max(1)).max(1).ilog2() as usize + 1
+    }
+}
+
+/// Length of the link message starting at `data[0]`.
+fn link_length(data: &[u8], offset_size: usize) -> Result<usize, String> {
+    let mut cursor = Cursor::new(data, 0);
+    cursor.skip(1)?;
+    let flags = cursor.u8()?;
+    let link_type = if flags & 0x08 != 0 { cursor.u8()? } else { 0 };
+    if flags & 0x04 != 0 {
+        cursor.skip(8)?;
+    }
+    if flags & 0x10 != 0 {
+        cursor.skip(1)?;
+    }
+    let name_length = cursor.uint(1 << (flags & 0x03))? as usize;
+    cursor.skip(name_length)?;
+    match link_type {
+        0 => cursor.skip(offset_size)?,
+        _ => {
+            let length = cursor.u16()? as usize;
+            cursor.skip(length)?;
+        }
+    }
+    Ok(cursor.pos)
+}
+
+fn find_message<'a>(messages: &[Message<'a>], kind: u16) -> Option<&'a [u8]> {
+    messages
+        .iter()
+        .find(|message| message.kind == kind)
+        .map(|message| message.data)
+}
+
+fn parse_datatype(data: &[u8]) -> Result<Datatype, String> {
+    let mut cursor = Cursor::new(data, 0);
+    let class = cursor.u8()? & 0x0F;
+    let bits = cursor.u8()?;
+    cursor.skip(2)?;
+    let size = cursor.u32()? as usize;
+    let kind = match class {
+        0 if bits & 0x08 != 0 => NumberKind::Signed,
+        0 => NumberKind::Unsigned,
+        1 => NumberKind::Float,
+        3 => NumberKind::Text,
+        other => return Err(format!("Unsupported HDF5 datatype class {other}")),
+    };
+    if kind == NumberKind::Float && !matches!(size, 4 | 8) {
+        return Err(format!("Unsupported HDF5 float width {size}"));
+    }
+    if size == 0 {
+        return Err("Zero-sized HDF5 datatype".to_string());
+    }
+    Ok(Datatype {
+        kind,
+        size,
+        big_endian: class != 3 && bits & 0x01 != 0,
+    })
+}
+
+fn decode_number(element: &[u8], datatype: Datatype) -> Result<f64, String> {
+    let mut bytes = [0_u8; 8];
+    let size = datatype.size.min(8);
+    bytes[..size].copy_from_slice(&element[..size]);
+    if datatype.big_endian {
+        bytes[..size].reverse();
+    }
+    let raw = u64::from_le_bytes(bytes);
+    Ok(match (datatype.kind, size) {
+        (NumberKind::Float, 4) => f32::from_bits(raw as u32) as f64,
+        (NumberKind::Float, _) => f64::from_bits(raw),
+        (NumberKind::Unsigned, _) => raw as f64,
+        (NumberKind::Signed, 8) => raw as i64 as f64,
+        (NumberKind::Signed, size) => {
+            let shift = 64 - size * 8;
+            ((raw << shift) as i64 >> shift) as f64
+        }
+        (NumberKind::Text, _) => return Err("Expected a numeric HDF5 dataset".to_string()),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Filter {
+    id: u16,
+    optional: bool,
+}
+
+fn parse_filters(data: &[u8]) -> Result<Vec<Filter>, String> {
+    let mut cursor = Cursor::new(data, 0);
+    let version = cursor.u8()?;
+    let count = cursor.u8()? as usize;
+    if version == 1 {
+        cursor.skip(6)?;
+    }
+    let mut filters = Vec::with_capacity(count);
+    for _ in 0..count {
+        let id = cursor.u16()?;
+        let name_length = if version == 1 || id >= 256 {
+            cursor.u16()? as usize
+        } else {
+            0
+        };
+        let flags = cursor.u16()?;
+        let values = cursor.u16()? as usize;
+        cursor.skip(if version == 1 {
+            name_length.div_ceil(8) * 8
+        } else {
+            name_length
+        })?;
+        cursor.skip(values * 4)?;
+        if version == 1 && values % 2 == 1 {
+            cursor.skip(4)?;
+        }
+        filters.push(Filter {
+            id,
+            optional: flags & 0x01 != 0,
+        });
+    }
+    Ok(filters)
+}
+
+/// Undoes the filter pipeline (last filter first). Bit `i` of `mask` marks filter `i` as
+/// skipped for this chunk.
+fn apply_filters(
+    stored: &[u8],
+    filters: &[Filter],
+    element_size: usize,
+    mask: u32,
+) -> Result<Vec<u8>, String> {
+    let mut data = stored.to_vec();
+    for (index, filter) in filters.iter().enumerate().rev() {
+        if index < 32 && mask & (1 << index) != 0 {
+            continue;
+        }
+        data = match filter.id {
+            FILTER_DEFLATE => {
+                let mut out = Vec::with_capacity(data.len() * 4);
+                ZlibDecoder::new(data.as_slice())
+                    .read_to_end(&mut out)
+                    .map_err(|e| format!("Failed to inflate HDF5 chunk: {e}"))?;
+                out
+            }
+            FILTER_SHUFFLE => unshuffle(&data, element_size),
+            FILTER_FLETCHER32 => data[..data.len().saturating_sub(4)].to_vec(),
+            _ if filter.optional => data,
+            other => return Err(format!("Unsupported HDF5 filter {other}")),
+        };
+    }
+    Ok(data)
+}
+
+/// Reverses the shuffle filter, which stores byte 0 of every element, then byte 1, …
+fn unshuffle(data: &[u8], element_size: usize) -> Vec<u8> {
+    let count = data.len() / element_size.max(1);
+    if element_size <= 1 || count == 0 {
+        return data.to_vec();
+    }
+    let mut out = data.to_vec();
+    for (byte, plane) in data.chunks_exact(count).take(element_size).enumerate() {
+        for (element, value) in plane.iter().enumerate() {
+            out[element * element_size + byte] = *value;
+        }
+    }
+    out
+}
+
+/// Copies one decoded chunk starting at element `origin` into the full row-major array,
+/// clipping chunks that overhang the dataset edge.
+fn copy_chunk(
+    chunk_data: &[u8],
+    origin: &[usize],
+    chunk: &[usize],
+    shape: &[usize],
+    element_size: usize,
+    out: &mut [u8],
+) {
+    let rank = shape.len();
+    if rank == 0 {
+        let length = element_size.min(chunk_data.len()).min(out.len());
+        out[..length].copy_from_slice(&chunk_data[..length]);
+        return;
+    }
+    let chunk_count = chunk.iter().product::<usize>();
+    let mut index = vec![0_usize; rank];
+    for position in 0..chunk_count {
+        let mut remainder = position;
+        for axis in (0..rank).rev() {
+            index[axis] = remainder % chunk[axis];
+            remainder /= chunk[axis];
+        }
+        let mut target = 0_usize;
+        let mut inside = true;
+        for axis in 0..rank {
+            let coordinate = origin[axis] + index[axis];
+            if coordinate >= shape[axis] {
+                inside = false;
+                break;
+            }
+            target = target * shape[axis] + coordinate;
+        }
+        let source = position * element_size;
+        if !inside || source + element_size > chunk_data.len() {
+            continue;
+        }
+        let target = target * element_size;
+        if target + element_size <= out.len() {
+            out[target..target + element_size]
+                .copy_from_slice(&chunk_data[source..source + element_size]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_chunk, unshuffle};
+
+    #[test]
+    fn reassembles_shuffled_edge_chunks() {
+        // Two 2-byte elements shuffled as [lo0, lo1, hi0, hi1].
+        assert_eq!(unshuffle(&[1, 2, 10, 20], 2), vec![1, 10, 2, 20]);
+
+        // A 2×2 chunk at (2, 2) of a 3×3 array of bytes keeps only its top-left cell.
+        let mut out = vec![0_u8; 9];
+        copy_chunk(&[7, 8, 9, 10], &[2, 2], &[2, 2], &[3, 3], 1, &mut out);
+        assert_eq!(out, vec![0, 0, 0, 0, 0, 0, 0, 0, 7]);
+        copy_chunk(&[1, 2, 3, 4], &[0, 0], &[2, 2], &[3, 3], 1, &mut out);
+        assert_eq!(out, vec![1, 2, 0, 3, 4, 0, 0, 0, 7]);
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use super::filters::{BiquadFilter, EqBandSettings, FilterType};
+
+/// Band the centre cancellation works on: below it are the kick and bass, above it the
+/// cymbals, which are usually panned centre too but are not voice.
+const VOICE_LOW_HZ: f32 = 120.0;
+const VOICE_HIGH_HZ: f32 = 7_000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KaraokeSettings {
+    pub enabled: bool,
+    /// How much of the voice is left, 0 (removed) to 1 (untouched).
+    pub vocal_level: f32,
+}
+
+impl Default for KaraokeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            vocal_level: 0.0,
+        }
+    }
+}
+
+impl KaraokeSettings {
+    /// Share of the voice to take out of the mix; 0 while karaoke is off.
+    pub fn cut(&self) -> f32 {
+        if self.enabled {
+            1.0 - self.vocal_level.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Karaoke without separated stems: the voice band of what the front pair has in
+/// common (the centre of the stereo image, where vocals are mixed) is subtracted from
+/// both sides. Side content and the lows and highs of the centre stay.
+pub struct CentreCanceller {
+    high_pass: BiquadFilter,
+    low_pass: BiquadFilter,
+}
+
+impl CentreCanceller {
+    pub fn new(sample_rate: f32) -> Self {
+        let sr = sample_rate.max(8_000.0);
+        let band = |filter_type, frequency| {
+            let mut filter = BiquadFilter::new();
+            filter.set_band(
+                sr,
+                &EqBandSettings {
+                    frequency,
+                    gain_db: 0.0,
+                    q_factor: std::f32::consts::FRAC_1_SQRT_2,
+                    filter_type,
+                },
+            );
+            filter
+        };
+        Self {
+            high_pass: band(FilterType::HighPass, VOICE_LOW_HZ),
+            low_pass: band(FilterType::LowPass, VOICE_HIGH_HZ.min(sr * 0.45)),
+        }
+    }
+
+    /// Takes `cut` of the centre's voice band out of the first two channels of an
+    /// interleaved frame. Mono frames pass through, having no centre to find.
+    pub fn process_frame(&mut self, frame: &mut [f32], cut: f32) {
+        let [left, right, ..] = frame else {
+            return;
+        };
+        let mid = 0.5 * (*left + *right);
+        let voice = self
+            .low_pass
+            .process_sample(self.high_pass.process_sample(mid));
+        *left -= cut * voice;
+        *right -= cut * voice;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CentreCanceller, KaraokeSettings};
+
+    fn peak(canceller: &mut CentreCanceller, frequency: f32, pan: f32) -> f32 {
+        let mut peak = 0.0_f32;
+        for n in 0..48_000 {
+            let sample = (2.0 * std::f32::consts::PI * frequency * n as f32 / 48_000.0).sin();
+            let mut frame = [0.5 * sample * (1.0 - pan), 0.5 * sample * (1.0 + pan)];
+            canceller.process_frame(&mut frame, 1.0);
+            if n > 24_000 {
+                peak = peak.max(frame[0].abs().max(frame[1].abs()));
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn centred_voice_band_is_cancelled() {
+        let settings = KaraokeSettings {
+            enabled: true,
+            vocal_level: 0.25,
+        };
+        assert_eq!(settings.cut(), 0.75);
+        assert_eq!(KaraokeSettings::default().cut(), 0.0);
+
+        let mut canceller = CentreCanceller::new(48_000.0);
+        assert!(peak(&mut canceller, 1_000.0, 0.0) < 0.05);
+        // Bass in the centre and a voice panned hard left both stay.
+        assert!(peak(&mut canceller, 40.0, 0.0) > 0.4);
+        assert!(peak(&mut canceller, 1_000.0, -1.0) > 0.5);
+    }
+}
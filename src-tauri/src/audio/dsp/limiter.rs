@@ -0,0 +1,308 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Longest lookahead; buffers are sized for it so changing lookahead never allocates.
+const MAX_LOOKAHEAD_MS: f32 = 5.0;
+
+/// Adjustable limiter parameters, in dBFS and milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LimiterSettings {
+    /// Peak level where gain reduction starts.
+    pub threshold_db: f32,
+    /// How long gain takes to recover after a peak.
+    pub release_ms: f32,
+    /// Output peaks never exceed this.
+    pub ceiling_db: f32,
+    /// How far ahead peaks are seen, which is also the latency added. At 0 the limiter
+    /// reacts within the sample, like the fixed soft limiter it replaced.
+    pub lookahead_ms: f32,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: -0.1,
+            release_ms: 50.0,
+            ceiling_db: 0.0,
+            lookahead_ms: 0.0,
+        }
+    }
+}
+
+/// Minimum of the last `window` values, kept as a monotonic deque in a fixed ring.
+struct SlidingMinimum {
+    entries: Vec<(u64, f32)>,
+    window: usize,
+    head: usize,
+    len: usize,
+    count: u64,
+}
+
+impl SlidingMinimum {
+    fn new(max_window: usize) -> Self {
+        Self {
+            entries: vec![(0, 1.0); max_window + 1],
+            window: 1,
+            head: 0,
+            len: 0,
+            count: 0,
+        }
+    }
+
+    fn clear(&mut self, window: usize) {
+        self.window = window.clamp(1, self.entries.len() - 1);
+        self.head = 0;
+        self.len = 0;
+        self.count = 0;
+    }
+
+    fn push(&mut self, value: f32) -> f32 {
+        let capacity = self.entries.len();
+        while self.len > 0 && self.entries[(self.head + self.len - 1) % capacity].1 >= value {
+            self.len -= 1;
+        }
+        self.entries[(self.head + self.len) % capacity] = (self.count, value);
+        self.len += 1;
+        while self.entries[self.head].0 + self.window as u64 <= self.count {
+            self.head = (self.head + 1) % capacity;
+            self.len -= 1;
+        }
+        self.count += 1;
+        self.entries[self.head].1
+    }
+}
+
+/// Brickwall limiter with lookahead: the gain needed for each peak is held over the
+/// lookahead window and ramped in with a moving average, so the delayed signal is
+/// already turned down when the peak arrives instead of being clipped. Gain is shared
+/// by both channels.
+pub struct LookaheadLimiter {
+    threshold_bits: AtomicU32,
+    release_bits: AtomicU32,
+    ceiling_bits: AtomicU32,
+    lookahead_bits: AtomicU32,
+    /// Linear gain currently applied, published by the audio thread.
+    gain_bits: AtomicU32,
+    needs_update: AtomicBool,
+    sample_rate: f32,
+    threshold: f32,
+    ceiling: f32,
+    release_coeff: f32,
+    minimum: SlidingMinimum,
+    envelope: f32,
+    /// Lookahead frames plus one; the delay, ramp and peak hold all span this many.
+    length: usize,
+    delay: Vec<(f32, f32)>,
+    ramp: Vec<f32>,
+    ramp_sum: f64,
+    position: usize,
+}
+
+impl LookaheadLimiter {
+    pub fn new(sample_rate: f32) -> Self {
+        let defaults = LimiterSettings::default();
+        let mut limiter = Self {
+            threshold_bits: AtomicU32::new(defaults.threshold_db.to_bits()),
+            release_bits: AtomicU32::new(defaults.release_ms.to_bits()),
+            ceiling_bits: AtomicU32::new(defaults.ceiling_db.to_bits()),
+            lookahead_bits: AtomicU32::new(defaults.lookahead_ms.to_bits()),
+            gain_bits: AtomicU32::new(1.0_f32.to_bits()),
+            needs_update: AtomicBool::new(true),
+            sample_rate: 0.0,
+            threshold: 1.0,
+            ceiling: 1.0,
+            release_coeff: 0.0,
+            minimum: SlidingMinimum::new(1),
+            envelope: 1.0,
+            length: 1,
+            delay: Vec::new(),
+            ramp: Vec::new(),
+            ramp_sum: 0.0,
+            position: 0,
+        };
+        limiter.set_sample_rate(sample_rate);
+        limiter
+    }
+
+    /// Threshold is clamped to -24..0 dBFS and never above the ceiling, release to
+    /// 1..1000 ms, ceiling to -12..0 dBFS and lookahead to 0..5 ms.
+    pub fn set_params(&self, settings: LimiterSettings) -> Result<(), String> {
+        let LimiterSettings {
+            threshold_db,
+            release_ms,
+            ceiling_db,
+            lookahead_ms,
+        } = settings;
+        if ![threshold_db, release_ms, ceiling_db, lookahead_ms]
+            .iter()
+            .all(|value| value.is_finite())
+        {
+            return Err("Limiter parameters must be finite".to_string());
+        }
+        let ceiling_db = ceiling_db.clamp(-12.0, 0.0);
+        self.threshold_bits.store(
+            threshold_db.clamp(-24.0, 0.0).min(ceiling_db).to_bits(),
+            Ordering::SeqCst,
+        );
+        self.release_bits
+            .store(release_ms.clamp(1.0, 1_000.0).to_bits(), Ordering::SeqCst);
+        self.ceiling_bits
+            .store(ceiling_db.to_bits(), Ordering::SeqCst);
+        self.lookahead_bits.store(
+            lookahead_ms.clamp(0.0, MAX_LOOKAHEAD_MS).to_bits(),
+            Ordering::SeqCst,
+        );
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn settings(&self) -> LimiterSettings {
+        LimiterSettings {
+            threshold_db: f32::from_bits(self.threshold_bits.load(Ordering::Relaxed)),
+            release_ms: f32::from_bits(self.release_bits.load(Ordering::Relaxed)),
+            ceiling_db: f32::from_bits(self.ceiling_bits.load(Ordering::Relaxed)),
+            lookahead_ms: f32::from_bits(self.lookahead_bits.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Current gain reduction in dB (0 when the limiter is idle).
+    pub fn gain_reduction_db(&self) -> f32 {
+        let gain = f32::from_bits(self.gain_bits.load(Ordering::Relaxed));
+        (-20.0 * gain.max(1.0e-6).log10()).max(0.0)
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() > f32::EPSILON {
+            self.sample_rate = sr;
+            let max_length = lookahead_frames(MAX_LOOKAHEAD_MS, sr) + 1;
+            self.minimum = SlidingMinimum::new(max_length);
+            self.delay = vec![(0.0, 0.0); max_length];
+            self.ramp = vec![1.0; max_length];
+            // Forces the buffers to be cleared on the next frame.
+            self.length = 0;
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if self.needs_update.swap(false, Ordering::SeqCst) {
+            self.recalculate();
+        }
+
+        let peak = left.abs().max(right.abs());
+        let required = if peak <= self.threshold {
+            1.0
+        } else {
+            // Same soft curve the fixed limiter used: peaks approach the ceiling
+            // asymptotically once past the threshold.
+            let over = peak - self.threshold;
+            let range = (self.ceiling - self.threshold).max(f32::EPSILON);
+            (self.threshold + over / (1.0 + over / range)) / peak
+        };
+        let held = self.minimum.push(required);
+        self.envelope = if held < self.envelope {
+            held
+        } else {
+            held + (self.envelope - held) * self.release_coeff
+        };
+
+        self.ramp_sum += f64::from(self.envelope) - f64::from(self.ramp[self.position]);
+        self.ramp[self.position] = self.envelope;
+        let gain = (self.ramp_sum / self.length as f64) as f32;
+        self.delay[self.position] = (left, right);
+        self.position = (self.position + 1) % self.length;
+        // The slot after the newest holds the oldest frame, `length - 1` frames back.
+        let (delayed_left, delayed_right) = self.delay[self.position];
+        self.gain_bits.store(gain.to_bits(), Ordering::Relaxed);
+
+        (
+            (delayed_left * gain).clamp(-self.ceiling, self.ceiling),
+            (delayed_right * gain).clamp(-self.ceiling, self.ceiling),
+        )
+    }
+
+    fn recalculate(&mut self) {
+        let settings = self.settings();
+        self.threshold = 10.0_f32.powf(settings.threshold_db / 20.0);
+        self.ceiling = 10.0_f32.powf(settings.ceiling_db / 20.0);
+        self.release_coeff = (-1.0 / (settings.release_ms * 0.001 * self.sample_rate)).exp();
+
+        // The peak hold spans as many frames as the ramp, so the ramp has fully reached
+        // a peak's gain by the time that peak leaves the delay.
+        let length =
+            (lookahead_frames(settings.lookahead_ms, self.sample_rate) + 1).min(self.delay.len());
+        if length != self.length {
+            self.length = length;
+            self.minimum.clear(length);
+            self.envelope = 1.0;
+            self.delay.fill((0.0, 0.0));
+            self.ramp.fill(1.0);
+            self.ramp_sum = length as f64;
+            self.position = 0;
+        }
+    }
+}
+
+fn lookahead_frames(lookahead_ms: f32, sample_rate: f32) -> usize {
+    (lookahead_ms * 0.001 * sample_rate).round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LimiterSettings, LookaheadLimiter};
+
+    #[test]
+    fn limiter_caps_extreme_levels_before_they_arrive() {
+        let mut limiter = LookaheadLimiter::new(48_000.0);
+        // Without lookahead, quiet audio passes untouched and without delay.
+        assert_eq!(limiter.process_stereo_frame(0.25, -0.25), (0.25, -0.25));
+        let (left, right) = limiter.process_stereo_frame(2.0, -2.0);
+        assert!(left <= 1.0 && right >= -1.0);
+
+        limiter
+            .set_params(LimiterSettings {
+                lookahead_ms: 2.0,
+                ..LimiterSettings::default()
+            })
+            .expect("params");
+        let outputs = (0..4_800)
+            .map(|n| {
+                let sample = if n < 2_400 { 0.1 } else { 2.0 };
+                limiter.process_stereo_frame(sample, -sample)
+            })
+            .collect::<Vec<_>>();
+        assert!(outputs
+            .iter()
+            .all(|(left, right)| *left <= 1.0 && *right >= -1.0));
+        // Delayed by the 96-frame lookahead.
+        assert_eq!(outputs[95], (0.0, 0.0));
+        assert_eq!(outputs[96], (0.1, -0.1));
+        // The step is met by a ramp, not a clip: the limit holds just under the ceiling.
+        let step = outputs[2_400 + 96].0;
+        assert!(step > 0.9 && step < 1.0, "{step}");
+        assert!(limiter.gain_reduction_db() > 6.0);
+
+        limiter
+            .set_params(LimiterSettings {
+                threshold_db: -6.0,
+                release_ms: 20.0,
+                ceiling_db: -3.0,
+                lookahead_ms: 2.0,
+            })
+            .expect("params");
+        let ceiling = 10.0_f32.powf(-3.0 / 20.0);
+        for _ in 0..480 {
+            let (left, _) = limiter.process_stereo_frame(2.0, 2.0);
+            assert!(left <= ceiling);
+        }
+        assert_eq!(limiter.settings().ceiling_db, -3.0);
+        assert!(limiter
+            .set_params(LimiterSettings {
+                threshold_db: f32::NAN,
+                ..LimiterSettings::default()
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use super::filters::ParametricEQ;
+
+/// FIR length. Odd, so the filter is symmetric around a whole sample; long enough to
+/// resolve third-octave bands down to about 25 Hz at 48 kHz.
+const TAPS: usize = 4095;
+/// Samples the FIR delays its input by: its center tap.
+const FILTER_DELAY: usize = TAPS / 2;
+/// Size of the zero-phase design grid.
+const DESIGN_SIZE: usize = TAPS + 1;
+/// Samples processed per convolution; they are buffered before any comes out.
+const BLOCK: usize = 4096;
+/// Overlap-save FFT size: one block plus enough history to cover the filter.
+const FFT_SIZE: usize = 2 * BLOCK;
+
+/// Latency of the linear-phase EQ in frames: one block of buffering plus half the
+/// filter.
+pub const LINEAR_PHASE_LATENCY_FRAMES: usize = BLOCK + FILTER_DELAY;
+
+/// Linear-phase version of the user EQ: a symmetric FIR with the magnitude response of
+/// the biquad EQ it follows, applied by FFT convolution. There is no phase shift at
+/// any frequency, in exchange for `LINEAR_PHASE_LATENCY_FRAMES` of delay and some
+/// pre-ringing around sharp, heavy corrections.
+pub struct LinearPhaseEQ {
+    enabled: AtomicBool,
+    /// Set on enabling, so stale audio from the last use is not played back.
+    needs_reset: AtomicBool,
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    design_inverse: Arc<dyn Fft<f32>>,
+    /// FFT of the current FIR, scaled for the unnormalized inverse transform.
+    kernel: Vec<Complex<f32>>,
+    /// Revision of the EQ the kernel was designed from, and whether it was the graphic
+    /// one.
+    designed: Option<(bool, u32)>,
+    /// Last `FFT_SIZE` input samples per channel, the newest block at the end.
+    input: [Vec<f32>; 2],
+    /// Filtered block being played out while the next one is collected.
+    output: [Vec<f32>; 2],
+    position: usize,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl LinearPhaseEQ {
+    pub fn new() -> Self {
+        let mut planner = FftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(FFT_SIZE);
+        let inverse = planner.plan_fft_inverse(FFT_SIZE);
+        let design_inverse = planner.plan_fft_inverse(DESIGN_SIZE);
+        let scratch_len = forward
+            .get_inplace_scratch_len()
+            .max(inverse.get_inplace_scratch_len())
+            .max(design_inverse.get_inplace_scratch_len());
+        Self {
+            enabled: AtomicBool::new(false),
+            needs_reset: AtomicBool::new(true),
+            forward,
+            inverse,
+            design_inverse,
+            kernel: vec![Complex::default(); FFT_SIZE],
+            designed: None,
+            input: [vec![0.0; FFT_SIZE], vec![0.0; FFT_SIZE]],
+            output: [vec![0.0; BLOCK], vec![0.0; BLOCK]],
+            position: 0,
+            buffer: vec![Complex::default(); FFT_SIZE],
+            scratch: vec![Complex::default(); scratch_len],
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if enabled && !self.enabled.swap(true, Ordering::SeqCst) {
+            self.needs_reset.store(true, Ordering::SeqCst);
+        }
+        if !enabled {
+            self.enabled.store(false, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Filters one frame with the response of `eq`; `graphic` tells which user EQ that
+    /// is, so switching between them redesigns the filter. The output lags the input
+    /// by `LINEAR_PHASE_LATENCY_FRAMES`.
+    pub fn process_stereo_frame(
+        &mut self,
+        left: f32,
+        right: f32,
+        eq: &ParametricEQ,
+        graphic: bool,
+    ) -> (f32, f32) {
+        if self.needs_reset.swap(false, Ordering::SeqCst) {
+            for channel in self.input.iter_mut().chain(self.output.iter_mut()) {
+                channel.fill(0.0);
+            }
+            self.position = 0;
+        }
+        if self.designed.is_none() {
+            self.design(eq, graphic);
+        }
+
+        let out = (self.output[0][self.position], self.output[1][self.position]);
+        self.input[0][BLOCK + self.position] = left;
+        self.input[1][BLOCK + self.position] = right;
+        self.position += 1;
+        if self.position == BLOCK {
+            self.position = 0;
+            // Changes are picked up between blocks, at most one redesign per block.
+            if self.designed != Some((graphic, eq.revision())) {
+                self.design(eq, graphic);
+            }
+            for channel in 0..2 {
+                self.convolve(channel);
+            }
+        }
+        out
+    }
+
+    /// Frequency-sampling design: the EQ magnitude on a zero-phase grid, turned into an
+    /// impulse response, centered, and windowed to `TAPS`.
+    fn design(&mut self, eq: &ParametricEQ, graphic: bool) {
+        let magnitudes = eq.linear_magnitudes(DESIGN_SIZE / 2 + 1);
+        let design = &mut self.buffer[..DESIGN_SIZE];
+        for (bin, value) in design.iter_mut().enumerate() {
+            let mirrored = bin.min(DESIGN_SIZE - bin);
+            *value = Complex::new(magnitudes[mirrored], 0.0);
+        }
+        self.design_inverse
+            .process_with_scratch(design, &mut self.scratch);
+
+        // The zero-phase response wraps around sample 0; shift its center to
+        // FILTER_DELAY and taper the ends.
+        let mut taps = [0.0_f32; TAPS];
+        for (n, tap) in taps.iter_mut().enumerate() {
+            let source = (n + DESIGN_SIZE - FILTER_DELAY) % DESIGN_SIZE;
+            let window =
+                0.5 * (1.0 - (2.0 * std::f32::consts::PI * n as f32 / (TAPS - 1) as f32).cos());
+            *tap = design[source].re / DESIGN_SIZE as f32 * window;
+        }
+
+        // Both FFT normalizations go into the kernel.
+        let scale = 1.0 / FFT_SIZE as f32;
+        for (index, value) in self.kernel.iter_mut().enumerate() {
+            *value = Complex::new(taps.get(index).copied().unwrap_or(0.0) * scale, 0.0);
+        }
+        self.forward
+            .process_with_scratch(&mut self.kernel, &mut self.scratch);
+        self.designed = Some((graphic, eq.revision()));
+    }
+
+    /// Overlap-save: filters the newest block using the one before as history.
+    fn convolve(&mut self, channel: usize) {
+        for (value, &sample) in self.buffer.iter_mut().zip(&self.input[channel]) {
+            *value = Complex::new(sample, 0.0);
+        }
+        self.forward
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+        for (value, kernel) in self.buffer.iter_mut().zip(&self.kernel) {
+            *value *= kernel;
+        }
+        self.inverse
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+        for (out, value) in self.output[channel]
+            .iter_mut()
+            .zip(&self.buffer[FFT_SIZE - BLOCK..])
+        {
+            *out = value.re;
+        }
+        self.input[channel].copy_within(BLOCK.., 0);
+    }
+}
+
+impl Default for LinearPhaseEQ {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinearPhaseEQ, LINEAR_PHASE_LATENCY_FRAMES};
+    use crate::audio::dsp::filters::ParametricEQ;
+
+    #[test]
+    fn linear_phase_eq_delays_and_keeps_impulses_symmetric() {
+        let eq = ParametricEQ::new(10, 48_000.0);
+        let mut node = LinearPhaseEQ::new();
+        node.set_enabled(true);
+
+        // Flat: the input comes back unchanged, exactly the reported latency later.
+        let length = LINEAR_PHASE_LATENCY_FRAMES + 4096;
+        let flat = (0..length)
+            .map(|n| {
+                node.process_stereo_frame(f32::from(n == 0), 0.0, &eq, false)
+                    .0
+            })
+            .collect::<Vec<_>>();
+        assert!((flat[LINEAR_PHASE_LATENCY_FRAMES] - 1.0).abs() < 1e-3);
+        let leak = flat
+            .iter()
+            .enumerate()
+            .filter(|(n, _)| *n != LINEAR_PHASE_LATENCY_FRAMES)
+            .map(|(_, sample)| sample.abs())
+            .fold(0.0_f32, f32::max);
+        assert!(leak < 1e-3, "flat EQ should pass impulses through: {leak}");
+
+        // Boosted: the impulse response rings symmetrically around the same point,
+        // which is what linear phase means.
+        eq.update_band(2, 1_000.0, 9.0, 2.0).expect("band");
+        node.set_enabled(false);
+        node.set_enabled(true);
+        let boosted = (0..length)
+            .map(|n| {
+                node.process_stereo_frame(f32::from(n == 0), 0.0, &eq, false)
+                    .0
+            })
+            .collect::<Vec<_>>();
+        let center = LINEAR_PHASE_LATENCY_FRAMES;
+        assert!(boosted[center] > 1.0);
+        for offset in [1, 10, 48, 200] {
+            let (before, after) = (boosted[center - offset], boosted[center + offset]);
+            assert!(
+                (before - after).abs() < 1e-4,
+                "{offset}: {before} vs {after}"
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use super::filters::BiquadFilter;
+
+const BASS_FREQ: f32 = 100.0;
+const TREBLE_FREQ: f32 = 10_000.0;
+const SHELF_SLOPE: f32 = 0.5;
+/// Extra bass and treble per dB of attenuation below full volume. Between the 80 and
+/// 40 phon contours of ISO 226 the threshold at 100 Hz moves about 30% less than at
+/// 1 kHz and at 10 kHz about 10% less, so that much goes missing as volume drops.
+const BASS_DB_PER_DB: f32 = 0.3;
+const TREBLE_DB_PER_DB: f32 = 0.1;
+const MAX_BASS_DB: f32 = 12.0;
+const MAX_TREBLE_DB: f32 = 6.0;
+/// Volumes below this (-60 dB) get the same compensation as this.
+const MIN_VOLUME: f32 = 0.001;
+
+/// Equal-loudness compensation: as the output volume drops below full scale, low and
+/// high shelves lift the bass and treble the ear stops hearing, so the tonal balance
+/// stays the same at low volume. Full volume is taken as the reference level and left
+/// untouched. Bypassed while disabled.
+pub struct LoudnessNode {
+    enabled: AtomicBool,
+    volume_bits: AtomicU32,
+    needs_update: AtomicBool,
+    sample_rate: f32,
+    left_bass: BiquadFilter,
+    right_bass: BiquadFilter,
+    left_treble: BiquadFilter,
+    right_treble: BiquadFilter,
+}
+
+impl LoudnessNode {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut node = Self {
+            enabled: AtomicBool::new(false),
+            volume_bits: AtomicU32::new(1.0_f32.to_bits()),
+            needs_update: AtomicBool::new(true),
+            sample_rate: sample_rate.max(8_000.0),
+            left_bass: BiquadFilter::new(),
+            right_bass: BiquadFilter::new(),
+            left_treble: BiquadFilter::new(),
+            right_treble: BiquadFilter::new(),
+        };
+        node.recalculate();
+        node
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Follows the player volume (0.0–1.0) the compensation is computed for.
+    pub fn set_volume(&self, volume: f32) {
+        self.volume_bits
+            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    /// Current bass and treble boost in dB.
+    pub fn boost_db(&self) -> (f32, f32) {
+        boost_for_volume(f32::from_bits(self.volume_bits.load(Ordering::Relaxed)))
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() > f32::EPSILON {
+            self.sample_rate = sr;
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return (left, right);
+        }
+        if self.needs_update.swap(false, Ordering::SeqCst) {
+            self.recalculate();
+        }
+        let l = self
+            .left_treble
+            .process_sample(self.left_bass.process_sample(left));
+        let r = self
+            .right_treble
+            .process_sample(self.right_bass.process_sample(right));
+        (l, r)
+    }
+
+    fn recalculate(&mut self) {
+        let (bass_db, treble_db) = self.boost_db();
+        for bass in [&mut self.left_bass, &mut self.right_bass] {
+            bass.set_low_shelf(self.sample_rate, BASS_FREQ, bass_db, SHELF_SLOPE);
+        }
+        for treble in [&mut self.left_treble, &mut self.right_treble] {
+            treble.set_high_shelf(self.sample_rate, TREBLE_FREQ, treble_db, SHELF_SLOPE);
+        }
+    }
+}
+
+fn boost_for_volume(volume: f32) -> (f32, f32) {
+    let attenuation_db = -20.0 * volume.max(MIN_VOLUME).log10();
+    (
+        (attenuation_db * BASS_DB_PER_DB).min(MAX_BASS_DB),
+        (attenuation_db * TREBLE_DB_PER_DB).min(MAX_TREBLE_DB),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoudnessNode;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+
+    /// Peak of the output over the last half of one second of a sine at `frequency`.
+    fn output_peak(node: &mut LoudnessNode, frequency: f32) -> f32 {
+        let total = SAMPLE_RATE as usize;
+        let mut peak = 0.0_f32;
+        for n in 0..total {
+            let phase = 2.0 * std::f32::consts::PI * frequency * n as f32 / SAMPLE_RATE;
+            let (left, _) = node.process_stereo_frame(0.1 * phase.sin(), 0.0);
+            if n >= total / 2 {
+                peak = peak.max(left.abs());
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn lower_volume_lifts_bass_and_treble_more() {
+        let mut node = LoudnessNode::new(SAMPLE_RATE);
+        node.set_volume(0.1);
+        assert_eq!(node.process_stereo_frame(0.3, -0.2), (0.3, -0.2));
+
+        node.set_enabled(true);
+        node.set_volume(1.0);
+        assert_eq!(node.boost_db(), (0.0, 0.0));
+        assert!((output_peak(&mut node, 40.0) - 0.1).abs() < 0.002);
+
+        node.set_volume(0.1);
+        let (bass_db, treble_db) = node.boost_db();
+        assert!((bass_db - 6.0).abs() < 0.01 && (treble_db - 2.0).abs() < 0.01);
+        let bass = output_peak(&mut node, 40.0);
+        let mids = output_peak(&mut node, 1_000.0);
+        let treble = output_peak(&mut node, 16_000.0);
+        assert!(bass > 0.15, "bass should be lifted: {bass}");
+        assert!((mids - 0.1).abs() < 0.01, "mids should stay put: {mids}");
+        assert!(treble > 0.11, "treble should be lifted: {treble}");
+
+        node.set_volume(0.0);
+        assert_eq!(node.boost_db().0, 12.0);
+    }
+}
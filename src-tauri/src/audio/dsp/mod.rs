@@ -1,6 +1,26 @@
+pub mod analog;
 pub mod autoeq;
+pub mod bass_management;
+pub mod beat;
+pub mod binaural_downmix;
+pub mod compressor;
+pub mod correction;
 pub mod fft;
 pub mod filters;
+pub mod hdf5;
+pub mod karaoke;
+pub mod limiter;
+pub mod linear_phase;
+pub mod loudness;
+pub mod night_mode;
+pub mod plugin_rack;
+pub mod presets;
+pub mod profiles;
 pub mod reverb;
+pub mod sofa;
 pub mod spatial;
+pub mod spatial_motion;
+pub mod spatial_scene;
 pub mod tone;
+pub mod upmix;
+pub mod vbap;
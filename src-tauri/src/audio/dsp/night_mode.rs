@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::filters::BiquadFilter;
+
+/// Quiet passages below this are raised...
+const UPWARD_THRESHOLD_DB: f32 = -35.0;
+const UPWARD_RATIO: f32 = 3.0;
+const MAX_BOOST_DB: f32 = 15.0;
+/// ...but not the noise floor: the boost fades out over the 10 dB below this.
+const NOISE_FLOOR_DB: f32 = -65.0;
+/// Loud passages above this are pulled down.
+const DOWNWARD_THRESHOLD_DB: f32 = -18.0;
+const DOWNWARD_RATIO: f32 = 4.0;
+const ATTACK_MS: f32 = 10.0;
+const RELEASE_MS: f32 = 300.0;
+/// RMS window of the level detector.
+const DETECTOR_MS: f32 = 20.0;
+const SHELF_FREQ: f32 = 6_000.0;
+const SHELF_GAIN_DB: f32 = -3.0;
+const SHELF_SLOPE: f32 = 0.7;
+/// How long switching night mode on or off crossfades.
+const TOGGLE_RAMP_MS: f32 = 250.0;
+
+/// One-switch dynamics reduction for low listening levels: quiet passages are lifted
+/// (upward compression), loud ones are tamed, and a gentle high shelf takes the edge
+/// off the treble. Gain is shared by both channels, and toggling crossfades.
+pub struct NightModeNode {
+    enabled: AtomicBool,
+    needs_update: AtomicBool,
+    sample_rate: f32,
+    left_shelf: BiquadFilter,
+    right_shelf: BiquadFilter,
+    mean_square: f32,
+    gain_db: f32,
+    detector_coeff: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    /// 0 (bypassed) to 1 (fully applied).
+    mix: f32,
+    mix_step: f32,
+}
+
+impl NightModeNode {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut node = Self {
+            enabled: AtomicBool::new(false),
+            needs_update: AtomicBool::new(true),
+            sample_rate: sample_rate.max(8_000.0),
+            left_shelf: BiquadFilter::new(),
+            right_shelf: BiquadFilter::new(),
+            mean_square: 0.0,
+            gain_db: 0.0,
+            detector_coeff: 0.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            mix: 0.0,
+            mix_step: 0.0,
+        };
+        node.recalculate();
+        node
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() > f32::EPSILON {
+            self.sample_rate = sr;
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let target_mix = if self.enabled.load(Ordering::Relaxed) {
+            1.0
+        } else {
+            0.0
+        };
+        if self.mix == 0.0 && target_mix == 0.0 {
+            return (left, right);
+        }
+        if self.needs_update.swap(false, Ordering::SeqCst) {
+            self.recalculate();
+        }
+        self.mix = if target_mix > self.mix {
+            (self.mix + self.mix_step).min(target_mix)
+        } else {
+            (self.mix - self.mix_step).max(target_mix)
+        };
+
+        let power = 0.5 * (left * left + right * right);
+        self.mean_square = power + (self.mean_square - power) * self.detector_coeff;
+        let level_db = 10.0 * self.mean_square.max(1.0e-12).log10();
+        let target = gain_for_level(level_db);
+        let coeff = if target < self.gain_db {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.gain_db = target + (self.gain_db - target) * coeff;
+
+        let gain = 10.0_f32.powf(self.gain_db * self.mix / 20.0);
+        let shelved_left = self.left_shelf.process_sample(left);
+        let shelved_right = self.right_shelf.process_sample(right);
+        (
+            (left + (shelved_left - left) * self.mix) * gain,
+            (right + (shelved_right - right) * self.mix) * gain,
+        )
+    }
+
+    fn recalculate(&mut self) {
+        for shelf in [&mut self.left_shelf, &mut self.right_shelf] {
+            shelf.set_high_shelf(self.sample_rate, SHELF_FREQ, SHELF_GAIN_DB, SHELF_SLOPE);
+        }
+        let coeff = |ms: f32| (-1.0 / (ms * 0.001 * self.sample_rate)).exp();
+        self.detector_coeff = coeff(DETECTOR_MS);
+        self.attack_coeff = coeff(ATTACK_MS);
+        self.release_coeff = coeff(RELEASE_MS);
+        self.mix_step = 1.0 / (TOGGLE_RAMP_MS * 0.001 * self.sample_rate);
+    }
+}
+
+/// Static gain curve in dB for an RMS level in dBFS.
+fn gain_for_level(level_db: f32) -> f32 {
+    if level_db > DOWNWARD_THRESHOLD_DB {
+        return -(level_db - DOWNWARD_THRESHOLD_DB) * (1.0 - 1.0 / DOWNWARD_RATIO);
+    }
+    if level_db >= UPWARD_THRESHOLD_DB {
+        return 0.0;
+    }
+    let boost = ((UPWARD_THRESHOLD_DB - level_db) * (1.0 - 1.0 / UPWARD_RATIO)).min(MAX_BOOST_DB);
+    let above_floor = ((level_db - (NOISE_FLOOR_DB - 10.0)) / 10.0).clamp(0.0, 1.0);
+    boost * above_floor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gain_for_level, NightModeNode};
+
+    #[test]
+    fn night_mode_narrows_dynamics_and_bypasses_when_off() {
+        // Quiet is lifted, loud is cut, silence and the noise floor stay put.
+        assert!(gain_for_level(-50.0) > 9.0);
+        assert_eq!(gain_for_level(-25.0), 0.0);
+        assert!(gain_for_level(-6.0) < -8.0);
+        assert_eq!(gain_for_level(-90.0), 0.0);
+
+        let mut node = NightModeNode::new(48_000.0);
+        assert_eq!(node.process_stereo_frame(0.5, -0.5), (0.5, -0.5));
+
+        node.set_enabled(true);
+        let peak = |node: &mut NightModeNode, amplitude: f32| {
+            let mut loudest = 0.0_f32;
+            for n in 0..48_000 {
+                let sample = amplitude * (n as f32 * 0.05).sin();
+                let (left, _) = node.process_stereo_frame(sample, sample);
+                if n >= 24_000 {
+                    loudest = loudest.max(left.abs());
+                }
+            }
+            loudest
+        };
+        let quiet = peak(&mut node, 0.005);
+        let loud = peak(&mut node, 0.9);
+        assert!(quiet > 0.01, "quiet passage should be lifted: {quiet}");
+        assert!(loud < 0.5, "loud passage should be tamed: {loud}");
+
+        // Switching off eases back to an untouched signal.
+        node.set_enabled(false);
+        let _ = peak(&mut node, 0.5);
+        assert_eq!(node.process_stereo_frame(0.5, -0.5), (0.5, -0.5));
+    }
+}
@@ -0,0 +1,334 @@
+use serde::{Deserialize, Serialize};
+
+use super::analog::AnalogSettings;
+use super::compressor::{CompressorSettings, COMPRESSOR_BANDS};
+use super::filters::{DspChain, DspNode, EqBandSettings, EqMode, GRAPHIC_EQ_FREQUENCIES};
+use super::limiter::LimiterSettings;
+use super::reverb::ReverbPreset;
+use super::tone::{BalanceMode, CrossfeedPreset};
+
+/// Format version written into every chain preset. Presets from a newer version are
+/// refused rather than half applied.
+pub const CHAIN_PRESET_VERSION: u32 = 1;
+
+/// Crossfeed amount plus the filter, delay and level it runs with.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CrossfeedSettings {
+    pub amount: f32,
+    pub cutoff_hz: f32,
+    pub delay_ms: f32,
+    pub feed_db: f32,
+    pub compensated: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReverbSettings {
+    pub room_size: f32,
+    pub damping: f32,
+    pub predelay_ms: f32,
+    pub lowpass_filter: f32,
+    pub decay: f32,
+    pub wet_mix: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpatialSourceSettings {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub active: bool,
+}
+
+/// The spatial room, in metres. Head orientation is not included, since head tracking
+/// drives it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpatialSettings {
+    pub enabled: bool,
+    pub width: f32,
+    pub length: f32,
+    pub height: f32,
+    pub damping: f32,
+    pub sources: Vec<SpatialSourceSettings>,
+}
+
+/// Every user setting of the DSP chain, saved under a name and shareable as a JSON
+/// file. The AutoEQ profile, room correction, plugin inserts and HRTF are left out: they
+/// depend on the headphones, the room and what is installed on the machine, not on the
+/// sound.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainPreset {
+    pub version: u32,
+    pub preamp_db: f32,
+    pub bass_db: f32,
+    pub treble_db: f32,
+    pub loudness_compensation: bool,
+    pub eq_mode: EqMode,
+    pub eq_linear_phase: bool,
+    pub eq_bands: Vec<EqBandSettings>,
+    pub graphic_eq_gains_db: Vec<f32>,
+    pub compressor: CompressorSettings,
+    pub night_mode: bool,
+    pub analog: AnalogSettings,
+    pub balance: f32,
+    /// Missing from presets saved before rotate mode existed.
+    #[serde(default)]
+    pub balance_mode: BalanceMode,
+    pub mono: bool,
+    pub crossfeed: CrossfeedSettings,
+    pub spatial: SpatialSettings,
+    pub reverb: ReverbSettings,
+    pub limiter: LimiterSettings,
+    /// Nodes switched to bypass; all others run.
+    pub bypassed: Vec<DspNode>,
+}
+
+impl ChainPreset {
+    /// Reads the current settings of `chain`. The preamp lives in the engine, so it is
+    /// passed in.
+    pub fn capture(chain: &DspChain, preamp_db: f32) -> Self {
+        let crossfeed = chain.expansion().current_preset();
+        let reverb = chain.reverb().current_params();
+        let spatial = chain.spatial();
+        let (width, length, height) = spatial.room_size();
+        Self {
+            version: CHAIN_PRESET_VERSION,
+            preamp_db,
+            bass_db: chain.tone().bass(),
+            treble_db: chain.tone().treble(),
+            loudness_compensation: chain.loudness().is_enabled(),
+            eq_mode: chain.eq_mode(),
+            eq_linear_phase: chain.linear_phase().is_enabled(),
+            eq_bands: chain.user_eq_settings(),
+            graphic_eq_gains_db: chain.graphic_eq().gains(),
+            compressor: chain.compressor().settings(),
+            night_mode: chain.night_mode().is_enabled(),
+            analog: chain.analog().settings(),
+            balance: chain.balance().balance(),
+            balance_mode: chain.balance().mode(),
+            mono: chain.balance().is_mono(),
+            crossfeed: CrossfeedSettings {
+                amount: chain.expansion().amount(),
+                cutoff_hz: crossfeed.cutoff_hz,
+                delay_ms: crossfeed.delay_ms,
+                feed_db: crossfeed.feed_db,
+                compensated: crossfeed.compensated,
+            },
+            spatial: SpatialSettings {
+                enabled: spatial.is_enabled(),
+                width,
+                length,
+                height,
+                damping: spatial.damping(),
+                sources: spatial
+                    .source_positions()
+                    .into_iter()
+                    .map(|(x, y, z, active)| SpatialSourceSettings { x, y, z, active })
+                    .collect(),
+            },
+            reverb: ReverbSettings {
+                room_size: reverb.room_size,
+                damping: reverb.damping,
+                predelay_ms: reverb.predelay_ms,
+                lowpass_filter: reverb.lowpass_filter,
+                decay: reverb.decay,
+                wet_mix: reverb.wet_mix,
+            },
+            limiter: chain.limiter().settings(),
+            bypassed: DspNode::ALL
+                .into_iter()
+                .filter(|node| chain.is_node_bypassed(*node))
+                .collect(),
+        }
+    }
+
+    /// Rejects presets that could only be applied in part: a newer format, non-finite
+    /// values, or the wrong number of bands.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version > CHAIN_PRESET_VERSION {
+            return Err(format!(
+                "Chain preset version {} is newer than the supported version {CHAIN_PRESET_VERSION}",
+                self.version
+            ));
+        }
+        if self.graphic_eq_gains_db.len() > GRAPHIC_EQ_FREQUENCIES.len() {
+            return Err(format!(
+                "Chain preset has {} graphic EQ gains; the graphic EQ has {}",
+                self.graphic_eq_gains_db.len(),
+                GRAPHIC_EQ_FREQUENCIES.len()
+            ));
+        }
+        if self.compressor.bands.len() != COMPRESSOR_BANDS {
+            return Err(format!(
+                "Chain preset has {} compressor bands; the compressor has {COMPRESSOR_BANDS}",
+                self.compressor.bands.len()
+            ));
+        }
+        let LimiterSettings {
+            threshold_db,
+            release_ms,
+            ceiling_db,
+            lookahead_ms,
+        } = self.limiter;
+        let crossfeed = &self.crossfeed;
+        let reverb = &self.reverb;
+        let spatial = &self.spatial;
+        let scalars = [
+            self.preamp_db,
+            self.bass_db,
+            self.treble_db,
+            self.analog.saturation,
+            self.analog.wow_flutter,
+            self.analog.crackle,
+            self.balance,
+            crossfeed.amount,
+            crossfeed.cutoff_hz,
+            crossfeed.delay_ms,
+            crossfeed.feed_db,
+            spatial.width,
+            spatial.length,
+            spatial.height,
+            spatial.damping,
+            reverb.room_size,
+            reverb.damping,
+            reverb.predelay_ms,
+            reverb.lowpass_filter,
+            reverb.decay,
+            reverb.wet_mix,
+            threshold_db,
+            release_ms,
+            ceiling_db,
+            lookahead_ms,
+        ];
+        let finite = scalars
+            .into_iter()
+            .chain(
+                self.eq_bands
+                    .iter()
+                    .flat_map(|band| [band.frequency, band.gain_db, band.q_factor]),
+            )
+            .chain(self.graphic_eq_gains_db.iter().copied())
+            .chain(self.compressor.crossovers)
+            .chain(
+                self.compressor
+                    .bands
+                    .iter()
+                    .flat_map(|band| [band.threshold_db, band.ratio]),
+            )
+            .chain(
+                spatial
+                    .sources
+                    .iter()
+                    .flat_map(|source| [source.x, source.y, source.z]),
+            )
+            .all(f32::is_finite);
+        if !finite {
+            return Err("Chain preset values must be finite numbers".to_string());
+        }
+        Ok(())
+    }
+
+    /// Applies everything but the preamp to `chain`. Settings that can fail are applied
+    /// first, so a rejected preset leaves the other stages untouched.
+    pub fn apply(&self, chain: &DspChain) -> Result<(), String> {
+        self.validate()?;
+        chain.apply_user_eq_settings(&self.eq_bands)?;
+        for (index, gain_db) in self.graphic_eq_gains_db.iter().enumerate() {
+            chain.graphic_eq().set_gain(index, *gain_db)?;
+        }
+        let compressor = chain.compressor();
+        compressor.set_crossovers(self.compressor.crossovers)?;
+        for (index, band) in self.compressor.bands.iter().enumerate() {
+            compressor.set_band(index, band.threshold_db, band.ratio)?;
+        }
+        chain.limiter().set_params(self.limiter)?;
+
+        compressor.set_enabled(self.compressor.enabled);
+        chain.set_eq_mode(self.eq_mode);
+        chain.linear_phase().set_enabled(self.eq_linear_phase);
+        chain.tone().set_bass(self.bass_db);
+        chain.tone().set_treble(self.treble_db);
+        chain.loudness().set_enabled(self.loudness_compensation);
+        chain.night_mode().set_enabled(self.night_mode);
+
+        let analog = chain.analog();
+        analog.set_enabled(self.analog.enabled);
+        analog.set_saturation(self.analog.saturation);
+        analog.set_wow_flutter(self.analog.wow_flutter);
+        analog.set_crackle(self.analog.crackle);
+
+        chain.balance().set_balance(self.balance);
+        chain.balance().set_mode(self.balance_mode);
+        chain.balance().set_mono(self.mono);
+        chain.expansion().load_preset(&CrossfeedPreset {
+            name: "Custom",
+            cutoff_hz: self.crossfeed.cutoff_hz,
+            delay_ms: self.crossfeed.delay_ms,
+            feed_db: self.crossfeed.feed_db,
+            compensated: self.crossfeed.compensated,
+        });
+        chain.expansion().set_amount(self.crossfeed.amount);
+
+        let spatial = chain.spatial();
+        spatial.set_enabled(self.spatial.enabled);
+        spatial.set_room_size(self.spatial.width, self.spatial.length, self.spatial.height);
+        spatial.set_damping(self.spatial.damping);
+        for (index, source) in self.spatial.sources.iter().enumerate() {
+            spatial.set_source_position(index, source.x, source.y, source.z);
+            spatial.set_source_active(index, source.active);
+        }
+
+        chain.reverb().load_preset(&ReverbPreset {
+            name: "Custom",
+            room_size: self.reverb.room_size,
+            damping: self.reverb.damping,
+            predelay_ms: self.reverb.predelay_ms,
+            lowpass_filter: self.reverb.lowpass_filter,
+            decay: self.reverb.decay,
+            wet_mix: self.reverb.wet_mix,
+        });
+        for node in DspNode::ALL {
+            chain.set_node_bypassed(node, self.bypassed.contains(&node));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChainPreset;
+    use crate::audio::dsp::filters::{DspChain, DspNode, EqMode};
+    use crate::audio::dsp::tone::BalanceMode;
+
+    #[test]
+    fn chain_presets_round_trip_through_json() {
+        let chain = DspChain::new(48_000.0);
+        chain.tone().set_bass(4.0);
+        chain.set_eq_mode(EqMode::Graphic);
+        chain.graphic_eq().set_gain(10, -3.0).expect("graphic band");
+        chain.compressor().set_band(1, -20.0, 4.0).expect("band");
+        chain.analog().set_crackle(0.5);
+        chain.balance().set_balance(-0.25);
+        chain.balance().set_mode(BalanceMode::Rotate);
+        chain.expansion().set_amount(0.6);
+        chain.spatial().set_room_size(6.0, 8.0, 3.0);
+        chain.reverb().set_wet_mix(0.3);
+        chain.set_node_bypassed(DspNode::Limiter, true);
+        let saved = ChainPreset::capture(&chain, -3.0);
+
+        let json = serde_json::to_string(&saved).expect("serialize");
+        let loaded: ChainPreset = serde_json::from_str(&json).expect("parse");
+        let fresh = DspChain::new(48_000.0);
+        loaded.apply(&fresh).expect("apply");
+        assert_eq!(ChainPreset::capture(&fresh, -3.0), saved);
+
+        // A preset that cannot be fully applied changes nothing.
+        let mut broken = saved.clone();
+        broken.compressor.bands.pop();
+        let untouched = DspChain::new(48_000.0);
+        assert!(broken.apply(&untouched).is_err());
+        assert_eq!(untouched.tone().bass(), 0.0);
+        let mut newer = saved;
+        newer.version += 1;
+        assert!(newer.validate().is_err());
+    }
+}
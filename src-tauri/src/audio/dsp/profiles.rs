@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+use super::reverb::get_preset;
+
+/// Value accepted in `reverb_preset` to switch the reverb off.
+pub const REVERB_OFF: &str = "off";
+const MAX_EQ_BANDS: usize = 10;
+
+/// Named DSP settings applied automatically on track load. Unset fields leave the
+/// matching stage alone, so profiles from several scopes can be layered.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspProfile {
+    /// User EQ gains in band order; band frequencies and Q are kept.
+    pub eq_gains_db: Option<Vec<f32>>,
+    pub bass_db: Option<f32>,
+    pub treble_db: Option<f32>,
+    /// Stereo widener amount, 0.0..=1.0.
+    pub expansion: Option<f32>,
+    /// Reverb preset name (see `reverb::get_preset`) or `"off"`.
+    pub reverb_preset: Option<String>,
+}
+
+impl DspProfile {
+    /// Returns `self` with every unset field taken from `base`.
+    pub fn layered_over(&self, base: &DspProfile) -> DspProfile {
+        DspProfile {
+            eq_gains_db: self
+                .eq_gains_db
+                .clone()
+                .or_else(|| base.eq_gains_db.clone()),
+            bass_db: self.bass_db.or(base.bass_db),
+            treble_db: self.treble_db.or(base.treble_db),
+            expansion: self.expansion.or(base.expansion),
+            reverb_preset: self
+                .reverb_preset
+                .clone()
+                .or_else(|| base.reverb_preset.clone()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(gains) = &self.eq_gains_db {
+            if gains.len() > MAX_EQ_BANDS {
+                return Err(format!(
+                    "DSP profile has {} EQ gains; at most {MAX_EQ_BANDS} bands are supported",
+                    gains.len()
+                ));
+            }
+        }
+        let non_finite = self
+            .eq_gains_db
+            .iter()
+            .flatten()
+            .chain(&self.bass_db)
+            .chain(&self.treble_db)
+            .chain(&self.expansion)
+            .any(|value| !value.is_finite());
+        if non_finite {
+            return Err("DSP profile values must be finite numbers".to_string());
+        }
+        match self.reverb_preset.as_deref() {
+            Some(name) if !name.eq_ignore_ascii_case(REVERB_OFF) && get_preset(name).is_none() => {
+                Err(format!("Unknown reverb preset: {name}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What a profile assignment is keyed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DspProfileScope {
+    Device,
+    Genre,
+    Track,
+}
+
+impl DspProfileScope {
+    /// Lowest precedence first: the genre profile overrides the output-device profile and
+    /// a per-track profile overrides both, one setting at a time.
+    pub const PRECEDENCE: [Self; 3] = [Self::Device, Self::Genre, Self::Track];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Device => "device",
+            Self::Genre => "genre",
+            Self::Track => "track",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "device" => Ok(Self::Device),
+            "genre" => Ok(Self::Genre),
+            "track" => Ok(Self::Track),
+            _ => Err(format!("Unknown DSP profile scope: {value}")),
+        }
+    }
+}
+
+/// Layered profile for one track plus the `(scope, profile name)` pairs it was built
+/// from, lowest precedence first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedDspProfile {
+    pub profile: DspProfile,
+    pub sources: Vec<(DspProfileScope, String)>,
+}
+
+/// Lookup keys for a genre tag: the whole tag, then each value of a multi-valued tag
+/// (`"Electronic; EDM"`, or NUL-separated ID3v2.4), trimmed and lowercased.
+pub fn genre_keys(genre: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let whole = genre.trim().to_lowercase();
+    if !whole.is_empty() {
+        keys.push(whole);
+    }
+    for part in genre.split([';', '\0']) {
+        let key = part.trim().to_lowercase();
+        if !key.is_empty() && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Resolves the profile for a track from its per-track, genre and output-device
+/// assignments. `lookup` returns the profile assigned to a normalized scope key.
+pub fn resolve_profile(
+    track: &str,
+    genre: Option<&str>,
+    device: &str,
+    lookup: impl Fn(DspProfileScope, &str) -> Result<Option<(String, DspProfile)>, String>,
+) -> Result<Option<ResolvedDspProfile>, String> {
+    let mut resolved: Option<ResolvedDspProfile> = None;
+    for scope in DspProfileScope::PRECEDENCE {
+        let found = match scope {
+            DspProfileScope::Device => lookup(scope, device)?,
+            DspProfileScope::Track => lookup(scope, track)?,
+            DspProfileScope::Genre => {
+                let mut found = None;
+                for key in genre.map(genre_keys).unwrap_or_default() {
+                    found = lookup(scope, &key)?;
+                    if found.is_some() {
+                        break;
+                    }
+                }
+                found
+            }
+        };
+        let Some((name, profile)) = found else {
+            continue;
+        };
+        resolved = Some(match resolved {
+            Some(mut layered) => {
+                layered.profile = profile.layered_over(&layered.profile);
+                layered.sources.push((scope, name));
+                layered
+            }
+            None => ResolvedDspProfile {
+                profile,
+                sources: vec![(scope, name)],
+            },
+        });
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{genre_keys, resolve_profile, DspProfile, DspProfileScope};
+
+    #[test]
+    fn genre_keys_split_multi_valued_tags() {
+        assert_eq!(genre_keys("  Classical "), vec!["classical"]);
+        assert_eq!(
+            genre_keys("Electronic; EDM"),
+            vec!["electronic; edm", "electronic", "edm"]
+        );
+        assert_eq!(genre_keys("Rock\0Pop"), vec!["rock\0pop", "rock", "pop"]);
+        assert!(genre_keys(" ").is_empty());
+    }
+
+    #[test]
+    fn track_overrides_genre_which_overrides_device() {
+        let device = DspProfile {
+            bass_db: Some(-2.0),
+            treble_db: Some(1.0),
+            expansion: Some(0.1),
+            ..DspProfile::default()
+        };
+        let edm = DspProfile {
+            bass_db: Some(6.0),
+            expansion: Some(0.6),
+            ..DspProfile::default()
+        };
+        let track = DspProfile {
+            expansion: Some(0.0),
+            ..DspProfile::default()
+        };
+        let resolved = resolve_profile(
+            "/music/a.flac",
+            Some("Dance; EDM"),
+            "Headphones",
+            |scope, key| {
+                Ok(match (scope, key) {
+                    (DspProfileScope::Device, "Headphones") => {
+                        Some(("Cans".to_string(), device.clone()))
+                    }
+                    (DspProfileScope::Genre, "edm") => Some(("EDM".to_string(), edm.clone())),
+                    (DspProfileScope::Track, "/music/a.flac") => {
+                        Some(("Dry".to_string(), track.clone()))
+                    }
+                    _ => None,
+                })
+            },
+        )
+        .expect("resolution should succeed")
+        .expect("profiles should match");
+
+        assert_eq!(resolved.profile.bass_db, Some(6.0));
+        assert_eq!(resolved.profile.treble_db, Some(1.0));
+        assert_eq!(resolved.profile.expansion, Some(0.0));
+        assert_eq!(
+            resolved.sources,
+            vec![
+                (DspProfileScope::Device, "Cans".to_string()),
+                (DspProfileScope::Genre, "EDM".to_string()),
+                (DspProfileScope::Track, "Dry".to_string()),
+            ]
+        );
+        assert!(
+            resolve_profile("/x.flac", None, "Speakers", |_, _| Ok(None))
+                .expect("resolution should succeed")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn validation_rejects_unknown_reverb_and_bad_values() {
+        let mut profile = DspProfile {
+            reverb_preset: Some("Off".to_string()),
+            eq_gains_db: Some(vec![0.0; 10]),
+            ..DspProfile::default()
+        };
+        assert!(profile.validate().is_ok());
+        profile.reverb_preset = Some("Cathedral of Doom".to_string());
+        assert!(profile.validate().is_err());
+        profile.reverb_preset = Some("church".to_string());
+        profile.bass_db = Some(f32::NAN);
+        assert!(profile.validate().is_err());
+        profile.bass_db = None;
+        profile.eq_gains_db = Some(vec![0.0; 11]);
+        assert!(profile.validate().is_err());
+    }
+}
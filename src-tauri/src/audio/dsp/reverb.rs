@@ -257,6 +257,20 @@ impl ReverbNode {
         self.set_wet_mix(preset.wet_mix);
     }
 
+    /// Current parameters as an unnamed preset, e.g. to restore them later.
+    pub fn current_params(&self) -> ReverbPreset {
+        let load = |bits: &AtomicU32| f32::from_bits(bits.load(Ordering::Relaxed));
+        ReverbPreset {
+            name: "Custom",
+            room_size: load(&self.room_size_bits),
+            damping: load(&self.damping_bits),
+            predelay_ms: load(&self.predelay_ms_bits),
+            lowpass_filter: load(&self.lowpass_freq_bits),
+            decay: load(&self.decay_bits),
+            wet_mix: load(&self.wet_mix_bits),
+        }
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         let sr = sample_rate.max(8_000.0);
         if (sr - self.sample_rate).abs() > f32::EPSILON {
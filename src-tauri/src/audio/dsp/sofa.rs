@@ -0,0 +1,272 @@
+//! Measured head-related impulse responses loaded from SOFA (AES69) files, with
+//! interpolation between the measured directions.
+
+use std::path::Path;
+
+use super::hdf5::Hdf5File;
+
+/// Longest HRIR kept per ear (in samples at the output rate); longer responses are
+/// truncated to bound the per-sample convolution cost.
+pub const MAX_HRIR_LENGTH: usize = 512;
+/// Measured directions blended for each lookup.
+const NEIGHBOURS: usize = 3;
+
+/// A set of measured left/right HRIR pairs.
+pub struct HrirSet {
+    pub name: String,
+    pub sample_rate: f32,
+    /// Unit vectors towards each measured source, in SOFA orientation (x front, y left,
+    /// z up).
+    directions: Vec<[f32; 3]>,
+    left: Vec<Vec<f32>>,
+    right: Vec<Vec<f32>>,
+}
+
+impl HrirSet {
+    /// Reads a `SimpleFreeFieldHRIR`-style SOFA file: `Data.IR` shaped `[M, 2, N]`,
+    /// `Data.SamplingRate`, and `SourcePosition` in spherical (degrees) or cartesian
+    /// coordinates. Per-ear `Data.Delay`s are folded into the responses.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = Hdf5File::open(path)?;
+        let ir = file.dataset("Data.IR")?;
+        let [measurements, receivers, length] = ir.shape[..] else {
+            return Err("Data.IR must have three dimensions".to_string());
+        };
+        if receivers != 2 {
+            return Err(format!("Expected 2 receivers (ears), found {receivers}"));
+        }
+        let sample_rate =
+            file.dataset("Data.SamplingRate")?
+                .values
+                .first()
+                .copied()
+                .ok_or_else(|| "Data.SamplingRate is empty".to_string())? as f32;
+
+        let positions = file.dataset("SourcePosition")?;
+        let rows = positions.shape.first().copied().unwrap_or(0);
+        if positions.shape.get(1) != Some(&3) || (rows != measurements && rows != 1) {
+            return Err("SourcePosition must be [M, 3]".to_string());
+        }
+        let cartesian = file
+            .string_attribute("SourcePosition", "Type")
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("cartesian"));
+        let directions = (0..measurements)
+            .map(|m| {
+                let row = &positions.values[(m % rows) * 3..(m % rows) * 3 + 3];
+                if cartesian {
+                    normalize([row[0] as f32, row[1] as f32, row[2] as f32])
+                } else {
+                    spherical_direction(row[0] as f32, row[1] as f32)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Delays are [I or M, R] in samples.
+        let delays = file.dataset("Data.Delay").ok();
+        let delay = |m: usize, ear: usize| -> usize {
+            delays.as_ref().map_or(0, |d| {
+                let rows = d.shape.first().copied().unwrap_or(1).max(1);
+                let index = (m % rows) * 2 + ear;
+                d.values
+                    .get(index)
+                    .map_or(0, |v| v.max(0.0).round() as usize)
+            })
+        };
+        let mut left = Vec::with_capacity(measurements);
+        let mut right = Vec::with_capacity(measurements);
+        for m in 0..measurements {
+            for (ear, irs) in [&mut left, &mut right].into_iter().enumerate() {
+                let start = (m * 2 + ear) * length;
+                let mut response = vec![0.0_f32; delay(m, ear)];
+                response.extend(ir.values[start..start + length].iter().map(|v| *v as f32));
+                irs.push(response);
+            }
+        }
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "HRTF".to_string());
+        Self::from_measurements(name, sample_rate, directions, left, right)
+    }
+
+    /// Builds a set from measured directions (unit vectors, SOFA orientation) and their
+    /// responses, normalizing so the loudest response has unit energy.
+    pub fn from_measurements(
+        name: String,
+        sample_rate: f32,
+        directions: Vec<[f32; 3]>,
+        mut left: Vec<Vec<f32>>,
+        mut right: Vec<Vec<f32>>,
+    ) -> Result<Self, String> {
+        if directions.is_empty() || directions.len() != left.len() || left.len() != right.len() {
+            return Err("HRTF set has no usable measurements".to_string());
+        }
+        if sample_rate < 8_000.0 {
+            return Err(format!("Unsupported HRTF sample rate {sample_rate} Hz"));
+        }
+        let peak = left
+            .iter()
+            .chain(&right)
+            .map(|ir| ir.iter().map(|v| v * v).sum::<f32>().sqrt())
+            .fold(0.0_f32, f32::max);
+        if peak <= f32::EPSILON || !peak.is_finite() {
+            return Err("HRTF responses are silent".to_string());
+        }
+        for ir in left.iter_mut().chain(right.iter_mut()) {
+            ir.iter_mut().for_each(|v| *v /= peak);
+        }
+        Ok(Self {
+            name,
+            sample_rate,
+            directions,
+            left,
+            right,
+        })
+    }
+
+    pub fn measurements(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Longest response in the set, in samples at its own rate.
+    pub fn ir_length(&self) -> usize {
+        self.left
+            .iter()
+            .chain(&self.right)
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Left/right HRIRs towards (`azimuth`, `elevation`) in degrees (SOFA convention: 0°
+    /// is straight ahead, +90° azimuth is left), resampled to `sample_rate`. Blends the
+    /// nearest measured directions by inverse angular distance.
+    pub fn interpolate(
+        &self,
+        azimuth: f32,
+        elevation: f32,
+        sample_rate: f32,
+    ) -> (Vec<f32>, Vec<f32>) {
+        let target = spherical_direction(azimuth, elevation);
+        let mut nearest = self
+            .directions
+            .iter()
+            .enumerate()
+            .map(|(index, direction)| {
+                let cosine = dot(*direction, target).clamp(-1.0, 1.0);
+                (index, cosine.acos())
+            })
+            .collect::<Vec<_>>();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        nearest.truncate(NEIGHBOURS);
+
+        let weights = if nearest[0].1 < 1e-4 {
+            vec![(nearest[0].0, 1.0)]
+        } else {
+            let total = nearest.iter().map(|(_, angle)| 1.0 / angle).sum::<f32>();
+            nearest
+                .iter()
+                .map(|(index, angle)| (*index, 1.0 / angle / total))
+                .collect()
+        };
+
+        let ratio = self.sample_rate / sample_rate.max(1.0);
+        let length = ((self.ir_length() as f32 / ratio).ceil() as usize).min(MAX_HRIR_LENGTH);
+        let mut left = vec![0.0_f32; length];
+        let mut right = vec![0.0_f32; length];
+        for (index, weight) in weights {
+            accumulate_resampled(&mut left, &self.left[index], ratio, weight);
+            accumulate_resampled(&mut right, &self.right[index], ratio, weight);
+        }
+        (left, right)
+    }
+}
+
+/// Adds `source` (at its own rate) into `out`, reading it `ratio` source samples per
+/// output sample with linear interpolation. When upsampling, scaling by `ratio` keeps the
+/// overall gain of the response.
+fn accumulate_resampled(out: &mut [f32], source: &[f32], ratio: f32, weight: f32) {
+    let gain = weight * ratio.min(1.0);
+    for (n, slot) in out.iter_mut().enumerate() {
+        let position = n as f32 * ratio;
+        let index = position.floor() as usize;
+        let Some(&current) = source.get(index) else {
+            break;
+        };
+        let next = source.get(index + 1).copied().unwrap_or(0.0);
+        let fraction = position - index as f32;
+        *slot += (current + (next - current) * fraction) * gain;
+    }
+}
+
+fn spherical_direction(azimuth: f32, elevation: f32) -> [f32; 3] {
+    let (az, el) = (azimuth.to_radians(), elevation.to_radians());
+    [el.cos() * az.cos(), el.cos() * az.sin(), el.sin()]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    if length <= f32::EPSILON {
+        [1.0, 0.0, 0.0]
+    } else {
+        [v[0] / length, v[1] / length, v[2] / length]
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spherical_direction, HrirSet};
+
+    fn impulse(at: usize, gain: f32) -> Vec<f32> {
+        let mut ir = vec![0.0; 8];
+        ir[at] = gain;
+        ir
+    }
+
+    #[test]
+    fn blends_nearest_measured_directions() {
+        let set = HrirSet::from_measurements(
+            "test".to_string(),
+            48_000.0,
+            vec![
+                spherical_direction(0.0, 0.0),
+                spherical_direction(90.0, 0.0),
+                spherical_direction(180.0, 0.0),
+                spherical_direction(270.0, 0.0),
+            ],
+            vec![
+                impulse(0, 2.0),
+                impulse(0, 2.0),
+                impulse(1, 1.0),
+                impulse(2, 1.0),
+            ],
+            vec![
+                impulse(0, 2.0),
+                impulse(2, 1.0),
+                impulse(1, 1.0),
+                impulse(0, 2.0),
+            ],
+        )
+        .unwrap();
+
+        // Exactly on a measurement: that response alone, normalized to unit peak energy.
+        let (left, right) = set.interpolate(90.0, 0.0, 48_000.0);
+        assert_eq!(left[0], 1.0);
+        assert_eq!(right[2], 0.5);
+
+        // Half way between front and left: equal parts of both, plus a smaller share of
+        // the third-nearest measurement.
+        let (left, _) = set.interpolate(45.0, 0.0, 48_000.0);
+        assert!(left[0] > 0.75 && left[0] < 1.0, "{left:?}");
+
+        // Resampling to half the rate halves the length and keeps the gain.
+        let (left, _) = set.interpolate(0.0, 0.0, 24_000.0);
+        assert_eq!(left.len(), 4);
+        assert_eq!(left[0], 1.0);
+    }
+}
@@ -1,16 +1,67 @@
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
 
-use super::filters::BiquadFilter;
+use serde::{Deserialize, Serialize};
+
+use super::filters::{sanitize_frequency, sanitize_q, BiquadFilter, EqBandSettings, FilterType};
+use super::sofa::{HrirSet, MAX_HRIR_LENGTH};
+use super::spatial_motion::SourceMotion;
+use super::vbap::{self, MAX_SPEAKER_CHANNELS};
 
 /// Speed of sound in air (m/s).
-const SPEED_OF_SOUND: f32 = 343.0;
+pub(super) const SPEED_OF_SOUND: f32 = 343.0;
 /// Maximum ITD delay in samples (capped to avoid excessive buffer usage).
 const MAX_DELAY_SAMPLES: usize = 128;
 /// Number of early reflection taps per source.
 const NUM_REFLECTIONS: usize = 6;
+/// Deepest pinna notch, reached 45° below the horizon.
+const PINNA_NOTCH_DB: f32 = -10.0;
+/// Boost of the ~8 kHz "above" band (Blauert) for a source straight overhead.
+const OVERHEAD_BOOST_DB: f32 = 6.0;
+/// Frames between updates of moving sources, about 10 ms at 48 kHz.
+const MOTION_UPDATE_FRAMES: u32 = 512;
+
+/// Names of the stems a source can play. The room starts with a source for each of the
+/// first four; piano and guitar only come from six-stem separation.
+pub const SOURCE_NAMES: [&str; 6] = ["vocals", "drums", "bass", "other", "piano", "guitar"];
+/// Sources the room starts with.
+const DEFAULT_SOURCE_COUNT: usize = 4;
+/// Stem that plays the stems no source claims.
+const OTHER_STEM: usize = 3;
+/// One mono sample of each separated stem, in `SOURCE_NAMES` order.
+pub type StemFrame = [f32; SOURCE_NAMES.len()];
+/// Most sources a room may hold, including the defaults.
+pub const MAX_SOURCES: usize = 16;
+/// Bands of each source's own EQ.
+pub const SOURCE_EQ_BANDS: usize = 4;
+/// Type and frequency of each source EQ band: a low shelf, two peaks and a high shelf.
+const SOURCE_EQ_LAYOUT: [(FilterType, f32); SOURCE_EQ_BANDS] = [
+    (FilterType::LowShelf, 100.0),
+    (FilterType::Peaking, 500.0),
+    (FilterType::Peaking, 2_500.0),
+    (FilterType::HighShelf, 8_000.0),
+];
+
+/// Where the room is rendered: binaural stereo for headphones, or panned (VBAP) over
+/// the real speakers of a 5.1 or 7.1 output. Speaker mode falls back to binaural on
+/// other outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpatialOutputMode {
+    #[default]
+    Binaural,
+    Speakers,
+}
 
-/// Names for the four stem sources used in spatial positioning.
-pub const SOURCE_NAMES: [&str; 4] = ["vocals", "drums", "bass", "other"];
+/// How loud one source plays in the room. While any source is soloed, only soloed
+/// sources are heard; mute wins over solo.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceMix {
+    /// Gain in dB, -60 to +12.
+    pub gain_db: f32,
+    pub muted: bool,
+    pub solo: bool,
+}
 
 /// 3-D position in the virtual room.
 #[derive(Clone, Copy, Debug)]
@@ -32,21 +83,48 @@ impl Vec3 {
         (dx * dx + dy * dy + dz * dz).sqrt()
     }
 
-    /// Azimuth angle (radians) from `self` looking towards `other`, projected on the XY plane.
-    fn azimuth_to(&self, other: &Vec3) -> f32 {
+    /// Head-relative (azimuth, elevation) in degrees towards `other` for a listener at
+    /// `self` facing +y, using the SOFA convention (0° ahead, +90° to the left).
+    fn head_direction_to(&self, other: &Vec3) -> (f32, f32) {
         let dx = other.x - self.x;
         let dy = other.y - self.y;
-        dy.atan2(dx)
+        let dz = other.z - self.z;
+        let azimuth = (-dx).atan2(dy).to_degrees();
+        let elevation = dz.atan2(dx.hypot(dy)).to_degrees();
+        (azimuth, elevation)
+    }
+
+    /// Where `other` sits for a listener at `self` whose head is turned by `orientation`
+    /// (yaw, pitch, roll in degrees), expressed as if the head still faced +y. Sources
+    /// therefore stay put in the room while the head turns.
+    fn heard_from(&self, other: &Vec3, orientation: [f32; 3]) -> Vec3 {
+        let [yaw, pitch, roll] = orientation.map(f32::to_radians);
+        let (x, y, z) = (other.x - self.x, other.y - self.y, other.z - self.z);
+        // Undo the yaw (about z), then the pitch (about x), then the roll (about y).
+        let (sin, cos) = (-yaw).sin_cos();
+        let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+        let (sin, cos) = (-pitch).sin_cos();
+        let (y, z) = (y * cos - z * sin, y * sin + z * cos);
+        let (sin, cos) = (-roll).sin_cos();
+        let (x, z) = (x * cos + z * sin, z * cos - x * sin);
+        Vec3::new(self.x + x, self.y + y, self.z + z)
     }
 }
 
 /// Parameters for a single sound source inside the virtual room.
 struct SpatialSource {
+    /// Stem or auxiliary input the source plays, unique within the room.
+    name: String,
+    /// Index of the stem in a `StemFrame` when the name is one of `SOURCE_NAMES`.
+    stem: Option<usize>,
     /// Atomic x, y, z packed as f32 bits for lock-free updates.
     x_bits: AtomicU32,
     y_bits: AtomicU32,
     z_bits: AtomicU32,
     active: AtomicBool,
+    gain_db_bits: AtomicU32,
+    muted: AtomicBool,
+    solo: AtomicBool,
 
     // Per-source processing state (mutated only on the audio thread)
     delay_line_l: Vec<f32>,
@@ -56,9 +134,24 @@ struct SpatialSource {
     itd_delay_r: usize,
     gain_l: f32,
     gain_r: f32,
+    /// Gain on each speaker when rendering to speakers, distance and mix included.
+    speaker_gains: [f32; MAX_SPEAKER_CHANNELS],
     /// Simple low-pass filter for ILD shadow on the far ear.
     shadow_filter_l: BiquadFilter,
     shadow_filter_r: BiquadFilter,
+    /// Elevation cues on both ears: the pinna notch and the overhead band.
+    pinna_l: [BiquadFilter; 2],
+    pinna_r: [BiquadFilter; 2],
+    /// The source's own EQ, applied to its input ahead of everything else.
+    eq_bands: [EqBandSettings; SOURCE_EQ_BANDS],
+    eq: [BiquadFilter; SOURCE_EQ_BANDS],
+    /// Whether every EQ band is a 0 dB peak or shelf, i.e. the EQ does nothing.
+    eq_flat: bool,
+    /// With an HRTF loaded, a source whose EQ is not flat cannot share the combined
+    /// FIR: it convolves its own equalized input history with its own HRIR pair.
+    eq_history: Vec<f32>,
+    hrir_l: Vec<f32>,
+    hrir_r: Vec<f32>,
     /// Early reflection taps (delay in samples, attenuation).
     reflection_taps: Vec<(usize, f32)>,
     reflection_buffer_l: Vec<f32>,
@@ -67,13 +160,18 @@ struct SpatialSource {
 }
 
 impl SpatialSource {
-    fn new(pos: Vec3) -> Self {
+    fn new(name: &str, pos: Vec3) -> Self {
         let max_ref_delay = 4800_usize; // ~100 ms at 48 kHz
         Self {
+            name: name.to_string(),
+            stem: SOURCE_NAMES.iter().position(|stem| *stem == name),
             x_bits: AtomicU32::new(pos.x.to_bits()),
             y_bits: AtomicU32::new(pos.y.to_bits()),
             z_bits: AtomicU32::new(pos.z.to_bits()),
             active: AtomicBool::new(true),
+            gain_db_bits: AtomicU32::new(0.0_f32.to_bits()),
+            muted: AtomicBool::new(false),
+            solo: AtomicBool::new(false),
             delay_line_l: vec![0.0; MAX_DELAY_SAMPLES],
             delay_line_r: vec![0.0; MAX_DELAY_SAMPLES],
             delay_pos: 0,
@@ -81,8 +179,22 @@ impl SpatialSource {
             itd_delay_r: 0,
             gain_l: 1.0,
             gain_r: 1.0,
+            speaker_gains: [0.0; MAX_SPEAKER_CHANNELS],
             shadow_filter_l: BiquadFilter::new(),
             shadow_filter_r: BiquadFilter::new(),
+            pinna_l: [BiquadFilter::new(), BiquadFilter::new()],
+            pinna_r: [BiquadFilter::new(), BiquadFilter::new()],
+            eq_bands: SOURCE_EQ_LAYOUT.map(|(filter_type, frequency)| EqBandSettings {
+                frequency,
+                gain_db: 0.0,
+                q_factor: 0.707,
+                filter_type,
+            }),
+            eq: [(); SOURCE_EQ_BANDS].map(|_| BiquadFilter::new()),
+            eq_flat: true,
+            eq_history: vec![0.0; MAX_HRIR_LENGTH * 2],
+            hrir_l: Vec::new(),
+            hrir_r: Vec::new(),
             reflection_taps: Vec::new(),
             reflection_buffer_l: vec![0.0; max_ref_delay],
             reflection_buffer_r: vec![0.0; max_ref_delay],
@@ -103,15 +215,67 @@ impl SpatialSource {
         self.y_bits.store(pos.y.to_bits(), Ordering::SeqCst);
         self.z_bits.store(pos.z.to_bits(), Ordering::SeqCst);
     }
+
+    /// What the source hears of this frame: its stem, or the mix, through its EQ.
+    fn input(&mut self, stems: Option<StemFrame>, mono: f32) -> f32 {
+        let raw = match stems {
+            Some(frame) => self.stem.map_or(0.0, |stem| frame[stem]),
+            None => mono,
+        };
+        self.eq
+            .iter_mut()
+            .fold(raw, |sample, filter| filter.process_sample(sample))
+    }
+
+    fn design_eq(&mut self, sample_rate: f32) {
+        for (filter, band) in self.eq.iter_mut().zip(&self.eq_bands) {
+            filter.set_band(sample_rate, band);
+        }
+        self.eq_flat = self.eq_bands.iter().all(|band| {
+            band.gain_db == 0.0
+                && matches!(
+                    band.filter_type,
+                    FilterType::Peaking | FilterType::LowShelf | FilterType::HighShelf
+                )
+        });
+    }
+
+    fn mix(&self) -> SourceMix {
+        SourceMix {
+            gain_db: f32::from_bits(self.gain_db_bits.load(Ordering::Relaxed)),
+            muted: self.muted.load(Ordering::Relaxed),
+            solo: self.solo.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Linear level of the source, given whether any source in the room is soloed.
+    fn level(&self, any_solo: bool) -> f32 {
+        let mix = self.mix();
+        if mix.muted || (any_solo && !mix.solo) {
+            0.0
+        } else {
+            10.0_f32.powf(mix.gain_db / 20.0)
+        }
+    }
 }
 
 /// Virtual room for spatial audio processing using simplified HRTF (binaural pan).
 ///
-/// Processing chain per source:
-///   1. Compute ITD (inter-aural time difference) from azimuth → per-ear delay.
+/// Processing chain per source, after the source's own EQ:
+///   1. Compute ITD (inter-aural time difference) from the lateral angle → per-ear delay.
 ///   2. Compute ILD (inter-aural level difference) → per-ear gain + head-shadow LP filter.
-///   3. Distance attenuation (inverse-distance).
-///   4. Early reflections from virtual walls.
+///   3. Elevation cues: a pinna notch that deepens below the horizon and rises in
+///      frequency above it, plus the ~8 kHz band that makes overhead sources sound above.
+///   4. Distance attenuation (inverse-distance).
+///   5. Early reflections from virtual walls.
+///
+/// With a measured HRTF set loaded, steps 1–3 are replaced by convolution with the
+/// HRIRs interpolated towards each source. Sources with a flat EQ hear the same mono
+/// input, so their distance-weighted HRIRs are summed into one FIR per ear; equalized
+/// sources are convolved one by one.
+///
+/// While separated stems play (`set_stem_frame`), each stem source hears its own stem
+/// instead of the mix, other sources are silent, and every source is convolved alone.
 ///
 /// The node accepts a normal stereo frame and outputs a binaural stereo frame.
 pub struct SpatialRoomNode {
@@ -126,11 +290,44 @@ pub struct SpatialRoomNode {
 
     sample_rate: f32,
 
-    /// Listener is always at the centre of the room by default.
-    listener: Vec3,
-
-    /// Four sources: Vocals (0), Drums (1), Bass (2), Other (3).
+    /// Listener position in metres, at the centre of the default room until moved.
+    listener_x_bits: AtomicU32,
+    listener_y_bits: AtomicU32,
+    listener_z_bits: AtomicU32,
+    /// Direction the listener faces in degrees (positive turns left, 0 faces +y).
+    facing_bits: AtomicU32,
+    /// Head orientation in degrees, relative to the facing: yaw (positive turns left),
+    /// pitch (positive looks up), roll (positive tilts the right ear down).
+    yaw_bits: AtomicU32,
+    pitch_bits: AtomicU32,
+    roll_bits: AtomicU32,
+
+    /// Named sources, starting with Vocals (0), Drums (1), Bass (2), Other (3); more
+    /// can be added and any removed, which shifts the indices after it.
     sources: Vec<SpatialSource>,
+    /// Automation per source, evaluated on the audio thread.
+    motions: Vec<Option<SourceMotion>>,
+    /// Frames processed since the motions were last changed.
+    motion_frames: u64,
+
+    /// Measured HRTF set; `None` uses the ITD/ILD model.
+    hrtf: Option<Arc<HrirSet>>,
+    /// Combined direct-path FIR per ear, rebuilt on recalculation.
+    hrir_l: Vec<f32>,
+    hrir_r: Vec<f32>,
+    /// Mono input history, stored twice over so the newest `MAX_HRIR_LENGTH` samples
+    /// are always one contiguous slice.
+    hrir_history: Vec<f32>,
+    hrir_pos: usize,
+    /// Stems of the frame being processed; `None` spatializes the mix.
+    stem_frame: Option<StemFrame>,
+
+    /// Whether `SpatialOutputMode::Speakers` is selected.
+    speakers_requested: AtomicBool,
+    /// Channels of the output, as last reported by the engine.
+    output_channels: usize,
+    /// Every speaker's sample of the last frame rendered to speakers.
+    speaker_frame: [f32; MAX_SPEAKER_CHANNELS],
 }
 
 impl SpatialRoomNode {
@@ -140,8 +337,6 @@ impl SpatialRoomNode {
         let default_length: f32 = 10.0;
         let default_height: f32 = 3.5;
 
-        let listener = Vec3::new(default_width / 2.0, default_length / 2.0, 1.7);
-
         // Default positions: front-left, front-right, front-center, rear
         let default_positions = [
             Vec3::new(2.0, 7.0, 1.7), // Vocals: front-center-left
@@ -150,9 +345,10 @@ impl SpatialRoomNode {
             Vec3::new(4.0, 3.0, 1.7), // Other: rear
         ];
 
-        let sources: Vec<SpatialSource> = default_positions
+        let sources: Vec<SpatialSource> = SOURCE_NAMES[..DEFAULT_SOURCE_COUNT]
             .iter()
-            .map(|pos| SpatialSource::new(*pos))
+            .zip(default_positions)
+            .map(|(name, pos)| SpatialSource::new(name, pos))
             .collect();
 
         let mut node = Self {
@@ -163,9 +359,29 @@ impl SpatialRoomNode {
             height_bits: AtomicU32::new(default_height.to_bits()),
             damping_bits: AtomicU32::new(0.5_f32.to_bits()),
             sample_rate: sr,
-            listener,
+            listener_x_bits: AtomicU32::new((default_width / 2.0).to_bits()),
+            listener_y_bits: AtomicU32::new((default_length / 2.0).to_bits()),
+            listener_z_bits: AtomicU32::new(1.7_f32.to_bits()),
+            facing_bits: AtomicU32::new(0.0_f32.to_bits()),
+            yaw_bits: AtomicU32::new(0.0_f32.to_bits()),
+            pitch_bits: AtomicU32::new(0.0_f32.to_bits()),
+            roll_bits: AtomicU32::new(0.0_f32.to_bits()),
+            motions: vec![None; sources.len()],
+            motion_frames: 0,
             sources,
+            hrtf: None,
+            hrir_l: Vec::new(),
+            hrir_r: Vec::new(),
+            hrir_history: vec![0.0; MAX_HRIR_LENGTH * 2],
+            hrir_pos: 0,
+            stem_frame: None,
+            speakers_requested: AtomicBool::new(false),
+            output_channels: 2,
+            speaker_frame: [0.0; MAX_SPEAKER_CHANNELS],
         };
+        for src in &mut node.sources {
+            src.design_eq(sr);
+        }
         node.recalculate();
         node
     }
@@ -180,6 +396,33 @@ impl SpatialRoomNode {
         self.enabled.load(Ordering::Relaxed)
     }
 
+    pub fn set_output_mode(&self, mode: SpatialOutputMode) {
+        self.speakers_requested
+            .store(mode == SpatialOutputMode::Speakers, Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    pub fn output_mode(&self) -> SpatialOutputMode {
+        if self.speakers_requested.load(Ordering::Relaxed) {
+            SpatialOutputMode::Speakers
+        } else {
+            SpatialOutputMode::Binaural
+        }
+    }
+
+    /// Whether the room is panned over speakers: speaker mode on a 5.1 or 7.1 output.
+    pub fn renders_speakers(&self) -> bool {
+        self.speakers().is_some()
+    }
+
+    /// Azimuths of the speakers the room is rendered to, `None` while binaural.
+    fn speakers(&self) -> Option<&'static [Option<f32>]> {
+        if !self.speakers_requested.load(Ordering::Relaxed) {
+            return None;
+        }
+        vbap::speaker_layout(self.output_channels)
+    }
+
     pub fn set_room_size(&self, width: f32, length: f32, height: f32) {
         self.width_bits
             .store(width.clamp(2.0, 50.0).to_bits(), Ordering::SeqCst);
@@ -190,6 +433,19 @@ impl SpatialRoomNode {
         self.needs_update.store(true, Ordering::SeqCst);
     }
 
+    /// Room width, length and height in metres.
+    pub fn room_size(&self) -> (f32, f32, f32) {
+        (
+            f32::from_bits(self.width_bits.load(Ordering::Relaxed)),
+            f32::from_bits(self.length_bits.load(Ordering::Relaxed)),
+            f32::from_bits(self.height_bits.load(Ordering::Relaxed)),
+        )
+    }
+
+    pub fn damping(&self) -> f32 {
+        f32::from_bits(self.damping_bits.load(Ordering::Relaxed))
+    }
+
     pub fn set_damping(&self, val: f32) {
         self.damping_bits
             .store(val.clamp(0.0, 1.0).to_bits(), Ordering::SeqCst);
@@ -206,9 +462,199 @@ impl SpatialRoomNode {
     pub fn set_source_active(&self, index: usize, active: bool) {
         if let Some(src) = self.sources.get(index) {
             src.active.store(active, Ordering::SeqCst);
+            self.needs_update.store(true, Ordering::SeqCst);
         }
     }
 
+    /// Moves the listener inside the room (metres); positions outside it are clamped to
+    /// the walls and non-finite ones ignored.
+    pub fn set_listener_position(&self, x: f32, y: f32, z: f32) {
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+            return;
+        }
+        let (width, length, height) = self.room_size();
+        self.listener_x_bits
+            .store(x.clamp(0.0, width).to_bits(), Ordering::SeqCst);
+        self.listener_y_bits
+            .store(y.clamp(0.0, length).to_bits(), Ordering::SeqCst);
+        self.listener_z_bits
+            .store(z.clamp(0.0, height).to_bits(), Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    pub fn listener_position(&self) -> (f32, f32, f32) {
+        (
+            f32::from_bits(self.listener_x_bits.load(Ordering::Relaxed)),
+            f32::from_bits(self.listener_y_bits.load(Ordering::Relaxed)),
+            f32::from_bits(self.listener_z_bits.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Turns the listener to face a direction in the room (degrees, positive turns
+    /// left), normalized to -180..180. Head tracking turns the head relative to it.
+    pub fn set_listener_yaw(&self, degrees: f32) {
+        if !degrees.is_finite() {
+            return;
+        }
+        let yaw = (degrees + 180.0).rem_euclid(360.0) - 180.0;
+        self.facing_bits.store(yaw.to_bits(), Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    pub fn listener_yaw(&self) -> f32 {
+        f32::from_bits(self.facing_bits.load(Ordering::Relaxed))
+    }
+
+    /// Turns the listener's head (degrees); the sources keep their room positions, so
+    /// the scene rotates the other way around the ears. Non-finite angles are ignored.
+    pub fn set_listener_orientation(&self, yaw: f32, pitch: f32, roll: f32) {
+        if !(yaw.is_finite() && pitch.is_finite() && roll.is_finite()) {
+            return;
+        }
+        self.yaw_bits.store(yaw.to_bits(), Ordering::SeqCst);
+        self.pitch_bits
+            .store(pitch.clamp(-90.0, 90.0).to_bits(), Ordering::SeqCst);
+        self.roll_bits.store(roll.to_bits(), Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    pub fn listener_orientation(&self) -> (f32, f32, f32) {
+        (
+            f32::from_bits(self.yaw_bits.load(Ordering::Relaxed)),
+            f32::from_bits(self.pitch_bits.load(Ordering::Relaxed)),
+            f32::from_bits(self.roll_bits.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Adds a source at a position in metres and returns its index. Names are trimmed
+    /// and must be unique.
+    pub fn add_source(&mut self, name: &str, x: f32, y: f32, z: f32) -> Result<usize, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Spatial source name cannot be empty".to_string());
+        }
+        if self.source_index(name).is_some() {
+            return Err(format!("Spatial source {name} already exists"));
+        }
+        if self.sources.len() >= MAX_SOURCES {
+            return Err(format!(
+                "A room holds at most {MAX_SOURCES} spatial sources"
+            ));
+        }
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+            return Err("Spatial source position must be finite".to_string());
+        }
+        let mut src = SpatialSource::new(name, Vec3::new(x, y, z));
+        src.design_eq(self.sample_rate);
+        self.sources.push(src);
+        self.motions.push(None);
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(self.sources.len() - 1)
+    }
+
+    /// Removes a source and its motion; the sources after it move down one index.
+    pub fn remove_source(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .source_index(name)
+            .ok_or_else(|| format!("Unknown spatial source: {name}"))?;
+        self.sources.remove(index);
+        self.motions.remove(index);
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn source_index(&self, name: &str) -> Option<usize> {
+        let name = name.trim();
+        self.sources.iter().position(|src| src.name == name)
+    }
+
+    pub fn source_names(&self) -> Vec<String> {
+        self.sources.iter().map(|src| src.name.clone()).collect()
+    }
+
+    /// Sets a source's gain, mute and solo together; the gain is clamped to -60..+12 dB.
+    pub fn set_source_mix(&self, index: usize, mix: SourceMix) -> Result<(), String> {
+        if !mix.gain_db.is_finite() {
+            return Err("Spatial source gain must be finite".to_string());
+        }
+        let src = self
+            .sources
+            .get(index)
+            .ok_or_else(|| format!("Spatial source {index} does not exist"))?;
+        src.gain_db_bits
+            .store(mix.gain_db.clamp(-60.0, 12.0).to_bits(), Ordering::SeqCst);
+        src.muted.store(mix.muted, Ordering::SeqCst);
+        src.solo.store(mix.solo, Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn source_mix(&self) -> Vec<SourceMix> {
+        self.sources.iter().map(SpatialSource::mix).collect()
+    }
+
+    /// Changes one band of a source's EQ, like `update_eq_band` does for the master EQ.
+    /// Band types are fixed: a low shelf, two peaks and a high shelf.
+    pub fn update_source_eq_band(
+        &mut self,
+        index: usize,
+        band: usize,
+        frequency: f32,
+        gain_db: f32,
+        q_factor: f32,
+    ) -> Result<(), String> {
+        if !(frequency.is_finite() && gain_db.is_finite() && q_factor.is_finite()) {
+            return Err("Source EQ values must be finite numbers".to_string());
+        }
+        let sr = self.sample_rate;
+        let src = self
+            .sources
+            .get_mut(index)
+            .ok_or_else(|| format!("Spatial source {index} does not exist"))?;
+        let settings = src.eq_bands.get_mut(band).ok_or_else(|| {
+            format!(
+                "Band index out of range: {band} (valid: 0 to {})",
+                SOURCE_EQ_BANDS - 1
+            )
+        })?;
+        settings.frequency = sanitize_frequency(frequency, sr);
+        settings.gain_db = gain_db.clamp(-24.0, 24.0);
+        settings.q_factor = sanitize_q(q_factor);
+        src.design_eq(sr);
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Flattens a source's EQ back to its default bands.
+    pub fn reset_source_eq(&mut self, index: usize) -> Result<(), String> {
+        let sr = self.sample_rate;
+        let src = self
+            .sources
+            .get_mut(index)
+            .ok_or_else(|| format!("Spatial source {index} does not exist"))?;
+        for (settings, (filter_type, frequency)) in src.eq_bands.iter_mut().zip(SOURCE_EQ_LAYOUT) {
+            *settings = EqBandSettings {
+                frequency,
+                gain_db: 0.0,
+                q_factor: 0.707,
+                filter_type,
+            };
+        }
+        src.design_eq(sr);
+        self.needs_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn source_eq(&self, index: usize) -> Option<Vec<EqBandSettings>> {
+        self.sources.get(index).map(|src| src.eq_bands.to_vec())
+    }
+
+    fn any_solo(&self) -> bool {
+        self.sources
+            .iter()
+            .any(|src| src.solo.load(Ordering::Relaxed))
+    }
+
     pub fn source_positions(&self) -> Vec<(f32, f32, f32, bool)> {
         self.sources
             .iter()
@@ -219,8 +665,9 @@ impl SpatialRoomNode {
             .collect()
     }
 
-    /// Distributes 4 sources in a 180° arc in front of the listener,
-    /// ordered by approximate frequency content (bass → other).
+    /// Distributes the sources over a 120° arc in front of the listener, the stems
+    /// ordered by approximate frequency content (bass → vocals) and any other sources
+    /// after them.
     pub fn auto_orchestra(&self) {
         let w = f32::from_bits(self.width_bits.load(Ordering::Relaxed));
         let l = f32::from_bits(self.length_bits.load(Ordering::Relaxed));
@@ -230,32 +677,114 @@ impl SpatialRoomNode {
         let radius = (w.min(l) / 2.0) * 0.75;
 
         // Order by typical spectral content: bass, drums, other, vocals
-        // Arc angles: -90° (left) to +90° (right) mapped to 4 positions
-        let order: [usize; 4] = [2, 1, 3, 0]; // bass, drums, other, vocals
-        let angles: [f32; 4] = [-60.0, -20.0, 20.0, 60.0];
-
-        for (slot, &src_idx) in order.iter().enumerate() {
-            let angle_rad = angles[slot].to_radians();
+        const STEM_ORDER: [&str; 4] = ["bass", "drums", "other", "vocals"];
+        let mut order: Vec<&SpatialSource> = self.sources.iter().collect();
+        order.sort_by_key(|src| {
+            STEM_ORDER
+                .iter()
+                .position(|stem| *stem == src.name)
+                .unwrap_or(STEM_ORDER.len())
+        });
+
+        // Arc angles from -60° (left) to +60° (right), evenly spaced
+        let last = order.len().saturating_sub(1);
+        for (slot, src) in order.into_iter().enumerate() {
+            let angle = if last == 0 {
+                0.0
+            } else {
+                -60.0 + 120.0 * slot as f32 / last as f32
+            };
+            let angle_rad = angle.to_radians();
             let x = cx + radius * angle_rad.sin();
             let y = cy + radius * angle_rad.cos();
             let z = (h * 0.5).min(2.0);
-            if let Some(src) = self.sources.get(src_idx) {
-                src.set_position(Vec3::new(x, y, z));
-            }
+            src.set_position(Vec3::new(x, y, z));
+        }
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    /// Moves source `index` along `motion`, or leaves it where it is with `None`. Every
+    /// motion is timed from the last change, so sources set together move in step.
+    pub fn set_source_motion(
+        &mut self,
+        index: usize,
+        motion: Option<SourceMotion>,
+    ) -> Result<(), String> {
+        if let Some(motion) = &motion {
+            motion.validate()?;
         }
+        let slot = self
+            .motions
+            .get_mut(index)
+            .ok_or_else(|| format!("Spatial source {index} does not exist"))?;
+        *slot = motion;
+        self.motion_frames = 0;
+        self.apply_motions();
+        Ok(())
+    }
+
+    pub fn source_motions(&self) -> Vec<Option<SourceMotion>> {
+        self.motions.clone()
+    }
+
+    /// Switches the direct path to convolution with `hrtf`, or back to the ITD/ILD model.
+    pub fn set_hrtf(&mut self, hrtf: Option<Arc<HrirSet>>) {
+        self.hrtf = hrtf;
+        self.hrir_history.fill(0.0);
         self.needs_update.store(true, Ordering::SeqCst);
     }
 
+    pub fn hrtf(&self) -> Option<Arc<HrirSet>> {
+        self.hrtf.clone()
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         let sr = sample_rate.max(8_000.0);
         if (sr - self.sample_rate).abs() > f32::EPSILON {
             self.sample_rate = sr;
+            for src in &mut self.sources {
+                src.design_eq(sr);
+            }
             self.needs_update.store(true, Ordering::SeqCst);
         }
     }
 
     // ── Audio-thread processing ────────────────────────────────────────
 
+    /// Hands over the stems of the next frame, or `None` to go back to the mix. Piano
+    /// and guitar play through "other" unless the room has sources of their own, so a
+    /// six-stem track is heard whole in a four-source room.
+    pub fn set_stem_frame(&mut self, stems: Option<StemFrame>) {
+        if stems.is_some() != self.stem_frame.is_some() {
+            // HRTF sources stop (or start again) sharing the combined FIR.
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+        self.stem_frame = stems.map(|mut frame| {
+            for stem in DEFAULT_SOURCE_COUNT..SOURCE_NAMES.len() {
+                if !self.sources.iter().any(|src| src.stem == Some(stem)) {
+                    frame[OTHER_STEM] += frame[stem];
+                    frame[stem] = 0.0;
+                }
+            }
+            frame
+        });
+    }
+
+    /// The output's channel count, reported by the engine with every buffer.
+    pub fn set_output_channels(&mut self, channels: usize) {
+        if channels != self.output_channels {
+            self.output_channels = channels;
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Output `channel` of the frame just processed, when the room is rendered to
+    /// speakers; `process_stereo_frame` returns the front pair.
+    pub fn speaker_sample(&self, channel: usize) -> Option<f32> {
+        (self.is_enabled() && self.renders_speakers())
+            .then(|| self.speaker_frame.get(channel).copied().unwrap_or(0.0))
+    }
+
     /// Process a single stereo frame.  When spatial mode is disabled the
     /// function returns `(left, right)` immediately (zero CPU cost bypass).
     pub fn process_stereo_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
@@ -263,24 +792,59 @@ impl SpatialRoomNode {
             return (left, right);
         }
 
+        if self
+            .motion_frames
+            .is_multiple_of(u64::from(MOTION_UPDATE_FRAMES))
+        {
+            self.apply_motions();
+        }
+        self.motion_frames += 1;
         if self.needs_update.swap(false, Ordering::SeqCst) {
             self.recalculate();
         }
+        if self.renders_speakers() {
+            return self.process_speaker_frame(left, right);
+        }
 
         // Mix the input mono-down per source, then spatialise each independently.
         let mono = (left + right) * 0.5;
+        let stems = self.stem_frame;
         let mut out_l = 0.0_f32;
         let mut out_r = 0.0_f32;
 
+        let convolving = self.hrtf.is_some();
+        let hrir_pos = (self.hrir_pos + 1) % MAX_HRIR_LENGTH;
+        let newest = hrir_pos + MAX_HRIR_LENGTH;
+        if convolving {
+            // ── Direct path through the measured HRIRs ──
+            self.hrir_pos = hrir_pos;
+            self.hrir_history[hrir_pos] = mono;
+            self.hrir_history[newest] = mono;
+            out_l = convolve(&self.hrir_l, &self.hrir_history, newest);
+            out_r = convolve(&self.hrir_r, &self.hrir_history, newest);
+        }
+
         for src in &mut self.sources {
             if !src.active.load(Ordering::Relaxed) {
                 continue;
             }
+            let input = src.input(stems, mono);
+
+            if convolving {
+                src.eq_history[hrir_pos] = input;
+                src.eq_history[newest] = input;
+                if !src.eq_flat || stems.is_some() {
+                    out_l += convolve(&src.hrir_l, &src.eq_history, newest);
+                    out_r += convolve(&src.hrir_r, &src.eq_history, newest);
+                }
+                write_reflections(src, input, &mut out_l, &mut out_r);
+                continue;
+            }
 
             // ── Direct path with ITD delay ──
             let dl_len = src.delay_line_l.len();
-            src.delay_line_l[src.delay_pos] = mono;
-            src.delay_line_r[src.delay_pos] = mono;
+            src.delay_line_l[src.delay_pos] = input;
+            src.delay_line_r[src.delay_pos] = input;
 
             let read_l = (src.delay_pos + dl_len - src.itd_delay_l) % dl_len;
             let read_r = (src.delay_pos + dl_len - src.itd_delay_r) % dl_len;
@@ -288,53 +852,95 @@ impl SpatialRoomNode {
             let direct_l = src.delay_line_l[read_l] * src.gain_l;
             let direct_r = src.delay_line_r[read_r] * src.gain_r;
 
-            // Apply head-shadow low-pass on the far ear
+            // Apply head-shadow low-pass on the far ear, then the elevation cues
             let direct_l = src.shadow_filter_l.process_sample(direct_l);
             let direct_r = src.shadow_filter_r.process_sample(direct_r);
+            let direct_l = src
+                .pinna_l
+                .iter_mut()
+                .fold(direct_l, |sample, filter| filter.process_sample(sample));
+            let direct_r = src
+                .pinna_r
+                .iter_mut()
+                .fold(direct_r, |sample, filter| filter.process_sample(sample));
 
             out_l += direct_l;
             out_r += direct_r;
 
-            // ── Early reflections ──
-            let rb_len = src.reflection_buffer_l.len();
-            src.reflection_buffer_l[src.reflection_pos] = mono;
-            src.reflection_buffer_r[src.reflection_pos] = mono;
-
-            for &(tap_delay, tap_gain) in &src.reflection_taps {
-                let tap_idx = (src.reflection_pos + rb_len - tap_delay) % rb_len;
-                out_l += src.reflection_buffer_l[tap_idx] * tap_gain * src.gain_l;
-                out_r += src.reflection_buffer_r[tap_idx] * tap_gain * src.gain_r;
-            }
-
+            write_reflections(src, input, &mut out_l, &mut out_r);
             src.delay_pos = (src.delay_pos + 1) % dl_len;
-            src.reflection_pos = (src.reflection_pos + 1) % rb_len;
         }
 
         (out_l, out_r)
     }
 
+    /// Pans every source over the speakers into `speaker_frame` and returns the front
+    /// pair. Real speakers in a real room give the cues the binaural path imitates, so
+    /// there are no ear delays, head shadow, pinna filters or reflections.
+    fn process_speaker_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let mono = (left + right) * 0.5;
+        let stems = self.stem_frame;
+        self.speaker_frame = [0.0; MAX_SPEAKER_CHANNELS];
+        for src in &mut self.sources {
+            if !src.active.load(Ordering::Relaxed) {
+                continue;
+            }
+            let input = src.input(stems, mono);
+            for (out, gain) in self.speaker_frame.iter_mut().zip(src.speaker_gains) {
+                *out += input * gain;
+            }
+        }
+        (self.speaker_frame[0], self.speaker_frame[1])
+    }
+
     // ── Internal recalculation ─────────────────────────────────────────
 
+    /// Moves automated sources to where their motion has them now.
+    fn apply_motions(&self) {
+        let time_s = self.motion_frames as f32 / self.sample_rate;
+        let mut moved = false;
+        for (src, motion) in self.sources.iter().zip(&self.motions) {
+            if let Some(motion) = motion {
+                src.set_position(motion.position_at(time_s));
+                moved = true;
+            }
+        }
+        if moved {
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
     fn recalculate(&mut self) {
         let width = f32::from_bits(self.width_bits.load(Ordering::Relaxed));
         let length = f32::from_bits(self.length_bits.load(Ordering::Relaxed));
         let height = f32::from_bits(self.height_bits.load(Ordering::Relaxed));
         let damping = f32::from_bits(self.damping_bits.load(Ordering::Relaxed));
-        let listener = self.listener;
+        let (x, y, z) = self.listener_position();
+        let listener = Vec3::new(x, y, z);
         let sr = self.sample_rate;
+        let (yaw, pitch, roll) = self.listener_orientation();
+        let orientation = [self.listener_yaw() + yaw, pitch, roll];
 
         // Approximate head radius for ITD computation (Woodworth formula).
         let head_radius: f32 = 0.0875; // metres
+        let any_solo = self.any_solo();
+        let speakers = self.speakers();
 
         for src in &mut self.sources {
             let pos = src.position();
             let dist = listener.distance_to(&pos).max(0.1);
-            let azimuth = listener.azimuth_to(&pos); // radians
+            let heard = listener.heard_from(&pos, orientation);
+            let (sofa_azimuth, elevation) = listener.head_direction_to(&heard);
+            // Radians from straight ahead, positive to the right.
+            let azimuth = -sofa_azimuth.to_radians();
 
             // ── ITD (inter-aural time difference) ──
             // Woodworth approximation: ITD = (r/c) * (sin(θ) + θ)  for |θ| ≤ π/2
-            // Clamp azimuth to ±90° where the approximation is valid.
-            let abs_az = azimuth.abs().min(std::f32::consts::FRAC_PI_2);
+            // θ is the angle off the median plane, so sources behind fold onto the
+            // same angle in front and raised sources move towards the centre.
+            let abs_az = (azimuth.sin().abs() * elevation.to_radians().cos())
+                .clamp(0.0, 1.0)
+                .asin();
             let itd_seconds = (head_radius / SPEED_OF_SOUND) * (abs_az.sin() + abs_az);
             let itd_samples = (itd_seconds * sr).round() as usize;
             let itd_clamped = itd_samples.min(MAX_DELAY_SAMPLES - 1);
@@ -351,7 +957,8 @@ impl SpatialRoomNode {
             // ── ILD (inter-aural level difference) ──
             // Simplified: up to ~6 dB attenuation on the far ear at 90°.
             let ild_db = 6.0 * abs_az.sin();
-            let near_gain = 1.0 / dist; // inverse-distance attenuation
+            // Inverse-distance attenuation times the source's mix level
+            let near_gain = src.level(any_solo) / dist;
             let far_gain = near_gain * 10.0_f32.powf(-ild_db / 20.0);
 
             if azimuth >= 0.0 {
@@ -361,6 +968,10 @@ impl SpatialRoomNode {
                 src.gain_l = near_gain;
                 src.gain_r = far_gain;
             }
+            if let Some(speakers) = speakers {
+                src.speaker_gains =
+                    vbap::vbap_gains(speakers, sofa_azimuth).map(|gain| gain * near_gain);
+            }
 
             // Head-shadow low-pass: cut-off frequency lowers as azimuth increases.
             let shadow_cutoff = 20_000.0 - 12_000.0 * abs_az.sin();
@@ -378,6 +989,13 @@ impl SpatialRoomNode {
                     .set_low_pass(sr, 20_000.0, 0.707);
             }
 
+            // ── Elevation cues ──
+            let (notch_hz, notch_db, overhead_db) = pinna_cues(elevation);
+            for pinna in [&mut src.pinna_l, &mut src.pinna_r] {
+                pinna[0].set_peaking(sr, notch_hz, notch_db, 2.0);
+                pinna[1].set_peaking(sr, 8_000.0, overhead_db, 1.0);
+            }
+
             // ── Early reflections ──
             // Compute image sources for 6 walls (±x, ±y, ±z).
             let walls: [(Vec3, f32); NUM_REFLECTIONS] = [
@@ -409,9 +1027,86 @@ impl SpatialRoomNode {
                 src.reflection_taps.push((delay_samples, atten.max(0.0)));
             }
         }
+
+        self.rebuild_hrir(listener, orientation);
+    }
+
+    /// Sums each active source's interpolated HRIR pair, weighted by inverse distance,
+    /// into the combined per-ear FIRs, or keeps it as the source's own pair when the
+    /// source's EQ is not flat or stems are playing.
+    fn rebuild_hrir(&mut self, listener: Vec3, orientation: [f32; 3]) {
+        self.hrir_l.clear();
+        self.hrir_r.clear();
+        let Some(hrtf) = &self.hrtf else {
+            return;
+        };
+        let any_solo = self.any_solo();
+        let shared = self.stem_frame.is_none();
+        for src in &mut self.sources {
+            src.hrir_l.clear();
+            src.hrir_r.clear();
+            if !src.active.load(Ordering::Relaxed) {
+                continue;
+            }
+            let pos = src.position();
+            let gain = src.level(any_solo) / listener.distance_to(&pos).max(0.1);
+            let heard = listener.heard_from(&pos, orientation);
+            let (azimuth, elevation) = listener.head_direction_to(&heard);
+            let (left, right) = hrtf.interpolate(azimuth, elevation, self.sample_rate);
+            let (target_l, target_r) = if src.eq_flat && shared {
+                (&mut self.hrir_l, &mut self.hrir_r)
+            } else {
+                (&mut src.hrir_l, &mut src.hrir_r)
+            };
+            for (combined, ir) in [(target_l, left), (target_r, right)] {
+                if combined.len() < ir.len() {
+                    combined.resize(ir.len(), 0.0);
+                }
+                for (tap, value) in combined.iter_mut().zip(ir) {
+                    *tap += value * gain;
+                }
+            }
+        }
     }
 }
 
+/// Pinna notch frequency and depth, and the overhead band gain, in dB, for a source
+/// `elevation` degrees above the horizon. Flat at the horizon, so sources at ear
+/// height sound as before. Below, the notch deepens and drops towards 6 kHz; above,
+/// it rises towards 10 kHz and fades out as the overhead band comes in.
+fn pinna_cues(elevation: f32) -> (f32, f32, f32) {
+    let below = (-elevation / 45.0).clamp(0.0, 1.0);
+    let above = (elevation / 90.0).clamp(0.0, 1.0);
+    let notch_hz = 7_000.0 - 1_000.0 * below + 3_000.0 * above;
+    let notch_db =
+        PINNA_NOTCH_DB * below + 0.6 * PINNA_NOTCH_DB * (std::f32::consts::PI * above).sin();
+    (notch_hz, notch_db, OVERHEAD_BOOST_DB * above)
+}
+
+/// Feeds `mono` into the source's early-reflection buffer and adds its wall taps.
+fn write_reflections(src: &mut SpatialSource, mono: f32, out_l: &mut f32, out_r: &mut f32) {
+    let rb_len = src.reflection_buffer_l.len();
+    src.reflection_buffer_l[src.reflection_pos] = mono;
+    src.reflection_buffer_r[src.reflection_pos] = mono;
+
+    for &(tap_delay, tap_gain) in &src.reflection_taps {
+        let tap_idx = (src.reflection_pos + rb_len - tap_delay) % rb_len;
+        *out_l += src.reflection_buffer_l[tap_idx] * tap_gain * src.gain_l;
+        *out_r += src.reflection_buffer_r[tap_idx] * tap_gain * src.gain_r;
+    }
+
+    src.reflection_pos = (src.reflection_pos + 1) % rb_len;
+}
+
+/// FIR output for the sample at `history[newest]`, tap `k` reading `k` samples back.
+pub(super) fn convolve(taps: &[f32], history: &[f32], newest: usize) -> f32 {
+    let start = newest + 1 - taps.len();
+    taps.iter()
+        .zip(history[start..=newest].iter().rev())
+        .map(|(tap, sample)| tap * sample)
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -440,6 +1135,32 @@ mod tests {
         assert!(l.abs() > 0.0 || r.abs() > 0.0);
     }
 
+    #[test]
+    fn speaker_mode_pans_over_surround_outputs_only() {
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        node.set_output_mode(SpatialOutputMode::Speakers);
+        assert!(!node.renders_speakers());
+
+        node.set_output_channels(6);
+        assert!(node.renders_speakers());
+        for index in 1..4 {
+            node.set_source_active(index, false);
+        }
+        let (x, y, z) = node.listener_position();
+        node.set_source_position(0, x, y + 2.0, z);
+        let (l, r) = node.process_stereo_frame(0.5, 0.5);
+        // Straight ahead plays from the centre speaker alone.
+        assert!(l.abs() < 1e-6 && r.abs() < 1e-6);
+        let center = node.speaker_sample(2).expect("speaker mode");
+        assert!(center > 0.1, "{center}");
+        assert_eq!(node.speaker_sample(3), Some(0.0));
+
+        node.set_output_channels(2);
+        node.process_stereo_frame(0.5, 0.5);
+        assert_eq!(node.speaker_sample(2), None);
+    }
+
     #[test]
     fn room_size_is_clamped() {
         let node = SpatialRoomNode::new(48_000.0);
@@ -481,6 +1202,78 @@ mod tests {
         assert!(positions[0].3); // active by default
     }
 
+    #[test]
+    fn sources_can_be_added_and_removed_by_name() {
+        let mut node = SpatialRoomNode::new(48_000.0);
+        assert_eq!(node.add_source(" piano ", 3.0, 7.0, 1.2), Ok(4));
+        assert!(node.add_source("piano", 0.0, 0.0, 0.0).is_err());
+        assert!(node.add_source(" ", 0.0, 0.0, 0.0).is_err());
+        assert_eq!(node.source_positions()[4], (3.0, 7.0, 1.2, true));
+
+        node.remove_source("drums").expect("remove");
+        assert!(node.remove_source("drums").is_err());
+        assert_eq!(
+            node.source_names(),
+            ["vocals", "bass", "other", "piano"].map(String::from)
+        );
+        assert_eq!(node.source_index("piano"), Some(3));
+        assert_eq!(node.source_motions().len(), 4);
+
+        node.set_enabled(true);
+        let (l, r) = node.process_stereo_frame(0.5, 0.5);
+        assert!(l.is_finite() && r.is_finite());
+        node.auto_orchestra();
+        // Piano is not a stem, so it is placed last, at the right end of the arc.
+        let (x, ..) = node.source_positions()[3];
+        assert!(x > 4.0, "piano should sit right of centre: {x}");
+        while node.sources.len() < MAX_SOURCES {
+            let name = format!("aux {}", node.sources.len());
+            node.add_source(&name, 1.0, 1.0, 1.0)
+                .expect("room for more");
+        }
+        assert!(node.add_source("one too many", 1.0, 1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn mute_and_solo_silence_sources() {
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        let peak = |node: &mut SpatialRoomNode| {
+            (0..4_800)
+                .map(|_| {
+                    let (l, r) = node.process_stereo_frame(0.5, 0.5);
+                    l.abs().max(r.abs())
+                })
+                .fold(0.0_f32, f32::max)
+        };
+        let all = peak(&mut node);
+
+        let solo = SourceMix {
+            solo: true,
+            ..SourceMix::default()
+        };
+        node.set_source_mix(1, solo).expect("drums exist");
+        let soloed = peak(&mut node);
+        assert!(soloed > 0.0 && soloed < all, "{soloed} vs {all}");
+
+        // A muted solo leaves nothing to hear once the filters have rung out.
+        let muted = SourceMix {
+            muted: true,
+            ..solo
+        };
+        node.set_source_mix(1, muted).expect("drums exist");
+        peak(&mut node);
+        assert!(peak(&mut node) < 1e-6);
+
+        let loud = SourceMix {
+            gain_db: 40.0,
+            ..SourceMix::default()
+        };
+        node.set_source_mix(1, loud).expect("drums exist");
+        assert_eq!(node.source_mix()[1].gain_db, 12.0);
+        assert!(node.set_source_mix(9, loud).is_err());
+    }
+
     #[test]
     fn auto_orchestra_updates_positions() {
         let node = SpatialRoomNode::new(48_000.0);
@@ -513,6 +1306,280 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hrtf_convolves_with_the_measured_response() {
+        use super::super::sofa::HrirSet;
+
+        // A single measurement: the left ear hears the source one sample late at half
+        // level, the right ear directly.
+        let set = HrirSet::from_measurements(
+            "test".to_string(),
+            48_000.0,
+            vec![[1.0, 0.0, 0.0]],
+            vec![vec![0.0, 0.5, 0.0, 0.0]],
+            vec![vec![1.0, 0.0, 0.0, 0.0]],
+        )
+        .unwrap();
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        node.set_damping(1.0);
+        for i in 1..4 {
+            node.set_source_active(i, false);
+        }
+        // Vocals straight ahead at 2 m, inside a room large enough that no wall
+        // reflection arrives during the test.
+        node.set_room_size(50.0, 50.0, 20.0);
+        node.set_listener_position(25.0, 25.0, 1.7);
+        node.set_source_position(0, 25.0, 27.0, 1.7);
+        node.set_hrtf(Some(Arc::new(set)));
+
+        let first = node.process_stereo_frame(1.0, 1.0);
+        let second = node.process_stereo_frame(0.0, 0.0);
+        assert!(
+            first.0.abs() < 1e-6 && (first.1 - 0.5).abs() < 1e-6,
+            "{first:?}"
+        );
+        assert!(
+            (second.0 - 0.25).abs() < 1e-6 && second.1.abs() < 1e-6,
+            "{second:?}"
+        );
+    }
+
+    #[test]
+    fn source_eq_filters_only_its_source() {
+        use super::super::sofa::HrirSet;
+
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        for i in 1..4 {
+            node.set_source_active(i, false);
+        }
+        let peak = |node: &mut SpatialRoomNode| {
+            (0..9_600)
+                .map(|n| {
+                    let sample =
+                        (2.0 * std::f32::consts::PI * 12_000.0 * n as f32 / 48_000.0).sin();
+                    let (l, r) = node.process_stereo_frame(sample, sample);
+                    if n > 4_800 {
+                        l.abs().max(r.abs())
+                    } else {
+                        0.0
+                    }
+                })
+                .fold(0.0_f32, f32::max)
+        };
+        let flat = peak(&mut node);
+        node.update_source_eq_band(0, 3, 8_000.0, -24.0, 0.707)
+            .expect("high shelf");
+        let cut = peak(&mut node);
+        assert!(
+            cut < flat * 0.2,
+            "high shelf should cut 12 kHz: {cut} vs {flat}"
+        );
+        // Other sources keep a flat EQ.
+        assert!(node.source_eq(1).expect("drums")[3].gain_db == 0.0);
+        assert!(node.update_source_eq_band(0, 4, 1_000.0, 0.0, 1.0).is_err());
+        node.reset_source_eq(0).expect("reset");
+        assert!(node.sources[0].eq_flat);
+
+        // With an HRTF, an equalized source is convolved on its own.
+        let set = HrirSet::from_measurements(
+            "test".to_string(),
+            48_000.0,
+            vec![[1.0, 0.0, 0.0]],
+            vec![vec![1.0, 0.0, 0.0, 0.0]],
+            vec![vec![1.0, 0.0, 0.0, 0.0]],
+        )
+        .unwrap();
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        for i in 1..4 {
+            node.set_source_active(i, false);
+        }
+        node.set_damping(1.0);
+        node.set_room_size(50.0, 50.0, 20.0);
+        node.set_listener_position(25.0, 25.0, 1.7);
+        node.set_source_position(0, 25.0, 26.0, 1.7);
+        node.set_hrtf(Some(Arc::new(set)));
+        node.update_source_eq_band(0, 1, 500.0, 12.0, 1.0)
+            .expect("peak");
+        let (l, _) = node.process_stereo_frame(1.0, 1.0);
+        assert!(l > 1.01, "a +12 dB peak raises the first sample: {l}");
+    }
+
+    #[test]
+    fn stems_feed_only_their_own_sources() {
+        use super::super::sofa::HrirSet;
+
+        let set = HrirSet::from_measurements(
+            "test".to_string(),
+            48_000.0,
+            vec![[1.0, 0.0, 0.0]],
+            vec![vec![1.0, 0.0, 0.0, 0.0]],
+            vec![vec![1.0, 0.0, 0.0, 0.0]],
+        )
+        .unwrap();
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        node.set_damping(1.0);
+        node.set_room_size(50.0, 50.0, 20.0);
+        node.set_listener_position(25.0, 25.0, 1.7);
+        // Vocals 2 m and drums 1 m ahead; bass and other are off.
+        node.set_source_position(0, 25.0, 27.0, 1.7);
+        node.set_source_position(1, 25.0, 26.0, 1.7);
+        node.set_source_active(2, false);
+        node.set_source_active(3, false);
+        node.add_source("synth", 25.0, 26.0, 1.7)
+            .expect("new source");
+        node.set_hrtf(Some(Arc::new(set)));
+
+        // The mix reaches the three active sources.
+        let (mix, _) = node.process_stereo_frame(1.0, 1.0);
+        assert!((mix - 2.5).abs() < 1e-5, "{mix}");
+        // Stems reach their own source only; the synth has no stem.
+        node.set_stem_frame(Some([1.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+        let (vocals, _) = node.process_stereo_frame(1.0, 1.0);
+        assert!((vocals - 0.5).abs() < 1e-5, "{vocals}");
+        node.set_stem_frame(Some([0.0, 1.0, 0.0, 0.0, 0.0, 0.0]));
+        let (drums, _) = node.process_stereo_frame(0.0, 0.0);
+        assert!((drums - 1.0).abs() < 1e-5, "{drums}");
+        node.set_stem_frame(None);
+        let (mix, _) = node.process_stereo_frame(1.0, 1.0);
+        assert!((mix - 2.5).abs() < 1e-5, "{mix}");
+
+        // The piano stem plays through "other" (off here) until the room has a piano.
+        let piano_stem = Some([0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        node.set_stem_frame(piano_stem);
+        let (piano, _) = node.process_stereo_frame(0.0, 0.0);
+        assert!(piano.abs() < 1e-5, "{piano}");
+        node.add_source("piano", 25.0, 26.0, 1.7)
+            .expect("new source");
+        node.set_stem_frame(piano_stem);
+        let (piano, _) = node.process_stereo_frame(0.0, 0.0);
+        assert!((piano - 1.0).abs() < 1e-5, "{piano}");
+    }
+
+    #[test]
+    fn head_rotation_keeps_sources_anchored() {
+        let listener = Vec3::new(0.0, 0.0, 0.0);
+        let ahead = Vec3::new(0.0, 1.0, 0.0);
+
+        // Turning left puts a source that was ahead on the right.
+        let heard = listener.heard_from(&ahead, [90.0, 0.0, 0.0]);
+        assert!(
+            (heard.x - 1.0).abs() < 1e-5 && heard.y.abs() < 1e-5,
+            "{heard:?}"
+        );
+        assert!((listener.head_direction_to(&heard).0 + 90.0).abs() < 1e-3);
+
+        // Looking up puts it below.
+        let heard = listener.heard_from(&ahead, [0.0, 90.0, 0.0]);
+        assert!((heard.z + 1.0).abs() < 1e-5, "{heard:?}");
+
+        // Tilting the right ear down lifts a source on the right above the head.
+        let heard = listener.heard_from(&Vec3::new(1.0, 0.0, 0.0), [0.0, 0.0, 90.0]);
+        assert!((heard.z - 1.0).abs() < 1e-5, "{heard:?}");
+
+        let node = SpatialRoomNode::new(48_000.0);
+        node.set_listener_orientation(30.0, 120.0, f32::NAN);
+        assert_eq!(node.listener_orientation(), (0.0, 0.0, 0.0));
+        node.set_listener_orientation(30.0, 120.0, -10.0);
+        assert_eq!(node.listener_orientation(), (30.0, 90.0, -10.0));
+    }
+
+    #[test]
+    fn listener_moves_and_turns_inside_the_room() {
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        for i in 1..4 {
+            node.set_source_active(i, false);
+        }
+        // Vocals at the left wall, level with the listener.
+        node.set_source_position(0, 0.5, 5.0, 1.7);
+        let louder_left = |node: &mut SpatialRoomNode| {
+            let (mut left, mut right) = (0.0_f32, 0.0_f32);
+            for n in 0..4_800 {
+                let sample = (n as f32 * 0.05).sin();
+                let (l, r) = node.process_stereo_frame(sample, sample);
+                left += l * l;
+                right += r * r;
+            }
+            left > right
+        };
+        assert!(louder_left(&mut node));
+        // Facing the other way puts the source on the right.
+        node.set_listener_yaw(540.0);
+        assert_eq!(node.listener_yaw(), -180.0);
+        assert!(!louder_left(&mut node));
+
+        node.set_listener_position(-3.0, 4.0, 30.0);
+        assert_eq!(node.listener_position(), (0.0, 4.0, 3.5));
+        node.set_listener_position(f32::NAN, 1.0, 1.0);
+        assert_eq!(node.listener_position(), (0.0, 4.0, 3.5));
+    }
+
+    #[test]
+    fn elevation_changes_the_spectrum() {
+        // Direct-path level of a sine from a source 2 m away in direction
+        // (forward, up), measured before the first reflection arrives.
+        let level = |frequency: f32, forward: f32, up: f32| {
+            let mut node = SpatialRoomNode::new(48_000.0);
+            node.set_enabled(true);
+            for i in 1..4 {
+                node.set_source_active(i, false);
+            }
+            node.set_source_position(0, 4.0, 5.0 + 2.0 * forward, 1.7 + 2.0 * up);
+            let mut peak = 0.0_f32;
+            for n in 0..500 {
+                let sample = (std::f32::consts::TAU * frequency * n as f32 / 48_000.0).sin();
+                let (left, _) = node.process_stereo_frame(sample, sample);
+                if n > 200 {
+                    peak = peak.max(left.abs());
+                }
+            }
+            peak
+        };
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        let ahead = level(8_000.0, 1.0, 0.0);
+        assert!((ahead - 0.5).abs() < 0.05, "flat when level: {ahead}");
+        let overhead = level(8_000.0, 0.0, 1.0);
+        assert!(overhead > 1.5 * ahead, "overhead: {overhead}");
+        let below = level(6_000.0, diagonal, -diagonal);
+        assert!(below < 0.5 * level(6_000.0, 1.0, 0.0), "notch: {below}");
+        assert_eq!(pinna_cues(0.0), (7_000.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn source_motion_moves_sources_while_processing() {
+        let mut node = SpatialRoomNode::new(48_000.0);
+        node.set_enabled(true);
+        let orbit = SourceMotion::Orbit {
+            center_x: 4.0,
+            center_y: 5.0,
+            z: 1.7,
+            radius: 2.0,
+            period_s: 1.0,
+            clockwise: true,
+        };
+        node.set_source_motion(1, Some(orbit.clone()))
+            .expect("valid motion");
+        let (x, y, _, _) = node.source_positions()[1];
+        assert!((x - 4.0).abs() < 1e-5 && (y - 7.0).abs() < 1e-5);
+
+        // A quarter second later the source is a quarter turn clockwise, on the right.
+        for _ in 0..12_000 + MOTION_UPDATE_FRAMES {
+            node.process_stereo_frame(0.1, 0.1);
+        }
+        let (x, y, _, _) = node.source_positions()[1];
+        assert!(x > 5.9 && (y - 5.0).abs() < 0.2, "({x}, {y})");
+        assert_eq!(node.source_motions()[1], Some(orbit));
+
+        node.set_source_motion(1, None).expect("clear");
+        node.process_stereo_frame(0.1, 0.1);
+        assert_eq!(node.source_positions()[1].0, x);
+        assert!(node.set_source_motion(9, None).is_err());
+    }
+
     #[test]
     fn damping_is_clamped() {
         let node = SpatialRoomNode::new(48_000.0);
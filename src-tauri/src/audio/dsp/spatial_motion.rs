@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use super::spatial::Vec3;
+
+/// Most keyframes one path may have.
+const MAX_KEYFRAMES: usize = 256;
+
+/// A source position at `time_s` seconds into the motion.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MotionKeyframe {
+    pub time_s: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Automated movement of one spatial source, timed from when it was set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SourceMotion {
+    /// Moves in straight lines between keyframes, in time order. Holds the last
+    /// position at the end, or starts over when `looped`.
+    Path {
+        keyframes: Vec<MotionKeyframe>,
+        looped: bool,
+    },
+    /// Circles around a point at height `z`, counterclockwise seen from above (from
+    /// the front towards the left) unless `clockwise`.
+    Orbit {
+        center_x: f32,
+        center_y: f32,
+        z: f32,
+        radius: f32,
+        period_s: f32,
+        clockwise: bool,
+    },
+}
+
+impl SourceMotion {
+    /// Rejects motions that cannot be evaluated: empty or oversized paths, keyframes
+    /// out of time order, non-finite values, or orbits without a positive period.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            SourceMotion::Path { keyframes, .. } => {
+                if keyframes.is_empty() || keyframes.len() > MAX_KEYFRAMES {
+                    return Err(format!(
+                        "A motion path needs 1 to {MAX_KEYFRAMES} keyframes, got {}",
+                        keyframes.len()
+                    ));
+                }
+                let finite = keyframes
+                    .iter()
+                    .flat_map(|key| [key.time_s, key.x, key.y, key.z])
+                    .all(f32::is_finite);
+                if !finite {
+                    return Err("Motion keyframes must be finite numbers".to_string());
+                }
+                if keyframes[0].time_s < 0.0
+                    || keyframes
+                        .windows(2)
+                        .any(|pair| pair[1].time_s <= pair[0].time_s)
+                {
+                    return Err(
+                        "Motion keyframe times must start at 0 or later and increase".to_string(),
+                    );
+                }
+            }
+            SourceMotion::Orbit {
+                center_x,
+                center_y,
+                z,
+                radius,
+                period_s,
+                ..
+            } => {
+                if ![*center_x, *center_y, *z, *radius, *period_s]
+                    .iter()
+                    .all(|value| value.is_finite())
+                {
+                    return Err("Orbit values must be finite numbers".to_string());
+                }
+                if *radius < 0.0 || *period_s < 0.1 {
+                    return Err(
+                        "An orbit needs a non-negative radius and a period of at least 0.1 s"
+                            .to_string(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the source is `time_s` seconds into the motion.
+    pub fn position_at(&self, time_s: f32) -> Vec3 {
+        match self {
+            SourceMotion::Path { keyframes, looped } => {
+                let (Some(first), Some(last)) = (keyframes.first(), keyframes.last()) else {
+                    return Vec3::new(0.0, 0.0, 0.0);
+                };
+                let time_s = if *looped && last.time_s > 0.0 {
+                    time_s % last.time_s
+                } else {
+                    time_s
+                };
+                let at = |key: &MotionKeyframe| Vec3::new(key.x, key.y, key.z);
+                if time_s <= first.time_s {
+                    return at(first);
+                }
+                let Some(pair) = keyframes.windows(2).find(|pair| time_s < pair[1].time_s) else {
+                    return at(last);
+                };
+                let (from, to) = (&pair[0], &pair[1]);
+                let t = (time_s - from.time_s) / (to.time_s - from.time_s);
+                Vec3::new(
+                    from.x + (to.x - from.x) * t,
+                    from.y + (to.y - from.y) * t,
+                    from.z + (to.z - from.z) * t,
+                )
+            }
+            SourceMotion::Orbit {
+                center_x,
+                center_y,
+                z,
+                radius,
+                period_s,
+                clockwise,
+            } => {
+                let turn = (time_s / period_s).fract() * std::f32::consts::TAU;
+                let angle = if *clockwise { -turn } else { turn };
+                // Starts in front of the centre (+y).
+                Vec3::new(
+                    center_x - radius * angle.sin(),
+                    center_y + radius * angle.cos(),
+                    *z,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MotionKeyframe, SourceMotion};
+
+    #[test]
+    fn paths_and_orbits_are_evaluated_over_time() {
+        let key = |time_s, x| MotionKeyframe {
+            time_s,
+            x,
+            y: 5.0,
+            z: 1.7,
+        };
+        let path = SourceMotion::Path {
+            keyframes: vec![key(1.0, 2.0), key(3.0, 6.0)],
+            looped: false,
+        };
+        path.validate().expect("valid path");
+        assert_eq!(path.position_at(0.0).x, 2.0);
+        assert_eq!(path.position_at(2.0).x, 4.0);
+        assert_eq!(path.position_at(10.0).x, 6.0);
+        let looped = SourceMotion::Path {
+            keyframes: vec![key(0.0, 0.0), key(2.0, 4.0)],
+            looped: true,
+        };
+        assert_eq!(looped.position_at(3.0).x, 2.0);
+
+        let orbit = SourceMotion::Orbit {
+            center_x: 4.0,
+            center_y: 5.0,
+            z: 2.0,
+            radius: 2.0,
+            period_s: 4.0,
+            clockwise: false,
+        };
+        orbit.validate().expect("valid orbit");
+        let start = orbit.position_at(0.0);
+        assert!((start.x - 4.0).abs() < 1e-5 && (start.y - 7.0).abs() < 1e-5);
+        // A quarter turn counterclockwise moves it to the left (-x).
+        let quarter = orbit.position_at(5.0);
+        assert!((quarter.x - 2.0).abs() < 1e-4 && (quarter.y - 5.0).abs() < 1e-4);
+
+        let unordered = SourceMotion::Path {
+            keyframes: vec![key(2.0, 0.0), key(1.0, 0.0)],
+            looped: false,
+        };
+        assert!(unordered.validate().is_err());
+        let still = SourceMotion::Orbit {
+            center_x: 0.0,
+            center_y: 0.0,
+            z: 0.0,
+            radius: 1.0,
+            period_s: 0.0,
+            clockwise: true,
+        };
+        assert!(still.validate().is_err());
+    }
+}
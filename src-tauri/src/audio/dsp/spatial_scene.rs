@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use super::spatial::{SourceMix, SpatialRoomNode, MAX_SOURCES};
+use super::spatial_motion::SourceMotion;
+
+/// Format version written into every scene file. Scenes from a newer version are
+/// refused rather than half applied.
+pub const SPATIAL_SCENE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneSource {
+    /// Stem or auxiliary input the source plays.
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub active: bool,
+    /// Missing from scenes saved before per-source gain, mute and solo existed.
+    #[serde(default)]
+    pub mix: SourceMix,
+    #[serde(default)]
+    pub motion: Option<SourceMotion>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneListener {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// Direction faced in degrees, positive left.
+    pub yaw: f32,
+}
+
+/// A whole spatial scene in metres: room, listener and sources, as written to scene
+/// files. Sources are matched by name, so files stay valid if the source order changes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpatialScene {
+    pub version: u32,
+    pub width: f32,
+    pub length: f32,
+    pub height: f32,
+    pub damping: f32,
+    pub listener: SceneListener,
+    pub sources: Vec<SceneSource>,
+}
+
+impl SpatialScene {
+    pub fn capture(spatial: &SpatialRoomNode) -> Self {
+        let (width, length, height) = spatial.room_size();
+        let (x, y, z) = spatial.listener_position();
+        let motions = spatial.source_motions();
+        Self {
+            version: SPATIAL_SCENE_VERSION,
+            width,
+            length,
+            height,
+            damping: spatial.damping(),
+            listener: SceneListener {
+                x,
+                y,
+                z,
+                yaw: spatial.listener_yaw(),
+            },
+            sources: spatial
+                .source_positions()
+                .into_iter()
+                .zip(motions)
+                .zip(spatial.source_mix())
+                .zip(spatial.source_names())
+                .map(|((((x, y, z, active), motion), mix), name)| SceneSource {
+                    name,
+                    x,
+                    y,
+                    z,
+                    active,
+                    mix,
+                    motion,
+                })
+                .collect(),
+        }
+    }
+
+    /// Rejects scenes that could only be applied in part: a newer format, non-finite
+    /// values, unnamed, repeated or too many sources, or invalid motions.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version > SPATIAL_SCENE_VERSION {
+            return Err(format!(
+                "Spatial scene version {} is newer than the supported version {SPATIAL_SCENE_VERSION}",
+                self.version
+            ));
+        }
+        let listener = &self.listener;
+        let finite = [
+            self.width,
+            self.length,
+            self.height,
+            self.damping,
+            listener.x,
+            listener.y,
+            listener.z,
+            listener.yaw,
+        ]
+        .into_iter()
+        .chain(
+            self.sources
+                .iter()
+                .flat_map(|source| [source.x, source.y, source.z, source.mix.gain_db]),
+        )
+        .all(f32::is_finite);
+        if !finite {
+            return Err("Spatial scene values must be finite numbers".to_string());
+        }
+        if self.sources.len() > MAX_SOURCES {
+            return Err(format!(
+                "Spatial scene has {} sources; a room holds at most {MAX_SOURCES}",
+                self.sources.len()
+            ));
+        }
+        for (index, source) in self.sources.iter().enumerate() {
+            if source.name.trim().is_empty() {
+                return Err("Spatial source name cannot be empty".to_string());
+            }
+            if self.sources[..index]
+                .iter()
+                .any(|other| other.name.trim() == source.name.trim())
+            {
+                return Err(format!("Spatial source {} appears twice", source.name));
+            }
+            if let Some(motion) = &source.motion {
+                motion
+                    .validate()
+                    .map_err(|e| format!("Motion of {}: {e}", source.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the scene in `spatial`. The room is resized first so the listener is
+    /// clamped to the new walls. Afterwards the room holds exactly the scene's sources:
+    /// others are removed and missing ones added at the end.
+    pub fn apply(&self, spatial: &mut SpatialRoomNode) -> Result<(), String> {
+        self.validate()?;
+        for name in spatial.source_names() {
+            if !self.sources.iter().any(|source| source.name.trim() == name) {
+                spatial.remove_source(&name)?;
+            }
+        }
+        spatial.set_room_size(self.width, self.length, self.height);
+        spatial.set_damping(self.damping);
+        spatial.set_listener_position(self.listener.x, self.listener.y, self.listener.z);
+        spatial.set_listener_yaw(self.listener.yaw);
+        for source in &self.sources {
+            let index = match spatial.source_index(&source.name) {
+                Some(index) => index,
+                None => spatial.add_source(&source.name, source.x, source.y, source.z)?,
+            };
+            spatial.set_source_position(index, source.x, source.y, source.z);
+            spatial.set_source_active(index, source.active);
+            spatial.set_source_mix(index, source.mix)?;
+            spatial.set_source_motion(index, source.motion.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpatialScene;
+    use crate::audio::dsp::spatial::{SourceMix, SpatialRoomNode};
+    use crate::audio::dsp::spatial_motion::SourceMotion;
+
+    #[test]
+    fn spatial_scenes_round_trip_through_json() {
+        let mut spatial = SpatialRoomNode::new(48_000.0);
+        spatial.set_room_size(6.0, 9.0, 3.0);
+        spatial.set_damping(0.7);
+        spatial.set_listener_position(2.0, 3.0, 1.2);
+        spatial.set_listener_yaw(45.0);
+        spatial.set_source_active(2, false);
+        spatial
+            .add_source("piano", 1.0, 8.0, 1.0)
+            .expect("new source");
+        let quiet = SourceMix {
+            gain_db: -6.0,
+            muted: false,
+            solo: true,
+        };
+        spatial.set_source_mix(4, quiet).expect("piano exists");
+        let orbit = SourceMotion::Orbit {
+            center_x: 3.0,
+            center_y: 4.5,
+            z: 1.5,
+            radius: 1.0,
+            period_s: 6.0,
+            clockwise: true,
+        };
+        spatial
+            .set_source_motion(1, Some(orbit))
+            .expect("valid motion");
+        let saved = SpatialScene::capture(&spatial);
+
+        let json = serde_json::to_string(&saved).expect("serialize");
+        let loaded: SpatialScene = serde_json::from_str(&json).expect("parse");
+        let mut fresh = SpatialRoomNode::new(48_000.0);
+        loaded.apply(&mut fresh).expect("apply");
+        assert_eq!(SpatialScene::capture(&fresh), saved);
+
+        // The scene's sources replace whatever the room held.
+        let mut fewer = saved.clone();
+        fewer.sources.retain(|source| source.name != "drums");
+        fewer.apply(&mut fresh).expect("apply");
+        assert_eq!(fresh.source_names(), ["vocals", "bass", "other", "piano"]);
+
+        let mut repeated = saved.clone();
+        repeated.sources[0].name = "piano".to_string();
+        assert!(repeated.apply(&mut fresh).is_err());
+        let mut newer = saved;
+        newer.version += 1;
+        assert!(newer.validate().is_err());
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
 
 use super::filters::BiquadFilter;
 
@@ -51,6 +54,14 @@ impl ToneNode {
             .store(true, std::sync::atomic::Ordering::SeqCst);
     }
 
+    pub fn bass(&self) -> f32 {
+        f32::from_bits(self.bass_gain_bits.load(Ordering::Relaxed))
+    }
+
+    pub fn treble(&self) -> f32 {
+        f32::from_bits(self.treble_gain_bits.load(Ordering::Relaxed))
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         let sr = sample_rate.max(8_000.0);
         if (sr - self.sample_rate).abs() > f32::EPSILON {
@@ -86,29 +97,101 @@ impl ToneNode {
     }
 }
 
-/// Stereo balance control. `balance` ranges from -1.0 (full left) to 1.0 (full right).
+/// How `BalanceNode` moves the stereo image off center.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceMode {
+    /// Turns the far channel down and the near one up, keeping the total power.
+    #[default]
+    Balance,
+    /// Pans both channels toward one side, so nothing is lost: at full right the left
+    /// channel plays from the center.
+    Rotate,
+}
+
+/// Constant-power (sin/cos) gains for a position from -1.0 (left) to 1.0 (right);
+/// -3 dB on each side at the center.
+fn pan_gains(position: f32) -> (f32, f32) {
+    let angle = (position.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// Left/right balance, with an optional mono downmix ahead of it. `balance` ranges
+/// from -1.0 (full left) to 1.0 (full right) and follows a constant-power law, so the
+/// loudness does not dip off center; at the extremes the remaining channel is 3 dB
+/// louder. The center is an exact passthrough.
 pub struct BalanceNode {
     balance_bits: AtomicU32,
+    mono: AtomicBool,
+    rotate: AtomicBool,
 }
 
 impl BalanceNode {
     pub fn new() -> Self {
         Self {
             balance_bits: AtomicU32::new(0.0_f32.to_bits()),
+            mono: AtomicBool::new(false),
+            rotate: AtomicBool::new(false),
         }
     }
 
+    pub fn set_mode(&self, mode: BalanceMode) {
+        self.rotate
+            .store(mode == BalanceMode::Rotate, Ordering::SeqCst);
+    }
+
+    pub fn mode(&self) -> BalanceMode {
+        if self.rotate.load(Ordering::Relaxed) {
+            BalanceMode::Rotate
+        } else {
+            BalanceMode::Balance
+        }
+    }
+
+    /// Sums both channels into each before the balance is applied, for single-ear
+    /// listening or a single speaker. The sum is halved so it cannot clip.
+    pub fn set_mono(&self, enabled: bool) {
+        self.mono.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.mono.load(Ordering::Relaxed)
+    }
+
     pub fn set_balance(&self, balance: f32) {
         let clamped = balance.clamp(-1.0, 1.0);
         self.balance_bits
             .store(clamped.to_bits(), Ordering::SeqCst);
     }
 
+    pub fn balance(&self) -> f32 {
+        f32::from_bits(self.balance_bits.load(Ordering::Relaxed))
+    }
+
     pub fn process_stereo_frame(&self, left: f32, right: f32) -> (f32, f32) {
+        let (left, right) = if self.mono.load(Ordering::Relaxed) {
+            let mid = 0.5 * (left + right);
+            (mid, mid)
+        } else {
+            (left, right)
+        };
         let balance = f32::from_bits(self.balance_bits.load(Ordering::Relaxed));
-        let l_gain = 1.0_f32.min(1.0 - balance);
-        let r_gain = 1.0_f32.min(1.0 + balance);
-        (left * l_gain, right * r_gain)
+        if balance == 0.0 {
+            return (left, right);
+        }
+        if self.rotate.load(Ordering::Relaxed) {
+            // Each channel is panned from its own side by the balance.
+            let (left_to_left, left_to_right) = pan_gains(balance - 1.0);
+            let (right_to_left, right_to_right) = pan_gains(balance + 1.0);
+            (
+                left * left_to_left + right * right_to_left,
+                left * left_to_right + right * right_to_right,
+            )
+        } else {
+            // Scaled so the center is unity rather than -3 dB.
+            let (l_gain, r_gain) = pan_gains(balance);
+            (left * l_gain * SQRT_2, right * r_gain * SQRT_2)
+        }
     }
 }
 
@@ -118,10 +201,78 @@ impl Default for BalanceNode {
     }
 }
 
-/// Basic crossfeed stereo expansion node. Delays one channel by a few milliseconds,
-/// applies a low-pass filter, and mixes it with the opposite channel.
+/// Crossfeed configuration: how much of each channel, low-passed and delayed, reaches
+/// the opposite ear.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrossfeedPreset {
+    pub name: &'static str,
+    pub cutoff_hz: f32,
+    pub delay_ms: f32,
+    /// Level of the crossfed signal below the direct one at low frequencies.
+    pub feed_db: f32,
+    /// Cuts the same low-passed share from the direct path, so mono content stays flat
+    /// (Bauer's design) instead of gaining bass.
+    pub compensated: bool,
+}
+
+/// The original fixed configuration; the expansion amount sets the feed level.
+pub const CROSSFEED_CLASSIC: CrossfeedPreset = CrossfeedPreset {
+    name: "Classic",
+    cutoff_hz: 700.0,
+    delay_ms: 0.3,
+    feed_db: 0.0,
+    compensated: false,
+};
+
+/// Bauer stereophonic-to-binaural defaults as popularized by bs2b.
+pub const CROSSFEED_BAUER: CrossfeedPreset = CrossfeedPreset {
+    name: "Bauer",
+    cutoff_hz: 700.0,
+    delay_ms: 0.26,
+    feed_db: 4.5,
+    compensated: true,
+};
+
+pub const CROSSFEED_CHU_MOY: CrossfeedPreset = CrossfeedPreset {
+    name: "Chu Moy",
+    cutoff_hz: 700.0,
+    delay_ms: 0.26,
+    feed_db: 6.0,
+    compensated: true,
+};
+
+pub const CROSSFEED_MEIER: CrossfeedPreset = CrossfeedPreset {
+    name: "Jan Meier",
+    cutoff_hz: 650.0,
+    delay_ms: 0.28,
+    feed_db: 9.5,
+    compensated: true,
+};
+
+/// Value accepted by `set_crossfeed_preset` to switch crossfeed off.
+pub const CROSSFEED_OFF: &str = "off";
+
+pub fn get_crossfeed_preset(name: &str) -> Option<&'static CrossfeedPreset> {
+    let normalized = name.trim().to_lowercase();
+    match normalized.as_str() {
+        "classic" => Some(&CROSSFEED_CLASSIC),
+        "bauer" | "bs2b" | "default" => Some(&CROSSFEED_BAUER),
+        "chu moy" | "chumoy" | "cmoy" => Some(&CROSSFEED_CHU_MOY),
+        "jan meier" | "meier" | "jmeier" => Some(&CROSSFEED_MEIER),
+        _ => None,
+    }
+}
+
+/// Crossfeed stereo expansion node. Delays each channel slightly, applies a low-pass
+/// filter, and mixes it into the opposite channel according to a `CrossfeedPreset`.
 pub struct StereoExpansionNode {
     amount_bits: AtomicU32,
+    cutoff_bits: AtomicU32,
+    delay_ms_bits: AtomicU32,
+    /// Linear crossfeed gain at full amount.
+    feed_gain_bits: AtomicU32,
+    compensated: AtomicBool,
+    needs_update: AtomicBool,
     sample_rate: f32,
     delay_buffer_l: Vec<f32>,
     delay_buffer_r: Vec<f32>,
@@ -129,30 +280,36 @@ pub struct StereoExpansionNode {
     delay_len: usize,
     lp_left: BiquadFilter,
     lp_right: BiquadFilter,
+    /// Low-pass of each direct channel, subtracted when the preset is compensated.
+    direct_lp_left: BiquadFilter,
+    direct_lp_right: BiquadFilter,
 }
 
-const CROSSFEED_DELAY_MS: f32 = 0.3;
-const CROSSFEED_LP_FREQ: f32 = 700.0;
+/// Longest crossfeed delay any preset may use.
+const MAX_CROSSFEED_DELAY_MS: f32 = 1.0;
 
 impl StereoExpansionNode {
     pub fn new(sample_rate: f32) -> Self {
         let sr = sample_rate.max(8_000.0);
-        let delay_len = ((CROSSFEED_DELAY_MS / 1000.0) * sr).ceil() as usize;
-        let delay_len = delay_len.max(1);
-        let mut lp_left = BiquadFilter::new();
-        let mut lp_right = BiquadFilter::new();
-        lp_left.set_low_pass(sr, CROSSFEED_LP_FREQ, 0.707);
-        lp_right.set_low_pass(sr, CROSSFEED_LP_FREQ, 0.707);
-        Self {
+        let node = Self {
             amount_bits: AtomicU32::new(0.0_f32.to_bits()),
+            cutoff_bits: AtomicU32::new(0.0_f32.to_bits()),
+            delay_ms_bits: AtomicU32::new(0.0_f32.to_bits()),
+            feed_gain_bits: AtomicU32::new(1.0_f32.to_bits()),
+            compensated: AtomicBool::new(false),
+            needs_update: AtomicBool::new(true),
             sample_rate: sr,
-            delay_buffer_l: vec![0.0; delay_len],
-            delay_buffer_r: vec![0.0; delay_len],
+            delay_buffer_l: vec![0.0; max_delay_len(sr)],
+            delay_buffer_r: vec![0.0; max_delay_len(sr)],
             delay_pos: 0,
-            delay_len,
-            lp_left,
-            lp_right,
-        }
+            delay_len: 1,
+            lp_left: BiquadFilter::new(),
+            lp_right: BiquadFilter::new(),
+            direct_lp_left: BiquadFilter::new(),
+            direct_lp_right: BiquadFilter::new(),
+        };
+        node.load_preset(&CROSSFEED_CLASSIC);
+        node
     }
 
     pub fn set_amount(&self, amount: f32) {
@@ -161,17 +318,63 @@ impl StereoExpansionNode {
             .store(clamped.to_bits(), Ordering::SeqCst);
     }
 
+    pub fn amount(&self) -> f32 {
+        f32::from_bits(self.amount_bits.load(Ordering::Relaxed))
+    }
+
+    /// Switches the crossfeed filter, delay and level; the amount still scales the feed.
+    pub fn load_preset(&self, preset: &CrossfeedPreset) {
+        self.cutoff_bits.store(
+            preset.cutoff_hz.clamp(200.0, 2_000.0).to_bits(),
+            Ordering::SeqCst,
+        );
+        self.delay_ms_bits.store(
+            preset.delay_ms.clamp(0.0, MAX_CROSSFEED_DELAY_MS).to_bits(),
+            Ordering::SeqCst,
+        );
+        let feed_gain = 10.0_f32.powf(-preset.feed_db.max(0.0) / 20.0);
+        self.feed_gain_bits.store(feed_gain.to_bits(), Ordering::SeqCst);
+        self.compensated.store(preset.compensated, Ordering::SeqCst);
+        self.needs_update.store(true, Ordering::SeqCst);
+    }
+
+    /// Current crossfeed filter, delay and level as an unnamed preset.
+    pub fn current_preset(&self) -> CrossfeedPreset {
+        let feed_gain = f32::from_bits(self.feed_gain_bits.load(Ordering::Relaxed));
+        CrossfeedPreset {
+            name: "Custom",
+            cutoff_hz: f32::from_bits(self.cutoff_bits.load(Ordering::Relaxed)),
+            delay_ms: f32::from_bits(self.delay_ms_bits.load(Ordering::Relaxed)),
+            feed_db: -20.0 * feed_gain.log10(),
+            compensated: self.compensated.load(Ordering::Relaxed),
+        }
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         let sr = sample_rate.max(8_000.0);
         if (sr - self.sample_rate).abs() > f32::EPSILON {
             self.sample_rate = sr;
-            self.delay_len = ((CROSSFEED_DELAY_MS / 1000.0) * sr).ceil() as usize;
-            self.delay_len = self.delay_len.max(1);
-            self.delay_buffer_l = vec![0.0; self.delay_len];
-            self.delay_buffer_r = vec![0.0; self.delay_len];
+            self.delay_buffer_l = vec![0.0; max_delay_len(sr)];
+            self.delay_buffer_r = vec![0.0; max_delay_len(sr)];
             self.delay_pos = 0;
-            self.lp_left.set_low_pass(sr, CROSSFEED_LP_FREQ, 0.707);
-            self.lp_right.set_low_pass(sr, CROSSFEED_LP_FREQ, 0.707);
+            self.needs_update.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn recalculate(&mut self) {
+        let sr = self.sample_rate;
+        let cutoff = f32::from_bits(self.cutoff_bits.load(Ordering::Relaxed));
+        let delay_ms = f32::from_bits(self.delay_ms_bits.load(Ordering::Relaxed));
+        self.delay_len =
+            (((delay_ms / 1000.0) * sr).round() as usize).clamp(1, self.delay_buffer_l.len());
+        self.delay_pos %= self.delay_len;
+        for filter in [
+            &mut self.lp_left,
+            &mut self.lp_right,
+            &mut self.direct_lp_left,
+            &mut self.direct_lp_right,
+        ] {
+            filter.set_low_pass(sr, cutoff, 0.707);
         }
     }
 
@@ -180,6 +383,10 @@ impl StereoExpansionNode {
         if amount < f32::EPSILON {
             return (left, right);
         }
+        if self.needs_update.swap(false, Ordering::SeqCst) {
+            self.recalculate();
+        }
+        let feed = amount * f32::from_bits(self.feed_gain_bits.load(Ordering::Relaxed));
 
         // Read delayed samples from the opposite channel
         let delayed_l = self.delay_buffer_l[self.delay_pos];
@@ -191,15 +398,26 @@ impl StereoExpansionNode {
         self.delay_pos = (self.delay_pos + 1) % self.delay_len;
 
         // Apply low-pass to the delayed crossfeed
-        let cross_l = self.lp_left.process_sample(delayed_r) * amount;
-        let cross_r = self.lp_right.process_sample(delayed_l) * amount;
+        let cross_l = self.lp_left.process_sample(delayed_r) * feed;
+        let cross_r = self.lp_right.process_sample(delayed_l) * feed;
+
+        let (direct_l, direct_r) = if self.compensated.load(Ordering::Relaxed) {
+            (
+                left - self.direct_lp_left.process_sample(left) * feed,
+                right - self.direct_lp_right.process_sample(right) * feed,
+            )
+        } else {
+            (left, right)
+        };
 
-        let out_l = left + cross_l;
-        let out_r = right + cross_r;
-        (out_l, out_r)
+        (direct_l + cross_l, direct_r + cross_r)
     }
 }
 
+fn max_delay_len(sample_rate: f32) -> usize {
+    ((MAX_CROSSFEED_DELAY_MS / 1000.0) * sample_rate).ceil() as usize + 1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,13 +451,24 @@ mod tests {
         assert!((r - 0.5).abs() < f32::EPSILON);
     }
 
+    #[test]
+    fn mono_sums_both_channels_before_balance() {
+        let node = BalanceNode::new();
+        node.set_mono(true);
+        assert_eq!(node.process_stereo_frame(0.8, 0.0), (0.4, 0.4));
+        node.set_balance(1.0);
+        let (l, r) = node.process_stereo_frame(0.8, -0.2);
+        assert!(l.abs() < 1e-6);
+        assert!((r - 0.3 * SQRT_2).abs() < 1e-6);
+    }
+
     #[test]
     fn balance_hard_left_mutes_right() {
         let node = BalanceNode::new();
         node.set_balance(-1.0);
         let (l, r) = node.process_stereo_frame(0.8, 0.8);
-        assert!((l - 0.8).abs() < f32::EPSILON);
-        assert!(r.abs() < f32::EPSILON);
+        assert!((l - 0.8 * SQRT_2).abs() < 1e-6);
+        assert!(r.abs() < 1e-6);
     }
 
     #[test]
@@ -247,8 +476,32 @@ mod tests {
         let node = BalanceNode::new();
         node.set_balance(1.0);
         let (l, r) = node.process_stereo_frame(0.8, 0.8);
-        assert!(l.abs() < f32::EPSILON);
-        assert!((r - 0.8).abs() < f32::EPSILON);
+        assert!(l.abs() < 1e-6);
+        assert!((r - 0.8 * SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn balance_keeps_power_off_center() {
+        let node = BalanceNode::new();
+        for balance in [-0.8, -0.3, 0.25, 0.6] {
+            node.set_balance(balance);
+            let (l, r) = node.process_stereo_frame(1.0, 1.0);
+            assert!((l * l + r * r - 2.0).abs() < 1e-5, "{balance}: {l} {r}");
+        }
+    }
+
+    #[test]
+    fn rotate_moves_both_channels_toward_one_side() {
+        let node = BalanceNode::new();
+        node.set_mode(BalanceMode::Rotate);
+        node.set_balance(1.0);
+        // The left channel ends up in the center, the right one stays hard right.
+        let (l, r) = node.process_stereo_frame(1.0, 0.0);
+        assert!((l - r).abs() < 1e-6 && (l - FRAC_PI_4.cos()).abs() < 1e-6);
+        let (l, r) = node.process_stereo_frame(0.0, 1.0);
+        assert!(l.abs() < 1e-6 && (r - 1.0).abs() < 1e-6);
+        node.set_balance(0.0);
+        assert_eq!(node.process_stereo_frame(0.5, -0.3), (0.5, -0.3));
     }
 
     #[test]
@@ -272,4 +525,29 @@ mod tests {
         // Right channel should pick up some crossfeed from left
         assert!(r.abs() > 0.01, "expected crossfeed in right, got {r}");
     }
+
+    #[test]
+    fn compensated_crossfeed_keeps_mono_flat() {
+        let mut node = StereoExpansionNode::new(48_000.0);
+        node.set_amount(1.0);
+        node.load_preset(get_crossfeed_preset("Jan Meier").unwrap());
+        let mut out = (0.0, 0.0);
+        for _ in 0..4_800 {
+            out = node.process_stereo_frame(0.5, 0.5);
+        }
+        assert!(
+            (out.0 - 0.5).abs() < 1e-3,
+            "expected flat mono, got {out:?}"
+        );
+
+        // A hard-left signal reaches the right ear 9.5 dB down at low frequencies.
+        for _ in 0..4_800 {
+            out = node.process_stereo_frame(1.0, 0.0);
+        }
+        let expected = 10.0_f32.powf(-9.5 / 20.0);
+        assert!((out.1 - expected).abs() < 1e-3, "got {out:?}");
+        assert!((out.0 - (1.0 - expected)).abs() < 1e-3, "got {out:?}");
+        assert!(get_crossfeed_preset("bs2b").is_some());
+        assert!(get_crossfeed_preset("unknown").is_none());
+    }
 }
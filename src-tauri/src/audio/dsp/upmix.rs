@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+
+use super::filters::BiquadFilter;
+
+/// Butterworth Q; two in series make a Linkwitz-Riley crossover whose halves sum flat.
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// How a mono or stereo source is spread over a multichannel output.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpmixSettings {
+    /// When off, source channels are copied round-robin as before.
+    pub enabled: bool,
+    /// Share of the common L/R signal steered into the center, 0.0–1.0. It is taken
+    /// out of the front pair, so a centered voice keeps its level.
+    pub center_level: f32,
+    /// Level of the L/R difference (the ambience) sent to the surrounds, 0.0–1.0.
+    pub surround_level: f32,
+    /// Where bass is split off for the LFE channel.
+    pub crossover_hz: f32,
+    /// High-passes the other channels at the crossover, so all bass comes from the
+    /// subwoofer. Without it the LFE duplicates the bass of the full-range channels.
+    pub bass_redirect: bool,
+}
+
+impl Default for UpmixSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            center_level: 0.5,
+            surround_level: 0.5,
+            crossover_hz: 80.0,
+            bass_redirect: false,
+        }
+    }
+}
+
+impl UpmixSettings {
+    /// Clamps every field into its supported range.
+    pub fn clamped(self) -> Self {
+        Self {
+            enabled: self.enabled,
+            center_level: self.center_level.clamp(0.0, 1.0),
+            surround_level: self.surround_level.clamp(0.0, 1.0),
+            crossover_hz: self.crossover_hz.clamp(40.0, 200.0),
+            bass_redirect: self.bass_redirect,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Speaker {
+    FrontLeft,
+    FrontRight,
+    Center,
+    Lfe,
+    SurroundLeft,
+    SurroundRight,
+    Unused,
+}
+
+/// Speaker of each output channel in WAVE (`WAVEFORMATEXTENSIBLE`) order: FL FR FC LFE
+/// BL BR SL SR. Quad and 5.0 skip the channels they lack; back and side pairs both
+/// carry the surrounds.
+pub(super) fn layout(channels: usize) -> Vec<Speaker> {
+    use Speaker::*;
+    let known: &[Speaker] = match channels {
+        3 => &[FrontLeft, FrontRight, Center],
+        4 => &[FrontLeft, FrontRight, SurroundLeft, SurroundRight],
+        5 => &[FrontLeft, FrontRight, Center, SurroundLeft, SurroundRight],
+        6 => &[
+            FrontLeft,
+            FrontRight,
+            Center,
+            Lfe,
+            SurroundLeft,
+            SurroundRight,
+        ],
+        // 6.1 puts a back center at index 4, which is left silent.
+        7 => &[
+            FrontLeft,
+            FrontRight,
+            Center,
+            Lfe,
+            Unused,
+            SurroundLeft,
+            SurroundRight,
+        ],
+        _ => &[
+            FrontLeft,
+            FrontRight,
+            Center,
+            Lfe,
+            SurroundLeft,
+            SurroundRight,
+            SurroundLeft,
+            SurroundRight,
+        ],
+    };
+    (0..channels)
+        .map(|channel| known.get(channel).copied().unwrap_or(Unused))
+        .collect()
+}
+
+/// Fourth-order Linkwitz-Riley section: two identical Butterworth biquads in series.
+pub(super) struct Crossover([BiquadFilter; 2]);
+
+impl Crossover {
+    pub(super) fn low_pass(sample_rate: f32, frequency: f32) -> Self {
+        let mut stages = [BiquadFilter::new(), BiquadFilter::new()];
+        for stage in &mut stages {
+            stage.set_low_pass(sample_rate, frequency, BUTTERWORTH_Q);
+        }
+        Self(stages)
+    }
+
+    pub(super) fn high_pass(sample_rate: f32, frequency: f32) -> Self {
+        let mut stages = [BiquadFilter::new(), BiquadFilter::new()];
+        for stage in &mut stages {
+            stage.set_high_pass(sample_rate, frequency, BUTTERWORTH_Q);
+        }
+        Self(stages)
+    }
+
+    pub(super) fn process(&mut self, sample: f32) -> f32 {
+        let [first, second] = &mut self.0;
+        second.process_sample(first.process_sample(sample))
+    }
+}
+
+/// Derives L/R/C/LFE/Ls/Rs from a mono or stereo source: the center gets the common
+/// (mid) signal, the surrounds the difference (side) signal, and the LFE the
+/// low-passed mid. Filter state carries over between calls, so a stream can be fed in
+/// packets.
+pub struct Upmixer {
+    settings: UpmixSettings,
+    speakers: Vec<Speaker>,
+    lfe: Crossover,
+    high_left: Crossover,
+    high_right: Crossover,
+    redirect_bass: bool,
+    /// Leaves the LFE silent, for when bass management fills it after the DSP chain.
+    bass_managed: bool,
+}
+
+impl Upmixer {
+    pub fn new(settings: UpmixSettings, sample_rate: f32, out_channels: usize) -> Self {
+        let settings = settings.clamped();
+        let sample_rate = sample_rate.max(8_000.0);
+        let speakers = layout(out_channels);
+        // Redirected bass needs somewhere to go.
+        let redirect_bass = settings.bass_redirect && speakers.contains(&Speaker::Lfe);
+        Self {
+            settings,
+            lfe: Crossover::low_pass(sample_rate, settings.crossover_hz),
+            high_left: Crossover::high_pass(sample_rate, settings.crossover_hz),
+            high_right: Crossover::high_pass(sample_rate, settings.crossover_hz),
+            speakers,
+            redirect_bass,
+            bass_managed: false,
+        }
+    }
+
+    /// Leaves the bass to `BassManager`: the LFE stays silent and the other channels
+    /// full-range, so the bass is not split off twice.
+    pub fn bass_managed(mut self) -> Self {
+        self.bass_managed = true;
+        self.redirect_bass = false;
+        self
+    }
+
+    /// Upmixes interleaved `input`, or returns `None` when this upmixer does not apply:
+    /// upmixing is off, the source has more than two channels, or the output has two
+    /// or fewer.
+    pub fn upmix(&mut self, input: &[f32], in_channels: usize) -> Option<Vec<f32>> {
+        let out_channels = self.speakers.len();
+        if !self.settings.enabled || !(1..=2).contains(&in_channels) || out_channels <= 2 {
+            return None;
+        }
+        let center = self.settings.center_level;
+        let surround = self.settings.surround_level;
+        let frames = input.len() / in_channels;
+        let mut out = vec![0.0_f32; frames * out_channels];
+        for (source, target) in input
+            .chunks_exact(in_channels)
+            .zip(out.chunks_exact_mut(out_channels))
+        {
+            let left = source[0];
+            let right = source[in_channels - 1];
+            let bass = self.lfe.process(0.5 * (left + right));
+            let (left, right) = if self.redirect_bass {
+                (self.high_left.process(left), self.high_right.process(right))
+            } else {
+                (left, right)
+            };
+            let mid = 0.5 * (left + right);
+            let side = 0.5 * (left - right);
+            for (sample, speaker) in target.iter_mut().zip(&self.speakers) {
+                *sample = match speaker {
+                    Speaker::FrontLeft => left - center * mid,
+                    Speaker::FrontRight => right - center * mid,
+                    Speaker::Center => center * mid,
+                    Speaker::Lfe if self.bass_managed => 0.0,
+                    Speaker::Lfe => bass,
+                    Speaker::SurroundLeft => surround * side,
+                    Speaker::SurroundRight => -surround * side,
+                    Speaker::Unused => 0.0,
+                };
+            }
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UpmixSettings, Upmixer};
+
+    #[test]
+    fn stereo_spreads_over_five_one() {
+        let mut upmixer = Upmixer::new(UpmixSettings::default(), 48_000.0, 6);
+        // A centered voice goes half to the center and leaves the surrounds silent.
+        let out = upmixer.upmix(&[0.4, 0.4], 2).expect("upmix applies");
+        assert_eq!(&out[..3], &[0.2, 0.2, 0.2]);
+        assert_eq!(&out[4..], &[0.0, 0.0]);
+        // A hard-left sound reaches the surrounds in opposite polarity.
+        let out = upmixer.upmix(&[0.4, 0.0], 2).expect("upmix applies");
+        assert!((out[4] - 0.1).abs() < 1e-6 && (out[5] + 0.1).abs() < 1e-6);
+
+        // Low bass ends up in the LFE, and with redirection only there.
+        let settings = UpmixSettings {
+            bass_redirect: true,
+            ..UpmixSettings::default()
+        };
+        let mut upmixer = Upmixer::new(settings, 48_000.0, 6);
+        let tone = (0..48_000)
+            .flat_map(|n| {
+                let sample = 0.5 * (2.0 * std::f32::consts::PI * 30.0 * n as f32 / 48_000.0).sin();
+                [sample, sample]
+            })
+            .collect::<Vec<_>>();
+        let out = upmixer.upmix(&tone, 2).expect("upmix applies");
+        let peak = |channel: usize| {
+            out.chunks_exact(6)
+                .skip(24_000)
+                .map(|frame| frame[channel].abs())
+                .fold(0.0_f32, f32::max)
+        };
+        assert!(peak(3) > 0.45, "LFE should carry the bass: {}", peak(3));
+        assert!(peak(0) < 0.05, "fronts should be high-passed: {}", peak(0));
+
+        // Off, multichannel sources and stereo outputs are left to the channel copy.
+        let off = UpmixSettings {
+            enabled: false,
+            ..UpmixSettings::default()
+        };
+        assert!(Upmixer::new(off, 48_000.0, 6)
+            .upmix(&[0.1, 0.2], 2)
+            .is_none());
+        assert!(Upmixer::new(settings, 48_000.0, 2)
+            .upmix(&[0.1, 0.2], 2)
+            .is_none());
+        assert!(Upmixer::new(settings, 48_000.0, 8)
+            .upmix(&[0.0; 6], 6)
+            .is_none());
+    }
+}
@@ -0,0 +1,109 @@
+/// Channels of the largest speaker layout, 7.1.
+pub const MAX_SPEAKER_CHANNELS: usize = 8;
+
+/// Azimuth of each channel of a 5.1 output in degrees (0° ahead, positive to the left,
+/// as in SOFA), in WAVE order FL FR FC LFE SL SR. The LFE is not panned to.
+const LAYOUT_5_1: [Option<f32>; 6] = [
+    Some(30.0),
+    Some(-30.0),
+    Some(0.0),
+    None,
+    Some(110.0),
+    Some(-110.0),
+];
+/// 7.1 in WAVE order FL FR FC LFE BL BR SL SR.
+const LAYOUT_7_1: [Option<f32>; 8] = [
+    Some(30.0),
+    Some(-30.0),
+    Some(0.0),
+    None,
+    Some(150.0),
+    Some(-150.0),
+    Some(90.0),
+    Some(-90.0),
+];
+
+/// Speaker azimuths of an output with `channels` channels, for the layouts sources can
+/// be panned over: 5.1 and 7.1.
+pub fn speaker_layout(channels: usize) -> Option<&'static [Option<f32>]> {
+    match channels {
+        6 => Some(&LAYOUT_5_1),
+        8 => Some(&LAYOUT_7_1),
+        _ => None,
+    }
+}
+
+/// Pairwise 2-D VBAP (Pulkki 1997): a source at `azimuth` degrees plays from the two
+/// adjacent speakers of `layout` either side of it, with gains whose vectors sum to its
+/// direction, power-normalised so its loudness does not change as it moves. Elevation
+/// is ignored; the layouts are horizontal.
+pub fn vbap_gains(layout: &[Option<f32>], azimuth: f32) -> [f32; MAX_SPEAKER_CHANNELS] {
+    let mut gains = [0.0; MAX_SPEAKER_CHANNELS];
+    let mut speakers: Vec<(usize, f32)> = layout
+        .iter()
+        .enumerate()
+        .filter_map(|(channel, azimuth)| azimuth.map(|azimuth| (channel, azimuth)))
+        .collect();
+    if speakers.is_empty() {
+        return gains;
+    }
+    speakers.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    for (i, &(first, from)) in speakers.iter().enumerate() {
+        let (second, to) = speakers[(i + 1) % speakers.len()];
+        let span = (to - from).rem_euclid(360.0);
+        let offset = (azimuth - from).rem_euclid(360.0);
+        if offset > span && span > 0.0 {
+            continue;
+        }
+        let direction = |degrees: f32| {
+            let radians = degrees.to_radians();
+            (radians.cos(), radians.sin())
+        };
+        let (px, py) = direction(azimuth);
+        let (ax, ay) = direction(from);
+        let (bx, by) = direction(to);
+        let det = ax * by - ay * bx;
+        if det.abs() < 1e-6 {
+            gains[first] = 1.0;
+            return gains;
+        }
+        let g1 = ((px * by - py * bx) / det).max(0.0);
+        let g2 = ((ax * py - ay * px) / det).max(0.0);
+        let norm = (g1 * g1 + g2 * g2).sqrt().max(1e-9);
+        gains[first] = g1 / norm;
+        gains[second] = g2 / norm;
+        return gains;
+    }
+    gains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{speaker_layout, vbap_gains};
+
+    #[test]
+    fn sources_pan_between_adjacent_speakers() {
+        let layout = speaker_layout(6).expect("5.1");
+        assert!(speaker_layout(2).is_none());
+
+        // On a speaker: that speaker alone.
+        let center = vbap_gains(layout, 0.0);
+        assert!((center[2] - 1.0).abs() < 1e-5);
+        assert_eq!(center.iter().filter(|gain| **gain > 1e-5).count(), 1);
+
+        // Between front left and left surround, at constant power.
+        let side = vbap_gains(layout, 70.0);
+        assert!(side[0] > 0.1 && side[4] > 0.1);
+        assert!((side[0] * side[0] + side[4] * side[4] - 1.0).abs() < 1e-5);
+        assert_eq!(side[3], 0.0);
+
+        // Straight behind splits the surround pair evenly across the back gap.
+        let behind = vbap_gains(layout, 180.0);
+        assert!((behind[4] - behind[5]).abs() < 1e-5 && behind[4] > 0.5);
+
+        let layout = speaker_layout(8).expect("7.1");
+        let right = vbap_gains(layout, -90.0);
+        assert!((right[7] - 1.0).abs() < 1e-5);
+    }
+}
@@ -1,41 +1,61 @@
+use super::dsp::analog::AnalogSettings;
+use super::dsp::bass_management::BassManagementSettings;
+use super::dsp::beat::{BeatDetector, ONSET_FRAME_SIZE};
+use super::dsp::binaural_downmix::BinauralDownmixer;
+use super::dsp::compressor::{CompressorSettings, COMPRESSOR_BANDS};
+use super::dsp::correction::RoomCorrection;
 use super::dsp::fft::compute_spectrum_mono;
-use super::dsp::{autoeq::EqBandConfig, filters::DspChain};
-use super::lyrics::{load_lyrics_for_track, LyricsLine};
-use serde::Serialize;
+use super::dsp::filters::{DspChain, DspNode, EqBandSettings, EqMode};
+use super::dsp::karaoke::{CentreCanceller, KaraokeSettings};
+use super::dsp::limiter::LimiterSettings;
+use super::dsp::linear_phase::LINEAR_PHASE_LATENCY_FRAMES;
+use super::dsp::plugin_rack::{PluginInsertInfo, PluginPosition};
+use super::dsp::presets::ChainPreset;
+use super::dsp::profiles::{DspProfile, REVERB_OFF};
+use super::dsp::reverb::{get_preset, ReverbPreset};
+use super::dsp::sofa::HrirSet;
+use super::dsp::spatial::{SourceMix, SpatialOutputMode, StemFrame, SOURCE_NAMES};
+use super::dsp::spatial_motion::SourceMotion;
+use super::dsp::spatial_scene::SpatialScene;
+use super::dsp::tone::{get_crossfeed_preset, BalanceMode, CROSSFEED_OFF};
+use super::dsp::upmix::{UpmixSettings, Upmixer};
+use super::lyrics::{load_lyrics_for_track, LrcParseMode, LrcTags, LrcWarning, LyricsLine};
+use super::plugin_host::{EffectPlugin, PluginParameter, PluginState};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::{
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering},
         Arc, Mutex,
     },
     thread,
 };
 use tauri::{AppHandle, Emitter};
 
-#[cfg(target_os = "windows")]
 use log::{info, warn};
-
-#[cfg(target_os = "windows")]
-use cpal::{
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-    SampleFormat, SampleRate, Stream, StreamConfig,
-};
-#[cfg(target_os = "windows")]
 use ringbuf::{
-    traits::{Consumer as _, Observer as _, Producer as _, Split},
+    traits::{Observer as _, Producer as _, Split},
     HeapRb,
 };
 
+use super::decoder::{
+    audible_frame_range, decode_file, resample_hq, resample_linear, silent_gaps, DecodedTrack,
+};
 #[cfg(target_os = "windows")]
-use super::decoder::{decode_file, resample_hq, resample_linear, DecodedTrack};
+use super::output::CpalOutput;
+use super::output::{OutputBackend, StreamGuard};
+use super::stream::{open_stream, StreamDecoder, StreamInfo, StreamMetadata};
 
 const STATE_PAUSED: u8 = 0;
 const STATE_PLAYING: u8 = 1;
 const NO_ACTIVE_LYRIC: u32 = u32::MAX;
 const LYRICS_POLL_INTERVAL_MS: u64 = 40;
-#[cfg(target_os = "windows")]
+/// ~43 onset frames per second: fine enough for beat timing, cheap enough for a 1024-point FFT.
+const BEAT_POLL_INTERVAL_MS: u64 = 23;
 const STREAM_FADE_OUT_MS: u32 = 12;
+/// Each half of the dip that hides an A/B snapshot switch.
+const AB_SWITCH_FADE_MS: u32 = 15;
 /// Sample history used by the visualizer FFT.
 /// 4096 mono samples balance frequency detail while keeping visual updates responsive.
 const VIBE_WINDOW_SAMPLES: usize = 4096;
@@ -43,10 +63,16 @@ const VIBE_WINDOW_SAMPLES: usize = 4096;
 /// 4096 frames is a low-latency compromise: enough headroom against occasional decode jitter
 /// while keeping callback fill chunks small to reduce interaction latency for pause/seek.
 /// On underrun the callback injects silence, so this size also caps audible dropouts to short gaps.
-#[cfg(target_os = "windows")]
 const RING_BUFFER_FRAMES: usize = 4096;
-#[cfg(target_os = "windows")]
 const PRODUCER_CHUNK_FRAMES: usize = 256;
+/// Samples queued ahead of each file frame: a stems-playing flag and one per stem.
+const STEM_SLOTS: usize = 1 + SOURCE_NAMES.len();
+/// Ring depth for live streams, in seconds of output audio.
+const STREAM_BUFFER_SECONDS: usize = 2;
+const DEFAULT_GAP_THRESHOLD_DB: f32 = -50.0;
+const DEFAULT_GAP_MIN_SECONDS: f32 = 3.0;
+/// Ended plays kept for `take_ended_plays`; older ones are dropped if nobody collects them.
+const MAX_ENDED_PLAYS: usize = 64;
 
 pub struct AudioState {
     inner: Arc<AudioEngine>,
@@ -59,6 +85,13 @@ pub struct LyricsEventPayload {
     pub text: Option<String>,
 }
 
+#[derive(Clone, Serialize)]
+pub struct BeatEventPayload {
+    pub confidence: f32,
+    pub tempo_bpm: Option<f32>,
+    pub position_ms: u32,
+}
+
 #[derive(Clone, Serialize)]
 pub struct AudioStats {
     pub device: String,
@@ -67,6 +100,125 @@ pub struct AudioStats {
     pub file_sample_rate_hz: u32,
     pub ring_buffer_capacity_bytes: u32,
     pub ring_buffer_used_bytes: u32,
+    pub limiter_gain_reduction_db: f32,
+    /// Delay added by the linear-phase EQ; 0 while it is off.
+    pub eq_latency_ms: f32,
+}
+
+/// A finished play of a file: how long it was heard, and whether it ran to its end or
+/// was replaced or stopped part-way.
+#[derive(Clone, Debug)]
+pub struct EndedPlay {
+    pub path: PathBuf,
+    pub listened_seconds: f64,
+    pub duration_seconds: f64,
+    pub reached_end: bool,
+}
+
+/// DSP settings captured before the first automatic profile, restored once a track
+/// without a profile loads.
+#[derive(Clone)]
+struct DspSnapshot {
+    eq_bands: Vec<EqBandSettings>,
+    bass: f32,
+    treble: f32,
+    expansion: f32,
+    reverb: ReverbPreset,
+}
+
+impl DspSnapshot {
+    fn capture(chain: &DspChain) -> Self {
+        Self {
+            eq_bands: chain.user_eq_settings(),
+            bass: chain.tone().bass(),
+            treble: chain.tone().treble(),
+            expansion: chain.expansion().amount(),
+            reverb: chain.reverb().current_params(),
+        }
+    }
+
+    fn restore(&self, chain: &DspChain) -> Result<(), String> {
+        chain.apply_user_eq_settings(&self.eq_bands)?;
+        chain.tone().set_bass(self.bass);
+        chain.tone().set_treble(self.treble);
+        chain.expansion().set_amount(self.expansion);
+        chain.reverb().load_preset(&self.reverb);
+        Ok(())
+    }
+}
+
+/// A/B comparison slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbSlot {
+    A,
+    B,
+}
+
+/// Everything an A/B switch swaps: the settings DSP profiles control plus the EQ mode,
+/// graphic EQ and spatial room. Head orientation is left alone, since head tracking
+/// drives it.
+#[derive(Clone)]
+struct AbSnapshot {
+    dsp: DspSnapshot,
+    eq_mode: EqMode,
+    linear_phase: bool,
+    graphic_eq_gains: Vec<f32>,
+    spatial_enabled: bool,
+    room_size: (f32, f32, f32),
+    room_damping: f32,
+    sources: Vec<(f32, f32, f32, bool)>,
+}
+
+impl AbSnapshot {
+    fn capture(chain: &DspChain) -> Self {
+        Self {
+            dsp: DspSnapshot::capture(chain),
+            eq_mode: chain.eq_mode(),
+            linear_phase: chain.linear_phase().is_enabled(),
+            graphic_eq_gains: chain.graphic_eq().gains(),
+            spatial_enabled: chain.spatial().is_enabled(),
+            room_size: chain.spatial().room_size(),
+            room_damping: chain.spatial().damping(),
+            sources: chain.spatial().source_positions(),
+        }
+    }
+
+    fn restore(&self, chain: &DspChain) -> Result<(), String> {
+        self.dsp.restore(chain)?;
+        chain.set_eq_mode(self.eq_mode);
+        chain.linear_phase().set_enabled(self.linear_phase);
+        for (index, gain_db) in self.graphic_eq_gains.iter().enumerate() {
+            chain.graphic_eq().set_gain(index, *gain_db)?;
+        }
+        let spatial = chain.spatial();
+        spatial.set_enabled(self.spatial_enabled);
+        let (width, length, height) = self.room_size;
+        spatial.set_room_size(width, length, height);
+        spatial.set_damping(self.room_damping);
+        for (index, (x, y, z, active)) in self.sources.iter().enumerate() {
+            spatial.set_source_position(index, *x, *y, *z);
+            spatial.set_source_active(index, *active);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct AbSnapshots {
+    a: Option<AbSnapshot>,
+    b: Option<AbSnapshot>,
+    /// The slot switched to last.
+    active: Option<AbSlot>,
+}
+
+impl AbSnapshots {
+    fn slot_mut(&mut self, slot: AbSlot) -> &mut Option<AbSnapshot> {
+        match slot {
+            AbSlot::A => &mut self.a,
+            AbSlot::B => &mut self.b,
+        }
+    }
 }
 
 struct AudioEngine {
@@ -75,6 +227,7 @@ struct AudioEngine {
     volume_bits: AtomicU32,
     preamp_db_bits: AtomicU32,
     output_rate_hz: AtomicU32,
+    output_channels: AtomicU32,
     file_rate_hz: AtomicU32,
     stream_latency_ms_bits: AtomicU32,
     ring_capacity_bytes: AtomicU32,
@@ -82,29 +235,75 @@ struct AudioEngine {
     seek_frame: AtomicU32,
     current_frame: AtomicU32,
     track_duration_bits: AtomicU32,
+    /// Frame at which the producer stops reading the current track (excludes trimmed silence).
+    playback_end_frame: AtomicU32,
+    trim_silence: AtomicBool,
+    /// "Gap killer": fast-forward through long silent regions inside a track.
+    skip_gaps: AtomicBool,
+    gap_threshold_db_bits: AtomicU32,
+    gap_min_seconds_bits: AtomicU32,
+    /// Pending gap skip handed from the producer to the output callback: when playback
+    /// reaches `gap_skip_from`, the position jumps to `gap_skip_to`. `u32::MAX` = none.
+    gap_skip_from: AtomicU32,
+    gap_skip_to: AtomicU32,
     vibe_amplitude_bits: AtomicU32,
     vibe_samples: Mutex<VecDeque<f32>>,
     lyrics: Mutex<Vec<LyricsLine>>,
+    lyrics_tags: Mutex<LrcTags>,
+    lyrics_warnings: Mutex<Vec<LrcWarning>>,
+    lyrics_lenient: AtomicBool,
     active_lyric_index: AtomicU32,
     lookahead_started: AtomicBool,
     lookahead_completed: AtomicBool,
     dsp_chain: Mutex<DspChain>,
+    dsp_profile_baseline: Mutex<Option<DspSnapshot>>,
+    ab_snapshots: Mutex<AbSnapshots>,
+    /// Snapshot the output callback applies at the bottom of the switch dip.
+    ab_pending: Mutex<Option<AbSnapshot>>,
+    ab_dip_total_samples: AtomicU32,
+    ab_dip_remaining_samples: AtomicU32,
     next_track: Mutex<Option<PathBuf>>,
-    #[cfg(target_os = "windows")]
     preloaded_next_track: Mutex<Option<DecodedTrack>>,
-    #[cfg(target_os = "windows")]
-    stream: Mutex<Option<Stream>>,
-    #[cfg(target_os = "windows")]
+    stream: Mutex<Option<StreamGuard>>,
+    /// Set by the producer after a seek; the output callback drops queued audio and clears it.
+    flush_requested: AtomicBool,
     fade_out_total_samples: AtomicU32,
-    #[cfg(target_os = "windows")]
     fade_out_remaining_samples: AtomicU32,
     decoder_thread: Mutex<Option<thread::JoinHandle<()>>>,
     lyric_monitor_thread: Mutex<Option<thread::JoinHandle<()>>>,
-    #[cfg(target_os = "windows")]
+    beat_monitor_thread: Mutex<Option<thread::JoinHandle<()>>>,
     loaded_path: Mutex<Option<PathBuf>>,
-    output_device_name: Mutex<String>,
+    /// Set while an internet radio stream plays, which cannot seek.
+    live_stream: AtomicBool,
+    /// Device the current stream plays on; `None` until a stream has been opened.
+    output_device_name: Mutex<Option<String>>,
+    /// Network sink (e.g. an AirPlay speaker) used instead of the default device.
+    output_override: Mutex<Option<SharedOutput>>,
+    /// How mono and stereo sources fill outputs with more than two channels.
+    upmix: Mutex<UpmixSettings>,
+    karaoke_enabled: AtomicBool,
+    karaoke_vocal_level_bits: AtomicU32,
+    /// Vocal stem of the loaded track, in the output's rate and channels, subtracted by
+    /// the producer in karaoke mode. `None` falls back to centre cancellation.
+    karaoke_vocals: Mutex<Option<Arc<Vec<f32>>>>,
+    /// Whether separated stems should replace the mix in the spatial room.
+    stem_playback: AtomicBool,
+    /// One `StemFrame` per frame of the loaded track, at the output rate, queued by the
+    /// producer next to the mix.
+    stem_sources: Mutex<Option<Arc<Vec<StemFrame>>>>,
+    /// Audio actually played for the current play, in microseconds.
+    listened_micros: AtomicU64,
+    /// Set once the current play has been handed to `ended_plays`.
+    play_ended: AtomicBool,
+    /// Set by the output callback once playback passes `playback_end_frame`. The
+    /// producer records the play from there, as `end_play` locks and allocates.
+    play_end_reached: AtomicBool,
+    ended_plays: Mutex<VecDeque<EndedPlay>>,
 }
 
+/// An output backend that can be handed to the engine from another thread.
+pub type SharedOutput = Arc<dyn OutputBackend + Send + Sync>;
+
 impl AudioState {
     pub fn new() -> Self {
         Self {
@@ -114,6 +313,7 @@ impl AudioState {
                 volume_bits: AtomicU32::new(1.0_f32.to_bits()),
                 preamp_db_bits: AtomicU32::new(0.0_f32.to_bits()),
                 output_rate_hz: AtomicU32::new(48_000),
+                output_channels: AtomicU32::new(2),
                 file_rate_hz: AtomicU32::new(48_000),
                 stream_latency_ms_bits: AtomicU32::new(0.0_f32.to_bits()),
                 ring_capacity_bytes: AtomicU32::new(0),
@@ -121,117 +321,179 @@ impl AudioState {
                 seek_frame: AtomicU32::new(0),
                 current_frame: AtomicU32::new(0),
                 track_duration_bits: AtomicU32::new(0.0_f32.to_bits()),
+                playback_end_frame: AtomicU32::new(0),
+                trim_silence: AtomicBool::new(false),
+                skip_gaps: AtomicBool::new(false),
+                gap_threshold_db_bits: AtomicU32::new(DEFAULT_GAP_THRESHOLD_DB.to_bits()),
+                gap_min_seconds_bits: AtomicU32::new(DEFAULT_GAP_MIN_SECONDS.to_bits()),
+                gap_skip_from: AtomicU32::new(u32::MAX),
+                gap_skip_to: AtomicU32::new(0),
                 vibe_amplitude_bits: AtomicU32::new(0.0_f32.to_bits()),
                 vibe_samples: Mutex::new(VecDeque::with_capacity(VIBE_WINDOW_SAMPLES)),
                 lyrics: Mutex::new(Vec::new()),
+                lyrics_tags: Mutex::new(LrcTags::default()),
+                lyrics_warnings: Mutex::new(Vec::new()),
+                lyrics_lenient: AtomicBool::new(true),
                 active_lyric_index: AtomicU32::new(NO_ACTIVE_LYRIC),
                 lookahead_started: AtomicBool::new(false),
                 lookahead_completed: AtomicBool::new(false),
                 dsp_chain: Mutex::new(DspChain::new(48_000.0)),
+                dsp_profile_baseline: Mutex::new(None),
+                ab_snapshots: Mutex::new(AbSnapshots::default()),
+                ab_pending: Mutex::new(None),
+                ab_dip_total_samples: AtomicU32::new(0),
+                ab_dip_remaining_samples: AtomicU32::new(0),
                 next_track: Mutex::new(None),
-                #[cfg(target_os = "windows")]
                 preloaded_next_track: Mutex::new(None),
-                #[cfg(target_os = "windows")]
                 stream: Mutex::new(None),
-                #[cfg(target_os = "windows")]
+                flush_requested: AtomicBool::new(false),
                 fade_out_total_samples: AtomicU32::new(0),
-                #[cfg(target_os = "windows")]
                 fade_out_remaining_samples: AtomicU32::new(0),
                 decoder_thread: Mutex::new(None),
                 lyric_monitor_thread: Mutex::new(None),
-                #[cfg(target_os = "windows")]
+                beat_monitor_thread: Mutex::new(None),
                 loaded_path: Mutex::new(None),
-                output_device_name: Mutex::new("Unavailable".to_string()),
+                live_stream: AtomicBool::new(false),
+                output_device_name: Mutex::new(None),
+                output_override: Mutex::new(None),
+                upmix: Mutex::new(UpmixSettings::default()),
+                karaoke_enabled: AtomicBool::new(false),
+                karaoke_vocal_level_bits: AtomicU32::new(0.0_f32.to_bits()),
+                karaoke_vocals: Mutex::new(None),
+                stem_playback: AtomicBool::new(false),
+                stem_sources: Mutex::new(None),
+                listened_micros: AtomicU64::new(0),
+                play_ended: AtomicBool::new(false),
+                play_end_reached: AtomicBool::new(false),
+                ended_plays: Mutex::new(VecDeque::new()),
             }),
         }
     }
 
-    #[cfg(target_os = "windows")]
     pub fn load_track(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let output = self.output_backend()?;
+        self.load_track_with_output(path, &*output)
+    }
+
+    /// The selected network sink, or the default device where the platform has one.
+    fn output_backend(&self) -> Result<SharedOutput, String> {
+        if let Some(output) = self.inner.output_override.lock().map_err(lock_err)?.clone() {
+            return Ok(output);
+        }
+        #[cfg(target_os = "windows")]
+        {
+            Ok(Arc::new(CpalOutput::default_device()?))
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            Err("Audio engine WASAPI implementation is only available on Windows targets"
+                .to_string())
+        }
+    }
+
+    /// Routes playback to `output` (or back to the default device with `None`). A loaded
+    /// track is restarted on the new output at the same position and play state.
+    pub fn set_output(&self, output: Option<SharedOutput>) -> Result<(), String> {
+        *self.inner.output_override.lock().map_err(lock_err)? = output;
+        if !self.playback_supported() {
+            return self.stop_current_stream();
+        }
+        self.reload_on_output()
+    }
+
+    /// Moves a loaded track to the default device when that is no longer the one it
+    /// plays on, e.g. after headphones were plugged in. Live streams stay where they
+    /// are. Returns whether the track moved.
+    pub fn follow_default_output(&self) -> Result<bool, String> {
         if self
             .inner
-            .stream
+            .output_override
             .lock()
             .map_err(lock_err)?
-            .as_ref()
             .is_some()
+            || !self.playback_supported()
+            || self.inner.live_stream.load(Ordering::SeqCst)
+            || self.loaded_path().is_none()
         {
-            let fade_samples =
-                ((self.inner.output_rate_hz.load(Ordering::SeqCst) * STREAM_FADE_OUT_MS) / 1000)
-                    .max(1);
-            self.inner
-                .fade_out_total_samples
-                .store(fade_samples, Ordering::SeqCst);
-            self.inner
-                .fade_out_remaining_samples
-                .store(fade_samples, Ordering::SeqCst);
-            thread::sleep(std::time::Duration::from_millis(STREAM_FADE_OUT_MS as u64));
+            return Ok(false);
+        }
+        let Some(current) = self.output_device() else {
+            return Ok(false);
+        };
+        if self.output_backend()?.device_name() == current {
+            return Ok(false);
         }
+        info!("Default output device changed; moving playback from {current}");
+        self.reload_on_output()?;
+        Ok(true)
+    }
 
-        self.inner.should_stop.store(true, Ordering::SeqCst);
-        self.inner.is_playing.store(STATE_PAUSED, Ordering::SeqCst);
-        self.inner.seek_frame.store(0, Ordering::SeqCst);
-        self.inner.current_frame.store(0, Ordering::SeqCst);
-        self.inner.lookahead_started.store(false, Ordering::SeqCst);
-        self.inner
-            .lookahead_completed
-            .store(false, Ordering::SeqCst);
+    /// Device the current or last stream played on.
+    pub fn output_device(&self) -> Option<String> {
         self.inner
-            .active_lyric_index
-            .store(NO_ACTIVE_LYRIC, Ordering::SeqCst);
-
-        if let Some(handle) = self.inner.decoder_thread.lock().map_err(lock_err)?.take() {
-            let _ = handle.join();
-        }
-        if let Some(handle) = self
-            .inner
-            .lyric_monitor_thread
+            .output_device_name
             .lock()
-            .map_err(lock_err)?
-            .take()
-        {
-            let _ = handle.join();
+            .ok()
+            .and_then(|name| name.clone())
+    }
+
+    /// Restarts a loaded file on the current output at the same position and play state.
+    fn reload_on_output(&self) -> Result<(), String> {
+        let Some(path) = self.loaded_path() else {
+            return Ok(());
+        };
+        if self.inner.live_stream.load(Ordering::SeqCst) {
+            return Ok(());
         }
-        self.inner.stream.lock().map_err(lock_err)?.take();
-        if let Ok(mut preloaded) = self.inner.preloaded_next_track.lock() {
-            preloaded.take();
+        let position = self.position_seconds();
+        let was_playing = self.is_playing();
+        // The play carries on across the switch rather than ending.
+        let listened = self.inner.listened_micros.load(Ordering::SeqCst);
+        self.inner.play_ended.store(true, Ordering::SeqCst);
+        self.load_track(&path)?;
+        self.inner.listened_micros.store(listened, Ordering::SeqCst);
+        self.seek(position);
+        if was_playing {
+            self.play();
         }
+        Ok(())
+    }
+
+    /// Decodes `path` and starts playing it through `output`, replacing the current stream.
+    pub(crate) fn load_track_with_output(
+        &self,
+        path: impl AsRef<Path>,
+        output: &dyn OutputBackend,
+    ) -> Result<(), String> {
+        self.stop_current_stream()?;
+        self.inner.live_stream.store(false, Ordering::SeqCst);
 
         let path = path.as_ref().to_path_buf();
         let decoded = decode_file(&path)?;
 
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or_else(|| "No default output device available".to_string())?;
         if let Ok(mut name) = self.inner.output_device_name.lock() {
-            *name = device
-                .name()
-                .unwrap_or_else(|_| "Unknown output device".to_string());
+            *name = Some(output.device_name());
         }
 
-        let (stream_config, sample_format, exact_rate) = select_stream_config(&device, &decoded)?;
-        #[cfg(target_os = "windows")]
-        {
-            info!(
-                "WASAPI path selected through default host. Exact rate match: {}. cpal exclusive-mode APIs are limited, so stream starts in best available mode.",
-                exact_rate
-            );
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            info!("Default host output configured. Exact rate match: {exact_rate}");
-        }
+        let format = output.negotiate(decoded.sample_rate, decoded.channels)?;
+        info!(
+            "Output stream configured for {}. Exact rate match: {}",
+            output.device_name(),
+            format.exact_rate
+        );
 
         let source_channels = decoded.channels as usize;
-        let output_channels = stream_config.channels as usize;
-        let output_rate = stream_config.sample_rate.0;
+        let output_channels = format.channels as usize;
+        let output_rate = format.sample_rate;
         self.inner
             .file_rate_hz
             .store(decoded.sample_rate, Ordering::SeqCst);
         self.inner
             .output_rate_hz
             .store(output_rate, Ordering::SeqCst);
+        self.inner
+            .output_channels
+            .store(format.channels as u32, Ordering::SeqCst);
         self.inner.stream_latency_ms_bits.store(
             ((RING_BUFFER_FRAMES as f32 / output_rate.max(1) as f32) * 1000.0).to_bits(),
             Ordering::SeqCst,
@@ -254,55 +516,82 @@ impl AudioState {
 
         if source_channels != output_channels {
             warn!(
-                "Channel adaptation required: source {} -> output {}. Using simple channel copy/fold strategy.",
+                "Channel adaptation required: source {} -> output {}.",
                 source_channels, output_channels
             );
-            pcm = adapt_channels(&pcm, source_channels, output_channels);
+            let mut upmixer = upmixer(&self.inner, output_rate, output_channels);
+            let mut downmixer = downmixer(&self.inner, output_rate, output_channels);
+            pcm = adapt_channels(
+                &pcm,
+                source_channels,
+                output_channels,
+                &mut upmixer,
+                &mut downmixer,
+            );
         }
         self.inner.track_duration_bits.store(
             (pcm.len() as f32 / output_channels as f32 / output_rate as f32).to_bits(),
             Ordering::SeqCst,
         );
+        let playable = playable_frames(&self.inner, &pcm, output_channels, output_rate);
+        self.inner
+            .current_frame
+            .store(playable.start as u32, Ordering::SeqCst);
+        self.inner
+            .playback_end_frame
+            .store(playable.end as u32, Ordering::SeqCst);
+        let gaps = track_gaps(&self.inner, &pcm, output_channels, output_rate);
 
-        let ring = HeapRb::<f32>::new(RING_BUFFER_FRAMES * output_channels);
+        // Every frame is queued behind its stem slots.
+        let frame_width = STEM_SLOTS + output_channels;
+        let ring = HeapRb::<f32>::new(RING_BUFFER_FRAMES * frame_width);
         let (mut producer, mut consumer) = ring.split();
         self.inner.ring_capacity_bytes.store(
-            (RING_BUFFER_FRAMES * output_channels * std::mem::size_of::<f32>()) as u32,
+            (RING_BUFFER_FRAMES * frame_width * std::mem::size_of::<f32>()) as u32,
             Ordering::SeqCst,
         );
 
         self.inner.should_stop.store(false, Ordering::SeqCst);
         let producer_engine = Arc::clone(&self.inner);
         let producer_handle = thread::spawn(move || {
-            let mut read_frame: usize = 0;
-            let mut total_frames = pcm.len() / output_channels;
+            let mut start_frame = playable.start;
+            let mut read_frame = playable.start;
+            let mut total_frames = playable.end;
+            let mut gaps = gaps;
+            let mut chunk = Vec::with_capacity(PRODUCER_CHUNK_FRAMES * output_channels);
+            let mut frames = Vec::with_capacity(PRODUCER_CHUNK_FRAMES * frame_width);
+            let mut centre_canceller = CentreCanceller::new(output_rate as f32);
 
             loop {
                 if producer_engine.should_stop.load(Ordering::SeqCst) {
                     break;
                 }
+                if producer_engine
+                    .play_end_reached
+                    .swap(false, Ordering::SeqCst)
+                {
+                    end_play(&producer_engine, true);
+                }
 
-                if producer_engine.lookahead_started.load(Ordering::SeqCst) {
-                    if !producer_engine.lookahead_completed.load(Ordering::SeqCst) {
-                        let next_path = producer_engine
-                            .next_track
-                            .lock()
-                            .ok()
-                            .and_then(|path| path.clone());
-                        if let Some(next_path) = next_path {
-                            if let Ok(decoded_next) = decode_file(&next_path) {
-                                if let Ok(mut preloaded) =
-                                    producer_engine.preloaded_next_track.lock()
-                                {
-                                    if preloaded.is_none() {
-                                        *preloaded = Some(decoded_next);
-                                        producer_engine
-                                            .lookahead_started
-                                            .store(false, Ordering::SeqCst);
-                                        producer_engine
-                                            .lookahead_completed
-                                            .store(true, Ordering::SeqCst);
-                                    }
+                if producer_engine.lookahead_started.load(Ordering::SeqCst)
+                    && !producer_engine.lookahead_completed.load(Ordering::SeqCst)
+                {
+                    let next_path = producer_engine
+                        .next_track
+                        .lock()
+                        .ok()
+                        .and_then(|path| path.clone());
+                    if let Some(next_path) = next_path {
+                        if let Ok(decoded_next) = decode_file(&next_path) {
+                            if let Ok(mut preloaded) = producer_engine.preloaded_next_track.lock() {
+                                if preloaded.is_none() {
+                                    *preloaded = Some(decoded_next);
+                                    producer_engine
+                                        .lookahead_started
+                                        .store(false, Ordering::SeqCst);
+                                    producer_engine
+                                        .lookahead_completed
+                                        .store(true, Ordering::SeqCst);
                                 }
                             }
                         }
@@ -311,48 +600,68 @@ impl AudioState {
 
                 let requested_seek = producer_engine.seek_frame.swap(u32::MAX, Ordering::SeqCst);
                 if requested_seek != u32::MAX {
-                    read_frame = (requested_seek as usize).min(total_frames);
-                    producer.clear();
+                    producer_engine
+                        .gap_skip_from
+                        .store(u32::MAX, Ordering::SeqCst);
+                    read_frame = (requested_seek as usize).clamp(start_frame, total_frames);
+                    if read_frame != requested_seek as usize {
+                        producer_engine
+                            .current_frame
+                            .store(read_frame as u32, Ordering::SeqCst);
+                    }
+                    // Queued audio can only be dropped from the consumer side, so hand the
+                    // flush to the output callback and hold off until it has happened.
+                    producer_engine
+                        .flush_requested
+                        .store(true, Ordering::SeqCst);
+                }
+                if producer_engine.flush_requested.load(Ordering::SeqCst) {
+                    thread::sleep(std::time::Duration::from_millis(1));
+                    continue;
                 }
 
                 if read_frame >= total_frames {
                     if let Ok(mut preloaded) = producer_engine.preloaded_next_track.lock() {
                         if let Some(next) = preloaded.take() {
-                            let mut next_pcm = next.samples;
-                            if next.sample_rate != output_rate {
-                                next_pcm = resample_hq(
-                                    &next_pcm,
-                                    next.sample_rate,
-                                    output_rate,
-                                    next.channels as usize,
-                                )
-                                .unwrap_or_else(|_| {
-                                    resample_linear(
-                                        &next_pcm,
-                                        next.sample_rate,
-                                        output_rate,
-                                        next.channels as usize,
-                                    )
-                                });
+                            end_play(&producer_engine, true);
+                            let next_rate = next.sample_rate;
+                            pcm = to_output_format(
+                                &producer_engine,
+                                next,
+                                output_rate,
+                                output_channels,
+                            );
+                            // The stems belonged to the previous track.
+                            if let Ok(mut vocals) = producer_engine.karaoke_vocals.lock() {
+                                *vocals = None;
                             }
-                            if next.channels as usize != output_channels {
-                                next_pcm = adapt_channels(
-                                    &next_pcm,
-                                    next.channels as usize,
-                                    output_channels,
-                                );
+                            if let Ok(mut stems) = producer_engine.stem_sources.lock() {
+                                *stems = None;
                             }
-                            pcm = next_pcm;
-                            total_frames = pcm.len() / output_channels;
-                            read_frame = 0;
-                            producer_engine.current_frame.store(0, Ordering::SeqCst);
-                            producer_engine
-                                .file_rate_hz
-                                .store(next.sample_rate, Ordering::SeqCst);
                             producer_engine.track_duration_bits.store(
-                                (total_frames as f32 / output_rate as f32).to_bits(),
+                                (pcm.len() as f32 / output_channels as f32 / output_rate as f32)
+                                    .to_bits(),
                                 Ordering::SeqCst,
                             );
+                            let playable = playable_frames(
+                                &producer_engine,
+                                &pcm,
+                                output_channels,
+                                output_rate,
+                            );
+                            start_frame = playable.start;
+                            read_frame = playable.start;
+                            total_frames = playable.end;
+                            gaps = track_gaps(&producer_engine, &pcm, output_channels, output_rate);
+                            producer_engine
+                                .current_frame
+                                .store(read_frame as u32, Ordering::SeqCst);
+                            producer_engine
+                                .playback_end_frame
+                                .store(total_frames as u32, Ordering::SeqCst);
+                            producer_engine
+                                .file_rate_hz
+                                .store(next_rate, Ordering::SeqCst);
                             producer_engine
                                 .lookahead_started
                                 .store(false, Ordering::SeqCst);
@@ -360,8 +669,13 @@ impl AudioState {
                                 .lookahead_completed
                                 .store(false, Ordering::SeqCst);
                             if let Ok(mut next_track) = producer_engine.next_track.lock() {
-                                next_track.take();
+                                if let Some(path) = next_track.take() {
+                                    if let Ok(mut loaded) = producer_engine.loaded_path.lock() {
+                                        *loaded = Some(path);
+                                    }
+                                }
                             }
+                            producer_engine.play_ended.store(false, Ordering::SeqCst);
                             continue;
                         }
                     }
@@ -370,66 +684,72 @@ impl AudioState {
                 }
 
                 let free_slots = producer.vacant_len();
-                if free_slots < output_channels {
+                if free_slots < frame_width {
                     thread::sleep(std::time::Duration::from_millis(2));
                     continue;
                 }
 
-                // 256-frame batches reduce producer wakeups without building long queueing latency.
-                let writable_frames = (free_slots / output_channels).min(PRODUCER_CHUNK_FRAMES);
-                let end = ((read_frame + writable_frames) * output_channels).min(pcm.len());
-                for sample in &pcm[read_frame * output_channels..end] {
-                    if producer.try_push(*sample).is_err() {
-                        break;
+                let mut chunk_end_frame = total_frames;
+                if producer_engine.skip_gaps.load(Ordering::Relaxed) {
+                    if let Some(gap) = gaps.iter().find(|gap| gap.end > read_frame) {
+                        if gap.start <= read_frame {
+                            // Only one skip can be in flight; gaps are far longer than the
+                            // ring, so a busy slot just means this one plays out.
+                            if producer_engine.gap_skip_from.load(Ordering::SeqCst) == u32::MAX {
+                                producer_engine
+                                    .gap_skip_to
+                                    .store(gap.end as u32, Ordering::SeqCst);
+                                producer_engine
+                                    .gap_skip_from
+                                    .store(read_frame as u32, Ordering::SeqCst);
+                                read_frame = gap.end;
+                                continue;
+                            }
+                        } else {
+                            chunk_end_frame = gap.start;
+                        }
                     }
                 }
+
+                // 256-frame batches reduce producer wakeups without building long queueing latency.
+                let writable_frames = (free_slots / frame_width).min(PRODUCER_CHUNK_FRAMES);
+                let end = ((read_frame + writable_frames).min(chunk_end_frame) * output_channels)
+                    .min(pcm.len());
+                let start = read_frame * output_channels;
+                chunk.clear();
+                chunk.extend_from_slice(&pcm[start..end]);
+                apply_karaoke(
+                    &producer_engine,
+                    &mut centre_canceller,
+                    &mut chunk,
+                    start,
+                    output_channels,
+                );
+                queue_with_stems(
+                    &producer_engine,
+                    &chunk,
+                    read_frame,
+                    output_channels,
+                    &mut frames,
+                );
+                producer.push_slice(&frames);
                 read_frame = end / output_channels;
             }
         });
 
         let callback_engine = Arc::clone(&self.inner);
-        let err_fn = |err| warn!("Audio stream error: {err}");
-        let stream = match sample_format {
-            SampleFormat::F32 => device
-                .build_output_stream(
-                    &stream_config,
-                    move |output: &mut [f32], _| {
-                        write_samples(output, output_channels, &mut consumer, &callback_engine);
-                    },
-                    err_fn,
-                    None,
-                )
-                .map_err(|e| format!("Failed to build f32 output stream: {e}"))?,
-            SampleFormat::I16 => device
-                .build_output_stream(
-                    &stream_config,
-                    move |output: &mut [i16], _| {
-                        write_samples_i16(output, output_channels, &mut consumer, &callback_engine);
-                    },
-                    err_fn,
-                    None,
-                )
-                .map_err(|e| format!("Failed to build i16 output stream: {e}"))?,
-            SampleFormat::U16 => device
-                .build_output_stream(
-                    &stream_config,
-                    move |output: &mut [u16], _| {
-                        write_samples_u16(output, output_channels, &mut consumer, &callback_engine);
-                    },
-                    err_fn,
-                    None,
-                )
-                .map_err(|e| format!("Failed to build u16 output stream: {e}"))?,
-            other => {
-                return Err(format!(
-                    "Unsupported output sample format {other:?}; expected f32/i16/u16"
-                ))
-            }
-        };
-
-        stream
-            .play()
-            .map_err(|e| format!("Failed to start stream: {e}"))?;
+        let stream = output.start(
+            &format,
+            Box::new(move |buffer: &mut [f32]| {
+                write_samples(
+                    buffer,
+                    output_channels,
+                    true,
+                    &mut consumer,
+                    &callback_engine,
+                );
+            }),
+        )?;
 
         *self.inner.loaded_path.lock().map_err(lock_err)? = Some(path);
         *self.inner.stream.lock().map_err(lock_err)? = Some(stream);
@@ -438,59 +758,416 @@ impl AudioState {
         Ok(())
     }
 
-    #[cfg(not(target_os = "windows"))]
-    pub fn load_track(&self, _path: impl AsRef<Path>) -> Result<(), String> {
-        Err("Audio engine WASAPI implementation is only available on Windows targets".to_string())
+    /// Connects to an internet radio stream and starts playing it, replacing the current
+    /// track. `on_metadata` receives the ICY now-playing updates.
+    pub fn load_stream(
+        &self,
+        url: &str,
+        on_metadata: Box<dyn FnMut(StreamMetadata) + Send + Sync>,
+    ) -> Result<StreamInfo, String> {
+        let output = self.output_backend()?;
+        let (reader, info) = open_stream(url, on_metadata)?;
+        let decoder = StreamDecoder::new(reader, info.content_type.as_deref())?;
+        self.load_stream_with_output(decoder, &*output)?;
+        Ok(info)
     }
 
-    #[cfg(target_os = "windows")]
-    pub fn playback_supported(&self) -> bool {
-        true
-    }
+    /// Plays a live stream through `output`. Packets are decoded as they arrive, so there
+    /// is no duration, seeking or gapless follow-up; the position counts listening time.
+    pub(crate) fn load_stream_with_output(
+        &self,
+        mut decoder: StreamDecoder,
+        output: &dyn OutputBackend,
+    ) -> Result<(), String> {
+        self.stop_current_stream()?;
+        self.inner.live_stream.store(true, Ordering::SeqCst);
+        if let Ok(mut lyrics) = self.inner.lyrics.lock() {
+            lyrics.clear();
+        }
 
-    #[cfg(not(target_os = "windows"))]
-    pub fn playback_supported(&self) -> bool {
-        false
-    }
+        if let Ok(mut name) = self.inner.output_device_name.lock() {
+            *name = Some(output.device_name());
+        }
+        let format = output.negotiate(decoder.sample_rate(), decoder.channels())?;
+        info!(
+            "Output stream configured for {} (live stream). Exact rate match: {}",
+            output.device_name(),
+            format.exact_rate
+        );
 
-    pub fn play(&self) {
-        self.inner.is_playing.store(STATE_PLAYING, Ordering::SeqCst);
+        let output_channels = format.channels as usize;
+        let output_rate = format.sample_rate;
+        // A deeper ring than file playback absorbs network jitter. Pause, volume and DSP act
+        // on the output side and streams cannot seek, so the depth adds no control latency.
+        let ring_frames = output_rate as usize * STREAM_BUFFER_SECONDS;
+        self.inner
+            .file_rate_hz
+            .store(decoder.sample_rate(), Ordering::SeqCst);
+        self.inner
+            .output_rate_hz
+            .store(output_rate, Ordering::SeqCst);
+        self.inner.stream_latency_ms_bits.store(
+            ((ring_frames as f32 / output_rate.max(1) as f32) * 1000.0).to_bits(),
+            Ordering::SeqCst,
+        );
+        if let Ok(mut chain) = self.inner.dsp_chain.lock() {
+            chain.set_sample_rate(output_rate as f32);
+        }
+        self.inner
+            .track_duration_bits
+            .store(0.0_f32.to_bits(), Ordering::SeqCst);
+        self.inner.playback_end_frame.store(0, Ordering::SeqCst);
+
+        let ring = HeapRb::<f32>::new(ring_frames * output_channels);
+        let (mut producer, mut consumer) = ring.split();
+        self.inner.ring_capacity_bytes.store(
+            (ring_frames * output_channels * std::mem::size_of::<f32>()) as u32,
+            Ordering::SeqCst,
+        );
+
+        self.inner.should_stop.store(false, Ordering::SeqCst);
+        let producer_engine = Arc::clone(&self.inner);
+        let producer_handle = thread::spawn(move || {
+            let mut pending = Vec::<f32>::new();
+            let mut offset = 0;
+            let mut upmixer = upmixer(&producer_engine, output_rate, output_channels);
+            let mut downmixer = downmixer(&producer_engine, output_rate, output_channels);
+            loop {
+                if producer_engine.should_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if offset >= pending.len() {
+                    let samples = match decoder.next_samples() {
+                        Ok(Some(samples)) => samples,
+                        Ok(None) => {
+                            info!("Live stream ended");
+                            break;
+                        }
+                        Err(err) => {
+                            warn!("Live stream stopped: {err}");
+                            break;
+                        }
+                    };
+                    let rate = decoder.sample_rate();
+                    let channels = decoder.channels() as usize;
+                    producer_engine.file_rate_hz.store(rate, Ordering::Relaxed);
+                    // Linear resampling per packet keeps up in real time; the rubato path
+                    // needs whole-file input.
+                    let resampled = resample_linear(&samples, rate, output_rate, channels);
+                    pending = adapt_channels(
+                        &resampled,
+                        channels,
+                        output_channels,
+                        &mut upmixer,
+                        &mut downmixer,
+                    );
+                    offset = 0;
+                    continue;
+                }
+
+                let free_slots = producer.vacant_len();
+                if free_slots < output_channels {
+                    thread::sleep(std::time::Duration::from_millis(2));
+                    continue;
+                }
+                let end = (offset + free_slots - free_slots % output_channels).min(pending.len());
+                for sample in &pending[offset..end] {
+                    if producer.try_push(*sample).is_err() {
+                        break;
+                    }
+                }
+                offset = end;
+            }
+        });
+
+        let callback_engine = Arc::clone(&self.inner);
+        let stream = output.start(
+            &format,
+            Box::new(move |buffer: &mut [f32]| {
+                write_samples(
+                    buffer,
+                    output_channels,
+                    false,
+                    &mut consumer,
+                    &callback_engine,
+                );
+            }),
+        )?;
+
+        *self.inner.loaded_path.lock().map_err(lock_err)? = None;
+        *self.inner.stream.lock().map_err(lock_err)? = Some(stream);
+        *self.inner.decoder_thread.lock().map_err(lock_err)? = Some(producer_handle);
+
+        Ok(())
     }
 
-    pub fn set_next_track(&self, path: Option<impl AsRef<Path>>) {
-        if let Ok(mut next_track) = self.inner.next_track.lock() {
-            *next_track = path.map(|path| path.as_ref().to_path_buf());
+    /// Fades out and tears down the current output stream and its worker threads.
+    fn stop_current_stream(&self) -> Result<(), String> {
+        // The producer may not have picked up an end the callback just reached.
+        let reached_end = self.inner.play_end_reached.swap(false, Ordering::SeqCst);
+        end_play(&self.inner, reached_end);
+        self.inner.play_ended.store(false, Ordering::SeqCst);
+        self.inner.listened_micros.store(0, Ordering::SeqCst);
+        if self
+            .inner
+            .stream
+            .lock()
+            .map_err(lock_err)?
+            .as_ref()
+            .is_some()
+        {
+            let fade_samples =
+                ((self.inner.output_rate_hz.load(Ordering::SeqCst) * STREAM_FADE_OUT_MS) / 1000)
+                    .max(1);
+            self.inner
+                .fade_out_total_samples
+                .store(fade_samples, Ordering::SeqCst);
+            self.inner
+                .fade_out_remaining_samples
+                .store(fade_samples, Ordering::SeqCst);
+            thread::sleep(std::time::Duration::from_millis(STREAM_FADE_OUT_MS as u64));
         }
+
+        self.inner.should_stop.store(true, Ordering::SeqCst);
+        self.inner.is_playing.store(STATE_PAUSED, Ordering::SeqCst);
+        *self.inner.karaoke_vocals.lock().map_err(lock_err)? = None;
+        *self.inner.stem_sources.lock().map_err(lock_err)? = None;
+        // `u32::MAX` means "no seek pending"; a fresh track starts at frame 0 anyway.
+        self.inner.seek_frame.store(u32::MAX, Ordering::SeqCst);
+        self.inner.current_frame.store(0, Ordering::SeqCst);
+        self.inner.gap_skip_from.store(u32::MAX, Ordering::SeqCst);
         self.inner.lookahead_started.store(false, Ordering::SeqCst);
         self.inner
             .lookahead_completed
             .store(false, Ordering::SeqCst);
-        #[cfg(target_os = "windows")]
-        if let Ok(mut preloaded) = self.inner.preloaded_next_track.lock() {
-            preloaded.take();
-        }
-    }
-
-    pub fn pause(&self) {
-        self.inner.is_playing.store(STATE_PAUSED, Ordering::SeqCst);
-    }
-
-    pub fn seek(&self, seconds: f64) {
-        let clamped = seconds.max(0.0);
-        let sample_rate = self.inner.output_rate_hz.load(Ordering::SeqCst) as f64;
-        let frame = (clamped * sample_rate) as u32;
-        self.inner.seek_frame.store(frame, Ordering::SeqCst);
-        self.inner.current_frame.store(frame, Ordering::SeqCst);
         self.inner
             .active_lyric_index
             .store(NO_ACTIVE_LYRIC, Ordering::SeqCst);
-    }
 
-    pub fn set_volume(&self, volume: f32) {
-        let clamped = volume.clamp(0.0, 1.0);
-        self.inner
-            .volume_bits
-            .store(clamped.to_bits(), Ordering::SeqCst);
+        if let Some(handle) = self.inner.decoder_thread.lock().map_err(lock_err)?.take() {
+            let _ = handle.join();
+        }
+        if let Some(handle) = self
+            .inner
+            .lyric_monitor_thread
+            .lock()
+            .map_err(lock_err)?
+            .take()
+        {
+            let _ = handle.join();
+        }
+        if let Some(handle) = self
+            .inner
+            .beat_monitor_thread
+            .lock()
+            .map_err(lock_err)?
+            .take()
+        {
+            let _ = handle.join();
+        }
+        self.inner.stream.lock().map_err(lock_err)?.take();
+        self.inner.flush_requested.store(false, Ordering::SeqCst);
+        self.inner.play_end_reached.store(false, Ordering::SeqCst);
+        // A fade the old stream did not finish must not leak into the new track.
+        self.inner
+            .fade_out_remaining_samples
+            .store(0, Ordering::SeqCst);
+        if let Ok(mut preloaded) = self.inner.preloaded_next_track.lock() {
+            preloaded.take();
+        }
+        Ok(())
+    }
+
+    pub fn playback_supported(&self) -> bool {
+        cfg!(target_os = "windows")
+            || self
+                .inner
+                .output_override
+                .lock()
+                .map(|output| output.is_some())
+                .unwrap_or(false)
+    }
+
+    pub fn play(&self) {
+        self.inner.is_playing.store(STATE_PLAYING, Ordering::SeqCst);
+    }
+
+    pub fn set_next_track(&self, path: Option<impl AsRef<Path>>) {
+        if let Ok(mut next_track) = self.inner.next_track.lock() {
+            *next_track = path.map(|path| path.as_ref().to_path_buf());
+        }
+        self.inner.lookahead_started.store(false, Ordering::SeqCst);
+        self.inner
+            .lookahead_completed
+            .store(false, Ordering::SeqCst);
+        if let Ok(mut preloaded) = self.inner.preloaded_next_track.lock() {
+            preloaded.take();
+        }
+    }
+
+    pub fn pause(&self) {
+        self.inner.is_playing.store(STATE_PAUSED, Ordering::SeqCst);
+    }
+
+    pub fn seek(&self, seconds: f64) {
+        if self.inner.live_stream.load(Ordering::SeqCst) {
+            return;
+        }
+        let clamped = seconds.max(0.0);
+        let sample_rate = self.inner.output_rate_hz.load(Ordering::SeqCst) as f64;
+        let frame = (clamped * sample_rate) as u32;
+        self.inner.gap_skip_from.store(u32::MAX, Ordering::SeqCst);
+        self.inner.seek_frame.store(frame, Ordering::SeqCst);
+        self.inner.current_frame.store(frame, Ordering::SeqCst);
+        // Seeking back from the end starts another play of the same track.
+        self.inner.play_end_reached.store(false, Ordering::SeqCst);
+        self.inner.play_ended.store(false, Ordering::SeqCst);
+        self.inner
+            .active_lyric_index
+            .store(NO_ACTIVE_LYRIC, Ordering::SeqCst);
+    }
+
+    /// Jumps back `seconds` from the current position (clamped to the track start) and
+    /// returns the new position. The lyrics index is reset by the seek, so the monitor
+    /// re-emits the line active at the replay point.
+    pub fn instant_replay(&self, seconds: f64) -> f64 {
+        let target = (self.position_seconds() - seconds.max(0.0)).max(0.0);
+        self.seek(target);
+        target
+    }
+
+    /// Lyric line active at `seconds`, if lyrics are loaded and the line has started.
+    pub fn lyric_line_at(&self, seconds: f64) -> Option<LyricsLine> {
+        let lyrics = self.inner.lyrics.lock().ok()?;
+        let index = lyric_index_at(&lyrics, (seconds.max(0.0) * 1000.0) as u32)?;
+        lyrics.get(index).cloned()
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        let clamped = volume.clamp(0.0, 1.0);
+        self.inner
+            .volume_bits
+            .store(clamped.to_bits(), Ordering::SeqCst);
+        if let Ok(chain) = self.inner.dsp_chain.lock() {
+            chain.loudness().set_volume(clamped);
+        }
+    }
+
+    /// Enables or disables the gap killer. Threshold and minimum duration apply to tracks
+    /// loaded (or gaplessly queued) from now on and keep their current values when `None`
+    /// (initially `DEFAULT_GAP_THRESHOLD_DB` and `DEFAULT_GAP_MIN_SECONDS`); toggling takes
+    /// effect immediately.
+    pub fn set_gap_skipping(
+        &self,
+        enabled: bool,
+        threshold_db: Option<f32>,
+        min_seconds: Option<f32>,
+    ) {
+        if let Some(threshold_db) = threshold_db {
+            self.inner
+                .gap_threshold_db_bits
+                .store(threshold_db.clamp(-90.0, -20.0).to_bits(), Ordering::SeqCst);
+        }
+        if let Some(min_seconds) = min_seconds {
+            self.inner
+                .gap_min_seconds_bits
+                .store(min_seconds.clamp(0.5, 600.0).to_bits(), Ordering::SeqCst);
+        }
+        self.inner.skip_gaps.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn set_karaoke(&self, settings: KaraokeSettings) {
+        self.inner.karaoke_vocal_level_bits.store(
+            settings.vocal_level.clamp(0.0, 1.0).to_bits(),
+            Ordering::SeqCst,
+        );
+        self.inner
+            .karaoke_enabled
+            .store(settings.enabled, Ordering::SeqCst);
+    }
+
+    pub fn karaoke(&self) -> KaraokeSettings {
+        karaoke_settings(&self.inner)
+    }
+
+    /// Gives karaoke mode the vocal stem of the loaded track to subtract, or `None` to
+    /// cancel the centre instead. Loading another track drops the stem.
+    pub fn set_karaoke_vocals(&self, vocals: Option<DecodedTrack>) -> Result<(), String> {
+        let vocals = vocals.map(|vocals| {
+            Arc::new(to_output_format(
+                &self.inner,
+                vocals,
+                self.inner.output_rate_hz.load(Ordering::SeqCst),
+                self.inner.output_channels.load(Ordering::SeqCst) as usize,
+            ))
+        });
+        *self.inner.karaoke_vocals.lock().map_err(lock_err)? = vocals;
+        Ok(())
+    }
+
+    /// Whether karaoke mode has a vocal stem for the loaded track.
+    pub fn has_karaoke_vocals(&self) -> bool {
+        self.inner
+            .karaoke_vocals
+            .lock()
+            .is_ok_and(|vocals| vocals.is_some())
+    }
+
+    /// Sets whether separated stems should replace the mix in the spatial room. The
+    /// stems themselves are handed over with `set_stem_sources`.
+    pub fn set_stem_playback(&self, enabled: bool) {
+        self.inner.stem_playback.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn stem_playback(&self) -> bool {
+        self.inner.stem_playback.load(Ordering::SeqCst)
+    }
+
+    /// Plays the separated stems of the loaded track, in `SOURCE_NAMES` order, through
+    /// their own spatial sources, or `None` to spatialize the mix again. Stems a
+    /// four-stem track lacks are `None` and stay silent. Each stem is converted to the
+    /// output rate and summed to mono. Loading another track drops them.
+    pub fn set_stem_sources(
+        &self,
+        stems: Option<[Option<DecodedTrack>; SOURCE_NAMES.len()]>,
+    ) -> Result<(), String> {
+        let output_rate = self.inner.output_rate_hz.load(Ordering::SeqCst);
+        let stems = stems.map(|stems| {
+            let mono = stems.map(|stem| {
+                let Some(stem) = stem else {
+                    return Vec::new();
+                };
+                let channels = usize::from(stem.channels.max(1));
+                to_output_format(&self.inner, stem, output_rate, channels)
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                    .collect::<Vec<_>>()
+            });
+            let frames = mono.iter().map(Vec::len).max().unwrap_or(0);
+            Arc::new(
+                (0..frames)
+                    .map(|frame| {
+                        mono.each_ref()
+                            .map(|stem| stem.get(frame).copied().unwrap_or(0.0))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        });
+        *self.inner.stem_sources.lock().map_err(lock_err)? = stems;
+        Ok(())
+    }
+
+    /// Whether stems of the loaded track are playing in place of the mix.
+    pub fn has_stem_sources(&self) -> bool {
+        self.inner
+            .stem_sources
+            .lock()
+            .is_ok_and(|stems| stems.is_some())
+    }
+
+    /// Skips leading/trailing silence of tracks loaded (or gaplessly queued) from now on.
+    pub fn set_silence_trimming(&self, enabled: bool) {
+        self.inner.trim_silence.store(enabled, Ordering::SeqCst);
     }
 
     pub fn set_preamp_db(&self, preamp_db: f32) {
@@ -500,6 +1177,10 @@ impl AudioState {
             .store(clamped.to_bits(), Ordering::SeqCst);
     }
 
+    fn preamp_db(&self) -> f32 {
+        f32::from_bits(self.inner.preamp_db_bits.load(Ordering::SeqCst))
+    }
+
     pub fn update_eq_band(
         &self,
         index: usize,
@@ -511,13 +1192,21 @@ impl AudioState {
         chain.update_user_eq_band(index, frequency, gain_db, q_factor)
     }
 
-    pub fn set_autoeq_profile(&self, profile: &[EqBandConfig]) -> Result<(), String> {
+    pub fn set_autoeq_profile(&self, profile: &[EqBandSettings]) -> Result<(), String> {
         let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
-        let mapped = profile
-            .iter()
-            .map(|band| (band.frequency, band.gain_db, band.q_factor))
-            .collect::<Vec<_>>();
-        chain.set_autoeq_profile(&mapped)
+        chain.set_autoeq_profile(profile)
+    }
+
+    /// Replaces the room correction stage; an empty correction turns it off.
+    pub fn set_room_correction(&self, correction: RoomCorrection) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.correction_mut().set_correction(correction);
+        Ok(())
+    }
+
+    pub fn room_correction(&self) -> Result<RoomCorrection, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.correction().correction().clone())
     }
 
     pub fn set_tone(&self, bass: f32, treble: f32) -> Result<(), String> {
@@ -533,12 +1222,173 @@ impl AudioState {
         Ok(())
     }
 
+    pub fn set_balance_mode(&self, mode: BalanceMode) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.balance().set_mode(mode);
+        Ok(())
+    }
+
+    pub fn balance_mode(&self) -> Result<BalanceMode, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.balance().mode())
+    }
+
+    pub fn set_mono(&self, enabled: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.balance().set_mono(enabled);
+        Ok(())
+    }
+
+    pub fn mono(&self) -> Result<bool, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.balance().is_mono())
+    }
+
+    /// Takes effect with the next track or stream that is loaded.
+    pub fn set_upmix(&self, settings: UpmixSettings) -> Result<(), String> {
+        *self.inner.upmix.lock().map_err(lock_err)? = settings.clamped();
+        Ok(())
+    }
+
+    pub fn upmix(&self) -> Result<UpmixSettings, String> {
+        Ok(*self.inner.upmix.lock().map_err(lock_err)?)
+    }
+
+    /// Applies at once. Upmixed sources pick up the change with the next track.
+    pub fn set_bass_management(&self, settings: BassManagementSettings) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.bass_management().set_settings(settings);
+        Ok(())
+    }
+
+    pub fn bass_management(&self) -> Result<BassManagementSettings, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.bass_management().settings())
+    }
+
     pub fn set_expansion(&self, amount: f32) -> Result<(), String> {
         let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
         chain.expansion().set_amount(amount);
         Ok(())
     }
 
+    pub fn set_loudness_compensation(&self, enabled: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.loudness().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn loudness_compensation(&self) -> Result<bool, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.loudness().is_enabled())
+    }
+
+    pub fn set_night_mode(&self, enabled: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.night_mode().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn night_mode(&self) -> Result<bool, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.night_mode().is_enabled())
+    }
+
+    pub fn set_analog_enabled(&self, enabled: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.analog().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn set_analog_params(
+        &self,
+        saturation: f32,
+        wow_flutter: f32,
+        crackle: f32,
+    ) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let analog = chain.analog();
+        analog.set_saturation(saturation);
+        analog.set_wow_flutter(wow_flutter);
+        analog.set_crackle(crackle);
+        Ok(())
+    }
+
+    pub fn analog(&self) -> Result<AnalogSettings, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.analog().settings())
+    }
+
+    pub fn set_compressor_enabled(&self, enabled: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.compressor().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn set_compressor_band(
+        &self,
+        index: usize,
+        threshold_db: f32,
+        ratio: f32,
+    ) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.compressor().set_band(index, threshold_db, ratio)
+    }
+
+    pub fn set_compressor_crossovers(&self, crossovers: &[f32]) -> Result<(), String> {
+        let crossovers = crossovers.try_into().map_err(|_| {
+            format!(
+                "Expected {} compressor crossovers, got {}",
+                COMPRESSOR_BANDS - 1,
+                crossovers.len()
+            )
+        })?;
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.compressor().set_crossovers(crossovers)
+    }
+
+    /// Compressor settings and the current gain reduction of each band in dB.
+    pub fn get_compressor(&self) -> Result<(CompressorSettings, Vec<f32>), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let compressor = chain.compressor();
+        Ok((compressor.settings(), compressor.gain_reduction_db()))
+    }
+
+    /// `lookahead_ms` of `None` keeps the current lookahead.
+    pub fn set_limiter_params(
+        &self,
+        threshold_db: f32,
+        release_ms: f32,
+        ceiling_db: f32,
+        lookahead_ms: Option<f32>,
+    ) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let limiter = chain.limiter();
+        limiter.set_params(LimiterSettings {
+            threshold_db,
+            release_ms,
+            ceiling_db,
+            lookahead_ms: lookahead_ms.unwrap_or(limiter.settings().lookahead_ms),
+        })
+    }
+
+    /// Loads a named crossfeed preset (see `tone::get_crossfeed_preset`), or `"off"`.
+    /// Selecting a preset while crossfeed is off turns it on at full amount.
+    pub fn set_crossfeed_preset(&self, name: &str) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        if name.trim().eq_ignore_ascii_case(CROSSFEED_OFF) {
+            chain.expansion().set_amount(0.0);
+            return Ok(());
+        }
+        let preset = get_crossfeed_preset(name)
+            .ok_or_else(|| format!("Unknown crossfeed preset: {name}"))?;
+        chain.expansion().load_preset(preset);
+        if chain.expansion().amount() < f32::EPSILON {
+            chain.expansion().set_amount(1.0);
+        }
+        Ok(())
+    }
+
     pub fn set_reverb_params(
         &self,
         room_size: f32,
@@ -566,6 +1416,51 @@ impl AudioState {
         Ok(())
     }
 
+    /// Applies the profile resolved for the track being loaded, or `None` when nothing
+    /// matched. Settings in effect before the first automatic profile are restored for
+    /// tracks without one, so a genre profile never leaks into the next album.
+    pub fn apply_track_dsp_profile(&self, profile: Option<&DspProfile>) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let mut baseline = self.inner.dsp_profile_baseline.lock().map_err(lock_err)?;
+        let Some(profile) = profile else {
+            return match baseline.take() {
+                Some(snapshot) => snapshot.restore(&chain),
+                None => Ok(()),
+            };
+        };
+        profile.validate()?;
+        baseline
+            .get_or_insert_with(|| DspSnapshot::capture(&chain))
+            .restore(&chain)?;
+
+        if let Some(gains) = &profile.eq_gains_db {
+            for (index, ((frequency, _, q_factor), gain_db)) in
+                chain.user_eq_bands().into_iter().zip(gains).enumerate()
+            {
+                chain.update_user_eq_band(index, frequency, *gain_db, q_factor)?;
+            }
+        }
+        if let Some(bass) = profile.bass_db {
+            chain.tone().set_bass(bass);
+        }
+        if let Some(treble) = profile.treble_db {
+            chain.tone().set_treble(treble);
+        }
+        if let Some(amount) = profile.expansion {
+            chain.expansion().set_amount(amount);
+        }
+        match profile.reverb_preset.as_deref() {
+            Some(name) if name.eq_ignore_ascii_case(REVERB_OFF) => chain.reverb().set_wet_mix(0.0),
+            Some(name) => {
+                let preset =
+                    get_preset(name).ok_or_else(|| format!("Unknown reverb preset: {name}"))?;
+                chain.reverb().load_preset(preset);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
     // ── Spatial audio controls ─────────────────────────────────────────
 
     pub fn set_spatial_enabled(&self, enabled: bool) -> Result<(), String> {
@@ -579,6 +1474,20 @@ impl AudioState {
         Ok(chain.spatial().is_enabled())
     }
 
+    pub fn set_spatial_output_mode(&self, mode: SpatialOutputMode) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial().set_output_mode(mode);
+        Ok(())
+    }
+
+    /// The selected output mode, and whether the room is actually rendered to speakers:
+    /// speaker mode falls back to binaural unless the output is 5.1 or 7.1.
+    pub fn spatial_output_mode(&self) -> Result<(SpatialOutputMode, bool), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let spatial = chain.spatial();
+        Ok((spatial.output_mode(), spatial.renders_speakers()))
+    }
+
     pub fn set_spatial_room_size(
         &self,
         width: f32,
@@ -614,6 +1523,128 @@ impl AudioState {
         Ok(())
     }
 
+    pub fn set_spatial_listener_orientation(
+        &self,
+        yaw: f32,
+        pitch: f32,
+        roll: f32,
+    ) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial().set_listener_orientation(yaw, pitch, roll);
+        Ok(())
+    }
+
+    pub fn set_spatial_listener_position(&self, x: f32, y: f32, z: f32) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial().set_listener_position(x, y, z);
+        Ok(())
+    }
+
+    pub fn set_spatial_listener_yaw(&self, degrees: f32) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial().set_listener_yaw(degrees);
+        Ok(())
+    }
+
+    /// Listener position in metres and the direction it faces in degrees.
+    pub fn spatial_listener(&self) -> Result<(f32, f32, f32, f32), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let spatial = chain.spatial();
+        let (x, y, z) = spatial.listener_position();
+        Ok((x, y, z, spatial.listener_yaw()))
+    }
+
+    /// Automates a source's position, or stops it where it is with `None`.
+    pub fn set_spatial_source_motion(
+        &self,
+        index: usize,
+        motion: Option<SourceMotion>,
+    ) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial_mut().set_source_motion(index, motion)
+    }
+
+    pub fn spatial_source_motions(&self) -> Result<Vec<Option<SourceMotion>>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.spatial().source_motions())
+    }
+
+    pub fn spatial_scene(&self) -> Result<SpatialScene, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(SpatialScene::capture(chain.spatial()))
+    }
+
+    /// Replaces the room, listener and sources; an invalid scene changes nothing.
+    pub fn apply_spatial_scene(&self, scene: &SpatialScene) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        scene.apply(chain.spatial_mut())
+    }
+
+    /// Adds a named source to the spatial room and returns its index.
+    pub fn add_spatial_source(&self, name: &str, x: f32, y: f32, z: f32) -> Result<usize, String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial_mut().add_source(name, x, y, z)
+    }
+
+    pub fn remove_spatial_source(&self, name: &str) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial_mut().remove_source(name)
+    }
+
+    pub fn update_spatial_source_eq_band(
+        &self,
+        index: usize,
+        band: usize,
+        frequency: f32,
+        gain_db: f32,
+        q_factor: f32,
+    ) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain
+            .spatial_mut()
+            .update_source_eq_band(index, band, frequency, gain_db, q_factor)
+    }
+
+    pub fn reset_spatial_source_eq(&self, index: usize) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial_mut().reset_source_eq(index)
+    }
+
+    pub fn spatial_source_eq(&self, index: usize) -> Result<Vec<EqBandSettings>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain
+            .spatial()
+            .source_eq(index)
+            .ok_or_else(|| format!("Spatial source {index} does not exist"))
+    }
+
+    /// Changes part of a source's gain, mute and solo, e.g. only its solo flag.
+    pub fn update_spatial_source_mix(
+        &self,
+        index: usize,
+        update: impl FnOnce(&mut SourceMix),
+    ) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let spatial = chain.spatial();
+        let mut mix = spatial
+            .source_mix()
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("Spatial source {index} does not exist"))?;
+        update(&mut mix);
+        spatial.set_source_mix(index, mix)
+    }
+
+    pub fn spatial_source_mix(&self) -> Result<Vec<SourceMix>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.spatial().source_mix())
+    }
+
+    pub fn spatial_source_names(&self) -> Result<Vec<String>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.spatial().source_names())
+    }
+
     pub fn get_spatial_source_positions(&self) -> Result<Vec<(f32, f32, f32, bool)>, String> {
         let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
         Ok(chain.spatial().source_positions())
@@ -625,10 +1656,245 @@ impl AudioState {
         Ok(())
     }
 
-    /// Returns current EQ band parameters as Vec of (frequency, gain_db, q_factor).
-    pub fn get_eq_bands(&self) -> Result<Vec<(f32, f32, f32)>, String> {
+    /// Convolves spatial sources with a measured HRTF set, or returns to the built-in
+    /// ITD/ILD model with `None`.
+    pub fn set_spatial_hrtf(&self, hrtf: Option<Arc<HrirSet>>) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.spatial_mut().set_hrtf(hrtf);
+        Ok(())
+    }
+
+    /// Adds a loaded plugin effect at `position` and returns its insert ID.
+    pub fn insert_plugin(
+        &self,
+        position: PluginPosition,
+        plugin: Box<dyn EffectPlugin>,
+    ) -> Result<u32, String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.plugins_mut().insert(position, plugin)
+    }
+
+    /// Returns `false` when no insert had that ID.
+    pub fn remove_plugin(&self, id: u32) -> Result<bool, String> {
+        let plugin = self
+            .inner
+            .dsp_chain
+            .lock()
+            .map_err(lock_err)?
+            .plugins_mut()
+            .remove(id);
+        // Unloaded here, after the chain lock is released.
+        Ok(plugin.is_some())
+    }
+
+    /// Swaps the whole rack for `plugins`, the chain of `track` (`None` for the default
+    /// chain). Inserts that fail to take the current sample rate are left out.
+    pub fn replace_plugin_chain(
+        &self,
+        track: Option<String>,
+        plugins: Vec<(PluginPosition, Box<dyn EffectPlugin>)>,
+    ) -> Result<(), String> {
+        let old = {
+            let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+            let rack = chain.plugins_mut();
+            let old = rack.clear();
+            rack.set_track(track);
+            for (position, plugin) in plugins {
+                let name = plugin.name().to_string();
+                if let Err(err) = rack.insert(position, plugin) {
+                    eprintln!("Failed to insert plugin {name}: {err}");
+                }
+            }
+            old
+        };
+        // Unloaded here, after the chain lock is released.
+        drop(old);
+        Ok(())
+    }
+
+    /// The track whose own plugin chain is loaded, or `None` for the default chain.
+    pub fn plugin_chain_track(&self) -> Result<Option<String>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.plugins().track().map(ToOwned::to_owned))
+    }
+
+    /// Makes the loaded inserts the chain of `track` without reloading them.
+    pub fn set_plugin_chain_track(&self, track: Option<String>) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.plugins_mut().set_track(track);
+        Ok(())
+    }
+
+    pub fn plugin_inserts(&self) -> Result<Vec<PluginInsertInfo>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.plugins().inserts())
+    }
+
+    pub fn plugin_parameters(&self, id: u32) -> Result<Vec<PluginParameter>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain
+            .plugins()
+            .plugin(id)
+            .map(|plugin| plugin.parameters())
+            .ok_or_else(|| format!("No plugin insert with ID {id}"))
+    }
+
+    pub fn set_plugin_parameter(&self, id: u32, param_id: u32, value: f64) -> Result<(), String> {
+        let mut chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain
+            .plugins_mut()
+            .plugin_mut(id)
+            .ok_or_else(|| format!("No plugin insert with ID {id}"))?
+            .set_parameter(param_id, value)
+    }
+
+    /// Every insert in chain order with its current plugin state, for persistence.
+    pub fn plugin_states(&self) -> Result<Vec<(PluginInsertInfo, PluginState)>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let rack = chain.plugins();
+        rack.inserts()
+            .into_iter()
+            .map(|info| {
+                let state = rack
+                    .plugin(info.id)
+                    .ok_or_else(|| format!("No plugin insert with ID {}", info.id))?
+                    .state()?;
+                Ok((info, state))
+            })
+            .collect()
+    }
+
+    /// Returns the current user EQ bands, filter types included.
+    pub fn get_eq_bands(&self) -> Result<Vec<EqBandSettings>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.user_eq_settings())
+    }
+
+    /// Replaces the user EQ with `bands` (e.g. a saved preset); any remaining bands are
+    /// flattened.
+    pub fn apply_eq_bands(&self, bands: &[EqBandSettings]) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.apply_user_eq_settings(bands)
+    }
+
+    /// Stores the current EQ, tone, reverb, expansion and spatial settings as `slot`.
+    pub fn capture_ab_snapshot(&self, slot: AbSlot) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        let snapshot = AbSnapshot::capture(&chain);
+        drop(chain);
+        let mut snapshots = self.inner.ab_snapshots.lock().map_err(lock_err)?;
+        *snapshots.slot_mut(slot) = Some(snapshot);
+        Ok(())
+    }
+
+    /// Applies the settings captured as `slot`. During playback the output dips for
+    /// 2 × `AB_SWITCH_FADE_MS` and the settings change at the bottom of the dip, so
+    /// the switch does not click.
+    pub fn switch_ab_snapshot(&self, slot: AbSlot) -> Result<(), String> {
+        let mut snapshots = self.inner.ab_snapshots.lock().map_err(lock_err)?;
+        let snapshot = snapshots
+            .slot_mut(slot)
+            .clone()
+            .ok_or_else(|| format!("Snapshot {slot:?} has not been captured"))?;
+        snapshots.active = Some(slot);
+        drop(snapshots);
+
+        let streaming = self.is_playing() && self.inner.stream.lock().map_err(lock_err)?.is_some();
+        if !streaming {
+            self.inner
+                .ab_dip_remaining_samples
+                .store(0, Ordering::SeqCst);
+            self.inner.ab_pending.lock().map_err(lock_err)?.take();
+            let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+            return snapshot.restore(&chain);
+        }
+        *self.inner.ab_pending.lock().map_err(lock_err)? = Some(snapshot);
+        let half =
+            (AB_SWITCH_FADE_MS * self.inner.output_rate_hz.load(Ordering::Relaxed) / 1000).max(1);
+        self.inner
+            .ab_dip_total_samples
+            .store(2 * half, Ordering::SeqCst);
+        self.inner
+            .ab_dip_remaining_samples
+            .store(2 * half, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Whether A and B have been captured, and which one was switched to last.
+    pub fn ab_status(&self) -> Result<(bool, bool, Option<AbSlot>), String> {
+        let snapshots = self.inner.ab_snapshots.lock().map_err(lock_err)?;
+        Ok((
+            snapshots.a.is_some(),
+            snapshots.b.is_some(),
+            snapshots.active,
+        ))
+    }
+
+    /// Current settings of the whole DSP chain, preamp included.
+    pub fn capture_chain_preset(&self) -> Result<ChainPreset, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(ChainPreset::capture(&chain, self.preamp_db()))
+    }
+
+    pub fn apply_chain_preset(&self, preset: &ChainPreset) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        preset.apply(&chain)?;
+        drop(chain);
+        self.set_preamp_db(preset.preamp_db);
+        Ok(())
+    }
+
+    pub fn set_node_bypassed(&self, node: DspNode, bypassed: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.set_node_bypassed(node, bypassed);
+        Ok(())
+    }
+
+    /// Bypass state of every `DspNode`, in signal order.
+    pub fn dsp_node_bypass(&self) -> Result<Vec<(DspNode, bool)>, String> {
         let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
-        Ok(chain.user_eq_bands())
+        Ok(DspNode::ALL
+            .iter()
+            .map(|node| (*node, chain.is_node_bypassed(*node)))
+            .collect())
+    }
+
+    /// Switches the user EQ stage between the parametric and the 31-band graphic EQ.
+    /// Both keep their settings while the other one runs.
+    pub fn set_eq_mode(&self, mode: EqMode) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.set_eq_mode(mode);
+        Ok(())
+    }
+
+    pub fn eq_mode(&self) -> Result<EqMode, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.eq_mode())
+    }
+
+    /// Runs the user EQ stage (parametric or graphic) as a linear-phase FIR instead of
+    /// biquads. This adds `LINEAR_PHASE_LATENCY_FRAMES` of delay, reported in
+    /// `AudioStats::eq_latency_ms`.
+    pub fn set_eq_linear_phase(&self, enabled: bool) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.linear_phase().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn eq_linear_phase(&self) -> Result<bool, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.linear_phase().is_enabled())
+    }
+
+    pub fn set_graphic_eq_band(&self, index: usize, gain_db: f32) -> Result<(), String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        chain.graphic_eq().set_gain(index, gain_db)
+    }
+
+    /// Graphic EQ gains in dB, in the order of `GRAPHIC_EQ_FREQUENCIES`.
+    pub fn graphic_eq_gains(&self) -> Result<Vec<f32>, String> {
+        let chain = self.inner.dsp_chain.lock().map_err(lock_err)?;
+        Ok(chain.graphic_eq().gains())
     }
 
     /// Computes the combined EQ frequency response curve.
@@ -639,20 +1905,40 @@ impl AudioState {
     }
 
     pub fn get_audio_stats(&self) -> AudioStats {
+        let output_sample_rate_hz = self.inner.output_rate_hz.load(Ordering::Relaxed);
+        let (limiter_gain_reduction_db, linear_phase) = self
+            .inner
+            .dsp_chain
+            .lock()
+            .map(|chain| {
+                (
+                    chain.limiter().gain_reduction_db(),
+                    chain.linear_phase().is_enabled(),
+                )
+            })
+            .unwrap_or_default();
+        let eq_latency_ms = if linear_phase && output_sample_rate_hz > 0 {
+            LINEAR_PHASE_LATENCY_FRAMES as f32 * 1000.0 / output_sample_rate_hz as f32
+        } else {
+            0.0
+        };
         AudioStats {
             device: self
                 .inner
                 .output_device_name
                 .lock()
-                .map(|name| name.clone())
-                .unwrap_or_else(|_| "Unavailable".to_string()),
+                .ok()
+                .and_then(|name| name.clone())
+                .unwrap_or_else(|| "Unavailable".to_string()),
             stream_latency_ms: f32::from_bits(
                 self.inner.stream_latency_ms_bits.load(Ordering::Relaxed),
             ),
-            output_sample_rate_hz: self.inner.output_rate_hz.load(Ordering::Relaxed),
+            output_sample_rate_hz,
             file_sample_rate_hz: self.inner.file_rate_hz.load(Ordering::Relaxed),
             ring_buffer_capacity_bytes: self.inner.ring_capacity_bytes.load(Ordering::Relaxed),
             ring_buffer_used_bytes: self.inner.ring_used_bytes.load(Ordering::Relaxed),
+            limiter_gain_reduction_db,
+            eq_latency_ms,
         }
     }
 
@@ -671,14 +1957,58 @@ impl AudioState {
         (compute_spectrum_mono(&mono), amplitude)
     }
 
+    /// Plays that ended since the last call, oldest first.
+    pub fn take_ended_plays(&self) -> Vec<EndedPlay> {
+        self.inner
+            .ended_plays
+            .lock()
+            .map(|mut plays| plays.drain(..).collect())
+            .unwrap_or_default()
+    }
+
+    /// Path of the track playing now; gapless transitions move it to the next track.
+    pub fn loaded_path(&self) -> Option<PathBuf> {
+        self.inner.loaded_path.lock().ok()?.clone()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.inner.is_playing.load(Ordering::SeqCst) == STATE_PLAYING
+    }
+
+    pub fn position_seconds(&self) -> f64 {
+        let rate = self.inner.output_rate_hz.load(Ordering::SeqCst).max(1) as f64;
+        self.inner.current_frame.load(Ordering::SeqCst) as f64 / rate
+    }
+
     pub fn get_track_duration_seconds(&self) -> f32 {
         f32::from_bits(self.inner.track_duration_bits.load(Ordering::Relaxed))
     }
 
+    pub fn lyrics_parse_mode(&self) -> LrcParseMode {
+        if self.inner.lyrics_lenient.load(Ordering::Relaxed) {
+            LrcParseMode::Lenient
+        } else {
+            LrcParseMode::Strict
+        }
+    }
+
     pub fn load_lyrics_for_track(&self, path: impl AsRef<Path>) {
-        let lyrics = load_lyrics_for_track(path.as_ref());
+        let document = load_lyrics_for_track(path.as_ref(), self.lyrics_parse_mode());
+        if !document.warnings.is_empty() {
+            warn!(
+                "{} lyric line(s) dropped or repaired for {}",
+                document.warnings.len(),
+                path.as_ref().display()
+            );
+        }
         if let Ok(mut shared) = self.inner.lyrics.lock() {
-            *shared = lyrics;
+            *shared = document.lines;
+        }
+        if let Ok(mut tags) = self.inner.lyrics_tags.lock() {
+            *tags = document.tags;
+        }
+        if let Ok(mut warnings) = self.inner.lyrics_warnings.lock() {
+            *warnings = document.warnings;
         }
         self.inner
             .active_lyric_index
@@ -693,6 +2023,27 @@ impl AudioState {
             .unwrap_or_default()
     }
 
+    /// Selects the parser mode used the next time lyrics are loaded.
+    pub fn set_lyrics_lenient(&self, lenient: bool) {
+        self.inner.lyrics_lenient.store(lenient, Ordering::SeqCst);
+    }
+
+    pub fn get_lyrics_tags(&self) -> LrcTags {
+        self.inner
+            .lyrics_tags
+            .lock()
+            .map(|tags| tags.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_lyrics_warnings(&self) -> Vec<LrcWarning> {
+        self.inner
+            .lyrics_warnings
+            .lock()
+            .map(|warnings| warnings.clone())
+            .unwrap_or_default()
+    }
+
     pub fn start_lyrics_monitor(&self, app: AppHandle) -> Result<(), String> {
         if let Some(handle) = self
             .inner
@@ -715,12 +2066,7 @@ impl AudioState {
             let rate = engine.output_rate_hz.load(Ordering::Relaxed).max(1);
             let frame = engine.current_frame.load(Ordering::Relaxed);
             let now_ms = ((frame as u64) * 1000 / (rate as u64)) as u32;
-            // `Err(next)` means insertion point for `now_ms`, so the active lyric is `next - 1`.
-            let index = match lyrics.binary_search_by(|line| line.timestamp.cmp(&now_ms)) {
-                Ok(found) => Some(found),
-                Err(0) => None,
-                Err(next) => Some(next - 1),
-            };
+            let index = lyric_index_at(&lyrics, now_ms);
             let current_idx = index.map(|i| i as u32).unwrap_or(NO_ACTIVE_LYRIC);
             if engine
                 .active_lyric_index
@@ -747,6 +2093,62 @@ impl AudioState {
         Ok(())
     }
 
+    /// Runs spectral-flux beat detection over the visualizer sample window and emits
+    /// `beat-detected` events while the current track is playing.
+    pub fn start_beat_monitor(&self, app: AppHandle) -> Result<(), String> {
+        if let Some(handle) = self
+            .inner
+            .beat_monitor_thread
+            .lock()
+            .map_err(lock_err)?
+            .take()
+        {
+            let _ = handle.join();
+        }
+        let engine = Arc::clone(&self.inner);
+        let handle = thread::spawn(move || {
+            let mut detector = BeatDetector::new(1000.0 / BEAT_POLL_INTERVAL_MS as f32);
+            let mut last_frame = engine.current_frame.load(Ordering::Relaxed);
+            loop {
+                if engine.should_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                thread::sleep(std::time::Duration::from_millis(BEAT_POLL_INTERVAL_MS));
+                if engine.is_playing.load(Ordering::Relaxed) != STATE_PLAYING {
+                    continue;
+                }
+                let frame = engine.current_frame.load(Ordering::Relaxed);
+                // A backwards jump means a seek or gapless transition: old history no longer applies.
+                if frame < last_frame {
+                    detector.reset();
+                }
+                last_frame = frame;
+
+                let window = match engine.vibe_samples.lock() {
+                    Ok(samples) if samples.len() >= ONSET_FRAME_SIZE => samples
+                        .iter()
+                        .skip(samples.len() - ONSET_FRAME_SIZE)
+                        .copied()
+                        .collect::<Vec<_>>(),
+                    _ => continue,
+                };
+                if let Some(beat) = detector.process_window(&window) {
+                    let rate = engine.output_rate_hz.load(Ordering::Relaxed).max(1);
+                    let _ = app.emit(
+                        "beat-detected",
+                        BeatEventPayload {
+                            confidence: beat.confidence,
+                            tempo_bpm: beat.tempo_bpm,
+                            position_ms: ((frame as u64) * 1000 / (rate as u64)) as u32,
+                        },
+                    );
+                }
+            }
+        });
+        *self.inner.beat_monitor_thread.lock().map_err(lock_err)? = Some(handle);
+        Ok(())
+    }
+
     #[cfg(test)]
     fn playing_state(&self) -> u8 {
         self.inner.is_playing.load(Ordering::SeqCst)
@@ -757,11 +2159,6 @@ impl AudioState {
         f32::from_bits(self.inner.volume_bits.load(Ordering::SeqCst))
     }
 
-    #[cfg(test)]
-    fn preamp_db(&self) -> f32 {
-        f32::from_bits(self.inner.preamp_db_bits.load(Ordering::SeqCst))
-    }
-
     #[cfg(test)]
     fn has_next_track(&self) -> bool {
         self.inner
@@ -777,11 +2174,8 @@ impl Drop for AudioState {
         self.inner.should_stop.store(true, Ordering::SeqCst);
         // Release the WASAPI / cpal stream first so the audio device is freed
         // before we block on worker threads, allowing other apps to use audio.
-        #[cfg(target_os = "windows")]
-        {
-            if let Ok(mut stream) = self.inner.stream.lock() {
-                drop(stream.take());
-            }
+        if let Ok(mut stream) = self.inner.stream.lock() {
+            drop(stream.take());
         }
         if let Ok(mut handle) = self.inner.decoder_thread.lock() {
             if let Some(join_handle) = handle.take() {
@@ -793,69 +2187,170 @@ impl Drop for AudioState {
                 let _ = join_handle.join();
             }
         }
+        if let Ok(mut handle) = self.inner.beat_monitor_thread.lock() {
+            if let Some(join_handle) = handle.take() {
+                let _ = join_handle.join();
+            }
+        }
+    }
+}
+
+/// Upmixer for the current settings; each stream or gapless track gets a fresh one.
+fn upmixer(engine: &AudioEngine, output_rate: u32, output_channels: usize) -> Upmixer {
+    let settings = engine
+        .upmix
+        .lock()
+        .map(|settings| *settings)
+        .unwrap_or_default();
+    let upmixer = Upmixer::new(settings, output_rate as f32, output_channels);
+    let bass_managed = engine
+        .dsp_chain
+        .lock()
+        .is_ok_and(|chain| chain.bass_management().is_enabled());
+    if bass_managed {
+        upmixer.bass_managed()
+    } else {
+        upmixer
+    }
+}
+
+/// Binaural downmixer for multichannel sources on a stereo output, through the spatial
+/// room's HRTF set when one is loaded. Like the upmixer, each stream or gapless track
+/// gets a fresh one.
+fn downmixer(engine: &AudioEngine, output_rate: u32, output_channels: usize) -> BinauralDownmixer {
+    let hrtf = engine
+        .dsp_chain
+        .lock()
+        .ok()
+        .and_then(|chain| chain.spatial().hrtf());
+    BinauralDownmixer::new(output_rate as f32, output_channels, hrtf)
+}
+
+/// Resamples and adapts a decoded track to the output's rate and channel count.
+fn to_output_format(
+    engine: &AudioEngine,
+    track: DecodedTrack,
+    output_rate: u32,
+    output_channels: usize,
+) -> Vec<f32> {
+    let channels = track.channels as usize;
+    let mut pcm = track.samples;
+    if track.sample_rate != output_rate {
+        pcm = resample_hq(&pcm, track.sample_rate, output_rate, channels)
+            .unwrap_or_else(|_| resample_linear(&pcm, track.sample_rate, output_rate, channels));
+    }
+    if channels == output_channels {
+        return pcm;
+    }
+    let mut upmixer = upmixer(engine, output_rate, output_channels);
+    let mut downmixer = downmixer(engine, output_rate, output_channels);
+    adapt_channels(
+        &pcm,
+        channels,
+        output_channels,
+        &mut upmixer,
+        &mut downmixer,
+    )
+}
+
+fn karaoke_settings(engine: &AudioEngine) -> KaraokeSettings {
+    KaraokeSettings {
+        enabled: engine.karaoke_enabled.load(Ordering::Relaxed),
+        vocal_level: f32::from_bits(engine.karaoke_vocal_level_bits.load(Ordering::Relaxed)),
+    }
+}
+
+/// Takes the voice out of a chunk of the track starting at sample `offset`: the vocal
+/// stem is subtracted when there is one, otherwise the centre is cancelled.
+fn apply_karaoke(
+    engine: &AudioEngine,
+    canceller: &mut CentreCanceller,
+    chunk: &mut [f32],
+    offset: usize,
+    channels: usize,
+) {
+    let cut = karaoke_settings(engine).cut();
+    if cut <= 0.0 {
+        return;
+    }
+    let vocals = engine.karaoke_vocals.lock().ok().and_then(|v| v.clone());
+    match vocals {
+        Some(vocals) => {
+            let stem = vocals.get(offset..).unwrap_or_default();
+            for (sample, vocal) in chunk.iter_mut().zip(stem) {
+                *sample -= cut * vocal;
+            }
+        }
+        None => {
+            for frame in chunk.chunks_mut(channels.max(1)) {
+                canceller.process_frame(frame, cut);
+            }
+        }
+    }
+}
+
+/// Interleaves a chunk of the track starting at `first_frame` into `frames`, each frame
+/// behind its stem slots: a flag set when stems are playing, then one sample per stem.
+/// In karaoke mode the vocal stem is turned down like the voice in the mix.
+fn queue_with_stems(
+    engine: &AudioEngine,
+    chunk: &[f32],
+    first_frame: usize,
+    channels: usize,
+    frames: &mut Vec<f32>,
+) {
+    frames.clear();
+    let stems = engine
+        .stem_sources
+        .lock()
+        .ok()
+        .and_then(|stems| stems.clone());
+    let vocal_gain = 1.0 - karaoke_settings(engine).cut();
+    for (index, frame) in chunk.chunks(channels.max(1)).enumerate() {
+        match stems
+            .as_ref()
+            .and_then(|stems| stems.get(first_frame + index))
+        {
+            Some(stem_frame) => {
+                frames.push(1.0);
+                frames.push(stem_frame[0] * vocal_gain);
+                frames.extend_from_slice(&stem_frame[1..]);
+            }
+            None => frames.extend_from_slice(&[0.0; STEM_SLOTS]),
+        }
+        frames.extend_from_slice(frame);
     }
 }
 
-#[cfg(target_os = "windows")]
-fn select_stream_config(
-    device: &cpal::Device,
-    track: &DecodedTrack,
-) -> Result<(StreamConfig, SampleFormat, bool), String> {
-    let mut preferred: Option<(StreamConfig, SampleFormat, bool)> = None;
-    let mut fallback: Option<(StreamConfig, SampleFormat, bool)> = None;
-
-    let ranges = device
-        .supported_output_configs()
-        .map_err(|e| format!("Cannot query output configs: {e}"))?;
-
-    for cfg in ranges {
-        let channels = cfg.channels();
-        let sample_format = cfg.sample_format();
-        let min = cfg.min_sample_rate().0;
-        let max = cfg.max_sample_rate().0;
-
-        let exact_rate = track.sample_rate >= min && track.sample_rate <= max;
-        let f32_preferred = matches!(sample_format, SampleFormat::F32);
-
-        if channels == track.channels && exact_rate {
-            let chosen = (
-                StreamConfig {
-                    channels,
-                    sample_rate: SampleRate(track.sample_rate),
-                    buffer_size: cpal::BufferSize::Default,
-                },
-                sample_format,
-                true,
-            );
-            if f32_preferred {
-                return Ok(chosen);
-            }
-            preferred = Some(chosen);
-        }
-
-        if fallback.is_none() || f32_preferred {
-            fallback = Some((
-                StreamConfig {
-                    channels,
-                    sample_rate: cfg.max_sample_rate(),
-                    buffer_size: cpal::BufferSize::Default,
-                },
-                sample_format,
-                false,
-            ));
-        }
+/// Pops the stem slots ahead of a file frame; `None` when the frame has no stems.
+fn pop_stem_frame(consumer: &mut impl ringbuf::traits::Consumer<Item = f32>) -> Option<StemFrame> {
+    let playing = consumer.try_pop().unwrap_or(0.0) > 0.5;
+    let mut stems = [0.0; SOURCE_NAMES.len()];
+    for stem in &mut stems {
+        *stem = consumer.try_pop().unwrap_or(0.0);
     }
-
-    preferred
-        .or(fallback)
-        .ok_or_else(|| "No output stream configuration available".to_string())
+    playing.then_some(stems)
 }
 
-#[cfg(target_os = "windows")]
-fn adapt_channels(input: &[f32], in_channels: usize, out_channels: usize) -> Vec<f32> {
+/// Fits `input` to the output's channel count: mono and stereo sources are upmixed for
+/// multichannel outputs, surround sources are downmixed binaurally for stereo outputs,
+/// anything else is copied round-robin.
+fn adapt_channels(
+    input: &[f32],
+    in_channels: usize,
+    out_channels: usize,
+    upmixer: &mut Upmixer,
+    downmixer: &mut BinauralDownmixer,
+) -> Vec<f32> {
     if in_channels == out_channels || in_channels == 0 || out_channels == 0 {
         return input.to_vec();
     }
+    if let Some(upmixed) = upmixer.upmix(input, in_channels) {
+        return upmixed;
+    }
+    if let Some(downmixed) = downmixer.downmix(input, in_channels) {
+        return downmixed;
+    }
 
     let frames = input.len() / in_channels;
     let mut out = vec![0.0_f32; frames * out_channels];
@@ -867,13 +2362,20 @@ fn adapt_channels(input: &[f32], in_channels: usize, out_channels: usize) -> Vec
     out
 }
 
-#[cfg(target_os = "windows")]
+/// Plays queued frames through the DSP chain into `output`. File playback queues each
+/// frame behind its `STEM_SLOTS`; a frame is only taken once it is fully queued, so an
+/// underrun never shifts the frame layout.
 fn write_samples(
     output: &mut [f32],
     channels: usize,
+    stem_slots: bool,
     consumer: &mut impl ringbuf::traits::Consumer<Item = f32>,
     engine: &AudioEngine,
 ) {
+    if engine.flush_requested.load(Ordering::SeqCst) {
+        consumer.clear();
+        engine.flush_requested.store(false, Ordering::SeqCst);
+    }
     if engine.is_playing.load(Ordering::SeqCst) != STATE_PLAYING {
         output.fill(0.0);
         return;
@@ -883,138 +2385,78 @@ fn write_samples(
     let preamp_db = f32::from_bits(engine.preamp_db_bits.load(Ordering::Relaxed));
     let mut chain = engine.dsp_chain.lock().ok();
     let frame_channels = channels.max(1);
-    for frame in output.chunks_mut(frame_channels) {
-        let mut left = consumer.try_pop().unwrap_or(0.0);
-        let mut right = if frame.len() > 1 {
-            consumer.try_pop().unwrap_or(0.0)
-        } else {
-            left
-        };
-        if let Some(chain) = chain.as_mut() {
-            (left, right) = chain.process_stereo_frame(left, right, preamp_db);
-        }
-        frame[0] = apply_fade_out(engine, left) * volume;
-        if frame.len() > 1 {
-            frame[1] = apply_fade_out(engine, right) * volume;
-        }
-        for out_sample in frame.iter_mut().skip(2) {
-            let sample = consumer.try_pop().unwrap_or(0.0);
-            *out_sample = apply_fade_out(engine, sample) * volume;
-        }
-    }
-    engine.ring_used_bytes.store(
-        (consumer.occupied_len() * std::mem::size_of::<f32>()) as u32,
-        Ordering::Relaxed,
-    );
-    update_vibe_from_f32(engine, output, frame_channels);
-    let frame = engine
-        .current_frame
-        .fetch_add((output.len() / frame_channels) as u32, Ordering::Relaxed)
-        + (output.len() / frame_channels) as u32;
-    trigger_next_track_lookahead(engine, frame);
-}
-
-#[cfg(target_os = "windows")]
-fn write_samples_i16(
-    output: &mut [i16],
-    channels: usize,
-    consumer: &mut impl ringbuf::traits::Consumer<Item = f32>,
-    engine: &AudioEngine,
-) {
-    if engine.is_playing.load(Ordering::SeqCst) != STATE_PLAYING {
-        output.fill(0);
-        return;
+    let frame_width = frame_channels + if stem_slots { STEM_SLOTS } else { 0 };
+    if let Some(chain) = chain.as_mut() {
+        chain.spatial_mut().set_output_channels(frame_channels);
     }
-
-    let volume = f32::from_bits(engine.volume_bits.load(Ordering::Relaxed));
-    let preamp_db = f32::from_bits(engine.preamp_db_bits.load(Ordering::Relaxed));
-    let mut chain = engine.dsp_chain.lock().ok();
-    let frame_channels = channels.max(1);
+    let mut played_frames = 0_u64;
     for frame in output.chunks_mut(frame_channels) {
-        let mut left = consumer.try_pop().unwrap_or(0.0);
-        let mut right = if frame.len() > 1 {
-            consumer.try_pop().unwrap_or(0.0)
+        let queued = consumer.occupied_len() >= frame_width;
+        played_frames += u64::from(queued);
+        let stems = if queued && stem_slots {
+            pop_stem_frame(consumer)
         } else {
-            left
+            None
+        };
+        let mut pop = || {
+            if queued {
+                consumer.try_pop().unwrap_or(0.0)
+            } else {
+                0.0
+            }
         };
+        let mut left = pop();
+        let mut right = if frame.len() > 1 { pop() } else { left };
+        let gain = volume * ab_switch_gain(engine, chain.as_deref());
         if let Some(chain) = chain.as_mut() {
+            chain.spatial_mut().set_stem_frame(stems);
             (left, right) = chain.process_stereo_frame(left, right, preamp_db);
         }
-        let left = apply_fade_out(engine, left) * volume;
-        frame[0] = (left.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        frame[0] = apply_fade_out(engine, left) * gain;
         if frame.len() > 1 {
-            let right = apply_fade_out(engine, right) * volume;
-            frame[1] = (right.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            frame[1] = apply_fade_out(engine, right) * gain;
         }
-        for out_sample in frame.iter_mut().skip(2) {
-            let sample = consumer.try_pop().unwrap_or(0.0);
-            let limited = apply_fade_out(engine, sample) * volume;
-            *out_sample = (limited.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        for (channel, out_sample) in frame.iter_mut().enumerate().skip(2) {
+            let sample = pop();
+            // The spatial room, when rendered to speakers, replaces the track's own
+            // surround channels.
+            let sample = chain
+                .as_deref()
+                .and_then(|chain| chain.spatial_speaker_sample(channel))
+                .unwrap_or(sample);
+            *out_sample = apply_fade_out(engine, sample) * gain;
         }
-    }
-    engine.ring_used_bytes.store(
-        (consumer.occupied_len() * std::mem::size_of::<f32>()) as u32,
-        Ordering::Relaxed,
-    );
-    update_vibe_from_i16(engine, output, frame_channels);
-    let frame = engine
-        .current_frame
-        .fetch_add((output.len() / frame_channels) as u32, Ordering::Relaxed)
-        + (output.len() / frame_channels) as u32;
-    trigger_next_track_lookahead(engine, frame);
-}
-
-#[cfg(target_os = "windows")]
-fn write_samples_u16(
-    output: &mut [u16],
-    channels: usize,
-    consumer: &mut impl ringbuf::traits::Consumer<Item = f32>,
-    engine: &AudioEngine,
-) {
-    if engine.is_playing.load(Ordering::SeqCst) != STATE_PLAYING {
-        output.fill(u16::MAX / 2);
-        return;
-    }
-
-    let volume = f32::from_bits(engine.volume_bits.load(Ordering::Relaxed));
-    let preamp_db = f32::from_bits(engine.preamp_db_bits.load(Ordering::Relaxed));
-    let mut chain = engine.dsp_chain.lock().ok();
-    let frame_channels = channels.max(1);
-    for frame in output.chunks_mut(frame_channels) {
-        let mut left = consumer.try_pop().unwrap_or(0.0);
-        let mut right = if frame.len() > 1 {
-            consumer.try_pop().unwrap_or(0.0)
-        } else {
-            left
-        };
         if let Some(chain) = chain.as_mut() {
-            (left, right) = chain.process_stereo_frame(left, right, preamp_db);
-        }
-        let left = apply_fade_out(engine, left) * volume;
-        frame[0] = (((left.clamp(-1.0, 1.0) + 1.0) * 0.5) * u16::MAX as f32) as u16;
-        if frame.len() > 1 {
-            let right = apply_fade_out(engine, right) * volume;
-            frame[1] = (((right.clamp(-1.0, 1.0) + 1.0) * 0.5) * u16::MAX as f32) as u16;
-        }
-        for out_sample in frame.iter_mut().skip(2) {
-            let sample = consumer.try_pop().unwrap_or(0.0);
-            let limited = apply_fade_out(engine, sample) * volume;
-            *out_sample = (((limited.clamp(-1.0, 1.0) + 1.0) * 0.5) * u16::MAX as f32) as u16;
+            chain.process_output_frame(frame);
         }
     }
     engine.ring_used_bytes.store(
         (consumer.occupied_len() * std::mem::size_of::<f32>()) as u32,
         Ordering::Relaxed,
     );
-    update_vibe_from_u16(engine, output, frame_channels);
-    let frame = engine
+    update_vibe_from_f32(engine, output, frame_channels);
+    let rate = u64::from(engine.output_rate_hz.load(Ordering::Relaxed).max(1));
+    engine
+        .listened_micros
+        .fetch_add(played_frames * 1_000_000 / rate, Ordering::Relaxed);
+    let mut frame = engine
         .current_frame
         .fetch_add((output.len() / frame_channels) as u32, Ordering::Relaxed)
         + (output.len() / frame_channels) as u32;
+    let skip_from = engine.gap_skip_from.load(Ordering::SeqCst);
+    if frame >= skip_from {
+        // The producer already jumped past the gap; catch the reported position up.
+        frame = frame - skip_from + engine.gap_skip_to.load(Ordering::SeqCst);
+        engine.current_frame.store(frame, Ordering::Relaxed);
+        engine.gap_skip_from.store(u32::MAX, Ordering::SeqCst);
+    }
     trigger_next_track_lookahead(engine, frame);
+    let end_frame = engine.playback_end_frame.load(Ordering::Relaxed);
+    if end_frame > 0 && frame >= end_frame {
+        engine.play_end_reached.store(true, Ordering::SeqCst);
+    }
 }
 
-#[cfg(target_os = "windows")]
 fn apply_fade_out(engine: &AudioEngine, sample: f32) -> f32 {
     let remaining = engine.fade_out_remaining_samples.load(Ordering::Relaxed);
     if remaining == 0 {
@@ -1027,54 +2469,46 @@ fn apply_fade_out(engine: &AudioEngine, sample: f32) -> f32 {
     sample * (prev as f32 / total).clamp(0.0, 1.0)
 }
 
-#[cfg(target_os = "windows")]
-fn update_vibe_from_f32(engine: &AudioEngine, output: &[f32], channels: usize) {
-    let mut peak = 0.0_f32;
-    let mut mono = Vec::with_capacity(output.len() / channels.max(1));
-    for frame in output.chunks(channels.max(1)) {
-        let mut sum = 0.0_f32;
-        for sample in frame {
-            peak = peak.max(sample.abs());
-            sum += *sample;
-        }
-        mono.push(sum / frame.len() as f32);
+/// Gain of the dip hiding an A/B switch: down to silence, where the pending snapshot
+/// is applied, and back up.
+fn ab_switch_gain(engine: &AudioEngine, chain: Option<&DspChain>) -> f32 {
+    let remaining = engine.ab_dip_remaining_samples.load(Ordering::Relaxed);
+    if remaining == 0 {
+        return 1.0;
     }
-    update_vibe_state(engine, mono, peak);
-}
-
-#[cfg(target_os = "windows")]
-fn update_vibe_from_i16(engine: &AudioEngine, output: &[i16], channels: usize) {
-    let mut peak = 0.0_f32;
-    let mut mono = Vec::with_capacity(output.len() / channels.max(1));
-    for frame in output.chunks(channels.max(1)) {
-        let mut sum = 0.0_f32;
-        for sample in frame {
-            let normalized = *sample as f32 / i16::MAX as f32;
-            peak = peak.max(normalized.abs());
-            sum += normalized;
+    engine
+        .ab_dip_remaining_samples
+        .fetch_sub(1, Ordering::Relaxed);
+    let half = (engine.ab_dip_total_samples.load(Ordering::Relaxed) / 2).max(1);
+    if remaining == half {
+        let pending = engine
+            .ab_pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.take());
+        if let (Some(snapshot), Some(chain)) = (pending, chain) {
+            if let Err(err) = snapshot.restore(chain) {
+                warn!("Failed to apply A/B snapshot: {err}");
+            }
         }
-        mono.push(sum / frame.len() as f32);
     }
-    update_vibe_state(engine, mono, peak);
+    (remaining.abs_diff(half) as f32 / half as f32).min(1.0)
 }
 
-#[cfg(target_os = "windows")]
-fn update_vibe_from_u16(engine: &AudioEngine, output: &[u16], channels: usize) {
+fn update_vibe_from_f32(engine: &AudioEngine, output: &[f32], channels: usize) {
     let mut peak = 0.0_f32;
     let mut mono = Vec::with_capacity(output.len() / channels.max(1));
     for frame in output.chunks(channels.max(1)) {
         let mut sum = 0.0_f32;
         for sample in frame {
-            let normalized = (*sample as f32 / u16::MAX as f32) * 2.0 - 1.0;
-            peak = peak.max(normalized.abs());
-            sum += normalized;
+            peak = peak.max(sample.abs());
+            sum += *sample;
         }
         mono.push(sum / frame.len() as f32);
     }
     update_vibe_state(engine, mono, peak);
 }
 
-#[cfg(target_os = "windows")]
 fn update_vibe_state(engine: &AudioEngine, mono_samples: Vec<f32>, peak: f32) {
     engine
         .vibe_amplitude_bits
@@ -1091,11 +2525,76 @@ fn update_vibe_state(engine: &AudioEngine, mono_samples: Vec<f32>, peak: f32) {
     }
 }
 
-#[cfg(target_os = "windows")]
+/// Frames of `pcm` the producer should play: everything, or only the audible part when
+/// silence trimming is enabled.
+fn playable_frames(
+    engine: &AudioEngine,
+    pcm: &[f32],
+    channels: usize,
+    sample_rate: u32,
+) -> std::ops::Range<usize> {
+    if engine.trim_silence.load(Ordering::Relaxed) {
+        audible_frame_range(pcm, channels, sample_rate)
+    } else {
+        0..pcm.len() / channels.max(1)
+    }
+}
+
+fn lyric_index_at(lyrics: &[LyricsLine], now_ms: u32) -> Option<usize> {
+    // `Err(next)` means insertion point for `now_ms`, so the active lyric is `next - 1`.
+    match lyrics.binary_search_by(|line| line.timestamp.cmp(&now_ms)) {
+        Ok(found) => Some(found),
+        Err(0) => None,
+        Err(next) => Some(next - 1),
+    }
+}
+
+/// Long silent regions of `pcm` using the current gap-killer settings.
+fn track_gaps(
+    engine: &AudioEngine,
+    pcm: &[f32],
+    channels: usize,
+    sample_rate: u32,
+) -> Vec<std::ops::Range<usize>> {
+    silent_gaps(
+        pcm,
+        channels,
+        sample_rate,
+        f32::from_bits(engine.gap_threshold_db_bits.load(Ordering::Relaxed)),
+        f32::from_bits(engine.gap_min_seconds_bits.load(Ordering::Relaxed)),
+    )
+}
+
+/// Queues the loaded file's play for `take_ended_plays`, once per play. Nothing is
+/// queued for live streams or plays that never produced audio.
+fn end_play(engine: &AudioEngine, reached_end: bool) {
+    if engine.play_ended.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let listened_seconds = engine.listened_micros.swap(0, Ordering::SeqCst) as f64 / 1e6;
+    let Some(path) = engine.loaded_path.lock().ok().and_then(|path| path.clone()) else {
+        return;
+    };
+    if listened_seconds <= 0.0 {
+        return;
+    }
+    let duration_seconds = f32::from_bits(engine.track_duration_bits.load(Ordering::SeqCst));
+    if let Ok(mut plays) = engine.ended_plays.lock() {
+        if plays.len() == MAX_ENDED_PLAYS {
+            plays.pop_front();
+        }
+        plays.push_back(EndedPlay {
+            path,
+            listened_seconds,
+            duration_seconds: f64::from(duration_seconds),
+            reached_end,
+        });
+    }
+}
+
 fn trigger_next_track_lookahead(engine: &AudioEngine, current_frame: u32) {
-    let duration = f32::from_bits(engine.track_duration_bits.load(Ordering::Relaxed));
-    let rate = engine.output_rate_hz.load(Ordering::Relaxed).max(1);
-    if duration <= 0.0 {
+    let end_frame = engine.playback_end_frame.load(Ordering::Relaxed);
+    if end_frame == 0 {
         return;
     }
     if engine.lookahead_completed.load(Ordering::Relaxed) {
@@ -1110,11 +2609,12 @@ fn trigger_next_track_lookahead(engine: &AudioEngine, current_frame: u32) {
     {
         return;
     }
-    let progress = current_frame as f32 / (duration * rate as f32);
-    // Short-circuit keeps swap() from running before 95%. Once >=95%, swap(true) returns the
-    // previous armed flag; if it was already true, we skip to avoid duplicate preload attempts.
-    if progress < 0.95 || engine.lookahead_started.swap(true, Ordering::SeqCst) {
-        return;
+    // Measured against the playable end so trimmed trailing silence still preloads in time.
+    let progress = current_frame as f32 / end_frame as f32;
+    // Arm the producer's preload once playback passes 95%; it clears the flag when done and
+    // `lookahead_completed` (checked above) keeps it from re-arming for the same track.
+    if progress >= 0.95 {
+        engine.lookahead_started.store(true, Ordering::SeqCst);
     }
 }
 
@@ -1124,7 +2624,155 @@ fn lock_err<T>(_: T) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{AudioState, STATE_PAUSED, STATE_PLAYING};
+    use super::{
+        AbSlot, AudioState, DecodedTrack, DspProfile, LyricsLine, Ordering,
+        DEFAULT_GAP_MIN_SECONDS, DEFAULT_GAP_THRESHOLD_DB, NO_ACTIVE_LYRIC, STATE_PAUSED,
+        STATE_PLAYING,
+    };
+    use crate::audio::output::VirtualOutput;
+    use crate::audio::stream::StreamDecoder;
+    use crate::test_support::unique_temp_path;
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    const RATE: u32 = 48_000;
+
+    /// Interleaved stereo IEEE-float WAV file contents.
+    fn wav_bytes(samples: &[f32]) -> Vec<u8> {
+        let data_len = (samples.len() * 4) as u32;
+        let mut bytes = Vec::with_capacity(44 + data_len as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16_u32.to_le_bytes());
+        bytes.extend_from_slice(&3_u16.to_le_bytes());
+        bytes.extend_from_slice(&2_u16.to_le_bytes());
+        bytes.extend_from_slice(&RATE.to_le_bytes());
+        bytes.extend_from_slice(&(RATE * 8).to_le_bytes());
+        bytes.extend_from_slice(&8_u16.to_le_bytes());
+        bytes.extend_from_slice(&32_u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn write_wav(path: &Path, samples: &[f32]) {
+        std::fs::write(path, wav_bytes(samples)).expect("wav should be written");
+    }
+
+    fn test_track(name: &str, frames: usize, value: impl Fn(usize) -> f32) -> PathBuf {
+        let path = unique_temp_path(&format!("engine-{name}.wav"));
+        let samples = (0..frames)
+            .flat_map(|frame| [value(frame), value(frame)])
+            .collect::<Vec<_>>();
+        write_wav(&path, &samples);
+        path
+    }
+
+    /// Pulls roughly in real time so the producer and lookahead keep up.
+    fn pull_frames(output: &VirtualOutput, frames: usize) -> Vec<f32> {
+        let mut out = Vec::with_capacity(frames * 2);
+        while out.len() < frames * 2 {
+            out.extend(output.pull(256));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        out
+    }
+
+    fn start_playback(state: &AudioState, output: &VirtualOutput, path: &Path) {
+        state
+            .load_track_with_output(path, output)
+            .expect("track should load");
+        state.play();
+        // Let the producer fill the ring before the first pull.
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    #[test]
+    fn track_dsp_profiles_restore_the_manual_baseline() {
+        let state = AudioState::new();
+        state.set_tone(2.0, -1.0).expect("tone should apply");
+        state
+            .update_eq_band(0, 31.0, 3.0, 1.0)
+            .expect("eq should apply");
+        let edm = DspProfile {
+            bass_db: Some(8.0),
+            expansion: Some(0.6),
+            eq_gains_db: Some(vec![0.0]),
+            ..DspProfile::default()
+        };
+        let classical = DspProfile {
+            reverb_preset: Some("off".to_string()),
+            ..DspProfile::default()
+        };
+        let read = |state: &AudioState| {
+            let chain = state.inner.dsp_chain.lock().expect("dsp lock");
+            (
+                chain.tone().bass(),
+                chain.tone().treble(),
+                chain.expansion().amount(),
+                chain.user_eq_bands()[0].1,
+            )
+        };
+
+        state
+            .apply_track_dsp_profile(Some(&edm))
+            .expect("profile should apply");
+        assert_eq!(read(&state), (8.0, -1.0, 0.6, 0.0));
+
+        // Fields a profile leaves unset fall back to the baseline, not the previous profile.
+        state
+            .apply_track_dsp_profile(Some(&classical))
+            .expect("profile should apply");
+        assert_eq!(read(&state), (2.0, -1.0, 0.0, 3.0));
+
+        state
+            .apply_track_dsp_profile(None)
+            .expect("baseline should restore");
+        assert_eq!(read(&state), (2.0, -1.0, 0.0, 3.0));
+        assert!(state
+            .inner
+            .dsp_profile_baseline
+            .lock()
+            .expect("baseline lock")
+            .is_none());
+    }
+
+    #[test]
+    fn instant_replay_seeks_back_and_finds_lyric() {
+        let state = AudioState::new();
+        *state.inner.lyrics.lock().expect("lyrics lock") = vec![
+            LyricsLine {
+                timestamp: 1_000,
+                text: "first".to_string(),
+            },
+            LyricsLine {
+                timestamp: 4_000,
+                text: "second".to_string(),
+            },
+        ];
+        state.inner.current_frame.store(RATE * 5, Ordering::SeqCst);
+        state.inner.active_lyric_index.store(1, Ordering::SeqCst);
+
+        let position = state.instant_replay(2.5);
+        assert!((position - 2.5).abs() < 1e-9);
+        assert_eq!(state.inner.seek_frame.load(Ordering::SeqCst), RATE * 5 / 2);
+        assert_eq!(
+            state.inner.active_lyric_index.load(Ordering::SeqCst),
+            NO_ACTIVE_LYRIC
+        );
+        assert_eq!(
+            state.lyric_line_at(position).map(|line| line.text),
+            Some("first".to_string())
+        );
+
+        assert_eq!(state.instant_replay(10.0), 0.0);
+        assert_eq!(state.lyric_line_at(0.0), None);
+    }
 
     #[test]
     fn volume_is_clamped() {
@@ -1162,4 +2810,337 @@ mod tests {
         state.set_next_track(None::<&str>);
         assert!(!state.has_next_track());
     }
+
+    #[test]
+    fn virtual_output_plays_and_pauses() {
+        let path = test_track("play", RATE as usize, |_| 0.25);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        assert_eq!(state.output_device(), None);
+        start_playback(&state, &output, &path);
+
+        let audio = pull_frames(&output, 1024);
+        assert!(audio.iter().all(|sample| (sample - 0.25).abs() < 1e-3));
+        assert!(state.inner.current_frame.load(Ordering::SeqCst) >= 1024);
+        assert_eq!(state.get_audio_stats().device, "Virtual output");
+        assert_eq!(state.output_device().as_deref(), Some("Virtual output"));
+
+        state.pause();
+        let frame = state.inner.current_frame.load(Ordering::SeqCst);
+        assert!(output.pull(256).iter().all(|sample| *sample == 0.0));
+        assert_eq!(state.inner.current_frame.load(Ordering::SeqCst), frame);
+
+        drop(state);
+        assert!(!output.is_running());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn live_streams_decode_on_the_fly_and_cannot_seek() {
+        let samples = vec![0.25_f32; RATE as usize * 2];
+        let decoder = StreamDecoder::new(Cursor::new(wav_bytes(&samples)), Some("audio/x-wav"))
+            .expect("stream should probe");
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        state
+            .load_stream_with_output(decoder, &output)
+            .expect("stream should load");
+        state.play();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let audio = pull_frames(&output, 2048);
+        assert!(audio.iter().all(|sample| (sample - 0.25).abs() < 1e-3));
+        assert_eq!(state.get_track_duration_seconds(), 0.0);
+        assert_eq!(state.loaded_path(), None);
+
+        let position = state.position_seconds();
+        state.seek(30.0);
+        assert_eq!(state.position_seconds(), position);
+    }
+
+    #[test]
+    fn seek_discards_buffered_audio() {
+        // The ramp encodes the playback position in the sample value.
+        let frames = RATE as usize;
+        let path = test_track("seek", frames, |frame| 0.5 * frame as f32 / frames as f32);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        start_playback(&state, &output, &path);
+
+        let before = pull_frames(&output, 256);
+        assert!(before[0] < 0.01);
+
+        state.seek(0.75);
+        std::thread::sleep(Duration::from_millis(20));
+        // The first pull flushes the stale queue; audio after it comes from the new position.
+        let _ = output.pull(256);
+        std::thread::sleep(Duration::from_millis(20));
+        let after = output.pull(16);
+        assert!(
+            (after[0] - 0.375).abs() < 0.01,
+            "expected audio from 0.75 s, got {}",
+            after[0]
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn next_track_follows_without_gap() {
+        let first = test_track("gapless-a", RATE as usize, |_| 0.25);
+        let second = test_track("gapless-b", RATE as usize / 2, |_| -0.25);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        start_playback(&state, &output, &first);
+        state.set_next_track(Some(&second));
+
+        let audio = pull_frames(&output, RATE as usize + RATE as usize / 4);
+        let switch = audio
+            .iter()
+            .position(|sample| *sample < 0.0)
+            .expect("second track should start");
+        assert!(audio[..switch]
+            .iter()
+            .all(|sample| (sample - 0.25).abs() < 1e-3));
+        assert!(audio[switch..switch + 2_048]
+            .iter()
+            .all(|sample| (sample + 0.25).abs() < 1e-3));
+        assert!(!state.has_next_track());
+        assert_eq!(state.loaded_path(), Some(second.clone()));
+
+        let _ = std::fs::remove_file(first);
+        let _ = std::fs::remove_file(second);
+    }
+
+    #[test]
+    fn ended_plays_report_how_much_was_heard() {
+        let first = test_track("history-a", RATE as usize, |_| 0.25);
+        let second = test_track("history-b", RATE as usize / 2, |_| -0.25);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        start_playback(&state, &output, &first);
+        state.set_next_track(Some(&second));
+
+        let _ = pull_frames(&output, RATE as usize * 7 / 4);
+        let plays = state.take_ended_plays();
+        assert_eq!(plays.len(), 2);
+        for (play, path, seconds) in [(&plays[0], &first, 1.0), (&plays[1], &second, 0.5)] {
+            assert_eq!(&play.path, path);
+            assert!(play.reached_end);
+            assert!((play.duration_seconds - seconds).abs() < 1e-3);
+            assert!(
+                (play.listened_seconds - seconds).abs() < 0.1,
+                "heard {} s of {seconds} s",
+                play.listened_seconds
+            );
+        }
+
+        // Replacing a track part-way ends its play unfinished.
+        start_playback(&state, &output, &first);
+        let _ = pull_frames(&output, RATE as usize / 4);
+        start_playback(&state, &output, &second);
+        let plays = state.take_ended_plays();
+        assert_eq!(plays.len(), 1);
+        assert!(!plays[0].reached_end);
+        assert!((plays[0].listened_seconds - 0.25).abs() < 0.1);
+        assert!(state.take_ended_plays().is_empty());
+
+        let _ = std::fs::remove_file(first);
+        let _ = std::fs::remove_file(second);
+    }
+
+    #[test]
+    fn dsp_chain_is_applied_to_output() {
+        let path = test_track("dsp", RATE as usize, |_| 0.25);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        state.set_balance(1.0).expect("balance should apply");
+        start_playback(&state, &output, &path);
+
+        let audio = pull_frames(&output, 512);
+        for frame in audio.chunks(2) {
+            assert!(frame[0].abs() < 1e-3, "left should be muted");
+            assert!((frame[1] - 0.25 * std::f32::consts::SQRT_2).abs() < 1e-3);
+        }
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stems_replace_the_mix_in_the_spatial_room() {
+        let path = test_track("stems", RATE as usize, |_| 0.25);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        state
+            .set_spatial_enabled(true)
+            .expect("spatial should enable");
+        start_playback(&state, &output, &path);
+
+        // Silent stems: once the queued mix and the reflections have played out,
+        // nothing is left.
+        let silent = || DecodedTrack {
+            sample_rate: RATE,
+            channels: 1,
+            samples: vec![0.0; RATE as usize],
+        };
+        state
+            .set_stem_sources(Some([
+                Some(silent()),
+                Some(silent()),
+                Some(silent()),
+                Some(silent()),
+                None,
+                None,
+            ]))
+            .expect("stems should convert");
+        assert!(state.has_stem_sources());
+        let audio = pull_frames(&output, 16_384);
+        let tail = &audio[audio.len() - 2_048..];
+        assert!(tail.iter().all(|sample| sample.abs() < 1e-4));
+
+        state.set_stem_sources(None).expect("stems should clear");
+        let audio = pull_frames(&output, 8_192);
+        let tail = &audio[audio.len() - 2_048..];
+        assert!(tail.iter().any(|sample| sample.abs() > 0.01));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn fade_out_ramps_down_and_does_not_leak_into_next_track() {
+        let first = test_track("fade-a", RATE as usize, |_| 0.25);
+        let second = test_track("fade-b", RATE as usize, |_| 0.5);
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        start_playback(&state, &output, &first);
+
+        state
+            .inner
+            .fade_out_total_samples
+            .store(128, Ordering::SeqCst);
+        state
+            .inner
+            .fade_out_remaining_samples
+            .store(128, Ordering::SeqCst);
+        let faded = output.pull(64);
+        assert!(faded.windows(2).all(|pair| pair[1] <= pair[0] + 1e-6));
+        assert!(faded[faded.len() - 1] < 0.01);
+
+        // Nobody pulls while the new track loads, so the fade is still pending here.
+        state
+            .inner
+            .fade_out_remaining_samples
+            .store(128, Ordering::SeqCst);
+        start_playback(&state, &output, &second);
+        let audio = output.pull(64);
+        assert!(audio.iter().all(|sample| (sample - 0.5).abs() < 1e-3));
+
+        let _ = std::fs::remove_file(first);
+        let _ = std::fs::remove_file(second);
+    }
+
+    #[test]
+    fn ab_snapshots_switch_through_a_short_dip() {
+        let state = AudioState::new();
+        let bass = |state: &AudioState| state.inner.dsp_chain.lock().unwrap().tone().bass();
+        state.capture_ab_snapshot(AbSlot::A).expect("capture A");
+        state.set_tone(6.0, 0.0).expect("tone should apply");
+        state.capture_ab_snapshot(AbSlot::B).expect("capture B");
+
+        // Without playback the switch applies at once.
+        state.switch_ab_snapshot(AbSlot::A).expect("switch to A");
+        assert_eq!(bass(&state), 0.0);
+        assert_eq!(state.ab_status().unwrap(), (true, true, Some(AbSlot::A)));
+
+        let path = test_track("ab", RATE as usize, |_| 0.25);
+        let output = VirtualOutput::new(RATE, 2);
+        start_playback(&state, &output, &path);
+        state.switch_ab_snapshot(AbSlot::B).expect("switch to B");
+        let audio = pull_frames(&output, RATE as usize / 10);
+        let lowest = audio[..2 * 1_440]
+            .iter()
+            .fold(f32::MAX, |low, sample| low.min(sample.abs()));
+        assert!(lowest < 0.01, "the switch should dip, lowest {lowest}");
+        assert_eq!(bass(&state), 6.0);
+        // +6 dB of bass doubles a DC signal.
+        let last = audio[audio.len() - 1];
+        assert!((last - 0.5).abs() < 0.02, "B should be audible: {last}");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn silence_trimming_skips_dead_air() {
+        let frames = RATE as usize;
+        // 0.5 s of silence, then a constant signal, then 0.25 s of silence.
+        let path = test_track("trim", frames, |frame| {
+            if (frames / 2..frames * 3 / 4).contains(&frame) {
+                0.25
+            } else {
+                0.0
+            }
+        });
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        state.set_silence_trimming(true);
+        state
+            .load_track_with_output(&path, &output)
+            .expect("track should load");
+
+        // Playback starts just before the signal (20 ms padding) instead of at zero.
+        let start = state.inner.current_frame.load(Ordering::SeqCst) as usize;
+        assert_eq!(start, frames / 2 - RATE as usize / 50);
+        let end = state.inner.playback_end_frame.load(Ordering::SeqCst) as usize;
+        assert_eq!(end, frames * 3 / 4 + RATE as usize / 50);
+
+        state.play();
+        std::thread::sleep(Duration::from_millis(50));
+        let audio = pull_frames(&output, 1_024);
+        assert!(audio[..2 * 960].iter().all(|sample| *sample == 0.0));
+        assert!(audio[2 * 960..]
+            .iter()
+            .all(|sample| (sample - 0.25).abs() < 1e-3));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn gap_skipping_keeps_current_settings_when_omitted() {
+        let state = AudioState::new();
+        let settings = |state: &AudioState| {
+            (
+                f32::from_bits(state.inner.gap_threshold_db_bits.load(Ordering::SeqCst)),
+                f32::from_bits(state.inner.gap_min_seconds_bits.load(Ordering::SeqCst)),
+            )
+        };
+        state.set_gap_skipping(true, None, None);
+        assert_eq!(
+            settings(&state),
+            (DEFAULT_GAP_THRESHOLD_DB, DEFAULT_GAP_MIN_SECONDS)
+        );
+        state.set_gap_skipping(true, Some(-60.0), Some(10.0));
+        state.set_gap_skipping(false, None, None);
+        assert_eq!(settings(&state), (-60.0, 10.0));
+        assert!(!state.inner.skip_gaps.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn gap_killer_fast_forwards_through_long_silence() {
+        let frames = RATE as usize * 7 / 4;
+        // 0.25 s of signal, 1.25 s of dead air, then a louder 0.25 s "hidden track".
+        let path = test_track("gap", frames, |frame| match frame * 4 / RATE as usize {
+            0 => 0.25,
+            6 => 0.5,
+            _ => 0.0,
+        });
+        let output = VirtualOutput::new(RATE, 2);
+        let state = AudioState::new();
+        state.set_gap_skipping(true, Some(-60.0), Some(0.5));
+        start_playback(&state, &output, &path);
+
+        // 0.25 s of audio plus 20 ms of padding on each side of the gap, then the hidden track.
+        let audio = pull_frames(&output, RATE as usize / 4 + 4_096);
+        assert!(audio.iter().any(|sample| (sample - 0.5).abs() < 1e-3));
+        let position = state.inner.current_frame.load(Ordering::SeqCst) as usize;
+        assert!(
+            position > RATE as usize * 3 / 2,
+            "position should follow the skip, got {position}"
+        );
+        let _ = std::fs::remove_file(path);
+    }
 }
@@ -0,0 +1,179 @@
+//! Receives head orientation from phone or IMU trackers over OSC (UDP), so the spatial
+//! scene can follow the listener's head.
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_OSC_PORT: u16 = 9000;
+/// How long a blocked receive waits before checking whether the tracker was stopped.
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Head orientation in degrees, in the spatial node's convention: yaw positive turning
+/// left, pitch positive looking up, roll positive tilting the right ear down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeadOrientation {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+struct Listening {
+    stop: Arc<AtomicBool>,
+}
+
+/// The OSC listener, if one is running.
+#[derive(Default)]
+pub struct HeadTracker {
+    listening: Mutex<Option<Listening>>,
+}
+
+impl HeadTracker {
+    /// Listens on UDP `port` (0 picks a free one) for OSC messages whose address ends in
+    /// `/ypr`, carrying yaw, pitch and roll in degrees, and reports each to
+    /// `on_orientation`. Replaces any listener already running; returns the bound port.
+    pub fn start(
+        &self,
+        port: u16,
+        on_orientation: impl Fn(HeadOrientation) + Send + 'static,
+    ) -> Result<u16, String> {
+        self.stop();
+        let socket = UdpSocket::bind(("0.0.0.0", port))
+            .map_err(|e| format!("Failed to listen for head tracking on UDP {port}: {e}"))?;
+        socket
+            .set_read_timeout(Some(RECEIVE_TIMEOUT))
+            .map_err(|e| format!("Failed to configure the head tracking socket: {e}"))?;
+        let port = socket
+            .local_addr()
+            .map_err(|e| format!("Failed to read the head tracking port: {e}"))?
+            .port();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || {
+            let mut packet = [0_u8; 1536];
+            while !stopped.load(Ordering::Relaxed) {
+                let Ok(length) = socket.recv(&mut packet) else {
+                    continue;
+                };
+                if let Some(orientation) = parse_packet(&packet[..length]) {
+                    on_orientation(orientation);
+                }
+            }
+        });
+        *self.listening.lock().map_err(|_| lock_err())? = Some(Listening { stop });
+        Ok(port)
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut listening) = self.listening.lock() {
+            if let Some(listening) = listening.take() {
+                listening.stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+fn lock_err() -> String {
+    "Head tracker lock poisoned".to_string()
+}
+
+/// The last orientation in an OSC message or bundle.
+fn parse_packet(packet: &[u8]) -> Option<HeadOrientation> {
+    if let Some(mut rest) = packet.strip_prefix(b"#bundle\0") {
+        // Skip the time tag, then walk the size-prefixed elements.
+        rest = rest.get(8..)?;
+        let mut latest = None;
+        while rest.len() >= 4 {
+            let size = u32::from_be_bytes(rest[..4].try_into().ok()?) as usize;
+            let element = rest.get(4..4 + size)?;
+            latest = parse_packet(element).or(latest);
+            rest = &rest[4 + size..];
+        }
+        return latest;
+    }
+
+    let (address, rest) = osc_string(packet)?;
+    if !address.ends_with("/ypr") {
+        return None;
+    }
+    let (tags, mut args) = osc_string(rest)?;
+    let mut values = Vec::with_capacity(3);
+    for tag in tags.strip_prefix(',')?.chars() {
+        let (value, size) = match tag {
+            'f' => (f32::from_be_bytes(args.get(..4)?.try_into().ok()?), 4),
+            'd' => (
+                f64::from_be_bytes(args.get(..8)?.try_into().ok()?) as f32,
+                8,
+            ),
+            'i' => (
+                i32::from_be_bytes(args.get(..4)?.try_into().ok()?) as f32,
+                4,
+            ),
+            _ => return None,
+        };
+        values.push(value);
+        args = &args[size..];
+    }
+    match values[..] {
+        [yaw, pitch, roll] => Some(HeadOrientation { yaw, pitch, roll }),
+        _ => None,
+    }
+}
+
+/// A null-terminated OSC string padded to 4 bytes, and what follows it.
+fn osc_string(data: &[u8]) -> Option<(&str, &[u8])> {
+    let end = data.iter().position(|byte| *byte == 0)?;
+    let text = std::str::from_utf8(&data[..end]).ok()?;
+    let padded = (end + 4) & !3;
+    Some((text, data.get(padded..)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_packet, HeadOrientation};
+
+    fn message(address: &str, values: &[f32]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        for text in [
+            address.to_string(),
+            format!(",{}", "f".repeat(values.len())),
+        ] {
+            packet.extend(text.as_bytes());
+            packet.resize((packet.len() + 4) & !3, 0);
+        }
+        for value in values {
+            packet.extend(value.to_be_bytes());
+        }
+        packet
+    }
+
+    #[test]
+    fn reads_yaw_pitch_roll_messages_and_bundles() {
+        let orientation = HeadOrientation {
+            yaw: 30.0,
+            pitch: -5.5,
+            roll: 2.0,
+        };
+        assert_eq!(
+            parse_packet(&message("/SceneRotator/ypr", &[30.0, -5.5, 2.0])),
+            Some(orientation)
+        );
+        assert_eq!(parse_packet(&message("/ypr", &[30.0, -5.5])), None);
+        assert_eq!(parse_packet(&message("/volume", &[1.0, 2.0, 3.0])), None);
+
+        // A bundle reports its last orientation.
+        let mut bundle = b"#bundle\0".to_vec();
+        bundle.extend([0_u8; 8]);
+        for element in [
+            message("/ypr", &[1.0, 2.0, 3.0]),
+            message("/ypr", &[30.0, -5.5, 2.0]),
+            message("/other", &[0.0]),
+        ] {
+            bundle.extend((element.len() as u32).to_be_bytes());
+            bundle.extend(element);
+        }
+        assert_eq!(parse_packet(&bundle), Some(orientation));
+    }
+}
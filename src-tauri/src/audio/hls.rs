@@ -0,0 +1,539 @@
+use std::io::{self, Cursor, Read};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use reqwest::blocking::Client;
+use reqwest::Url;
+
+use super::stream::StreamInfo;
+use crate::library::network_usage::{self, NetworkProvider};
+
+/// Segments fetched ahead of the one being decoded.
+const PREFETCH_SEGMENTS: usize = 2;
+/// Live playback starts this many segments before the end of the playlist, as the HLS
+/// spec recommends, so there is room to absorb a slow segment.
+const LIVE_EDGE_SEGMENTS: usize = 3;
+/// Consecutive playlist reload failures after which a live stream is given up.
+const MAX_RELOAD_FAILURES: u32 = 5;
+const TS_PACKET_SIZE: usize = 188;
+
+pub fn is_hls_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.to_ascii_lowercase().ends_with(".m3u8")
+}
+
+pub fn is_hls_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    [
+        "application/vnd.apple.mpegurl",
+        "application/x-mpegurl",
+        "audio/mpegurl",
+        "audio/x-mpegurl",
+    ]
+    .iter()
+    .any(|hls| mime.eq_ignore_ascii_case(hls))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Variant {
+    uri: String,
+    bandwidth: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct MediaPlaylist {
+    target_duration: f64,
+    media_sequence: u64,
+    segments: Vec<String>,
+    ended: bool,
+    encrypted: bool,
+    fragmented_mp4: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Playlist {
+    Master {
+        variants: Vec<Variant>,
+        /// `#EXT-X-MEDIA:TYPE=AUDIO` renditions with a URI, and whether each is the default.
+        audio_renditions: Vec<(String, bool)>,
+    },
+    Media(MediaPlaylist),
+}
+
+fn parse_playlist(text: &str) -> Result<Playlist, String> {
+    let mut lines = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    if lines.next() != Some("#EXTM3U") {
+        return Err("Not an HLS playlist (missing #EXTM3U)".to_string());
+    }
+
+    let mut variants = Vec::new();
+    let mut audio_renditions = Vec::new();
+    let mut media = MediaPlaylist::default();
+    let mut pending_bandwidth: Option<Option<u64>> = None;
+    for line in lines {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            pending_bandwidth =
+                Some(attribute(attributes, "BANDWIDTH").and_then(|value| value.parse().ok()));
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MEDIA:") {
+            if attribute(attributes, "TYPE").as_deref() == Some("AUDIO") {
+                if let Some(uri) = attribute(attributes, "URI") {
+                    let default = attribute(attributes, "DEFAULT").as_deref() == Some("YES");
+                    audio_renditions.push((uri, default));
+                }
+            }
+        } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            media.target_duration = value.trim().parse().unwrap_or(0.0);
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            media.media_sequence = value.trim().parse().unwrap_or(0);
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            media.encrypted |= attribute(attributes, "METHOD").as_deref() != Some("NONE");
+        } else if line.starts_with("#EXT-X-MAP:") {
+            media.fragmented_mp4 = true;
+        } else if line == "#EXT-X-ENDLIST" {
+            media.ended = true;
+        } else if !line.starts_with('#') {
+            match pending_bandwidth.take() {
+                Some(bandwidth) => variants.push(Variant {
+                    uri: line.to_string(),
+                    bandwidth,
+                }),
+                None => media.segments.push(line.to_string()),
+            }
+        }
+    }
+
+    if !variants.is_empty() || !audio_renditions.is_empty() {
+        return Ok(Playlist::Master {
+            variants,
+            audio_renditions,
+        });
+    }
+    Ok(Playlist::Media(media))
+}
+
+/// Value of `name` in an attribute list such as `BANDWIDTH=128000,CODECS="mp4a.40.2"`.
+fn attribute(list: &str, name: &str) -> Option<String> {
+    let mut rest = list;
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=')?;
+        let (value, remainder) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, after) = quoted.split_once('"')?;
+                (value, after.trim_start_matches(','))
+            }
+            None => after_key.split_once(',').unwrap_or((after_key, "")),
+        };
+        if key.trim() == name {
+            return Some(value.to_string());
+        }
+        rest = remainder;
+    }
+    None
+}
+
+/// Audio rendition (default first) or, failing that, the highest-bandwidth variant.
+fn choose_rendition(variants: &[Variant], audio_renditions: &[(String, bool)]) -> Option<Variant> {
+    if let Some((uri, _)) = audio_renditions
+        .iter()
+        .find(|(_, default)| *default)
+        .or_else(|| audio_renditions.first())
+    {
+        return Some(Variant {
+            uri: uri.clone(),
+            bandwidth: None,
+        });
+    }
+    variants
+        .iter()
+        .max_by_key(|variant| variant.bandwidth.unwrap_or(0))
+        .cloned()
+}
+
+/// Elementary audio stream of one segment (MPEG-TS or packed ADTS/MP3) and the MIME
+/// type to hint the decoder with.
+fn demux_segment(data: &[u8]) -> Result<(Vec<u8>, &'static str), String> {
+    if data.first() == Some(&0x47) && data.len() >= TS_PACKET_SIZE {
+        return demux_transport_stream(data);
+    }
+    // Packed audio segments start with an ID3 tag carrying the segment timestamp.
+    let audio = skip_id3_tag(data);
+    match audio {
+        [0xFF, second, ..] if second & 0xF6 == 0xF0 => Ok((audio.to_vec(), "audio/aac")),
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => Ok((audio.to_vec(), "audio/mpeg")),
+        _ => Err("Unsupported HLS segment format".to_string()),
+    }
+}
+
+fn skip_id3_tag(data: &[u8]) -> &[u8] {
+    let mut data = data;
+    while data.len() >= 10 && data.starts_with(b"ID3") {
+        // Tag size is a 28-bit "syncsafe" integer (7 bits per byte) after the 10-byte header.
+        let size = data[6..10]
+            .iter()
+            .fold(0_usize, |size, byte| (size << 7) | (*byte & 0x7F) as usize);
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        data = data.get(10 + size + footer..).unwrap_or_default();
+    }
+    data
+}
+
+/// Pulls the first AAC (ADTS) or MPEG audio stream out of an MPEG transport stream.
+fn demux_transport_stream(data: &[u8]) -> Result<(Vec<u8>, &'static str), String> {
+    let mut pmt_pid = None;
+    let mut audio: Option<(u16, &'static str)> = None;
+    let mut elementary = Vec::new();
+    for packet in data.chunks_exact(TS_PACKET_SIZE) {
+        if packet[0] != 0x47 {
+            return Err("Lost MPEG-TS packet sync".to_string());
+        }
+        let unit_start = packet[1] & 0x40 != 0;
+        let pid = u16::from(packet[1] & 0x1F) << 8 | u16::from(packet[2]);
+        let adaptation = (packet[3] >> 4) & 0x3;
+        if adaptation & 0x1 == 0 {
+            continue;
+        }
+        let offset = if adaptation & 0x2 != 0 {
+            5 + packet[4] as usize
+        } else {
+            4
+        };
+        let Some(payload) = packet.get(offset..).filter(|payload| !payload.is_empty()) else {
+            continue;
+        };
+
+        if pid == 0 && unit_start {
+            pmt_pid = psi_section(payload).and_then(parse_pat);
+        } else if Some(pid) == pmt_pid && unit_start && audio.is_none() {
+            audio = psi_section(payload).and_then(parse_pmt);
+        } else if audio.is_some_and(|(audio_pid, _)| audio_pid == pid) {
+            if unit_start {
+                elementary.extend_from_slice(pes_payload(payload)?);
+            } else {
+                elementary.extend_from_slice(payload);
+            }
+        }
+    }
+    let (_, content_type) =
+        audio.ok_or_else(|| "No AAC or MP3 audio in MPEG-TS segment".to_string())?;
+    Ok((elementary, content_type))
+}
+
+/// PSI table bytes after the pointer field, up to (not including) the CRC.
+fn psi_section(payload: &[u8]) -> Option<&[u8]> {
+    let table = payload.get(1 + *payload.first()? as usize..)?;
+    let section_length = (usize::from(*table.get(1)? & 0x0F) << 8) | usize::from(*table.get(2)?);
+    table.get(..(3 + section_length).checked_sub(4)?)
+}
+
+fn parse_pat(section: &[u8]) -> Option<u16> {
+    section
+        .get(8..)?
+        .chunks_exact(4)
+        .find(|program| program[0] != 0 || program[1] != 0)
+        .map(|program| u16::from(program[2] & 0x1F) << 8 | u16::from(program[3]))
+}
+
+fn parse_pmt(section: &[u8]) -> Option<(u16, &'static str)> {
+    let program_info_length =
+        (usize::from(*section.get(10)? & 0x0F) << 8) | usize::from(*section.get(11)?);
+    let mut streams = section.get(12 + program_info_length..)?;
+    while streams.len() >= 5 {
+        let stream_type = streams[0];
+        let pid = u16::from(streams[1] & 0x1F) << 8 | u16::from(streams[2]);
+        let info_length = (usize::from(streams[3] & 0x0F) << 8) | usize::from(streams[4]);
+        match stream_type {
+            0x0F => return Some((pid, "audio/aac")),
+            0x03 | 0x04 => return Some((pid, "audio/mpeg")),
+            _ => {}
+        }
+        streams = streams.get(5 + info_length..)?;
+    }
+    None
+}
+
+fn pes_payload(payload: &[u8]) -> Result<&[u8], String> {
+    if !payload.starts_with(&[0, 0, 1]) || payload.len() < 9 {
+        return Err("Invalid PES header in MPEG-TS segment".to_string());
+    }
+    payload
+        .get(9 + payload[8] as usize..)
+        .ok_or_else(|| "Truncated PES header in MPEG-TS segment".to_string())
+}
+
+/// Byte stream of an HLS playlist's audio: segments are fetched and demuxed on a
+/// background thread and read here back to back, so the decoder sees one continuous
+/// ADTS or MP3 stream.
+pub struct HlsReader {
+    current: Cursor<Vec<u8>>,
+    // `Receiver` is not `Sync`; the lock is never contended since reads take `&mut self`.
+    segments: Mutex<Receiver<Vec<u8>>>,
+}
+
+impl Read for HlsReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let segments = self
+                .segments
+                .get_mut()
+                .map_err(|_| io::Error::other("HLS segment queue poisoned"))?;
+            match segments.recv() {
+                Ok(segment) => self.current = Cursor::new(segment),
+                // The fetcher finished (end of a VOD playlist) or gave up.
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Opens the HLS playlist already downloaded from `url` and starts fetching segments.
+/// Encrypted and fragmented-MP4 (`#EXT-X-MAP`) playlists are not supported.
+pub fn open_hls(
+    client: Client,
+    url: Url,
+    playlist: &str,
+) -> Result<(HlsReader, StreamInfo), String> {
+    let (url, media, bandwidth) = match parse_playlist(playlist)? {
+        Playlist::Media(media) => (url, media, None),
+        Playlist::Master {
+            variants,
+            audio_renditions,
+        } => {
+            let rendition = choose_rendition(&variants, &audio_renditions)
+                .ok_or_else(|| "HLS master playlist has no playable variant".to_string())?;
+            let media_url = url
+                .join(&rendition.uri)
+                .map_err(|e| format!("Invalid HLS variant URI {}: {e}", rendition.uri))?;
+            match parse_playlist(&fetch_text(&client, &media_url)?)? {
+                Playlist::Media(media) => (media_url, media, rendition.bandwidth),
+                Playlist::Master { .. } => {
+                    return Err("Nested HLS master playlists are not supported".to_string())
+                }
+            }
+        }
+    };
+    if media.encrypted {
+        return Err("Encrypted HLS streams are not supported".to_string());
+    }
+    if media.fragmented_mp4 {
+        return Err("Fragmented MP4 HLS streams are not supported".to_string());
+    }
+    let start = if media.ended {
+        0
+    } else {
+        media.segments.len().saturating_sub(LIVE_EDGE_SEGMENTS)
+    };
+    let first_uri = media
+        .segments
+        .get(start)
+        .ok_or_else(|| "HLS playlist has no segments".to_string())?;
+    let (first_segment, content_type) = demux_segment(&fetch_segment(&client, &url, first_uri)?)?;
+
+    let (sender, receiver) = sync_channel(PREFETCH_SEGMENTS);
+    let next_sequence = media.media_sequence + start as u64 + 1;
+    thread::spawn(move || fetch_segments(client, url, media, next_sequence, sender));
+
+    let info = StreamInfo {
+        name: None,
+        genre: None,
+        content_type: Some(content_type.to_string()),
+        bitrate_kbps: bandwidth.map(|bits| (bits / 1000) as u32),
+    };
+    let reader = HlsReader {
+        current: Cursor::new(first_segment),
+        segments: Mutex::new(receiver),
+    };
+    Ok((reader, info))
+}
+
+/// Fetcher thread: sends every segment from `next_sequence` on, reloading live
+/// playlists until they end. Returns once the reader is dropped.
+fn fetch_segments(
+    client: Client,
+    url: Url,
+    mut playlist: MediaPlaylist,
+    mut next_sequence: u64,
+    sender: SyncSender<Vec<u8>>,
+) {
+    let mut reload_failures = 0;
+    loop {
+        // Segments that already left a live window are lost; continue at the oldest one.
+        next_sequence = next_sequence.max(playlist.media_sequence);
+        let skip = (next_sequence - playlist.media_sequence) as usize;
+        for uri in playlist.segments.iter().skip(skip) {
+            next_sequence += 1;
+            let segment = fetch_segment(&client, &url, uri)
+                .and_then(|bytes| demux_segment(&bytes).map(|(audio, _)| audio));
+            match segment {
+                Ok(audio) => {
+                    if sender.send(audio).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => warn!("Skipping HLS segment {uri}: {err}"),
+            }
+        }
+        if playlist.ended {
+            return;
+        }
+
+        // The spec allows reloading after half a target duration when nothing changed.
+        thread::sleep(Duration::from_secs_f64(
+            (playlist.target_duration / 2.0).max(1.0),
+        ));
+        match fetch_text(&client, &url).and_then(|text| parse_playlist(&text)) {
+            Ok(Playlist::Media(reloaded)) => {
+                playlist = reloaded;
+                reload_failures = 0;
+            }
+            Ok(Playlist::Master { .. }) => {
+                warn!("HLS media playlist turned into a master playlist");
+                return;
+            }
+            Err(err) => {
+                reload_failures += 1;
+                warn!("Failed to reload HLS playlist: {err}");
+                if reload_failures >= MAX_RELOAD_FAILURES {
+                    return;
+                }
+                playlist.segments.clear();
+            }
+        }
+    }
+}
+
+fn fetch_text(client: &Client, url: &Url) -> Result<String, String> {
+    let bytes = fetch_bytes(client, url)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn fetch_segment(client: &Client, playlist_url: &Url, uri: &str) -> Result<Vec<u8>, String> {
+    let url = playlist_url
+        .join(uri)
+        .map_err(|e| format!("Invalid HLS segment URI {uri}: {e}"))?;
+    fetch_bytes(client, &url)
+}
+
+fn fetch_bytes(client: &Client, url: &Url) -> Result<Vec<u8>, String> {
+    let body = client
+        .get(url.clone())
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+    network_usage::record(NetworkProvider::Streams, body.len() as u64);
+    Ok(body.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        choose_rendition, demux_segment, is_hls_url, parse_playlist, Playlist, Variant,
+        TS_PACKET_SIZE,
+    };
+
+    #[test]
+    fn parses_master_and_media_playlists() {
+        let master = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.5\"
+low/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=192000,CODECS=\"mp4a.40.2\"
+high/index.m3u8
+";
+        let Playlist::Master {
+            variants,
+            audio_renditions,
+        } = parse_playlist(master).expect("master should parse")
+        else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(
+            choose_rendition(&variants, &audio_renditions),
+            Some(Variant {
+                uri: "high/index.m3u8".to_string(),
+                bandwidth: Some(192_000),
+            })
+        );
+
+        let media = "#EXTM3U
+#EXT-X-TARGETDURATION:6
+#EXT-X-MEDIA-SEQUENCE:1042
+#EXTINF:6.0,
+seg1042.ts
+#EXTINF:6.0,
+seg1043.ts
+";
+        let Playlist::Media(media) = parse_playlist(media).expect("media should parse") else {
+            panic!("expected a media playlist");
+        };
+        assert_eq!(media.media_sequence, 1042);
+        assert_eq!(media.target_duration, 6.0);
+        assert_eq!(media.segments, ["seg1042.ts", "seg1043.ts"]);
+        assert!(!media.ended && !media.encrypted);
+
+        assert!(is_hls_url(
+            "https://radio.example/live/playlist.M3U8?token=1"
+        ));
+        assert!(!is_hls_url("https://radio.example/stream.mp3"));
+        assert!(parse_playlist("<html>").is_err());
+    }
+
+    fn ts_packet(pid: u16, unit_start: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![
+            0x47,
+            (pid >> 8) as u8 | if unit_start { 0x40 } else { 0 },
+            pid as u8,
+        ];
+        let stuffing = TS_PACKET_SIZE - 4 - payload.len();
+        if stuffing == 0 {
+            packet.push(0x10);
+        } else {
+            packet.push(0x30);
+            packet.push((stuffing - 1) as u8);
+            if stuffing > 1 {
+                packet.push(0);
+                packet.extend(std::iter::repeat_n(0xFF, stuffing - 2));
+            }
+        }
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn demuxes_audio_from_transport_streams_and_packed_segments() {
+        let pat = [
+            0, 0x00, 0xB0, 13, 0, 1, 0xC1, 0, 0, 0, 1, 0xE0, 0x20, 0, 0, 0, 0,
+        ];
+        let pmt = [
+            0, 0x02, 0xB0, 18, 0, 1, 0xC1, 0, 0, 0xE1, 0x01, 0xF0, 0, 0x0F, 0xE1, 0x01, 0xF0, 0, 0,
+            0, 0, 0,
+        ];
+        let adts = [0xFF, 0xF1, 0x50, 0x80, 0x01, 0x7F, 0xFC, 0xAA, 0xBB];
+        let mut pes = vec![0, 0, 1, 0xC0, 0, 0, 0x80, 0x80, 5, 0x21, 0, 1, 0, 1];
+        pes.extend_from_slice(&adts[..4]);
+
+        let mut segment = ts_packet(0, true, &pat);
+        segment.extend(ts_packet(0x20, true, &pmt));
+        segment.extend(ts_packet(0x101, true, &pes));
+        segment.extend(ts_packet(0x101, false, &adts[4..]));
+        let (audio, content_type) = demux_segment(&segment).expect("ts should demux");
+        assert_eq!(content_type, "audio/aac");
+        assert_eq!(audio, adts);
+
+        let mut packed = b"ID3\x04\x00\x00\x00\x00\x00\x02\xAA\xBB".to_vec();
+        packed.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        let (audio, content_type) = demux_segment(&packed).expect("packed audio");
+        assert_eq!(content_type, "audio/mpeg");
+        assert_eq!(audio, [0xFF, 0xFB, 0x90, 0x64]);
+    }
+}
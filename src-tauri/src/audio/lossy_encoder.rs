@@ -0,0 +1,204 @@
+//! MP3 and Ogg Vorbis encoding for exports, through LAME (`mp3lame-encoder`) and
+//! aoTuV libvorbis (`vorbis_rs`). Both take the same interleaved samples and Vorbis
+//! comment style tags as `flac_encoder::write_flac`.
+
+use std::io::Write;
+use std::num::{NonZeroU32, NonZeroU8};
+use std::path::Path;
+
+use id3::TagLike;
+use mp3lame_encoder::{
+    max_required_buffer_size, Builder, FlushNoGap, InterleavedPcm, MonoPcm, Quality, VbrMode,
+};
+use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
+
+/// Frames handed to the encoders at a time. libvorbis slows down sharply on large
+/// blocks, and this keeps LAME's output buffer small as well.
+const CHUNK_FRAMES: usize = 4096;
+/// Vorbis quality 6 (about 192 kbit/s for stereo), transparent for most material.
+const VORBIS_QUALITY: f32 = 0.6;
+
+/// Encodes interleaved mono or stereo samples as a LAME V2 VBR MP3 with an ID3v2.4 tag
+/// holding `tags` and a Xing/LAME header, so players know the exact length.
+pub fn write_mp3(
+    path: &Path,
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    tags: &[(&str, String)],
+) -> Result<(), String> {
+    let bytes = encode_mp3(samples, sample_rate, channels, tags)?;
+    let mut file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create MP3 file {}: {e}", path.display()))?;
+    file.write_all(&bytes)
+        .map_err(|e| format!("Failed to write MP3 file {}: {e}", path.display()))
+}
+
+fn encode_mp3(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    tags: &[(&str, String)],
+) -> Result<Vec<u8>, String> {
+    if !(1..=2).contains(&channels) {
+        return Err(format!("MP3 supports 1 or 2 channels, not {channels}"));
+    }
+    let build_error = |e| format!("Unsupported MP3 stream ({sample_rate} Hz, {channels} ch): {e}");
+    let mut builder = Builder::new().ok_or("Failed to allocate the MP3 encoder")?;
+    builder
+        .set_num_channels(channels as u8)
+        .map_err(build_error)?;
+    builder.set_sample_rate(sample_rate).map_err(build_error)?;
+    builder.set_vbr_mode(VbrMode::Mtrh).map_err(build_error)?;
+    builder
+        .set_vbr_quality(Quality::NearBest)
+        .map_err(build_error)?;
+    builder
+        .set_quality(Quality::NearBest)
+        .map_err(build_error)?;
+    let mut encoder = builder.build().map_err(build_error)?;
+
+    let channels = usize::from(channels);
+    let frames = samples.len() / channels;
+    let mut audio = Vec::with_capacity(max_required_buffer_size(frames));
+    let encode_error = |e| format!("MP3 encoding failed: {e}");
+    for chunk in samples[..frames * channels].chunks(CHUNK_FRAMES * channels) {
+        audio.reserve(max_required_buffer_size(chunk.len() / channels));
+        let written = match channels {
+            1 => encoder.encode_to_vec(MonoPcm(chunk), &mut audio),
+            _ => encoder.encode_to_vec(InterleavedPcm(chunk), &mut audio),
+        };
+        written.map_err(encode_error)?;
+    }
+    audio.reserve(max_required_buffer_size(0));
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut audio)
+        .map_err(encode_error)?;
+    // LAME leaves an empty first frame for the Xing/LAME header, which needs the
+    // finished stream's length and size.
+    let mut header = Vec::with_capacity(encoder.lame_tag_size());
+    if encoder.lame_tag_encode_to_vec(&mut header).is_some() && header.len() <= audio.len() {
+        audio[..header.len()].copy_from_slice(&header);
+    }
+
+    let mut tag = id3::Tag::new();
+    for (name, value) in tags {
+        let id = match *name {
+            "TITLE" => "TIT2",
+            "ARTIST" => "TPE1",
+            "ALBUM" => "TALB",
+            "GENRE" => "TCON",
+            _ => continue,
+        };
+        tag.set_text(id, value.as_str());
+    }
+    let mut bytes = Vec::new();
+    tag.write_to(&mut bytes, id3::Version::Id3v24)
+        .map_err(|e| format!("Failed to write MP3 tags: {e}"))?;
+    bytes.extend_from_slice(&audio);
+    Ok(bytes)
+}
+
+/// Encodes interleaved samples as a quality 6 Ogg Vorbis file with `tags` as its
+/// Vorbis comments.
+pub fn write_ogg_vorbis(
+    path: &Path,
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    tags: &[(&str, String)],
+) -> Result<(), String> {
+    let bytes = encode_ogg_vorbis(samples, sample_rate, channels, tags)?;
+    let mut file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create OGG file {}: {e}", path.display()))?;
+    file.write_all(&bytes)
+        .map_err(|e| format!("Failed to write OGG file {}: {e}", path.display()))
+}
+
+fn encode_ogg_vorbis(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    tags: &[(&str, String)],
+) -> Result<Vec<u8>, String> {
+    let (Some(rate), Some(channel_count)) = (
+        NonZeroU32::new(sample_rate),
+        u8::try_from(channels).ok().and_then(NonZeroU8::new),
+    ) else {
+        return Err(format!(
+            "Unsupported OGG stream ({sample_rate} Hz, {channels} ch)"
+        ));
+    };
+    let encode_error = |e| format!("Vorbis encoding failed: {e}");
+    let mut builder =
+        VorbisEncoderBuilder::new(rate, channel_count, Vec::new()).map_err(encode_error)?;
+    builder
+        .bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr {
+            target_quality: VORBIS_QUALITY,
+        })
+        .comment_tags(tags.iter().map(|(name, value)| (*name, value.as_str())))
+        .map_err(encode_error)?;
+    let mut encoder = builder.build().map_err(encode_error)?;
+
+    let channels = usize::from(channels);
+    let frames = samples.len() / channels;
+    let mut planes = vec![Vec::with_capacity(CHUNK_FRAMES); channels];
+    for chunk in samples[..frames * channels].chunks(CHUNK_FRAMES * channels) {
+        for (channel, plane) in planes.iter_mut().enumerate() {
+            plane.clear();
+            plane.extend(chunk.iter().skip(channel).step_by(channels));
+        }
+        encoder.encode_audio_block(&planes).map_err(encode_error)?;
+    }
+    encoder.finish().map_err(encode_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_mp3, write_ogg_vorbis};
+    use crate::audio::decoder::{decode_file, read_track_metadata};
+    use crate::test_support::unique_temp_path;
+
+    fn sine(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|n| {
+                let sample = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * n as f32 / 44_100.0).sin();
+                [sample, sample * 0.5]
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn mp3_and_ogg_round_trip_with_tags() {
+        let samples = sine(44_100);
+        let tags = [
+            ("GENRE", "Rock".to_string()),
+            ("ARTIST", "Band".to_string()),
+        ];
+        for extension in ["mp3", "ogg"] {
+            let path = unique_temp_path(&format!("lossy.{extension}"));
+            let written = match extension {
+                "mp3" => write_mp3(&path, &samples, 44_100, 2, &tags),
+                _ => write_ogg_vorbis(&path, &samples, 44_100, 2, &tags),
+            };
+            written.expect("export should encode");
+            let decoded = decode_file(&path).expect("export should decode");
+            let metadata = read_track_metadata(&path).expect("export should probe");
+            std::fs::remove_file(&path).ok();
+
+            assert_eq!((decoded.sample_rate, decoded.channels), (44_100, 2));
+            assert!(
+                decoded.samples.len().abs_diff(samples.len()) < 2 * 2304,
+                "{extension}: {} samples",
+                decoded.samples.len()
+            );
+            assert!((rms(&decoded.samples) - rms(&samples)).abs() < 0.05 * rms(&samples));
+            assert_eq!(metadata.artist.as_deref(), Some("Band"), "{extension}");
+            assert_eq!(metadata.genre.as_deref(), Some("Rock"), "{extension}");
+        }
+    }
+}
@@ -1,69 +1,192 @@
 use crate::audio::lyrics_downloader;
+use crate::library::cue::split_virtual_path;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
 pub struct LyricsLine {
     pub timestamp: u32,
     pub text: String,
 }
 
 pub fn find_lrc_for_track(track_path: &Path) -> Option<PathBuf> {
-    let stem = track_path.file_stem()?;
-    let parent = track_path.parent()?;
-    let candidate = parent.join(stem).with_extension("lrc");
-    if candidate.is_file() {
-        return Some(candidate);
+    // A sidecar next to a cue sheet would match every song of the album.
+    if split_virtual_path(track_path).is_none() {
+        let stem = track_path.file_stem()?;
+        let parent = track_path.parent()?;
+        let candidate = parent.join(stem).with_extension("lrc");
+        if candidate.is_file() {
+            return Some(candidate);
+        }
     }
     let cached = lyrics_downloader::cached_lyrics_path(track_path);
     cached.is_file().then_some(cached)
 }
 
-pub fn load_lyrics_for_track(track_path: &Path) -> Vec<LyricsLine> {
+/// ID tags found in the header of an LRC file (`[ar:]`, `[ti:]`, `[offset:]`, ...).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LrcTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub author: Option<String>,
+    pub creator: Option<String>,
+    pub length: Option<String>,
+    /// Milliseconds; positive values make the lyrics appear earlier.
+    pub offset_ms: i32,
+}
+
+/// A line that was dropped or had to be repaired while parsing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LrcWarning {
+    /// 1-based line number in the source file.
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParsedLrc {
+    pub lines: Vec<LyricsLine>,
+    pub tags: LrcTags,
+    pub warnings: Vec<LrcWarning>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LrcParseMode {
+    /// Only well-formed `[mm:ss.xx]` timestamps are accepted; anything else is dropped.
+    Strict,
+    /// Also recovers common real-world variants (`,` or `:` before the fraction,
+    /// seconds >= 60, negative times clamped to zero) instead of dropping the line.
+    Lenient,
+}
+
+pub fn load_lyrics_for_track(track_path: &Path, mode: LrcParseMode) -> ParsedLrc {
     let Some(lrc_path) = find_lrc_for_track(track_path) else {
-        return Vec::new();
+        return ParsedLrc::default();
     };
-    let Ok(content) = fs::read_to_string(lrc_path) else {
-        return Vec::new();
+    let Ok(bytes) = fs::read(lrc_path) else {
+        return ParsedLrc::default();
     };
-    parse_lrc(&content)
+    parse_lrc_document(&String::from_utf8_lossy(&bytes), mode)
 }
 
-pub fn parse_lrc(content: &str) -> Vec<LyricsLine> {
-    let mut lines = Vec::new();
-    for raw in content.lines() {
-        let parsed = parse_line(raw);
-        lines.extend(parsed);
+pub fn parse_lrc_document(content: &str, mode: LrcParseMode) -> ParsedLrc {
+    let mut document = ParsedLrc::default();
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    // Old Mac files use bare CR; `lines()` only understands LF and CRLF.
+    let normalized = content.replace("\r\n", "\n").replace('\r', "\n");
+    for (index, raw) in normalized.lines().enumerate() {
+        parse_line(raw, index + 1, mode, &mut document);
+    }
+
+    let offset = i64::from(document.tags.offset_ms);
+    if offset != 0 {
+        for line in &mut document.lines {
+            line.timestamp =
+                (i64::from(line.timestamp) - offset).clamp(0, i64::from(u32::MAX)) as u32;
+        }
     }
-    lines.sort_by_key(|line| line.timestamp);
-    lines
+    document.lines.sort_by_key(|line| line.timestamp);
+    document
 }
 
-fn parse_line(line: &str) -> Vec<LyricsLine> {
-    let mut rest = line.trim();
+fn parse_line(raw: &str, number: usize, mode: LrcParseMode, document: &mut ParsedLrc) {
+    let line = raw.trim();
+    if line.is_empty() {
+        return;
+    }
+    if let Some((key, value)) = parse_id_tag(line) {
+        apply_id_tag(
+            &key,
+            value,
+            number,
+            &mut document.tags,
+            &mut document.warnings,
+        );
+        return;
+    }
+
+    let mut rest = line;
     let mut timestamps = Vec::new();
     while let Some(stripped) = rest.strip_prefix('[') {
         let Some(close) = stripped.find(']') else {
             break;
         };
         let time_token = &stripped[..close];
-        let Some(timestamp) = parse_timestamp(time_token) else {
-            break;
+        let timestamp = match parse_timestamp(time_token) {
+            Some(timestamp) => timestamp,
+            None if mode == LrcParseMode::Lenient => {
+                let Some((timestamp, note)) = parse_timestamp_lenient(time_token) else {
+                    break;
+                };
+                document.warnings.push(LrcWarning {
+                    line: number,
+                    message: format!("Recovered timestamp [{time_token}]: {note}"),
+                });
+                timestamp
+            }
+            None => break,
         };
         timestamps.push(timestamp);
         rest = stripped[close + 1..].trim_start();
     }
     if timestamps.is_empty() {
-        return Vec::new();
+        let message = if line.starts_with('[') {
+            "Malformed timestamp; line dropped"
+        } else {
+            "Line has no timestamp; dropped"
+        };
+        document.warnings.push(LrcWarning {
+            line: number,
+            message: message.to_string(),
+        });
+        return;
     }
     let text = rest.trim().to_string();
-    timestamps
-        .into_iter()
-        .map(|timestamp| LyricsLine {
+    document
+        .lines
+        .extend(timestamps.into_iter().map(|timestamp| LyricsLine {
             timestamp,
             text: text.clone(),
-        })
-        .collect()
+        }));
+}
+
+/// Matches a whole-line `[key:value]` tag whose key is alphabetic, e.g. `[ar:Artist]`.
+fn parse_id_tag(line: &str) -> Option<(String, &str)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, value) = inner.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|ch| ch.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((key.to_ascii_lowercase(), value.trim()))
+}
+
+fn apply_id_tag(
+    key: &str,
+    value: &str,
+    number: usize,
+    tags: &mut LrcTags,
+    warnings: &mut Vec<LrcWarning>,
+) {
+    let text = (!value.is_empty()).then(|| value.to_string());
+    match key {
+        "ti" => tags.title = text,
+        "ar" => tags.artist = text,
+        "al" => tags.album = text,
+        "au" => tags.author = text,
+        "by" => tags.creator = text,
+        "length" => tags.length = text,
+        "offset" => match value.trim_start_matches('+').parse::<i32>() {
+            Ok(offset) => tags.offset_ms = offset,
+            Err(_) => warnings.push(LrcWarning {
+                line: number,
+                message: format!("Invalid offset \"{value}\" ignored"),
+            }),
+        },
+        // Other tags (re, ve, ...) carry no playback information.
+        _ => {}
+    }
 }
 
 fn parse_timestamp(value: &str) -> Option<u32> {
@@ -92,6 +215,36 @@ fn parse_timestamp(value: &str) -> Option<u32> {
         .checked_add(millis)
 }
 
+/// Accepts `mm:ss,xx`, `mm:ss:xx`, `mm:75.00` and `-mm:ss.xx`. Returns the timestamp and
+/// a description of what was repaired.
+fn parse_timestamp_lenient(value: &str) -> Option<(u32, &'static str)> {
+    let trimmed = value.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, trimmed),
+    };
+    let normalized = unsigned.replace(',', ".");
+    let parts = normalized.split(':').map(str::trim).collect::<Vec<_>>();
+    let (minutes, seconds, fraction) = match parts.as_slice() {
+        [minutes, rest] => {
+            let (seconds, fraction) = rest.split_once('.').unwrap_or((rest, ""));
+            (*minutes, seconds, fraction)
+        }
+        [minutes, seconds, fraction] => (*minutes, *seconds, *fraction),
+        _ => return None,
+    };
+    let minutes = minutes.parse::<u32>().ok()?;
+    let seconds = seconds.parse::<u32>().ok()?;
+    let millis = minutes
+        .checked_mul(60_000)?
+        .checked_add(seconds.checked_mul(1_000)?)?
+        .checked_add(parse_fraction_to_millis(fraction)?)?;
+    if negative {
+        return Some((0, "negative time clamped to 0"));
+    }
+    Some((millis, "non-standard format"))
+}
+
 fn parse_fraction_to_millis(fraction: &str) -> Option<u32> {
     if fraction.is_empty() {
         return Some(0);
@@ -108,13 +261,14 @@ fn parse_fraction_to_millis(fraction: &str) -> Option<u32> {
 
 #[cfg(test)]
 mod tests {
-    use super::{find_lrc_for_track, parse_lrc, LyricsLine};
+    use super::{find_lrc_for_track, parse_lrc_document, LrcParseMode, LyricsLine};
     use crate::audio::lyrics_downloader::cached_lyrics_path;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::test_support::unique_temp_path;
+    use proptest::prelude::*;
 
     #[test]
     fn parses_single_timestamp_line() {
-        let parsed = parse_lrc("[01:02.34] Hello world");
+        let parsed = parse_lrc_document("[01:02.34] Hello world", LrcParseMode::Lenient).lines;
         assert_eq!(
             parsed,
             vec![LyricsLine {
@@ -126,7 +280,7 @@ mod tests {
 
     #[test]
     fn parses_multiple_timestamps_in_one_line() {
-        let parsed = parse_lrc("[00:10.00][00:12.50] Chorus");
+        let parsed = parse_lrc_document("[00:10.00][00:12.50] Chorus", LrcParseMode::Lenient).lines;
         assert_eq!(parsed.len(), 2);
         assert_eq!(parsed[0].timestamp, 10_000);
         assert_eq!(parsed[1].timestamp, 12_500);
@@ -135,7 +289,11 @@ mod tests {
 
     #[test]
     fn ignores_invalid_lines_and_sorts() {
-        let parsed = parse_lrc("[00:20.xx]bad\n[00:15.00]A\n[00:10.00]B");
+        let parsed = parse_lrc_document(
+            "[00:20.xx]bad\n[00:15.00]A\n[00:10.00]B",
+            LrcParseMode::Lenient,
+        )
+        .lines;
         assert_eq!(
             parsed
                 .iter()
@@ -147,11 +305,7 @@ mod tests {
 
     #[test]
     fn falls_back_to_cached_lrc_file() {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        let track = std::env::temp_dir().join(format!("powerplayer-lyrics-{nanos}.flac"));
+        let track = unique_temp_path("lyrics.flac");
         let cached = cached_lyrics_path(&track);
         if let Some(parent) = cached.parent() {
             std::fs::create_dir_all(parent).expect("cache directory should exist");
@@ -163,4 +317,113 @@ mod tests {
 
         let _ = std::fs::remove_file(cached);
     }
+
+    #[test]
+    fn handles_bom_crlf_and_id_tags() {
+        let content = "\u{feff}[ar:Artist]\r\n[ti: Title ]\r\n[re:Editor]\r\n[00:01.00]One\r[00:02.00]Two\r\n";
+        let parsed = parse_lrc_document(content, LrcParseMode::Strict);
+        assert_eq!(parsed.tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(parsed.tags.title.as_deref(), Some("Title"));
+        assert_eq!(
+            parsed
+                .lines
+                .iter()
+                .map(|line| (line.timestamp, line.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1_000, "One"), (2_000, "Two")]
+        );
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn applies_offset_tag() {
+        let parsed = parse_lrc_document(
+            "[offset:+500]\n[00:00.20]Early\n[00:10.00]Later",
+            LrcParseMode::Lenient,
+        )
+        .lines;
+        assert_eq!(parsed[0].timestamp, 0);
+        assert_eq!(parsed[1].timestamp, 9_500);
+
+        let delayed =
+            parse_lrc_document("[00:01.00]Line\n[offset:-250]", LrcParseMode::Lenient).lines;
+        assert_eq!(delayed[0].timestamp, 1_250);
+    }
+
+    #[test]
+    fn lenient_mode_recovers_malformed_timestamps() {
+        let content =
+            "[00:01,50]Comma\n[00:02:25]Colon\n[-00:01.00]Negative\n[00:75.00]Overflow\nplain text";
+
+        let strict = parse_lrc_document(content, LrcParseMode::Strict);
+        assert!(strict.lines.is_empty());
+        assert_eq!(strict.warnings.len(), 5);
+
+        let lenient = parse_lrc_document(content, LrcParseMode::Lenient);
+        assert_eq!(
+            lenient
+                .lines
+                .iter()
+                .map(|line| (line.timestamp, line.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, "Negative"),
+                (1_500, "Comma"),
+                (2_250, "Colon"),
+                (75_000, "Overflow")
+            ]
+        );
+        assert_eq!(lenient.warnings.len(), 5);
+        assert_eq!(lenient.warnings[4].line, 5);
+    }
+
+    proptest! {
+        #[test]
+        fn any_input_parses_into_sorted_lines(
+            content in "([\\[\\]0-9:.,+\\-a-z ]|\\PC|\r|\n){0,200}",
+        ) {
+            for mode in [LrcParseMode::Strict, LrcParseMode::Lenient] {
+                let parsed = parse_lrc_document(&content, mode);
+                prop_assert!(parsed
+                    .lines
+                    .windows(2)
+                    .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+            }
+        }
+
+        #[test]
+        fn well_formed_lines_keep_their_timestamps(
+            lines in prop::collection::vec(
+                (0u32..100, 0u32..60, 0u32..100, "[a-z][a-z ]{0,10}[a-z]"),
+                1..10,
+            ),
+        ) {
+            let content = lines
+                .iter()
+                .map(|(minutes, seconds, centis, text)| {
+                    format!("[{minutes:02}:{seconds:02}.{centis:02}]{text}")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let parsed = parse_lrc_document(&content, LrcParseMode::Strict);
+            let mut expected = lines
+                .iter()
+                .map(|(minutes, seconds, centis, _)| minutes * 60_000 + seconds * 1_000 + centis * 10)
+                .collect::<Vec<_>>();
+            expected.sort_unstable();
+            prop_assert!(parsed.warnings.is_empty());
+            prop_assert_eq!(
+                parsed.lines.iter().map(|line| line.timestamp).collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_offset_is_reported() {
+        let parsed = parse_lrc_document("[offset:abc]\n[00:01.00]A", LrcParseMode::Lenient);
+        assert_eq!(parsed.tags.offset_ms, 0);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert_eq!(parsed.warnings[0].line, 1);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::library::network_usage::{self, NetworkProvider};
 use reqwest::blocking::Client;
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
@@ -41,10 +42,13 @@ pub fn download_lyrics_for_track(
         ])
         .send()
         .ok()?;
-    if !response.status().is_success() {
+    let success = response.status().is_success();
+    let body = response.bytes().ok()?;
+    network_usage::record(NetworkProvider::Lyrics, body.len() as u64);
+    if !success {
         return None;
     }
-    let payload: LrcLibResponse = response.json().ok()?;
+    let payload: LrcLibResponse = serde_json::from_slice(&body).ok()?;
     let synced = payload
         .synced_lyrics
         .filter(|value| !value.trim().is_empty())?;
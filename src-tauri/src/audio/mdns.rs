@@ -0,0 +1,244 @@
+//! Minimal mDNS/DNS-SD browser used to find network speakers (Cast, AirPlay).
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const MDNS_ADDR: &str = "224.0.0.251:5353";
+
+/// One advertised service instance, e.g. `Kitchen._googlecast._tcp.local`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MdnsService {
+    pub instance: String,
+    pub host: IpAddr,
+    pub port: u16,
+    /// TXT record entries, keys lowercased.
+    pub txt: HashMap<String, String>,
+}
+
+impl MdnsService {
+    /// The instance name without the service suffix.
+    pub fn label(&self) -> &str {
+        self.instance.split("._").next().unwrap_or(&self.instance)
+    }
+}
+
+/// Sends a PTR query for `service` (e.g. `_raop._tcp.local`) and collects every instance
+/// that answers within `window`.
+pub fn browse(service: &str, window: Duration) -> Result<Vec<MdnsService>, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("Failed to open mDNS socket: {e}"))?;
+    let query = query_packet(service);
+    // Sent from an ephemeral port, so devices answer by unicast (a "legacy" query). The
+    // query is repeated once since mDNS runs over UDP.
+    for _ in 0..2 {
+        socket
+            .send_to(&query, MDNS_ADDR)
+            .map_err(|e| format!("Failed to send mDNS query: {e}"))?;
+    }
+
+    let deadline = Instant::now() + window;
+    let mut found: Vec<MdnsService> = Vec::new();
+    let mut buffer = [0_u8; 9000];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        if remaining.is_zero() {
+            break;
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(|e| format!("Failed to configure mDNS socket: {e}"))?;
+        match socket.recv_from(&mut buffer) {
+            Ok((read, from)) => {
+                for instance in parse_response(&buffer[..read], from.ip(), service) {
+                    if !found
+                        .iter()
+                        .any(|known| known.instance == instance.instance)
+                    {
+                        found.push(instance);
+                    }
+                }
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break
+            }
+            Err(err) => return Err(format!("Failed to read mDNS answer: {err}")),
+        }
+    }
+    Ok(found)
+}
+
+fn query_packet(service: &str) -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in service.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&12_u16.to_be_bytes()); // PTR
+    packet.extend_from_slice(&1_u16.to_be_bytes()); // IN
+    packet
+}
+
+/// Reads a possibly compressed DNS name at `offset`; returns it and the offset just past
+/// it in the original record.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bounds the number of compression jumps so a malicious loop cannot hang discovery.
+    for _ in 0..64 {
+        let length = *packet.get(offset)? as usize;
+        if length == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+        if length & 0xC0 == 0xC0 {
+            let pointer = (length & 0x3F) << 8 | *packet.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+        let label = packet.get(offset + 1..offset + 1 + length)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        offset += 1 + length;
+    }
+    None
+}
+
+/// Turns one answer packet into the `service` instances it describes. The address comes
+/// from the A record, or from the sender of the packet when the answer carries none.
+fn parse_response(packet: &[u8], sender: IpAddr, service: &str) -> Vec<MdnsService> {
+    let count = |index: usize| {
+        packet
+            .get(index..index + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+            .unwrap_or(0)
+    };
+    let questions = count(4);
+    let records = count(6) + count(8) + count(10);
+    let mut offset = 12;
+    for _ in 0..questions {
+        let Some((_, next)) = read_name(packet, offset) else {
+            return Vec::new();
+        };
+        offset = next + 4;
+    }
+
+    let mut instances = Vec::new();
+    let mut services: HashMap<String, (u16, String)> = HashMap::new();
+    let mut texts: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut addresses: HashMap<String, IpAddr> = HashMap::new();
+    for _ in 0..records {
+        let Some((name, next)) = read_name(packet, offset) else {
+            break;
+        };
+        let Some(header) = packet.get(next..next + 10) else {
+            break;
+        };
+        let kind = u16::from_be_bytes([header[0], header[1]]);
+        let length = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let data_start = next + 10;
+        let Some(data) = packet.get(data_start..data_start + length) else {
+            break;
+        };
+        match kind {
+            12 if name.eq_ignore_ascii_case(service) => {
+                if let Some((instance, _)) = read_name(packet, data_start) {
+                    instances.push(instance);
+                }
+            }
+            33 if data.len() > 6 => {
+                if let Some((target, _)) = read_name(packet, data_start + 6) {
+                    services.insert(name, (u16::from_be_bytes([data[4], data[5]]), target));
+                }
+            }
+            16 => {
+                let mut entries = HashMap::new();
+                let mut rest = data;
+                while let Some((&entry_length, tail)) = rest.split_first() {
+                    let (entry, remainder) = tail.split_at((entry_length as usize).min(tail.len()));
+                    let entry = String::from_utf8_lossy(entry);
+                    if let Some((key, value)) = entry.split_once('=') {
+                        entries.insert(key.to_ascii_lowercase(), value.to_string());
+                    }
+                    rest = remainder;
+                }
+                texts.insert(name, entries);
+            }
+            1 if data.len() == 4 => {
+                addresses.insert(name, IpAddr::from([data[0], data[1], data[2], data[3]]));
+            }
+            _ => {}
+        }
+        offset = data_start + length;
+    }
+
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let (port, target) = services.get(&instance)?;
+            Some(MdnsService {
+                host: addresses.get(target).copied().unwrap_or(sender),
+                port: *port,
+                txt: texts.remove(&instance).unwrap_or_default(),
+                instance,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_response;
+    use std::net::IpAddr;
+
+    fn name(out: &mut Vec<u8>, name: &str) {
+        for label in name.split('.') {
+            out.push(label.len() as u8);
+            out.extend_from_slice(label.as_bytes());
+        }
+        out.push(0);
+    }
+
+    fn record(out: &mut Vec<u8>, owner: &str, kind: u16, data: &[u8]) {
+        name(out, owner);
+        out.extend_from_slice(&kind.to_be_bytes());
+        out.extend_from_slice(&[0, 1, 0, 0, 0, 120]);
+        out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        out.extend_from_slice(data);
+    }
+
+    #[test]
+    fn parses_service_instances_from_answers() {
+        let instance = "Chromecast-abc123._googlecast._tcp.local";
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 3];
+        let mut ptr = Vec::new();
+        name(&mut ptr, instance);
+        record(&mut packet, "_googlecast._tcp.local", 12, &ptr);
+        let mut srv = vec![0, 0, 0, 0, 0x1f, 0x49];
+        name(&mut srv, "abc123.local");
+        record(&mut packet, instance, 33, &srv);
+        let mut txt = Vec::new();
+        for entry in ["id=abc123", "md=Chromecast Audio", "FN=Kitchen speaker"] {
+            txt.push(entry.len() as u8);
+            txt.extend_from_slice(entry.as_bytes());
+        }
+        record(&mut packet, instance, 16, &txt);
+        record(&mut packet, "abc123.local", 1, &[192, 168, 1, 42]);
+
+        let sender = IpAddr::from([10, 0, 0, 1]);
+        let found = parse_response(&packet, sender, "_googlecast._tcp.local");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].label(), "Chromecast-abc123");
+        assert_eq!(found[0].host, IpAddr::from([192, 168, 1, 42]));
+        assert_eq!(found[0].port, 8009);
+        assert_eq!(found[0].txt["fn"], "Kitchen speaker");
+        assert_eq!(found[0].txt["md"], "Chromecast Audio");
+
+        assert!(parse_response(&packet, sender, "_raop._tcp.local").is_empty());
+    }
+}
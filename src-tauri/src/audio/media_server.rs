@@ -0,0 +1,364 @@
+//! Tiny HTTP server that hands the current track to network players (DLNA renderers,
+//! Chromecasts), which fetch media by URL rather than accepting pushed audio.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::decoder::decode_file;
+use crate::library::cue::split_virtual_path;
+
+/// Started on first use and kept for the life of the app.
+static SERVER: Mutex<Option<Arc<MediaServer>>> = Mutex::new(None);
+/// Requests served at once; players open one or two, so more are turned away.
+const MAX_CONNECTIONS: usize = 8;
+/// Longest request line plus headers read from a client.
+const MAX_REQUEST_HEAD_BYTES: u64 = 8 * 1024;
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a client may stop reading the body; players pause while their buffer is full.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where a network player can fetch the published track.
+#[derive(Clone, Debug)]
+pub struct PublishedMedia {
+    pub url: String,
+    pub content_type: &'static str,
+}
+
+/// Serves `path` (transcoded when the format is not widely supported) at a fresh URL
+/// reachable from `peer`, replacing whatever was published before.
+pub fn publish(path: &Path, peer: SocketAddr) -> Result<PublishedMedia, String> {
+    let server = {
+        let mut server = SERVER
+            .lock()
+            .map_err(|_| "Media server lock poisoned".to_string())?;
+        if server.is_none() {
+            *server = Some(Arc::new(MediaServer::start()?));
+        }
+        Arc::clone(server.as_ref().expect("server was just started"))
+    };
+
+    // Cue sheet songs are a slice of a larger file, so they are always transcoded.
+    let native = native_mime_type(path).filter(|_| split_virtual_path(path).is_none());
+    let (content_type, extension, body) = match native {
+        Some(content_type) => (
+            content_type,
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("bin")
+                .to_ascii_lowercase(),
+            MediaBody::File(path.to_path_buf()),
+        ),
+        None => (
+            "audio/wav",
+            "wav".to_string(),
+            MediaBody::Memory(Arc::new(transcode_to_wav(path)?)),
+        ),
+    };
+    let token = random_token()?;
+    *server
+        .current
+        .lock()
+        .map_err(|_| "Media server lock poisoned".to_string())? = Some(ServedMedia {
+        token: token.clone(),
+        content_type,
+        body,
+    });
+
+    let host = match local_address_for(peer)? {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    };
+    Ok(PublishedMedia {
+        url: format!("http://{host}:{}/{token}.{extension}", server.port),
+        content_type,
+    })
+}
+
+/// Content type for formats network players handle natively; anything else is transcoded.
+fn native_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "mp3" => Some("audio/mpeg"),
+        "flac" => Some("audio/flac"),
+        "wav" => Some("audio/wav"),
+        "m4a" | "aac" => Some("audio/mp4"),
+        "ogg" => Some("audio/ogg"),
+        _ => None,
+    }
+}
+
+/// 16 bytes from the OS random source, as hex.
+fn random_token() -> Result<String, String> {
+    let mut bytes = [0_u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| format!("Failed to generate media token: {e}"))?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Size of the `data` chunk holding `samples` 16-bit samples, or an error when the WAV
+/// would pass the format's 4 GiB limit.
+fn wav_data_len(samples: usize) -> Result<u32, String> {
+    samples
+        .checked_mul(2)
+        .and_then(|len| u32::try_from(len).ok())
+        .filter(|len| len.checked_add(36).is_some())
+        .ok_or_else(|| "Track is too long to stream as WAV".to_string())
+}
+
+/// 16-bit PCM WAV, which every DLNA renderer and Chromecast must play.
+fn transcode_to_wav(path: &Path) -> Result<Vec<u8>, String> {
+    let track = decode_file(path)?;
+    let data_len = wav_data_len(track.samples.len())?;
+    let block_align = track.channels * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16_u32.to_le_bytes());
+    bytes.extend_from_slice(&1_u16.to_le_bytes());
+    bytes.extend_from_slice(&track.channels.to_le_bytes());
+    bytes.extend_from_slice(&track.sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(track.sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16_u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in track.samples {
+        bytes
+            .extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+#[derive(Clone)]
+enum MediaBody {
+    File(PathBuf),
+    Memory(Arc<Vec<u8>>),
+}
+
+#[derive(Clone)]
+struct ServedMedia {
+    /// Random request path, so other LAN clients cannot guess what is served.
+    token: String,
+    content_type: &'static str,
+    body: MediaBody,
+}
+
+/// Serves the one published track. Plain HTTP/1.1 with single byte-range support,
+/// which players use to seek.
+struct MediaServer {
+    port: u16,
+    current: Arc<Mutex<Option<ServedMedia>>>,
+}
+
+impl MediaServer {
+    fn start() -> Result<Self, String> {
+        let listener = TcpListener::bind("0.0.0.0:0")
+            .map_err(|e| format!("Failed to start media server: {e}"))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read media server address: {e}"))?
+            .port();
+        let current = Arc::new(Mutex::new(None));
+        let served = Arc::clone(&current);
+        let connections = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let served = Arc::clone(&served);
+                let connections = Arc::clone(&connections);
+                thread::spawn(move || {
+                    let result = stream
+                        .set_read_timeout(Some(READ_TIMEOUT))
+                        .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)))
+                        .and_then(|_| handle_request(stream, &served));
+                    if let Err(err) = result {
+                        eprintln!("Media server request failed: {err}");
+                    }
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Self { port, current })
+    }
+}
+
+fn handle_request(mut stream: TcpStream, current: &Mutex<Option<ServedMedia>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_HEAD_BYTES);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut range = None;
+    let mut complete = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            complete = true;
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("range") {
+                range = Some(value.trim().to_string());
+            }
+        }
+    }
+    if !complete {
+        return stream.write_all(
+            b"HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let media = current.lock().ok().and_then(|current| current.clone());
+    let Some(media) = media.filter(|media| {
+        target.trim_start_matches('/').split('.').next() == Some(media.token.as_str())
+    }) else {
+        return stream.write_all(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+    };
+    if method != "GET" && method != "HEAD" {
+        return stream.write_all(
+            b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+    }
+
+    let length = match &media.body {
+        MediaBody::File(path) => std::fs::metadata(path)?.len(),
+        MediaBody::Memory(bytes) => bytes.len() as u64,
+    };
+    let (status, start, end) = match range
+        .as_deref()
+        .and_then(|range| parse_range(range, length))
+    {
+        Some((start, end)) => ("206 Partial Content", start, end),
+        None => ("200 OK", 0, length.saturating_sub(1)),
+    };
+    let body_len = if length == 0 { 0 } else { end - start + 1 };
+    let mut header = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {}\r\nContent-Length: {body_len}\r\nAccept-Ranges: bytes\r\ntransferMode.dlna.org: Streaming\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n",
+        media.content_type
+    );
+    if status.starts_with("206") {
+        header.push_str(&format!("Content-Range: bytes {start}-{end}/{length}\r\n"));
+    }
+    header.push_str("\r\n");
+    stream.write_all(header.as_bytes())?;
+    if method == "HEAD" || body_len == 0 {
+        return Ok(());
+    }
+    match &media.body {
+        MediaBody::File(path) => {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(start))?;
+            io::copy(&mut file.take(body_len), &mut stream)?;
+        }
+        MediaBody::Memory(bytes) => {
+            stream.write_all(&bytes[start as usize..=end as usize])?;
+        }
+    }
+    stream.flush()
+}
+
+/// Parses a single `bytes=start-[end]` or `bytes=-suffix` range into inclusive offsets.
+fn parse_range(header: &str, length: u64) -> Option<(u64, u64)> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') || length == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?.min(length);
+            (length - suffix, length - 1)
+        }
+        (start, "") => (start.parse().ok()?, length - 1),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(length - 1),
+        ),
+    };
+    (start <= end && start < length).then_some((start, end))
+}
+
+/// The local address `peer` can reach us on: the interface the OS would route its
+/// traffic through.
+fn local_address_for(peer: SocketAddr) -> Result<IpAddr, String> {
+    let unspecified = if peer.is_ipv4() {
+        IpAddr::from([0, 0, 0, 0])
+    } else {
+        IpAddr::from([0_u16; 8])
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(unspecified, 0))
+        .map_err(|e| format!("Failed to find local address: {e}"))?;
+    socket
+        .connect(peer)
+        .and_then(|_| socket.local_addr())
+        .map(|addr| addr.ip())
+        .map_err(|e| format!("No route to {peer}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{handle_request, parse_range, random_token, wav_data_len};
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Mutex;
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-", 1000), Some((0, 999)));
+        assert_eq!(parse_range("bytes=100-199", 1000), Some((100, 199)));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+    }
+
+    #[test]
+    fn tokens_are_random_hex() {
+        let token = random_token().expect("token");
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, random_token().expect("token"));
+    }
+
+    #[test]
+    fn wav_size_fields_do_not_overflow() {
+        assert_eq!(wav_data_len(1000), Ok(2000));
+        assert!(wav_data_len((u32::MAX / 2) as usize).is_err());
+    }
+
+    #[test]
+    fn oversized_request_heads_are_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().expect("address");
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).expect("connect");
+            // Exactly the limit and never terminated, so the server reads all of it.
+            let mut header = b"GET / HTTP/1.1\r\nX-Padding: ".to_vec();
+            header.resize(8 * 1024, b'a');
+            stream.write_all(&header).expect("send");
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        });
+        let (stream, _) = listener.accept().expect("accept");
+        handle_request(stream, &Mutex::new(None)).expect("request should be answered");
+        let response = client.join().expect("client");
+        assert!(response.starts_with("HTTP/1.1 431"), "{response}");
+    }
+}
@@ -1,6 +1,27 @@
+pub mod airplay;
 pub mod analyzer;
+pub mod ape;
+pub mod autoeq_downloader;
+pub mod cast;
+pub mod chapters;
+pub mod clap;
+pub mod codecs;
 pub mod decoder;
+pub mod dlna;
+pub mod dsd;
 pub mod dsp;
+pub mod flac_encoder;
 pub mod engine;
+pub mod head_tracking;
+pub mod hls;
+pub mod lossy_encoder;
 pub mod lyrics;
 pub mod lyrics_downloader;
+pub mod mdns;
+pub mod media_server;
+pub mod opus;
+pub mod output;
+pub mod plugin_host;
+pub mod stream;
+pub mod vst3;
+pub mod wavpack;
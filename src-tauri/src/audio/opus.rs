@@ -0,0 +1,374 @@
+//! Opus support. Symphonia's Ogg demuxer already splits `.opus` files into packets but
+//! ships no Opus decoder, so the packets are decoded here with libopus (the pure-Rust
+//! translation in `unsafe-libopus`, which needs no C toolchain).
+
+use std::ptr::NonNull;
+
+use symphonia::core::{
+    audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec},
+    codecs::{
+        CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult, CODEC_TYPE_OPUS,
+    },
+    errors::{decode_error, unsupported_error, Result},
+    formats::Packet,
+    support_codec,
+};
+use unsafe_libopus::{
+    opus_multistream_decode_float, opus_multistream_decoder_create,
+    opus_multistream_decoder_destroy, opus_multistream_decoder_init, OpusMSDecoder, OPUS_OK,
+};
+
+/// libopus decodes at 48 kHz, the rate Ogg Opus pre-skip and granule positions count in.
+const OPUS_RATE: u32 = 48_000;
+/// The longest packet Opus allows is 120 ms.
+const MAX_FRAMES_PER_PACKET: usize = OPUS_RATE as usize * 120 / 1000;
+
+/// Order of the channels in a Vorbis mapping family stream, indexed by channel count.
+const VORBIS_CHANNEL_ORDER: [&[Channels]; 8] = [
+    &[Channels::FRONT_LEFT],
+    &[Channels::FRONT_LEFT, Channels::FRONT_RIGHT],
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_RIGHT,
+    ],
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_RIGHT,
+        Channels::REAR_LEFT,
+        Channels::REAR_RIGHT,
+    ],
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_RIGHT,
+        Channels::REAR_LEFT,
+        Channels::REAR_RIGHT,
+    ],
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_RIGHT,
+        Channels::REAR_LEFT,
+        Channels::REAR_RIGHT,
+        Channels::LFE1,
+    ],
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_RIGHT,
+        Channels::SIDE_LEFT,
+        Channels::SIDE_RIGHT,
+        Channels::REAR_CENTRE,
+        Channels::LFE1,
+    ],
+    &[
+        Channels::FRONT_LEFT,
+        Channels::FRONT_CENTRE,
+        Channels::FRONT_RIGHT,
+        Channels::SIDE_LEFT,
+        Channels::SIDE_RIGHT,
+        Channels::REAR_LEFT,
+        Channels::REAR_RIGHT,
+        Channels::LFE1,
+    ],
+];
+
+/// Stream layout from the `OpusHead` identification header (RFC 7845 §5.1).
+struct OpusHead {
+    channels: usize,
+    pre_skip: usize,
+    /// Linear factor of the header's output gain, applied to every decoded sample.
+    gain: f32,
+    streams: u8,
+    coupled_streams: u8,
+    mapping: Vec<u8>,
+}
+
+impl OpusHead {
+    fn parse(header: &[u8]) -> Option<Self> {
+        if header.len() < 19 || &header[..8] != b"OpusHead" {
+            return None;
+        }
+        let channels = usize::from(header[9]);
+        let pre_skip = usize::from(u16::from_le_bytes([header[10], header[11]]));
+        // Output gain is a signed Q7.8 number of dB.
+        let gain_db = f32::from(i16::from_le_bytes([header[16], header[17]])) / 256.0;
+        let gain = 10_f32.powf(gain_db / 20.0);
+        match header[18] {
+            // Family 0 is one mono or stereo stream.
+            0 if (1..=2).contains(&channels) => Some(Self {
+                channels,
+                pre_skip,
+                gain,
+                streams: 1,
+                coupled_streams: u8::from(channels == 2),
+                mapping: (0..channels as u8).collect(),
+            }),
+            _ => {
+                let mapping = header.get(21..21 + channels)?.to_vec();
+                Some(Self {
+                    channels,
+                    pre_skip,
+                    gain,
+                    streams: header[19],
+                    coupled_streams: header[20],
+                    mapping,
+                })
+            }
+        }
+    }
+}
+
+/// Owned libopus multistream decoder state.
+struct MultistreamState(NonNull<OpusMSDecoder>);
+
+// SAFETY: the state is plain memory owned by this handle and only touched through
+// `&mut self`, so it may move between threads and be shared behind a reference.
+unsafe impl Send for MultistreamState {}
+unsafe impl Sync for MultistreamState {}
+
+impl MultistreamState {
+    fn new(head: &OpusHead) -> Option<Self> {
+        let mut error = 0;
+        // SAFETY: `mapping` holds one entry per channel, as libopus reads.
+        let state = unsafe {
+            opus_multistream_decoder_create(
+                OPUS_RATE as i32,
+                head.channels as i32,
+                i32::from(head.streams),
+                i32::from(head.coupled_streams),
+                head.mapping.as_ptr(),
+                &mut error,
+            )
+        };
+        if error != OPUS_OK {
+            return None;
+        }
+        NonNull::new(state).map(Self)
+    }
+}
+
+impl Drop for MultistreamState {
+    fn drop(&mut self) {
+        // SAFETY: the pointer came from `opus_multistream_decoder_create` and is freed once.
+        unsafe { opus_multistream_decoder_destroy(self.0.as_ptr()) }
+    }
+}
+
+pub struct OpusDecoder {
+    params: CodecParameters,
+    head: OpusHead,
+    state: MultistreamState,
+    /// Output plane of each decoded channel, which libopus emits in Vorbis order.
+    planes: Vec<usize>,
+    pcm: Vec<f32>,
+    buf: AudioBuffer<f32>,
+    /// Frames still to drop from the start of the stream: the encoder's lookahead.
+    pre_skip: usize,
+}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let Some(head) = params.extra_data.as_deref().and_then(OpusHead::parse) else {
+            return unsupported_error("opus: missing or invalid OpusHead");
+        };
+        let Some(order) = VORBIS_CHANNEL_ORDER.get(head.channels.wrapping_sub(1)) else {
+            return unsupported_error("opus: unsupported channel count");
+        };
+        let channels = order
+            .iter()
+            .fold(Channels::empty(), |all, channel| all | *channel);
+        let planes = order
+            .iter()
+            .map(|channel| (channels.bits() & (channel.bits() - 1)).count_ones() as usize)
+            .collect();
+        let Some(state) = MultistreamState::new(&head) else {
+            return unsupported_error("opus: libopus rejected the stream layout");
+        };
+        Ok(Self {
+            params: params.clone(),
+            pcm: vec![0.0; MAX_FRAMES_PER_PACKET * head.channels],
+            buf: AudioBuffer::new(
+                MAX_FRAMES_PER_PACKET as u64,
+                SignalSpec::new(OPUS_RATE, channels),
+            ),
+            pre_skip: head.pre_skip,
+            head,
+            state,
+            planes,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_OPUS, "opus", "Opus")]
+    }
+
+    fn reset(&mut self) {
+        // SAFETY: re-initializing keeps the allocation and layout `new` validated.
+        unsafe {
+            opus_multistream_decoder_init(
+                self.state.0.as_ptr(),
+                OPUS_RATE as i32,
+                self.head.channels as i32,
+                i32::from(self.head.streams),
+                i32::from(self.head.coupled_streams),
+                self.head.mapping.as_ptr(),
+            );
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let data = packet.buf();
+        // SAFETY: `pcm` holds `MAX_FRAMES_PER_PACKET` interleaved frames.
+        let frames = unsafe {
+            opus_multistream_decode_float(
+                self.state.0.as_ptr(),
+                data.as_ptr(),
+                data.len() as i32,
+                self.pcm.as_mut_ptr(),
+                MAX_FRAMES_PER_PACKET as i32,
+                0,
+            )
+        };
+        let Ok(frames) = usize::try_from(frames) else {
+            return decode_error("opus: corrupt packet");
+        };
+
+        self.buf.render_reserved(Some(frames));
+        for (channel, plane) in self.planes.iter().enumerate() {
+            let samples = self.pcm.iter().skip(channel).step_by(self.head.channels);
+            for (out, sample) in self.buf.chan_mut(*plane).iter_mut().zip(samples) {
+                *out = *sample * self.head.gain;
+            }
+        }
+        let skip = self.pre_skip.min(frames);
+        self.pre_skip -= skip;
+        self.buf.trim(skip, 0);
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::decoder::decode_file;
+    use crate::test_support::unique_temp_path;
+    use ogg::{PacketWriteEndInfo, PacketWriter};
+    use unsafe_libopus::{
+        opus_encode_float, opus_encoder_create, opus_encoder_destroy, OPUS_APPLICATION_AUDIO,
+    };
+
+    const FRAME: usize = 960;
+    const PRE_SKIP: u16 = 312;
+
+    /// Ogg Opus file holding `packets` 20 ms stereo frames of a 440 Hz sine, with the
+    /// header's output gain set to `gain` (Q7.8 dB).
+    fn encode_sine(packets: usize, gain: i16) -> (Vec<u8>, f32) {
+        let pcm = (0..packets * FRAME)
+            .flat_map(|n| {
+                let sample = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * n as f32 / 48_000.0).sin();
+                [sample, sample]
+            })
+            .collect::<Vec<f32>>();
+        let rms = (pcm.iter().map(|s| s * s).sum::<f32>() / pcm.len() as f32).sqrt();
+
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2]);
+        head.extend_from_slice(&PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&48_000_u32.to_le_bytes());
+        head.extend_from_slice(&gain.to_le_bytes());
+        head.push(0);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&0_u32.to_le_bytes());
+        tags.extend_from_slice(&0_u32.to_le_bytes());
+
+        let mut writer = PacketWriter::new(Vec::new());
+        writer
+            .write_packet(head, 1, PacketWriteEndInfo::EndPage, 0)
+            .expect("head should be written");
+        writer
+            .write_packet(tags, 1, PacketWriteEndInfo::EndPage, 0)
+            .expect("tags should be written");
+        let mut error = 0;
+        // SAFETY: valid rate, channels and application; the encoder is destroyed below.
+        let encoder = unsafe { opus_encoder_create(48_000, 2, OPUS_APPLICATION_AUDIO, &mut error) };
+        assert_eq!(error, 0);
+        for (index, frame) in pcm.chunks(FRAME * 2).enumerate() {
+            let mut packet = vec![0_u8; 4000];
+            // SAFETY: `frame` holds FRAME stereo frames and `packet` has room for the output.
+            let length = unsafe {
+                opus_encode_float(
+                    encoder,
+                    frame.as_ptr(),
+                    FRAME as i32,
+                    packet.as_mut_ptr(),
+                    packet.len() as i32,
+                )
+            };
+            packet.truncate(usize::try_from(length).expect("frame should encode"));
+            let end = if index + 1 == packets {
+                PacketWriteEndInfo::EndStream
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            let granule = u64::from(PRE_SKIP) + ((index + 1) * FRAME) as u64;
+            writer
+                .write_packet(packet, 1, end, granule)
+                .expect("packet should be written");
+        }
+        // SAFETY: created above and not used afterwards.
+        unsafe { opus_encoder_destroy(encoder) };
+        (writer.into_inner(), rms)
+    }
+
+    #[test]
+    fn ogg_opus_file_decodes_without_pre_skip() {
+        let (file, rms) = encode_sine(25, 0);
+        let path = unique_temp_path("sine.opus");
+        std::fs::write(&path, file).expect("opus should be written");
+
+        let track = decode_file(&path).expect("opus should decode");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!((track.sample_rate, track.channels), (48_000, 2));
+        assert_eq!(track.samples.len(), (25 * FRAME - PRE_SKIP as usize) * 2);
+        let decoded_rms =
+            (track.samples.iter().map(|s| s * s).sum::<f32>() / track.samples.len() as f32).sqrt();
+        assert!(
+            (decoded_rms - rms).abs() < 0.1 * rms,
+            "{decoded_rms} vs {rms}"
+        );
+    }
+
+    #[test]
+    fn opus_output_gain_is_applied() {
+        // -6 dB halves the amplitude.
+        let (file, rms) = encode_sine(25, -6 * 256);
+        let path = unique_temp_path("quiet.opus");
+        std::fs::write(&path, file).expect("opus should be written");
+
+        let track = decode_file(&path).expect("opus should decode");
+        std::fs::remove_file(&path).ok();
+
+        let expected = rms * 10_f32.powf(-6.0 / 20.0);
+        let decoded_rms =
+            (track.samples.iter().map(|s| s * s).sum::<f32>() / track.samples.len() as f32).sqrt();
+        assert!(
+            (decoded_rms - expected).abs() < 0.1 * expected,
+            "{decoded_rms} vs {expected}"
+        );
+    }
+}
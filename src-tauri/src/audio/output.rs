@@ -0,0 +1,270 @@
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+#[cfg(target_os = "windows")]
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SampleFormat, SampleRate, StreamConfig,
+};
+#[cfg(target_os = "windows")]
+use log::warn;
+
+/// Sample encoding the device expects. The engine always renders `f32`; backends
+/// convert when the device only accepts integer samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub enum OutputSampleKind {
+    F32,
+    I16,
+    U16,
+}
+
+/// Stream format negotiated between a track and an output device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_kind: OutputSampleKind,
+    /// True when the device runs at the track's native rate (no resampling needed).
+    pub exact_rate: bool,
+}
+
+/// Fills an interleaved `f32` buffer; invoked from the backend's audio thread.
+pub type RenderCallback = Box<dyn FnMut(&mut [f32]) + Send + 'static>;
+
+/// Keeps a running stream alive; dropping it stops the stream and releases the device.
+pub type StreamGuard = Box<dyn Send>;
+
+/// A destination for rendered audio: a real device, or a virtual sink in tests.
+pub trait OutputBackend {
+    fn device_name(&self) -> String;
+
+    /// Chooses the stream format for a track with the given native rate and channels.
+    fn negotiate(&self, sample_rate: u32, channels: u16) -> Result<OutputFormat, String>;
+
+    /// Starts pulling audio through `render`.
+    fn start(&self, format: &OutputFormat, render: RenderCallback) -> Result<StreamGuard, String>;
+}
+
+/// Default WASAPI device through cpal's default host.
+#[cfg(target_os = "windows")]
+pub struct CpalOutput {
+    device: cpal::Device,
+}
+
+#[cfg(target_os = "windows")]
+impl CpalOutput {
+    pub fn default_device() -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device available".to_string())?;
+        Ok(Self { device })
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl OutputBackend for CpalOutput {
+    fn device_name(&self) -> String {
+        self.device
+            .name()
+            .unwrap_or_else(|_| "Unknown output device".to_string())
+    }
+
+    fn negotiate(&self, sample_rate: u32, channels: u16) -> Result<OutputFormat, String> {
+        let mut preferred: Option<OutputFormat> = None;
+        let mut fallback: Option<OutputFormat> = None;
+
+        let ranges = self
+            .device
+            .supported_output_configs()
+            .map_err(|e| format!("Cannot query output configs: {e}"))?;
+
+        for cfg in ranges {
+            let Some(sample_kind) = sample_kind(cfg.sample_format()) else {
+                continue;
+            };
+            let min = cfg.min_sample_rate().0;
+            let max = cfg.max_sample_rate().0;
+
+            let exact_rate = sample_rate >= min && sample_rate <= max;
+            let f32_preferred = sample_kind == OutputSampleKind::F32;
+
+            if cfg.channels() == channels && exact_rate {
+                let chosen = OutputFormat {
+                    sample_rate,
+                    channels,
+                    sample_kind,
+                    exact_rate: true,
+                };
+                if f32_preferred {
+                    return Ok(chosen);
+                }
+                preferred = Some(chosen);
+            }
+
+            if fallback.is_none() || f32_preferred {
+                fallback = Some(OutputFormat {
+                    sample_rate: max,
+                    channels: cfg.channels(),
+                    sample_kind,
+                    exact_rate: false,
+                });
+            }
+        }
+
+        preferred
+            .or(fallback)
+            .ok_or_else(|| "No output stream configuration available".to_string())
+    }
+
+    fn start(
+        &self,
+        format: &OutputFormat,
+        mut render: RenderCallback,
+    ) -> Result<StreamGuard, String> {
+        let config = StreamConfig {
+            channels: format.channels,
+            sample_rate: SampleRate(format.sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let err_fn = |err| warn!("Audio stream error: {err}");
+        let mut scratch: Vec<f32> = Vec::new();
+        let stream = match format.sample_kind {
+            OutputSampleKind::F32 => self
+                .device
+                .build_output_stream(
+                    &config,
+                    move |output: &mut [f32], _| render(output),
+                    err_fn,
+                    None,
+                )
+                .map_err(|e| format!("Failed to build f32 output stream: {e}"))?,
+            OutputSampleKind::I16 => self
+                .device
+                .build_output_stream(
+                    &config,
+                    move |output: &mut [i16], _| {
+                        scratch.resize(output.len(), 0.0);
+                        render(&mut scratch);
+                        for (out, sample) in output.iter_mut().zip(&scratch) {
+                            *out = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                        }
+                    },
+                    err_fn,
+                    None,
+                )
+                .map_err(|e| format!("Failed to build i16 output stream: {e}"))?,
+            OutputSampleKind::U16 => self
+                .device
+                .build_output_stream(
+                    &config,
+                    move |output: &mut [u16], _| {
+                        scratch.resize(output.len(), 0.0);
+                        render(&mut scratch);
+                        for (out, sample) in output.iter_mut().zip(&scratch) {
+                            *out =
+                                (((sample.clamp(-1.0, 1.0) + 1.0) * 0.5) * u16::MAX as f32) as u16;
+                        }
+                    },
+                    err_fn,
+                    None,
+                )
+                .map_err(|e| format!("Failed to build u16 output stream: {e}"))?,
+        };
+
+        stream
+            .play()
+            .map_err(|e| format!("Failed to start stream: {e}"))?;
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn sample_kind(format: SampleFormat) -> Option<OutputSampleKind> {
+    match format {
+        SampleFormat::F32 => Some(OutputSampleKind::F32),
+        SampleFormat::I16 => Some(OutputSampleKind::I16),
+        SampleFormat::U16 => Some(OutputSampleKind::U16),
+        _ => None,
+    }
+}
+
+/// Deterministic in-memory sink for tests: nothing plays on its own, callers pull
+/// rendered buffers with [`VirtualOutput::pull`]. Lets playback be exercised without
+/// audio hardware (CI, non-Windows hosts).
+#[cfg(test)]
+#[derive(Clone)]
+pub struct VirtualOutput {
+    sample_rate: u32,
+    channels: u16,
+    render: Arc<Mutex<Option<RenderCallback>>>,
+}
+
+#[cfg(test)]
+struct VirtualStream {
+    render: Arc<Mutex<Option<RenderCallback>>>,
+}
+
+#[cfg(test)]
+impl Drop for VirtualStream {
+    fn drop(&mut self) {
+        if let Ok(mut render) = self.render.lock() {
+            render.take();
+        }
+    }
+}
+
+#[cfg(test)]
+impl VirtualOutput {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels: channels.max(1),
+            render: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.render
+            .lock()
+            .map(|render| render.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Renders `frames` interleaved frames, or returns silence when no stream is running.
+    pub fn pull(&self, frames: usize) -> Vec<f32> {
+        let mut buffer = vec![0.0; frames * self.channels as usize];
+        if let Ok(mut render) = self.render.lock() {
+            if let Some(render) = render.as_mut() {
+                render(&mut buffer);
+            }
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+impl OutputBackend for VirtualOutput {
+    fn device_name(&self) -> String {
+        "Virtual output".to_string()
+    }
+
+    fn negotiate(&self, sample_rate: u32, _channels: u16) -> Result<OutputFormat, String> {
+        Ok(OutputFormat {
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            sample_kind: OutputSampleKind::F32,
+            exact_rate: sample_rate == self.sample_rate,
+        })
+    }
+
+    fn start(&self, _format: &OutputFormat, render: RenderCallback) -> Result<StreamGuard, String> {
+        *self
+            .render
+            .lock()
+            .map_err(|_| "Virtual output lock poisoned".to_string())? = Some(render);
+        Ok(Box::new(VirtualStream {
+            render: Arc::clone(&self.render),
+        }))
+    }
+}
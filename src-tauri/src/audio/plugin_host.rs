@@ -0,0 +1,123 @@
+//! What the DSP chain, persistence and IPC layers see of an effect plugin, whichever
+//! format it was loaded from. The format-specific hosts live in `vst3` and `clap`.
+
+use serde::{Deserialize, Serialize};
+
+use super::{clap, vst3};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginFormat {
+    Vst3,
+    Clap,
+}
+
+impl PluginFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginFormat::Vst3 => "vst3",
+            PluginFormat::Clap => "clap",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "vst3" => Ok(PluginFormat::Vst3),
+            "clap" => Ok(PluginFormat::Clap),
+            _ => Err(format!("Unknown plugin format: {value}")),
+        }
+    }
+}
+
+/// An effect class offered by an installed plugin.
+#[derive(Clone, Debug, Serialize)]
+pub struct PluginClassInfo {
+    pub format: PluginFormat,
+    pub path: String,
+    /// Identifies the effect within its module: 32 hex digits for VST3, the plugin ID
+    /// (such as `com.example.reverb`) for CLAP.
+    pub class_id: String,
+    pub name: String,
+    /// `|`-separated categories such as `Fx|EQ`, when the plugin reports them.
+    pub categories: String,
+}
+
+/// A plugin parameter with its current normalized (0.0–1.0) value.
+#[derive(Clone, Debug, Serialize)]
+pub struct PluginParameter {
+    pub id: u32,
+    pub title: String,
+    pub units: String,
+    pub value: f64,
+    pub default_value: f64,
+    /// 0 for continuous parameters, otherwise the number of steps.
+    pub step_count: i32,
+    /// The current value as the plugin displays it.
+    pub display: String,
+}
+
+/// Opaque plugin state. VST3 effects fill both halves; CLAP effects keep everything in
+/// `component`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginState {
+    pub component: Vec<u8>,
+    pub controller: Vec<u8>,
+}
+
+/// One instantiated stereo effect.
+pub trait EffectPlugin: Send {
+    fn format(&self) -> PluginFormat;
+
+    fn name(&self) -> &str;
+
+    fn path(&self) -> &str;
+
+    fn class_id(&self) -> &str;
+
+    fn parameters(&self) -> Vec<PluginParameter>;
+
+    /// Sets a parameter to a normalized value (clamped to 0.0–1.0).
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String>;
+
+    fn state(&self) -> Result<PluginState, String>;
+
+    fn restore_state(&mut self, state: &PluginState) -> Result<(), String>;
+
+    fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), String>;
+
+    /// Runs one block. `input` and `output` hold left and right; blocks longer than the
+    /// size set at load time are passed through untouched, as are failed calls.
+    fn process(&mut self, input: [&mut [f32]; 2], output: [&mut [f32]; 2]);
+}
+
+/// Loads the effect `class_id` from the plugin at `path`, ready for blocks of up to
+/// `max_block` frames.
+pub fn load(
+    format: PluginFormat,
+    path: &str,
+    class_id: &str,
+    sample_rate: f32,
+    max_block: usize,
+) -> Result<Box<dyn EffectPlugin>, String> {
+    Ok(match format {
+        PluginFormat::Vst3 => Box::new(vst3::Vst3Plugin::load(
+            path,
+            class_id,
+            sample_rate,
+            max_block,
+        )?),
+        PluginFormat::Clap => Box::new(clap::ClapPlugin::load(
+            path,
+            class_id,
+            sample_rate,
+            max_block,
+        )?),
+    })
+}
+
+/// Effect classes of every VST3 and CLAP plugin in the standard install folders.
+pub fn scan_installed() -> Vec<PluginClassInfo> {
+    let mut found = vst3::scan(&vst3::default_search_dirs());
+    found.extend(clap::scan(&clap::default_search_dirs()));
+    found
+}
@@ -0,0 +1,382 @@
+use std::io::{self, Read};
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use serde::Serialize;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions},
+    errors::Error,
+    formats::{FormatOptions, FormatReader},
+    io::{MediaSourceStream, ReadOnlySource},
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use super::{codecs, hls};
+use crate::library::network_usage::{self, NetworkProvider};
+
+const STREAM_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// The blocking client applies its timeout to each body read rather than the whole
+/// response, so this only ends a stream whose server has stalled.
+const STREAM_READ_TIMEOUT_SECS: u64 = 15;
+/// Stream bytes are added to the network usage ledger in batches of this size, so a
+/// long listening session does not rewrite the settings row every few milliseconds.
+const USAGE_RECORD_BATCH_BYTES: u64 = 256 * 1024;
+
+/// Station details from the `icy-*` response headers.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct StreamInfo {
+    pub name: Option<String>,
+    pub genre: Option<String>,
+    pub content_type: Option<String>,
+    pub bitrate_kbps: Option<u32>,
+}
+
+/// Now-playing block sent in-band by SHOUTcast/Icecast servers.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct StreamMetadata {
+    pub title: Option<String>,
+    pub url: Option<String>,
+}
+
+type MetadataCallback = Box<dyn FnMut(StreamMetadata) + Send + Sync>;
+
+/// Strips the ICY metadata blocks interleaved every `metaint` bytes of audio and hands
+/// changed now-playing metadata to a callback. Without a `metaint` it passes bytes through.
+pub struct IcyReader<R> {
+    inner: R,
+    metaint: Option<usize>,
+    until_metadata: usize,
+    last_metadata: Option<StreamMetadata>,
+    on_metadata: MetadataCallback,
+    unrecorded_bytes: u64,
+}
+
+impl<R: Read> IcyReader<R> {
+    pub fn new(inner: R, metaint: Option<usize>, on_metadata: MetadataCallback) -> Self {
+        let metaint = metaint.filter(|interval| *interval > 0);
+        Self {
+            inner,
+            metaint,
+            until_metadata: metaint.unwrap_or(0),
+            last_metadata: None,
+            on_metadata,
+            unrecorded_bytes: 0,
+        }
+    }
+
+    fn read_metadata_block(&mut self) -> io::Result<()> {
+        let mut length = [0_u8; 1];
+        self.inner.read_exact(&mut length)?;
+        let mut block = vec![0_u8; length[0] as usize * 16];
+        self.inner.read_exact(&mut block)?;
+        self.count_bytes(1 + block.len());
+        // Most servers send an empty block unless the song changed, but some repeat it.
+        if let Some(metadata) = parse_icy_metadata(&block) {
+            if self.last_metadata.as_ref() != Some(&metadata) {
+                self.last_metadata = Some(metadata.clone());
+                (self.on_metadata)(metadata);
+            }
+        }
+        Ok(())
+    }
+
+    fn count_bytes(&mut self, bytes: usize) {
+        self.unrecorded_bytes += bytes as u64;
+        if self.unrecorded_bytes >= USAGE_RECORD_BATCH_BYTES {
+            network_usage::record_bytes(NetworkProvider::Streams, self.unrecorded_bytes);
+            self.unrecorded_bytes = 0;
+        }
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(metaint) = self.metaint else {
+            let read = self.inner.read(buf)?;
+            self.count_bytes(read);
+            return Ok(read);
+        };
+        if self.until_metadata == 0 {
+            self.read_metadata_block()?;
+            self.until_metadata = metaint;
+        }
+        let limit = buf.len().min(self.until_metadata);
+        let read = self.inner.read(&mut buf[..limit])?;
+        self.until_metadata -= read;
+        self.count_bytes(read);
+        Ok(read)
+    }
+}
+
+impl<R> Drop for IcyReader<R> {
+    fn drop(&mut self) {
+        if self.unrecorded_bytes > 0 {
+            network_usage::record_bytes(NetworkProvider::Streams, self.unrecorded_bytes);
+        }
+    }
+}
+
+/// Parses `StreamTitle='Artist - Song';StreamUrl='...';` (NUL padded). `None` when the
+/// block carries neither field.
+pub fn parse_icy_metadata(block: &[u8]) -> Option<StreamMetadata> {
+    let text = String::from_utf8_lossy(block);
+    let text = text.trim_end_matches('\0');
+    let mut metadata = StreamMetadata::default();
+    // Titles may contain `';` themselves, so fields are split on the next known key.
+    for (key, slot) in [
+        ("StreamTitle='", &mut metadata.title),
+        ("StreamUrl='", &mut metadata.url),
+    ] {
+        let Some(start) = text.find(key).map(|index| index + key.len()) else {
+            continue;
+        };
+        let rest = &text[start..];
+        let end = rest
+            .find("';Stream")
+            .or_else(|| rest.rfind("';"))
+            .or_else(|| rest.rfind('\''))
+            .unwrap_or(rest.len());
+        let value = rest[..end].trim();
+        *slot = (!value.is_empty()).then(|| value.to_string());
+    }
+    (metadata.title.is_some() || metadata.url.is_some()).then_some(metadata)
+}
+
+/// Connects to an HTTP(S) radio stream, asking the server for in-band ICY metadata, or
+/// to an HLS playlist (see `audio::hls`). Only servers that answer with a regular
+/// `HTTP/1.x` status line are supported; legacy SHOUTcast v1 servers replying
+/// `ICY 200 OK` are rejected by the HTTP client.
+pub fn open_stream(
+    url: &str,
+    on_metadata: MetadataCallback,
+) -> Result<(Box<dyn Read + Send + Sync>, StreamInfo), String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Unsupported stream URL: {url}"));
+    }
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(STREAM_CONNECT_TIMEOUT_SECS))
+        .timeout(Duration::from_secs(STREAM_READ_TIMEOUT_SECS))
+        .user_agent("PowerPlayer/0.1")
+        .build()
+        .map_err(|e| format!("Failed to build stream HTTP client: {e}"))?;
+    let response = client
+        .get(url)
+        .header("Icy-MetaData", "1")
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to open stream {url}: {e}"))?;
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    if hls::is_hls_url(url)
+        || header("content-type")
+            .is_some_and(|content_type| hls::is_hls_content_type(&content_type))
+    {
+        let playlist_url = response.url().clone();
+        let playlist = response
+            .bytes()
+            .map_err(|e| format!("Failed to read HLS playlist {url}: {e}"))?;
+        network_usage::record(NetworkProvider::Streams, playlist.len() as u64);
+        let (reader, info) =
+            hls::open_hls(client, playlist_url, &String::from_utf8_lossy(&playlist))?;
+        return Ok((Box::new(reader), info));
+    }
+    network_usage::record(NetworkProvider::Streams, 0);
+
+    let info = StreamInfo {
+        name: header("icy-name"),
+        genre: header("icy-genre"),
+        content_type: header("content-type"),
+        bitrate_kbps: header("icy-br").and_then(|value| {
+            // Some servers send "128, 128"; the first value is the stream bitrate.
+            value.split(',').next()?.trim().parse().ok()
+        }),
+    };
+    let metaint = header("icy-metaint").and_then(|value| value.parse().ok());
+    Ok((
+        Box::new(IcyReader::new(response, metaint, on_metadata)),
+        info,
+    ))
+}
+
+/// Symphonia decoder over an endless byte stream, yielding one packet of interleaved
+/// samples at a time instead of decoding the whole source like `decode_file`.
+pub struct StreamDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: u16,
+    /// Samples of the first packet, decoded up front to learn the stream format.
+    first_packet: Option<Vec<f32>>,
+}
+
+impl StreamDecoder {
+    pub fn new(
+        source: impl Read + Send + Sync + 'static,
+        content_type: Option<&str>,
+    ) -> Result<Self, String> {
+        let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(source)), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = content_type.and_then(extension_for_content_type) {
+            hint.with_extension(extension);
+        }
+        let probed = codecs::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| format!("Stream format probe failed: {e}"))?;
+        let format = probed.format;
+        let track = format
+            .default_track()
+            .ok_or_else(|| "No audio track found in stream".to_string())?;
+        let track_id = track.id;
+        let decoder = codecs::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("Decoder creation failed: {e}"))?;
+
+        let mut stream = Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate: 0,
+            channels: 0,
+            first_packet: None,
+        };
+        stream.first_packet = Some(
+            stream
+                .decode_next()?
+                .ok_or_else(|| "Stream ended before any audio arrived".to_string())?,
+        );
+        Ok(stream)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Interleaved samples of the next packet; `None` once the server closes the stream.
+    pub fn next_samples(&mut self) -> Result<Option<Vec<f32>>, String> {
+        if let Some(samples) = self.first_packet.take() {
+            return Ok(Some(samples));
+        }
+        self.decode_next()
+    }
+
+    fn decode_next(&mut self) -> Result<Option<Vec<f32>>, String> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(_)) => return Ok(None),
+                Err(Error::ResetRequired) => {
+                    return Err("Decoder reset required; unsupported stream transition".to_string())
+                }
+                Err(err) => return Err(format!("Error reading stream packet: {err}")),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A frame damaged in transit is dropped; the next one resynchronizes.
+                Err(Error::DecodeError(_)) => continue,
+                Err(err) => return Err(format!("Decode failure: {err}")),
+            };
+            if decoded.frames() == 0 {
+                continue;
+            }
+            let spec = *decoded.spec();
+            self.sample_rate = spec.rate;
+            self.channels = spec.channels.count() as u16;
+            // Streams may change format between songs, so the buffer is not reused.
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            return Ok(Some(buffer.samples().to_vec()));
+        }
+    }
+}
+
+pub(crate) fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match mime.as_str() {
+        "audio/mpeg" | "audio/mp3" | "audio/mpeg3" => Some("mp3"),
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("aac"),
+        "audio/ogg" | "application/ogg" => Some("ogg"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        "audio/opus" => Some("opus"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_icy_metadata, IcyReader, StreamMetadata};
+    use std::io::{Cursor, Read};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn parses_icy_metadata_blocks() {
+        let block = b"StreamTitle='Daft Punk - Da Funk';StreamUrl='http://example.com';\0\0\0";
+        assert_eq!(
+            parse_icy_metadata(block),
+            Some(StreamMetadata {
+                title: Some("Daft Punk - Da Funk".to_string()),
+                url: Some("http://example.com".to_string()),
+            })
+        );
+        let tricky = parse_icy_metadata(b"StreamTitle='Rock';n';Roll';").expect("title");
+        assert_eq!(tricky.title.as_deref(), Some("Rock';n';Roll"));
+        assert_eq!(parse_icy_metadata(b"StreamTitle='';\0"), None);
+        assert_eq!(parse_icy_metadata(&[0; 16]), None);
+    }
+
+    #[test]
+    fn icy_reader_strips_metadata_between_audio() {
+        let mut stream = Vec::new();
+        let title = b"StreamTitle='One';";
+        let mut block = title.to_vec();
+        block.resize(32, 0);
+        for chunk in [b"abcd", b"efgh", b"ijkl"] {
+            stream.extend_from_slice(chunk);
+            stream.push((block.len() / 16) as u8);
+            stream.extend_from_slice(&block);
+        }
+        stream.extend_from_slice(b"mn");
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let mut reader = IcyReader::new(
+            Cursor::new(stream),
+            Some(4),
+            Box::new(move |metadata| sink.lock().expect("lock").push(metadata)),
+        );
+        let mut audio = Vec::new();
+        reader.read_to_end(&mut audio).expect("read");
+        assert_eq!(audio, b"abcdefghijklmn");
+        // The repeated block only reports the title once.
+        let seen = seen.lock().expect("lock");
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].title.as_deref(), Some("One"));
+    }
+}
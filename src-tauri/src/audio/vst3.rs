@@ -0,0 +1,1365 @@
+//! Minimal VST3 host. Loads plugin modules, instantiates their audio effects through the
+//! COM-style interfaces of the VST3 SDK and runs them on stereo blocks. Only what an
+//! insert effect needs is implemented: no editors, note events, sidechains or
+//! component/controller message connections.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::path::{Path, PathBuf};
+use std::ptr::{self, NonNull};
+
+use libloading::Library;
+use walkdir::WalkDir;
+
+use super::plugin_host::{
+    EffectPlugin, PluginClassInfo, PluginFormat, PluginParameter, PluginState,
+};
+
+type TResult = i32;
+type Tuid = [u8; 16];
+type ParamId = u32;
+type String128 = [u16; 128];
+
+const RESULT_OK: TResult = 0;
+const RESULT_FALSE: TResult = 1;
+#[cfg(target_os = "windows")]
+const NO_INTERFACE: TResult = 0x8000_4002_u32 as i32;
+#[cfg(not(target_os = "windows"))]
+const NO_INTERFACE: TResult = -1;
+#[cfg(target_os = "windows")]
+const INVALID_ARGUMENT: TResult = 0x8007_0057_u32 as i32;
+#[cfg(not(target_os = "windows"))]
+const INVALID_ARGUMENT: TResult = 2;
+
+const MEDIA_AUDIO: i32 = 0;
+const DIRECTION_INPUT: i32 = 0;
+const DIRECTION_OUTPUT: i32 = 1;
+const SPEAKER_STEREO: u64 = 0x3;
+const SAMPLE_32: i32 = 0;
+const PROCESS_REALTIME: i32 = 0;
+const SEEK_SET: i32 = 0;
+const SEEK_CUR: i32 = 1;
+const SEEK_END: i32 = 2;
+
+/// Category of the factory classes that are audio processors.
+const AUDIO_MODULE_CLASS: &str = "Audio Module Class";
+
+const FUNKNOWN_IID: Tuid = uid(0x0000_0000, 0x0000_0000, 0xC000_0000, 0x0000_0046);
+const PLUGIN_FACTORY2_IID: Tuid = uid(0x0007_B650, 0xF24B_4C0B, 0xA464_EDB9, 0xF00B_2ABB);
+const COMPONENT_IID: Tuid = uid(0xE831_FF31, 0xF2D5_4301, 0x928E_BBEE, 0x2569_7802);
+const AUDIO_PROCESSOR_IID: Tuid = uid(0x4204_3F99, 0xB7DA_453C, 0xA569_E79D, 0x9AAE_C33D);
+const EDIT_CONTROLLER_IID: Tuid = uid(0xDCD7_BBE3, 0x7742_448D, 0xA874_AACC, 0x979C_759E);
+const BSTREAM_IID: Tuid = uid(0xC3BF_6EA2, 0x3099_4752, 0x9B6B_F990, 0x1EE3_3E9B);
+const HOST_APPLICATION_IID: Tuid = uid(0x58E5_95CC, 0xDB2D_4969, 0x8B6A_AF8C, 0x36A6_64E5);
+const PARAM_VALUE_QUEUE_IID: Tuid = uid(0x0126_3A18, 0xED07_4F6F, 0x98C9_D356, 0x4686_F9BA);
+
+/// Interface IDs as the SDK lays them out: COM GUID byte order on Windows, big-endian
+/// everywhere else.
+const fn uid(l1: u32, l2: u32, l3: u32, l4: u32) -> Tuid {
+    let (a, b, c, d) = (
+        l1.to_be_bytes(),
+        l2.to_be_bytes(),
+        l3.to_be_bytes(),
+        l4.to_be_bytes(),
+    );
+    if cfg!(target_os = "windows") {
+        [
+            a[3], a[2], a[1], a[0], b[1], b[0], b[3], b[2], c[0], c[1], c[2], c[3], d[0], d[1],
+            d[2], d[3],
+        ]
+    } else {
+        [
+            a[0], a[1], a[2], a[3], b[0], b[1], b[2], b[3], c[0], c[1], c[2], c[3], d[0], d[1],
+            d[2], d[3],
+        ]
+    }
+}
+
+#[repr(C)]
+struct FUnknownVtbl {
+    query_interface:
+        unsafe extern "system" fn(*mut c_void, *const Tuid, *mut *mut c_void) -> TResult,
+    add_ref: unsafe extern "system" fn(*mut c_void) -> u32,
+    release: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+
+#[repr(C)]
+struct PluginBaseVtbl {
+    unknown: FUnknownVtbl,
+    initialize: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    terminate: unsafe extern "system" fn(*mut c_void) -> TResult,
+}
+
+#[repr(C)]
+struct PluginFactoryVtbl {
+    unknown: FUnknownVtbl,
+    get_factory_info: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    count_classes: unsafe extern "system" fn(*mut c_void) -> i32,
+    get_class_info: unsafe extern "system" fn(*mut c_void, i32, *mut ClassInfo) -> TResult,
+    create_instance: unsafe extern "system" fn(
+        *mut c_void,
+        *const c_char,
+        *const c_char,
+        *mut *mut c_void,
+    ) -> TResult,
+    get_class_info2: unsafe extern "system" fn(*mut c_void, i32, *mut ClassInfo2) -> TResult,
+}
+
+#[repr(C)]
+struct ComponentVtbl {
+    base: PluginBaseVtbl,
+    get_controller_class_id: unsafe extern "system" fn(*mut c_void, *mut Tuid) -> TResult,
+    set_io_mode: unsafe extern "system" fn(*mut c_void, i32) -> TResult,
+    get_bus_count: unsafe extern "system" fn(*mut c_void, i32, i32) -> i32,
+    get_bus_info: unsafe extern "system" fn(*mut c_void, i32, i32, i32, *mut c_void) -> TResult,
+    get_routing_info: unsafe extern "system" fn(*mut c_void, *mut c_void, *mut c_void) -> TResult,
+    activate_bus: unsafe extern "system" fn(*mut c_void, i32, i32, i32, u8) -> TResult,
+    set_active: unsafe extern "system" fn(*mut c_void, u8) -> TResult,
+    set_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    get_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+}
+
+#[repr(C)]
+struct AudioProcessorVtbl {
+    unknown: FUnknownVtbl,
+    set_bus_arrangements:
+        unsafe extern "system" fn(*mut c_void, *mut u64, i32, *mut u64, i32) -> TResult,
+    get_bus_arrangement: unsafe extern "system" fn(*mut c_void, i32, i32, *mut u64) -> TResult,
+    can_process_sample_size: unsafe extern "system" fn(*mut c_void, i32) -> TResult,
+    get_latency_samples: unsafe extern "system" fn(*mut c_void) -> u32,
+    setup_processing: unsafe extern "system" fn(*mut c_void, *mut ProcessSetup) -> TResult,
+    set_processing: unsafe extern "system" fn(*mut c_void, u8) -> TResult,
+    process: unsafe extern "system" fn(*mut c_void, *mut ProcessData) -> TResult,
+    get_tail_samples: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+
+/// Only the part of `IEditController` the host calls; the editor methods that follow it
+/// in the real vtable are never reached.
+#[repr(C)]
+struct EditControllerVtbl {
+    base: PluginBaseVtbl,
+    set_component_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    set_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    get_state: unsafe extern "system" fn(*mut c_void, *mut c_void) -> TResult,
+    get_parameter_count: unsafe extern "system" fn(*mut c_void) -> i32,
+    get_parameter_info: unsafe extern "system" fn(*mut c_void, i32, *mut ParameterInfo) -> TResult,
+    get_param_string_by_value:
+        unsafe extern "system" fn(*mut c_void, ParamId, f64, *mut String128) -> TResult,
+    get_param_value_by_string:
+        unsafe extern "system" fn(*mut c_void, ParamId, *mut u16, *mut f64) -> TResult,
+    normalized_param_to_plain: unsafe extern "system" fn(*mut c_void, ParamId, f64) -> f64,
+    plain_param_to_normalized: unsafe extern "system" fn(*mut c_void, ParamId, f64) -> f64,
+    get_param_normalized: unsafe extern "system" fn(*mut c_void, ParamId) -> f64,
+    set_param_normalized: unsafe extern "system" fn(*mut c_void, ParamId, f64) -> TResult,
+}
+
+#[repr(C)]
+struct ClassInfo {
+    cid: Tuid,
+    cardinality: i32,
+    category: [c_char; 32],
+    name: [c_char; 64],
+}
+
+#[repr(C)]
+struct ClassInfo2 {
+    cid: Tuid,
+    cardinality: i32,
+    category: [c_char; 32],
+    name: [c_char; 64],
+    class_flags: u32,
+    sub_categories: [c_char; 128],
+    vendor: [c_char; 64],
+    version: [c_char; 64],
+    sdk_version: [c_char; 64],
+}
+
+#[repr(C)]
+struct ParameterInfo {
+    id: ParamId,
+    title: String128,
+    short_title: String128,
+    units: String128,
+    step_count: i32,
+    default_normalized_value: f64,
+    unit_id: i32,
+    flags: i32,
+}
+
+#[repr(C)]
+struct ProcessSetup {
+    process_mode: i32,
+    symbolic_sample_size: i32,
+    max_samples_per_block: i32,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+struct AudioBusBuffers {
+    num_channels: i32,
+    silence_flags: u64,
+    channel_buffers: *mut *mut f32,
+}
+
+#[repr(C)]
+struct ProcessData {
+    process_mode: i32,
+    symbolic_sample_size: i32,
+    num_samples: i32,
+    num_inputs: i32,
+    num_outputs: i32,
+    inputs: *mut AudioBusBuffers,
+    outputs: *mut AudioBusBuffers,
+    input_parameter_changes: *mut c_void,
+    output_parameter_changes: *mut c_void,
+    input_events: *mut c_void,
+    output_events: *mut c_void,
+    process_context: *mut c_void,
+}
+
+/// Owned reference to a plugin interface whose vtable is `V`.
+struct ComPtr<V> {
+    ptr: NonNull<*const V>,
+}
+
+impl<V> ComPtr<V> {
+    /// Takes over one reference to `raw`, which must implement the interface of `V`.
+    unsafe fn from_raw(raw: *mut c_void) -> Option<Self> {
+        NonNull::new(raw.cast()).map(|ptr| Self { ptr })
+    }
+
+    fn as_raw(&self) -> *mut c_void {
+        self.ptr.as_ptr().cast()
+    }
+
+    fn vtbl(&self) -> &V {
+        // SAFETY: a live interface pointer always points at its vtable pointer.
+        unsafe { &**self.ptr.as_ptr() }
+    }
+
+    fn unknown(&self) -> &FUnknownVtbl {
+        // SAFETY: every VST3 vtable starts with the FUnknown methods.
+        unsafe { &*(*self.ptr.as_ptr()).cast::<FUnknownVtbl>() }
+    }
+
+    fn query<W>(&self, iid: &Tuid) -> Option<ComPtr<W>> {
+        let mut raw = ptr::null_mut();
+        // SAFETY: on success the plugin hands us a new reference of the requested type.
+        unsafe {
+            if (self.unknown().query_interface)(self.as_raw(), iid, &mut raw) != RESULT_OK {
+                return None;
+            }
+            ComPtr::from_raw(raw)
+        }
+    }
+}
+
+impl<V> Drop for ComPtr<V> {
+    fn drop(&mut self) {
+        // SAFETY: releases the reference taken over in `from_raw`.
+        unsafe {
+            (self.unknown().release)(self.as_raw());
+        }
+    }
+}
+
+/// Host objects are owned by the host, so their reference counting is a formality.
+unsafe extern "system" fn host_add_ref(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "system" fn host_release(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe fn host_query(
+    this: *mut c_void,
+    iid: *const Tuid,
+    obj: *mut *mut c_void,
+    own_iid: &Tuid,
+) -> TResult {
+    if iid.is_null() || obj.is_null() {
+        return INVALID_ARGUMENT;
+    }
+    if *iid == FUNKNOWN_IID || *iid == *own_iid {
+        *obj = this;
+        RESULT_OK
+    } else {
+        *obj = ptr::null_mut();
+        NO_INTERFACE
+    }
+}
+
+#[repr(C)]
+struct HostApplicationVtbl {
+    unknown: FUnknownVtbl,
+    get_name: unsafe extern "system" fn(*mut c_void, *mut String128) -> TResult,
+    create_instance:
+        unsafe extern "system" fn(*mut c_void, *mut Tuid, *mut Tuid, *mut *mut c_void) -> TResult,
+}
+
+/// The `IHostApplication` context passed to `initialize`; shared by every plugin.
+#[repr(C)]
+struct HostApplication {
+    vtbl: &'static HostApplicationVtbl,
+}
+
+static HOST_APPLICATION_VTBL: HostApplicationVtbl = HostApplicationVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: host_application_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    get_name: host_application_name,
+    create_instance: host_application_create_instance,
+};
+
+static HOST_APPLICATION: HostApplication = HostApplication {
+    vtbl: &HOST_APPLICATION_VTBL,
+};
+
+fn host_context() -> *mut c_void {
+    ptr::addr_of!(HOST_APPLICATION).cast_mut().cast()
+}
+
+unsafe extern "system" fn host_application_query(
+    this: *mut c_void,
+    iid: *const Tuid,
+    obj: *mut *mut c_void,
+) -> TResult {
+    host_query(this, iid, obj, &HOST_APPLICATION_IID)
+}
+
+unsafe extern "system" fn host_application_name(
+    _this: *mut c_void,
+    name: *mut String128,
+) -> TResult {
+    if name.is_null() {
+        return INVALID_ARGUMENT;
+    }
+    write_string128(&mut *name, "PowerPlayer");
+    RESULT_OK
+}
+
+/// The host offers no objects (such as `IMessage`) to plugins.
+unsafe extern "system" fn host_application_create_instance(
+    _this: *mut c_void,
+    _cid: *mut Tuid,
+    _iid: *mut Tuid,
+    obj: *mut *mut c_void,
+) -> TResult {
+    if !obj.is_null() {
+        *obj = ptr::null_mut();
+    }
+    NO_INTERFACE
+}
+
+#[repr(C)]
+struct StreamVtbl {
+    unknown: FUnknownVtbl,
+    read: unsafe extern "system" fn(*mut c_void, *mut c_void, i32, *mut i32) -> TResult,
+    write: unsafe extern "system" fn(*mut c_void, *mut c_void, i32, *mut i32) -> TResult,
+    seek: unsafe extern "system" fn(*mut c_void, i64, i32, *mut i64) -> TResult,
+    tell: unsafe extern "system" fn(*mut c_void, *mut i64) -> TResult,
+}
+
+/// In-memory `IBStream` used to move plugin state in and out.
+#[repr(C)]
+struct MemoryStream {
+    vtbl: &'static StreamVtbl,
+    data: Vec<u8>,
+    position: usize,
+}
+
+static STREAM_VTBL: StreamVtbl = StreamVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: stream_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    read: stream_read,
+    write: stream_write,
+    seek: stream_seek,
+    tell: stream_tell,
+};
+
+impl MemoryStream {
+    fn new(data: Vec<u8>) -> Box<Self> {
+        Box::new(Self {
+            vtbl: &STREAM_VTBL,
+            data,
+            position: 0,
+        })
+    }
+
+    fn as_raw(&mut self) -> *mut c_void {
+        (self as *mut Self).cast()
+    }
+}
+
+unsafe extern "system" fn stream_query(
+    this: *mut c_void,
+    iid: *const Tuid,
+    obj: *mut *mut c_void,
+) -> TResult {
+    host_query(this, iid, obj, &BSTREAM_IID)
+}
+
+unsafe extern "system" fn stream_read(
+    this: *mut c_void,
+    buffer: *mut c_void,
+    num_bytes: i32,
+    num_bytes_read: *mut i32,
+) -> TResult {
+    let stream = &mut *this.cast::<MemoryStream>();
+    if buffer.is_null() || num_bytes < 0 {
+        return INVALID_ARGUMENT;
+    }
+    let available = stream.data.len().saturating_sub(stream.position);
+    let count = (num_bytes as usize).min(available);
+    ptr::copy_nonoverlapping(
+        stream.data.as_ptr().add(stream.position),
+        buffer.cast::<u8>(),
+        count,
+    );
+    stream.position += count;
+    if !num_bytes_read.is_null() {
+        *num_bytes_read = count as i32;
+    }
+    RESULT_OK
+}
+
+unsafe extern "system" fn stream_write(
+    this: *mut c_void,
+    buffer: *mut c_void,
+    num_bytes: i32,
+    num_bytes_written: *mut i32,
+) -> TResult {
+    let stream = &mut *this.cast::<MemoryStream>();
+    if buffer.is_null() || num_bytes < 0 {
+        return INVALID_ARGUMENT;
+    }
+    let count = num_bytes as usize;
+    let end = stream.position + count;
+    if stream.data.len() < end {
+        stream.data.resize(end, 0);
+    }
+    ptr::copy_nonoverlapping(
+        buffer.cast::<u8>(),
+        stream.data.as_mut_ptr().add(stream.position),
+        count,
+    );
+    stream.position = end;
+    if !num_bytes_written.is_null() {
+        *num_bytes_written = num_bytes;
+    }
+    RESULT_OK
+}
+
+unsafe extern "system" fn stream_seek(
+    this: *mut c_void,
+    pos: i64,
+    mode: i32,
+    result: *mut i64,
+) -> TResult {
+    let stream = &mut *this.cast::<MemoryStream>();
+    let base = match mode {
+        SEEK_SET => 0,
+        SEEK_CUR => stream.position as i64,
+        SEEK_END => stream.data.len() as i64,
+        _ => return INVALID_ARGUMENT,
+    };
+    let Some(target) = base.checked_add(pos).filter(|target| *target >= 0) else {
+        return INVALID_ARGUMENT;
+    };
+    stream.position = target as usize;
+    if !result.is_null() {
+        *result = target;
+    }
+    RESULT_OK
+}
+
+unsafe extern "system" fn stream_tell(this: *mut c_void, pos: *mut i64) -> TResult {
+    let stream = &*this.cast::<MemoryStream>();
+    if pos.is_null() {
+        return INVALID_ARGUMENT;
+    }
+    *pos = stream.position as i64;
+    RESULT_OK
+}
+
+#[repr(C)]
+struct ParamValueQueueVtbl {
+    unknown: FUnknownVtbl,
+    get_parameter_id: unsafe extern "system" fn(*mut c_void) -> ParamId,
+    get_point_count: unsafe extern "system" fn(*mut c_void) -> i32,
+    get_point: unsafe extern "system" fn(*mut c_void, i32, *mut i32, *mut f64) -> TResult,
+    add_point: unsafe extern "system" fn(*mut c_void, i32, f64, *mut i32) -> TResult,
+}
+
+/// One parameter change, delivered at the start of the next block.
+#[repr(C)]
+struct ParamValueQueue {
+    vtbl: &'static ParamValueQueueVtbl,
+    id: ParamId,
+    value: f64,
+}
+
+static PARAM_VALUE_QUEUE_VTBL: ParamValueQueueVtbl = ParamValueQueueVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: param_value_queue_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    get_parameter_id: param_value_queue_id,
+    get_point_count: param_value_queue_point_count,
+    get_point: param_value_queue_point,
+    add_point: param_value_queue_add_point,
+};
+
+unsafe extern "system" fn param_value_queue_query(
+    this: *mut c_void,
+    iid: *const Tuid,
+    obj: *mut *mut c_void,
+) -> TResult {
+    host_query(this, iid, obj, &PARAM_VALUE_QUEUE_IID)
+}
+
+unsafe extern "system" fn param_value_queue_id(this: *mut c_void) -> ParamId {
+    (*this.cast::<ParamValueQueue>()).id
+}
+
+unsafe extern "system" fn param_value_queue_point_count(_this: *mut c_void) -> i32 {
+    1
+}
+
+unsafe extern "system" fn param_value_queue_point(
+    this: *mut c_void,
+    index: i32,
+    sample_offset: *mut i32,
+    value: *mut f64,
+) -> TResult {
+    if index != 0 || sample_offset.is_null() || value.is_null() {
+        return INVALID_ARGUMENT;
+    }
+    *sample_offset = 0;
+    *value = (*this.cast::<ParamValueQueue>()).value;
+    RESULT_OK
+}
+
+unsafe extern "system" fn param_value_queue_add_point(
+    _this: *mut c_void,
+    _sample_offset: i32,
+    _value: f64,
+    _index: *mut i32,
+) -> TResult {
+    RESULT_FALSE
+}
+
+#[repr(C)]
+struct ParameterChangesVtbl {
+    unknown: FUnknownVtbl,
+    get_parameter_count: unsafe extern "system" fn(*mut c_void) -> i32,
+    get_parameter_data: unsafe extern "system" fn(*mut c_void, i32) -> *mut c_void,
+    add_parameter_data:
+        unsafe extern "system" fn(*mut c_void, *const ParamId, *mut i32) -> *mut c_void,
+}
+
+/// Parameter changes made since the last block, handed to `process` as its input.
+#[repr(C)]
+struct ParameterChanges {
+    vtbl: &'static ParameterChangesVtbl,
+    queues: Vec<ParamValueQueue>,
+}
+
+static PARAMETER_CHANGES_VTBL: ParameterChangesVtbl = ParameterChangesVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: parameter_changes_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    get_parameter_count: parameter_changes_count,
+    get_parameter_data: parameter_changes_data,
+    add_parameter_data: parameter_changes_add,
+};
+
+impl ParameterChanges {
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            vtbl: &PARAMETER_CHANGES_VTBL,
+            queues: Vec::new(),
+        })
+    }
+
+    fn push(&mut self, id: ParamId, value: f64) {
+        match self.queues.iter_mut().find(|queue| queue.id == id) {
+            Some(queue) => queue.value = value,
+            None => self.queues.push(ParamValueQueue {
+                vtbl: &PARAM_VALUE_QUEUE_VTBL,
+                id,
+                value,
+            }),
+        }
+    }
+
+    fn as_raw(&mut self) -> *mut c_void {
+        (self as *mut Self).cast()
+    }
+}
+
+unsafe extern "system" fn parameter_changes_query(
+    this: *mut c_void,
+    iid: *const Tuid,
+    obj: *mut *mut c_void,
+) -> TResult {
+    host_query(this, iid, obj, &FUNKNOWN_IID)
+}
+
+unsafe extern "system" fn parameter_changes_count(this: *mut c_void) -> i32 {
+    (*this.cast::<ParameterChanges>()).queues.len() as i32
+}
+
+unsafe extern "system" fn parameter_changes_data(this: *mut c_void, index: i32) -> *mut c_void {
+    let changes = &mut *this.cast::<ParameterChanges>();
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| changes.queues.get_mut(index))
+        .map_or(ptr::null_mut(), |queue| {
+            (queue as *mut ParamValueQueue).cast()
+        })
+}
+
+/// Input changes are read-only for the plugin.
+unsafe extern "system" fn parameter_changes_add(
+    _this: *mut c_void,
+    _id: *const ParamId,
+    _index: *mut i32,
+) -> *mut c_void {
+    ptr::null_mut()
+}
+
+/// A loaded plugin binary and its class factory.
+struct Module {
+    factory: Option<ComPtr<PluginFactoryVtbl>>,
+    library: Option<Library>,
+}
+
+impl Module {
+    fn load(bundle: &Path) -> Result<Self, String> {
+        let binary = module_binary(bundle)?;
+        // SAFETY: loading runs the plugin's initializers; plugins are code the user
+        // installed and chose to load.
+        let library = unsafe { Library::new(&binary) }
+            .map_err(|e| format!("Failed to load VST3 module {}: {e}", binary.display()))?;
+        // SAFETY: the entry points have the signatures the VST3 SDK exports.
+        unsafe {
+            #[cfg(target_os = "windows")]
+            if let Ok(init) = library.get::<unsafe extern "system" fn() -> bool>(b"InitDll\0") {
+                if !init() {
+                    return Err(format!(
+                        "VST3 module {} failed to initialize",
+                        bundle.display()
+                    ));
+                }
+            }
+            #[cfg(target_os = "linux")]
+            {
+                let entry = library
+                    .get::<unsafe extern "C" fn(*mut c_void) -> bool>(b"ModuleEntry\0")
+                    .map_err(|e| {
+                        format!("VST3 module {} has no ModuleEntry: {e}", bundle.display())
+                    })?;
+                if !entry(ptr::null_mut()) {
+                    return Err(format!(
+                        "VST3 module {} failed to initialize",
+                        bundle.display()
+                    ));
+                }
+            }
+            let get_factory = library
+                .get::<unsafe extern "system" fn() -> *mut c_void>(b"GetPluginFactory\0")
+                .map_err(|e| format!("VST3 module {} has no factory: {e}", bundle.display()))?;
+            let factory = ComPtr::from_raw(get_factory());
+            let module = Self {
+                factory,
+                library: Some(library),
+            };
+            if module.factory.is_none() {
+                return Err(format!(
+                    "VST3 module {} returned no factory",
+                    bundle.display()
+                ));
+            }
+            Ok(module)
+        }
+    }
+
+    fn factory(&self) -> &ComPtr<PluginFactoryVtbl> {
+        self.factory.as_ref().expect("factory is set until drop")
+    }
+
+    fn classes(&self) -> Vec<(Tuid, String, String, String)> {
+        let factory = self.factory();
+        let factory2 = factory.query::<PluginFactoryVtbl>(&PLUGIN_FACTORY2_IID);
+        // SAFETY: class indices come from `count_classes` and the info structs match the SDK.
+        let count = unsafe { (factory.vtbl().count_classes)(factory.as_raw()) };
+        (0..count)
+            .filter_map(|index| unsafe {
+                if let Some(factory2) = &factory2 {
+                    let mut info: ClassInfo2 = std::mem::zeroed();
+                    if (factory2.vtbl().get_class_info2)(factory2.as_raw(), index, &mut info)
+                        == RESULT_OK
+                    {
+                        return Some((
+                            info.cid,
+                            c_string(&info.category),
+                            c_string(&info.name),
+                            c_string(&info.sub_categories),
+                        ));
+                    }
+                }
+                let mut info: ClassInfo = std::mem::zeroed();
+                ((factory.vtbl().get_class_info)(factory.as_raw(), index, &mut info) == RESULT_OK)
+                    .then(|| {
+                        (
+                            info.cid,
+                            c_string(&info.category),
+                            c_string(&info.name),
+                            String::new(),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    fn create<V>(&self, cid: &Tuid, iid: &Tuid) -> Option<ComPtr<V>> {
+        let factory = self.factory();
+        let mut raw = ptr::null_mut();
+        // SAFETY: the factory fills `raw` with a new reference of the requested interface.
+        unsafe {
+            let result = (factory.vtbl().create_instance)(
+                factory.as_raw(),
+                cid.as_ptr().cast(),
+                iid.as_ptr().cast(),
+                &mut raw,
+            );
+            if result != RESULT_OK {
+                return None;
+            }
+            ComPtr::from_raw(raw)
+        }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        self.factory = None;
+        let Some(library) = self.library.take() else {
+            return;
+        };
+        // SAFETY: every object from this module has been released by now.
+        unsafe {
+            #[cfg(target_os = "windows")]
+            if let Ok(exit) = library.get::<unsafe extern "system" fn() -> bool>(b"ExitDll\0") {
+                exit();
+            }
+            #[cfg(target_os = "linux")]
+            if let Ok(exit) = library.get::<unsafe extern "C" fn() -> bool>(b"ModuleExit\0") {
+                exit();
+            }
+        }
+        drop(library);
+    }
+}
+
+/// Path of the shared library inside a `.vst3` bundle (or the file itself for the old
+/// single-file layout on Windows).
+fn module_binary(bundle: &Path) -> Result<PathBuf, String> {
+    if bundle.is_file() && cfg!(target_os = "windows") {
+        return Ok(bundle.to_path_buf());
+    }
+    let stem = bundle
+        .file_stem()
+        .ok_or_else(|| format!("Invalid VST3 bundle path: {}", bundle.display()))?;
+    let arch = match std::env::consts::ARCH {
+        "aarch64" if cfg!(target_os = "windows") => "arm64",
+        arch => arch,
+    };
+    let binary = if cfg!(target_os = "windows") {
+        bundle
+            .join("Contents")
+            .join(format!("{arch}-win"))
+            .join(bundle.file_name().unwrap_or(stem))
+    } else if cfg!(target_os = "linux") {
+        bundle
+            .join("Contents")
+            .join(format!("{arch}-linux"))
+            .join(Path::new(stem).with_extension("so"))
+    } else {
+        return Err("VST3 plugins are only supported on Windows and Linux".to_string());
+    };
+    if !binary.is_file() {
+        return Err(format!(
+            "VST3 bundle {} has no binary for this platform",
+            bundle.display()
+        ));
+    }
+    Ok(binary)
+}
+
+/// Standard VST3 install folders for this platform.
+pub fn default_search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "windows") {
+        if let Some(common) = std::env::var_os("CommonProgramFiles") {
+            dirs.push(PathBuf::from(common).join("VST3"));
+        }
+        if let Some(local) = dirs::data_local_dir() {
+            dirs.push(local.join("Programs").join("Common").join("VST3"));
+        }
+    } else {
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home.join(".vst3"));
+        }
+        dirs.push(PathBuf::from("/usr/lib/vst3"));
+        dirs.push(PathBuf::from("/usr/local/lib/vst3"));
+    }
+    dirs
+}
+
+/// Effect classes of every `.vst3` bundle under `dirs`. Modules that fail to load are
+/// skipped; instruments are left out since they take no audio input.
+pub fn scan(dirs: &[PathBuf]) -> Vec<PluginClassInfo> {
+    let mut bundles = Vec::new();
+    for dir in dirs {
+        let mut entries = WalkDir::new(dir).follow_links(true).into_iter();
+        while let Some(entry) = entries.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let is_bundle = entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("vst3"));
+            if !is_bundle {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                entries.skip_current_dir();
+            }
+            bundles.push(entry.into_path());
+        }
+    }
+
+    let mut found = Vec::new();
+    for bundle in bundles {
+        let module = match Module::load(&bundle) {
+            Ok(module) => module,
+            Err(err) => {
+                eprintln!("Skipping VST3 plugin: {err}");
+                continue;
+            }
+        };
+        for (cid, category, name, categories) in module.classes() {
+            if category != AUDIO_MODULE_CLASS || categories.contains("Instrument") {
+                continue;
+            }
+            found.push(PluginClassInfo {
+                format: PluginFormat::Vst3,
+                path: bundle.to_string_lossy().to_string(),
+                class_id: class_id_to_hex(&cid),
+                name,
+                categories,
+            });
+        }
+    }
+    found
+}
+
+/// One instantiated stereo effect, set up for blocks of up to `max_block` frames.
+pub struct Vst3Plugin {
+    name: String,
+    path: String,
+    class_id: String,
+    component: ComPtr<ComponentVtbl>,
+    processor: ComPtr<AudioProcessorVtbl>,
+    controller: Option<ComPtr<EditControllerVtbl>>,
+    /// Whether the controller is its own object that needs `initialize`/`terminate`.
+    separate_controller: bool,
+    changes: Box<ParameterChanges>,
+    sample_rate: f32,
+    max_block: usize,
+    active: bool,
+    // Declared last so the module is unloaded after every interface is released.
+    module: Module,
+}
+
+// SAFETY: the plugin's interfaces are only reached through `&mut self` or `&self` while
+// the DSP chain mutex is held, so they are never used from two threads at once.
+unsafe impl Send for Vst3Plugin {}
+
+impl Vst3Plugin {
+    pub fn load(
+        path: &str,
+        class_id: &str,
+        sample_rate: f32,
+        max_block: usize,
+    ) -> Result<Self, String> {
+        let cid = class_id_from_hex(class_id)?;
+        let module = Module::load(Path::new(path))?;
+        let name = module
+            .classes()
+            .into_iter()
+            .find(|(class, ..)| *class == cid)
+            .map(|(_, _, name, _)| name)
+            .ok_or_else(|| format!("VST3 module {path} has no class {class_id}"))?;
+        let component = module
+            .create::<ComponentVtbl>(&cid, &COMPONENT_IID)
+            .ok_or_else(|| format!("Failed to create VST3 plugin {name}"))?;
+        // SAFETY: `initialize` takes the host context; the host application is static.
+        if unsafe { (component.vtbl().base.initialize)(component.as_raw(), host_context()) }
+            != RESULT_OK
+        {
+            return Err(format!("VST3 plugin {name} failed to initialize"));
+        }
+        let processor = match component.query::<AudioProcessorVtbl>(&AUDIO_PROCESSOR_IID) {
+            Some(processor) => processor,
+            None => {
+                // SAFETY: balances the successful `initialize` above.
+                unsafe { (component.vtbl().base.terminate)(component.as_raw()) };
+                return Err(format!("VST3 plugin {name} is not an audio processor"));
+            }
+        };
+
+        let mut plugin = Self {
+            name,
+            path: path.to_string(),
+            class_id: class_id_to_hex(&cid),
+            controller: None,
+            separate_controller: false,
+            component,
+            processor,
+            changes: ParameterChanges::new(),
+            sample_rate: sample_rate.max(8_000.0),
+            max_block: max_block.max(1),
+            active: false,
+            module,
+        };
+        plugin.setup_buses()?;
+        plugin.attach_controller();
+        plugin.activate()?;
+        Ok(plugin)
+    }
+
+    /// Activates the main stereo input and output and asks for a stereo arrangement.
+    fn setup_buses(&mut self) -> Result<(), String> {
+        let component = self.component.as_raw();
+        let vtbl = self.component.vtbl();
+        let processor = self.processor.as_raw();
+        let processor_vtbl = self.processor.vtbl();
+        // SAFETY: plain calls on live interfaces with valid arguments.
+        unsafe {
+            if (vtbl.get_bus_count)(component, MEDIA_AUDIO, DIRECTION_INPUT) < 1
+                || (vtbl.get_bus_count)(component, MEDIA_AUDIO, DIRECTION_OUTPUT) < 1
+            {
+                return Err(format!(
+                    "VST3 plugin {} is not an effect with audio input and output",
+                    self.name
+                ));
+            }
+            if (processor_vtbl.can_process_sample_size)(processor, SAMPLE_32) != RESULT_OK {
+                return Err(format!(
+                    "VST3 plugin {} cannot process 32-bit samples",
+                    self.name
+                ));
+            }
+            let mut input = SPEAKER_STEREO;
+            let mut output = SPEAKER_STEREO;
+            if (processor_vtbl.set_bus_arrangements)(processor, &mut input, 1, &mut output, 1)
+                != RESULT_OK
+            {
+                // Keep whatever the plugin chose, as long as it is stereo.
+                let (mut input, mut output) = (0, 0);
+                (processor_vtbl.get_bus_arrangement)(processor, DIRECTION_INPUT, 0, &mut input);
+                (processor_vtbl.get_bus_arrangement)(processor, DIRECTION_OUTPUT, 0, &mut output);
+                if input != SPEAKER_STEREO || output != SPEAKER_STEREO {
+                    return Err(format!("VST3 plugin {} has no stereo layout", self.name));
+                }
+            }
+            (vtbl.activate_bus)(component, MEDIA_AUDIO, DIRECTION_INPUT, 0, 1);
+            (vtbl.activate_bus)(component, MEDIA_AUDIO, DIRECTION_OUTPUT, 0, 1);
+        }
+        Ok(())
+    }
+
+    /// Finds the edit controller, either on the component itself or as a separate
+    /// class, and syncs it with the component's state. Without one the plugin still
+    /// runs, just without parameters.
+    fn attach_controller(&mut self) {
+        if let Some(controller) = self
+            .component
+            .query::<EditControllerVtbl>(&EDIT_CONTROLLER_IID)
+        {
+            self.controller = Some(controller);
+        } else {
+            let mut cid: Tuid = [0; 16];
+            // SAFETY: `cid` is a valid TUID buffer; the new controller gets the host context.
+            unsafe {
+                if (self.component.vtbl().get_controller_class_id)(
+                    self.component.as_raw(),
+                    &mut cid,
+                ) != RESULT_OK
+                {
+                    return;
+                }
+                let Some(controller) = self
+                    .module
+                    .create::<EditControllerVtbl>(&cid, &EDIT_CONTROLLER_IID)
+                else {
+                    return;
+                };
+                if (controller.vtbl().base.initialize)(controller.as_raw(), host_context())
+                    != RESULT_OK
+                {
+                    return;
+                }
+                self.controller = Some(controller);
+                self.separate_controller = true;
+            }
+        }
+        if let Ok(state) = self.state() {
+            if let Some(controller) = &self.controller {
+                let mut stream = MemoryStream::new(state.component);
+                // SAFETY: the stream outlives the call.
+                unsafe {
+                    (controller.vtbl().set_component_state)(controller.as_raw(), stream.as_raw())
+                };
+            }
+        }
+    }
+
+    fn activate(&mut self) -> Result<(), String> {
+        let mut setup = ProcessSetup {
+            process_mode: PROCESS_REALTIME,
+            symbolic_sample_size: SAMPLE_32,
+            max_samples_per_block: self.max_block as i32,
+            sample_rate: f64::from(self.sample_rate),
+        };
+        // SAFETY: plain calls on live interfaces; `setup` outlives the call.
+        unsafe {
+            if (self.processor.vtbl().setup_processing)(self.processor.as_raw(), &mut setup)
+                != RESULT_OK
+            {
+                return Err(format!(
+                    "VST3 plugin {} does not support {} Hz",
+                    self.name, self.sample_rate
+                ));
+            }
+            if (self.component.vtbl().set_active)(self.component.as_raw(), 1) != RESULT_OK {
+                return Err(format!("VST3 plugin {} failed to activate", self.name));
+            }
+            // Optional for plugins; a failure here is not fatal.
+            (self.processor.vtbl().set_processing)(self.processor.as_raw(), 1);
+        }
+        self.active = true;
+        Ok(())
+    }
+
+    fn deactivate(&mut self) {
+        if !self.active {
+            return;
+        }
+        // SAFETY: balances `activate`.
+        unsafe {
+            (self.processor.vtbl().set_processing)(self.processor.as_raw(), 0);
+            (self.component.vtbl().set_active)(self.component.as_raw(), 0);
+        }
+        self.active = false;
+    }
+}
+
+impl EffectPlugin for Vst3Plugin {
+    fn format(&self) -> PluginFormat {
+        PluginFormat::Vst3
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn class_id(&self) -> &str {
+        &self.class_id
+    }
+
+    fn parameters(&self) -> Vec<PluginParameter> {
+        let Some(controller) = &self.controller else {
+            return Vec::new();
+        };
+        let this = controller.as_raw();
+        let vtbl = controller.vtbl();
+        // SAFETY: indices come from `get_parameter_count` and the buffers match the SDK.
+        unsafe {
+            (0..(vtbl.get_parameter_count)(this))
+                .filter_map(|index| {
+                    let mut info: ParameterInfo = std::mem::zeroed();
+                    if (vtbl.get_parameter_info)(this, index, &mut info) != RESULT_OK {
+                        return None;
+                    }
+                    let value = (vtbl.get_param_normalized)(this, info.id);
+                    let mut display = [0_u16; 128];
+                    if (vtbl.get_param_string_by_value)(this, info.id, value, &mut display)
+                        != RESULT_OK
+                    {
+                        display[0] = 0;
+                    }
+                    Some(PluginParameter {
+                        id: info.id,
+                        title: utf16_string(&info.title),
+                        units: utf16_string(&info.units),
+                        value,
+                        default_value: info.default_normalized_value,
+                        step_count: info.step_count,
+                        display: utf16_string(&display),
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// The processor picks the new value up at the start of the next block.
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err("VST3 parameter value must be finite".to_string());
+        }
+        let value = value.clamp(0.0, 1.0);
+        let Some(controller) = &self.controller else {
+            return Err(format!("VST3 plugin {} has no parameters", self.name));
+        };
+        // SAFETY: plain call on a live controller.
+        let result =
+            unsafe { (controller.vtbl().set_param_normalized)(controller.as_raw(), id, value) };
+        if result != RESULT_OK {
+            return Err(format!("VST3 plugin {} rejected parameter {id}", self.name));
+        }
+        self.changes.push(id, value);
+        Ok(())
+    }
+
+    fn state(&self) -> Result<PluginState, String> {
+        let mut component = MemoryStream::new(Vec::new());
+        // SAFETY: the stream outlives the call.
+        let result = unsafe {
+            (self.component.vtbl().get_state)(self.component.as_raw(), component.as_raw())
+        };
+        if result != RESULT_OK {
+            return Err(format!("Failed to read state of VST3 plugin {}", self.name));
+        }
+        let mut controller = MemoryStream::new(Vec::new());
+        if let Some(edit) = &self.controller {
+            // SAFETY: as above. Plugins without controller state leave the stream empty.
+            unsafe { (edit.vtbl().get_state)(edit.as_raw(), controller.as_raw()) };
+        }
+        Ok(PluginState {
+            component: component.data,
+            controller: controller.data,
+        })
+    }
+
+    fn restore_state(&mut self, state: &PluginState) -> Result<(), String> {
+        let mut component = MemoryStream::new(state.component.clone());
+        // SAFETY: the streams outlive the calls.
+        unsafe {
+            if (self.component.vtbl().set_state)(self.component.as_raw(), component.as_raw())
+                != RESULT_OK
+            {
+                return Err(format!(
+                    "VST3 plugin {} rejected its saved state",
+                    self.name
+                ));
+            }
+            if let Some(edit) = &self.controller {
+                let mut component = MemoryStream::new(state.component.clone());
+                (edit.vtbl().set_component_state)(edit.as_raw(), component.as_raw());
+                if !state.controller.is_empty() {
+                    let mut controller = MemoryStream::new(state.controller.clone());
+                    (edit.vtbl().set_state)(edit.as_raw(), controller.as_raw());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), String> {
+        let sr = sample_rate.max(8_000.0);
+        if (sr - self.sample_rate).abs() <= f32::EPSILON {
+            return Ok(());
+        }
+        self.deactivate();
+        self.sample_rate = sr;
+        self.activate()
+    }
+
+    fn process(&mut self, input: [&mut [f32]; 2], output: [&mut [f32]; 2]) {
+        let frames = input[0]
+            .len()
+            .min(input[1].len())
+            .min(output[0].len())
+            .min(output[1].len());
+        let [in_left, in_right] = input;
+        let [out_left, out_right] = output;
+        if !self.active || frames > self.max_block {
+            out_left[..frames].copy_from_slice(&in_left[..frames]);
+            out_right[..frames].copy_from_slice(&in_right[..frames]);
+            return;
+        }
+        let mut inputs = [in_left.as_mut_ptr(), in_right.as_mut_ptr()];
+        let mut outputs = [out_left.as_mut_ptr(), out_right.as_mut_ptr()];
+        let mut input_bus = AudioBusBuffers {
+            num_channels: 2,
+            silence_flags: 0,
+            channel_buffers: inputs.as_mut_ptr(),
+        };
+        let mut output_bus = AudioBusBuffers {
+            num_channels: 2,
+            silence_flags: 0,
+            channel_buffers: outputs.as_mut_ptr(),
+        };
+        let mut data = ProcessData {
+            process_mode: PROCESS_REALTIME,
+            symbolic_sample_size: SAMPLE_32,
+            num_samples: frames as i32,
+            num_inputs: 1,
+            num_outputs: 1,
+            inputs: &mut input_bus,
+            outputs: &mut output_bus,
+            input_parameter_changes: self.changes.as_raw(),
+            output_parameter_changes: ptr::null_mut(),
+            input_events: ptr::null_mut(),
+            output_events: ptr::null_mut(),
+            process_context: ptr::null_mut(),
+        };
+        // SAFETY: every buffer and host object referenced by `data` outlives the call.
+        let result = unsafe { (self.processor.vtbl().process)(self.processor.as_raw(), &mut data) };
+        self.changes.queues.clear();
+        if result != RESULT_OK {
+            out_left[..frames].copy_from_slice(&in_left[..frames]);
+            out_right[..frames].copy_from_slice(&in_right[..frames]);
+        }
+    }
+}
+
+impl Drop for Vst3Plugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        // SAFETY: balances the `initialize` calls made in `load`.
+        unsafe {
+            if let Some(controller) = &self.controller {
+                if self.separate_controller {
+                    (controller.vtbl().base.terminate)(controller.as_raw());
+                }
+            }
+            (self.component.vtbl().base.terminate)(self.component.as_raw());
+        }
+    }
+}
+
+pub fn class_id_to_hex(cid: &Tuid) -> String {
+    cid.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+fn class_id_from_hex(hex: &str) -> Result<Tuid, String> {
+    let hex = hex.trim();
+    let invalid = || format!("Invalid VST3 class ID: {hex}");
+    if hex.len() != 32 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut cid = [0_u8; 16];
+    for (index, byte) in cid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(cid)
+}
+
+fn c_string(chars: &[c_char]) -> String {
+    let bytes = chars.iter().map(|c| *c as u8).collect::<Vec<_>>();
+    CStr::from_bytes_until_nul(&bytes)
+        .map(|text| text.to_string_lossy().trim().to_string())
+        .unwrap_or_default()
+}
+
+fn utf16_string(chars: &[u16]) -> String {
+    let end = chars.iter().position(|c| *c == 0).unwrap_or(chars.len());
+    String::from_utf16_lossy(&chars[..end]).trim().to_string()
+}
+
+fn write_string128(target: &mut String128, text: &str) {
+    target.fill(0);
+    for (slot, unit) in target[..127].iter_mut().zip(text.encode_utf16()) {
+        *slot = unit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        class_id_from_hex, class_id_to_hex, uid, MemoryStream, ParameterChanges, BSTREAM_IID,
+        FUNKNOWN_IID, RESULT_OK, SEEK_SET,
+    };
+    use std::ffi::c_void;
+    use std::ptr;
+
+    #[test]
+    fn host_objects_follow_the_vst3_abi() {
+        let iid = uid(0x0000_0000, 0x0000_0000, 0xC000_0000, 0x0000_0046);
+        assert_eq!(iid[8], 0xC0);
+        assert_eq!(iid[15], 0x46);
+        let hex = class_id_to_hex(&iid);
+        assert_eq!(class_id_from_hex(&hex), Ok(iid));
+        assert!(class_id_from_hex("not a class id").is_err());
+
+        // Plugins see the stream only through its vtable.
+        let mut stream = MemoryStream::new(Vec::new());
+        let this = stream.as_raw();
+        let vtbl = stream.vtbl;
+        let mut payload = *b"plugin state";
+        let (mut written, mut read, mut position) = (0, 0, 0);
+        let mut back = [0_u8; 12];
+        let mut found: *mut c_void = ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                (vtbl.unknown.query_interface)(this, &BSTREAM_IID, &mut found),
+                RESULT_OK
+            );
+            assert_eq!(found, this);
+            (vtbl.write)(this, payload.as_mut_ptr().cast(), 12, &mut written);
+            (vtbl.seek)(this, 7, SEEK_SET, &mut position);
+            (vtbl.read)(this, back.as_mut_ptr().cast(), 12, &mut read);
+        }
+        assert_eq!((written, position, read), (12, 7, 5));
+        assert_eq!(&back[..5], b"state");
+        assert_eq!(stream.data, b"plugin state");
+
+        let mut changes = ParameterChanges::new();
+        changes.push(3, 0.25);
+        changes.push(3, 0.5);
+        changes.push(9, 1.0);
+        let this = changes.as_raw();
+        unsafe {
+            assert_eq!((changes.vtbl.get_parameter_count)(this), 2);
+            let queue = (changes.vtbl.get_parameter_data)(this, 0);
+            let queue_vtbl = (*queue.cast::<super::ParamValueQueue>()).vtbl;
+            let (mut offset, mut value) = (-1, 0.0);
+            (queue_vtbl.get_point)(queue, 0, &mut offset, &mut value);
+            assert_eq!((queue_vtbl.get_parameter_id)(queue), 3);
+            assert_eq!((offset, value), (0, 0.5));
+            assert!((changes.vtbl.get_parameter_data)(this, 2).is_null());
+            assert_eq!(
+                (changes.vtbl.unknown.query_interface)(this, &FUNKNOWN_IID, &mut found),
+                RESULT_OK
+            );
+        }
+    }
+}
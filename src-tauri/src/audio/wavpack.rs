@@ -0,0 +1,343 @@
+//! WavPack support (`.wv`). Symphonia has neither a WavPack demuxer nor a decoder, so
+//! the block stream is split into packets here and each packet is decoded with
+//! `oxideav-wavpack`. Hybrid files play their lossy part; `.wvc` correction files are
+//! not read.
+
+use std::io::{ErrorKind, Seek, SeekFrom};
+
+use oxideav_wavpack::{
+    decode_multichannel_stream, parse_block, parse_block_header, HEADER_LEN, TOTAL_SAMPLES_UNKNOWN,
+};
+use symphonia::core::{
+    audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec},
+    codecs::{
+        CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult,
+        CODEC_TYPE_WAVPACK,
+    },
+    errors::{
+        decode_error, end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind,
+    },
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::{MediaSource, MediaSourceStream, ReadBytes},
+    meta::{Metadata, MetadataLog},
+    probe::{Descriptor, Instantiate, QueryDescriptor},
+    sample::SampleFormat,
+    support_codec, support_format,
+    units::TimeBase,
+};
+
+const BLOCK_MAGIC: &[u8; 4] = b"wvpk";
+
+pub struct WavPackReader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    data_start: u64,
+    /// The first packet, read up front to learn the stream layout.
+    first_packet: Option<Packet>,
+}
+
+/// The next block, or `None` where the blocks end (end of file or a trailing tag).
+fn read_block(reader: &mut MediaSourceStream) -> Result<Option<Vec<u8>>> {
+    let mut block = vec![0; HEADER_LEN];
+    match reader.read_buf_exact(&mut block) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    if &block[..4] != BLOCK_MAGIC {
+        return Ok(None);
+    }
+    // `ck_size` counts everything after itself.
+    let size = u32::from_le_bytes([block[4], block[5], block[6], block[7]]) as usize + 8;
+    if size < HEADER_LEN {
+        return decode_error("wavpack: invalid block size");
+    }
+    block.resize(size, 0);
+    reader.read_buf_exact(&mut block[HEADER_LEN..])?;
+    Ok(Some(block))
+}
+
+/// Reads the blocks holding the next run of frames: a single block for mono and
+/// stereo, or every member block from the first to the final flag for multichannel
+/// audio. Metadata-only blocks are skipped.
+fn read_packet(reader: &mut MediaSourceStream) -> Result<Packet> {
+    let mut data = Vec::new();
+    let mut timestamp = 0;
+    let mut frames = 0;
+    loop {
+        let Some(block) = read_block(reader)? else {
+            return if data.is_empty() {
+                end_of_stream_error()
+            } else {
+                decode_error("wavpack: stream ends inside a multichannel block set")
+            };
+        };
+        let Ok((header, _)) = parse_block_header(&block) else {
+            return decode_error("wavpack: invalid block header");
+        };
+        if !header.is_audio_block() {
+            continue;
+        }
+        if data.is_empty() {
+            timestamp = u64::from(header.block_index);
+            frames = u64::from(header.block_samples);
+        }
+        data.extend_from_slice(&block);
+        if header.flags.is_final_block() {
+            return Ok(Packet::new_from_boxed_slice(
+                0,
+                timestamp,
+                frames,
+                data.into_boxed_slice(),
+            ));
+        }
+    }
+}
+
+/// Codec parameters from the blocks of the first packet.
+fn codec_params(packet: &[u8]) -> Result<CodecParameters> {
+    let mut channel_count = 0;
+    let mut rest = packet;
+    let mut first = None;
+    while !rest.is_empty() {
+        let Ok((block, next)) = parse_block(rest) else {
+            return decode_error("wavpack: invalid block");
+        };
+        channel_count += usize::from(block.header().flags.channels_in_block());
+        first.get_or_insert(block);
+        rest = next;
+    }
+    let Some(block) = first else {
+        return decode_error("wavpack: no audio blocks");
+    };
+    let Ok(Some(sample_rate)) = block.sample_rate() else {
+        return unsupported_error("wavpack: unknown sample rate");
+    };
+    let header = block.header();
+    let mask = block
+        .channel_info()
+        .ok()
+        .flatten()
+        .map(|info| info.mask)
+        .filter(|mask| mask.count_ones() as usize == channel_count)
+        .unwrap_or((1_u32 << channel_count.min(31)) - 1);
+    let Some(channels) = Channels::from_bits(mask) else {
+        return unsupported_error("wavpack: unsupported channel layout");
+    };
+
+    let mut params = CodecParameters::new();
+    params
+        .for_codec(CODEC_TYPE_WAVPACK)
+        .with_sample_rate(sample_rate)
+        .with_time_base(TimeBase::new(1, sample_rate))
+        .with_channels(channels)
+        .with_bits_per_sample(u32::from(header.flags.bytes_per_sample()) * 8)
+        .with_max_frames_per_packet(u64::from(header.block_samples));
+    if block.is_float() {
+        params.with_sample_format(SampleFormat::F32);
+    }
+    if header.total_samples != TOTAL_SAMPLES_UNKNOWN {
+        params.with_n_frames(u64::from(header.total_samples));
+    }
+    Ok(params)
+}
+
+impl QueryDescriptor for WavPackReader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "wavpack",
+            "WavPack",
+            &["wv"],
+            &["audio/x-wavpack", "audio/wavpack"],
+            &[BLOCK_MAGIC]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for WavPackReader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let data_start = source.pos();
+        let first_packet = read_packet(&mut source)?;
+        let params = codec_params(first_packet.buf())?;
+        Ok(Self {
+            reader: source,
+            tracks: vec![Track::new(0, params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            data_start,
+            first_packet: Some(first_packet),
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => match self.tracks[0].codec_params.time_base {
+                Some(time_base) => time_base.calc_timestamp(time),
+                None => return seek_error(SeekErrorKind::Unseekable),
+            },
+        };
+        if !self.reader.is_seekable() {
+            return seek_error(SeekErrorKind::Unseekable);
+        }
+        // Blocks carry no index, so walk them from the start to the one holding `required_ts`.
+        self.reader.seek(SeekFrom::Start(self.data_start))?;
+        self.first_packet = None;
+        loop {
+            let position = self.reader.pos();
+            let packet = read_packet(&mut self.reader)?;
+            if packet.ts() + packet.dur() > required_ts {
+                self.reader.seek(SeekFrom::Start(position))?;
+                return Ok(SeekedTo {
+                    track_id: 0,
+                    required_ts,
+                    actual_ts: packet.ts(),
+                });
+            }
+        }
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        match self.first_packet.take() {
+            Some(packet) => Ok(packet),
+            None => read_packet(&mut self.reader),
+        }
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+pub struct WavPackDecoder {
+    params: CodecParameters,
+    buf: AudioBuffer<f32>,
+    /// Multiplier taking integer samples to [-1, 1); `None` for float streams.
+    scale: Option<f32>,
+}
+
+impl Decoder for WavPackDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let (Some(sample_rate), Some(channels)) = (params.sample_rate, params.channels) else {
+            return unsupported_error("wavpack: sample rate and channels are required");
+        };
+        let scale = match params.sample_format {
+            Some(SampleFormat::F32) => None,
+            _ => {
+                let bits = params.bits_per_sample.unwrap_or(16).clamp(8, 32);
+                Some(1.0 / (1_u64 << (bits - 1)) as f32)
+            }
+        };
+        Ok(Self {
+            params: params.clone(),
+            buf: AudioBuffer::new(
+                params.max_frames_per_packet.unwrap_or(0),
+                SignalSpec::new(sample_rate, channels),
+            ),
+            scale,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_WAVPACK, "wavpack", "WavPack")]
+    }
+
+    fn reset(&mut self) {
+        // Every block restarts the decoder, so nothing carries over between packets.
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let Ok(decoded) = decode_multichannel_stream(packet.buf()) else {
+            return decode_error("wavpack: corrupt block");
+        };
+        let spec = *self.buf.spec();
+        if decoded.channels != spec.channels.count() {
+            return decode_error("wavpack: channel count changed mid-stream");
+        }
+        let frames = decoded.samples.len() / decoded.channels;
+        if frames > self.buf.capacity() {
+            self.buf = AudioBuffer::new(frames as u64, spec);
+        }
+
+        self.buf.render_reserved(Some(frames));
+        for channel in 0..decoded.channels {
+            let samples = decoded
+                .samples
+                .iter()
+                .skip(channel)
+                .step_by(decoded.channels);
+            for (out, sample) in self.buf.chan_mut(channel).iter_mut().zip(samples) {
+                *out = match self.scale {
+                    Some(scale) => *sample as f32 * scale,
+                    None => f32::from_bits(*sample as u32),
+                };
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::decoder::{decode_file, read_track_metadata};
+    use crate::test_support::unique_temp_path;
+    use oxideav_wavpack::{encode_stream_stereo, set_stream_sample_rate};
+
+    #[test]
+    fn stereo_file_decodes_block_by_block() {
+        let pcm = (0..2500)
+            .flat_map(|n| {
+                let sample = (n * 37) % 20_000 - 10_000;
+                [sample, -sample / 2]
+            })
+            .collect::<Vec<i32>>();
+        let stream = encode_stream_stereo(&pcm, 1000, 2).expect("pcm should encode");
+        let stream = set_stream_sample_rate(&stream, 48_000).expect("rate should be stamped");
+        let path = unique_temp_path("stereo.wv");
+        std::fs::write(&path, stream).expect("wv should be written");
+
+        let track = decode_file(&path).expect("wv should decode");
+        let metadata = read_track_metadata(&path).expect("wv should probe");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!((track.sample_rate, track.channels), (48_000, 2));
+        let expected = pcm
+            .iter()
+            .map(|sample| *sample as f32 / 32_768.0)
+            .collect::<Vec<_>>();
+        assert_eq!(track.samples, expected);
+        let duration = metadata.duration_seconds.expect("duration should be known");
+        assert!((duration - 2500.0 / 48_000.0).abs() < 1e-6);
+    }
+}
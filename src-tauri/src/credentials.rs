@@ -0,0 +1,72 @@
+use keyring::{Entry, Error as KeyringError};
+
+/// Keychain service under which every integration secret is stored; the integration
+/// name (e.g. `lastfm`, `subsonic`, `webdav`) is used as the account.
+const KEYCHAIN_SERVICE: &str = "PowerPlayer";
+
+/// Stores (or replaces) the secret for `service` in the OS keychain: Windows
+/// Credential Manager, macOS Keychain, or the Secret Service (libsecret) on Linux.
+pub fn store_credential(service: &str, secret: &str) -> Result<(), String> {
+    if secret.is_empty() {
+        return Err(format!("Refusing to store an empty credential for {service}"));
+    }
+    entry(service)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store credential for {service}: {e}"))
+}
+
+/// Returns the secret for `service`, or `None` when nothing is stored.
+pub fn load_credential(service: &str) -> Result<Option<String>, String> {
+    match entry(service)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(KeyringError::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read credential for {service}: {e}")),
+    }
+}
+
+/// Removes the secret for `service`. Deleting a missing credential is not an error.
+pub fn delete_credential(service: &str) -> Result<(), String> {
+    match entry(service)?.delete_credential() {
+        Ok(()) | Err(KeyringError::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete credential for {service}: {e}")),
+    }
+}
+
+fn entry(service: &str) -> Result<Entry, String> {
+    validate_service(service)?;
+    Entry::new(KEYCHAIN_SERVICE, service)
+        .map_err(|e| format!("Failed to open keychain entry for {service}: {e}"))
+}
+
+/// Service names become keychain account names, so keep them short and predictable.
+fn validate_service(service: &str) -> Result<(), String> {
+    let valid = !service.is_empty()
+        && service.len() <= 64
+        && service
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | ':' | '@'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid credential service name: {service:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{store_credential, validate_service};
+
+    #[test]
+    fn service_names_are_validated() {
+        assert!(validate_service("lastfm").is_ok());
+        assert!(validate_service("subsonic:user@host").is_ok());
+        assert!(validate_service("").is_err());
+        assert!(validate_service("web dav").is_err());
+        assert!(validate_service(&"x".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn empty_secrets_are_rejected() {
+        assert!(store_credential("lastfm", "").is_err());
+    }
+}
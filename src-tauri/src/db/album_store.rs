@@ -0,0 +1,317 @@
+use rusqlite::params;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::db::manager::{
+    album_artist_sql, track_record, DbManager, TrackRecord, ALBUM_TRACK_ORDER, TRACK_RECORD_COLUMNS,
+};
+
+/// Album artist given to compilations, whether flagged in their tags or detected by
+/// [`DbManager::group_compilations`].
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// An album from the `albums` table with figures summed over its library tracks.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct AlbumRow {
+    pub name: String,
+    pub artist: Option<String>,
+    /// Earliest release year among the tracks.
+    pub year: Option<u32>,
+    pub track_count: usize,
+    /// Cover of the first track that has one.
+    pub art_url: Option<String>,
+    pub duration_seconds: f64,
+}
+
+impl DbManager {
+    /// Albums that still have tracks in the library, by artist then name. An album is
+    /// keyed by its album artist, so a compilation is one album rather than one per
+    /// track artist.
+    pub fn get_albums(&self) -> Result<Vec<AlbumRow>, String> {
+        let (cover_artist, track_artist) = (album_artist_sql("c"), album_artist_sql("t"));
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT a.name, a.artist, MIN(t.year), COUNT(t.id), SUM(t.duration_seconds),
+                        (SELECT c.art_url FROM tracks c
+                         WHERE c.album = a.name AND {cover_artist} IS a.artist
+                               AND c.art_url IS NOT NULL
+                         ORDER BY c.path LIMIT 1)
+                 FROM albums a JOIN tracks t ON t.album = a.name AND {track_artist} IS a.artist
+                 GROUP BY a.id
+                 ORDER BY a.artist COLLATE NOCASE, a.name COLLATE NOCASE"
+            ))
+            .map_err(|e| format!("Failed to prepare album query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(AlbumRow {
+                    name: row.get(0)?,
+                    artist: row.get(1)?,
+                    year: row.get(2)?,
+                    track_count: row.get::<_, i64>(3)? as usize,
+                    duration_seconds: row.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+                    art_url: row.get(5)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query albums: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read albums: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Tracks of `album` by album artist `artist` (`None` for tracks without one), by
+    /// disc and track number.
+    pub fn get_album_tracks(
+        &self,
+        album: &str,
+        artist: Option<&str>,
+    ) -> Result<Vec<TrackRecord>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TRACK_RECORD_COLUMNS} FROM tracks
+                 WHERE album = ?1 AND {} IS ?2
+                 ORDER BY {ALBUM_TRACK_ORDER}",
+                album_artist_sql("tracks")
+            ))
+            .map_err(|e| format!("Failed to prepare album track query: {e}"))?;
+        let rows = stmt
+            .query_map(params![album, artist], track_record)
+            .map_err(|e| format!("Failed to query tracks of album {album}: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read tracks of album {album}: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Gives untagged compilations the album artist [`VARIOUS_ARTISTS`], so they are one
+    /// album rather than one per track artist. Tracks without an album artist that share
+    /// an album name and folder are a compilation when at least three artists appear and
+    /// none of them has more than half the tracks. Returns the number of tracks updated.
+    pub fn group_compilations(&self) -> Result<usize, String> {
+        let mut conn = self.connection()?;
+        let candidates = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT path, album, artist FROM tracks
+                     WHERE album IS NOT NULL AND album <> ''
+                           AND COALESCE(album_artist, '') = ''",
+                )
+                .map_err(|e| format!("Failed to prepare compilation query: {e}"))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                })
+                .map_err(|e| format!("Failed to query compilation candidates: {e}"))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read compilation candidates: {e}"))?;
+            rows
+        };
+
+        let mut groups = HashMap::<(String, String), Vec<(String, String, Option<String>)>>::new();
+        for (path, album, artist) in candidates {
+            let folder = Path::new(&path)
+                .parent()
+                .map(|parent| parent.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            groups
+                .entry((folder, album.to_lowercase()))
+                .or_default()
+                .push((path, album, artist));
+        }
+
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start grouping compilations: {e}"))?;
+        let mut updated = 0;
+        for tracks in groups.into_values() {
+            let mut per_artist = HashMap::<String, usize>::new();
+            for artist in tracks.iter().filter_map(|(_, _, artist)| artist.as_deref()) {
+                *per_artist.entry(artist.trim().to_lowercase()).or_default() += 1;
+            }
+            let most = per_artist.values().copied().max().unwrap_or(0);
+            if per_artist.len() < 3 || most * 2 > tracks.len() {
+                continue;
+            }
+            for (path, album, _) in &tracks {
+                tx.execute(
+                    "UPDATE tracks SET album_artist = ?1 WHERE path = ?2",
+                    params![VARIOUS_ARTISTS, path],
+                )
+                .map_err(|e| format!("Failed to group compilation track {path}: {e}"))?;
+                tx.execute(
+                    "INSERT INTO albums (name, artist) VALUES (?1, ?2)
+                     ON CONFLICT(name, artist) DO NOTHING",
+                    params![album, VARIOUS_ARTISTS],
+                )
+                .map_err(|e| format!("Failed to save album {album}: {e}"))?;
+            }
+            updated += tracks.len();
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit grouping compilations: {e}"))?;
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VARIOUS_ARTISTS;
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn albums_sum_their_tracks() {
+        let db = DbManager::new(unique_temp_path("albums.db")).expect("db init");
+        let track =
+            |path: &str, album: Option<&str>, artist: Option<&str>, year, art: Option<&str>| {
+                db.save_track(&TrackInput {
+                    path: path.to_string(),
+                    title: None,
+                    artist: artist.map(str::to_string),
+                    album: album.map(str::to_string),
+                    genre: None,
+                    year,
+                    // Disc order runs against file name order.
+                    track_number: Some(1),
+                    disc_number: Some(if path.ends_with("b.flac") { 1 } else { 2 }),
+                    album_artist: None,
+                    composer: None,
+                    duration_seconds: Some(100.0),
+                    sample_rate: None,
+                    art_url: art.map(str::to_string),
+                    corrupted: false,
+                })
+                .expect("save track");
+            };
+        track(
+            "/m/b/a.flac",
+            Some("Blue"),
+            Some("Band"),
+            Some(2001),
+            Some("asset://blue.jpg"),
+        );
+        track("/m/b/b.flac", Some("Blue"), Some("Band"), Some(1999), None);
+        track("/m/x/01.flac", Some("Blue"), None, None, None);
+        track("/m/single.flac", None, Some("Band"), None, None);
+
+        // A compilation stays one album under its album artist.
+        for (path, artist) in [("/m/va/1.flac", "Amy"), ("/m/va/2.flac", "Bo")] {
+            db.save_track(&TrackInput {
+                path: path.to_string(),
+                title: None,
+                artist: Some(artist.to_string()),
+                album: Some("Hits".to_string()),
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: Some("Various Artists".to_string()),
+                composer: None,
+                duration_seconds: Some(100.0),
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        }
+
+        let albums = db.get_albums().expect("albums");
+        assert_eq!(albums.len(), 3);
+        // Tracks without an artist sort first.
+        assert_eq!(
+            (albums[0].artist.as_deref(), albums[0].track_count),
+            (None, 1)
+        );
+        let blue = &albums[1];
+        assert_eq!(blue.name, "Blue");
+        assert_eq!(blue.year, Some(1999));
+        assert_eq!(blue.track_count, 2);
+        assert_eq!(blue.duration_seconds, 200.0);
+        assert_eq!(blue.art_url.as_deref(), Some("asset://blue.jpg"));
+
+        let tracks = db.get_album_tracks("Blue", Some("Band")).expect("tracks");
+        let paths = tracks.iter().map(|t| t.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(paths, ["/m/b/b.flac", "/m/b/a.flac"]);
+        assert_eq!(tracks[0].year, Some(1999));
+        assert_eq!(db.get_album_tracks("Blue", None).expect("tracks").len(), 1);
+
+        let hits = &albums[2];
+        assert_eq!(
+            (hits.name.as_str(), hits.artist.as_deref(), hits.track_count),
+            ("Hits", Some("Various Artists"), 2)
+        );
+        let tracks = db
+            .get_album_tracks("Hits", Some("Various Artists"))
+            .expect("tracks");
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].artist.as_deref(), Some("Amy"));
+    }
+
+    #[test]
+    fn untagged_compilations_are_grouped_under_various_artists() {
+        let db = DbManager::new(unique_temp_path("compilations.db")).expect("db init");
+        let track = |path: &str, album: &str, artist: &str| {
+            db.save_track(&TrackInput {
+                path: path.to_string(),
+                title: None,
+                artist: Some(artist.to_string()),
+                album: Some(album.to_string()),
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        };
+        for (index, artist) in ["Amy", "Bo", "Cy", "Amy"].into_iter().enumerate() {
+            track(&format!("/m/hits/{index}.flac"), "Hits", artist);
+        }
+        // One artist with a guest spot is not a compilation.
+        for (index, artist) in ["Band", "Band", "Band", "Band feat. Amy"]
+            .into_iter()
+            .enumerate()
+        {
+            track(&format!("/m/band/{index}.flac"), "Blue", artist);
+        }
+        // Same name, different folders: two albums, neither a compilation.
+        track("/m/a/1.flac", "Greatest Hits", "Amy");
+        track("/m/b/1.flac", "Greatest Hits", "Bo");
+        track("/m/c/1.flac", "Greatest Hits", "Cy");
+
+        assert_eq!(db.group_compilations(), Ok(4));
+        assert_eq!(db.group_compilations(), Ok(0), "already grouped");
+        let albums = db.get_albums().expect("albums");
+        let hits = albums
+            .iter()
+            .find(|album| album.name == "Hits")
+            .expect("hits album");
+        assert_eq!(
+            (hits.artist.as_deref(), hits.track_count),
+            (Some(VARIOUS_ARTISTS), 4)
+        );
+        assert_eq!(
+            albums.len(),
+            6,
+            "Hits, two Blue artists, three Greatest Hits"
+        );
+
+        // Saving the untagged file again, as a rescan does, keeps the grouping.
+        track("/m/hits/1.flac", "Hits", "Bo");
+        assert_eq!(
+            db.get_album_tracks("Hits", Some(VARIOUS_ARTISTS))
+                .expect("tracks")
+                .len(),
+            4
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use rusqlite::{params, Connection};
+
+use crate::db::album_store::AlbumRow;
+use crate::db::manager::{
+    track_record, DbManager, TrackRecord, ALBUM_TRACK_ORDER, TRACK_RECORD_COLUMNS,
+};
+use crate::library::artists::split_artists;
+
+/// A track artist with how much of the library is theirs. Artists are taken from the
+/// split artist tags, so a featured artist is listed on their own and `"A feat. B"` is
+/// not listed at all.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct ArtistRow {
+    pub name: String,
+    pub album_count: usize,
+    pub track_count: usize,
+}
+
+impl DbManager {
+    /// Every artist credited on a library track, alphabetically.
+    pub fn get_artists(&self) -> Result<Vec<ArtistRow>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.name, COUNT(DISTINCT NULLIF(TRIM(t.album), '')), COUNT(*)
+                 FROM artists a
+                 JOIN track_artists ta ON ta.artist_id = a.id
+                 JOIN tracks t ON t.id = ta.track_id
+                 GROUP BY a.id
+                 ORDER BY a.name COLLATE NOCASE",
+            )
+            .map_err(|e| format!("Failed to prepare artist query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(ArtistRow {
+                    name: row.get(0)?,
+                    album_count: row.get::<_, i64>(1)? as usize,
+                    track_count: row.get::<_, i64>(2)? as usize,
+                })
+            })
+            .map_err(|e| format!("Failed to query artists: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read artists: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Albums credited to `artist` as album artist, oldest first.
+    pub fn get_artist_albums(&self, artist: &str) -> Result<Vec<AlbumRow>, String> {
+        let mut albums = self
+            .get_albums()?
+            .into_iter()
+            .filter(|album| album.artist.as_deref() == Some(artist))
+            .collect::<Vec<_>>();
+        // Albums without a year go last.
+        albums.sort_by_key(|album| (album.year.is_none(), album.year));
+        Ok(albums)
+    }
+
+    /// The `limit` most played tracks crediting `artist`, filled up with unplayed ones in
+    /// album order.
+    pub fn get_artist_top_tracks(
+        &self,
+        artist: &str,
+        limit: usize,
+    ) -> Result<Vec<TrackRecord>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TRACK_RECORD_COLUMNS} FROM tracks
+                 WHERE id IN (SELECT ta.track_id FROM track_artists ta
+                              JOIN artists a ON a.id = ta.artist_id
+                              WHERE a.name = ?1)
+                 ORDER BY play_count DESC, last_played_at DESC, album COLLATE NOCASE,
+                          {ALBUM_TRACK_ORDER}
+                 LIMIT ?2"
+            ))
+            .map_err(|e| format!("Failed to prepare top track query: {e}"))?;
+        let rows = stmt
+            .query_map(params![artist, limit as i64], track_record)
+            .map_err(|e| format!("Failed to query top tracks of {artist}: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read top tracks of {artist}: {e}"))?;
+        Ok(rows)
+    }
+}
+
+/// Replaces the artists credited on track `track_id` with those split from its `artist`
+/// tag.
+pub(super) fn save_track_artists(
+    conn: &Connection,
+    track_id: i64,
+    artist: Option<&str>,
+) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM track_artists WHERE track_id = ?1",
+        params![track_id],
+    )
+    .map_err(|e| format!("Failed to clear artists of track {track_id}: {e}"))?;
+    for (position, name) in split_artists(artist.unwrap_or_default()).iter().enumerate() {
+        conn.prepare_cached("INSERT INTO artists (name) VALUES (?1) ON CONFLICT(name) DO NOTHING")
+            .and_then(|mut stmt| stmt.execute(params![name]))
+            .map_err(|e| format!("Failed to save artist {name}: {e}"))?;
+        conn.prepare_cached(
+            "INSERT OR IGNORE INTO track_artists (track_id, artist_id, position)
+             SELECT ?1, id, ?3 FROM artists WHERE name = ?2",
+        )
+        .and_then(|mut stmt| stmt.execute(params![track_id, name, position as i64]))
+        .map_err(|e| format!("Failed to credit {name} on track {track_id}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Credits the artists of tracks with an artist tag but no credits yet: rows from
+/// before artists were split, and rows copied in SQL.
+pub(super) fn link_missing_track_artists(conn: &Connection) -> Result<(), String> {
+    let uncredited = conn
+        .prepare(
+            "SELECT id, artist FROM tracks t
+             WHERE TRIM(artist) <> ''
+                   AND NOT EXISTS (SELECT 1 FROM track_artists ta WHERE ta.track_id = t.id)",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("Failed to query uncredited tracks: {e}"))?;
+    if uncredited.is_empty() {
+        return Ok(());
+    }
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start crediting artists: {e}"))?;
+    for (track_id, artist) in uncredited {
+        save_track_artists(&tx, track_id, Some(&artist))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit crediting artists: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn artists_list_their_albums_and_most_played_tracks() {
+        let db = DbManager::new(unique_temp_path("artists.db")).expect("db init");
+        let track = |path: &str, artist: Option<&str>, album: Option<&str>, year| {
+            db.save_track(&TrackInput {
+                path: path.to_string(),
+                title: None,
+                artist: artist.map(str::to_string),
+                album: album.map(str::to_string),
+                genre: None,
+                year,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: Some(100.0),
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        };
+        track(
+            "/m/band/late/01.flac",
+            Some("Band"),
+            Some("Late"),
+            Some(2010),
+        );
+        track(
+            "/m/band/late/02.flac",
+            Some("Band"),
+            Some("Late"),
+            Some(2010),
+        );
+        track(
+            "/m/band/early/01.flac",
+            Some("Band"),
+            Some("Early"),
+            Some(1995),
+        );
+        track("/m/band/single.flac", Some("Band"), None, None);
+        track("/m/amy/01.flac", Some("amy"), Some("Debut"), None);
+        track("/m/unknown.flac", None, None, None);
+
+        let artists = db.get_artists().expect("artists");
+        let summary = artists
+            .iter()
+            .map(|a| (a.name.as_str(), a.album_count, a.track_count))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, [("amy", 1, 1), ("Band", 2, 4)]);
+
+        let albums = db.get_artist_albums("Band").expect("albums");
+        let names = albums.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Early", "Late"]);
+
+        db.record_track_play("/m/band/late/02.flac").expect("play");
+        let top = db.get_artist_top_tracks("Band", 2).expect("top tracks");
+        let paths = top.iter().map(|t| t.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(paths, ["/m/band/late/02.flac", "/m/band/single.flac"]);
+    }
+
+    #[test]
+    fn featured_artists_are_credited_on_their_own() {
+        let path = unique_temp_path("track-artists.db");
+        let db = DbManager::new(&path).expect("db init");
+        db.initialize_fts().expect("fts");
+        let track = |path: &str, artist: &str| {
+            db.save_track(&TrackInput {
+                path: path.to_string(),
+                title: Some("Song".to_string()),
+                artist: Some(artist.to_string()),
+                album: None,
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        };
+        track("/m/1.flac", "Band feat. Amy");
+        track("/m/2.flac", "Band");
+        track("/m/3.flac", "amy; Bo");
+        let names = |db: &DbManager| {
+            db.get_artists()
+                .expect("artists")
+                .into_iter()
+                .map(|a| (a.name, a.track_count))
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            ("Amy".to_string(), 2),
+            ("Band".to_string(), 2),
+            ("Bo".to_string(), 1),
+        ];
+        assert_eq!(names(&db), expected);
+        let top = db.get_artist_top_tracks("amy", 10).expect("top tracks");
+        assert_eq!(top.len(), 2);
+        let mut found = db.fast_search("Amy").expect("search").artists;
+        found.sort();
+        assert_eq!(found, ["Amy", "Band", "Bo"]);
+
+        // Retagging replaces the credits; deleting the track drops them.
+        track("/m/3.flac", "Bo");
+        db.delete_track("/m/1.flac").expect("delete");
+        assert_eq!(names(&db), [("Band".to_string(), 1), ("Bo".to_string(), 1)]);
+
+        // Rows from before the split are credited when the database is opened.
+        db.connection()
+            .expect("connection")
+            .execute_batch(
+                "INSERT INTO tracks (path, artist) VALUES ('/m/4.flac', 'Cy ft. Bo');
+                 DELETE FROM track_artists WHERE track_id IN
+                     (SELECT id FROM tracks WHERE path = '/m/4.flac');",
+            )
+            .expect("old row");
+        let db = DbManager::new(&path).expect("reopen");
+        assert_eq!(
+            names(&db),
+            [
+                ("Band".to_string(), 1),
+                ("Bo".to_string(), 2),
+                ("Cy".to_string(), 1)
+            ]
+        );
+    }
+}
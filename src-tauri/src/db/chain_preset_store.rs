@@ -0,0 +1,118 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::audio::dsp::presets::ChainPreset;
+use crate::db::manager::DbManager;
+
+impl DbManager {
+    /// Create the chain preset table if it doesn't exist. Each preset stores the whole
+    /// DSP chain as JSON, in the same format as exported preset files.
+    pub fn initialize_chain_preset_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS chain_presets (
+                name TEXT PRIMARY KEY,
+                settings TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        )
+        .map_err(|e| format!("Failed to create chain preset table: {e}"))?;
+        Ok(())
+    }
+
+    /// Create or replace a named preset.
+    pub fn save_chain_preset(&self, name: &str, preset: &ChainPreset) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Chain preset name cannot be empty".to_string());
+        }
+        preset.validate()?;
+        let settings = serde_json::to_string(preset)
+            .map_err(|e| format!("Failed to serialize chain preset {name}: {e}"))?;
+        self.connection()?
+            .execute(
+                "INSERT INTO chain_presets (name, settings) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET
+                    settings = excluded.settings,
+                    updated_at = CURRENT_TIMESTAMP",
+                params![name, settings],
+            )
+            .map_err(|e| format!("Failed to save chain preset {name}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn load_chain_preset(&self, name: &str) -> Result<Option<ChainPreset>, String> {
+        let settings = self
+            .connection()?
+            .query_row(
+                "SELECT settings FROM chain_presets WHERE name = ?1",
+                params![name.trim()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load chain preset {name}: {e}"))?;
+        settings
+            .map(|settings| {
+                serde_json::from_str(&settings)
+                    .map_err(|e| format!("Chain preset {name} is corrupt: {e}"))
+            })
+            .transpose()
+    }
+
+    /// Preset names, sorted case-insensitively.
+    pub fn get_chain_preset_names(&self) -> Result<Vec<String>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT name FROM chain_presets ORDER BY name COLLATE NOCASE")
+            .map_err(|e| format!("Failed to prepare chain preset query: {e}"))?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query chain presets: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read chain presets: {e}"))?;
+        Ok(names)
+    }
+
+    /// Returns `false` when no preset had that name.
+    pub fn delete_chain_preset(&self, name: &str) -> Result<bool, String> {
+        let deleted = self
+            .connection()?
+            .execute(
+                "DELETE FROM chain_presets WHERE name = ?1",
+                params![name.trim()],
+            )
+            .map_err(|e| format!("Failed to delete chain preset {name}: {e}"))?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::dsp::filters::DspChain;
+    use crate::audio::dsp::presets::ChainPreset;
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn chain_presets_round_trip_by_name() {
+        let db = DbManager::new(unique_temp_path("chain-preset.db")).expect("db init");
+        db.initialize_chain_preset_schema().expect("schema");
+
+        let chain = DspChain::new(48_000.0);
+        let flat = ChainPreset::capture(&chain, 0.0);
+        chain.tone().set_treble(-2.0);
+        let dark = ChainPreset::capture(&chain, -1.5);
+        db.save_chain_preset(" Dark ", &dark).expect("save");
+        db.save_chain_preset("flat", &flat).expect("save");
+        assert!(db.save_chain_preset(" ", &flat).is_err());
+
+        assert_eq!(db.load_chain_preset("Dark").expect("load"), Some(dark));
+        assert_eq!(db.load_chain_preset("Missing").expect("load"), None);
+        assert_eq!(
+            db.get_chain_preset_names().expect("names"),
+            vec!["Dark".to_string(), "flat".to_string()]
+        );
+
+        assert!(db.delete_chain_preset("Dark").expect("delete"));
+        assert!(!db.delete_chain_preset("Dark").expect("delete again"));
+    }
+}
@@ -0,0 +1,385 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::audio::dsp::bass_management::BassManagementSettings;
+use crate::audio::dsp::profiles::{DspProfile, DspProfileScope};
+use crate::audio::dsp::upmix::UpmixSettings;
+use crate::db::manager::DbManager;
+use crate::library::paths::canonical_path_string;
+
+const NIGHT_MODE_SETTING: &str = "night_mode";
+const UPMIX_SETTING: &str = "upmix";
+const BASS_MANAGEMENT_SETTING: &str = "bass_management";
+
+/// One profile assignment. Track assignments live in `tracks.dsp_profile` so they
+/// follow the file through moves and copies; genre and device ones in
+/// `dsp_profile_assignments`.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct DspProfileAssignmentRow {
+    pub scope: DspProfileScope,
+    pub key: String,
+    pub profile_name: String,
+}
+
+impl DbManager {
+    /// Create the DSP profile tables if they don't exist.
+    pub fn initialize_dsp_profile_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS dsp_profiles (
+                name TEXT PRIMARY KEY,
+                settings TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS dsp_profile_assignments (
+                scope TEXT NOT NULL,
+                key TEXT NOT NULL,
+                profile_name TEXT NOT NULL,
+                PRIMARY KEY(scope, key)
+            );",
+        )
+        .map_err(|e| format!("Failed to create DSP profile tables: {e}"))?;
+        Ok(())
+    }
+
+    /// Create or replace a named profile.
+    pub fn save_dsp_profile(&self, name: &str, profile: &DspProfile) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("DSP profile name cannot be empty".to_string());
+        }
+        profile.validate()?;
+        let settings = serde_json::to_string(profile)
+            .map_err(|e| format!("Failed to serialize DSP profile {name}: {e}"))?;
+        self.connection()?
+            .execute(
+                "INSERT INTO dsp_profiles (name, settings) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET settings = excluded.settings",
+                params![name, settings],
+            )
+            .map_err(|e| format!("Failed to save DSP profile {name}: {e}"))?;
+        Ok(())
+    }
+
+    /// Delete a profile together with every assignment that points at it.
+    pub fn delete_dsp_profile(&self, name: &str) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start DSP profile delete: {e}"))?;
+        tx.execute("DELETE FROM dsp_profiles WHERE name = ?1", params![name])
+            .map_err(|e| format!("Failed to delete DSP profile {name}: {e}"))?;
+        tx.execute(
+            "DELETE FROM dsp_profile_assignments WHERE profile_name = ?1",
+            params![name],
+        )
+        .map_err(|e| format!("Failed to delete assignments of DSP profile {name}: {e}"))?;
+        tx.execute(
+            "UPDATE tracks SET dsp_profile = NULL WHERE dsp_profile = ?1",
+            params![name],
+        )
+        .map_err(|e| format!("Failed to clear track assignments of DSP profile {name}: {e}"))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit DSP profile delete: {e}"))
+    }
+
+    pub fn get_dsp_profiles(&self) -> Result<Vec<(String, DspProfile)>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT name, settings FROM dsp_profiles ORDER BY name COLLATE NOCASE")
+            .map_err(|e| format!("Failed to prepare DSP profile query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Failed to query DSP profiles: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read DSP profiles: {e}"))?;
+        rows.into_iter()
+            .map(|(name, settings)| parse_profile(&name, &settings).map(|profile| (name, profile)))
+            .collect()
+    }
+
+    /// Assign `profile_name` to a track path, genre or output device; `None` clears the
+    /// assignment. Returns `false` for a track that is not in the library.
+    pub fn assign_dsp_profile(
+        &self,
+        scope: DspProfileScope,
+        key: &str,
+        profile_name: Option<&str>,
+    ) -> Result<bool, String> {
+        let conn = self.connection()?;
+        if let Some(name) = profile_name {
+            let exists = conn
+                .query_row(
+                    "SELECT 1 FROM dsp_profiles WHERE name = ?1",
+                    params![name],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(|e| format!("Failed to look up DSP profile {name}: {e}"))?
+                .is_some();
+            if !exists {
+                return Err(format!("Unknown DSP profile: {name}"));
+            }
+        }
+
+        let key = assignment_key(scope, key)?;
+        if scope == DspProfileScope::Track {
+            let updated = conn
+                .execute(
+                    "UPDATE tracks SET dsp_profile = ?1, updated_at = CURRENT_TIMESTAMP WHERE path = ?2",
+                    params![profile_name, key],
+                )
+                .map_err(|e| format!("Failed to assign DSP profile to {key}: {e}"))?;
+            return Ok(updated > 0);
+        }
+
+        match profile_name {
+            Some(name) => conn.execute(
+                "INSERT INTO dsp_profile_assignments (scope, key, profile_name) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(scope, key) DO UPDATE SET profile_name = excluded.profile_name",
+                params![scope.as_str(), key, name],
+            ),
+            None => conn.execute(
+                "DELETE FROM dsp_profile_assignments WHERE scope = ?1 AND key = ?2",
+                params![scope.as_str(), key],
+            ),
+        }
+        .map_err(|e| {
+            format!(
+                "Failed to assign DSP profile to {} {key}: {e}",
+                scope.as_str()
+            )
+        })?;
+        Ok(true)
+    }
+
+    pub fn get_dsp_profile_assignments(&self) -> Result<Vec<DspProfileAssignmentRow>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT scope, key, profile_name FROM dsp_profile_assignments
+                 UNION ALL
+                 SELECT 'track', path, dsp_profile FROM tracks WHERE dsp_profile IS NOT NULL
+                 ORDER BY 1, 2",
+            )
+            .map_err(|e| format!("Failed to prepare DSP assignment query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|e| format!("Failed to query DSP assignments: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read DSP assignments: {e}"))?;
+        rows.into_iter()
+            .map(|(scope, key, profile_name)| {
+                Ok(DspProfileAssignmentRow {
+                    scope: DspProfileScope::parse(&scope)?,
+                    key,
+                    profile_name,
+                })
+            })
+            .collect()
+    }
+
+    /// Profile assigned to an already-normalized key (see `profiles::resolve_profile`).
+    pub fn assigned_dsp_profile(
+        &self,
+        scope: DspProfileScope,
+        key: &str,
+    ) -> Result<Option<(String, DspProfile)>, String> {
+        let conn = self.connection()?;
+        let read_row =
+            |row: &rusqlite::Row<'_>| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?));
+        let row = if scope == DspProfileScope::Track {
+            conn.query_row(
+                "SELECT p.name, p.settings FROM tracks t
+                 JOIN dsp_profiles p ON p.name = t.dsp_profile
+                 WHERE t.path = ?1",
+                params![key],
+                read_row,
+            )
+        } else {
+            conn.query_row(
+                "SELECT p.name, p.settings FROM dsp_profile_assignments a
+                 JOIN dsp_profiles p ON p.name = a.profile_name
+                 WHERE a.scope = ?1 AND a.key = ?2",
+                params![scope.as_str(), key],
+                read_row,
+            )
+        }
+        .optional()
+        .map_err(|e| format!("Failed to look up DSP profile for {key}: {e}"))?;
+        row.map(|(name, settings)| parse_profile(&name, &settings).map(|profile| (name, profile)))
+            .transpose()
+    }
+
+    /// Night mode stays as the user left it across restarts; off until first set.
+    pub fn night_mode_enabled(&self) -> Result<bool, String> {
+        Ok(self
+            .get_setting(NIGHT_MODE_SETTING)?
+            .is_some_and(|value| value == "true"))
+    }
+
+    pub fn set_night_mode_enabled(&self, enabled: bool) -> Result<(), String> {
+        self.set_setting(NIGHT_MODE_SETTING, if enabled { "true" } else { "false" })
+    }
+
+    /// The surround upmix configuration, or `None` until it is first changed.
+    pub fn upmix_settings(&self) -> Result<Option<UpmixSettings>, String> {
+        self.get_setting(UPMIX_SETTING)?
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map_err(|e| format!("Failed to parse upmix settings: {e}"))
+            })
+            .transpose()
+    }
+
+    pub fn set_upmix_settings(&self, settings: &UpmixSettings) -> Result<(), String> {
+        let value = serde_json::to_string(settings)
+            .map_err(|e| format!("Failed to serialize upmix settings: {e}"))?;
+        self.set_setting(UPMIX_SETTING, &value)
+    }
+
+    /// The bass management configuration, or `None` until it is first changed.
+    pub fn bass_management_settings(&self) -> Result<Option<BassManagementSettings>, String> {
+        self.get_setting(BASS_MANAGEMENT_SETTING)?
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map_err(|e| format!("Failed to parse bass management settings: {e}"))
+            })
+            .transpose()
+    }
+
+    pub fn set_bass_management_settings(
+        &self,
+        settings: &BassManagementSettings,
+    ) -> Result<(), String> {
+        let value = serde_json::to_string(settings)
+            .map_err(|e| format!("Failed to serialize bass management settings: {e}"))?;
+        self.set_setting(BASS_MANAGEMENT_SETTING, &value)
+    }
+}
+
+fn parse_profile(name: &str, settings: &str) -> Result<DspProfile, String> {
+    serde_json::from_str(settings).map_err(|e| format!("Failed to parse DSP profile {name}: {e}"))
+}
+
+/// Track keys are canonical paths, genres are matched case-insensitively and device
+/// names exactly as reported by the output backend.
+fn assignment_key(scope: DspProfileScope, key: &str) -> Result<String, String> {
+    let key = match scope {
+        DspProfileScope::Track => canonical_path_string(key),
+        DspProfileScope::Genre => key.trim().to_lowercase(),
+        DspProfileScope::Device => key.trim().to_string(),
+    };
+    if key.is_empty() {
+        return Err(format!("Empty {} key for DSP profile", scope.as_str()));
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::dsp::profiles::{DspProfile, DspProfileScope};
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+    use std::path::PathBuf;
+
+    fn unique_db_path() -> PathBuf {
+        unique_temp_path("dsp-profile-test.db")
+    }
+
+    fn bass_boost() -> DspProfile {
+        DspProfile {
+            bass_db: Some(6.0),
+            expansion: Some(0.5),
+            ..DspProfile::default()
+        }
+    }
+
+    #[test]
+    fn genre_and_device_assignments_roundtrip() {
+        let db = DbManager::new(unique_db_path()).expect("db init");
+        db.initialize_dsp_profile_schema().expect("schema");
+        db.save_dsp_profile("EDM", &bass_boost())
+            .expect("save profile");
+
+        assert!(db
+            .assign_dsp_profile(DspProfileScope::Genre, " Electronic ", Some("EDM"))
+            .expect("assign genre"));
+        assert!(db
+            .assign_dsp_profile(DspProfileScope::Device, "Speakers", Some("EDM"))
+            .expect("assign device"));
+        assert!(db
+            .assign_dsp_profile(DspProfileScope::Genre, "Jazz", Some("Missing"))
+            .is_err());
+
+        let (name, profile) = db
+            .assigned_dsp_profile(DspProfileScope::Genre, "electronic")
+            .expect("lookup")
+            .expect("genre should be assigned");
+        assert_eq!(name, "EDM");
+        assert_eq!(profile, bass_boost());
+        assert_eq!(
+            db.get_dsp_profile_assignments().expect("assignments").len(),
+            2
+        );
+
+        db.assign_dsp_profile(DspProfileScope::Device, "Speakers", None)
+            .expect("clear device");
+        assert!(db
+            .assigned_dsp_profile(DspProfileScope::Device, "Speakers")
+            .expect("lookup")
+            .is_none());
+    }
+
+    #[test]
+    fn track_assignment_lives_on_the_track_row() {
+        let db = DbManager::new(unique_db_path()).expect("db init");
+        db.initialize_dsp_profile_schema().expect("schema");
+        db.save_dsp_profile("Quiet", &DspProfile::default())
+            .expect("save profile");
+        db.save_track(&TrackInput {
+            path: "/music/live.flac".to_string(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: Some("Rock".to_string()),
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save track");
+
+        assert!(!db
+            .assign_dsp_profile(DspProfileScope::Track, "/music/other.flac", Some("Quiet"))
+            .expect("assign missing track"));
+        assert!(db
+            .assign_dsp_profile(DspProfileScope::Track, "/music/live.flac", Some("Quiet"))
+            .expect("assign track"));
+        db.rename_track_path("/music/live.flac", "/music/moved.flac")
+            .expect("move track");
+        assert_eq!(
+            db.assigned_dsp_profile(DspProfileScope::Track, "/music/moved.flac")
+                .expect("lookup")
+                .map(|(name, _)| name),
+            Some("Quiet".to_string())
+        );
+
+        db.delete_dsp_profile("Quiet").expect("delete profile");
+        assert!(db
+            .get_dsp_profile_assignments()
+            .expect("assignments")
+            .is_empty());
+    }
+}
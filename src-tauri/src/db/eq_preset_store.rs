@@ -0,0 +1,144 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::audio::dsp::filters::EqBandSettings;
+use crate::db::manager::DbManager;
+
+impl DbManager {
+    /// Create the EQ preset table if it doesn't exist. Each preset stores its full band
+    /// set as JSON.
+    pub fn initialize_eq_preset_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS eq_presets (
+                name TEXT PRIMARY KEY,
+                bands TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        )
+        .map_err(|e| format!("Failed to create EQ preset table: {e}"))?;
+        Ok(())
+    }
+
+    /// Create or replace a named preset.
+    pub fn save_eq_preset(&self, name: &str, bands: &[EqBandSettings]) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("EQ preset name cannot be empty".to_string());
+        }
+        let finite = bands.iter().all(|band| {
+            band.frequency.is_finite() && band.gain_db.is_finite() && band.q_factor.is_finite()
+        });
+        if bands.is_empty() || !finite {
+            return Err(format!("EQ preset {name} needs finite band settings"));
+        }
+        let bands = serde_json::to_string(bands)
+            .map_err(|e| format!("Failed to serialize EQ preset {name}: {e}"))?;
+        self.connection()?
+            .execute(
+                "INSERT INTO eq_presets (name, bands) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET
+                    bands = excluded.bands,
+                    updated_at = CURRENT_TIMESTAMP",
+                params![name, bands],
+            )
+            .map_err(|e| format!("Failed to save EQ preset {name}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn load_eq_preset(&self, name: &str) -> Result<Option<Vec<EqBandSettings>>, String> {
+        let bands = self
+            .connection()?
+            .query_row(
+                "SELECT bands FROM eq_presets WHERE name = ?1",
+                params![name.trim()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load EQ preset {name}: {e}"))?;
+        bands.map(|bands| parse_bands(name, &bands)).transpose()
+    }
+
+    pub fn get_eq_presets(&self) -> Result<Vec<(String, Vec<EqBandSettings>)>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT name, bands FROM eq_presets ORDER BY name COLLATE NOCASE")
+            .map_err(|e| format!("Failed to prepare EQ preset query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Failed to query EQ presets: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read EQ presets: {e}"))?;
+        rows.into_iter()
+            .map(|(name, bands)| parse_bands(&name, &bands).map(|bands| (name, bands)))
+            .collect()
+    }
+
+    /// Returns `false` when no preset had that name.
+    pub fn delete_eq_preset(&self, name: &str) -> Result<bool, String> {
+        let deleted = self
+            .connection()?
+            .execute(
+                "DELETE FROM eq_presets WHERE name = ?1",
+                params![name.trim()],
+            )
+            .map_err(|e| format!("Failed to delete EQ preset {name}: {e}"))?;
+        Ok(deleted > 0)
+    }
+}
+
+fn parse_bands(name: &str, bands: &str) -> Result<Vec<EqBandSettings>, String> {
+    serde_json::from_str(bands).map_err(|e| format!("EQ preset {name} is corrupt: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::dsp::filters::{EqBandSettings, FilterType};
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn eq_presets_round_trip_full_band_sets() {
+        let db = DbManager::new(unique_temp_path("eq-preset.db")).expect("db init");
+        db.initialize_eq_preset_schema().expect("schema");
+
+        let rock = vec![
+            EqBandSettings {
+                frequency: 80.0,
+                gain_db: 4.0,
+                q_factor: 0.7,
+                filter_type: FilterType::LowShelf,
+            },
+            EqBandSettings {
+                frequency: 3_000.0,
+                gain_db: 2.5,
+                q_factor: 1.4,
+                filter_type: FilterType::Peaking,
+            },
+        ];
+        db.save_eq_preset(" Rock ", &rock).expect("save preset");
+        db.save_eq_preset("Podcast", &rock[1..])
+            .expect("save preset");
+        assert!(db.save_eq_preset("  ", &rock).is_err());
+        assert!(db.save_eq_preset("Empty", &[]).is_err());
+
+        assert_eq!(db.load_eq_preset("Rock").expect("load"), Some(rock.clone()));
+        assert_eq!(db.load_eq_preset("Missing").expect("load"), None);
+
+        // Saving under an existing name replaces the bands.
+        db.save_eq_preset("Rock", &rock[..1])
+            .expect("overwrite preset");
+        let presets = db.get_eq_presets().expect("presets");
+        assert_eq!(
+            presets
+                .iter()
+                .map(|(name, bands)| (name.as_str(), bands.len()))
+                .collect::<Vec<_>>(),
+            vec![("Podcast", 1), ("Rock", 1)]
+        );
+
+        assert!(db.delete_eq_preset("Rock").expect("delete"));
+        assert!(!db.delete_eq_preset("Rock").expect("delete again"));
+    }
+}
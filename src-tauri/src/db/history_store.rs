@@ -0,0 +1,132 @@
+use rusqlite::params;
+
+use crate::db::manager::DbManager;
+use crate::library::paths::canonical_path_string;
+
+/// Row from the `play_history` table, with the track's tags when it is in the library.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct PlayHistoryRow {
+    pub id: i64,
+    pub path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// UTC, as `YYYY-MM-DD HH:MM:SS`.
+    pub played_at: String,
+    pub completion_percent: f64,
+}
+
+impl DbManager {
+    /// Create the play history table if it doesn't exist. One row per finished play.
+    pub fn initialize_history_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS play_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL,
+                played_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                completion_percent REAL NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_play_history_path ON play_history(path);",
+        )
+        .map_err(|e| format!("Failed to create play history table: {e}"))?;
+        Ok(())
+    }
+
+    pub fn add_play_history(&self, path: &str, completion_percent: f64) -> Result<(), String> {
+        self.connection()?
+            .execute(
+                "INSERT INTO play_history (path, completion_percent) VALUES (?1, ?2)",
+                params![
+                    canonical_path_string(path),
+                    completion_percent.clamp(0.0, 100.0)
+                ],
+            )
+            .map_err(|e| format!("Failed to record play of {path}: {e}"))?;
+        Ok(())
+    }
+
+    /// The `limit` most recent plays, newest first.
+    pub fn get_play_history(&self, limit: usize) -> Result<Vec<PlayHistoryRow>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT h.id, h.path, t.title, t.artist, t.album, h.played_at,
+                        h.completion_percent
+                 FROM play_history h LEFT JOIN tracks t ON t.path = h.path
+                 ORDER BY h.id DESC
+                 LIMIT ?1",
+            )
+            .map_err(|e| format!("Failed to prepare play history query: {e}"))?;
+        let rows = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(PlayHistoryRow {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    title: row.get(2)?,
+                    artist: row.get(3)?,
+                    album: row.get(4)?,
+                    played_at: row.get(5)?,
+                    completion_percent: row.get(6)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query play history: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read play history: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Deletes every recorded play and returns how many there were.
+    pub fn clear_play_history(&self) -> Result<usize, String> {
+        self.connection()?
+            .execute("DELETE FROM play_history", [])
+            .map_err(|e| format!("Failed to clear play history: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn history_lists_newest_plays_first() {
+        let db = DbManager::new(unique_temp_path("history.db")).expect("db init");
+        db.initialize_history_schema().expect("schema");
+        db.save_track(&TrackInput {
+            path: "/music/song.flac".to_string(),
+            title: Some("Song".to_string()),
+            artist: Some("Band".to_string()),
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: Some(200.0),
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save track");
+
+        db.add_play_history("/music/song.flac", 100.0)
+            .expect("record");
+        db.add_play_history("/music/other.flac", 120.0)
+            .expect("record");
+        db.add_play_history("/music/song.flac", 42.5)
+            .expect("record");
+
+        let history = db.get_play_history(2).expect("history");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].completion_percent, 42.5);
+        assert_eq!(history[0].title.as_deref(), Some("Song"));
+        assert_eq!(history[1].path, "/music/other.flac");
+        assert_eq!(history[1].title, None);
+        assert_eq!(history[1].completion_percent, 100.0);
+
+        assert_eq!(db.clear_play_history().expect("clear"), 3);
+        assert!(db.get_play_history(10).expect("history").is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+use rusqlite::params;
+use std::collections::HashMap;
+use std::path::{MAIN_SEPARATOR, MAIN_SEPARATOR_STR};
+
+use crate::db::manager::{
+    delete_track_settings, track_record, DbManager, TrackRecord, TRACK_RECORD_COLUMNS,
+};
+use crate::library::paths::canonical_path_string;
+
+/// Row from the `library_roots` table: a scanned folder that is watched for changes,
+/// with the number of library tracks under it.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct LibraryRootRow {
+    pub path: String,
+    pub track_count: usize,
+    pub added_at: String,
+}
+
+impl DbManager {
+    /// Create the library roots table if it doesn't exist.
+    pub fn initialize_library_root_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS library_roots (
+                path TEXT PRIMARY KEY,
+                added_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        )
+        .map_err(|e| format!("Failed to create library roots table: {e}"))?;
+        Ok(())
+    }
+
+    /// Remembers `path` as a library root. Adding a root twice keeps the first entry.
+    pub fn add_library_root(&self, path: &str) -> Result<(), String> {
+        let path = canonical_path_string(path);
+        self.connection()?
+            .execute(
+                "INSERT INTO library_roots (path) VALUES (?1) ON CONFLICT(path) DO NOTHING",
+                params![path],
+            )
+            .map_err(|e| format!("Failed to add library root {path}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn get_library_roots(&self) -> Result<Vec<LibraryRootRow>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT r.path, r.added_at,
+                        (SELECT COUNT(*) FROM tracks t
+                         WHERE substr(t.path, 1, length(r.path) + 1) = r.path || ?1)
+                 FROM library_roots r
+                 ORDER BY r.path COLLATE NOCASE",
+            )
+            .map_err(|e| format!("Failed to prepare library root query: {e}"))?;
+        let rows = stmt
+            .query_map(params![MAIN_SEPARATOR_STR], |row| {
+                Ok(LibraryRootRow {
+                    path: row.get(0)?,
+                    added_at: row.get(1)?,
+                    track_count: row.get::<_, i64>(2)? as usize,
+                })
+            })
+            .map_err(|e| format!("Failed to query library roots: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read library roots: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Library tracks directly inside folder `path`, in no particular order.
+    pub fn get_tracks_in_folder(&self, path: &str) -> Result<Vec<TrackRecord>, String> {
+        let prefix = format!("{}{MAIN_SEPARATOR}", path.trim_end_matches(MAIN_SEPARATOR));
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TRACK_RECORD_COLUMNS} FROM tracks
+                 WHERE substr(path, 1, length(?1)) = ?1
+                   AND instr(substr(path, length(?1) + 1), ?2) = 0"
+            ))
+            .map_err(|e| format!("Failed to prepare folder track query: {e}"))?;
+        let rows = stmt
+            .query_map(params![prefix, MAIN_SEPARATOR_STR], track_record)
+            .map_err(|e| format!("Failed to query tracks in {path}: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read tracks in {path}: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Number of library tracks anywhere below each subfolder of `path`, keyed by the
+    /// subfolder's name. Subfolders without tracks are left out.
+    pub fn count_tracks_by_subfolder(&self, path: &str) -> Result<HashMap<String, usize>, String> {
+        let prefix = format!("{}{MAIN_SEPARATOR}", path.trim_end_matches(MAIN_SEPARATOR));
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT substr(rest, 1, instr(rest, ?2) - 1) AS folder, COUNT(*)
+                 FROM (SELECT substr(path, length(?1) + 1) AS rest FROM tracks
+                       WHERE substr(path, 1, length(?1)) = ?1)
+                 WHERE instr(rest, ?2) > 0
+                 GROUP BY folder",
+            )
+            .map_err(|e| format!("Failed to prepare folder count query: {e}"))?;
+        let counts = stmt
+            .query_map(params![prefix, MAIN_SEPARATOR_STR], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })
+            .map_err(|e| format!("Failed to count tracks under {path}: {e}"))?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| format!("Failed to read track counts under {path}: {e}"))?;
+        Ok(counts)
+    }
+
+    /// Forgets library root `path` and deletes the tracks under it with their per-track
+    /// settings, in one transaction. Play history and playlist entries are kept, as when
+    /// a track is deleted: the history still names what was played, and playlists show
+    /// the entries as missing. Returns the paths of the deleted tracks.
+    pub fn remove_library_root(&self, path: &str) -> Result<Vec<String>, String> {
+        let path = canonical_path_string(path);
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start removing library root {path}: {e}"))?;
+        let removed = tx
+            .execute("DELETE FROM library_roots WHERE path = ?1", params![path])
+            .map_err(|e| format!("Failed to remove library root {path}: {e}"))?;
+        if removed == 0 {
+            return Err(format!("{path} is not a library root"));
+        }
+        let prefix = format!("{}{MAIN_SEPARATOR}", path.trim_end_matches(MAIN_SEPARATOR));
+        let deleted = {
+            let mut stmt = tx
+                .prepare("SELECT path FROM tracks WHERE substr(path, 1, length(?1)) = ?1")
+                .map_err(|e| format!("Failed to prepare root track query: {e}"))?;
+            let paths = stmt
+                .query_map(params![prefix], |row| row.get::<_, String>(0))
+                .map_err(|e| format!("Failed to query tracks under {path}: {e}"))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read tracks under {path}: {e}"))?;
+            paths
+        };
+        for track in &deleted {
+            delete_track_settings(&tx, track)?;
+        }
+        tx.execute(
+            "DELETE FROM tracks WHERE substr(path, 1, length(?1)) = ?1",
+            params![prefix],
+        )
+        .map_err(|e| format!("Failed to delete tracks under {path}: {e}"))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit removing library root {path}: {e}"))?;
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+    use std::path::{MAIN_SEPARATOR, MAIN_SEPARATOR_STR};
+
+    #[test]
+    fn removing_a_root_deletes_only_its_tracks() {
+        let db = DbManager::new(unique_temp_path("library-roots.db")).expect("db init");
+        db.initialize_library_root_schema().expect("schema");
+        db.initialize_spatial_schema().expect("spatial schema");
+        db.initialize_history_schema().expect("history schema");
+        db.initialize_playlist_schema().expect("playlist schema");
+        let path = |rel: &str| format!("{MAIN_SEPARATOR}{}", rel.replace('/', MAIN_SEPARATOR_STR));
+        let music = path("music");
+        for track in [
+            path("music/a.flac"),
+            path("music/Album/b.flac"),
+            path("music2/c.flac"),
+        ] {
+            db.save_track(&TrackInput {
+                path: track,
+                title: None,
+                artist: None,
+                album: None,
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        }
+        db.add_library_root(&music).expect("add root");
+        db.add_library_root(&music).expect("adding twice is fine");
+        db.add_library_root(&path("music2")).expect("add root");
+
+        let roots = db.get_library_roots().expect("roots");
+        let counts = roots
+            .iter()
+            .map(|root| root.track_count)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [2, 1]);
+
+        let song = path("music/a.flac");
+        db.save_spatial_scene(&song, "vocals", 1.0, 0.0, 0.0, true)
+            .expect("save scene");
+        db.add_play_history(&song, 100.0).expect("add play");
+        let playlist = db.create_playlist("Mix").expect("create playlist");
+        db.set_playlist_tracks(playlist, std::slice::from_ref(&song))
+            .expect("set playlist");
+
+        let mut removed = db.remove_library_root(&music).expect("remove root");
+        removed.sort();
+        assert_eq!(removed, [path("music/Album/b.flac"), song.clone()]);
+        assert!(db.remove_library_root(&music).is_err());
+        let tracks = db.get_tracks().expect("tracks");
+        assert_eq!(tracks.len(), 1, "the sibling folder keeps its tracks");
+        assert_eq!(db.get_library_roots().expect("roots").len(), 1);
+        assert!(db.load_spatial_scene(&song).expect("scene").is_empty());
+        // History and playlist entries outlive the track, as on deletion.
+        assert_eq!(db.get_play_history(10).expect("history")[0].path, song);
+        assert_eq!(db.get_playlist_tracks(playlist).expect("playlist"), [song]);
+    }
+}
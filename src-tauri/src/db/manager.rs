@@ -2,7 +2,74 @@ use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::OptionalExtension;
 use rusqlite::params;
-use std::path::Path;
+use rusqlite::{Connection, TransactionBehavior};
+use std::collections::HashMap;
+use std::path::{Path, MAIN_SEPARATOR};
+
+use crate::audio::analyzer::AudioFingerprint;
+use crate::db::album_store::VARIOUS_ARTISTS;
+use crate::db::artist_store::{link_missing_track_artists, save_track_artists};
+use crate::library::paths::canonical_path_string;
+
+/// Per-track data that must survive when duplicate rows are merged into one, and that
+/// is carried over to copied files. On merge, columns are only copied into the
+/// surviving row when it has no value yet.
+const MERGED_TRACK_COLUMNS: &[&str] = &[
+    "art_url",
+    "waveform_data",
+    "bpm",
+    "bpm_analyzed_at",
+    "loudness_profile",
+    "dsp_profile",
+    "resume_seconds",
+    "play_heatmap",
+    "fingerprint",
+    "fingerprint_duration",
+    "rating",
+];
+/// Tag metadata copied alongside `MERGED_TRACK_COLUMNS` when a file is duplicated.
+const COPIED_TRACK_COLUMNS: &[&str] = &[
+    "title",
+    "artist",
+    "album",
+    "genre",
+    "year",
+    "track_number",
+    "disc_number",
+    "album_artist",
+    "composer",
+    "duration_seconds",
+    "sample_rate",
+    "corrupted",
+];
+const PATHS_NORMALIZED_SETTING: &str = "track_paths_normalized";
+/// Per-track settings stored outside `tracks`, as `(table, path column, extra filter)`.
+/// Each group moves as a whole, and only when the target path has none of its own.
+const TRACK_SETTING_TABLES: &[&[(&str, &str, &str)]] = &[
+    &[
+        ("spatial_scenes", "track_id", ""),
+        ("spatial_listeners", "track_id", ""),
+        ("spatial_motions", "track_id", ""),
+        ("spatial_source_mix", "track_id", ""),
+    ],
+    &[
+        ("track_plugin_chains", "track_path", ""),
+        ("plugin_inserts", "track_path", ""),
+    ],
+    &[("dsp_profile_assignments", "key", " AND scope = 'track'")],
+];
+/// Tables whose rows always follow a track to its new path.
+const TRACK_ENTRY_TABLES: &[(&str, &str)] =
+    &[("play_history", "path"), ("playlist_tracks", "path")];
+/// Columns read into a `TrackRecord`, in field order.
+pub(super) const TRACK_RECORD_COLUMNS: &str =
+    "path, title, artist, album, genre, year, track_number, disc_number, album_artist, \
+     composer, duration_seconds, sample_rate, art_url, corrupted, bpm, play_count, \
+     last_played_at, rating";
+/// Album order: by disc and track number, with untagged tracks after the tagged ones
+/// and then by file name.
+pub(super) const ALBUM_TRACK_ORDER: &str =
+    "disc_number IS NULL, disc_number, track_number IS NULL, track_number, path COLLATE NOCASE";
 
 #[derive(Clone)]
 pub struct DbManager {
@@ -15,6 +82,14 @@ pub struct TrackInput {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    /// Artist the album is credited to, when it differs from the track artist
+    /// (compilations, featured artists). Albums are grouped under it.
+    pub album_artist: Option<String>,
+    pub composer: Option<String>,
     pub duration_seconds: Option<f32>,
     pub sample_rate: Option<u32>,
     pub art_url: Option<String>,
@@ -27,10 +102,104 @@ pub struct TrackRecord {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub album_artist: Option<String>,
+    pub composer: Option<String>,
     pub duration_seconds: Option<f32>,
     pub sample_rate: Option<u32>,
     pub art_url: Option<String>,
     pub corrupted: bool,
+    pub bpm: Option<f32>,
+    pub play_count: u32,
+    /// UTC, as `YYYY-MM-DD HH:MM:SS`; `None` until the first counted play.
+    pub last_played_at: Option<String>,
+    /// 1 to 5 stars; `None` when unrated.
+    pub rating: Option<u8>,
+}
+
+/// Modification time and size of a file when its metadata was last read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified_ms: i64,
+    pub size: i64,
+}
+
+/// MusicBrainz identifiers of a track, stored once enrichment matched it so later
+/// lookups can go straight to the recording.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct MusicBrainzIds {
+    pub recording_id: Option<String>,
+    pub release_id: Option<String>,
+    pub artist_id: Option<String>,
+}
+
+/// Library views ordered or filtered by play statistics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum TrackPlayQuery {
+    /// Played tracks, most plays first, then the most recently played.
+    MostPlayed,
+    /// Tracks without a counted play, in library order.
+    NeverPlayed,
+    /// Tracks played before but not in the last `months` months, longest forgotten
+    /// first.
+    NotPlayedFor { months: u32 },
+}
+
+/// Column the library track list is sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackSortKey {
+    Title,
+    #[default]
+    Artist,
+    Album,
+    DateAdded,
+    Duration,
+    PlayCount,
+    Rating,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+pub struct TrackSort {
+    pub key: TrackSortKey,
+    pub direction: SortDirection,
+}
+
+impl TrackSort {
+    /// `ORDER BY` terms: the key in the requested direction with unset values last,
+    /// then library order (artist, album, disc and track) to break ties. Only fixed
+    /// column names are used, so no user input reaches the SQL.
+    fn order_by(self) -> String {
+        let direction = match self.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        let key = match self.key {
+            TrackSortKey::Title => format!("title IS NULL, title COLLATE NOCASE {direction}"),
+            TrackSortKey::Artist => format!("artist IS NULL, artist COLLATE NOCASE {direction}"),
+            TrackSortKey::Album => format!("album IS NULL, album COLLATE NOCASE {direction}"),
+            TrackSortKey::DateAdded => format!("created_at {direction}, id {direction}"),
+            TrackSortKey::Duration => {
+                format!("duration_seconds IS NULL, duration_seconds {direction}")
+            }
+            TrackSortKey::PlayCount => format!("play_count {direction}"),
+            TrackSortKey::Rating => format!("rating IS NULL, rating {direction}"),
+        };
+        format!("{key}, artist COLLATE NOCASE, album COLLATE NOCASE, {ALBUM_TRACK_ORDER}")
+    }
 }
 
 impl DbManager {
@@ -43,74 +212,279 @@ impl DbManager {
     }
 
     pub fn save_track(&self, track: &TrackInput) -> Result<(), String> {
-        let conn = self.connection()?;
-        conn.execute(
-            "INSERT INTO tracks (path, title, artist, album, duration_seconds, sample_rate, art_url, corrupted)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
-             ON CONFLICT(path) DO UPDATE SET
-                  title = excluded.title,
-                  artist = excluded.artist,
-                  album = excluded.album,
-                  duration_seconds = excluded.duration_seconds,
-                  sample_rate = excluded.sample_rate,
-                  art_url = excluded.art_url,
-                  corrupted = excluded.corrupted,
-                  updated_at = CURRENT_TIMESTAMP",
-            params![
-                track.path,
-                track.title,
-                track.artist,
-                track.album,
-                track.duration_seconds,
-                track.sample_rate,
-                track.art_url,
-                track.corrupted as i32
-            ],
-        )
-        .map_err(|e| format!("Failed to save track {}: {e}", track.path))?;
+        save_track_row(&*self.connection()?, track)
+    }
 
-        // Empty/blank album names are intentionally skipped to keep the albums table normalized.
-        if let Some(album) = track.album.as_ref().filter(|name| !name.trim().is_empty()) {
-            conn.execute(
-                "INSERT INTO albums (name, artist) VALUES (?1, ?2)
-                 ON CONFLICT(name, artist) DO NOTHING",
-                params![album, track.artist],
-            )
-            .map_err(|e| format!("Failed to save album {}: {e}", album))?;
+    /// Saves tracks read together, each with the stamp its file had when read, in one
+    /// transaction.
+    pub fn save_indexed_tracks(
+        &self,
+        tracks: &[(TrackInput, Option<FileStamp>)],
+    ) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start saving tracks: {e}"))?;
+        for (track, stamp) in tracks {
+            save_track_row(&tx, track)?;
+            if let Some(stamp) = stamp {
+                save_file_stamp_row(&tx, &canonical_path_string(&track.path), *stamp)?;
+            }
         }
-
-        Ok(())
+        tx.commit()
+            .map_err(|e| format!("Failed to commit saved tracks: {e}"))
     }
 
     pub fn get_tracks(&self) -> Result<Vec<TrackRecord>, String> {
         let conn = self.connection()?;
         let mut stmt = conn
-            .prepare(
-                "SELECT path, title, artist, album, duration_seconds, sample_rate, art_url, corrupted
+            .prepare(&format!(
+                "SELECT {TRACK_RECORD_COLUMNS}
                  FROM tracks
-                 ORDER BY artist COLLATE NOCASE, album COLLATE NOCASE, title COLLATE NOCASE, path",
-            )
+                 ORDER BY artist COLLATE NOCASE, album COLLATE NOCASE, {ALBUM_TRACK_ORDER}"
+            ))
             .map_err(|e| format!("Failed to prepare track query: {e}"))?;
 
         let rows = stmt
-            .query_map([], |row| {
-                Ok(TrackRecord {
-                    path: row.get(0)?,
-                    title: row.get(1)?,
-                    artist: row.get(2)?,
-                    album: row.get(3)?,
-                    duration_seconds: row.get(4)?,
-                    sample_rate: row.get(5)?,
-                    art_url: row.get(6)?,
-                    corrupted: row.get::<_, i32>(7)? != 0,
-                })
-            })
+            .query_map([], track_record)
             .map_err(|e| format!("Failed to query tracks: {e}"))?;
 
         rows.collect::<Result<Vec<_>, _>>()
             .map_err(|e| format!("Failed to read tracks: {e}"))
     }
 
+    /// The library row of `path`, if it is a library track.
+    pub fn get_track(&self, path: &str) -> Result<Option<TrackRecord>, String> {
+        let path = canonical_path_string(path);
+        self.connection()?
+            .query_row(
+                &format!("SELECT {TRACK_RECORD_COLUMNS} FROM tracks WHERE path = ?1"),
+                params![path],
+                track_record,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read track {path}: {e}"))
+    }
+
+    /// Library paths whose file name is `file_name`, ignoring ASCII case, for matching
+    /// paths written on another machine.
+    pub fn get_track_paths_named(&self, file_name: &str) -> Result<Vec<String>, String> {
+        let mut pattern = format!("%{MAIN_SEPARATOR}");
+        for c in file_name.chars() {
+            if matches!(c, '!' | '%' | '_') {
+                pattern.push('!');
+            }
+            pattern.push(c);
+        }
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT path FROM tracks WHERE path LIKE ?1 ESCAPE '!'")
+            .map_err(|e| format!("Failed to prepare track name query: {e}"))?;
+        let paths = stmt
+            .query_map(params![pattern], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query tracks named {file_name}: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read tracks named {file_name}: {e}"))?;
+        Ok(paths)
+    }
+
+    /// Library tracks in `sort` order, skipping the first `offset` and returning at most
+    /// `limit` (all without one).
+    pub fn get_tracks_sorted(
+        &self,
+        sort: TrackSort,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<TrackRecord>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TRACK_RECORD_COLUMNS} FROM tracks ORDER BY {} LIMIT ?1 OFFSET ?2",
+                sort.order_by()
+            ))
+            .map_err(|e| format!("Failed to prepare sorted track query: {e}"))?;
+        // SQLite reads a negative limit as no limit.
+        let limit = limit.map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX));
+        let rows = stmt
+            .query_map(params![limit, offset as i64], track_record)
+            .map_err(|e| format!("Failed to query sorted tracks: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read sorted tracks: {e}"))
+    }
+
+    pub fn get_track_count(&self) -> Result<usize, String> {
+        self.connection()?
+            .query_row("SELECT COUNT(*) FROM tracks", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|count| count as usize)
+            .map_err(|e| format!("Failed to count tracks: {e}"))
+    }
+
+    /// Up to `limit` library tracks matching `query`, in its order.
+    pub fn get_tracks_by_plays(
+        &self,
+        query: TrackPlayQuery,
+        limit: usize,
+    ) -> Result<Vec<TrackRecord>, String> {
+        let (filter, order) = match query {
+            TrackPlayQuery::MostPlayed => (
+                "play_count > 0".to_string(),
+                "play_count DESC, last_played_at DESC".to_string(),
+            ),
+            TrackPlayQuery::NeverPlayed => (
+                "play_count = 0".to_string(),
+                format!("artist COLLATE NOCASE, album COLLATE NOCASE, {ALBUM_TRACK_ORDER}"),
+            ),
+            TrackPlayQuery::NotPlayedFor { months } => (
+                format!("last_played_at < datetime('now', '-{months} months')"),
+                "last_played_at".to_string(),
+            ),
+        };
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TRACK_RECORD_COLUMNS} FROM tracks
+                 WHERE {filter}
+                 ORDER BY {order}, path
+                 LIMIT ?1"
+            ))
+            .map_err(|e| format!("Failed to prepare play statistics query: {e}"))?;
+        let rows = stmt
+            .query_map(params![limit as i64], track_record)
+            .map_err(|e| format!("Failed to query tracks by plays: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read tracks by plays: {e}"))
+    }
+
+    /// Counts a qualifying play of `path` and stamps it as last played now. Returns
+    /// `false` when `path` is not a library track.
+    pub fn record_track_play(&self, path: &str) -> Result<bool, String> {
+        let path = canonical_path_string(path);
+        let updated = self
+            .connection()?
+            .execute(
+                "UPDATE tracks SET play_count = play_count + 1, last_played_at = CURRENT_TIMESTAMP
+                 WHERE path = ?1",
+                params![path],
+            )
+            .map_err(|e| format!("Failed to count play of {path}: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    /// Merges play statistics and a rating brought over from another player: the play
+    /// count and last play become the larger of the stored and imported ones, and the
+    /// rating only fills an unrated track. Returns `false` when `path` is not a library
+    /// track.
+    pub fn merge_imported_stats(
+        &self,
+        path: &str,
+        play_count: u32,
+        last_played_at: Option<&str>,
+        rating: Option<u8>,
+    ) -> Result<bool, String> {
+        let path = canonical_path_string(path);
+        let updated = self
+            .connection()?
+            .execute(
+                "UPDATE tracks SET
+                     play_count = MAX(play_count, ?2),
+                     last_played_at = CASE
+                         WHEN last_played_at IS NULL OR last_played_at < ?3 THEN ?3
+                         ELSE last_played_at
+                     END,
+                     rating = COALESCE(rating, ?4)
+                 WHERE path = ?1",
+                params![
+                    path,
+                    play_count,
+                    last_played_at,
+                    rating.filter(|stars| (1..=5).contains(stars))
+                ],
+            )
+            .map_err(|e| format!("Failed to import play statistics of {path}: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    /// Rates `path` from 1 to 5 stars, or clears its rating with `None`. Returns `false`
+    /// when `path` is not a library track.
+    pub fn set_track_rating(&self, path: &str, rating: Option<u8>) -> Result<bool, String> {
+        if rating.is_some_and(|stars| !(1..=5).contains(&stars)) {
+            return Err(format!("Rating must be 1 to 5 stars, got {rating:?}"));
+        }
+        let path = canonical_path_string(path);
+        let updated = self
+            .connection()?
+            .execute(
+                "UPDATE tracks SET rating = ?2 WHERE path = ?1",
+                params![path, rating],
+            )
+            .map_err(|e| format!("Failed to rate {path}: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    /// Stamps of the tracks whose files were scanned, by path.
+    pub fn get_file_stamps(&self) -> Result<HashMap<String, FileStamp>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT path, file_mtime_ms, file_size FROM tracks
+                 WHERE file_mtime_ms IS NOT NULL AND file_size IS NOT NULL",
+            )
+            .map_err(|e| format!("Failed to prepare file stamp query: {e}"))?;
+        let stamps = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    FileStamp {
+                        modified_ms: row.get(1)?,
+                        size: row.get(2)?,
+                    },
+                ))
+            })
+            .map_err(|e| format!("Failed to query file stamps: {e}"))?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| format!("Failed to read file stamps: {e}"))?;
+        Ok(stamps)
+    }
+
+    pub fn save_file_stamp(&self, path: &str, stamp: FileStamp) -> Result<(), String> {
+        save_file_stamp_row(&*self.connection()?, &canonical_path_string(path), stamp)
+    }
+
+    /// MusicBrainz IDs stored for `path`, or `None` when it was never matched.
+    pub fn get_musicbrainz_ids(&self, path: &str) -> Result<Option<MusicBrainzIds>, String> {
+        let path = canonical_path_string(path);
+        let ids = self
+            .connection()?
+            .query_row(
+                "SELECT mb_recording_id, mb_release_id, mb_artist_id FROM tracks WHERE path = ?1",
+                params![path],
+                |row| {
+                    Ok(MusicBrainzIds {
+                        recording_id: row.get(0)?,
+                        release_id: row.get(1)?,
+                        artist_id: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query MusicBrainz IDs of {path}: {e}"))?;
+        Ok(ids.filter(|ids| ids.recording_id.is_some()))
+    }
+
+    pub fn save_musicbrainz_ids(&self, path: &str, ids: &MusicBrainzIds) -> Result<(), String> {
+        let path = canonical_path_string(path);
+        self.connection()?
+            .execute(
+                "UPDATE tracks SET mb_recording_id = ?2, mb_release_id = ?3, mb_artist_id = ?4
+                 WHERE path = ?1",
+                params![path, ids.recording_id, ids.release_id, ids.artist_id],
+            )
+            .map_err(|e| format!("Failed to save MusicBrainz IDs of {path}: {e}"))?;
+        Ok(())
+    }
+
     pub fn get_waveform_data(&self, path: &str) -> Result<Option<Vec<f32>>, String> {
         let conn = self.connection()?;
         let waveform_json: Option<String> = conn
@@ -130,15 +504,199 @@ impl DbManager {
         }
     }
 
-    pub fn save_waveform_data(&self, path: &str, waveform: &[f32]) -> Result<(), String> {
+    /// Returns `false` when `path` is not a library track (nothing was stored).
+    pub fn save_waveform_data(&self, path: &str, waveform: &[f32]) -> Result<bool, String> {
         let conn = self.connection()?;
         let waveform_json = serde_json::to_string(waveform)
             .map_err(|e| format!("Failed to serialize waveform cache for {path}: {e}"))?;
+        let updated = conn
+            .execute(
+                "UPDATE tracks SET waveform_data = ?1, updated_at = CURRENT_TIMESTAMP WHERE path = ?2",
+                params![waveform_json, path],
+            )
+            .map_err(|e| format!("Failed to store waveform cache for {path}: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    /// The stored tempo, or `None` when the track was never analyzed. `Some(None)` means
+    /// it was analyzed but has no steady beat, so there is no point in decoding it again.
+    pub fn get_track_bpm(&self, path: &str) -> Result<Option<Option<f32>>, String> {
+        let conn = self.connection()?;
+        let row = conn
+            .query_row(
+                "SELECT bpm, bpm_analyzed_at IS NOT NULL FROM tracks WHERE path = ?1",
+                params![path],
+                |row| Ok((row.get::<_, Option<f32>>(0)?, row.get::<_, bool>(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query BPM for {path}: {e}"))?;
+        Ok(row.and_then(|(bpm, analyzed)| (analyzed || bpm.is_some()).then_some(bpm)))
+    }
+
+    pub fn get_track_genre(&self, path: &str) -> Result<Option<String>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT genre FROM tracks WHERE path = ?1",
+            params![path],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map(Option::flatten)
+        .map_err(|e| format!("Failed to query genre for {path}: {e}"))
+    }
+
+    pub fn save_track_bpm(&self, path: &str, bpm: Option<f32>) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE tracks SET bpm = ?1, bpm_analyzed_at = CURRENT_TIMESTAMP, \
+             updated_at = CURRENT_TIMESTAMP WHERE path = ?2",
+            params![bpm, path],
+        )
+        .map_err(|e| format!("Failed to store BPM for {path}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn get_resume_position(&self, path: &str) -> Result<Option<f64>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT resume_seconds FROM tracks WHERE path = ?1",
+            params![path],
+            |row| row.get::<_, Option<f64>>(0),
+        )
+        .optional()
+        .map(Option::flatten)
+        .map_err(|e| format!("Failed to query resume position for {path}: {e}"))
+    }
+
+    /// Stores (or with `None` clears) where playback of a long-form track stopped.
+    /// Returns `false` when `path` is not a library track.
+    pub fn save_resume_position(&self, path: &str, seconds: Option<f64>) -> Result<bool, String> {
+        let updated = self
+            .connection()?
+            .execute(
+                "UPDATE tracks SET resume_seconds = ?2 WHERE path = ?1",
+                params![path, seconds],
+            )
+            .map_err(|e| format!("Failed to save resume position for {path}: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    /// Encoded play-count buckets (see `library::heatmap`).
+    pub fn get_play_heatmap(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT play_heatmap FROM tracks WHERE path = ?1",
+            params![path],
+            |row| row.get::<_, Option<Vec<u8>>>(0),
+        )
+        .optional()
+        .map(Option::flatten)
+        .map_err(|e| format!("Failed to query play heatmap for {path}: {e}"))
+    }
+
+    /// Returns `false` when `path` is not a library track.
+    pub fn save_play_heatmap(&self, path: &str, heatmap: &[u8]) -> Result<bool, String> {
+        let updated = self
+            .connection()?
+            .execute(
+                "UPDATE tracks SET play_heatmap = ?2 WHERE path = ?1",
+                params![path, heatmap],
+            )
+            .map_err(|e| format!("Failed to save play heatmap for {path}: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    pub fn get_loudness_profile(&self, path: &str) -> Result<Option<Vec<f32>>, String> {
+        let conn = self.connection()?;
+        let profile_json: Option<String> = conn
+            .query_row(
+                "SELECT loudness_profile FROM tracks WHERE path = ?1",
+                params![path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query loudness profile for {path}: {e}"))?
+            .flatten();
+
+        match profile_json {
+            Some(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| format!("Failed to parse loudness profile for {path}: {e}")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_loudness_profile(&self, path: &str, profile: &[f32]) -> Result<(), String> {
+        let conn = self.connection()?;
+        let profile_json = serde_json::to_string(profile)
+            .map_err(|e| format!("Failed to serialize loudness profile for {path}: {e}"))?;
+        conn.execute(
+            "UPDATE tracks SET loudness_profile = ?1, updated_at = CURRENT_TIMESTAMP WHERE path = ?2",
+            params![profile_json, path],
+        )
+        .map_err(|e| format!("Failed to store loudness profile for {path}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn get_fingerprint(&self, path: &str) -> Result<Option<AudioFingerprint>, String> {
+        let path = canonical_path_string(path);
+        let row = self
+            .connection()?
+            .query_row(
+                "SELECT fingerprint, fingerprint_duration FROM tracks WHERE path = ?1",
+                params![path],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<u32>>(1)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query fingerprint of {path}: {e}"))?;
+        Ok(row.and_then(|(fingerprint, duration)| {
+            Some(AudioFingerprint {
+                fingerprint: fingerprint?,
+                duration_seconds: duration.unwrap_or(0),
+            })
+        }))
+    }
+
+    pub fn save_fingerprint(
+        &self,
+        path: &str,
+        fingerprint: &AudioFingerprint,
+    ) -> Result<(), String> {
+        let path = canonical_path_string(path);
+        self.connection()?
+            .execute(
+                "UPDATE tracks SET fingerprint = ?2, fingerprint_duration = ?3 WHERE path = ?1",
+                params![path, fingerprint.fingerprint, fingerprint.duration_seconds],
+            )
+            .map_err(|e| format!("Failed to save fingerprint of {path}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map(Option::flatten)
+        .map_err(|e| format!("Failed to read setting {key}: {e}"))
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<(), String> {
+        let conn = self.connection()?;
         conn.execute(
-            "UPDATE tracks SET waveform_data = ?1, updated_at = CURRENT_TIMESTAMP WHERE path = ?2",
-            params![waveform_json, path],
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
         )
-        .map_err(|e| format!("Failed to store waveform cache for {path}: {e}"))?;
+        .map_err(|e| format!("Failed to store setting {key}: {e}"))?;
         Ok(())
     }
 
@@ -168,22 +726,306 @@ impl DbManager {
             CREATE TABLE IF NOT EXISTS settings (
                 key TEXT PRIMARY KEY,
                 value TEXT
-            );",
+            );
+            CREATE TABLE IF NOT EXISTS artists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE
+            );
+            CREATE TABLE IF NOT EXISTS track_artists (
+                track_id INTEGER NOT NULL,
+                artist_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                PRIMARY KEY (track_id, artist_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_track_artists_artist ON track_artists(artist_id);
+            CREATE TRIGGER IF NOT EXISTS tracks_artists_ad AFTER DELETE ON tracks BEGIN
+                DELETE FROM track_artists WHERE track_id = old.id;
+            END;",
         )
         .map_err(|e| format!("Failed to initialize DB schema: {e}"))?;
         self.ensure_track_column("art_url", "TEXT")?;
         self.ensure_track_column("corrupted", "INTEGER NOT NULL DEFAULT 0")?;
         self.ensure_track_column("waveform_data", "TEXT")?;
+        self.ensure_track_column("bpm", "REAL")?;
+        self.ensure_track_column("bpm_analyzed_at", "TEXT")?;
+        self.ensure_track_column("loudness_profile", "TEXT")?;
+        self.ensure_track_column("genre", "TEXT")?;
+        self.ensure_track_column("dsp_profile", "TEXT")?;
+        self.ensure_track_column("cue_source", "TEXT")?;
+        self.ensure_track_column("cue_start_seconds", "REAL")?;
+        self.ensure_track_column("cue_end_seconds", "REAL")?;
+        self.ensure_track_column("resume_seconds", "REAL")?;
+        self.ensure_track_column("play_heatmap", "BLOB")?;
+        self.ensure_track_column("play_count", "INTEGER NOT NULL DEFAULT 0")?;
+        self.ensure_track_column("last_played_at", "TEXT")?;
+        self.ensure_track_column("year", "INTEGER")?;
+        self.ensure_track_column("track_number", "INTEGER")?;
+        self.ensure_track_column("disc_number", "INTEGER")?;
+        self.ensure_track_column("album_artist", "TEXT")?;
+        self.ensure_track_column("composer", "TEXT")?;
+        self.ensure_track_column("file_mtime_ms", "INTEGER")?;
+        self.ensure_track_column("file_size", "INTEGER")?;
+        self.ensure_track_column("mb_recording_id", "TEXT")?;
+        self.ensure_track_column("mb_release_id", "TEXT")?;
+        self.ensure_track_column("mb_artist_id", "TEXT")?;
+        self.ensure_track_column("fingerprint", "TEXT")?;
+        self.ensure_track_column("fingerprint_duration", "INTEGER")?;
+        self.ensure_track_column("rating", "INTEGER")?;
+        link_missing_track_artists(&conn)?;
         Ok(())
     }
 
+    /// Re-points a library row at a moved file, keeping its id and cached analysis.
+    /// Returns `false` when `from` is not a library track.
+    pub fn rename_track_path(&self, from: &str, to: &str) -> Result<bool, String> {
+        let from = canonical_path_string(from);
+        let to = canonical_path_string(to);
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start moving track {from}: {e}"))?;
+        let updated = tx
+            .execute(
+                "UPDATE tracks SET path = ?2, updated_at = CURRENT_TIMESTAMP WHERE path = ?1",
+                params![from, to],
+            )
+            .map_err(|e| format!("Failed to move track {from} to {to}: {e}"))?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        move_track_path_references(&tx, &from, &to)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit moving track {from}: {e}"))?;
+        Ok(true)
+    }
+
+    /// Creates a row for a copied file with the source row's metadata and cached
+    /// analysis. Returns `false` when `from` is not a library track.
+    pub fn duplicate_track(&self, from: &str, to: &str) -> Result<bool, String> {
+        let from = canonical_path_string(from);
+        let to = canonical_path_string(to);
+        let columns = COPIED_TRACK_COLUMNS
+            .iter()
+            .chain(MERGED_TRACK_COLUMNS)
+            .copied()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let inserted = self
+            .connection()?
+            .execute(
+                &format!(
+                    "INSERT INTO tracks (path, {columns}) SELECT ?2, {columns} FROM tracks WHERE path = ?1
+                     ON CONFLICT(path) DO NOTHING"
+                ),
+                params![from, to],
+            )
+            .map_err(|e| format!("Failed to copy track {from} to {to}: {e}"))?;
+        link_missing_track_artists(&*self.connection()?)?;
+        Ok(inserted > 0)
+    }
+
     pub fn delete_track(&self, path: &str) -> Result<(), String> {
+        let canonical = canonical_path_string(path);
         self.connection()?
-            .execute("DELETE FROM tracks WHERE path = ?1", params![path])
+            .execute(
+                "DELETE FROM tracks WHERE path IN (?1, ?2)",
+                params![path, canonical],
+            )
             .map_err(|e| format!("Failed to delete track {path}: {e}"))?;
         Ok(())
     }
 
+    /// Deletes the row of `path` and its per-track settings (`TRACK_SETTING_TABLES`), then
+    /// calls `remove_file`, all in one write transaction: when removing the file fails,
+    /// nothing is deleted. Play history and playlist entries are kept.
+    pub fn delete_track_with(
+        &self,
+        path: &str,
+        remove_file: impl FnOnce() -> Result<(), String>,
+    ) -> Result<(), String> {
+        let canonical = canonical_path_string(path);
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("Failed to start deleting track {path}: {e}"))?;
+        tx.execute(
+            "DELETE FROM tracks WHERE path IN (?1, ?2)",
+            params![path, canonical],
+        )
+        .map_err(|e| format!("Failed to delete track {path}: {e}"))?;
+        delete_track_settings(&tx, &canonical)?;
+        remove_file()?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit deleting track {path}: {e}"))
+    }
+
+    /// Deletes the row of a track whose file is already gone, with its per-track
+    /// settings, like `delete_track_with`.
+    pub fn delete_track_and_settings(&self, path: &str) -> Result<(), String> {
+        self.delete_track_with(path, || Ok(()))
+    }
+
+    /// Records where a cue sheet song lives in its source file; `end_seconds` is `None`
+    /// for the last song of a file. The row must already exist (see `save_track`).
+    pub fn save_track_segment(
+        &self,
+        path: &str,
+        source: &str,
+        start_seconds: f64,
+        end_seconds: Option<f64>,
+    ) -> Result<(), String> {
+        let path = canonical_path_string(path);
+        let source = canonical_path_string(source);
+        self.connection()?
+            .execute(
+                "UPDATE tracks SET cue_source = ?2, cue_start_seconds = ?3, cue_end_seconds = ?4,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE path = ?1",
+                params![path, source, start_seconds, end_seconds],
+            )
+            .map_err(|e| format!("Failed to save cue offsets of {path}: {e}"))?;
+        Ok(())
+    }
+
+    /// Paths of the cue sheet songs cut from `source`.
+    pub fn get_cue_track_paths(&self, source: &str) -> Result<Vec<String>, String> {
+        let source = canonical_path_string(source);
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT path FROM tracks WHERE cue_source = ?1 ORDER BY path")
+            .map_err(|e| format!("Failed to prepare cue track query: {e}"))?;
+        let paths = stmt
+            .query_map(params![source], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query cue tracks of {source}: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read cue tracks of {source}: {e}"))?;
+        Ok(paths)
+    }
+
+    /// Deletes the rows whose path starts with `prefix` (the virtual tracks of one cue
+    /// sheet) except those in `keep`. Returns the source files of the removed rows.
+    pub fn delete_cue_tracks(&self, prefix: &str, keep: &[String]) -> Result<Vec<String>, String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start cue track cleanup: {e}"))?;
+        let stale = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT path, cue_source FROM tracks WHERE substr(path, 1, length(?1)) = ?1",
+                )
+                .map_err(|e| format!("Failed to prepare cue track cleanup: {e}"))?;
+            let rows = stmt
+                .query_map(params![prefix], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+                })
+                .map_err(|e| format!("Failed to query cue tracks under {prefix}: {e}"))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read cue tracks under {prefix}: {e}"))?;
+            rows.into_iter()
+                .filter(|(path, _)| !keep.contains(path))
+                .collect::<Vec<_>>()
+        };
+        let mut sources = Vec::new();
+        for (path, source) in stale {
+            tx.execute("DELETE FROM tracks WHERE path = ?1", params![path])
+                .map_err(|e| format!("Failed to delete cue track {path}: {e}"))?;
+            if let Some(source) = source.filter(|source| !sources.contains(source)) {
+                sources.push(source);
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit cue track cleanup: {e}"))?;
+        Ok(sources)
+    }
+
+    /// One-time migration for libraries scanned before paths were canonicalized:
+    /// rows whose paths resolve to the same file are merged into a single row keyed
+    /// by the canonical path. Returns the number of rows removed.
+    pub fn merge_duplicate_track_paths(&self) -> Result<usize, String> {
+        let mut conn = self.connection()?;
+        let already_done: Option<String> = conn
+            .query_row(
+                "SELECT value FROM settings WHERE key = ?1",
+                params![PATHS_NORMALIZED_SETTING],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read path normalization flag: {e}"))?;
+        if already_done.is_some() {
+            return Ok(0);
+        }
+
+        let rows = {
+            let mut stmt = conn
+                .prepare("SELECT id, path FROM tracks ORDER BY id")
+                .map_err(|e| format!("Failed to prepare track path query: {e}"))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|e| format!("Failed to query track paths: {e}"))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read track paths: {e}"))?;
+            rows
+        };
+
+        let mut groups: HashMap<String, Vec<(i64, String)>> = HashMap::new();
+        for (id, path) in rows {
+            groups
+                .entry(canonical_path_string(&path))
+                .or_default()
+                .push((id, path));
+        }
+
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start path migration: {e}"))?;
+        let merge_sql = format!(
+            "UPDATE tracks SET {} WHERE id = ?1",
+            MERGED_TRACK_COLUMNS
+                .iter()
+                .map(|column| format!(
+                    "{column} = COALESCE({column}, (SELECT {column} FROM tracks WHERE id = ?2))"
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let mut removed = 0;
+        for (canonical, entries) in groups {
+            // Prefer the row already stored under the canonical spelling, else the oldest.
+            let (keeper, keeper_path) = entries
+                .iter()
+                .find(|(_, path)| *path == canonical)
+                .unwrap_or(&entries[0]);
+            let keeper = *keeper;
+            // The keeper's settings go first so a duplicate's cannot displace them.
+            move_track_path_references(&tx, keeper_path, &canonical)?;
+            for (id, path) in entries.iter().filter(|(id, _)| *id != keeper) {
+                move_track_path_references(&tx, path, &canonical)?;
+                tx.execute(&merge_sql, params![keeper, id])
+                    .map_err(|e| format!("Failed to merge duplicate track {canonical}: {e}"))?;
+                tx.execute("DELETE FROM tracks WHERE id = ?1", params![id])
+                    .map_err(|e| format!("Failed to remove duplicate track {canonical}: {e}"))?;
+                removed += 1;
+            }
+            tx.execute(
+                "UPDATE tracks SET path = ?1 WHERE id = ?2 AND path <> ?1",
+                params![canonical, keeper],
+            )
+            .map_err(|e| format!("Failed to normalize track path {canonical}: {e}"))?;
+        }
+        tx.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, '1')
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![PATHS_NORMALIZED_SETTING],
+        )
+        .map_err(|e| format!("Failed to record path normalization: {e}"))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit path migration: {e}"))?;
+        Ok(removed)
+    }
+
     fn ensure_track_column(&self, name: &str, definition: &str) -> Result<(), String> {
         let conn = self.connection()?;
         let mut stmt = conn
@@ -214,18 +1056,220 @@ impl DbManager {
     }
 }
 
+/// Names of the tables in the database; the settings tables are created by their stores.
+fn table_names(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .map_err(|e| format!("Failed to prepare table list query: {e}"))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to list tables: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read table list: {e}"))?;
+    Ok(names)
+}
+
+/// Deletes every settings group from `TRACK_SETTING_TABLES` stored for track `path`.
+pub(crate) fn delete_track_settings(conn: &Connection, path: &str) -> Result<(), String> {
+    let existing = table_names(conn)?;
+    for (table, column, filter) in TRACK_SETTING_TABLES
+        .iter()
+        .copied()
+        .flatten()
+        .filter(|(table, _, _)| existing.iter().any(|name| name == table))
+    {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE {column} = ?1{filter}"),
+            params![path],
+        )
+        .map_err(|e| format!("Failed to delete {table} of {path}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Re-keys what is stored for track path `from` outside its `tracks` row to `to`:
+/// settings groups from `TRACK_SETTING_TABLES` (dropped when `to` has its own),
+/// history and playlist entries, and the cue sheet songs played from the file.
+fn move_track_path_references(conn: &Connection, from: &str, to: &str) -> Result<(), String> {
+    if from == to {
+        return Ok(());
+    }
+    let existing = table_names(conn)?;
+    let exists = |table: &str| existing.iter().any(|name| name == table);
+
+    for group in TRACK_SETTING_TABLES {
+        let tables = group
+            .iter()
+            .filter(|(table, _, _)| exists(table))
+            .collect::<Vec<_>>();
+        let mut taken = false;
+        for (table, column, filter) in &tables {
+            taken |= conn
+                .query_row(
+                    &format!("SELECT EXISTS(SELECT 1 FROM {table} WHERE {column} = ?1{filter})"),
+                    params![to],
+                    |row| row.get::<_, bool>(0),
+                )
+                .map_err(|e| format!("Failed to look up {table} of {to}: {e}"))?;
+        }
+        for (table, column, filter) in tables {
+            if taken {
+                conn.execute(
+                    &format!("DELETE FROM {table} WHERE {column} = ?1{filter}"),
+                    params![from],
+                )
+            } else {
+                conn.execute(
+                    &format!("UPDATE {table} SET {column} = ?2 WHERE {column} = ?1{filter}"),
+                    params![from, to],
+                )
+            }
+            .map_err(|e| format!("Failed to move {table} of {from} to {to}: {e}"))?;
+        }
+    }
+    for (table, column) in TRACK_ENTRY_TABLES.iter().filter(|(table, _)| exists(table)) {
+        conn.execute(
+            &format!("UPDATE {table} SET {column} = ?2 WHERE {column} = ?1"),
+            params![from, to],
+        )
+        .map_err(|e| format!("Failed to move {table} of {from} to {to}: {e}"))?;
+    }
+    conn.execute(
+        "UPDATE tracks SET cue_source = ?2 WHERE cue_source = ?1",
+        params![from, to],
+    )
+    .map_err(|e| format!("Failed to move cue sheet songs of {from} to {to}: {e}"))?;
+    Ok(())
+}
+
+/// Inserts or updates the row of `track` and its album.
+fn save_track_row(conn: &Connection, track: &TrackInput) -> Result<(), String> {
+    let path = canonical_path_string(&track.path);
+    conn.execute(
+        "INSERT INTO tracks (path, title, artist, album, genre, year, track_number, disc_number, album_artist, composer, duration_seconds, sample_rate, art_url, corrupted)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+         ON CONFLICT(path) DO UPDATE SET
+              title = excluded.title,
+              artist = excluded.artist,
+              album = excluded.album,
+              genre = excluded.genre,
+              year = excluded.year,
+              track_number = excluded.track_number,
+              disc_number = excluded.disc_number,
+              album_artist = CASE
+                  WHEN COALESCE(excluded.album_artist, '') = '' AND tracks.album_artist = ?15
+                       AND excluded.album IS tracks.album
+                  THEN tracks.album_artist
+                  ELSE excluded.album_artist
+              END,
+              composer = excluded.composer,
+              duration_seconds = excluded.duration_seconds,
+              sample_rate = excluded.sample_rate,
+              art_url = excluded.art_url,
+              corrupted = excluded.corrupted,
+              updated_at = CURRENT_TIMESTAMP",
+        params![
+            path,
+            track.title,
+            track.artist,
+            track.album,
+            track.genre,
+            track.year,
+            track.track_number,
+            track.disc_number,
+            track.album_artist,
+            track.composer,
+            track.duration_seconds,
+            track.sample_rate,
+            track.art_url,
+            track.corrupted as i32,
+            // A detected compilation stays grouped when the tags are saved again.
+            VARIOUS_ARTISTS
+        ],
+    )
+    .map_err(|e| format!("Failed to save track {}: {e}", track.path))?;
+    let track_id = conn
+        .query_row(
+            "SELECT id FROM tracks WHERE path = ?1",
+            params![path],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| format!("Failed to read id of track {}: {e}", track.path))?;
+    save_track_artists(conn, track_id, track.artist.as_deref())?;
+
+    // Empty/blank album names are intentionally skipped to keep the albums table normalized.
+    if let Some(album) = track.album.as_ref().filter(|name| !name.trim().is_empty()) {
+        conn.execute(
+            "INSERT INTO albums (name, artist) VALUES (?1, ?2)
+             ON CONFLICT(name, artist) DO NOTHING",
+            params![album, track_album_artist(track)],
+        )
+        .map_err(|e| format!("Failed to save album {}: {e}", album))?;
+    }
+
+    Ok(())
+}
+
+fn save_file_stamp_row(conn: &Connection, path: &str, stamp: FileStamp) -> Result<(), String> {
+    conn.execute(
+        "UPDATE tracks SET file_mtime_ms = ?2, file_size = ?3 WHERE path = ?1",
+        params![path, stamp.modified_ms, stamp.size],
+    )
+    .map_err(|e| format!("Failed to save file stamp of {path}: {e}"))?;
+    Ok(())
+}
+
+/// Artist an album row is keyed by: the album artist, else the track artist. Matches
+/// `album_artist_sql`.
+fn track_album_artist(track: &TrackInput) -> Option<&String> {
+    track
+        .album_artist
+        .as_ref()
+        .filter(|name| !name.is_empty())
+        .or(track.artist.as_ref())
+}
+
+/// SQL for the artist the album of a row of `table` is grouped under.
+pub(super) fn album_artist_sql(table: &str) -> String {
+    format!("COALESCE(NULLIF({table}.album_artist, ''), {table}.artist)")
+}
+
+pub(super) fn track_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<TrackRecord> {
+    Ok(TrackRecord {
+        path: row.get(0)?,
+        title: row.get(1)?,
+        artist: row.get(2)?,
+        album: row.get(3)?,
+        genre: row.get(4)?,
+        year: row.get(5)?,
+        track_number: row.get(6)?,
+        disc_number: row.get(7)?,
+        album_artist: row.get(8)?,
+        composer: row.get(9)?,
+        duration_seconds: row.get(10)?,
+        sample_rate: row.get(11)?,
+        art_url: row.get(12)?,
+        corrupted: row.get::<_, i32>(13)? != 0,
+        bpm: row.get(14)?,
+        play_count: row.get(15)?,
+        last_played_at: row.get(16)?,
+        rating: row.get(17)?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{DbManager, TrackInput};
+    use super::{
+        canonical_path_string, DbManager, SortDirection, TrackInput, TrackPlayQuery, TrackSort,
+        TrackSortKey,
+    };
+    use crate::audio::dsp::profiles::{DspProfile, DspProfileScope};
+    use crate::test_support::unique_temp_path;
+    use rusqlite::params;
     use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
     fn unique_db_path() -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        std::env::temp_dir().join(format!("powerplayer-test-{nanos}.db"))
+        unique_temp_path("test.db")
     }
 
     #[test]
@@ -237,6 +1281,12 @@ mod tests {
             title: Some("Song A".to_string()),
             artist: Some("Artist A".to_string()),
             album: Some("Album A".to_string()),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
             duration_seconds: Some(120.0),
             sample_rate: Some(48_000),
             art_url: Some("asset:///tmp/art.jpg".to_string()),
@@ -256,6 +1306,34 @@ mod tests {
         assert!(!rows[0].corrupted);
     }
 
+    #[test]
+    fn tracks_are_found_by_file_name() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        for path in ["/music/a_b.flac", "/music/axb.flac", "/other/A_B.FLAC"] {
+            let track = TrackInput {
+                path: path.to_string(),
+                title: None,
+                artist: None,
+                album: None,
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            };
+            db.save_track(&track).expect("save should work");
+        }
+        let mut paths = db.get_track_paths_named("a_b.flac").expect("query");
+        paths.sort();
+        // `_` is literal, and the match ignores ASCII case.
+        assert_eq!(paths, ["/music/a_b.flac", "/other/A_B.FLAC"]);
+    }
+
     #[test]
     fn delete_track_removes_row() {
         let path = unique_db_path();
@@ -265,6 +1343,12 @@ mod tests {
             title: None,
             artist: None,
             album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
             duration_seconds: None,
             sample_rate: None,
             art_url: None,
@@ -277,6 +1361,61 @@ mod tests {
         assert!(rows.is_empty());
     }
 
+    #[test]
+    fn deleting_a_track_with_its_file_clears_its_settings() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        db.initialize_spatial_schema()
+            .expect("spatial schema should initialize");
+        db.initialize_plugin_schema()
+            .expect("plugin schema should initialize");
+        db.initialize_dsp_profile_schema()
+            .expect("dsp profile schema should initialize");
+        let track = TrackInput {
+            path: "/music/kept.flac".to_string(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        };
+        db.save_track(&track).expect("save should work");
+        db.save_spatial_scene(&track.path, "vocals", 1.0, 0.0, 0.0, true)
+            .expect("scene should be saved");
+        db.save_plugin_chain(Some(&track.path), &[])
+            .expect("plugin chain should be saved");
+        db.save_dsp_profile("Loud", &DspProfile::default())
+            .expect("profile should be saved");
+        db.assign_dsp_profile(DspProfileScope::Track, &track.path, Some("Loud"))
+            .expect("profile should be assigned");
+        let settings = |db: &DbManager| {
+            (
+                db.load_spatial_scene(&track.path).expect("scene").len(),
+                db.has_plugin_chain(&track.path).expect("plugin chain"),
+                db.assigned_dsp_profile(DspProfileScope::Track, &track.path)
+                    .expect("assignment")
+                    .is_some(),
+            )
+        };
+
+        let result = db.delete_track_with(&track.path, || Err("file is locked".to_string()));
+        assert_eq!(result, Err("file is locked".to_string()));
+        assert!(db.get_track(&track.path).expect("lookup").is_some());
+        assert_eq!(settings(&db), (1, true, true), "a failed removal keeps all");
+
+        db.delete_track_with(&track.path, || Ok(()))
+            .expect("delete should work");
+        assert!(db.get_track(&track.path).expect("lookup").is_none());
+        assert_eq!(settings(&db), (0, false, false));
+    }
+
     #[test]
     fn waveform_cache_roundtrip() {
         let path = unique_db_path();
@@ -286,6 +1425,12 @@ mod tests {
             title: None,
             artist: None,
             album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
             duration_seconds: None,
             sample_rate: None,
             art_url: None,
@@ -302,4 +1447,373 @@ mod tests {
             .expect("waveform should exist");
         assert_eq!(loaded, waveform);
     }
+
+    #[test]
+    fn bpm_roundtrip() {
+        let path = unique_db_path();
+        let db = DbManager::new(&path).expect("db should initialize");
+        let track = TrackInput {
+            path: "/music/tempo.flac".to_string(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        };
+        db.save_track(&track).expect("save should work");
+        assert_eq!(db.get_track_bpm(&track.path).expect("bpm query"), None);
+
+        db.save_track_bpm(&track.path, None)
+            .expect("bpm save should work");
+        assert_eq!(
+            db.get_track_bpm(&track.path).expect("bpm query"),
+            Some(None),
+            "a track without a steady beat is not analyzed again"
+        );
+
+        db.save_track_bpm(&track.path, Some(128.0))
+            .expect("bpm save should work");
+        assert_eq!(
+            db.get_track_bpm(&track.path).expect("bpm query"),
+            Some(Some(128.0))
+        );
+        let rows = db.get_tracks().expect("tracks should load");
+        assert_eq!(rows[0].bpm, Some(128.0));
+    }
+
+    #[test]
+    fn duplicate_path_spellings_are_merged() {
+        let dir = unique_temp_path("dupes");
+        std::fs::create_dir_all(dir.join("sub")).expect("dirs should be created");
+        let file = dir.join("song.flac");
+        std::fs::write(&file, b"x").expect("file should be written");
+        let alias = dir.join("sub").join("..").join("song.flac");
+
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        db.initialize_spatial_schema().expect("spatial schema");
+        let alias_path = alias.to_string_lossy().to_string();
+        db.save_spatial_scene(&alias_path, "vocals", 1.0, 2.0, 3.0, true)
+            .expect("scene should be saved");
+        {
+            // Simulate rows written before save_track canonicalized paths.
+            let conn = db.connection().expect("connection");
+            conn.execute(
+                "INSERT INTO tracks (path, title) VALUES (?1, 'Song')",
+                params![file.to_string_lossy().to_string()],
+            )
+            .expect("insert direct");
+            conn.execute(
+                "INSERT INTO tracks (path, title, bpm) VALUES (?1, 'Song', 96.0)",
+                params![alias.to_string_lossy().to_string()],
+            )
+            .expect("insert alias");
+        }
+
+        assert_eq!(db.merge_duplicate_track_paths().expect("merge"), 1);
+        let rows = db.get_tracks().expect("tracks should load");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].path, canonical_path_string(&file.to_string_lossy()));
+        assert_eq!(rows[0].bpm, Some(96.0));
+        assert_eq!(
+            db.load_spatial_scene(&rows[0].path)
+                .expect("scene query")
+                .len(),
+            1,
+            "the duplicate's spatial scene is kept"
+        );
+        assert!(db
+            .load_spatial_scene(&alias_path)
+            .expect("scene query")
+            .is_empty());
+
+        // The migration only runs once.
+        assert_eq!(db.merge_duplicate_track_paths().expect("merge"), 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn settings_roundtrip() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        assert_eq!(db.get_setting("theme").expect("read"), None);
+        db.set_setting("theme", "dark").expect("write");
+        db.set_setting("theme", "light").expect("overwrite");
+        assert_eq!(
+            db.get_setting("theme").expect("read"),
+            Some("light".to_string())
+        );
+    }
+
+    #[test]
+    fn moved_and_copied_tracks_keep_cached_analysis() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        let track = TrackInput {
+            path: "/music/a.flac".to_string(),
+            title: Some("A".to_string()),
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: Some(10.0),
+            sample_rate: Some(44_100),
+            art_url: None,
+            corrupted: false,
+        };
+        db.initialize_spatial_schema().expect("spatial schema");
+        db.initialize_history_schema().expect("history schema");
+        db.save_track(&track).expect("track should be saved");
+        db.save_track_bpm("/music/a.flac", Some(128.0))
+            .expect("bpm should be saved");
+        db.save_spatial_scene("/music/a.flac", "vocals", 1.0, 2.0, 3.0, true)
+            .expect("scene should be saved");
+        db.add_play_history("/music/a.flac", 100.0)
+            .expect("play should be recorded");
+
+        assert!(db
+            .rename_track_path("/music/a.flac", "/archive/a.flac")
+            .expect("rename should work"));
+        assert!(db
+            .load_spatial_scene("/music/a.flac")
+            .expect("scene query")
+            .is_empty());
+        let scene = db
+            .load_spatial_scene("/archive/a.flac")
+            .expect("scene query");
+        assert_eq!(scene.len(), 1, "the spatial scene follows the file");
+        assert_eq!(
+            db.get_play_history(10).expect("history")[0].path,
+            "/archive/a.flac"
+        );
+        assert!(!db
+            .rename_track_path("/music/a.flac", "/elsewhere/a.flac")
+            .expect("missing source is not an error"));
+        assert!(db
+            .duplicate_track("/archive/a.flac", "/backup/a.flac")
+            .expect("copy should work"));
+
+        let tracks = db.get_tracks().expect("tracks should load");
+        let mut paths = tracks.iter().map(|t| t.path.as_str()).collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, vec!["/archive/a.flac", "/backup/a.flac"]);
+        assert!(tracks
+            .iter()
+            .all(|t| t.bpm == Some(128.0) && t.title.as_deref() == Some("A")));
+    }
+
+    #[test]
+    fn loudness_profile_roundtrip() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        let track = TrackInput {
+            path: "/music/loud.flac".to_string(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        };
+        db.save_track(&track).expect("save should work");
+        assert_eq!(
+            db.get_loudness_profile(&track.path).expect("query"),
+            None
+        );
+        db.save_loudness_profile(&track.path, &[-14.0, -9.5, -70.0])
+            .expect("profile save should work");
+        assert_eq!(
+            db.get_loudness_profile(&track.path).expect("query"),
+            Some(vec![-14.0, -9.5, -70.0])
+        );
+    }
+
+    #[test]
+    fn resume_position_roundtrip() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        let track = TrackInput {
+            path: "/books/novel.m4b".to_string(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: Some(36_000.0),
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        };
+        db.save_track(&track).expect("save should work");
+        assert!(!db
+            .save_resume_position("/books/missing.m4b", Some(1.0))
+            .expect("missing track is not an error"));
+
+        assert!(db
+            .save_resume_position(&track.path, Some(1_234.5))
+            .expect("resume save should work"));
+        assert_eq!(
+            db.get_resume_position(&track.path).expect("resume query"),
+            Some(1_234.5)
+        );
+        db.save_resume_position(&track.path, None)
+            .expect("resume clear should work");
+        assert_eq!(
+            db.get_resume_position(&track.path).expect("resume query"),
+            None
+        );
+    }
+
+    #[test]
+    fn play_counts_order_and_filter_tracks() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        for name in ["a", "b", "c", "d"] {
+            db.save_track(&TrackInput {
+                path: format!("/music/{name}.flac"),
+                title: Some(name.to_uppercase()),
+                artist: None,
+                album: None,
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: Some(100.0),
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save should work");
+        }
+        for name in ["b", "c", "b", "d"] {
+            let path = format!("/music/{name}.flac");
+            assert!(db.record_track_play(&path).expect("count play"));
+        }
+        assert!(!db
+            .record_track_play("/music/missing.flac")
+            .expect("missing track is not an error"));
+        db.connection()
+            .expect("connection")
+            .execute(
+                "UPDATE tracks SET last_played_at = datetime('now', '-1 year') WHERE path = ?1",
+                params!["/music/d.flac"],
+            )
+            .expect("backdate play");
+
+        let paths = |query| {
+            db.get_tracks_by_plays(query, 10)
+                .expect("query")
+                .into_iter()
+                .map(|track| track.path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(TrackPlayQuery::MostPlayed),
+            ["/music/b.flac", "/music/c.flac", "/music/d.flac"]
+        );
+        assert_eq!(paths(TrackPlayQuery::NeverPlayed), ["/music/a.flac"]);
+        assert_eq!(
+            paths(TrackPlayQuery::NotPlayedFor { months: 6 }),
+            ["/music/d.flac"]
+        );
+        let top = db
+            .get_tracks_by_plays(TrackPlayQuery::MostPlayed, 1)
+            .expect("query");
+        assert_eq!(top.len(), 1);
+        let b = db
+            .get_tracks()
+            .expect("tracks should load")
+            .into_iter()
+            .find(|track| track.path == "/music/b.flac")
+            .expect("b");
+        assert_eq!(b.play_count, 2);
+        assert!(b.last_played_at.is_some());
+    }
+
+    #[test]
+    fn tracks_sort_by_key_with_unset_values_last() {
+        let db = DbManager::new(unique_db_path()).expect("db should initialize");
+        for (name, title, duration) in [
+            ("a", Some("beta"), Some(200.0)),
+            ("b", Some("Alpha"), None),
+            ("c", None, Some(100.0)),
+        ] {
+            db.save_track(&TrackInput {
+                path: format!("/music/{name}.flac"),
+                title: title.map(str::to_string),
+                artist: None,
+                album: None,
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: duration,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save should work");
+        }
+        assert!(db.set_track_rating("/music/c.flac", Some(5)).expect("rate"));
+        assert!(db.set_track_rating("/music/a.flac", Some(2)).expect("rate"));
+        assert!(db.set_track_rating("/music/a.flac", Some(6)).is_err());
+        assert!(!db
+            .set_track_rating("/music/missing.flac", Some(3))
+            .expect("missing track is not an error"));
+
+        let paths = |key, direction| {
+            db.get_tracks_sorted(TrackSort { key, direction }, 0, None)
+                .expect("query")
+                .into_iter()
+                .map(|track| track.path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(TrackSortKey::Title, SortDirection::Asc),
+            ["/music/b.flac", "/music/a.flac", "/music/c.flac"]
+        );
+        assert_eq!(
+            paths(TrackSortKey::Title, SortDirection::Desc),
+            ["/music/a.flac", "/music/b.flac", "/music/c.flac"]
+        );
+        assert_eq!(
+            paths(TrackSortKey::Duration, SortDirection::Desc),
+            ["/music/a.flac", "/music/c.flac", "/music/b.flac"]
+        );
+        assert_eq!(
+            paths(TrackSortKey::Rating, SortDirection::Desc),
+            ["/music/c.flac", "/music/a.flac", "/music/b.flac"]
+        );
+
+        let page = db
+            .get_tracks_sorted(TrackSort::default(), 1, Some(1))
+            .expect("page");
+        assert_eq!(page.len(), 1);
+        assert_eq!(
+            page[0].path,
+            paths(TrackSortKey::Artist, SortDirection::Asc)[1]
+        );
+        assert_eq!(db.get_track_count(), Ok(3));
+    }
 }
@@ -1,3 +1,13 @@
+pub mod album_store;
+pub mod artist_store;
+pub mod chain_preset_store;
+pub mod dsp_profile_store;
+pub mod eq_preset_store;
+pub mod history_store;
+pub mod library_root_store;
 pub mod manager;
+pub mod playlist_store;
+pub mod plugin_store;
 pub mod search;
 pub mod spatial_store;
+pub mod station_store;
@@ -0,0 +1,234 @@
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+
+use crate::db::manager::DbManager;
+use crate::library::paths::canonical_path_string;
+
+/// Row from the `playlists` table, with the number of entries it holds.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct PlaylistRow {
+    pub id: i64,
+    pub name: String,
+    pub track_count: usize,
+}
+
+impl DbManager {
+    /// Create the playlist tables if they don't exist. Entries are track paths in play
+    /// order; a path may appear more than once.
+    pub fn initialize_playlist_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS playlists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE IF NOT EXISTS playlist_tracks (
+                playlist_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                PRIMARY KEY (playlist_id, position)
+            );",
+        )
+        .map_err(|e| format!("Failed to create playlist tables: {e}"))?;
+        Ok(())
+    }
+
+    /// Creates an empty playlist and returns its id. Names are unique, ignoring case.
+    pub fn create_playlist(&self, name: &str) -> Result<i64, String> {
+        let name = playlist_name(name)?;
+        self.connection()?
+            .query_row(
+                "INSERT INTO playlists (name) VALUES (?1) RETURNING id",
+                params![name],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to create playlist {name}: {e}"))
+    }
+
+    pub fn rename_playlist(&self, id: i64, name: &str) -> Result<(), String> {
+        let name = playlist_name(name)?;
+        let updated = self
+            .connection()?
+            .execute(
+                "UPDATE playlists SET name = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![id, name],
+            )
+            .map_err(|e| format!("Failed to rename playlist {id} to {name}: {e}"))?;
+        if updated == 0 {
+            return Err(format!("Playlist {id} does not exist"));
+        }
+        Ok(())
+    }
+
+    pub fn delete_playlist(&self, id: i64) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start deleting playlist {id}: {e}"))?;
+        tx.execute(
+            "DELETE FROM playlist_tracks WHERE playlist_id = ?1",
+            params![id],
+        )
+        .map_err(|e| format!("Failed to delete tracks of playlist {id}: {e}"))?;
+        tx.execute("DELETE FROM playlists WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete playlist {id}: {e}"))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit deleting playlist {id}: {e}"))
+    }
+
+    pub fn get_playlists(&self) -> Result<Vec<PlaylistRow>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT p.id, p.name, COUNT(t.path)
+                 FROM playlists p LEFT JOIN playlist_tracks t ON t.playlist_id = p.id
+                 GROUP BY p.id
+                 ORDER BY p.name COLLATE NOCASE",
+            )
+            .map_err(|e| format!("Failed to prepare playlist query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(PlaylistRow {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    track_count: row.get::<_, i64>(2)? as usize,
+                })
+            })
+            .map_err(|e| format!("Failed to query playlists: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read playlists: {e}"))?;
+        Ok(rows)
+    }
+
+    /// Track paths of playlist `id` in play order.
+    pub fn get_playlist_tracks(&self, id: i64) -> Result<Vec<String>, String> {
+        let conn = self.connection()?;
+        read_playlist_tracks(&conn, id)
+    }
+
+    /// Replaces the entries of playlist `id` with `paths`, in one transaction.
+    pub fn set_playlist_tracks(&self, id: i64, paths: &[String]) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start updating playlist {id}: {e}"))?;
+        write_playlist_tracks(&tx, id, paths)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit playlist {id}: {e}"))
+    }
+
+    /// Reads the entries of playlist `id`, lets `edit` change them and writes them back,
+    /// all in one write transaction so concurrent edits cannot undo each other. Nothing
+    /// is written if `edit` fails. Returns the updated entries.
+    pub fn edit_playlist_tracks(
+        &self,
+        id: i64,
+        edit: impl FnOnce(&mut Vec<String>) -> Result<(), String>,
+    ) -> Result<Vec<String>, String> {
+        let mut conn = self.connection()?;
+        // Take the write lock up front: a deferred transaction would let two edits read
+        // the same entries before either writes.
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("Failed to start updating playlist {id}: {e}"))?;
+        let mut paths = read_playlist_tracks(&tx, id)?;
+        edit(&mut paths)?;
+        write_playlist_tracks(&tx, id, &paths)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit playlist {id}: {e}"))?;
+        Ok(paths)
+    }
+}
+
+fn read_playlist_tracks(conn: &Connection, id: i64) -> Result<Vec<String>, String> {
+    let exists = conn
+        .query_row("SELECT 1 FROM playlists WHERE id = ?1", params![id], |_| {
+            Ok(())
+        })
+        .optional()
+        .map_err(|e| format!("Failed to look up playlist {id}: {e}"))?
+        .is_some();
+    if !exists {
+        return Err(format!("Playlist {id} does not exist"));
+    }
+    let mut stmt = conn
+        .prepare("SELECT path FROM playlist_tracks WHERE playlist_id = ?1 ORDER BY position")
+        .map_err(|e| format!("Failed to prepare playlist track query: {e}"))?;
+    let paths = stmt
+        .query_map(params![id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to query tracks of playlist {id}: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read tracks of playlist {id}: {e}"))?;
+    Ok(paths)
+}
+
+/// Replaces the entries of playlist `id` inside the caller's transaction.
+fn write_playlist_tracks(tx: &Transaction<'_>, id: i64, paths: &[String]) -> Result<(), String> {
+    let updated = tx
+        .execute(
+            "UPDATE playlists SET updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| format!("Failed to update playlist {id}: {e}"))?;
+    if updated == 0 {
+        return Err(format!("Playlist {id} does not exist"));
+    }
+    tx.execute(
+        "DELETE FROM playlist_tracks WHERE playlist_id = ?1",
+        params![id],
+    )
+    .map_err(|e| format!("Failed to clear playlist {id}: {e}"))?;
+    for (position, path) in paths.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO playlist_tracks (playlist_id, position, path) VALUES (?1, ?2, ?3)",
+            params![id, position as i64, canonical_path_string(path)],
+        )
+        .map_err(|e| format!("Failed to add {path} to playlist {id}: {e}"))?;
+    }
+    Ok(())
+}
+
+fn playlist_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Playlist name cannot be empty".to_string());
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn playlists_keep_their_tracks_in_order() {
+        let db = DbManager::new(unique_temp_path("playlist.db")).expect("db init");
+        db.initialize_playlist_schema().expect("schema");
+
+        let road = db.create_playlist(" Road trip ").expect("create");
+        let chill = db.create_playlist("Chill").expect("create");
+        assert!(db.create_playlist("road TRIP").is_err());
+        assert!(db.create_playlist("  ").is_err());
+
+        let tracks = vec![
+            "/music/b.flac".to_string(),
+            "/music/a.flac".to_string(),
+            "/music/b.flac".to_string(),
+        ];
+        db.set_playlist_tracks(road, &tracks).expect("set tracks");
+        assert_eq!(db.get_playlist_tracks(road).expect("tracks"), tracks);
+        assert!(db.set_playlist_tracks(9_999, &tracks).is_err());
+
+        db.rename_playlist(chill, "Ambient").expect("rename");
+        let playlists = db.get_playlists().expect("playlists");
+        assert_eq!(playlists.len(), 2);
+        assert_eq!(playlists[0].name, "Ambient");
+        assert_eq!(playlists[1].track_count, 3);
+
+        db.delete_playlist(road).expect("delete");
+        assert!(db.get_playlist_tracks(road).is_err());
+        assert_eq!(db.get_playlists().expect("playlists").len(), 1);
+    }
+}
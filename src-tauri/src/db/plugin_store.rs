@@ -0,0 +1,246 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::audio::dsp::plugin_rack::PluginPosition;
+use crate::audio::plugin_host::{PluginFormat, PluginState};
+use crate::db::manager::DbManager;
+
+/// A plugin insert as saved between sessions, in chain order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedPluginInsert {
+    pub position: PluginPosition,
+    pub format: PluginFormat,
+    pub path: String,
+    pub class_id: String,
+    pub state: PluginState,
+}
+
+impl DbManager {
+    /// Create the plugin chain tables if they don't exist. Plugin state is stored as
+    /// the opaque blobs the plugin wrote. Inserts with an empty `track_path` make up
+    /// the default chain; tracks listed in `track_plugin_chains` have their own, which
+    /// may be empty.
+    pub fn initialize_plugin_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS plugin_inserts (
+                slot INTEGER PRIMARY KEY,
+                position TEXT NOT NULL,
+                path TEXT NOT NULL,
+                class_id TEXT NOT NULL,
+                component_state BLOB NOT NULL,
+                controller_state BLOB NOT NULL,
+                format TEXT NOT NULL DEFAULT 'vst3',
+                track_path TEXT NOT NULL DEFAULT ''
+            );
+            CREATE TABLE IF NOT EXISTS track_plugin_chains (
+                track_path TEXT PRIMARY KEY
+            );",
+        )
+        .map_err(|e| format!("Failed to create plugin insert tables: {e}"))?;
+
+        // Inserts saved before CLAP support and per-track chains are default-chain VST3.
+        let mut stmt = conn
+            .prepare("PRAGMA table_info(plugin_inserts)")
+            .map_err(|e| format!("Failed to inspect plugin insert schema: {e}"))?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .map_err(|e| format!("Failed to read plugin insert schema rows: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect plugin insert schema: {e}"))?;
+        for (name, definition) in [
+            ("format", "TEXT NOT NULL DEFAULT 'vst3'"),
+            ("track_path", "TEXT NOT NULL DEFAULT ''"),
+        ] {
+            if !columns.iter().any(|column| column == name) {
+                conn.execute(
+                    &format!("ALTER TABLE plugin_inserts ADD COLUMN {name} {definition}"),
+                    [],
+                )
+                .map_err(|e| format!("Failed to add plugin_inserts.{name} column: {e}"))?;
+            }
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_plugin_inserts_track
+             ON plugin_inserts(track_path, slot)",
+            [],
+        )
+        .map_err(|e| format!("Failed to create plugin insert index: {e}"))?;
+        Ok(())
+    }
+
+    /// Replace the chain of `track` (`None` for the default chain) with `inserts`.
+    pub fn save_plugin_chain(
+        &self,
+        track: Option<&str>,
+        inserts: &[SavedPluginInsert],
+    ) -> Result<(), String> {
+        let track_path = track.unwrap_or_default();
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start plugin chain save: {e}"))?;
+        tx.execute(
+            "DELETE FROM plugin_inserts WHERE track_path = ?1",
+            params![track_path],
+        )
+        .map_err(|e| format!("Failed to clear plugin chain: {e}"))?;
+        for insert in inserts {
+            // Slots are assigned in insertion order, which keeps the chain order.
+            tx.execute(
+                "INSERT INTO plugin_inserts
+                    (position, format, path, class_id, component_state, controller_state,
+                     track_path)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    insert.position.as_str(),
+                    insert.format.as_str(),
+                    insert.path,
+                    insert.class_id,
+                    insert.state.component,
+                    insert.state.controller,
+                    track_path
+                ],
+            )
+            .map_err(|e| format!("Failed to save plugin insert {}: {e}", insert.path))?;
+        }
+        if let Some(track) = track {
+            tx.execute(
+                "INSERT OR IGNORE INTO track_plugin_chains (track_path) VALUES (?1)",
+                params![track],
+            )
+            .map_err(|e| format!("Failed to save plugin chain for {track}: {e}"))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit plugin chain: {e}"))
+    }
+
+    pub fn get_plugin_chain(&self, track: Option<&str>) -> Result<Vec<SavedPluginInsert>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT position, format, path, class_id, component_state, controller_state
+                 FROM plugin_inserts WHERE track_path = ?1 ORDER BY slot",
+            )
+            .map_err(|e| format!("Failed to prepare plugin chain query: {e}"))?;
+        let rows = stmt
+            .query_map(params![track.unwrap_or_default()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Vec<u8>>(4)?,
+                    row.get::<_, Vec<u8>>(5)?,
+                ))
+            })
+            .map_err(|e| format!("Failed to query plugin chain: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read plugin chain: {e}"))?;
+        rows.into_iter()
+            .map(
+                |(position, format, path, class_id, component, controller)| {
+                    Ok(SavedPluginInsert {
+                        position: PluginPosition::parse(&position)?,
+                        format: PluginFormat::parse(&format)?,
+                        path,
+                        class_id,
+                        state: PluginState {
+                            component,
+                            controller,
+                        },
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Whether `track` has a chain of its own rather than using the default one.
+    pub fn has_plugin_chain(&self, track: &str) -> Result<bool, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT 1 FROM track_plugin_chains WHERE track_path = ?1",
+            params![track],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|found| found.is_some())
+        .map_err(|e| format!("Failed to look up plugin chain for {track}: {e}"))
+    }
+
+    /// Drops the chain of `track`, which goes back to the default chain.
+    pub fn delete_plugin_chain(&self, track: &str) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start plugin chain delete: {e}"))?;
+        tx.execute(
+            "DELETE FROM plugin_inserts WHERE track_path = ?1",
+            params![track],
+        )
+        .map_err(|e| format!("Failed to delete plugin chain for {track}: {e}"))?;
+        tx.execute(
+            "DELETE FROM track_plugin_chains WHERE track_path = ?1",
+            params![track],
+        )
+        .map_err(|e| format!("Failed to delete plugin chain for {track}: {e}"))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit plugin chain delete: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SavedPluginInsert;
+    use crate::audio::dsp::plugin_rack::PluginPosition;
+    use crate::audio::plugin_host::{PluginFormat, PluginState};
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn plugin_chains_round_trip_in_chain_order() {
+        let db = DbManager::new(unique_temp_path("plugins.db")).expect("db init");
+        db.initialize_plugin_schema().expect("schema");
+        assert!(db.get_plugin_chain(None).expect("inserts").is_empty());
+
+        let inserts = vec![
+            SavedPluginInsert {
+                position: PluginPosition::PreLimiter,
+                format: PluginFormat::Vst3,
+                path: "/vst3/Verb.vst3".to_string(),
+                class_id: "00112233445566778899AABBCCDDEEFF".to_string(),
+                state: PluginState {
+                    component: vec![1, 2, 3],
+                    controller: Vec::new(),
+                },
+            },
+            SavedPluginInsert {
+                position: PluginPosition::PostEq,
+                format: PluginFormat::Clap,
+                path: "/clap/Eq.clap".to_string(),
+                class_id: "com.example.eq".to_string(),
+                state: PluginState::default(),
+            },
+        ];
+        db.save_plugin_chain(None, &inserts).expect("save");
+        assert_eq!(db.get_plugin_chain(None).expect("inserts"), inserts);
+
+        // A track chain is kept apart from the default one, even when empty.
+        let track = "/music/live.flac";
+        assert!(!db.has_plugin_chain(track).expect("lookup"));
+        db.save_plugin_chain(Some(track), &inserts[1..])
+            .expect("save track");
+        db.save_plugin_chain(None, &inserts[..1])
+            .expect("save again");
+        assert_eq!(db.get_plugin_chain(None).expect("inserts"), inserts[..1]);
+        assert_eq!(
+            db.get_plugin_chain(Some(track)).expect("inserts"),
+            inserts[1..]
+        );
+        db.save_plugin_chain(Some(track), &[]).expect("empty track");
+        assert!(db.has_plugin_chain(track).expect("lookup"));
+
+        db.delete_plugin_chain(track).expect("delete");
+        assert!(!db.has_plugin_chain(track).expect("lookup"));
+        assert_eq!(db.get_plugin_chain(None).expect("inserts"), inserts[..1]);
+    }
+}
@@ -27,9 +27,22 @@ impl DbManager {
     /// Called once during schema initialization.
     pub fn initialize_fts(&self) -> Result<(), String> {
         let conn = self.connection()?;
+        // Indexes built before album artists and composers were searchable are rebuilt.
+        if conn
+            .prepare("SELECT composer FROM tracks_fts LIMIT 0")
+            .is_err()
+        {
+            conn.execute_batch(
+                "DROP TRIGGER IF EXISTS tracks_ai;
+                 DROP TRIGGER IF EXISTS tracks_ad;
+                 DROP TRIGGER IF EXISTS tracks_au;
+                 DROP TABLE IF EXISTS tracks_fts;",
+            )
+            .map_err(|e| format!("Failed to drop outdated FTS5 table: {e}"))?;
+        }
         conn.execute_batch(
             "CREATE VIRTUAL TABLE IF NOT EXISTS tracks_fts USING fts5(
-                title, artist, album, content='tracks', content_rowid='id'
+                title, artist, album, album_artist, composer, content='tracks', content_rowid='id'
             );",
         )
         .map_err(|e| format!("Failed to create FTS5 virtual table: {e}"))?;
@@ -37,26 +50,26 @@ impl DbManager {
         // Triggers to keep FTS in sync with the tracks table
         conn.execute_batch(
             "CREATE TRIGGER IF NOT EXISTS tracks_ai AFTER INSERT ON tracks BEGIN
-                INSERT INTO tracks_fts(rowid, title, artist, album)
-                VALUES (new.id, new.title, new.artist, new.album);
+                INSERT INTO tracks_fts(rowid, title, artist, album, album_artist, composer)
+                VALUES (new.id, new.title, new.artist, new.album, new.album_artist, new.composer);
             END;
             CREATE TRIGGER IF NOT EXISTS tracks_ad AFTER DELETE ON tracks BEGIN
-                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album)
-                VALUES ('delete', old.id, old.title, old.artist, old.album);
+                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, album_artist, composer)
+                VALUES ('delete', old.id, old.title, old.artist, old.album, old.album_artist, old.composer);
             END;
             CREATE TRIGGER IF NOT EXISTS tracks_au AFTER UPDATE ON tracks BEGIN
-                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album)
-                VALUES ('delete', old.id, old.title, old.artist, old.album);
-                INSERT INTO tracks_fts(rowid, title, artist, album)
-                VALUES (new.id, new.title, new.artist, new.album);
+                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, album_artist, composer)
+                VALUES ('delete', old.id, old.title, old.artist, old.album, old.album_artist, old.composer);
+                INSERT INTO tracks_fts(rowid, title, artist, album, album_artist, composer)
+                VALUES (new.id, new.title, new.artist, new.album, new.album_artist, new.composer);
             END;",
         )
         .map_err(|e| format!("Failed to create FTS5 triggers: {e}"))?;
 
         // Populate FTS from existing tracks (idempotent rebuild)
         conn.execute_batch(
-            "INSERT OR IGNORE INTO tracks_fts(rowid, title, artist, album)
-             SELECT id, title, artist, album FROM tracks;",
+            "INSERT OR IGNORE INTO tracks_fts(rowid, title, artist, album, album_artist, composer)
+             SELECT id, title, artist, album, album_artist, composer FROM tracks;",
         )
         .map_err(|e| format!("Failed to populate FTS5 table: {e}"))?;
 
@@ -132,13 +145,14 @@ impl DbManager {
             .collect::<Result<Vec<_>, _>>()
             .map_err(|e| format!("FTS album row read failed: {e}"))?;
 
-        // Distinct matching artists
+        // Distinct artists credited on matching tracks, split like the artist list
         let mut stmt = conn
             .prepare(
-                "SELECT DISTINCT t.artist
+                "SELECT DISTINCT a.name
                  FROM tracks_fts f
-                 JOIN tracks t ON t.id = f.rowid
-                 WHERE tracks_fts MATCH ?1 AND t.artist IS NOT NULL AND t.artist != ''
+                 JOIN track_artists ta ON ta.track_id = f.rowid
+                 JOIN artists a ON a.id = ta.artist_id
+                 WHERE tracks_fts MATCH ?1
                  ORDER BY rank
                  LIMIT 50",
             )
@@ -161,15 +175,11 @@ impl DbManager {
 #[cfg(test)]
 mod tests {
     use super::super::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
     use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
     fn unique_db_path() -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        std::env::temp_dir().join(format!("powerplayer-search-test-{nanos}.db"))
+        unique_temp_path("search-test.db")
     }
 
     #[test]
@@ -183,6 +193,12 @@ mod tests {
             title: Some("Billie Jean".to_string()),
             artist: Some("Michael Jackson".to_string()),
             album: Some("Thriller".to_string()),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
             duration_seconds: Some(295.0),
             sample_rate: Some(44100),
             art_url: None,
@@ -195,6 +211,12 @@ mod tests {
             title: Some("Someone Else".to_string()),
             artist: Some("Other Artist".to_string()),
             album: Some("Other Album".to_string()),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
             duration_seconds: Some(180.0),
             sample_rate: Some(44100),
             art_url: None,
@@ -231,6 +253,12 @@ mod tests {
             title: Some("Track One".to_string()),
             artist: Some("Some Artist".to_string()),
             album: Some("Michael".to_string()),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
             duration_seconds: Some(200.0),
             sample_rate: Some(48000),
             art_url: None,
@@ -242,4 +270,44 @@ mod tests {
         assert_eq!(results.tracks.len(), 1);
         assert!(results.albums.contains(&"Michael".to_string()));
     }
+
+    #[test]
+    fn fts_search_finds_composers_and_album_artists() {
+        let path = unique_db_path();
+        let db = DbManager::new(&path).expect("db should initialize");
+        // An index from before composers were searchable.
+        db.connection()
+            .expect("connection")
+            .execute_batch(
+                "CREATE VIRTUAL TABLE tracks_fts USING fts5(
+                    title, artist, album, content='tracks', content_rowid='id'
+                );",
+            )
+            .expect("old FTS table");
+        db.initialize_fts().expect("FTS should upgrade");
+
+        db.save_track(&TrackInput {
+            path: "/music/aria.flac".to_string(),
+            title: Some("Aria".to_string()),
+            artist: Some("Glenn Gould".to_string()),
+            album: Some("Goldberg Variations".to_string()),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: Some("Gould Ensemble".to_string()),
+            composer: Some("Johann Sebastian Bach".to_string()),
+            duration_seconds: Some(180.0),
+            sample_rate: Some(44100),
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save should work");
+
+        assert_eq!(db.fast_search("Bach").expect("search").tracks.len(), 1);
+        assert_eq!(db.fast_search("Ensemble").expect("search").tracks.len(), 1);
+        // Initializing again keeps the upgraded index.
+        db.initialize_fts().expect("FTS should initialize");
+        assert_eq!(db.fast_search("Bach").expect("search").tracks.len(), 1);
+    }
 }
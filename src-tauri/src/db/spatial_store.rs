@@ -1,5 +1,7 @@
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
 
+use crate::audio::dsp::spatial::SourceMix;
+use crate::audio::dsp::spatial_motion::SourceMotion;
 use crate::db::manager::DbManager;
 
 /// Row from the `spatial_scenes` table.
@@ -13,8 +15,18 @@ pub struct SpatialSceneRow {
     pub is_active: bool,
 }
 
+/// Row from the `spatial_listeners` table.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct SpatialListenerRow {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub yaw: f32,
+}
+
 impl DbManager {
-    /// Create the spatial_scenes table if it doesn't exist.
+    /// Create the spatial_scenes, spatial_listeners, spatial_motions and
+    /// spatial_source_mix tables if they don't exist.
     pub fn initialize_spatial_schema(&self) -> Result<(), String> {
         let conn = self.connection()?;
         conn.execute_batch(
@@ -27,6 +39,27 @@ impl DbManager {
                 z REAL NOT NULL DEFAULT 0.0,
                 is_active INTEGER NOT NULL DEFAULT 1,
                 UNIQUE(track_id, source_name)
+            );
+            CREATE TABLE IF NOT EXISTS spatial_listeners (
+                track_id TEXT PRIMARY KEY,
+                x REAL NOT NULL,
+                y REAL NOT NULL,
+                z REAL NOT NULL,
+                yaw REAL NOT NULL DEFAULT 0.0
+            );
+            CREATE TABLE IF NOT EXISTS spatial_motions (
+                track_id TEXT NOT NULL,
+                source_name TEXT NOT NULL,
+                motion TEXT NOT NULL,
+                PRIMARY KEY (track_id, source_name)
+            );
+            CREATE TABLE IF NOT EXISTS spatial_source_mix (
+                track_id TEXT NOT NULL,
+                source_name TEXT NOT NULL,
+                gain_db REAL NOT NULL DEFAULT 0.0,
+                muted INTEGER NOT NULL DEFAULT 0,
+                solo INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (track_id, source_name)
             );",
         )
         .map_err(|e| format!("Failed to create spatial_scenes table: {e}"))?;
@@ -87,6 +120,163 @@ impl DbManager {
             .map_err(|e| format!("Failed to read spatial scene rows: {e}"))
     }
 
+    /// Save or update where the listener stands and faces for a track.
+    pub fn save_spatial_listener(
+        &self,
+        track_id: &str,
+        listener: &SpatialListenerRow,
+    ) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO spatial_listeners (track_id, x, y, z, yaw)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(track_id) DO UPDATE SET
+                  x = excluded.x,
+                  y = excluded.y,
+                  z = excluded.z,
+                  yaw = excluded.yaw",
+            params![track_id, listener.x, listener.y, listener.z, listener.yaw],
+        )
+        .map_err(|e| format!("Failed to save spatial listener: {e}"))?;
+        Ok(())
+    }
+
+    /// The listener saved with a track's scene, or `None` if it was never moved.
+    pub fn load_spatial_listener(
+        &self,
+        track_id: &str,
+    ) -> Result<Option<SpatialListenerRow>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT x, y, z, yaw FROM spatial_listeners WHERE track_id = ?1",
+            params![track_id],
+            |row| {
+                Ok(SpatialListenerRow {
+                    x: row.get(0)?,
+                    y: row.get(1)?,
+                    z: row.get(2)?,
+                    yaw: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load spatial listener: {e}"))
+    }
+
+    /// Replace the source motions saved for a track; sources left out stand still.
+    pub fn save_spatial_motions(
+        &self,
+        track_id: &str,
+        motions: &[(&str, &SourceMotion)],
+    ) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start spatial motion transaction: {e}"))?;
+        tx.execute(
+            "DELETE FROM spatial_motions WHERE track_id = ?1",
+            params![track_id],
+        )
+        .map_err(|e| format!("Failed to clear spatial motions: {e}"))?;
+        for (source_name, motion) in motions {
+            let motion = serde_json::to_string(motion)
+                .map_err(|e| format!("Failed to serialize motion for {source_name}: {e}"))?;
+            tx.execute(
+                "INSERT INTO spatial_motions (track_id, source_name, motion)
+                 VALUES (?1, ?2, ?3)",
+                params![track_id, source_name, motion],
+            )
+            .map_err(|e| format!("Failed to save spatial motion: {e}"))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit spatial motions: {e}"))
+    }
+
+    /// Source names and their motions saved for a track.
+    pub fn load_spatial_motions(
+        &self,
+        track_id: &str,
+    ) -> Result<Vec<(String, SourceMotion)>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT source_name, motion FROM spatial_motions
+                 WHERE track_id = ?1
+                 ORDER BY source_name",
+            )
+            .map_err(|e| format!("Failed to prepare spatial motion query: {e}"))?;
+        let rows = stmt
+            .query_map(params![track_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Failed to query spatial motions: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read spatial motions: {e}"))?;
+        rows.into_iter()
+            .map(|(source_name, motion)| {
+                serde_json::from_str(&motion)
+                    .map(|motion| (source_name.clone(), motion))
+                    .map_err(|e| format!("Spatial motion for {source_name} is corrupt: {e}"))
+            })
+            .collect()
+    }
+
+    /// Save or update the gain, mute and solo of a source for a track.
+    pub fn save_spatial_source_mix(
+        &self,
+        track_id: &str,
+        source_name: &str,
+        mix: &SourceMix,
+    ) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO spatial_source_mix (track_id, source_name, gain_db, muted, solo)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(track_id, source_name) DO UPDATE SET
+                  gain_db = excluded.gain_db,
+                  muted = excluded.muted,
+                  solo = excluded.solo",
+            params![
+                track_id,
+                source_name,
+                mix.gain_db,
+                mix.muted as i32,
+                mix.solo as i32
+            ],
+        )
+        .map_err(|e| format!("Failed to save spatial source mix: {e}"))?;
+        Ok(())
+    }
+
+    /// Source names and their mix saved for a track.
+    pub fn load_spatial_source_mix(
+        &self,
+        track_id: &str,
+    ) -> Result<Vec<(String, SourceMix)>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT source_name, gain_db, muted, solo FROM spatial_source_mix
+                 WHERE track_id = ?1
+                 ORDER BY source_name",
+            )
+            .map_err(|e| format!("Failed to prepare spatial source mix query: {e}"))?;
+        let rows = stmt
+            .query_map(params![track_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    SourceMix {
+                        gain_db: row.get(1)?,
+                        muted: row.get::<_, i32>(2)? != 0,
+                        solo: row.get::<_, i32>(3)? != 0,
+                    },
+                ))
+            })
+            .map_err(|e| format!("Failed to query spatial source mix: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read spatial source mix rows: {e}"))
+    }
+
     /// Delete all spatial scene data for a track.
     pub fn delete_spatial_scene(&self, track_id: &str) -> Result<(), String> {
         let conn = self.connection()?;
@@ -95,22 +285,36 @@ impl DbManager {
             params![track_id],
         )
         .map_err(|e| format!("Failed to delete spatial scene: {e}"))?;
+        conn.execute(
+            "DELETE FROM spatial_listeners WHERE track_id = ?1",
+            params![track_id],
+        )
+        .map_err(|e| format!("Failed to delete spatial listener: {e}"))?;
+        conn.execute(
+            "DELETE FROM spatial_motions WHERE track_id = ?1",
+            params![track_id],
+        )
+        .map_err(|e| format!("Failed to delete spatial motions: {e}"))?;
+        conn.execute(
+            "DELETE FROM spatial_source_mix WHERE track_id = ?1",
+            params![track_id],
+        )
+        .map_err(|e| format!("Failed to delete spatial source mix: {e}"))?;
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::SpatialListenerRow;
+    use crate::audio::dsp::spatial::SourceMix;
+    use crate::audio::dsp::spatial_motion::{MotionKeyframe, SourceMotion};
     use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
     use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
     fn unique_db_path() -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        std::env::temp_dir().join(format!("powerplayer-spatial-test-{nanos}.db"))
+        unique_temp_path("spatial-test.db")
     }
 
     #[test]
@@ -178,6 +382,100 @@ mod tests {
         assert!(rows.is_empty());
     }
 
+    #[test]
+    fn spatial_listener_is_saved_with_the_scene() {
+        let db = DbManager::new(unique_db_path()).expect("db init");
+        db.initialize_spatial_schema().expect("schema");
+        assert_eq!(
+            db.load_spatial_listener("/music/song.flac").expect("load"),
+            None
+        );
+
+        let listener = SpatialListenerRow {
+            x: 2.0,
+            y: 3.0,
+            z: 1.2,
+            yaw: 90.0,
+        };
+        db.save_spatial_listener("/music/song.flac", &listener)
+            .expect("save");
+        assert_eq!(
+            db.load_spatial_listener("/music/song.flac").expect("load"),
+            Some(listener)
+        );
+
+        db.delete_spatial_scene("/music/song.flac").expect("delete");
+        assert_eq!(
+            db.load_spatial_listener("/music/song.flac").expect("load"),
+            None
+        );
+    }
+
+    #[test]
+    fn spatial_source_mix_round_trips() {
+        let db = DbManager::new(unique_db_path()).expect("db init");
+        db.initialize_spatial_schema().expect("schema");
+
+        let solo = SourceMix {
+            gain_db: -4.5,
+            muted: false,
+            solo: true,
+        };
+        db.save_spatial_source_mix("/music/song.flac", "drums", &SourceMix::default())
+            .expect("save");
+        db.save_spatial_source_mix("/music/song.flac", "drums", &solo)
+            .expect("update");
+        assert_eq!(
+            db.load_spatial_source_mix("/music/song.flac")
+                .expect("load"),
+            vec![("drums".to_string(), solo)]
+        );
+
+        db.delete_spatial_scene("/music/song.flac").expect("delete");
+        assert!(db
+            .load_spatial_source_mix("/music/song.flac")
+            .expect("load")
+            .is_empty());
+    }
+
+    #[test]
+    fn spatial_motions_replace_the_saved_set() {
+        let db = DbManager::new(unique_db_path()).expect("db init");
+        db.initialize_spatial_schema().expect("schema");
+
+        let orbit = SourceMotion::Orbit {
+            center_x: 4.0,
+            center_y: 5.0,
+            z: 1.7,
+            radius: 2.0,
+            period_s: 8.0,
+            clockwise: false,
+        };
+        let path = SourceMotion::Path {
+            keyframes: vec![MotionKeyframe {
+                time_s: 0.0,
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            }],
+            looped: true,
+        };
+        db.save_spatial_motions("/music/song.flac", &[("drums", &orbit), ("vocals", &path)])
+            .expect("save");
+        db.save_spatial_motions("/music/song.flac", &[("drums", &orbit)])
+            .expect("replace");
+        assert_eq!(
+            db.load_spatial_motions("/music/song.flac").expect("load"),
+            vec![("drums".to_string(), orbit)]
+        );
+
+        db.delete_spatial_scene("/music/song.flac").expect("delete");
+        assert!(db
+            .load_spatial_motions("/music/song.flac")
+            .expect("load")
+            .is_empty());
+    }
+
     #[test]
     fn load_empty_scene_returns_empty() {
         let db = DbManager::new(unique_db_path()).expect("db init");
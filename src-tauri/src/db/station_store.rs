@@ -0,0 +1,112 @@
+use rusqlite::params;
+
+use crate::db::manager::DbManager;
+
+/// Row from the `stations` table: a saved internet radio stream.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct StationRow {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub genre: Option<String>,
+}
+
+impl DbManager {
+    /// Create the stations table if it doesn't exist.
+    pub fn initialize_station_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS stations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                url TEXT NOT NULL UNIQUE,
+                genre TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+        )
+        .map_err(|e| format!("Failed to create stations table: {e}"))?;
+        Ok(())
+    }
+
+    /// Save a station, or rename/re-tag the one already saved with the same URL.
+    /// Returns its id.
+    pub fn save_station(&self, name: &str, url: &str, genre: Option<&str>) -> Result<i64, String> {
+        let name = name.trim();
+        let url = url.trim();
+        if name.is_empty() {
+            return Err("Station name cannot be empty".to_string());
+        }
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(format!("Station URL must be http(s): {url}"));
+        }
+        let conn = self.connection()?;
+        conn.query_row(
+            "INSERT INTO stations (name, url, genre) VALUES (?1, ?2, ?3)
+             ON CONFLICT(url) DO UPDATE SET name = excluded.name, genre = excluded.genre
+             RETURNING id",
+            params![name, url, genre],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to save station {name}: {e}"))
+    }
+
+    pub fn delete_station(&self, id: i64) -> Result<(), String> {
+        self.connection()?
+            .execute("DELETE FROM stations WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete station {id}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn get_stations(&self) -> Result<Vec<StationRow>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT id, name, url, genre FROM stations ORDER BY name COLLATE NOCASE")
+            .map_err(|e| format!("Failed to prepare station query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(StationRow {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    url: row.get(2)?,
+                    genre: row.get(3)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query stations: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read stations: {e}"))?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn stations_are_keyed_by_url() {
+        let db = DbManager::new(unique_temp_path("station.db")).expect("db init");
+        db.initialize_station_schema().expect("schema");
+
+        let jazz = db
+            .save_station("Jazz FM", "http://radio.example/jazz", Some("Jazz"))
+            .expect("save station");
+        let renamed = db
+            .save_station(" Smooth Jazz ", "http://radio.example/jazz", None)
+            .expect("update station");
+        assert_eq!(jazz, renamed);
+        db.save_station("Ambient", "https://radio.example/ambient", None)
+            .expect("save station");
+        assert!(db
+            .save_station("Local", "file:///etc/passwd", None)
+            .is_err());
+
+        let stations = db.get_stations().expect("stations");
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[1].name, "Smooth Jazz");
+        assert_eq!(stations[1].genre, None);
+
+        db.delete_station(jazz).expect("delete station");
+        assert_eq!(db.get_stations().expect("stations").len(), 1);
+    }
+}
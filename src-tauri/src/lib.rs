@@ -1,19 +1,61 @@
 use serde::Serialize;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tauri::Emitter;
 use tauri::Manager;
 use thiserror::Error;
 
 mod audio;
+mod credentials;
 mod db;
 mod library;
-use audio::engine::{AudioState, AudioStats};
-use db::manager::DbManager;
+mod media_keys;
+mod taskbar;
+#[cfg(test)]
+mod test_support;
+use audio::airplay::{AirPlayDevice, AirPlayOutput};
+use audio::cast::{CastDevice, CastOutput, CastStatus};
+use audio::dlna::{DlnaOutput, DlnaRenderer};
+use audio::dsp::analog::AnalogSettings;
+use audio::dsp::bass_management::BassManagementSettings;
+use audio::dsp::compressor::CompressorSettings;
+use audio::dsp::correction::{parse_correction, RoomCorrection};
+use audio::dsp::filters::{DspNode, EqBandSettings, EqMode, FilterType, GRAPHIC_EQ_FREQUENCIES};
+use audio::dsp::karaoke::KaraokeSettings;
+use audio::dsp::plugin_rack::{PluginInsertInfo, PluginPosition, PLUGIN_BLOCK_FRAMES};
+use audio::dsp::presets::ChainPreset;
+use audio::dsp::profiles::{resolve_profile, DspProfile, DspProfileScope};
+use audio::dsp::spatial::SpatialOutputMode;
+use audio::dsp::spatial_motion::SourceMotion;
+use audio::dsp::spatial_scene::SpatialScene;
+use audio::dsp::tone::BalanceMode;
+use audio::dsp::upmix::UpmixSettings;
+use audio::engine::{AbSlot, AudioState, AudioStats, SharedOutput};
+use audio::head_tracking::HeadTracker;
+use audio::plugin_host::{PluginClassInfo, PluginFormat, PluginParameter};
+use audio::stream::StreamInfo;
+use db::album_store::AlbumRow;
+use db::artist_store::ArtistRow;
+use db::dsp_profile_store::DspProfileAssignmentRow;
+use db::history_store::PlayHistoryRow;
+use db::library_root_store::LibraryRootRow;
+use db::manager::{DbManager, TrackPlayQuery, TrackRecord, TrackSort};
+use db::playlist_store::PlaylistRow;
+use db::plugin_store::SavedPluginInsert;
 use db::search::SearchResults;
-use db::spatial_store::SpatialSceneRow;
+use db::spatial_store::{SpatialListenerRow, SpatialSceneRow};
+use db::station_store::StationRow;
+use library::export::{ExportReport, LibraryExportFormat};
+use library::itunes::LibraryImport;
+use library::metadata::batch_tagger::{BatchTagPreview, BatchTagSummary};
+use library::metadata::tag_writer::TagFields;
+use library::network_usage::NetworkProvider;
+use library::playlist_files::PlaylistImport;
 use library::queue::PlaybackQueue;
-use library::stems::StemSeparator;
+use library::stem_export::StemExportFormat;
+use library::stem_queue::{StemJobEvent, StemJobStatus, StemQueue, StemQueueStatus};
+use library::stems::{SeparationMode, StemAnalysisToken, StemKind, StemSeparator};
+use media_keys::MediaKey;
 
 type AppResult<T> = Result<T, AppError>;
 
@@ -25,6 +67,8 @@ enum AppError {
     Db { error: String, code: &'static str },
     #[error("{error}")]
     Fs { error: String, code: &'static str },
+    #[error("{error}")]
+    Credential { error: String, code: &'static str },
 }
 
 impl AppError {
@@ -48,6 +92,13 @@ impl AppError {
             code: "FS_ERROR",
         }
     }
+
+    fn credential(error: impl Into<String>) -> Self {
+        Self::Credential {
+            error: error.into(),
+            code: "CREDENTIAL_ERROR",
+        }
+    }
 }
 
 impl Serialize for AppError {
@@ -61,7 +112,10 @@ impl Serialize for AppError {
             code: &'a str,
         }
         let payload = match self {
-            AppError::Dsp { error, code } | AppError::Db { error, code } | AppError::Fs { error, code } => {
+            AppError::Dsp { error, code }
+            | AppError::Db { error, code }
+            | AppError::Fs { error, code }
+            | AppError::Credential { error, code } => {
                 ErrorPayload {
                     error: error.as_str(),
                     code,
@@ -72,12 +126,85 @@ impl Serialize for AppError {
     }
 }
 
+#[derive(Serialize)]
+struct AbStatusData {
+    a_captured: bool,
+    b_captured: bool,
+    /// The snapshot switched to last.
+    active: Option<AbSlot>,
+}
+
+#[derive(Serialize)]
+struct DspNodeStatusData {
+    node: DspNode,
+    bypassed: bool,
+}
+
+#[derive(Serialize)]
+struct GraphicEqBandData {
+    index: usize,
+    frequency: f32,
+    gain_db: f32,
+}
+
 #[derive(Serialize)]
 struct EqBandData {
     index: usize,
     frequency: f32,
     gain_db: f32,
     q_factor: f32,
+    filter_type: FilterType,
+}
+
+#[derive(Serialize)]
+struct EqPresetData {
+    name: String,
+    bands: Vec<EqBandSettings>,
+}
+
+#[derive(Serialize)]
+struct AutoEqEntryData {
+    name: String,
+    source: String,
+}
+
+#[derive(Serialize)]
+struct AutoEqProfileData {
+    name: String,
+    source: String,
+    /// Gain AutoEq recommends ahead of the filters; not applied automatically.
+    preamp_db: f32,
+    bands: Vec<EqBandSettings>,
+}
+
+#[derive(Serialize)]
+struct PluginInsertData {
+    id: u32,
+    position: PluginPosition,
+    format: PluginFormat,
+    name: String,
+    path: String,
+    class_id: String,
+}
+
+impl From<PluginInsertInfo> for PluginInsertData {
+    fn from(info: PluginInsertInfo) -> Self {
+        Self {
+            id: info.id,
+            position: info.position,
+            format: info.format,
+            name: info.name,
+            path: info.path,
+            class_id: info.class_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CompressorData {
+    #[serde(flatten)]
+    settings: CompressorSettings,
+    gain_reduction_db: Vec<f32>,
 }
 
 #[derive(Serialize)]
@@ -86,18 +213,29 @@ struct FrequencyPoint {
     magnitude_db: f32,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct CoverArtData {
     media_type: String,
     data: Vec<u8>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct TrackData {
     artist: String,
     title: String,
     cover_art: Option<CoverArtData>,
     duration_seconds: f32,
+    /// DSP profiles applied on load, lowest precedence first.
+    dsp_profiles: Vec<String>,
+    /// Bookmark playback resumed from, for long-form files (audiobooks, podcasts).
+    resume_seconds: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ChapterData {
+    title: String,
+    start_seconds: f64,
+    end_seconds: f64,
 }
 
 #[derive(Serialize)]
@@ -114,6 +252,8 @@ struct AudioStatsData {
     file_sample_rate_hz: u32,
     ring_buffer_capacity_bytes: u32,
     ring_buffer_used_bytes: u32,
+    limiter_gain_reduction_db: f32,
+    eq_latency_ms: f32,
 }
 
 #[derive(Serialize)]
@@ -122,16 +262,144 @@ struct LyricsLineData {
     text: String,
 }
 
+#[derive(Serialize)]
+struct LyricsWarningData {
+    line: usize,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct LyricsDetailsData {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    author: Option<String>,
+    offset_ms: i32,
+    warnings: Vec<LyricsWarningData>,
+}
+
 #[derive(Serialize)]
 struct LibraryTrackData {
     path: String,
     title: Option<String>,
     artist: Option<String>,
     album: Option<String>,
+    genre: Option<String>,
+    year: Option<u32>,
+    track_number: Option<u32>,
+    disc_number: Option<u32>,
+    album_artist: Option<String>,
+    composer: Option<String>,
     duration_seconds: Option<f32>,
     sample_rate: Option<u32>,
     art_url: Option<String>,
     corrupted: bool,
+    bpm: Option<f32>,
+    play_count: u32,
+    last_played_at: Option<String>,
+    rating: Option<u8>,
+}
+
+impl From<TrackRecord> for LibraryTrackData {
+    fn from(track: TrackRecord) -> Self {
+        Self {
+            path: track.path,
+            title: track.title,
+            artist: track.artist,
+            album: track.album,
+            genre: track.genre,
+            year: track.year,
+            track_number: track.track_number,
+            disc_number: track.disc_number,
+            album_artist: track.album_artist,
+            composer: track.composer,
+            duration_seconds: track.duration_seconds,
+            sample_rate: track.sample_rate,
+            art_url: track.art_url,
+            corrupted: track.corrupted,
+            bpm: track.bpm,
+            play_count: track.play_count,
+            last_played_at: track.last_played_at,
+            rating: track.rating,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ArtistDetailData {
+    name: String,
+    /// Oldest first.
+    albums: Vec<AlbumRow>,
+    /// Most played first.
+    top_tracks: Vec<LibraryTrackData>,
+    /// `artist.jpg` from the artist's folder, else one of their album covers.
+    image_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct FolderListingData {
+    /// `None` at the top level, which lists the library roots.
+    path: Option<String>,
+    parent: Option<String>,
+    folders: Vec<library::folders::FolderEntry>,
+    files: Vec<FolderFileData>,
+}
+
+#[derive(Serialize)]
+struct FolderFileData {
+    name: String,
+    path: String,
+    /// Library metadata, when the file has been scanned.
+    track: Option<LibraryTrackData>,
+}
+
+/// Play counts per equal-width section of a track, for the seekbar heatmap.
+#[derive(Serialize)]
+struct PlayHeatmapData {
+    /// 0..1, relative to the most played section.
+    buckets: Vec<f32>,
+    max_plays: u16,
+}
+
+#[derive(Serialize)]
+struct InstantReplayData {
+    position_seconds: f64,
+    lyric: Option<LyricsLineData>,
+}
+
+#[derive(Serialize)]
+struct NetworkUsageData {
+    /// Calendar month (UTC, `YYYY-MM`) the download counters cover.
+    month: String,
+    providers: Vec<ProviderUsageData>,
+    caches: Vec<CacheUsageData>,
+}
+
+#[derive(Serialize)]
+struct ProviderUsageData {
+    provider: NetworkProvider,
+    bytes: u64,
+    requests: u64,
+}
+
+#[derive(Serialize)]
+struct CacheUsageData {
+    name: String,
+    bytes: u64,
+    files: u64,
+}
+
+#[derive(Serialize)]
+struct DspProfileData {
+    name: String,
+    settings: DspProfile,
+}
+
+#[derive(Serialize)]
+struct FileOperationData {
+    source: String,
+    destination: Option<String>,
+    error: Option<String>,
 }
 
 #[tauri::command]
@@ -152,478 +420,3619 @@ fn update_eq_band(
         .map_err(AppError::dsp)
 }
 
+/// Searches the AutoEq catalog by headphone model; the index is cached locally.
 #[tauri::command]
-fn activate_autoeq_profile(
-    state: tauri::State<'_, AudioState>,
-    model: String,
-) -> AppResult<Vec<EqBandData>> {
-    let profile = audio::dsp::autoeq::profile_for_model(&model)
-        .ok_or_else(|| AppError::dsp(format!("No AutoEQ profile found for model: {model}")))?;
-    state.set_autoeq_profile(&profile).map_err(AppError::dsp)?;
-
-    get_eq_bands(state)
-}
-
-#[tauri::command]
-fn get_eq_bands(state: tauri::State<'_, AudioState>) -> AppResult<Vec<EqBandData>> {
-    let bands = state.get_eq_bands().map_err(AppError::dsp)?;
-    Ok(bands
-        .into_iter()
-        .enumerate()
-        .map(|(i, (frequency, gain_db, q_factor))| EqBandData {
-            index: i,
-            frequency,
-            gain_db,
-            q_factor,
-        })
-        .collect())
-}
-
-#[tauri::command]
-fn get_eq_frequency_response(
-    state: tauri::State<'_, AudioState>,
-    num_points: usize,
-) -> AppResult<Vec<FrequencyPoint>> {
-    let response = state
-        .get_eq_frequency_response(num_points)
-        .map_err(AppError::dsp)?;
-    Ok(response
-        .into_iter()
-        .map(|(frequency, magnitude_db)| FrequencyPoint {
-            frequency,
-            magnitude_db,
-        })
-        .collect())
-}
-
-#[tauri::command]
-fn get_fft_data() -> AppResult<Vec<f32>> {
-    Ok(vec![-100.0; 1024])
+async fn search_autoeq_profiles(
+    query: String,
+    limit: Option<usize>,
+) -> AppResult<Vec<AutoEqEntryData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let entries = audio::autoeq_downloader::search_profiles(&query, limit.unwrap_or(25))
+            .map_err(AppError::dsp)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| AutoEqEntryData {
+                name: entry.name,
+                source: entry.source,
+            })
+            .collect())
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking AutoEQ search task failed: {err}")))?
 }
 
+/// Downloads (or reuses the cached) AutoEq profile for `model` and activates it.
+/// `source` picks one measurement when the model has several; `"flat"` turns AutoEQ off.
 #[tauri::command]
-async fn load_track(
+async fn activate_autoeq_profile(
     app: tauri::AppHandle,
-    path: String,
-) -> AppResult<TrackData> {
+    model: String,
+    source: Option<String>,
+) -> AppResult<AutoEqProfileData> {
     tauri::async_runtime::spawn_blocking(move || {
-        let state = app.state::<AudioState>();
-        let metadata = audio::decoder::read_track_metadata(Path::new(&path)).map_err(AppError::fs)?;
-        state.load_lyrics_for_track(&path);
-        if state.playback_supported() {
-            state.load_track(&path).map_err(AppError::dsp)?;
-            state
-                .start_lyrics_monitor(app.clone())
+        if model.trim().eq_ignore_ascii_case("flat") {
+            app.state::<AudioState>()
+                .set_autoeq_profile(&[])
                 .map_err(AppError::dsp)?;
+            return Ok(AutoEqProfileData {
+                name: "Flat".to_string(),
+                source: String::new(),
+                preamp_db: 0.0,
+                bands: Vec::new(),
+            });
         }
-
-        Ok(TrackData {
-            artist: metadata
-                .artist
-                .unwrap_or_else(|| "Unknown Artist".to_string()),
-            title: metadata
-                .title
-                .unwrap_or_else(|| "Unknown Title".to_string()),
-            cover_art: metadata.cover_art.map(|cover| CoverArtData {
-                media_type: cover.media_type,
-                data: cover.data,
-            }),
-            duration_seconds: state
-                .get_track_duration_seconds()
-                .max(metadata.duration_seconds.unwrap_or(0.0)),
+        let (entry, profile) = audio::autoeq_downloader::fetch_profile(&model, source.as_deref())
+            .map_err(AppError::dsp)?;
+        app.state::<AudioState>()
+            .set_autoeq_profile(&profile.bands)
+            .map_err(AppError::dsp)?;
+        Ok(AutoEqProfileData {
+            name: entry.name,
+            source: entry.source,
+            preamp_db: profile.preamp_db,
+            bands: profile.bands,
         })
     })
     .await
-    .map_err(|err| AppError::dsp(format!("Blocking load track task failed: {err}")))?
+    .map_err(|err| AppError::dsp(format!("Blocking AutoEQ profile task failed: {err}")))?
 }
 
+/// Loads a measured correction into the room correction stage: a REW "EQ filter
+/// export", an AutoEq/EqualizerAPO `ParametricEQ.txt`, or a miniDSP biquad file, which
+/// also needs the `sample_rate_hz` its coefficients were designed for.
 #[tauri::command]
-async fn extract_waveform(
+async fn import_room_correction(
     app: tauri::AppHandle,
     path: String,
-    points: usize,
-) -> AppResult<Vec<f32>> {
+    sample_rate_hz: Option<f32>,
+) -> AppResult<RoomCorrection> {
     tauri::async_runtime::spawn_blocking(move || {
-        let db = app.state::<DbManager>();
-        if let Some(cached) = db.get_waveform_data(&path).map_err(AppError::db)? {
-            if cached.len() == points {
-                return Ok(cached);
-            }
-        }
-
-        let waveform = audio::analyzer::extract_waveform(Path::new(&path), points).map_err(AppError::dsp)?;
-        db.save_waveform_data(&path, &waveform)
-            .map_err(AppError::db)?;
-        Ok(waveform)
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| AppError::fs(format!("Failed to read {path}: {err}")))?;
+        let correction = parse_correction(&text, sample_rate_hz).map_err(AppError::dsp)?;
+        app.state::<AudioState>()
+            .set_room_correction(correction.clone())
+            .map_err(AppError::dsp)?;
+        Ok(correction)
     })
     .await
-    .map_err(|err| AppError::dsp(format!("Blocking waveform extraction task failed: {err}")))?
+    .map_err(|err| AppError::dsp(format!("Blocking room correction task failed: {err}")))?
 }
 
 #[tauri::command]
-fn get_lyrics_lines(state: tauri::State<'_, AudioState>) -> AppResult<Vec<LyricsLineData>> {
-    Ok(state
-        .get_lyrics_lines()
-        .into_iter()
-        .map(|line| LyricsLineData {
-            timestamp: line.timestamp,
-            text: line.text,
-        })
-        .collect())
+fn clear_room_correction(state: tauri::State<'_, AudioState>) -> AppResult<()> {
+    state
+        .set_room_correction(RoomCorrection::default())
+        .map_err(AppError::dsp)
 }
 
 #[tauri::command]
-async fn scan_library(app: tauri::AppHandle, path: String) -> AppResult<usize> {
+fn get_room_correction(state: tauri::State<'_, AudioState>) -> AppResult<RoomCorrection> {
+    state.room_correction().map_err(AppError::dsp)
+}
+
+/// Lists the effect classes of the VST3 and CLAP plugins installed in the standard
+/// folders.
+#[tauri::command]
+async fn scan_plugins() -> AppResult<Vec<PluginClassInfo>> {
+    tauri::async_runtime::spawn_blocking(audio::plugin_host::scan_installed)
+        .await
+        .map_err(|err| AppError::dsp(format!("Blocking plugin scan task failed: {err}")))
+}
+
+/// Loads a VST3 or CLAP effect and inserts it into the DSP chain at `position`, after
+/// any plugins already there.
+#[tauri::command]
+async fn insert_plugin(
+    app: tauri::AppHandle,
+    format: PluginFormat,
+    path: String,
+    class_id: String,
+    position: PluginPosition,
+) -> AppResult<PluginInsertData> {
     tauri::async_runtime::spawn_blocking(move || {
-        let db = app.state::<DbManager>();
-        let root = PathBuf::from(path);
-        let scanned = library::scanner::scan_library_path(&root, &db).map_err(AppError::fs)?;
-        library::scanner::register_library_watch(&root, &db).map_err(AppError::fs)?;
-        Ok(scanned)
+        let plugin =
+            audio::plugin_host::load(format, &path, &class_id, 48_000.0, PLUGIN_BLOCK_FRAMES)
+                .map_err(AppError::dsp)?;
+        let (name, class_id) = (plugin.name().to_string(), plugin.class_id().to_string());
+        let audio = app.state::<AudioState>();
+        let id = audio
+            .insert_plugin(position, plugin)
+            .map_err(AppError::dsp)?;
+        persist_plugin_inserts(&audio, &app.state::<DbManager>())?;
+        Ok(PluginInsertData {
+            id,
+            position,
+            format,
+            name,
+            path,
+            class_id,
+        })
     })
     .await
-    .map_err(|err| AppError::fs(format!("Blocking library scan task failed: {err}")))?
+    .map_err(|err| AppError::dsp(format!("Blocking plugin load task failed: {err}")))?
 }
 
 #[tauri::command]
-fn get_library_tracks(state: tauri::State<'_, DbManager>) -> AppResult<Vec<LibraryTrackData>> {
-    Ok(state
-        .get_tracks()
-        .map_err(AppError::db)?
-        .into_iter()
-        .map(|track| LibraryTrackData {
-            path: track.path,
-            title: track.title,
-            artist: track.artist,
-            album: track.album,
-            duration_seconds: track.duration_seconds,
-            sample_rate: track.sample_rate,
-            art_url: track.art_url,
-            corrupted: track.corrupted,
-        })
-        .collect())
+fn remove_plugin(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    id: u32,
+) -> AppResult<bool> {
+    let removed = audio.remove_plugin(id).map_err(AppError::dsp)?;
+    if removed {
+        persist_plugin_inserts(&audio, &db)?;
+    }
+    Ok(removed)
 }
 
 #[tauri::command]
-fn play(state: tauri::State<'_, AudioState>) -> AppResult<()> {
-    state.play();
-    Ok(())
+fn list_plugin_inserts(state: tauri::State<'_, AudioState>) -> AppResult<Vec<PluginInsertData>> {
+    let inserts = state.plugin_inserts().map_err(AppError::dsp)?;
+    Ok(inserts.into_iter().map(PluginInsertData::from).collect())
 }
 
 #[tauri::command]
-fn pause(state: tauri::State<'_, AudioState>) -> AppResult<()> {
-    state.pause();
-    Ok(())
+fn get_plugin_parameters(
+    state: tauri::State<'_, AudioState>,
+    id: u32,
+) -> AppResult<Vec<PluginParameter>> {
+    state.plugin_parameters(id).map_err(AppError::dsp)
 }
 
+/// Sets a plugin parameter to a normalized value (0.0–1.0) and saves the plugin state.
 #[tauri::command]
-fn set_next_track(state: tauri::State<'_, AudioState>, path: Option<String>) -> AppResult<()> {
-    state.set_next_track(path.as_deref());
-    Ok(())
+fn set_plugin_parameter(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    id: u32,
+    param_id: u32,
+    value: f64,
+) -> AppResult<()> {
+    audio
+        .set_plugin_parameter(id, param_id, value)
+        .map_err(AppError::dsp)?;
+    persist_plugin_inserts(&audio, &db)
 }
 
+/// Saves the plugins now in the rack as the chain of `path`, which then plays with
+/// them instead of the default chain. If `path` is playing, further edits go to its
+/// chain.
 #[tauri::command]
-fn seek(state: tauri::State<'_, AudioState>, seconds: f64) -> AppResult<()> {
-    state.seek(seconds);
+fn save_track_plugin_chain(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    path: String,
+) -> AppResult<()> {
+    let path = library::paths::canonical_path_string(&path);
+    db.save_plugin_chain(Some(&path), &saved_plugin_inserts(&audio)?)
+        .map_err(AppError::db)?;
+    let playing = audio.loaded_path().is_some_and(|loaded| {
+        library::paths::canonical_path_string(&loaded.to_string_lossy()) == path
+    });
+    if playing {
+        audio
+            .set_plugin_chain_track(Some(path))
+            .map_err(AppError::dsp)?;
+    }
     Ok(())
 }
 
+/// Drops the plugin chain of `path`, which goes back to the default chain. If the
+/// rack holds that chain, the default chain is loaded in its place.
 #[tauri::command]
-fn set_volume(state: tauri::State<'_, AudioState>, volume: f32) -> AppResult<()> {
-    state.set_volume(volume);
-    Ok(())
+async fn clear_track_plugin_chain(app: tauri::AppHandle, path: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = library::paths::canonical_path_string(&path);
+        let audio = app.state::<AudioState>();
+        let db = app.state::<DbManager>();
+        db.delete_plugin_chain(&path).map_err(AppError::db)?;
+        if audio.plugin_chain_track().map_err(AppError::dsp)? == Some(path) {
+            load_plugin_chain(&audio, &db, None).map_err(AppError::dsp)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking plugin chain task failed: {err}")))?
 }
 
+/// The track whose own plugin chain is in the rack, or `None` for the default chain.
 #[tauri::command]
-fn get_vibe_data(state: tauri::State<'_, AudioState>) -> AppResult<VibeData> {
-    let (spectrum, amplitude) = state.get_vibe_data();
-    Ok(VibeData {
-        spectrum,
-        amplitude,
-    })
+fn get_plugin_chain_track(state: tauri::State<'_, AudioState>) -> AppResult<Option<String>> {
+    state.plugin_chain_track().map_err(AppError::dsp)
+}
+
+fn saved_plugin_inserts(audio: &AudioState) -> AppResult<Vec<SavedPluginInsert>> {
+    Ok(audio
+        .plugin_states()
+        .map_err(AppError::dsp)?
+        .into_iter()
+        .map(|(info, state)| SavedPluginInsert {
+            position: info.position,
+            format: info.format,
+            path: info.path,
+            class_id: info.class_id,
+            state,
+        })
+        .collect())
+}
+
+/// Saves the rack as the chain it was loaded from.
+fn persist_plugin_inserts(audio: &AudioState, db: &DbManager) -> AppResult<()> {
+    let track = audio.plugin_chain_track().map_err(AppError::dsp)?;
+    db.save_plugin_chain(track.as_deref(), &saved_plugin_inserts(audio)?)
+        .map_err(AppError::db)
+}
+
+/// Loads the saved chain of `track` (`None` for the default chain) into the rack, each
+/// plugin with its saved state. Plugins that fail to load are left out.
+fn load_plugin_chain(
+    audio: &AudioState,
+    db: &DbManager,
+    track: Option<String>,
+) -> Result<(), String> {
+    let plugins = db
+        .get_plugin_chain(track.as_deref())?
+        .into_iter()
+        .filter_map(|insert| {
+            let loaded = audio::plugin_host::load(
+                insert.format,
+                &insert.path,
+                &insert.class_id,
+                48_000.0,
+                PLUGIN_BLOCK_FRAMES,
+            )
+            .and_then(|mut plugin| {
+                plugin.restore_state(&insert.state)?;
+                Ok(plugin)
+            });
+            match loaded {
+                Ok(plugin) => Some((insert.position, plugin)),
+                Err(err) => {
+                    eprintln!("Failed to restore plugin {}: {err}", insert.path);
+                    None
+                }
+            }
+        })
+        .collect();
+    audio.replace_plugin_chain(track, plugins)
+}
+
+/// Switches the rack to the plugin chain of `path`, or to the default chain when the
+/// track has none. The outgoing chain is saved first; nothing is reloaded when the
+/// right chain is already in place.
+fn apply_track_plugin_chain(state: &AudioState, db: &DbManager, path: &str) -> Result<(), String> {
+    let path = library::paths::canonical_path_string(path);
+    let track = db.has_plugin_chain(&path)?.then_some(path);
+    if state.plugin_chain_track()? == track {
+        return Ok(());
+    }
+    persist_plugin_inserts(state, db).map_err(|err| err.to_string())?;
+    load_plugin_chain(state, db, track)
 }
 
 #[tauri::command]
-fn get_audio_stats(state: tauri::State<'_, AudioState>) -> AppResult<AudioStatsData> {
-    let AudioStats {
-        device,
-        stream_latency_ms,
-        output_sample_rate_hz,
-        file_sample_rate_hz,
-        ring_buffer_capacity_bytes,
-        ring_buffer_used_bytes,
-    } = state.get_audio_stats();
-    Ok(AudioStatsData {
-        device,
-        stream_latency_ms,
-        output_sample_rate_hz,
-        file_sample_rate_hz,
-        ring_buffer_capacity_bytes,
-        ring_buffer_used_bytes,
-    })
+fn get_eq_bands(state: tauri::State<'_, AudioState>) -> AppResult<Vec<EqBandData>> {
+    let bands = state.get_eq_bands().map_err(AppError::dsp)?;
+    Ok(bands
+        .into_iter()
+        .enumerate()
+        .map(|(i, band)| EqBandData {
+            index: i,
+            frequency: band.frequency,
+            gain_db: band.gain_db,
+            q_factor: band.q_factor,
+            filter_type: band.filter_type,
+        })
+        .collect())
 }
 
+/// Captures the current EQ, tone, reverb, expansion and spatial settings as snapshot
+/// `slot` (`"a"` or `"b"`) for A/B comparison.
 #[tauri::command]
-fn set_tone(
-    state: tauri::State<'_, AudioState>,
-    bass: f32,
-    treble: f32,
-) -> AppResult<()> {
-    state.set_tone(bass, treble).map_err(AppError::dsp)
+fn capture_ab_snapshot(state: tauri::State<'_, AudioState>, slot: AbSlot) -> AppResult<()> {
+    state.capture_ab_snapshot(slot).map_err(AppError::dsp)
 }
 
+/// Switches to snapshot `slot`; during playback the output dips for about 30 ms so
+/// the change does not click.
 #[tauri::command]
-fn set_balance(state: tauri::State<'_, AudioState>, val: f32) -> AppResult<()> {
-    state.set_balance(val).map_err(AppError::dsp)
+fn switch_ab_snapshot(state: tauri::State<'_, AudioState>, slot: AbSlot) -> AppResult<()> {
+    state.switch_ab_snapshot(slot).map_err(AppError::dsp)
 }
 
 #[tauri::command]
-fn set_expansion(state: tauri::State<'_, AudioState>, val: f32) -> AppResult<()> {
-    state.set_expansion(val).map_err(AppError::dsp)
+fn get_ab_status(state: tauri::State<'_, AudioState>) -> AppResult<AbStatusData> {
+    let (a_captured, b_captured, active) = state.ab_status().map_err(AppError::dsp)?;
+    Ok(AbStatusData {
+        a_captured,
+        b_captured,
+        active,
+    })
 }
 
+/// Takes one DSP stage out of the signal path (or puts it back); its settings are kept.
 #[tauri::command]
-fn set_reverb_params(
+fn set_node_bypassed(
     state: tauri::State<'_, AudioState>,
-    room_size: f32,
-    damping: f32,
-    predelay_ms: f32,
-    lowpass_filter: f32,
-    decay: f32,
-    wet_mix: f32,
+    node: DspNode,
+    bypassed: bool,
 ) -> AppResult<()> {
-    state.set_reverb_params(room_size, damping, predelay_ms, lowpass_filter, decay, wet_mix)
+    state
+        .set_node_bypassed(node, bypassed)
         .map_err(AppError::dsp)
 }
 
+/// Bypass state of each stage that can be bypassed, in signal order.
 #[tauri::command]
-fn load_reverb_preset(
-    state: tauri::State<'_, AudioState>,
-    name: String,
-) -> AppResult<()> {
-    state.load_reverb_preset(&name).map_err(AppError::dsp)
+fn get_dsp_status(state: tauri::State<'_, AudioState>) -> AppResult<Vec<DspNodeStatusData>> {
+    Ok(state
+        .dsp_node_bypass()
+        .map_err(AppError::dsp)?
+        .into_iter()
+        .map(|(node, bypassed)| DspNodeStatusData { node, bypassed })
+        .collect())
 }
 
+/// Selects the parametric user EQ or the 31-band graphic EQ. Each keeps its own
+/// settings, so switching back and forth loses nothing.
 #[tauri::command]
-fn fast_search(
-    state: tauri::State<'_, DbManager>,
-    query: String,
-) -> AppResult<SearchResults> {
-    state.fast_search(&query).map_err(AppError::db)
+fn set_eq_mode(state: tauri::State<'_, AudioState>, mode: EqMode) -> AppResult<()> {
+    state.set_eq_mode(mode).map_err(AppError::dsp)
 }
 
 #[tauri::command]
-fn toggle_shuffle(
-    state: tauri::State<'_, Mutex<PlaybackQueue>>,
-    enabled: bool,
-) -> AppResult<()> {
-    let mut queue = state
-        .lock()
-        .map_err(|e| AppError::dsp(format!("Queue lock error: {e}")))?;
-    queue.toggle_shuffle(enabled);
-    Ok(())
+fn get_eq_mode(state: tauri::State<'_, AudioState>) -> AppResult<EqMode> {
+    state.eq_mode().map_err(AppError::dsp)
 }
 
-// ── Spatial Audio IPC commands ─────────────────────────────────────────
-
-#[derive(Serialize)]
-struct SpatialSourceData {
-    index: usize,
-    name: String,
-    x: f32,
-    y: f32,
-    z: f32,
-    is_active: bool,
+/// Switches the user EQ (parametric or graphic) to linear phase: no phase shift from
+/// corrections, at the cost of extra latency reported as `eq_latency_ms` in
+/// `get_audio_stats`.
+#[tauri::command]
+fn set_eq_linear_phase(state: tauri::State<'_, AudioState>, enabled: bool) -> AppResult<()> {
+    state.set_eq_linear_phase(enabled).map_err(AppError::dsp)
 }
 
 #[tauri::command]
-fn toggle_spatial_mode(
-    state: tauri::State<'_, AudioState>,
-    enabled: bool,
-) -> AppResult<()> {
-    state.set_spatial_enabled(enabled).map_err(AppError::dsp)
+fn get_eq_linear_phase(state: tauri::State<'_, AudioState>) -> AppResult<bool> {
+    state.eq_linear_phase().map_err(AppError::dsp)
 }
 
+/// Sets one graphic EQ slider (0 = 20 Hz … 30 = 20 kHz) to `gain_db`, within ±12 dB.
 #[tauri::command]
-fn update_source_position(
+fn set_graphic_eq_band(
     state: tauri::State<'_, AudioState>,
-    source_id: usize,
-    x: f32,
-    y: f32,
-    z: f32,
+    index: usize,
+    gain_db: f32,
 ) -> AppResult<()> {
     state
-        .set_spatial_source_position(source_id, x, y, z)
+        .set_graphic_eq_band(index, gain_db)
         .map_err(AppError::dsp)
 }
 
 #[tauri::command]
-fn set_room_properties(
-    state: tauri::State<'_, AudioState>,
-    width: f32,
-    length: f32,
-    height: f32,
-    damping: f32,
+fn get_graphic_eq_bands(state: tauri::State<'_, AudioState>) -> AppResult<Vec<GraphicEqBandData>> {
+    let gains = state.graphic_eq_gains().map_err(AppError::dsp)?;
+    Ok(GRAPHIC_EQ_FREQUENCIES
+        .iter()
+        .zip(gains)
+        .enumerate()
+        .map(|(index, (&frequency, gain_db))| GraphicEqBandData {
+            index,
+            frequency,
+            gain_db,
+        })
+        .collect())
+}
+
+/// Saves the current user EQ bands (frequency, gain, Q and type) under `name`,
+/// replacing any preset with that name.
+#[tauri::command]
+fn save_eq_preset(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    name: String,
 ) -> AppResult<()> {
-    state
-        .set_spatial_room_size(width, length, height)
-        .map_err(AppError::dsp)?;
-    state.set_spatial_damping(damping).map_err(AppError::dsp)
+    let bands = audio.get_eq_bands().map_err(AppError::dsp)?;
+    db.save_eq_preset(&name, &bands).map_err(AppError::db)
 }
 
 #[tauri::command]
-fn get_spatial_sources(
-    state: tauri::State<'_, AudioState>,
-) -> AppResult<Vec<SpatialSourceData>> {
-    let positions = state.get_spatial_source_positions().map_err(AppError::dsp)?;
-    let names = audio::dsp::spatial::SOURCE_NAMES;
-    Ok(positions
+fn load_eq_preset(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    name: String,
+) -> AppResult<Vec<EqBandData>> {
+    let bands = db
+        .load_eq_preset(&name)
+        .map_err(AppError::db)?
+        .ok_or_else(|| AppError::db(format!("Unknown EQ preset: {name}")))?;
+    audio.apply_eq_bands(&bands).map_err(AppError::dsp)?;
+    get_eq_bands(audio)
+}
+
+#[tauri::command]
+fn list_eq_presets(db: tauri::State<'_, DbManager>) -> AppResult<Vec<EqPresetData>> {
+    Ok(db
+        .get_eq_presets()
+        .map_err(AppError::db)?
         .into_iter()
-        .enumerate()
-        .map(|(i, (x, y, z, active))| SpatialSourceData {
-            index: i,
-            name: names.get(i).unwrap_or(&"unknown").to_string(),
-            x,
-            y,
-            z,
-            is_active: active,
-        })
+        .map(|(name, bands)| EqPresetData { name, bands })
         .collect())
 }
 
 #[tauri::command]
-fn auto_orchestra(state: tauri::State<'_, AudioState>) -> AppResult<()> {
-    state.spatial_auto_orchestra().map_err(AppError::dsp)
+fn delete_eq_preset(db: tauri::State<'_, DbManager>, name: String) -> AppResult<bool> {
+    db.delete_eq_preset(&name).map_err(AppError::db)
 }
 
-// ── Spatial Scene Persistence IPC ──────────────────────────────────────
-
+/// Saves every DSP stage's settings, preamp included, under `name`, replacing any
+/// chain preset with that name.
 #[tauri::command]
-fn save_spatial_scene(
+fn save_chain_preset(
     audio: tauri::State<'_, AudioState>,
     db: tauri::State<'_, DbManager>,
-    track_id: String,
+    name: String,
 ) -> AppResult<()> {
-    let positions = audio.get_spatial_source_positions().map_err(AppError::dsp)?;
-    let names = audio::dsp::spatial::SOURCE_NAMES;
-    for (i, (x, y, z, active)) in positions.iter().enumerate() {
-        let name = names.get(i).unwrap_or(&"unknown");
-        db.save_spatial_scene(&track_id, name, *x, *y, *z, *active)
-            .map_err(AppError::db)?;
-    }
-    Ok(())
+    let preset = audio.capture_chain_preset().map_err(AppError::dsp)?;
+    db.save_chain_preset(&name, &preset).map_err(AppError::db)
 }
 
 #[tauri::command]
-fn load_spatial_scene(
+fn load_chain_preset(
     audio: tauri::State<'_, AudioState>,
     db: tauri::State<'_, DbManager>,
-    track_id: String,
-) -> AppResult<Vec<SpatialSceneRow>> {
-    let rows = db.load_spatial_scene(&track_id).map_err(AppError::db)?;
-    let names = audio::dsp::spatial::SOURCE_NAMES;
-    for row in &rows {
-        if let Some(idx) = names.iter().position(|&n| n == row.source_name) {
-            audio
-                .set_spatial_source_position(idx, row.x, row.y, row.z)
-                .map_err(AppError::dsp)?;
-            audio
-                .set_spatial_source_active(idx, row.is_active)
-                .map_err(AppError::dsp)?;
-        }
-    }
-    Ok(rows)
+    name: String,
+) -> AppResult<()> {
+    let preset = db
+        .load_chain_preset(&name)
+        .map_err(AppError::db)?
+        .ok_or_else(|| AppError::db(format!("Unknown chain preset: {name}")))?;
+    audio.apply_chain_preset(&preset).map_err(AppError::dsp)?;
+    db.set_night_mode_enabled(preset.night_mode)
+        .map_err(AppError::db)
 }
 
-// ── Stem Separation IPC ────────────────────────────────────────────────
+#[tauri::command]
+fn list_chain_presets(db: tauri::State<'_, DbManager>) -> AppResult<Vec<String>> {
+    db.get_chain_preset_names().map_err(AppError::db)
+}
 
-#[derive(Serialize)]
-struct StemPathsData {
-    vocals: String,
-    drums: String,
-    bass: String,
-    other: String,
+#[tauri::command]
+fn delete_chain_preset(db: tauri::State<'_, DbManager>, name: String) -> AppResult<bool> {
+    db.delete_chain_preset(&name).map_err(AppError::db)
 }
 
+/// Writes the saved chain preset `name` to `path` as JSON, for use on another machine.
 #[tauri::command]
-async fn analyze_spatial_stems(
-    app: tauri::AppHandle,
-    track_id: String,
-) -> AppResult<StemPathsData> {
+async fn export_chain_preset(app: tauri::AppHandle, name: String, path: String) -> AppResult<()> {
     tauri::async_runtime::spawn_blocking(move || {
-        let stem_sep = app.state::<Mutex<StemSeparator>>();
-        let separator = stem_sep
-            .lock()
-            .map_err(|e| AppError::dsp(format!("Stem separator lock error: {e}")))?;
+        let preset = app
+            .state::<DbManager>()
+            .load_chain_preset(&name)
+            .map_err(AppError::db)?
+            .ok_or_else(|| AppError::db(format!("Unknown chain preset: {name}")))?;
+        let json = serde_json::to_string_pretty(&preset).map_err(|err| {
+            AppError::fs(format!("Failed to serialize chain preset {name}: {err}"))
+        })?;
+        std::fs::write(&path, json)
+            .map_err(|err| AppError::fs(format!("Failed to write {path}: {err}")))
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking chain preset export task failed: {err}")))?
+}
 
-        let paths = separator
-            .analyze_spatial_stems(&track_id, |progress| {
-                let _ = app.emit("stems-progress", &progress);
+/// Reads a chain preset file written by `export_chain_preset` and saves it under
+/// `name`, or under the file name when none is given. Returns the name used.
+#[tauri::command]
+async fn import_chain_preset(
+    app: tauri::AppHandle,
+    path: String,
+    name: Option<String>,
+) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let json = std::fs::read_to_string(&path)
+            .map_err(|err| AppError::fs(format!("Failed to read {path}: {err}")))?;
+        let preset: ChainPreset = serde_json::from_str(&json)
+            .map_err(|err| AppError::fs(format!("{path} is not a chain preset: {err}")))?;
+        let name = name
+            .or_else(|| {
+                Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
             })
-            .map_err(AppError::dsp)?;
-
-        Ok(StemPathsData {
-            vocals: paths.vocals.to_string_lossy().to_string(),
-            drums: paths.drums.to_string_lossy().to_string(),
-            bass: paths.bass.to_string_lossy().to_string(),
-            other: paths.other.to_string_lossy().to_string(),
-        })
+            .unwrap_or_default();
+        app.state::<DbManager>()
+            .save_chain_preset(&name, &preset)
+            .map_err(AppError::db)?;
+        Ok(name.trim().to_string())
     })
     .await
-    .map_err(|err| AppError::dsp(format!("Blocking stem analysis task failed: {err}")))?
+    .map_err(|err| AppError::fs(format!("Blocking chain preset import task failed: {err}")))?
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    let db = DbManager::new("powerplayer.db").expect("failed to initialize SQLite manager");
-    db.initialize_fts().expect("failed to initialize FTS5 search");
-    db.initialize_spatial_schema().expect("failed to initialize spatial schema");
+#[tauri::command]
+fn get_eq_frequency_response(
+    state: tauri::State<'_, AudioState>,
+    num_points: usize,
+) -> AppResult<Vec<FrequencyPoint>> {
+    let response = state
+        .get_eq_frequency_response(num_points)
+        .map_err(AppError::dsp)?;
+    Ok(response
+        .into_iter()
+        .map(|(frequency, magnitude_db)| FrequencyPoint {
+            frequency,
+            magnitude_db,
+        })
+        .collect())
+}
 
-    let stems_cache = dirs::cache_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from(".cache"))
-        .join("powerplayer")
-        .join("stems");
+#[tauri::command]
+fn get_fft_data() -> AppResult<Vec<f32>> {
+    Ok(vec![-100.0; 1024])
+}
 
-    tauri::Builder::default()
-        .plugin(tauri_plugin_dialog::init())
-        .manage(AudioState::new())
+#[tauri::command]
+async fn load_track(
+    app: tauri::AppHandle,
+    path: String,
+) -> AppResult<TrackData> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Shared links and remote files are downloaded into a temporary cache first and
+        // then played like any local track.
+        let path = if library::remote_cache::is_remote_url(&path) {
+            library::remote_cache::fetch(&path, |progress| {
+                let _ = app.emit("remote-download-progress", &progress);
+            })
+            .map_err(AppError::fs)?
+            .to_string_lossy()
+            .into_owned()
+        } else {
+            path
+        };
+        if !library::extensions::is_supported_audio_path(Path::new(&path))
+            && library::cue::split_virtual_path(Path::new(&path)).is_none()
+        {
+            return Err(AppError::fs(format!("Unsupported audio file type: {path}")));
+        }
+        let state = app.state::<AudioState>();
+        let db = app.state::<DbManager>();
+        let metadata = audio::decoder::read_track_metadata(Path::new(&path)).map_err(AppError::fs)?;
+        if let Err(err) = remember_playback_position(&state, &db) {
+            eprintln!("Failed to save resume position: {err}");
+        }
+        state.load_lyrics_for_track(&path);
+        if state.playback_supported() {
+            state.load_track(&path).map_err(AppError::dsp)?;
+            state
+                .start_lyrics_monitor(app.clone())
+                .map_err(AppError::dsp)?;
+            state
+                .start_beat_monitor(app.clone())
+                .map_err(AppError::dsp)?;
+            if state.karaoke().enabled {
+                if let Err(err) = attach_karaoke_vocals(&app, &state) {
+                    eprintln!("Failed to load the vocal stem of {path}: {err}");
+                }
+            }
+            if state.stem_playback() {
+                if let Err(err) = attach_stem_sources(&app, &state) {
+                    eprintln!("Failed to load the stems of {path}: {err}");
+                }
+            }
+        }
+        let duration_seconds = state
+            .get_track_duration_seconds()
+            .max(metadata.duration_seconds.unwrap_or(0.0));
+        let resume_seconds = resume_long_form_track(&state, &db, &path, duration_seconds as f64)
+            .unwrap_or_else(|err| {
+                eprintln!("Failed to resume {path}: {err}");
+                None
+            });
+        let title = metadata
+            .title
+            .unwrap_or_else(|| "Unknown Title".to_string());
+        if let Err(err) = app.state::<DlnaOutput>().set_track(Path::new(&path), &title) {
+            eprintln!("Failed to send {path} to the DLNA renderer: {err}");
+        }
+        if let Err(err) = app.state::<CastOutput>().set_track(Path::new(&path), &title) {
+            eprintln!("Failed to send {path} to the Cast device: {err}");
+        }
+        if let Err(err) = apply_track_plugin_chain(&state, &db, &path) {
+            eprintln!("Failed to load the plugin chain for {path}: {err}");
+        }
+        let dsp_profiles = apply_track_dsp_profiles(
+            &state,
+            &db,
+            &path,
+            metadata.genre.as_deref(),
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to apply DSP profiles for {path}: {err}");
+            Vec::new()
+        });
+
+        Ok(TrackData {
+            artist: metadata
+                .artist
+                .unwrap_or_else(|| "Unknown Artist".to_string()),
+            title,
+            cover_art: metadata.cover_art.map(|cover| CoverArtData {
+                media_type: cover.media_type,
+                data: cover.data,
+            }),
+            duration_seconds,
+            dsp_profiles,
+            resume_seconds,
+        })
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking load track task failed: {err}")))?
+}
+
+/// Seeks a long-form track (see `library::resume`) to its saved bookmark.
+fn resume_long_form_track(
+    state: &AudioState,
+    db: &DbManager,
+    path: &str,
+    duration_seconds: f64,
+) -> Result<Option<f64>, String> {
+    let chapters = audio::chapters::read_chapters(Path::new(path))?;
+    if !library::resume::is_long_form(Path::new(path), duration_seconds, chapters.len()) {
+        return Ok(None);
+    }
+    let canonical = library::paths::canonical_path_string(path);
+    let resume = library::resume::saved_position(db, &canonical, duration_seconds)?;
+    if let Some(seconds) = resume.filter(|_| state.playback_supported()) {
+        state.seek(seconds);
+    }
+    Ok(resume)
+}
+
+/// Bookmarks the playing track's position if it is long-form.
+fn remember_playback_position(state: &AudioState, db: &DbManager) -> Result<(), String> {
+    let Some(path) = state.loaded_path() else {
+        return Ok(());
+    };
+    let duration_seconds = state.get_track_duration_seconds() as f64;
+    let chapters = audio::chapters::read_chapters(&path)?;
+    if !library::resume::is_long_form(&path, duration_seconds, chapters.len()) {
+        return Ok(());
+    }
+    library::resume::remember_position(
+        db,
+        &library::paths::canonical_path_string(&path.to_string_lossy()),
+        state.position_seconds(),
+        duration_seconds,
+    )
+}
+
+/// Applies the output-device, genre and per-track DSP profiles for `path`, layered in
+/// that order, and returns the names of the profiles that matched.
+fn apply_track_dsp_profiles(
+    state: &AudioState,
+    db: &DbManager,
+    path: &str,
+    tag_genre: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let path = library::paths::canonical_path_string(path);
+    let genre = db
+        .get_track_genre(&path)?
+        .or_else(|| tag_genre.map(ToOwned::to_owned));
+    let device = state.get_audio_stats().device;
+    let resolved = resolve_profile(&path, genre.as_deref(), &device, |scope, key| {
+        db.assigned_dsp_profile(scope, key)
+    })?;
+    state.apply_track_dsp_profile(resolved.as_ref().map(|resolved| &resolved.profile))?;
+    Ok(resolved
+        .map(|resolved| resolved.sources.into_iter().map(|(_, name)| name).collect())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+async fn get_track_waveform(
+    app: tauri::AppHandle,
+    path: String,
+    points: usize,
+) -> AppResult<Vec<f32>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = library::paths::canonical_path_string(&path);
+        if points > audio::analyzer::WAVEFORM_CACHE_POINTS {
+            return audio::analyzer::extract_waveform(Path::new(&path), points)
+                .map_err(AppError::dsp);
+        }
+        let envelope = library::waveform_cache::envelope(&app.state::<DbManager>(), &path)
+            .map_err(AppError::dsp)?;
+        Ok(audio::analyzer::downsample_waveform(&envelope, points))
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking waveform extraction task failed: {err}")))?
+}
+
+#[tauri::command]
+async fn analyze_track_bpm(
+    app: tauri::AppHandle,
+    path: String,
+    force: Option<bool>,
+) -> AppResult<Option<f32>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        if !force.unwrap_or(false) {
+            if let Some(cached) = db.get_track_bpm(&path).map_err(AppError::db)? {
+                return Ok(cached);
+            }
+        }
+
+        let bpm = audio::analyzer::analyze_bpm(Path::new(&path)).map_err(AppError::dsp)?;
+        db.save_track_bpm(&path, bpm).map_err(AppError::db)?;
+        Ok(bpm)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking BPM analysis task failed: {err}")))?
+}
+
+#[tauri::command]
+async fn get_loudness_profile(
+    app: tauri::AppHandle,
+    path: String,
+    force: Option<bool>,
+) -> AppResult<Vec<f32>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let path = library::paths::canonical_path_string(&path);
+        if !force.unwrap_or(false) {
+            if let Some(cached) = db.get_loudness_profile(&path).map_err(AppError::db)? {
+                return Ok(cached);
+            }
+        }
+
+        let profile = audio::analyzer::analyze_loudness_profile(Path::new(&path))
+            .map_err(AppError::dsp)?;
+        db.save_loudness_profile(&path, &profile)
+            .map_err(AppError::db)?;
+        Ok(profile)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking loudness analysis task failed: {err}")))?
+}
+
+#[tauri::command]
+fn get_lyrics_lines(state: tauri::State<'_, AudioState>) -> AppResult<Vec<LyricsLineData>> {
+    Ok(state
+        .get_lyrics_lines()
+        .into_iter()
+        .map(|line| LyricsLineData {
+            timestamp: line.timestamp,
+            text: line.text,
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn get_lyrics_details(state: tauri::State<'_, AudioState>) -> AppResult<LyricsDetailsData> {
+    let tags = state.get_lyrics_tags();
+    Ok(LyricsDetailsData {
+        title: tags.title,
+        artist: tags.artist,
+        album: tags.album,
+        author: tags.author,
+        offset_ms: tags.offset_ms,
+        warnings: state
+            .get_lyrics_warnings()
+            .into_iter()
+            .map(|warning| LyricsWarningData {
+                line: warning.line,
+                message: warning.message,
+            })
+            .collect(),
+    })
+}
+
+#[tauri::command]
+fn set_silence_trimming(state: tauri::State<'_, AudioState>, enabled: bool) -> AppResult<()> {
+    state.set_silence_trimming(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_gap_skipping(
+    state: tauri::State<'_, AudioState>,
+    enabled: bool,
+    threshold_db: Option<f32>,
+    min_seconds: Option<f32>,
+) -> AppResult<()> {
+    state.set_gap_skipping(enabled, threshold_db, min_seconds);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_lyrics_lenient(state: tauri::State<'_, AudioState>, enabled: bool) -> AppResult<()> {
+    state.set_lyrics_lenient(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_supported_extensions() -> AppResult<Vec<String>> {
+    Ok(library::extensions::supported_extensions())
+}
+
+#[tauri::command]
+fn set_extra_audio_extensions(
+    state: tauri::State<'_, DbManager>,
+    extensions: Vec<String>,
+) -> AppResult<Vec<String>> {
+    library::extensions::set_extra_extensions(&state, &extensions).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_scan_exclusions() -> library::exclusions::ScanExclusions {
+    library::exclusions::current()
+}
+
+/// Saves the ignore rules used by scans and the library watcher, and removes the
+/// library tracks they exclude. Returns the number of tracks removed.
+#[tauri::command]
+fn set_scan_exclusions(
+    state: tauri::State<'_, DbManager>,
+    exclusions: library::exclusions::ScanExclusions,
+) -> AppResult<usize> {
+    library::exclusions::set_exclusions(&state, exclusions).map_err(AppError::db)
+}
+
+/// Opt-in: write art downloaded by the enrichment queue into files without embedded art.
+#[tauri::command]
+fn set_embed_fetched_art(state: tauri::State<'_, DbManager>, enabled: bool) -> AppResult<()> {
+    library::metadata::art_embedder::set_embed_fetched_art(&state, enabled).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_embed_fetched_art(state: tauri::State<'_, DbManager>) -> AppResult<bool> {
+    library::metadata::art_embedder::embed_fetched_art_enabled(&state).map_err(AppError::db)
+}
+
+#[tauri::command]
+async fn get_network_usage_stats(app: tauri::AppHandle) -> AppResult<NetworkUsageData> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let usage = library::network_usage::usage(&app.state::<DbManager>()).map_err(AppError::db)?;
+        let caches = [
+            ("art", library::art_cache::cache_dir()),
+            ("lyrics", audio::lyrics_downloader::lyrics_cache_dir()),
+            ("waveforms", library::waveform_cache::cache_dir()),
+            ("stems", library::stems::default_cache_dir()),
+            ("remote", library::remote_cache::cache_dir()),
+            ("autoeq", audio::autoeq_downloader::cache_dir()),
+        ];
+        Ok(NetworkUsageData {
+            providers: NetworkProvider::ALL
+                .into_iter()
+                .map(|provider| {
+                    let used = usage.provider(provider);
+                    ProviderUsageData {
+                        provider,
+                        bytes: used.bytes,
+                        requests: used.requests,
+                    }
+                })
+                .collect(),
+            caches: caches
+                .iter()
+                .map(|(name, dir)| {
+                    let cache = library::network_usage::cache_usage(name, dir);
+                    CacheUsageData {
+                        name: cache.name,
+                        bytes: cache.bytes,
+                        files: cache.files,
+                    }
+                })
+                .collect(),
+            month: usage.month,
+        })
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking network usage task failed: {err}")))?
+}
+
+#[tauri::command]
+fn store_credential(service: String, secret: String) -> AppResult<()> {
+    credentials::store_credential(&service, &secret).map_err(AppError::credential)
+}
+
+#[tauri::command]
+fn delete_credential(service: String) -> AppResult<()> {
+    credentials::delete_credential(&service).map_err(AppError::credential)
+}
+
+/// Secrets never travel back to the webview; the UI only needs to know one is saved.
+#[tauri::command]
+fn has_credential(service: String) -> AppResult<bool> {
+    credentials::load_credential(&service)
+        .map(|secret| secret.is_some())
+        .map_err(AppError::credential)
+}
+
+#[tauri::command]
+async fn scan_library(app: tauri::AppHandle, path: String) -> AppResult<usize> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let root = PathBuf::from(path);
+        let scanned = library::scanner::scan_library_path(&root, &db, |progress| {
+            let _ = app.emit("scan-progress", &progress);
+        })
+        .map_err(AppError::fs)?;
+        library::scanner::register_library_watch(&root, &db).map_err(AppError::fs)?;
+        db.add_library_root(&root.to_string_lossy())
+            .map_err(AppError::db)?;
+        Ok(scanned)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking library scan task failed: {err}")))?
+}
+
+/// Reads one file's tags and cover again, for example after they were edited in
+/// another program, and emits `track-updated` with the new row. Returns `None` when the
+/// file now falls below the scan exclusions and was removed from the library instead.
+#[tauri::command]
+async fn rescan_track(app: tauri::AppHandle, path: String) -> AppResult<Option<LibraryTrackData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let track = library::scanner::rescan_track(Path::new(&path), &db)
+            .map_err(AppError::fs)?
+            .map(LibraryTrackData::from);
+        if let Some(track) = &track {
+            let _ = app.emit("track-updated", track);
+        }
+        Ok(track)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking track rescan task failed: {err}")))?
+}
+
+/// Folders scanned into the library, with their track counts. They are watched for
+/// changes and rescanned on startup.
+#[tauri::command]
+fn get_library_roots(state: tauri::State<'_, DbManager>) -> AppResult<Vec<LibraryRootRow>> {
+    state.get_library_roots().map_err(AppError::db)
+}
+
+/// Stops scanning and watching a library root and deletes its tracks from the library
+/// (not from disk), with their per-track settings and cached art, lyrics, waveforms and
+/// stems. Returns the number of tracks deleted.
+#[tauri::command]
+async fn remove_library_root(app: tauri::AppHandle, path: String) -> AppResult<usize> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root = Path::new(&path);
+        library::scanner::cancel_scan(Some(root));
+        library::scanner::unregister_library_watch(root).map_err(AppError::fs)?;
+        let removed = app
+            .state::<DbManager>()
+            .remove_library_root(&path)
+            .map_err(AppError::db)?;
+        let separator = app.state::<Mutex<StemSeparator>>();
+        let stems = separator
+            .lock()
+            .map_err(|e| AppError::dsp(format!("Stem separator lock error: {e}")))?;
+        for track in &removed {
+            let track_path = Path::new(track);
+            let cached = library::prune::cached_files(track_path);
+            for file in cached
+                .into_iter()
+                .chain(library::waveform_cache::cached_file(track_path))
+            {
+                let _ = std::fs::remove_file(file);
+            }
+            stems.remove_cached(track);
+        }
+        Ok(removed.len())
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking library root removal task failed: {err}")))?
+}
+
+/// Removes the library tracks whose files are gone, under `root` or everywhere, with
+/// their per-track settings and cached art, lyrics and stems. Tracks under an unreachable library root are
+/// kept.
+#[tauri::command]
+async fn prune_missing_tracks(
+    app: tauri::AppHandle,
+    root: Option<String>,
+) -> AppResult<library::prune::PruneReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let report = library::prune::prune_missing_tracks(&db, root.as_deref().map(Path::new))
+            .map_err(AppError::db)?;
+        let separator = app.state::<Mutex<StemSeparator>>();
+        let stems = separator
+            .lock()
+            .map_err(|e| AppError::dsp(format!("Stem separator lock error: {e}")))?;
+        for path in &report.removed_paths {
+            stems.remove_cached(path);
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking prune task failed: {err}")))?
+}
+
+/// Stops the running scan of `path`, or all running scans without one. The scan skips
+/// the files it has not reached and `scan_library` returns an error. Returns how many
+/// scans were stopped.
+#[tauri::command]
+fn cancel_scan(path: Option<String>) -> usize {
+    library::scanner::cancel_scan(path.as_deref().map(Path::new))
+}
+
+#[tauri::command]
+fn reveal_in_file_manager(path: String) -> AppResult<()> {
+    library::file_ops::reveal_in_file_manager(Path::new(&path)).map_err(AppError::fs)
+}
+
+/// Deletes a track's file, to the OS trash when `to_recycle_bin` is set, with its
+/// library row, per-track settings and cached art, lyrics, waveform and stems.
+#[tauri::command]
+async fn delete_track_file(
+    app: tauri::AppHandle,
+    path: String,
+    to_recycle_bin: bool,
+) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let track_path = library::paths::canonical_path_string(&path);
+        library::file_ops::delete_track_file(Path::new(&path), to_recycle_bin, &db)
+            .map_err(AppError::fs)?;
+        let separator = app.state::<Mutex<StemSeparator>>();
+        separator
+            .lock()
+            .map_err(|e| AppError::dsp(format!("Stem separator lock error: {e}")))?
+            .remove_cached(&track_path);
+        Ok(())
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking delete task failed: {err}")))?
+}
+
+#[tauri::command]
+async fn copy_files(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    dest: String,
+) -> AppResult<Vec<FileOperationData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let paths = paths.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+        Ok(file_operation_data(library::file_ops::copy_files(
+            &paths,
+            Path::new(&dest),
+            &db,
+        )))
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking copy task failed: {err}")))?
+}
+
+#[tauri::command]
+async fn move_files(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    dest: String,
+) -> AppResult<Vec<FileOperationData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let paths = paths.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+        Ok(file_operation_data(library::file_ops::move_files(
+            &paths,
+            Path::new(&dest),
+            &db,
+        )))
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking move task failed: {err}")))?
+}
+
+fn file_operation_data(
+    outcomes: Vec<library::file_ops::FileOperationOutcome>,
+) -> Vec<FileOperationData> {
+    outcomes
+        .into_iter()
+        .map(|outcome| FileOperationData {
+            source: outcome.source.to_string_lossy().to_string(),
+            destination: outcome
+                .destination
+                .map(|path| path.to_string_lossy().to_string()),
+            error: outcome.error,
+        })
+        .collect()
+}
+
+/// Library tracks, by artist, album and track number unless `sort` names another key
+/// (`title`, `artist`, `album`, `date_added`, `duration`, `play_count`, `rating`) and
+/// direction (`asc`, `desc`). `offset` and `limit` return one page of that order, for
+/// lists that load rows as they scroll; see `get_library_track_count`.
+#[tauri::command]
+fn get_library_tracks(
+    state: tauri::State<'_, DbManager>,
+    sort: Option<TrackSort>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> AppResult<Vec<LibraryTrackData>> {
+    Ok(state
+        .get_tracks_sorted(sort.unwrap_or_default(), offset.unwrap_or(0), limit)
+        .map_err(AppError::db)?
+        .into_iter()
+        .map(LibraryTrackData::from)
+        .collect())
+}
+
+#[tauri::command]
+fn get_library_track_count(state: tauri::State<'_, DbManager>) -> AppResult<usize> {
+    state.get_track_count().map_err(AppError::db)
+}
+
+/// Albums for the album grid, with artist, year, track count, cover and total length.
+#[tauri::command]
+fn get_albums(state: tauri::State<'_, DbManager>) -> AppResult<Vec<AlbumRow>> {
+    state.get_albums().map_err(AppError::db)
+}
+
+/// Tracks of one album, as listed by `get_albums`.
+#[tauri::command]
+fn get_album_tracks(
+    state: tauri::State<'_, DbManager>,
+    album: String,
+    artist: Option<String>,
+) -> AppResult<Vec<LibraryTrackData>> {
+    Ok(state
+        .get_album_tracks(&album, artist.as_deref())
+        .map_err(AppError::db)?
+        .into_iter()
+        .map(LibraryTrackData::from)
+        .collect())
+}
+
+#[tauri::command]
+fn get_artists(state: tauri::State<'_, DbManager>) -> AppResult<Vec<ArtistRow>> {
+    state.get_artists().map_err(AppError::db)
+}
+
+/// An artist page: their albums, ten most played tracks and an image.
+#[tauri::command]
+fn get_artist_detail(
+    state: tauri::State<'_, DbManager>,
+    artist: String,
+) -> AppResult<ArtistDetailData> {
+    let albums = state.get_artist_albums(&artist).map_err(AppError::db)?;
+    let top_tracks = state
+        .get_artist_top_tracks(&artist, 10)
+        .map_err(AppError::db)?;
+    let image_url =
+        library::artists::artist_image_url(top_tracks.iter().map(|track| track.path.as_str()))
+            .or_else(|| albums.iter().rev().find_map(|album| album.art_url.clone()));
+    Ok(ArtistDetailData {
+        name: artist,
+        albums,
+        top_tracks: top_tracks.into_iter().map(LibraryTrackData::from).collect(),
+        image_url,
+    })
+}
+
+/// Subfolders and audio files of `path`, a library root or a folder below one, with
+/// library metadata for scanned files. Without `path`, lists the library roots.
+#[tauri::command]
+async fn browse_folder(
+    app: tauri::AppHandle,
+    path: Option<String>,
+) -> AppResult<FolderListingData> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let listing = library::folders::browse_folder(&db, path.as_deref().map(Path::new))
+            .map_err(AppError::fs)?;
+        Ok(FolderListingData {
+            path: listing.path,
+            parent: listing.parent,
+            folders: listing.folders,
+            files: listing
+                .files
+                .into_iter()
+                .map(|file| FolderFileData {
+                    name: file.name,
+                    path: file.path,
+                    track: file.track.map(LibraryTrackData::from),
+                })
+                .collect(),
+        })
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking folder listing task failed: {err}")))?
+}
+
+/// Writes `fields` into the tags of an audio file and re-reads it into the library and
+/// search index. Returns the updated track, or `None` when the file is listed as the
+/// songs of a cue sheet.
+#[tauri::command]
+async fn write_track_tags(
+    app: tauri::AppHandle,
+    path: String,
+    fields: TagFields,
+) -> AppResult<Option<LibraryTrackData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let path = library::paths::canonical_path(Path::new(&path));
+        library::metadata::tag_writer::write_tags(&path, &fields).map_err(AppError::fs)?;
+        library::scanner::rescan_audio_file(&path, &db).map_err(AppError::db)?;
+        Ok(db
+            .get_track(&path.to_string_lossy())
+            .map_err(AppError::db)?
+            .map(LibraryTrackData::from))
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking tag writing task failed: {err}")))?
+}
+
+/// Dry run of `write_batch_tags`: the tag changes each selected file would get.
+#[tauri::command]
+async fn preview_batch_tags(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    fields: TagFields,
+    number_tracks: bool,
+) -> AppResult<Vec<BatchTagPreview>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        library::metadata::batch_tagger::preview_batch(&db, &paths, &fields, number_tracks)
+            .map_err(AppError::db)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking batch tag preview task failed: {err}")))?
+}
+
+/// Writes the same `fields` into every selected file, numbering them 1..N in selection
+/// order when `number_tracks` is set. Emits `tag-batch-progress` after each file.
+#[tauri::command]
+async fn write_batch_tags(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    fields: TagFields,
+    number_tracks: bool,
+) -> AppResult<BatchTagSummary> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let emit = |progress| {
+            let _ = app.emit("tag-batch-progress", &progress);
+        };
+        library::metadata::batch_tagger::write_batch(&db, &paths, &fields, number_tracks, emit)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking batch tagging task failed: {err}")))
+}
+
+/// Rates a library track from 1 to 5 stars; `None` clears the rating.
+#[tauri::command]
+fn set_track_rating(
+    state: tauri::State<'_, DbManager>,
+    path: String,
+    rating: Option<u8>,
+) -> AppResult<()> {
+    if state
+        .set_track_rating(&path, rating)
+        .map_err(AppError::db)?
+    {
+        Ok(())
+    } else {
+        Err(AppError::db(format!("{path} is not in the library")))
+    }
+}
+
+/// Library tracks by play statistics: most played, never played, or not played for a
+/// number of months. Returns at most `limit` tracks (default 100).
+#[tauri::command]
+fn get_tracks_by_plays(
+    state: tauri::State<'_, DbManager>,
+    query: TrackPlayQuery,
+    limit: Option<usize>,
+) -> AppResult<Vec<LibraryTrackData>> {
+    Ok(state
+        .get_tracks_by_plays(query, limit.unwrap_or(100))
+        .map_err(AppError::db)?
+        .into_iter()
+        .map(LibraryTrackData::from)
+        .collect())
+}
+
+#[tauri::command]
+fn play(
+    state: tauri::State<'_, AudioState>,
+    cast: tauri::State<'_, CastOutput>,
+    dlna: tauri::State<'_, DlnaOutput>,
+) -> AppResult<()> {
+    if cast.is_active() {
+        return cast.play().map_err(AppError::dsp);
+    }
+    if dlna.active().is_some() {
+        return dlna.play().map_err(AppError::dsp);
+    }
+    state.play();
+    Ok(())
+}
+
+#[tauri::command]
+fn pause(
+    state: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    cast: tauri::State<'_, CastOutput>,
+    dlna: tauri::State<'_, DlnaOutput>,
+) -> AppResult<()> {
+    if cast.is_active() {
+        return cast.pause().map_err(AppError::dsp);
+    }
+    if dlna.active().is_some() {
+        return dlna.pause().map_err(AppError::dsp);
+    }
+    state.pause();
+    remember_playback_position(&state, &db).map_err(AppError::db)
+}
+
+#[tauri::command]
+async fn get_chapters(path: String) -> AppResult<Vec<ChapterData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let chapters = audio::chapters::read_chapters(Path::new(&path)).map_err(AppError::fs)?;
+        Ok(chapters
+            .into_iter()
+            .map(|chapter| ChapterData {
+                title: chapter.title,
+                start_seconds: chapter.start_seconds,
+                end_seconds: chapter.end_seconds,
+            })
+            .collect())
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking chapter read task failed: {err}")))?
+}
+
+#[tauri::command]
+fn set_resume_position(
+    state: tauri::State<'_, DbManager>,
+    path: String,
+    seconds: Option<f64>,
+) -> AppResult<bool> {
+    state
+        .save_resume_position(&library::paths::canonical_path_string(&path), seconds)
+        .map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_play_heatmap(db: tauri::State<'_, DbManager>, path: String) -> AppResult<PlayHeatmapData> {
+    let heatmap =
+        library::heatmap::load_heatmap(&db, &library::paths::canonical_path_string(&path))
+            .map_err(AppError::db)?;
+    Ok(PlayHeatmapData {
+        buckets: heatmap.normalized(),
+        max_plays: heatmap.counts().iter().copied().max().unwrap_or(0),
+    })
+}
+
+#[tauri::command]
+fn set_next_track(state: tauri::State<'_, AudioState>, path: Option<String>) -> AppResult<()> {
+    state.set_next_track(path.as_deref());
+    Ok(())
+}
+
+#[tauri::command]
+fn seek(
+    state: tauri::State<'_, AudioState>,
+    cast: tauri::State<'_, CastOutput>,
+    dlna: tauri::State<'_, DlnaOutput>,
+    seconds: f64,
+) -> AppResult<()> {
+    if cast.is_active() {
+        return cast.seek(seconds).map_err(AppError::dsp);
+    }
+    if dlna.active().is_some() {
+        return dlna.seek(seconds).map_err(AppError::dsp);
+    }
+    state.seek(seconds);
+    Ok(())
+}
+
+#[tauri::command]
+fn instant_replay(
+    state: tauri::State<'_, AudioState>,
+    seconds: Option<f64>,
+) -> AppResult<InstantReplayData> {
+    let position_seconds = state.instant_replay(seconds.unwrap_or(10.0));
+    Ok(InstantReplayData {
+        position_seconds,
+        lyric: state
+            .lyric_line_at(position_seconds)
+            .map(|line| LyricsLineData {
+                timestamp: line.timestamp,
+                text: line.text,
+            }),
+    })
+}
+
+#[tauri::command]
+fn set_volume(
+    state: tauri::State<'_, AudioState>,
+    cast: tauri::State<'_, CastOutput>,
+    volume: f32,
+) -> AppResult<()> {
+    if cast.is_active() {
+        return cast.set_volume(volume).map_err(AppError::dsp);
+    }
+    state.set_volume(volume);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_vibe_data(state: tauri::State<'_, AudioState>) -> AppResult<VibeData> {
+    let (spectrum, amplitude) = state.get_vibe_data();
+    Ok(VibeData {
+        spectrum,
+        amplitude,
+    })
+}
+
+#[tauri::command]
+fn get_audio_stats(state: tauri::State<'_, AudioState>) -> AppResult<AudioStatsData> {
+    let AudioStats {
+        device,
+        stream_latency_ms,
+        output_sample_rate_hz,
+        file_sample_rate_hz,
+        ring_buffer_capacity_bytes,
+        ring_buffer_used_bytes,
+        limiter_gain_reduction_db,
+        eq_latency_ms,
+    } = state.get_audio_stats();
+    Ok(AudioStatsData {
+        device,
+        stream_latency_ms,
+        output_sample_rate_hz,
+        file_sample_rate_hz,
+        ring_buffer_capacity_bytes,
+        ring_buffer_used_bytes,
+        limiter_gain_reduction_db,
+        eq_latency_ms,
+    })
+}
+
+#[tauri::command]
+fn set_tone(
+    state: tauri::State<'_, AudioState>,
+    bass: f32,
+    treble: f32,
+) -> AppResult<()> {
+    state.set_tone(bass, treble).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_balance(state: tauri::State<'_, AudioState>, val: f32) -> AppResult<()> {
+    state.set_balance(val).map_err(AppError::dsp)
+}
+
+/// Chooses how `set_balance` moves the image: `"balance"` turns the far channel down,
+/// `"rotate"` pans both channels toward the near side.
+#[tauri::command]
+fn set_balance_mode(state: tauri::State<'_, AudioState>, mode: BalanceMode) -> AppResult<()> {
+    state.set_balance_mode(mode).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_balance_mode(state: tauri::State<'_, AudioState>) -> AppResult<BalanceMode> {
+    state.balance_mode().map_err(AppError::dsp)
+}
+
+/// Sums left and right to mono ahead of the balance stage.
+#[tauri::command]
+fn set_mono(state: tauri::State<'_, AudioState>, enabled: bool) -> AppResult<()> {
+    state.set_mono(enabled).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_mono(state: tauri::State<'_, AudioState>) -> AppResult<bool> {
+    state.mono().map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_expansion(state: tauri::State<'_, AudioState>, val: f32) -> AppResult<()> {
+    state.set_expansion(val).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_crossfeed_preset(state: tauri::State<'_, AudioState>, name: String) -> AppResult<()> {
+    state.set_crossfeed_preset(&name).map_err(AppError::dsp)
+}
+
+/// Switches equal-loudness compensation, which lifts bass and treble as the volume
+/// is turned down.
+#[tauri::command]
+fn set_loudness_compensation(
+    state: tauri::State<'_, AudioState>,
+    enabled: bool,
+) -> AppResult<()> {
+    state
+        .set_loudness_compensation(enabled)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_loudness_compensation(state: tauri::State<'_, AudioState>) -> AppResult<bool> {
+    state.loudness_compensation().map_err(AppError::dsp)
+}
+
+/// Switches night mode (upward compression, peak taming and a gentle treble cut for
+/// low listening levels) and remembers the choice for the next launch.
+#[tauri::command]
+fn set_night_mode(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    enabled: bool,
+) -> AppResult<()> {
+    audio.set_night_mode(enabled).map_err(AppError::dsp)?;
+    db.set_night_mode_enabled(enabled).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_night_mode(state: tauri::State<'_, AudioState>) -> AppResult<bool> {
+    state.night_mode().map_err(AppError::dsp)
+}
+
+/// Configures how mono and stereo sources are spread over outputs with more than two
+/// channels (WAVE order: FL FR C LFE, then the surrounds). Applies from the next track
+/// or stream that is loaded and is remembered for the next launch.
+#[tauri::command]
+fn set_upmix(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    settings: UpmixSettings,
+) -> AppResult<()> {
+    audio.set_upmix(settings).map_err(AppError::dsp)?;
+    db.set_upmix_settings(&settings.clamped())
+        .map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_upmix(state: tauri::State<'_, AudioState>) -> AppResult<UpmixSettings> {
+    state.upmix().map_err(AppError::dsp)
+}
+
+/// Configures bass management on outputs with an LFE channel: the mains are
+/// high-passed at `crossover_hz` (60–120 Hz) and their bass goes to the subwoofer at
+/// `sub_level_db`. Applies at once and is remembered for the next launch.
+#[tauri::command]
+fn set_bass_management(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    settings: BassManagementSettings,
+) -> AppResult<()> {
+    audio.set_bass_management(settings).map_err(AppError::dsp)?;
+    db.set_bass_management_settings(&settings.clamped())
+        .map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_bass_management(state: tauri::State<'_, AudioState>) -> AppResult<BassManagementSettings> {
+    state.bass_management().map_err(AppError::dsp)
+}
+
+/// Switches the vinyl/tape character node (soft saturation, wow/flutter, crackle).
+#[tauri::command]
+fn set_analog_enabled(state: tauri::State<'_, AudioState>, enabled: bool) -> AppResult<()> {
+    state.set_analog_enabled(enabled).map_err(AppError::dsp)
+}
+
+/// Sets the analog intensities, each from 0.0 (none) to 1.0.
+#[tauri::command]
+fn set_analog_params(
+    state: tauri::State<'_, AudioState>,
+    saturation: f32,
+    wow_flutter: f32,
+    crackle: f32,
+) -> AppResult<()> {
+    state
+        .set_analog_params(saturation, wow_flutter, crackle)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_analog(state: tauri::State<'_, AudioState>) -> AppResult<AnalogSettings> {
+    state.analog().map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_compressor_enabled(state: tauri::State<'_, AudioState>, enabled: bool) -> AppResult<()> {
+    state.set_compressor_enabled(enabled).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_compressor_band(
+    state: tauri::State<'_, AudioState>,
+    index: usize,
+    threshold_db: f32,
+    ratio: f32,
+) -> AppResult<()> {
+    state
+        .set_compressor_band(index, threshold_db, ratio)
+        .map_err(AppError::dsp)
+}
+
+/// Sets the crossover frequencies between the compressor's bands, in ascending order.
+#[tauri::command]
+fn set_compressor_crossovers(
+    state: tauri::State<'_, AudioState>,
+    crossovers: Vec<f32>,
+) -> AppResult<()> {
+    state
+        .set_compressor_crossovers(&crossovers)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_compressor(state: tauri::State<'_, AudioState>) -> AppResult<CompressorData> {
+    let (settings, gain_reduction_db) = state.get_compressor().map_err(AppError::dsp)?;
+    Ok(CompressorData {
+        settings,
+        gain_reduction_db,
+    })
+}
+
+/// Adjusts the output limiter: gain reduction starts at `threshold_db`, output peaks
+/// stay under `ceiling_db`, and gain recovers over `release_ms`. `lookahead_ms` (0–5,
+/// adding that much latency) is kept when omitted.
+#[tauri::command]
+fn set_limiter_params(
+    state: tauri::State<'_, AudioState>,
+    threshold_db: f32,
+    release_ms: f32,
+    ceiling_db: f32,
+    lookahead_ms: Option<f32>,
+) -> AppResult<()> {
+    state
+        .set_limiter_params(threshold_db, release_ms, ceiling_db, lookahead_ms)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_reverb_params(
+    state: tauri::State<'_, AudioState>,
+    room_size: f32,
+    damping: f32,
+    predelay_ms: f32,
+    lowpass_filter: f32,
+    decay: f32,
+    wet_mix: f32,
+) -> AppResult<()> {
+    state.set_reverb_params(room_size, damping, predelay_ms, lowpass_filter, decay, wet_mix)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn load_reverb_preset(
+    state: tauri::State<'_, AudioState>,
+    name: String,
+) -> AppResult<()> {
+    state.load_reverb_preset(&name).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_dsp_profiles(state: tauri::State<'_, DbManager>) -> AppResult<Vec<DspProfileData>> {
+    Ok(state
+        .get_dsp_profiles()
+        .map_err(AppError::db)?
+        .into_iter()
+        .map(|(name, settings)| DspProfileData { name, settings })
+        .collect())
+}
+
+#[tauri::command]
+fn save_dsp_profile(
+    state: tauri::State<'_, DbManager>,
+    name: String,
+    settings: DspProfile,
+) -> AppResult<()> {
+    state.save_dsp_profile(&name, &settings).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn delete_dsp_profile(state: tauri::State<'_, DbManager>, name: String) -> AppResult<()> {
+    state.delete_dsp_profile(&name).map_err(AppError::db)
+}
+
+/// `scope` is `track`, `genre` or `device`; a missing `name` clears the assignment.
+/// Returns `false` when a track assignment targets a file outside the library.
+#[tauri::command]
+fn assign_dsp_profile(
+    state: tauri::State<'_, DbManager>,
+    scope: String,
+    key: String,
+    name: Option<String>,
+) -> AppResult<bool> {
+    let scope = DspProfileScope::parse(&scope).map_err(AppError::db)?;
+    state
+        .assign_dsp_profile(scope, &key, name.as_deref())
+        .map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_dsp_profile_assignments(
+    state: tauri::State<'_, DbManager>,
+) -> AppResult<Vec<DspProfileAssignmentRow>> {
+    state.get_dsp_profile_assignments().map_err(AppError::db)
+}
+
+#[tauri::command]
+fn fast_search(
+    state: tauri::State<'_, DbManager>,
+    query: String,
+) -> AppResult<SearchResults> {
+    state.fast_search(&query).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn toggle_shuffle(
+    state: tauri::State<'_, Mutex<PlaybackQueue>>,
+    enabled: bool,
+) -> AppResult<()> {
+    let mut queue = state
+        .lock()
+        .map_err(|e| AppError::dsp(format!("Queue lock error: {e}")))?;
+    queue.toggle_shuffle(enabled);
+    Ok(())
+}
+
+/// Mirrors the frontend's play queue so media keys can skip tracks without the webview.
+/// The shuffle setting is kept.
+#[tauri::command]
+fn set_queue(
+    state: tauri::State<'_, Mutex<PlaybackQueue>>,
+    tracks: Vec<String>,
+    current_index: usize,
+) -> AppResult<()> {
+    let mut queue = state
+        .lock()
+        .map_err(|e| AppError::dsp(format!("Queue lock error: {e}")))?;
+    let shuffle = queue.is_shuffle_enabled();
+    queue.set_tracks(tracks);
+    queue.jump_to(current_index);
+    queue.toggle_shuffle(shuffle);
+    Ok(())
+}
+
+// ── Playlist IPC commands ──────────────────────────────────────────────
+
+#[tauri::command]
+fn get_playlists(db: tauri::State<'_, DbManager>) -> AppResult<Vec<PlaylistRow>> {
+    db.get_playlists().map_err(AppError::db)
+}
+
+#[tauri::command]
+fn get_playlist_tracks(db: tauri::State<'_, DbManager>, id: i64) -> AppResult<Vec<String>> {
+    db.get_playlist_tracks(id).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn create_playlist(db: tauri::State<'_, DbManager>, name: String) -> AppResult<i64> {
+    db.create_playlist(&name).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn rename_playlist(db: tauri::State<'_, DbManager>, id: i64, name: String) -> AppResult<()> {
+    db.rename_playlist(id, &name).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn delete_playlist(db: tauri::State<'_, DbManager>, id: i64) -> AppResult<()> {
+    db.delete_playlist(id).map_err(AppError::db)
+}
+
+/// Inserts `paths` before entry `position`, or appends them. Returns the playlist.
+#[tauri::command]
+fn add_playlist_tracks(
+    db: tauri::State<'_, DbManager>,
+    id: i64,
+    paths: Vec<String>,
+    position: Option<usize>,
+) -> AppResult<Vec<String>> {
+    library::playlists::add_tracks(&db, id, paths, position).map_err(AppError::db)
+}
+
+/// Removes entries by index, so one copy of a repeated track can go.
+#[tauri::command]
+fn remove_playlist_tracks(
+    db: tauri::State<'_, DbManager>,
+    id: i64,
+    indices: Vec<usize>,
+) -> AppResult<Vec<String>> {
+    library::playlists::remove_tracks(&db, id, &indices).map_err(AppError::db)
+}
+
+#[tauri::command]
+fn move_playlist_track(
+    db: tauri::State<'_, DbManager>,
+    id: i64,
+    from: usize,
+    to: usize,
+) -> AppResult<Vec<String>> {
+    library::playlists::move_track(&db, id, from, to).map_err(AppError::db)
+}
+
+/// Replaces the play queue with the playlist, starting at `start_index`, and returns
+/// the queued paths for the frontend to play.
+#[tauri::command]
+fn load_playlist_into_queue(
+    db: tauri::State<'_, DbManager>,
+    queue: tauri::State<'_, Mutex<PlaybackQueue>>,
+    id: i64,
+    start_index: Option<usize>,
+) -> AppResult<Vec<String>> {
+    let mut queue = queue
+        .lock()
+        .map_err(|e| AppError::dsp(format!("Queue lock error: {e}")))?;
+    library::playlists::load_into_queue(&db, &mut queue, id, start_index.unwrap_or(0))
+        .map_err(AppError::db)
+}
+
+/// Imports an M3U, M3U8 or PLS file as a new playlist, named after the file unless
+/// `name` is given. Entries found neither on disk nor in the library are reported in
+/// `missing`.
+#[tauri::command]
+async fn import_playlist(
+    app: tauri::AppHandle,
+    path: String,
+    name: Option<String>,
+) -> AppResult<PlaylistImport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        library::playlist_files::import_playlist(&db, Path::new(&path), name.as_deref())
+            .map_err(AppError::fs)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking playlist import task failed: {err}")))?
+}
+
+#[tauri::command]
+async fn export_playlist(app: tauri::AppHandle, id: i64, path: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        let tracks = db.get_playlist_tracks(id).map_err(AppError::db)?;
+        library::playlist_files::export_m3u8(&db, &tracks, Path::new(&path)).map_err(AppError::fs)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking playlist export task failed: {err}")))?
+}
+
+/// Writes the play queue, in its current (possibly shuffled) order, as M3U8.
+#[tauri::command]
+async fn export_queue(
+    app: tauri::AppHandle,
+    queue: tauri::State<'_, Mutex<PlaybackQueue>>,
+    path: String,
+) -> AppResult<()> {
+    let tracks = queue
+        .lock()
+        .map_err(|e| AppError::dsp(format!("Queue lock error: {e}")))?
+        .active_order()
+        .to_vec();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        library::playlist_files::export_m3u8(&db, &tracks, Path::new(&path)).map_err(AppError::fs)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking queue export task failed: {err}")))?
+}
+
+/// Writes all library tracks with their tags, ratings, play counts and playlists to
+/// `path` (`format` is `json` or `csv`), for backups and other tools.
+#[tauri::command]
+async fn export_library(
+    app: tauri::AppHandle,
+    path: String,
+    format: LibraryExportFormat,
+) -> AppResult<ExportReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        library::export::export_library(&db, Path::new(&path), format).map_err(AppError::fs)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking library export task failed: {err}")))?
+}
+
+/// Imports play counts, ratings and playlists from an iTunes or MusicBee `Library.xml`,
+/// scanning the files it lists that are not in the library yet.
+#[tauri::command]
+async fn import_itunes_library(app: tauri::AppHandle, path: String) -> AppResult<LibraryImport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbManager>();
+        library::itunes::import_itunes_library(&db, Path::new(&path)).map_err(AppError::fs)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking library import task failed: {err}")))?
+}
+
+/// Most recent plays first; 100 when no limit is given.
+#[tauri::command]
+fn get_play_history(
+    db: tauri::State<'_, DbManager>,
+    limit: Option<usize>,
+) -> AppResult<Vec<PlayHistoryRow>> {
+    db.get_play_history(limit.unwrap_or(100))
+        .map_err(AppError::db)
+}
+
+/// Deletes the whole play history; returns how many plays were removed.
+#[tauri::command]
+fn clear_play_history(db: tauri::State<'_, DbManager>) -> AppResult<usize> {
+    db.clear_play_history().map_err(AppError::db)
+}
+
+// ── Spatial Audio IPC commands ─────────────────────────────────────────
+
+#[derive(Serialize)]
+struct SpatialSourceData {
+    index: usize,
+    name: String,
+    x: f32,
+    y: f32,
+    z: f32,
+    is_active: bool,
+    gain_db: f32,
+    muted: bool,
+    solo: bool,
+}
+
+#[tauri::command]
+fn toggle_spatial_mode(
+    state: tauri::State<'_, AudioState>,
+    enabled: bool,
+) -> AppResult<()> {
+    state.set_spatial_enabled(enabled).map_err(AppError::dsp)
+}
+
+#[derive(Serialize)]
+struct SpatialOutputData {
+    mode: SpatialOutputMode,
+    /// False while speaker mode waits for a 5.1 or 7.1 output and renders binaurally.
+    rendering_speakers: bool,
+}
+
+fn spatial_output_data(state: &AudioState) -> AppResult<SpatialOutputData> {
+    let (mode, rendering_speakers) = state.spatial_output_mode().map_err(AppError::dsp)?;
+    Ok(SpatialOutputData {
+        mode,
+        rendering_speakers,
+    })
+}
+
+#[tauri::command]
+fn set_spatial_output_mode(
+    state: tauri::State<'_, AudioState>,
+    mode: SpatialOutputMode,
+) -> AppResult<SpatialOutputData> {
+    state.set_spatial_output_mode(mode).map_err(AppError::dsp)?;
+    spatial_output_data(&state)
+}
+
+#[tauri::command]
+fn get_spatial_output_mode(state: tauri::State<'_, AudioState>) -> AppResult<SpatialOutputData> {
+    spatial_output_data(&state)
+}
+
+#[tauri::command]
+fn update_source_position(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> AppResult<()> {
+    state
+        .set_spatial_source_position(source_id, x, y, z)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_room_properties(
+    state: tauri::State<'_, AudioState>,
+    width: f32,
+    length: f32,
+    height: f32,
+    damping: f32,
+) -> AppResult<()> {
+    state
+        .set_spatial_room_size(width, length, height)
+        .map_err(AppError::dsp)?;
+    state.set_spatial_damping(damping).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_spatial_sources(
+    state: tauri::State<'_, AudioState>,
+) -> AppResult<Vec<SpatialSourceData>> {
+    let positions = state.get_spatial_source_positions().map_err(AppError::dsp)?;
+    let names = state.spatial_source_names().map_err(AppError::dsp)?;
+    let mix = state.spatial_source_mix().map_err(AppError::dsp)?;
+    Ok(positions
+        .into_iter()
+        .zip(names)
+        .zip(mix)
+        .enumerate()
+        .map(|(i, (((x, y, z, active), name), mix))| SpatialSourceData {
+            index: i,
+            name,
+            x,
+            y,
+            z,
+            is_active: active,
+            gain_db: mix.gain_db,
+            muted: mix.muted,
+            solo: mix.solo,
+        })
+        .collect())
+}
+
+/// Sets a stem source's level in the spatial mix (dB, -60 to +12).
+#[tauri::command]
+fn set_stem_gain(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+    gain_db: f32,
+) -> AppResult<()> {
+    state
+        .update_spatial_source_mix(source_id, |mix| mix.gain_db = gain_db)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn set_stem_mute(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+    muted: bool,
+) -> AppResult<()> {
+    state
+        .update_spatial_source_mix(source_id, |mix| mix.muted = muted)
+        .map_err(AppError::dsp)
+}
+
+/// While any stem is soloed, only soloed stems are heard.
+#[tauri::command]
+fn set_stem_solo(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+    solo: bool,
+) -> AppResult<()> {
+    state
+        .update_spatial_source_mix(source_id, |mix| mix.solo = solo)
+        .map_err(AppError::dsp)
+}
+
+/// Changes one band of a stem source's own EQ (a low shelf, two peaks and a high
+/// shelf), like `update_eq_band` for the master EQ.
+#[tauri::command]
+fn update_stem_eq_band(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+    index: usize,
+    freq: f32,
+    gain: f32,
+    q: f32,
+) -> AppResult<()> {
+    state
+        .update_spatial_source_eq_band(source_id, index, freq, gain, q)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_stem_eq_bands(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+) -> AppResult<Vec<EqBandData>> {
+    let bands = state.spatial_source_eq(source_id).map_err(AppError::dsp)?;
+    Ok(bands
+        .into_iter()
+        .enumerate()
+        .map(|(i, band)| EqBandData {
+            index: i,
+            frequency: band.frequency,
+            gain_db: band.gain_db,
+            q_factor: band.q_factor,
+            filter_type: band.filter_type,
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn reset_stem_eq(state: tauri::State<'_, AudioState>, source_id: usize) -> AppResult<()> {
+    state
+        .reset_spatial_source_eq(source_id)
+        .map_err(AppError::dsp)
+}
+
+/// Adds a named source (an extra stem or an auxiliary input) to the spatial room at a
+/// position in metres; returns its index.
+#[tauri::command]
+fn add_spatial_source(
+    state: tauri::State<'_, AudioState>,
+    name: String,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> AppResult<usize> {
+    state
+        .add_spatial_source(&name, x, y, z)
+        .map_err(AppError::dsp)
+}
+
+/// Removes a source by name; the sources listed after it move down one index.
+#[tauri::command]
+fn remove_spatial_source(state: tauri::State<'_, AudioState>, name: String) -> AppResult<()> {
+    state.remove_spatial_source(&name).map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn auto_orchestra(state: tauri::State<'_, AudioState>) -> AppResult<()> {
+    state.spatial_auto_orchestra().map_err(AppError::dsp)
+}
+
+/// Turns the spatial listener's head (degrees: yaw positive left, pitch positive up,
+/// roll positive right ear down) so the scene stays anchored to the room.
+#[tauri::command]
+fn set_listener_orientation(
+    state: tauri::State<'_, AudioState>,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+) -> AppResult<()> {
+    state
+        .set_spatial_listener_orientation(yaw, pitch, roll)
+        .map_err(AppError::dsp)
+}
+
+/// Moves the spatial listener inside the room (metres, clamped to the walls).
+#[tauri::command]
+fn set_listener_position(
+    state: tauri::State<'_, AudioState>,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> AppResult<()> {
+    state
+        .set_spatial_listener_position(x, y, z)
+        .map_err(AppError::dsp)
+}
+
+/// Turns the spatial listener to face a direction in the room (degrees, positive
+/// left). Head tracking turns the head relative to it.
+#[tauri::command]
+fn set_listener_yaw(state: tauri::State<'_, AudioState>, degrees: f32) -> AppResult<()> {
+    state
+        .set_spatial_listener_yaw(degrees)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_spatial_listener(state: tauri::State<'_, AudioState>) -> AppResult<SpatialListenerRow> {
+    let (x, y, z, yaw) = state.spatial_listener().map_err(AppError::dsp)?;
+    Ok(SpatialListenerRow { x, y, z, yaw })
+}
+
+/// Automates a spatial source with keyframes or an orbit; `motion` omitted stops it
+/// where it is. All motions restart together whenever one changes.
+#[tauri::command]
+fn set_source_motion(
+    state: tauri::State<'_, AudioState>,
+    source_id: usize,
+    motion: Option<SourceMotion>,
+) -> AppResult<()> {
+    state
+        .set_spatial_source_motion(source_id, motion)
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn clear_source_motions(state: tauri::State<'_, AudioState>) -> AppResult<()> {
+    let count = state
+        .get_spatial_source_positions()
+        .map_err(AppError::dsp)?
+        .len();
+    for index in 0..count {
+        state
+            .set_spatial_source_motion(index, None)
+            .map_err(AppError::dsp)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_source_motions(state: tauri::State<'_, AudioState>) -> AppResult<Vec<Option<SourceMotion>>> {
+    state.spatial_source_motions().map_err(AppError::dsp)
+}
+
+/// Starts the OSC head-tracking listener (`/…/ypr` messages); returns the bound port.
+#[tauri::command]
+fn start_head_tracking(
+    app: tauri::AppHandle,
+    tracker: tauri::State<'_, HeadTracker>,
+    port: Option<u16>,
+) -> AppResult<u16> {
+    tracker
+        .start(
+            port.unwrap_or(audio::head_tracking::DEFAULT_OSC_PORT),
+            move |orientation| {
+                if let Err(err) = app.state::<AudioState>().set_spatial_listener_orientation(
+                    orientation.yaw,
+                    orientation.pitch,
+                    orientation.roll,
+                ) {
+                    eprintln!("Failed to apply head orientation: {err}");
+                }
+            },
+        )
+        .map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn stop_head_tracking(tracker: tauri::State<'_, HeadTracker>) -> AppResult<()> {
+    tracker.stop();
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct HrtfData {
+    name: String,
+    sample_rate: f32,
+    measurements: usize,
+    ir_length: usize,
+}
+
+/// Loads a SOFA HRTF file for the spatial node, or clears it when `path` is `None`.
+#[tauri::command]
+async fn load_spatial_hrtf(
+    app: tauri::AppHandle,
+    path: Option<String>,
+) -> AppResult<Option<HrtfData>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let hrtf = match path {
+            Some(path) => Some(Arc::new(
+                audio::dsp::sofa::HrirSet::load(Path::new(&path)).map_err(AppError::dsp)?,
+            )),
+            None => None,
+        };
+        let data = hrtf.as_ref().map(|set| HrtfData {
+            name: set.name.clone(),
+            sample_rate: set.sample_rate,
+            measurements: set.measurements(),
+            ir_length: set.ir_length(),
+        });
+        app.state::<AudioState>()
+            .set_spatial_hrtf(hrtf)
+            .map_err(AppError::dsp)?;
+        Ok(data)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking HRTF load task failed: {err}")))?
+}
+
+// ── Spatial Scene Persistence IPC ──────────────────────────────────────
+
+#[tauri::command]
+fn save_spatial_scene(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    track_id: String,
+) -> AppResult<()> {
+    store_spatial_scene(&audio, &db, &track_id)
+}
+
+/// Saves the sources, listener and motions currently in the spatial node for a track,
+/// replacing what was saved before so removed sources are forgotten.
+fn store_spatial_scene(audio: &AudioState, db: &DbManager, track_id: &str) -> AppResult<()> {
+    let positions = audio.get_spatial_source_positions().map_err(AppError::dsp)?;
+    let names = audio.spatial_source_names().map_err(AppError::dsp)?;
+    db.delete_spatial_scene(track_id).map_err(AppError::db)?;
+    let mix = audio.spatial_source_mix().map_err(AppError::dsp)?;
+    for ((name, (x, y, z, active)), mix) in names.iter().zip(&positions).zip(&mix) {
+        db.save_spatial_scene(track_id, name, *x, *y, *z, *active)
+            .map_err(AppError::db)?;
+        db.save_spatial_source_mix(track_id, name, mix)
+            .map_err(AppError::db)?;
+    }
+    let (x, y, z, yaw) = audio.spatial_listener().map_err(AppError::dsp)?;
+    db.save_spatial_listener(track_id, &SpatialListenerRow { x, y, z, yaw })
+        .map_err(AppError::db)?;
+    let motions = audio.spatial_source_motions().map_err(AppError::dsp)?;
+    let motions = names
+        .iter()
+        .zip(&motions)
+        .filter_map(|(name, motion)| motion.as_ref().map(|motion| (name.as_str(), motion)))
+        .collect::<Vec<_>>();
+    db.save_spatial_motions(track_id, &motions)
+        .map_err(AppError::db)
+}
+
+#[tauri::command]
+fn load_spatial_scene(
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    track_id: String,
+) -> AppResult<Vec<SpatialSceneRow>> {
+    let rows = db.load_spatial_scene(&track_id).map_err(AppError::db)?;
+    // A saved scene brings back exactly its sources, including added ones.
+    if !rows.is_empty() {
+        for name in audio.spatial_source_names().map_err(AppError::dsp)? {
+            if !rows.iter().any(|row| row.source_name == name) {
+                audio.remove_spatial_source(&name).map_err(AppError::dsp)?;
+            }
+        }
+    }
+    for row in &rows {
+        let names = audio.spatial_source_names().map_err(AppError::dsp)?;
+        let idx = match names.iter().position(|name| *name == row.source_name) {
+            Some(idx) => idx,
+            None => audio
+                .add_spatial_source(&row.source_name, row.x, row.y, row.z)
+                .map_err(AppError::dsp)?,
+        };
+        audio
+            .set_spatial_source_position(idx, row.x, row.y, row.z)
+            .map_err(AppError::dsp)?;
+        audio
+            .set_spatial_source_active(idx, row.is_active)
+            .map_err(AppError::dsp)?;
+    }
+    if let Some(listener) = db.load_spatial_listener(&track_id).map_err(AppError::db)? {
+        audio
+            .set_spatial_listener_position(listener.x, listener.y, listener.z)
+            .map_err(AppError::dsp)?;
+        audio
+            .set_spatial_listener_yaw(listener.yaw)
+            .map_err(AppError::dsp)?;
+    }
+    // Sources without a saved motion stand still at their saved positions, and those
+    // without a saved mix play at unity.
+    let motions = db.load_spatial_motions(&track_id).map_err(AppError::db)?;
+    let mixes = db
+        .load_spatial_source_mix(&track_id)
+        .map_err(AppError::db)?;
+    let names = audio.spatial_source_names().map_err(AppError::dsp)?;
+    for (idx, name) in names.iter().enumerate() {
+        let motion = motions
+            .iter()
+            .find(|(source_name, _)| source_name == name)
+            .map(|(_, motion)| motion.clone());
+        audio
+            .set_spatial_source_motion(idx, motion)
+            .map_err(AppError::dsp)?;
+        let saved = mixes
+            .iter()
+            .find(|(source_name, _)| source_name == name)
+            .map(|(_, mix)| *mix)
+            .unwrap_or_default();
+        audio
+            .update_spatial_source_mix(idx, |mix| *mix = saved)
+            .map_err(AppError::dsp)?;
+    }
+    Ok(rows)
+}
+
+/// Writes the current spatial scene (room, listener, sources and their motions) to
+/// `path` as JSON, to share a track's scene once it has been loaded.
+#[tauri::command]
+async fn export_spatial_scene(app: tauri::AppHandle, path: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let scene = app
+            .state::<AudioState>()
+            .spatial_scene()
+            .map_err(AppError::dsp)?;
+        let json = serde_json::to_string_pretty(&scene)
+            .map_err(|err| AppError::fs(format!("Failed to serialize spatial scene: {err}")))?;
+        std::fs::write(&path, json)
+            .map_err(|err| AppError::fs(format!("Failed to write {path}: {err}")))
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking spatial scene export task failed: {err}")))?
+}
+
+/// Reads a scene file written by `export_spatial_scene` and applies it. With a
+/// `track_id` the scene is also saved for that track, as by `save_spatial_scene`.
+#[tauri::command]
+async fn import_spatial_scene(
+    app: tauri::AppHandle,
+    path: String,
+    track_id: Option<String>,
+) -> AppResult<SpatialScene> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = app.state::<AudioState>();
+        let json = std::fs::read_to_string(&path)
+            .map_err(|err| AppError::fs(format!("Failed to read {path}: {err}")))?;
+        let scene: SpatialScene = serde_json::from_str(&json)
+            .map_err(|err| AppError::fs(format!("{path} is not a spatial scene: {err}")))?;
+        audio.apply_spatial_scene(&scene).map_err(AppError::dsp)?;
+        if let Some(track_id) = track_id {
+            store_spatial_scene(&audio, &app.state::<DbManager>(), &track_id)?;
+        }
+        audio.spatial_scene().map_err(AppError::dsp)
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking spatial scene import task failed: {err}")))?
+}
+
+// ── Stem Separation IPC ────────────────────────────────────────────────
+
+/// Stem files of an analysis; `drums`, `bass` and `other` are set for four or six
+/// stems, `piano` and `guitar` for six, `instrumental` only for two.
+#[derive(Serialize)]
+struct StemPathsData {
+    mode: SeparationMode,
+    vocals: String,
+    drums: Option<String>,
+    bass: Option<String>,
+    other: Option<String>,
+    piano: Option<String>,
+    guitar: Option<String>,
+    instrumental: Option<String>,
+}
+
+/// Separates `track_id` into four stems, with `mode` `six_stems` also into piano and
+/// guitar (needs the six-stem model), or with `two_stems` into vocals and instrumental
+/// only, which is faster and enough for karaoke. `mode` is the one the stems came
+/// from: six stems fall back to four without their model.
+#[tauri::command]
+async fn analyze_spatial_stems(
+    app: tauri::AppHandle,
+    track_id: String,
+    mode: Option<SeparationMode>,
+) -> AppResult<StemPathsData> {
+    let mode = mode.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let token = StemAnalysisToken::register(&track_id);
+        let text = |path: PathBuf| path.to_string_lossy().to_string();
+        let data = {
+            let stem_sep = app.state::<Mutex<StemSeparator>>();
+            let separator = stem_sep
+                .lock()
+                .map_err(|e| AppError::dsp(format!("Stem separator lock error: {e}")))?;
+            let progress = |progress| {
+                let _ = app.emit("stems-progress", &progress);
+            };
+            match mode {
+                SeparationMode::Four | SeparationMode::Six => {
+                    let paths = if mode == SeparationMode::Six {
+                        separator.analyze_six_stems(&track_id, &token, progress)
+                    } else {
+                        separator.analyze_spatial_stems(&track_id, &token, progress)
+                    }
+                    .map_err(AppError::dsp)?;
+                    StemPathsData {
+                        mode: if paths.piano.is_some() {
+                            SeparationMode::Six
+                        } else {
+                            SeparationMode::Four
+                        },
+                        vocals: text(paths.vocals),
+                        drums: Some(text(paths.drums)),
+                        bass: Some(text(paths.bass)),
+                        other: Some(text(paths.other)),
+                        piano: paths.piano.map(text),
+                        guitar: paths.guitar.map(text),
+                        instrumental: None,
+                    }
+                }
+                SeparationMode::Two => {
+                    let paths = separator
+                        .analyze_vocal_split(&track_id, &token, progress)
+                        .map_err(AppError::dsp)?;
+                    StemPathsData {
+                        mode,
+                        vocals: text(paths.vocals),
+                        drums: None,
+                        bass: None,
+                        other: None,
+                        piano: None,
+                        guitar: None,
+                        instrumental: Some(text(paths.instrumental)),
+                    }
+                }
+            }
+        };
+        let state = app.state::<AudioState>();
+        let loaded = state
+            .loaded_path()
+            .is_some_and(|path| path == Path::new(&track_id));
+        if loaded && mode != SeparationMode::Two && state.stem_playback() {
+            attach_stem_sources(&app, &state).map_err(AppError::dsp)?;
+        }
+        if loaded && state.karaoke().enabled {
+            attach_karaoke_vocals(&app, &state).map_err(AppError::dsp)?;
+        }
+        Ok(data)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking stem analysis task failed: {err}")))?
+}
+
+/// Converts the cached stems of `track_id` into `dest_dir` as tagged FLAC, MP3 or OGG
+/// files (`format` is `flac`, `mp3` or `ogg`) and returns their paths.
+#[tauri::command]
+async fn export_stems(
+    app: tauri::AppHandle,
+    track_id: String,
+    dest_dir: String,
+    format: StemExportFormat,
+) -> AppResult<Vec<String>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let cached = app
+            .state::<Mutex<StemSeparator>>()
+            .lock()
+            .map_err(|e| AppError::dsp(format!("Stem separator lock error: {e}")))?
+            .cached_paths(&track_id);
+        let stems =
+            cached.ok_or_else(|| AppError::fs(format!("{track_id} has no separated stems")))?;
+        let written =
+            library::stem_export::export_stems(&stems, &track_id, Path::new(&dest_dir), format)
+                .map_err(AppError::fs)?;
+        Ok(written
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking stem export task failed: {err}")))?
+}
+
+/// Stops the stem analysis of `track_id`, running or waiting for another to finish. The
+/// analysis fails with a cancelled error and leaves nothing in the stem cache. Returns
+/// whether there was one.
+#[tauri::command]
+fn cancel_stem_analysis(queue: tauri::State<'_, StemQueue>, track_id: String) -> AppResult<bool> {
+    let queued = queue.remove(&track_id).map_err(AppError::dsp)?;
+    let running = library::stems::cancel_analysis(&track_id);
+    Ok(queued || running)
+}
+
+/// Queues tracks (typically a playlist or album) for stem separation in the
+/// background, skipping tracks already queued or cached. Every track reports its
+/// progress as `stem-queue-progress`.
+#[tauri::command]
+fn queue_stem_analysis(
+    queue: tauri::State<'_, StemQueue>,
+    paths: Vec<String>,
+) -> AppResult<StemQueueStatus> {
+    queue.enqueue(paths).map_err(AppError::dsp)?;
+    queue.status().map_err(AppError::dsp)
+}
+
+/// Stops the background queue from starting tracks; running ones finish.
+#[tauri::command]
+fn pause_stem_queue(queue: tauri::State<'_, StemQueue>) -> AppResult<StemQueueStatus> {
+    queue.pause().map_err(AppError::dsp)?;
+    queue.status().map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn resume_stem_queue(queue: tauri::State<'_, StemQueue>) -> AppResult<StemQueueStatus> {
+    queue.resume().map_err(AppError::dsp)?;
+    queue.status().map_err(AppError::dsp)
+}
+
+/// How many queued tracks are separated at once (1 to 4).
+#[tauri::command]
+fn set_stem_queue_concurrency(
+    queue: tauri::State<'_, StemQueue>,
+    limit: usize,
+) -> AppResult<StemQueueStatus> {
+    queue.set_concurrency(limit).map_err(AppError::dsp)?;
+    queue.status().map_err(AppError::dsp)
+}
+
+#[tauri::command]
+fn get_stem_queue_status(queue: tauri::State<'_, StemQueue>) -> AppResult<StemQueueStatus> {
+    queue.status().map_err(AppError::dsp)
+}
+
+/// Forwards a background separation event, and plays the stems straight away when the
+/// track that finished is loaded with stem playback on.
+fn handle_stem_job_event(app: &tauri::AppHandle, event: StemJobEvent) {
+    if event.status == StemJobStatus::Done {
+        let state = app.state::<AudioState>();
+        let loaded = state
+            .loaded_path()
+            .is_some_and(|path| path == Path::new(&event.track_id));
+        if loaded && state.stem_playback() {
+            if let Err(err) = attach_stem_sources(app, &state) {
+                eprintln!("Failed to load the stems of {}: {err}", event.track_id);
+            }
+        }
+    }
+    let _ = app.emit("stem-queue-progress", &event);
+}
+
+#[derive(Serialize)]
+struct StemModelData {
+    /// Whether the four-stem separation model is in the stem cache.
+    installed: bool,
+    /// Whether the smaller vocals/instrumental model is.
+    two_stem_installed: bool,
+    /// Whether the six-stem model (adding piano and guitar) is.
+    six_stem_installed: bool,
+    /// Whether the ONNX Runtime library could be loaded; without it (or the model)
+    /// stems are split by centre cancellation.
+    runtime_available: bool,
+}
+
+fn stem_model_status() -> StemModelData {
+    StemModelData {
+        installed: library::stems::default_model_path(SeparationMode::Four).exists(),
+        two_stem_installed: library::stems::default_model_path(SeparationMode::Two).exists(),
+        six_stem_installed: library::stems::default_model_path(SeparationMode::Six).exists(),
+        runtime_available: library::stems::onnx_runtime_available(),
+    }
+}
+
+#[tauri::command]
+fn get_stem_model_status() -> AppResult<StemModelData> {
+    Ok(stem_model_status())
+}
+
+/// Downloads the ONNX separation model of `mode` (four stems unless given) from `url`,
+/// emitting `stem-model-progress`. Stems already cached stay as they are; later
+/// analyses use the model.
+#[tauri::command]
+async fn download_stem_model(
+    app: tauri::AppHandle,
+    url: String,
+    mode: Option<SeparationMode>,
+) -> AppResult<StemModelData> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let target = library::stems::default_model_path(mode.unwrap_or_default());
+        library::stems::download_model(&url, &target, |progress| {
+            let _ = app.emit("stem-model-progress", &progress);
+        })
+        .map_err(AppError::fs)?;
+        Ok(stem_model_status())
+    })
+    .await
+    .map_err(|err| AppError::fs(format!("Blocking stem model download failed: {err}")))?
+}
+
+#[derive(Clone, Serialize)]
+struct KaraokeData {
+    enabled: bool,
+    vocal_level: f32,
+    /// Whether the vocal stem is subtracted rather than the centre cancelled.
+    uses_stems: bool,
+}
+
+/// Gives karaoke mode the cached vocal stem of the loaded track, from a four- or
+/// two-stem split, if it was separated. Returns whether a stem is in use.
+fn attach_karaoke_vocals(app: &tauri::AppHandle, state: &AudioState) -> Result<bool, String> {
+    let cached = state.loaded_path().and_then(|path| {
+        app.state::<Mutex<StemSeparator>>()
+            .lock()
+            .ok()?
+            .cached_vocals(&path.to_string_lossy())
+    });
+    let vocals = cached
+        .map(|vocals| audio::decoder::decode_file(&vocals))
+        .transpose()?;
+    let uses_stems = vocals.is_some();
+    state.set_karaoke_vocals(vocals)?;
+    Ok(uses_stems)
+}
+
+/// Turns karaoke mode on or off. `vocal_level` (0–1) is how much of the voice is left
+/// to sing along with. When stems were separated for the current track the vocal stem
+/// is subtracted from the mix; otherwise the centre of the stereo image is cancelled.
+/// Emits `karaoke-changed` so the lyrics view can switch to its karaoke display.
+#[tauri::command]
+async fn set_karaoke(
+    app: tauri::AppHandle,
+    enabled: bool,
+    vocal_level: f32,
+) -> AppResult<KaraokeData> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let uses_stems = if enabled {
+            attach_karaoke_vocals(&app, &state).map_err(AppError::dsp)?
+        } else {
+            state.set_karaoke_vocals(None).map_err(AppError::dsp)?;
+            false
+        };
+        state.set_karaoke(KaraokeSettings {
+            enabled,
+            vocal_level,
+        });
+        let data = KaraokeData {
+            enabled,
+            vocal_level: state.karaoke().vocal_level,
+            uses_stems,
+        };
+        let _ = app.emit("karaoke-changed", &data);
+        Ok(data)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking karaoke task failed: {err}")))?
+}
+
+#[tauri::command]
+fn get_karaoke(state: tauri::State<'_, AudioState>) -> AppResult<KaraokeData> {
+    let settings = state.karaoke();
+    Ok(KaraokeData {
+        enabled: settings.enabled,
+        vocal_level: settings.vocal_level,
+        uses_stems: settings.enabled && state.has_karaoke_vocals(),
+    })
+}
+
+#[derive(Clone, Serialize)]
+struct StemPlaybackData {
+    enabled: bool,
+    /// Whether the current track's stems are playing; `false` while they have not been
+    /// separated yet.
+    playing_stems: bool,
+}
+
+/// Hands the cached stems of the loaded track to the engine, if it was separated.
+/// Returns whether stems are playing.
+fn attach_stem_sources(app: &tauri::AppHandle, state: &AudioState) -> Result<bool, String> {
+    let cached = state.loaded_path().and_then(|path| {
+        app.state::<Mutex<StemSeparator>>()
+            .lock()
+            .ok()?
+            .cached_paths(&path.to_string_lossy())
+    });
+    let stems = match cached {
+        Some(paths) => {
+            // `StemKind::all` is in the room's `SOURCE_NAMES` order.
+            let mut stems: [Option<audio::decoder::DecodedTrack>; 6] = Default::default();
+            for (stem, kind) in stems.iter_mut().zip(StemKind::all()) {
+                *stem = paths
+                    .get(*kind)
+                    .map(audio::decoder::decode_file)
+                    .transpose()?;
+            }
+            Some(stems)
+        }
+        None => None,
+    };
+    let playing = stems.is_some();
+    state.set_stem_sources(stems)?;
+    Ok(playing)
+}
+
+/// Plays each separated stem through its own spatial source instead of sending the
+/// whole mix to every source, for the current track and any later one whose stems are
+/// cached. Heard while spatial mode is on.
+#[tauri::command]
+async fn set_stem_playback(app: tauri::AppHandle, enabled: bool) -> AppResult<StemPlaybackData> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        state.set_stem_playback(enabled);
+        let playing_stems = if enabled {
+            attach_stem_sources(&app, &state).map_err(AppError::dsp)?
+        } else {
+            state.set_stem_sources(None).map_err(AppError::dsp)?;
+            false
+        };
+        Ok(StemPlaybackData {
+            enabled,
+            playing_stems,
+        })
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking stem playback task failed: {err}")))?
+}
+
+#[tauri::command]
+fn get_stem_playback(state: tauri::State<'_, AudioState>) -> AppResult<StemPlaybackData> {
+    Ok(StemPlaybackData {
+        enabled: state.stem_playback(),
+        playing_stems: state.has_stem_sources(),
+    })
+}
+
+// ── Album Experience IPC ───────────────────────────────────────────────
+
+/// Prepares art, palette, lyrics and waveform for every track of an album, in order,
+/// emitting one `album-experience-ready` event per track. Returns the session id;
+/// starting a new session cancels the previous one.
+#[tauri::command]
+fn start_album_experience(
+    app: tauri::AppHandle,
+    audio: tauri::State<'_, AudioState>,
+    db: tauri::State<'_, DbManager>,
+    paths: Vec<String>,
+    waveform_points: Option<usize>,
+) -> AppResult<u64> {
+    let handle = app.clone();
+    Ok(library::album_experience::start(
+        db.inner().clone(),
+        paths,
+        waveform_points.unwrap_or(library::album_experience::DEFAULT_WAVEFORM_POINTS),
+        audio.lyrics_parse_mode(),
+        move |track| {
+            let _ = handle.emit("album-experience-ready", &track);
+        },
+    ))
+}
+
+#[tauri::command]
+fn stop_album_experience() -> AppResult<()> {
+    library::album_experience::cancel();
+    Ok(())
+}
+
+// ── Internet Radio IPC ─────────────────────────────────────────────────
+
+/// Starts an HTTP/ICY radio stream in place of the current track. Now-playing updates
+/// from the stream arrive as `stream-metadata` events.
+#[tauri::command]
+async fn play_stream(app: tauri::AppHandle, url: String) -> AppResult<StreamInfo> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let db = app.state::<DbManager>();
+        if let Err(err) = remember_playback_position(&state, &db) {
+            eprintln!("Failed to save resume position: {err}");
+        }
+        let handle = app.clone();
+        let info = state
+            .load_stream(
+                url.trim(),
+                Box::new(move |metadata| {
+                    let _ = handle.emit("stream-metadata", &metadata);
+                }),
+            )
+            .map_err(AppError::dsp)?;
+        state
+            .start_beat_monitor(app.clone())
+            .map_err(AppError::dsp)?;
+        Ok(info)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking stream load task failed: {err}")))?
+}
+
+#[tauri::command]
+fn get_stations(db: tauri::State<'_, DbManager>) -> AppResult<Vec<StationRow>> {
+    db.get_stations().map_err(AppError::db)
+}
+
+#[tauri::command]
+fn save_station(
+    db: tauri::State<'_, DbManager>,
+    name: String,
+    url: String,
+    genre: Option<String>,
+) -> AppResult<i64> {
+    db.save_station(&name, &url, genre.as_deref())
+        .map_err(AppError::db)
+}
+
+#[tauri::command]
+fn delete_station(db: tauri::State<'_, DbManager>, id: i64) -> AppResult<()> {
+    db.delete_station(id).map_err(AppError::db)
+}
+
+// ── DLNA Output IPC ────────────────────────────────────────────────────
+
+/// Searches the LAN for DLNA/UPnP media renderers (takes a few seconds).
+#[tauri::command]
+async fn list_dlna_renderers(app: tauri::AppHandle) -> AppResult<Vec<DlnaRenderer>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<DlnaOutput>()
+            .refresh_renderers()
+            .map_err(AppError::dsp)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking renderer discovery task failed: {err}")))?
+}
+
+/// Makes a listed renderer the active output, moving the current track (and playback,
+/// if it was playing) over to it. `id` omitted switches back to the local device.
+#[tauri::command]
+async fn select_output_renderer(
+    app: tauri::AppHandle,
+    id: Option<String>,
+) -> AppResult<Option<DlnaRenderer>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let was_playing = state.is_playing();
+        if id.is_some() {
+            app.state::<CastOutput>().disconnect();
+            release_airplay(&app)?;
+        }
+        let renderer = app
+            .state::<DlnaOutput>()
+            .select(id.as_deref(), was_playing)
+            .map_err(AppError::dsp)?;
+        if renderer.is_some() {
+            state.pause();
+        }
+        Ok(renderer)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking renderer select task failed: {err}")))?
+}
+
+// ── Chromecast Output IPC ──────────────────────────────────────────────
+
+/// Searches the LAN for Chromecast and Google Cast speakers over mDNS (takes a few seconds).
+#[tauri::command]
+async fn list_cast_devices(app: tauri::AppHandle) -> AppResult<Vec<CastDevice>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<CastOutput>()
+            .refresh_devices()
+            .map_err(AppError::dsp)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking Cast discovery task failed: {err}")))?
+}
+
+/// Hands playback off to a listed Cast device at the current position. Status changes
+/// (including ones made on the device) arrive as `cast-status` events. `id` omitted
+/// disconnects and returns to the local device.
+#[tauri::command]
+async fn select_cast_device(
+    app: tauri::AppHandle,
+    id: Option<String>,
+) -> AppResult<Option<CastDevice>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let was_playing = state.is_playing();
+        if id.is_some() {
+            app.state::<DlnaOutput>()
+                .select(None, false)
+                .map_err(AppError::dsp)?;
+            release_airplay(&app)?;
+        }
+        let handle = app.clone();
+        let device = app
+            .state::<CastOutput>()
+            .select(
+                id.as_deref(),
+                was_playing,
+                state.position_seconds(),
+                Arc::new(move |status| {
+                    let _ = handle.emit("cast-status", &status);
+                }),
+            )
+            .map_err(AppError::dsp)?;
+        if device.is_some() {
+            state.pause();
+        }
+        Ok(device)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking Cast select task failed: {err}")))?
+}
+
+#[tauri::command]
+fn get_cast_status(cast: tauri::State<'_, CastOutput>) -> AppResult<Option<CastStatus>> {
+    Ok(cast.status())
+}
+
+// ── AirPlay Output IPC ─────────────────────────────────────────────────
+
+/// Searches the LAN for AirPlay speakers over mDNS (takes a few seconds).
+#[tauri::command]
+async fn list_airplay_devices(app: tauri::AppHandle) -> AppResult<Vec<AirPlayDevice>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<AirPlayOutput>()
+            .refresh_devices()
+            .map_err(AppError::dsp)
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking AirPlay discovery task failed: {err}")))?
+}
+
+/// Streams the engine's processed output to a listed AirPlay speaker, keeping the current
+/// position and play state. `id` omitted returns to the local device.
+#[tauri::command]
+async fn select_airplay_device(
+    app: tauri::AppHandle,
+    id: Option<String>,
+) -> AppResult<Option<AirPlayDevice>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(id) = id else {
+            release_airplay(&app)?;
+            return Ok(None);
+        };
+        app.state::<CastOutput>().disconnect();
+        app.state::<DlnaOutput>()
+            .select(None, false)
+            .map_err(AppError::dsp)?;
+        let (device, output) = app
+            .state::<AirPlayOutput>()
+            .connect(&id)
+            .map_err(AppError::dsp)?;
+        if let Err(err) = route_engine_output(&app, Some(output)) {
+            app.state::<AirPlayOutput>().disconnect();
+            return Err(err);
+        }
+        Ok(Some(device))
+    })
+    .await
+    .map_err(|err| AppError::dsp(format!("Blocking AirPlay select task failed: {err}")))?
+}
+
+/// Returns the engine to the local device and closes the AirPlay session, if one is open.
+fn release_airplay(app: &tauri::AppHandle) -> AppResult<()> {
+    let airplay = app.state::<AirPlayOutput>();
+    if airplay.active().is_none() {
+        return Ok(());
+    }
+    let routed = route_engine_output(app, None);
+    airplay.disconnect();
+    routed
+}
+
+/// Switches the engine's output; the loaded track restarts on it, so the lyric and beat
+/// monitors are restarted with it.
+fn route_engine_output(app: &tauri::AppHandle, output: Option<SharedOutput>) -> AppResult<()> {
+    let state = app.state::<AudioState>();
+    state.set_output(output).map_err(AppError::dsp)?;
+    if state.playback_supported() && state.loaded_path().is_some() {
+        state
+            .start_lyrics_monitor(app.clone())
+            .map_err(AppError::dsp)?;
+        state
+            .start_beat_monitor(app.clone())
+            .map_err(AppError::dsp)?;
+    }
+    Ok(())
+}
+
+// ── Output Device Changes ──────────────────────────────────────────────
+
+/// Payload of the `output-device-changed` event, with the DSP profiles applied for the
+/// new device.
+#[derive(Clone, Serialize)]
+struct OutputDeviceChangedEvent {
+    device: String,
+    dsp_profiles: Vec<String>,
+}
+
+/// Polls the output device once a second. A track follows the default device when it
+/// changes (headphones plugged in or removed), and whenever the device playback runs
+/// on changes, for that reason or a network sink being selected, the DSP profiles are
+/// resolved again so the device's profile takes over.
+fn start_output_device_monitor(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_device: Option<String> = None;
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            let state = app.state::<AudioState>();
+            match state.follow_default_output() {
+                Ok(true) => {
+                    if let Err(err) = state.start_lyrics_monitor(app.clone()) {
+                        eprintln!("Failed to restart the lyrics monitor: {err}");
+                    }
+                    if let Err(err) = state.start_beat_monitor(app.clone()) {
+                        eprintln!("Failed to restart the beat monitor: {err}");
+                    }
+                }
+                Ok(false) => {}
+                Err(err) => eprintln!("Failed to follow the default output device: {err}"),
+            }
+            let Some(device) = state.output_device() else {
+                continue;
+            };
+            // The first device is the one `load_track` already applied profiles for.
+            if last_device
+                .replace(device.clone())
+                .is_none_or(|last| last == device)
+            {
+                continue;
+            }
+            let db = app.state::<DbManager>();
+            let dsp_profiles = match state.loaded_path() {
+                Some(path) => apply_track_dsp_profiles(&state, &db, &path.to_string_lossy(), None)
+                    .unwrap_or_else(|err| {
+                        eprintln!("Failed to apply DSP profiles for {device}: {err}");
+                        Vec::new()
+                    }),
+                None => Vec::new(),
+            };
+            let _ = app.emit(
+                "output-device-changed",
+                OutputDeviceChangedEvent {
+                    device,
+                    dsp_profiles,
+                },
+            );
+        }
+    });
+}
+
+// ── Media Keys ─────────────────────────────────────────────────────────
+
+/// Payload of the `media-key` event, sent after a media key was handled so the UI can
+/// follow. `track` is set when the key loaded another track from the queue.
+#[derive(Clone, Serialize)]
+struct MediaKeyEvent {
+    key: MediaKey,
+    path: Option<String>,
+    track: Option<TrackData>,
+}
+
+/// Routes a global media key to the active output (local engine, Cast or DLNA) and,
+/// for next/previous, to the backend queue.
+fn handle_media_key(app: &tauri::AppHandle, key: MediaKey) {
+    let result = match key {
+        MediaKey::PlayPause => toggle_playback(app),
+        MediaKey::Stop => pause(app.state(), app.state(), app.state(), app.state())
+            .and_then(|()| seek(app.state(), app.state(), app.state(), 0.0)),
+        MediaKey::Next | MediaKey::Previous => return skip_in_queue(app, key),
+    };
+    match result {
+        Ok(()) => {
+            let _ = app.emit(
+                "media-key",
+                MediaKeyEvent {
+                    key,
+                    path: None,
+                    track: None,
+                },
+            );
+        }
+        Err(err) => eprintln!("Media key {key:?} failed: {err}"),
+    }
+}
+
+/// Playback position for the taskbar button, from the Cast device while one is active.
+fn taskbar_progress(app: &tauri::AppHandle) -> Option<taskbar::TaskbarProgress> {
+    let cast = app.state::<CastOutput>();
+    if cast.is_active() {
+        let status = cast.status()?;
+        let duration = status.duration_seconds.filter(|duration| *duration > 0.0)?;
+        return Some(taskbar::TaskbarProgress {
+            fraction: status.position_seconds / duration,
+            playing: status.player_state == "PLAYING",
+        });
+    }
+    let state = app.state::<AudioState>();
+    state.loaded_path()?;
+    let duration = state.get_track_duration_seconds() as f64;
+    Some(taskbar::TaskbarProgress {
+        fraction: if duration > 0.0 {
+            state.position_seconds() / duration
+        } else {
+            0.0
+        },
+        playing: state.is_playing(),
+    })
+}
+
+fn toggle_playback(app: &tauri::AppHandle) -> AppResult<()> {
+    let cast = app.state::<CastOutput>();
+    let dlna = app.state::<DlnaOutput>();
+    let playing = if cast.is_active() {
+        cast.status()
+            .is_some_and(|status| matches!(status.player_state.as_str(), "PLAYING" | "BUFFERING"))
+    } else if dlna.active().is_some() {
+        dlna.is_playing()
+    } else {
+        app.state::<AudioState>().is_playing()
+    };
+    if playing {
+        pause(app.state(), app.state(), cast, dlna)
+    } else {
+        play(app.state(), cast, dlna)
+    }
+}
+
+/// Loads and plays the neighbouring queue track. With an empty queue, or at either end
+/// of it, the key is only forwarded to the webview.
+fn skip_in_queue(app: &tauri::AppHandle, key: MediaKey) {
+    let path = match app.state::<Mutex<PlaybackQueue>>().lock() {
+        Ok(mut queue) => {
+            let before = queue.current_index();
+            let path = if key == MediaKey::Next {
+                queue.next()
+            } else {
+                queue.previous()
+            }
+            .map(str::to_string);
+            path.filter(|_| queue.current_index() != before)
+        }
+        Err(err) => {
+            eprintln!("Queue lock error: {err}");
+            None
+        }
+    };
+    let Some(path) = path else {
+        let _ = app.emit(
+            "media-key",
+            MediaKeyEvent {
+                key,
+                path: None,
+                track: None,
+            },
+        );
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let track = match load_track(app.clone(), path.clone()).await {
+            Ok(track) => track,
+            Err(err) => {
+                eprintln!("Media key {key:?} could not load {path}: {err}");
+                return;
+            }
+        };
+        if let Err(err) = play(app.state(), app.state(), app.state()) {
+            eprintln!("Media key {key:?} could not start {path}: {err}");
+        }
+        let _ = app.emit(
+            "media-key",
+            MediaKeyEvent {
+                key,
+                path: Some(path),
+                track: Some(track),
+            },
+        );
+    });
+}
+
+/// Watches the library roots of earlier sessions again and rescans them, as changes
+/// made while the app was closed were never seen; unchanged files are skipped.
+fn restore_library_roots(app: &tauri::AppHandle) {
+    let db = app.state::<DbManager>();
+    let roots = match db.get_library_roots() {
+        Ok(roots) => roots,
+        Err(err) => {
+            eprintln!("Failed to load library roots: {err}");
+            return;
+        }
+    };
+    for root in roots {
+        let path = Path::new(&root.path);
+        if let Err(err) = library::scanner::restore_library_watch(path, &db) {
+            eprintln!("Failed to watch library root {}: {err}", root.path);
+        }
+        if !path.is_dir() {
+            continue;
+        }
+        let scanned = library::scanner::scan_library_path(path, &db, |progress| {
+            let _ = app.emit("scan-progress", &progress);
+        });
+        if let Err(err) = scanned {
+            eprintln!("Failed to rescan library root {}: {err}", root.path);
+        }
+    }
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let db = DbManager::new("powerplayer.db").expect("failed to initialize SQLite manager");
+    db.initialize_fts().expect("failed to initialize FTS5 search");
+    db.initialize_spatial_schema().expect("failed to initialize spatial schema");
+    db.initialize_dsp_profile_schema()
+        .expect("failed to initialize DSP profile schema");
+    db.initialize_station_schema()
+        .expect("failed to initialize station schema");
+    db.initialize_eq_preset_schema()
+        .expect("failed to initialize EQ preset schema");
+    db.initialize_chain_preset_schema()
+        .expect("failed to initialize chain preset schema");
+    db.initialize_plugin_schema()
+        .expect("failed to initialize plugin schema");
+    db.initialize_playlist_schema()
+        .expect("failed to initialize playlist schema");
+    db.initialize_history_schema()
+        .expect("failed to initialize play history schema");
+    db.initialize_library_root_schema()
+        .expect("failed to initialize library root schema");
+    if let Err(err) = db.merge_duplicate_track_paths() {
+        eprintln!("Failed to merge duplicate track paths: {err}");
+    }
+    if let Err(err) = library::extensions::load_from_settings(&db) {
+        eprintln!("Failed to load audio extensions: {err}");
+    }
+    if let Err(err) = library::exclusions::load_from_settings(&db) {
+        eprintln!("Failed to load scan exclusions: {err}");
+    }
+
+    let audio = AudioState::new();
+    match db.night_mode_enabled() {
+        Ok(enabled) => {
+            if let Err(err) = audio.set_night_mode(enabled) {
+                eprintln!("Failed to restore night mode: {err}");
+            }
+        }
+        Err(err) => eprintln!("Failed to read night mode setting: {err}"),
+    }
+    match db.upmix_settings() {
+        Ok(Some(settings)) => {
+            if let Err(err) = audio.set_upmix(settings) {
+                eprintln!("Failed to restore upmix settings: {err}");
+            }
+        }
+        Ok(None) => {}
+        Err(err) => eprintln!("Failed to read upmix settings: {err}"),
+    }
+    match db.bass_management_settings() {
+        Ok(Some(settings)) => {
+            if let Err(err) = audio.set_bass_management(settings) {
+                eprintln!("Failed to restore bass management settings: {err}");
+            }
+        }
+        Ok(None) => {}
+        Err(err) => eprintln!("Failed to read bass management settings: {err}"),
+    }
+
+    let stems_cache = library::stems::default_cache_dir();
+    let stem_queue_cache = stems_cache.clone();
+    library::network_usage::attach(db.clone());
+    let heatmap_db = db.clone();
+    let history_db = db.clone();
+
+    tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            if let Err(err) = media_keys::register(app.handle(), handle_media_key) {
+                eprintln!("Global media keys are unavailable: {err}");
+            }
+            if let Some(window) = app.get_webview_window("main") {
+                #[cfg(target_os = "windows")]
+                {
+                    let handle = app.handle().clone();
+                    // Clicks arrive on the UI thread; Cast/DLNA commands block on the network.
+                    let on_click = move |key| {
+                        let handle = handle.clone();
+                        std::thread::spawn(move || handle_media_key(&handle, key));
+                    };
+                    if let Err(err) = taskbar::add_thumbnail_buttons(&window, on_click) {
+                        eprintln!("Thumbnail toolbar is unavailable: {err}");
+                    }
+                }
+                let handle = app.handle().clone();
+                taskbar::start_progress_monitor(window, move || taskbar_progress(&handle));
+            }
+            let handle = app.handle().clone();
+            // Plugins can take a while to load, so the window doesn't wait for them.
+            std::thread::spawn(move || {
+                let (audio, db) = (handle.state::<AudioState>(), handle.state::<DbManager>());
+                if let Err(err) = load_plugin_chain(&audio, &db, None) {
+                    eprintln!("Failed to restore the plugin chain: {err}");
+                }
+            });
+            start_output_device_monitor(app.handle().clone());
+            let handle = app.handle().clone();
+            library::scanner::start_watcher_watchdog(move |roots| {
+                let _ = handle.emit("watcher-degraded", roots);
+            });
+            let handle = app.handle().clone();
+            std::thread::spawn(move || restore_library_roots(&handle));
+            let handle = app.handle().clone();
+            app.manage(StemQueue::new(stem_queue_cache, move |event| {
+                handle_stem_job_event(&handle, event)
+            }));
+            let handle = app.handle().clone();
+            library::heatmap::start_position_monitor(heatmap_db, move || {
+                let state = handle.state::<AudioState>();
+                if !state.is_playing() {
+                    return None;
+                }
+                Some(library::heatmap::PositionSample {
+                    path: state.loaded_path()?,
+                    position_seconds: state.position_seconds(),
+                    duration_seconds: state.get_track_duration_seconds() as f64,
+                })
+            });
+            let handle = app.handle().clone();
+            library::history::start_history_recorder(history_db, move || {
+                handle.state::<AudioState>().take_ended_plays()
+            });
+            Ok(())
+        })
+        .manage(audio)
+        .manage(DlnaOutput::default())
+        .manage(CastOutput::default())
+        .manage(AirPlayOutput::default())
+        .manage(HeadTracker::default())
         .manage(db)
         .manage(Mutex::new(PlaybackQueue::new()))
         .manage(Mutex::new(StemSeparator::new(stems_cache)))
         .invoke_handler(tauri::generate_handler![
             greet,
             update_eq_band,
+            search_autoeq_profiles,
             activate_autoeq_profile,
+            import_room_correction,
+            clear_room_correction,
+            get_room_correction,
+            scan_plugins,
+            insert_plugin,
+            remove_plugin,
+            list_plugin_inserts,
+            get_plugin_parameters,
+            set_plugin_parameter,
+            save_track_plugin_chain,
+            clear_track_plugin_chain,
+            get_plugin_chain_track,
             get_eq_bands,
+            capture_ab_snapshot,
+            switch_ab_snapshot,
+            get_ab_status,
+            set_node_bypassed,
+            get_dsp_status,
+            set_eq_mode,
+            get_eq_mode,
+            set_eq_linear_phase,
+            get_eq_linear_phase,
+            set_graphic_eq_band,
+            get_graphic_eq_bands,
+            save_eq_preset,
+            load_eq_preset,
+            list_eq_presets,
+            delete_eq_preset,
+            save_chain_preset,
+            load_chain_preset,
+            list_chain_presets,
+            delete_chain_preset,
+            export_chain_preset,
+            import_chain_preset,
             get_eq_frequency_response,
             get_fft_data,
             load_track,
-            extract_waveform,
+            get_track_waveform,
+            analyze_track_bpm,
+            get_loudness_profile,
             play,
             pause,
+            get_chapters,
+            set_resume_position,
+            get_play_heatmap,
             set_next_track,
             seek,
+            instant_replay,
             set_volume,
             get_vibe_data,
             get_audio_stats,
             get_lyrics_lines,
+            get_lyrics_details,
+            set_lyrics_lenient,
+            set_silence_trimming,
+            set_gap_skipping,
+            set_embed_fetched_art,
+            get_embed_fetched_art,
+            get_network_usage_stats,
+            store_credential,
+            delete_credential,
+            has_credential,
             scan_library,
+            rescan_track,
+            cancel_scan,
+            prune_missing_tracks,
+            get_library_roots,
+            remove_library_root,
             get_library_tracks,
+            get_library_track_count,
+            get_tracks_by_plays,
+            set_track_rating,
+            get_albums,
+            get_album_tracks,
+            get_artists,
+            get_artist_detail,
+            browse_folder,
+            write_track_tags,
+            preview_batch_tags,
+            write_batch_tags,
+            reveal_in_file_manager,
+            delete_track_file,
+            copy_files,
+            move_files,
+            get_supported_extensions,
+            set_extra_audio_extensions,
+            get_scan_exclusions,
+            set_scan_exclusions,
             set_tone,
             set_balance,
+            set_balance_mode,
+            get_balance_mode,
+            set_mono,
+            get_mono,
             set_expansion,
+            set_crossfeed_preset,
+            set_loudness_compensation,
+            get_loudness_compensation,
+            set_night_mode,
+            get_night_mode,
+            set_upmix,
+            get_upmix,
+            set_bass_management,
+            get_bass_management,
+            set_analog_enabled,
+            set_analog_params,
+            get_analog,
+            set_compressor_enabled,
+            set_compressor_band,
+            set_compressor_crossovers,
+            get_compressor,
+            set_limiter_params,
             set_reverb_params,
             load_reverb_preset,
+            get_dsp_profiles,
+            save_dsp_profile,
+            delete_dsp_profile,
+            assign_dsp_profile,
+            get_dsp_profile_assignments,
             fast_search,
             toggle_shuffle,
+            set_queue,
             toggle_spatial_mode,
+            set_spatial_output_mode,
+            get_spatial_output_mode,
             update_source_position,
             set_room_properties,
             get_spatial_sources,
+            load_spatial_hrtf,
+            set_listener_orientation,
+            set_listener_position,
+            set_listener_yaw,
+            get_spatial_listener,
+            set_source_motion,
+            clear_source_motions,
+            get_source_motions,
+            start_head_tracking,
+            stop_head_tracking,
+            set_stem_gain,
+            set_stem_mute,
+            set_stem_solo,
+            update_stem_eq_band,
+            get_stem_eq_bands,
+            reset_stem_eq,
+            add_spatial_source,
+            remove_spatial_source,
             auto_orchestra,
             save_spatial_scene,
             load_spatial_scene,
+            export_spatial_scene,
+            import_spatial_scene,
             analyze_spatial_stems,
+            cancel_stem_analysis,
+            export_stems,
+            queue_stem_analysis,
+            pause_stem_queue,
+            resume_stem_queue,
+            set_stem_queue_concurrency,
+            get_stem_queue_status,
+            get_stem_model_status,
+            download_stem_model,
+            set_karaoke,
+            get_karaoke,
+            set_stem_playback,
+            get_stem_playback,
+            start_album_experience,
+            stop_album_experience,
+            play_stream,
+            get_stations,
+            get_playlists,
+            get_playlist_tracks,
+            create_playlist,
+            rename_playlist,
+            delete_playlist,
+            add_playlist_tracks,
+            remove_playlist_tracks,
+            move_playlist_track,
+            load_playlist_into_queue,
+            import_playlist,
+            export_playlist,
+            export_queue,
+            export_library,
+            import_itunes_library,
+            get_play_history,
+            clear_play_history,
+            save_station,
+            delete_station,
+            list_dlna_renderers,
+            select_output_renderer,
+            list_cast_devices,
+            select_cast_device,
+            get_cast_status,
+            list_airplay_devices,
+            select_airplay_device,
         ])
         .run(tauri::generate_context!())
         .expect("error while running PowerPlayer");
@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+use crate::audio::analyzer::downsample_waveform;
+use crate::audio::decoder::read_track_metadata;
+use crate::audio::lyrics::{load_lyrics_for_track, LrcParseMode, LyricsLine};
+use crate::audio::lyrics_downloader::download_lyrics_for_track;
+use crate::db::manager::DbManager;
+use crate::library::metadata::art_fetcher::find_local_cover;
+use crate::library::{art_cache, palette, paths::canonical_path_string, waveform_cache};
+
+const PALETTE_COLORS: usize = 5;
+/// Enough resolution for a fullscreen seekbar while keeping each event small.
+pub const DEFAULT_WAVEFORM_POINTS: usize = 512;
+
+/// Only the newest session keeps running; starting another one cancels it.
+static ACTIVE_SESSION: AtomicU64 = AtomicU64::new(0);
+
+/// Everything the fullscreen album mode shows for one track, prepared before the track
+/// plays so switching tracks needs no further loading.
+#[derive(Clone, Debug, Serialize)]
+pub struct TrackExperience {
+    pub session: u64,
+    /// Position in the album, 0-based, out of `total`.
+    pub index: usize,
+    pub total: usize,
+    pub path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_seconds: Option<f32>,
+    pub art_url: Option<String>,
+    /// Dominant cover colors as `#rrggbb`, most common first.
+    pub palette: Vec<String>,
+    pub lyrics: Vec<LyricsLine>,
+    pub lyrics_offset_ms: i32,
+    pub waveform: Vec<f32>,
+    /// Steps that failed for this track; the other fields are still usable.
+    pub errors: Vec<String>,
+}
+
+/// Prepares `paths` one after another, in album order, on a background thread and hands
+/// each finished track to `on_ready`. Returns the session id carried by every payload.
+pub fn start(
+    db: DbManager,
+    paths: Vec<String>,
+    waveform_points: usize,
+    lyrics_mode: LrcParseMode,
+    mut on_ready: impl FnMut(TrackExperience) + Send + 'static,
+) -> u64 {
+    let session = ACTIVE_SESSION.fetch_add(1, Ordering::SeqCst) + 1;
+    thread::spawn(move || {
+        let total = paths.len();
+        for (index, path) in paths.into_iter().enumerate() {
+            if ACTIVE_SESSION.load(Ordering::SeqCst) != session {
+                return;
+            }
+            let mut track = prepare_track(&db, &path, waveform_points, lyrics_mode);
+            track.session = session;
+            track.index = index;
+            track.total = total;
+            on_ready(track);
+        }
+    });
+    session
+}
+
+/// Stops the running session after the track it is preparing.
+pub fn cancel() {
+    ACTIVE_SESSION.fetch_add(1, Ordering::SeqCst);
+}
+
+fn prepare_track(
+    db: &DbManager,
+    path: &str,
+    waveform_points: usize,
+    lyrics_mode: LrcParseMode,
+) -> TrackExperience {
+    let track_path = Path::new(path);
+    let mut errors = Vec::new();
+    let metadata = match read_track_metadata(track_path) {
+        Ok(metadata) => Some(metadata),
+        Err(err) => {
+            errors.push(err);
+            None
+        }
+    };
+
+    let thumbnail = art_cache::cached_thumbnail(track_path).or_else(|| {
+        let cached = match metadata
+            .as_ref()
+            .and_then(|metadata| metadata.cover_art.as_ref())
+        {
+            Some(cover) => art_cache::cache_cover_art(track_path, cover),
+            None => find_local_cover(track_path)
+                .map(|cover| art_cache::cache_cover_file(track_path, &cover))
+                .unwrap_or(Ok(None)),
+        };
+        if let Err(err) = cached {
+            errors.push(err);
+        }
+        art_cache::cached_thumbnail(track_path)
+    });
+    let mut palette = Vec::new();
+    if let Some(thumbnail) = &thumbnail {
+        match fs::read(thumbnail)
+            .map_err(|e| format!("Failed to read cached art {}: {e}", thumbnail.display()))
+            .and_then(|bytes| palette::extract_palette(&bytes, PALETTE_COLORS))
+        {
+            Ok(colors) => palette = colors,
+            Err(err) => errors.push(err),
+        }
+    }
+
+    let title = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.title.clone());
+    let artist = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.artist.clone());
+    let duration_seconds = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.duration_seconds);
+    let mut lyrics = load_lyrics_for_track(track_path, lyrics_mode);
+    if lyrics.lines.is_empty() {
+        if let (Some(artist), Some(title)) = (&artist, &title) {
+            if download_lyrics_for_track(track_path, artist, title, duration_seconds).is_some() {
+                lyrics = load_lyrics_for_track(track_path, lyrics_mode);
+            }
+        }
+    }
+
+    let waveform = waveform_cache::envelope(db, &canonical_path_string(path))
+        .map(|envelope| downsample_waveform(&envelope, waveform_points))
+        .unwrap_or_else(|err| {
+            errors.push(err);
+            Vec::new()
+        });
+
+    TrackExperience {
+        session: 0,
+        index: 0,
+        total: 0,
+        path: path.to_string(),
+        title,
+        artist,
+        album: metadata.and_then(|metadata| metadata.album),
+        duration_seconds,
+        art_url: thumbnail.map(|thumbnail| art_cache::to_asset_url(&thumbnail)),
+        palette,
+        lyrics: lyrics.lines,
+        lyrics_offset_ms: lyrics.tags.offset_ms,
+        waveform,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prepare_track;
+    use crate::audio::lyrics::LrcParseMode;
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn unreadable_tracks_still_produce_a_payload() {
+        let db = DbManager::new(unique_temp_path("album.db")).expect("db init");
+        let missing = unique_temp_path("album.flac");
+
+        let track = prepare_track(&db, &missing.to_string_lossy(), 64, LrcParseMode::Lenient);
+        assert_eq!(track.title, None);
+        assert!(track.palette.is_empty());
+        assert!(track.lyrics.is_empty());
+        assert!(track.waveform.is_empty());
+        assert!(track.errors.len() >= 2, "metadata and waveform should fail");
+    }
+}
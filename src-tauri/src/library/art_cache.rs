@@ -45,7 +45,13 @@ pub fn cache_cover_bytes(track_path: &Path, bytes: &[u8]) -> Result<Option<Strin
     Ok(Some(to_asset_url(&cache_file)))
 }
 
-fn prune_flat_cache_dir(dir: &Path, max_files: usize) {
+/// Thumbnail cached for `track_path` by the scanner or art fetcher, if any.
+pub fn cached_thumbnail(track_path: &Path) -> Option<PathBuf> {
+    let cache_file = cache_file_path(track_path);
+    cache_file.is_file().then_some(cache_file)
+}
+
+pub(crate) fn prune_flat_cache_dir(dir: &Path, max_files: usize) {
     let Ok(entries) = fs::read_dir(dir) else {
         return;
     };
@@ -79,12 +85,16 @@ fn cache_file_path(track_path: &Path) -> PathBuf {
     hash.update(track_path.to_string_lossy().as_bytes());
     let filename = format!("{:x}.jpg", hash.finalize());
 
-    let cache_dir = std::env::temp_dir().join("powerplayer").join("art_cache");
+    let cache_dir = cache_dir();
     let _ = fs::create_dir_all(&cache_dir);
     cache_dir.join(filename)
 }
 
-fn to_asset_url(path: &Path) -> String {
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join("powerplayer").join("art_cache")
+}
+
+pub(crate) fn to_asset_url(path: &Path) -> String {
     format!("asset://{}", path.to_string_lossy().replace('\\', "/"))
 }
 
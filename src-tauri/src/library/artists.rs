@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use crate::library::art_cache;
+
+/// Image files looked for in an artist's folder, in order of preference.
+const ARTIST_IMAGE_NAMES: &[&str] = &["artist.jpg", "artist.jpeg", "artist.png"];
+/// Words introducing featured artists, matched case-insensitively after a space or an
+/// opening bracket.
+const FEATURING_MARKERS: &[&str] = &["featuring ", "feat. ", "feat ", "ft. ", "ft "];
+
+/// Splits an artist tag into the artists it credits, main artists first:
+/// `"A feat. B"`, `"A (ft. B)"`, `"A; B"` and `"A/B"` all credit A and B. A `/` only
+/// splits when every part has at least three characters, so names like `AC/DC` stay
+/// whole. Names repeated in another case are listed once.
+pub fn split_artists(tag: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for credit in tag.split(';') {
+        let (main, featured) = split_featuring(credit);
+        for name in [Some(main), featured]
+            .into_iter()
+            .flatten()
+            .flat_map(split_slashes)
+        {
+            if !name.is_empty()
+                && !names
+                    .iter()
+                    .any(|known| known.to_lowercase() == name.to_lowercase())
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// The main artist of `credit` and the artist after its first featuring marker.
+fn split_featuring(credit: &str) -> (&str, Option<&str>) {
+    // ASCII lowercasing keeps byte offsets valid in `credit`.
+    let lower = credit.to_ascii_lowercase();
+    let found = (1..lower.len())
+        .filter(|&index| {
+            lower.is_char_boundary(index)
+                && matches!(lower.as_bytes()[index - 1], b' ' | b'(' | b'[')
+        })
+        .find_map(|index| {
+            FEATURING_MARKERS
+                .iter()
+                .find(|marker| lower[index..].starts_with(*marker))
+                .map(|marker| (index, marker.len()))
+        });
+    let Some((index, marker_len)) = found else {
+        return (credit.trim(), None);
+    };
+    let main = credit[..index].trim_end_matches(['(', '[']).trim();
+    let featured = credit[index + marker_len..]
+        .trim()
+        .trim_end_matches([')', ']'])
+        .trim();
+    (main, Some(featured))
+}
+
+fn split_slashes(name: &str) -> Vec<&str> {
+    let parts = name.split('/').map(str::trim).collect::<Vec<_>>();
+    if parts.len() > 1 && parts.iter().all(|part| part.chars().count() >= 3) {
+        parts
+    } else {
+        vec![name.trim()]
+    }
+}
+
+/// Image for an artist page: an `artist.jpg` (or `.jpeg`/`.png`) in the folder of one
+/// of `track_paths` or the folder above it (the usual `Artist/Album/track` layout),
+/// cached like a cover. `None` when there is no such file; the page then shows an
+/// album cover instead.
+pub fn artist_image_url<'a>(track_paths: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    track_paths
+        .into_iter()
+        .filter_map(|path| Path::new(path).parent())
+        .flat_map(|album_dir| [Some(album_dir), album_dir.parent()])
+        .flatten()
+        .find_map(|dir| {
+            let image = ARTIST_IMAGE_NAMES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())?;
+            art_cache::cache_cover_file(dir, &image).ok().flatten()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{artist_image_url, split_artists};
+    use crate::test_support::unique_temp_path;
+    use image::RgbImage;
+
+    #[test]
+    fn artist_images_are_found_above_album_folders() {
+        let dir = unique_temp_path("artist-image");
+        let album = dir.join("Band").join("Album");
+        std::fs::create_dir_all(&album).expect("create dirs");
+        let track = album.join("01.flac").to_string_lossy().into_owned();
+        assert_eq!(artist_image_url([track.as_str()]), None);
+
+        RgbImage::from_pixel(8, 8, image::Rgb([0, 0, 255]))
+            .save(dir.join("Band").join("artist.png"))
+            .expect("write image");
+        let url = artist_image_url(["/elsewhere/a.flac", track.as_str()]).expect("image");
+        assert!(url.starts_with("asset://"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn artist_tags_split_on_featuring_and_separators() {
+        assert_eq!(split_artists("Band feat. Amy"), ["Band", "Amy"]);
+        assert_eq!(split_artists("Band (Ft. Amy)"), ["Band", "Amy"]);
+        assert_eq!(split_artists("Band [featuring Amy]"), ["Band", "Amy"]);
+        assert_eq!(split_artists("Amy; Bo;Cy"), ["Amy", "Bo", "Cy"]);
+        assert_eq!(
+            split_artists("Band/Singer feat. Amy"),
+            ["Band", "Singer", "Amy"]
+        );
+        assert_eq!(split_artists("Amy; amy"), ["Amy"]);
+        assert_eq!(split_artists("AC/DC"), ["AC/DC"]);
+        assert_eq!(split_artists("Daft Punk"), ["Daft Punk"]);
+        assert_eq!(
+            split_artists("Soft Cell"),
+            ["Soft Cell"],
+            "no marker inside words"
+        );
+        assert!(split_artists("  ").is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// CUE timestamps are `mm:ss:ff` with 75 frames (CD sectors) per second.
+const CUE_FRAMES_PER_SECOND: u64 = 75;
+/// Separates the cue sheet path from the track number in a virtual track path.
+const VIRTUAL_TRACK_SEPARATOR: char = '#';
+
+/// One song of a cue sheet: a slice of `file` from `start_frame` up to `end_frame`, or
+/// to the end of the file for the last song in it. Frames are CUE frames (1/75 s).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub file: PathBuf,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub start_frame: u64,
+    pub end_frame: Option<u64>,
+}
+
+impl CueTrack {
+    pub fn start_seconds(&self) -> f64 {
+        self.start_frame as f64 / CUE_FRAMES_PER_SECOND as f64
+    }
+
+    pub fn end_seconds(&self) -> Option<f64> {
+        self.end_frame
+            .map(|frame| frame as f64 / CUE_FRAMES_PER_SECOND as f64)
+    }
+
+    /// Sample-frame range of this song in its decoded file, clamped to `total_frames`.
+    pub fn frame_range(&self, sample_rate: u32, total_frames: usize) -> Range<usize> {
+        let to_frames = |cue_frame: u64| {
+            (cue_frame * sample_rate as u64 / CUE_FRAMES_PER_SECOND).min(total_frames as u64)
+                as usize
+        };
+        let start = to_frames(self.start_frame);
+        let end = self.end_frame.map(to_frames).unwrap_or(total_frames);
+        start..end.max(start)
+    }
+}
+
+pub fn is_cue_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+}
+
+/// Library path of a cue sheet song, e.g. `/music/Album.cue#03`.
+pub fn virtual_track_path(cue_path: &Path, number: u32) -> String {
+    format!(
+        "{}{VIRTUAL_TRACK_SEPARATOR}{number:02}",
+        cue_path.to_string_lossy()
+    )
+}
+
+/// Prefix shared by every virtual track of `cue_path`.
+pub fn virtual_track_prefix(cue_path: &Path) -> String {
+    format!("{}{VIRTUAL_TRACK_SEPARATOR}", cue_path.to_string_lossy())
+}
+
+/// Splits a virtual track path into its cue sheet and track number.
+pub fn split_virtual_path(path: &Path) -> Option<(PathBuf, u32)> {
+    let raw = path.to_str()?;
+    let (cue_path, number) = raw.rsplit_once(VIRTUAL_TRACK_SEPARATOR)?;
+    if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let cue_path = PathBuf::from(cue_path);
+    if !is_cue_path(&cue_path) {
+        return None;
+    }
+    Some((cue_path, number.parse().ok()?))
+}
+
+/// Re-reads the cue sheet behind a virtual track path. `None` when `path` is a plain
+/// file path.
+pub fn resolve_virtual_track(path: &Path) -> Option<Result<CueTrack, String>> {
+    let (cue_path, number) = split_virtual_path(path)?;
+    Some(read_cue_sheet(&cue_path).and_then(|tracks| {
+        let track = tracks
+            .into_iter()
+            .find(|track| track.number == number)
+            .ok_or_else(|| format!("Track {number} not found in {}", cue_path.display()))?;
+        // A sheet pointing at another virtual track would make decoding recurse forever.
+        if split_virtual_path(&track.file).is_some() {
+            return Err(format!("Invalid FILE in cue sheet {}", cue_path.display()));
+        }
+        Ok(track)
+    }))
+}
+
+/// Reads and parses a cue sheet; `FILE` entries are resolved next to the sheet.
+pub fn read_cue_sheet(path: &Path) -> Result<Vec<CueTrack>, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Cannot read cue sheet {}: {e}", path.display()))?;
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        // Sheets written by older rippers are usually Windows-1252; Latin-1 keeps the
+        // accented letters readable without pulling in an encoding crate.
+        Err(err) => err.into_bytes().iter().map(|&byte| byte as char).collect(),
+    };
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_cue_sheet(&text, base_dir)
+        .map_err(|e| format!("Invalid cue sheet {}: {e}", path.display()))
+}
+
+pub fn parse_cue_sheet(text: &str, base_dir: &Path) -> Result<Vec<CueTrack>, String> {
+    let mut album = None;
+    let mut album_performer = None;
+    let mut album_genre = None;
+    let mut file: Option<PathBuf> = None;
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    // Audio track currently being read; data tracks are parsed but never kept.
+    let mut current: Option<CueTrack> = None;
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                tracks.extend(current.take());
+                file = Some(base_dir.join(file_name_argument(rest)));
+            }
+            "TRACK" => {
+                tracks.extend(current.take());
+                let mut args = rest.split_whitespace();
+                let number = args
+                    .next()
+                    .and_then(|number| number.parse().ok())
+                    .ok_or_else(|| format!("Invalid TRACK line: {line}"))?;
+                let is_audio = args
+                    .next()
+                    .is_some_and(|kind| kind.eq_ignore_ascii_case("AUDIO"));
+                let file = file
+                    .clone()
+                    .ok_or_else(|| format!("TRACK {number} appears before any FILE"))?;
+                current = is_audio.then(|| CueTrack {
+                    number,
+                    file,
+                    title: None,
+                    performer: None,
+                    album: album.clone(),
+                    genre: album_genre.clone(),
+                    start_frame: u64::MAX,
+                    end_frame: None,
+                });
+            }
+            "TITLE" => match current.as_mut() {
+                Some(track) => track.title = text_argument(rest),
+                None => album = text_argument(rest),
+            },
+            "PERFORMER" => match current.as_mut() {
+                Some(track) => track.performer = text_argument(rest),
+                None => album_performer = text_argument(rest),
+            },
+            "REM" => {
+                if let Some((key, value)) = rest.split_once(char::is_whitespace) {
+                    if key.eq_ignore_ascii_case("GENRE") {
+                        match current.as_mut() {
+                            Some(track) => track.genre = text_argument(value),
+                            None => album_genre = text_argument(value),
+                        }
+                    }
+                }
+            }
+            "INDEX" => {
+                let mut args = rest.split_whitespace();
+                // INDEX 00 marks the pregap; the song itself starts at INDEX 01.
+                if args.next().and_then(|index| index.parse::<u32>().ok()) == Some(1) {
+                    let frame = args
+                        .next()
+                        .and_then(parse_timestamp)
+                        .ok_or_else(|| format!("Invalid INDEX line: {line}"))?;
+                    if let Some(track) = current.as_mut() {
+                        track.start_frame = frame;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    tracks.extend(current.take());
+
+    tracks.retain(|track| track.start_frame != u64::MAX);
+    for track in &mut tracks {
+        if track.performer.is_none() {
+            track.performer.clone_from(&album_performer);
+        }
+    }
+    for index in 1..tracks.len() {
+        if tracks[index].file == tracks[index - 1].file {
+            tracks[index - 1].end_frame = Some(tracks[index].start_frame);
+        }
+    }
+    Ok(tracks)
+}
+
+fn text_argument(rest: &str) -> Option<String> {
+    let value = rest
+        .strip_prefix('"')
+        .map(|quoted| quoted.split_once('"').map_or(quoted, |(value, _)| value))
+        .unwrap_or(rest)
+        .trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// `FILE "name with spaces.flac" WAVE`; unquoted names run up to the file type.
+fn file_name_argument(rest: &str) -> String {
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted
+            .split_once('"')
+            .map_or(quoted, |(name, _)| name)
+            .to_string();
+    }
+    rest.rsplit_once(char::is_whitespace)
+        .map_or(rest, |(name, _)| name)
+        .trim()
+        .to_string()
+}
+
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let mut parts = value.split(':').map(|part| part.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= CUE_FRAMES_PER_SECOND {
+        return None;
+    }
+    Some((minutes * 60 + seconds) * CUE_FRAMES_PER_SECOND + frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cue_sheet, split_virtual_path, virtual_track_path};
+    use std::path::{Path, PathBuf};
+
+    const SHEET: &str = "\u{feff}REM GENRE \"Progressive Rock\"
+PERFORMER \"The Band\"
+TITLE \"Live Album\"
+FILE \"Live Album.flac\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Intro\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Song\"
+    PERFORMER \"Guest\"
+    INDEX 00 03:10:00
+    INDEX 01 03:12:37
+  TRACK 03 DATA
+    INDEX 01 08:00:00
+FILE bonus.wav WAVE
+  TRACK 04 AUDIO
+    TITLE Encore
+    INDEX 01 00:00:00
+";
+
+    #[test]
+    fn parses_tracks_with_offsets_and_inherited_tags() {
+        let tracks = parse_cue_sheet(SHEET, Path::new("/music")).expect("sheet should parse");
+        assert_eq!(tracks.len(), 3);
+
+        let intro = &tracks[0];
+        assert_eq!(intro.file, PathBuf::from("/music/Live Album.flac"));
+        assert_eq!(intro.title.as_deref(), Some("Intro"));
+        assert_eq!(intro.performer.as_deref(), Some("The Band"));
+        assert_eq!(intro.album.as_deref(), Some("Live Album"));
+        assert_eq!(intro.genre.as_deref(), Some("Progressive Rock"));
+        assert_eq!(intro.end_frame, Some((3 * 60 + 12) * 75 + 37));
+
+        let song = &tracks[1];
+        assert_eq!(song.performer.as_deref(), Some("Guest"));
+        assert!((song.start_seconds() - 192.493).abs() < 0.001);
+        assert_eq!(song.end_frame, None, "data tracks do not end the song");
+
+        let encore = &tracks[2];
+        assert_eq!(encore.number, 4);
+        assert_eq!(encore.file, PathBuf::from("/music/bonus.wav"));
+        assert_eq!(encore.title.as_deref(), Some("Encore"));
+        assert_eq!(encore.start_frame, 0);
+    }
+
+    #[test]
+    fn frame_range_maps_cue_frames_to_samples() {
+        let tracks = parse_cue_sheet(SHEET, Path::new("/music")).expect("sheet should parse");
+        assert_eq!(tracks[0].frame_range(44_100, 10_000_000), 0..8_488_956);
+        assert_eq!(
+            tracks[1].frame_range(44_100, 10_000_000),
+            8_488_956..10_000_000
+        );
+        assert_eq!(tracks[1].frame_range(44_100, 100), 100..100);
+        assert!(parse_cue_sheet("TRACK 01 AUDIO\n", Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn virtual_paths_roundtrip() {
+        let path = virtual_track_path(Path::new("/music/Album.cue"), 7);
+        assert_eq!(path, "/music/Album.cue#07");
+        assert_eq!(
+            split_virtual_path(Path::new(&path)),
+            Some((PathBuf::from("/music/Album.cue"), 7))
+        );
+        assert_eq!(split_virtual_path(Path::new("/music/#1 Hit.flac")), None);
+        assert_eq!(split_virtual_path(Path::new("/music/song.flac#02")), None);
+    }
+}
@@ -1,6 +1,6 @@
-use crate::audio::lyrics_downloader;
+use crate::audio::{analyzer, lyrics_downloader};
 use crate::db::manager::{DbManager, TrackInput};
-use crate::library::metadata::art_fetcher;
+use crate::library::metadata::{acoustid, art_embedder, art_fetcher, musicbrainz};
 use std::path::Path;
 use std::sync::mpsc::{self, Sender};
 use std::sync::OnceLock;
@@ -35,18 +35,104 @@ fn queue_sender() -> &'static Sender<EnrichmentTask> {
 fn process_task(task: EnrichmentTask) {
     let mut updated_track = task.track.clone();
     let mut should_save = false;
+
+    // Fingerprinted once; tracks already matched to a recording are not looked up again.
+    if !updated_track.corrupted {
+        let fingerprint = match task.db.get_fingerprint(&updated_track.path) {
+            Ok(Some(fingerprint)) => Some(fingerprint),
+            Ok(None) => match analyzer::analyze_fingerprint(Path::new(&updated_track.path)) {
+                Ok(fingerprint) => {
+                    if let Some(fingerprint) = &fingerprint {
+                        if let Err(err) = task.db.save_fingerprint(&updated_track.path, fingerprint)
+                        {
+                            eprintln!(
+                                "Failed to store fingerprint for {}: {err}",
+                                updated_track.path
+                            );
+                        }
+                    }
+                    fingerprint
+                }
+                Err(err) => {
+                    eprintln!("Fingerprinting failed for {}: {err}", updated_track.path);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        let matched = !matches!(task.db.get_musicbrainz_ids(&updated_track.path), Ok(None));
+        if let Some(fingerprint) = fingerprint.filter(|_| !matched) {
+            match acoustid::lookup(&fingerprint, &updated_track) {
+                Ok(Some(found)) => {
+                    should_save |= acoustid::apply_match(&mut updated_track, &found);
+                    if let Err(err) = task
+                        .db
+                        .save_musicbrainz_ids(&updated_track.path, &found.ids)
+                    {
+                        eprintln!(
+                            "Failed to store MusicBrainz IDs for {}: {err}",
+                            updated_track.path
+                        );
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("AcoustID lookup failed for {}: {err}", updated_track.path),
+            }
+        }
+    }
+
+    // Tracks matched before, or identified by AcoustID above, are looked up by their
+    // stored recording ID.
+    if musicbrainz::needs_enrichment(&updated_track) {
+        let known = task
+            .db
+            .get_musicbrainz_ids(&updated_track.path)
+            .unwrap_or(None);
+        match musicbrainz::lookup(&updated_track, known.as_ref()) {
+            Ok(Some(found)) => {
+                should_save |= musicbrainz::fill_missing(&mut updated_track, &found);
+                if let Err(err) = task
+                    .db
+                    .save_musicbrainz_ids(&updated_track.path, &found.ids)
+                {
+                    eprintln!(
+                        "Failed to store MusicBrainz IDs for {}: {err}",
+                        updated_track.path
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!(
+                "MusicBrainz lookup failed for {}: {err}",
+                updated_track.path
+            ),
+        }
+    }
+
     let track_path = Path::new(&updated_track.path);
 
     if updated_track.art_url.is_none() {
-        if let Ok(art_url) = art_fetcher::fetch_and_cache_art(
+        if let Ok(fetched) = art_fetcher::fetch_and_cache_art(
             track_path,
             updated_track.artist.as_deref(),
             updated_track.title.as_deref(),
         ) {
-            if art_url.is_some() {
-                updated_track.art_url = art_url;
+            if fetched.art_url.is_some() {
+                updated_track.art_url = fetched.art_url;
                 should_save = true;
             }
+            // `art_url` was unset, so the file has no embedded art of its own.
+            if let Some(image) = fetched
+                .downloaded
+                .filter(|_| art_embedder::embed_fetched_art_enabled(&task.db).unwrap_or(false))
+            {
+                if let Err(err) = art_embedder::embed_cover_art(track_path, &image) {
+                    eprintln!(
+                        "Failed to embed cover art into {}: {err}",
+                        updated_track.path
+                    );
+                }
+            }
         }
     }
 
@@ -65,4 +151,20 @@ fn process_task(task: EnrichmentTask) {
     if should_save {
         let _ = task.db.save_track(&updated_track);
     }
+
+    if !updated_track.corrupted
+        && matches!(task.db.get_loudness_profile(&updated_track.path), Ok(None))
+    {
+        match analyzer::analyze_loudness_profile(track_path) {
+            Ok(profile) => {
+                if let Err(err) = task.db.save_loudness_profile(&updated_track.path, &profile) {
+                    eprintln!(
+                        "Failed to store loudness profile for {}: {err}",
+                        updated_track.path
+                    );
+                }
+            }
+            Err(err) => eprintln!("Loudness analysis failed for {}: {err}", updated_track.path),
+        }
+    }
 }
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, MAIN_SEPARATOR};
+use std::sync::{OnceLock, RwLock};
+
+use crate::db::manager::DbManager;
+use crate::library::cue;
+
+/// Settings key holding the scan exclusions as a JSON object.
+const SCAN_EXCLUSIONS_SETTING: &str = "scan_exclusions";
+
+/// Rules that keep files out of the library, such as ringtones, sample packs and
+/// podcast folders. Path rules apply to paths below the library root; the duration and
+/// bitrate minimums apply once a file's metadata has been read.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanExclusions {
+    /// Case-insensitive globs with `*`, `**` and `?`. A pattern without `/` is matched
+    /// against every folder and file name (`Ringtones`, `*sample*`); one with `/`
+    /// against the whole path below the root (`Podcasts/**`, `**/Stems/*.wav`).
+    pub patterns: Vec<String>,
+    /// Skips files and folders whose name starts with a dot.
+    pub skip_hidden: bool,
+    pub min_duration_seconds: Option<f32>,
+    /// Average bitrate computed from the file size and duration.
+    pub min_bitrate_kbps: Option<u32>,
+}
+
+impl ScanExclusions {
+    /// Whether `relative`, a path below a library root, is excluded by the path rules.
+    pub fn excludes_path(&self, relative: &Path) -> bool {
+        let names = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if self.skip_hidden && names.iter().any(|name| name.starts_with('.')) {
+            return true;
+        }
+        let whole = names.join("/").chars().collect::<Vec<_>>();
+        self.patterns.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+            if pattern.contains(&'/') {
+                glob_matches(&pattern, &whole)
+            } else {
+                names
+                    .iter()
+                    .any(|name| glob_matches(&pattern, &name.chars().collect::<Vec<_>>()))
+            }
+        })
+    }
+
+    /// Whether a file of `size_bytes` lasting `duration_seconds` falls below the
+    /// minimums. Files of unknown duration are kept.
+    pub fn excludes_audio(&self, duration_seconds: Option<f32>, size_bytes: Option<u64>) -> bool {
+        let Some(duration) = duration_seconds.filter(|duration| *duration > 0.0) else {
+            return false;
+        };
+        if self
+            .min_duration_seconds
+            .is_some_and(|minimum| duration < minimum)
+        {
+            return true;
+        }
+        match (self.min_bitrate_kbps, size_bytes) {
+            (Some(minimum), Some(size)) => {
+                (size as f64 * 8.0 / 1000.0 / f64::from(duration)) < f64::from(minimum)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Matches `text` against a glob where `*` spans characters within one path segment,
+/// `**` spans segments (`**/` also matches no folder at all) and `?` is one character.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            (0..=text.len()).any(|start| glob_matches(rest, &text[start..]))
+                || rest
+                    .strip_prefix(&['/'])
+                    .is_some_and(|rest| glob_matches(rest, text))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&start| start == 0 || text[start - 1] != '/')
+            .any(|start| glob_matches(rest, &text[start..])),
+        ['?', rest @ ..] => {
+            text.first().is_some_and(|ch| *ch != '/') && glob_matches(rest, &text[1..])
+        }
+        [ch, rest @ ..] => text.first() == Some(ch) && glob_matches(rest, &text[1..]),
+    }
+}
+
+fn exclusions() -> &'static RwLock<ScanExclusions> {
+    static EXCLUSIONS: OnceLock<RwLock<ScanExclusions>> = OnceLock::new();
+    EXCLUSIONS.get_or_init(|| RwLock::new(ScanExclusions::default()))
+}
+
+pub fn current() -> ScanExclusions {
+    exclusions()
+        .read()
+        .map(|exclusions| exclusions.clone())
+        .unwrap_or_default()
+}
+
+/// Whether `path` is excluded by the path rules, relative to library root `root`.
+pub fn is_excluded_path(root: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    exclusions()
+        .read()
+        .is_ok_and(|exclusions| exclusions.excludes_path(relative))
+}
+
+/// Whether an audio file read as lasting `duration_seconds` is below the minimums.
+pub fn is_excluded_audio(path: &Path, duration_seconds: Option<f32>) -> bool {
+    exclusions().read().is_ok_and(|exclusions| {
+        let size = std::fs::metadata(path).ok().map(|metadata| metadata.len());
+        exclusions.excludes_audio(duration_seconds, size)
+    })
+}
+
+/// Applies the exclusions persisted in settings. Called once at startup.
+pub fn load_from_settings(db: &DbManager) -> Result<(), String> {
+    let loaded = match db.get_setting(SCAN_EXCLUSIONS_SETTING)? {
+        Some(json) => serde_json::from_str::<ScanExclusions>(&json)
+            .map_err(|e| format!("Invalid {SCAN_EXCLUSIONS_SETTING} setting: {e}"))?,
+        None => ScanExclusions::default(),
+    };
+    replace_exclusions(loaded)
+}
+
+/// Persists `new_exclusions`, applies them to future scans and watcher events, and
+/// removes the library tracks they now exclude. Returns the number of tracks removed.
+pub fn set_exclusions(db: &DbManager, new_exclusions: ScanExclusions) -> Result<usize, String> {
+    let mut patterns = new_exclusions
+        .patterns
+        .iter()
+        .map(|pattern| pattern.trim().replace('\\', "/"))
+        .filter(|pattern| !pattern.is_empty())
+        .collect::<Vec<_>>();
+    patterns.dedup();
+    let normalized = ScanExclusions {
+        patterns,
+        skip_hidden: new_exclusions.skip_hidden,
+        min_duration_seconds: new_exclusions
+            .min_duration_seconds
+            .filter(|seconds| *seconds > 0.0),
+        min_bitrate_kbps: new_exclusions.min_bitrate_kbps.filter(|kbps| *kbps > 0),
+    };
+    let json = serde_json::to_string(&normalized)
+        .map_err(|e| format!("Failed to serialize scan exclusions: {e}"))?;
+    db.set_setting(SCAN_EXCLUSIONS_SETTING, &json)?;
+    replace_exclusions(normalized.clone())?;
+    prune_library(db, &normalized)
+}
+
+/// Deletes the library tracks `exclusions` keeps out. Paths are taken relative to the
+/// library root holding them; cue sheet songs are checked by path only.
+fn prune_library(db: &DbManager, exclusions: &ScanExclusions) -> Result<usize, String> {
+    let roots = db
+        .get_library_roots()?
+        .into_iter()
+        .map(|root| {
+            format!(
+                "{}{MAIN_SEPARATOR}",
+                root.path.trim_end_matches(MAIN_SEPARATOR)
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut removed = 0;
+    for track in db.get_tracks()? {
+        let path = Path::new(&track.path);
+        let relative = roots
+            .iter()
+            .filter(|root| track.path.starts_with(root.as_str()))
+            .max_by_key(|root| root.len())
+            .map_or(path, |root| Path::new(&track.path[root.len()..]));
+        let excluded = exclusions.excludes_path(relative)
+            || (cue::split_virtual_path(path).is_none()
+                && exclusions.excludes_audio(
+                    track.duration_seconds,
+                    std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+                ));
+        if excluded {
+            db.delete_track(&track.path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn replace_exclusions(new_exclusions: ScanExclusions) -> Result<(), String> {
+    *exclusions()
+        .write()
+        .map_err(|_| "Scan exclusions lock poisoned".to_string())? = new_exclusions;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScanExclusions;
+    use std::path::Path;
+
+    #[test]
+    fn path_rules_match_names_and_relative_paths() {
+        let exclusions = ScanExclusions {
+            patterns: vec![
+                "ringtones".to_string(),
+                "*Sample*".to_string(),
+                "Podcasts/**".to_string(),
+                "**/stems/*.wav".to_string(),
+            ],
+            skip_hidden: true,
+            ..ScanExclusions::default()
+        };
+        let excluded = |path: &str| exclusions.excludes_path(Path::new(path));
+        assert!(excluded("Ringtones/beep.mp3"));
+        assert!(excluded("Producer/Drum Samples/kick.wav"));
+        assert!(excluded("Podcasts/Show/episode.mp3"));
+        assert!(excluded("stems/bass.wav"));
+        assert!(excluded("Band/Album/Stems/bass.wav"));
+        assert!(excluded("Band/.trash/song.flac"));
+        assert!(!excluded("Band/Album/Stems/bass.flac"));
+        assert!(!excluded("Band/Podcasts Live/song.flac"));
+        assert!(!excluded("Band/Album/01 Song.flac"));
+    }
+
+    #[test]
+    fn audio_rules_use_duration_and_average_bitrate() {
+        let exclusions = ScanExclusions {
+            min_duration_seconds: Some(30.0),
+            min_bitrate_kbps: Some(96),
+            ..ScanExclusions::default()
+        };
+        // 10 s ringtone.
+        assert!(exclusions.excludes_audio(Some(10.0), Some(400_000)));
+        // 60 s at 64 kbps.
+        assert!(exclusions.excludes_audio(Some(60.0), Some(480_000)));
+        // 60 s at 320 kbps.
+        assert!(!exclusions.excludes_audio(Some(60.0), Some(2_400_000)));
+        assert!(!exclusions.excludes_audio(None, Some(1)));
+    }
+}
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::db::manager::{DbManager, TrackRecord};
+
+/// Version of the JSON export layout, bumped when fields change meaning.
+const EXPORT_VERSION: u32 = 1;
+
+/// Columns of the CSV export, in order.
+const CSV_COLUMNS: &[&str] = &[
+    "path",
+    "title",
+    "artist",
+    "album",
+    "album_artist",
+    "composer",
+    "genre",
+    "year",
+    "track_number",
+    "disc_number",
+    "duration_seconds",
+    "sample_rate",
+    "bpm",
+    "rating",
+    "play_count",
+    "last_played_at",
+    "playlists",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryExportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ExportReport {
+    pub tracks: usize,
+    pub playlists: usize,
+}
+
+#[derive(Serialize)]
+struct ExportedLibrary {
+    version: u32,
+    tracks: Vec<ExportedTrack>,
+    playlists: Vec<ExportedPlaylist>,
+}
+
+#[derive(Serialize)]
+struct ExportedTrack {
+    path: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    composer: Option<String>,
+    genre: Option<String>,
+    year: Option<u32>,
+    track_number: Option<u32>,
+    disc_number: Option<u32>,
+    duration_seconds: Option<f32>,
+    sample_rate: Option<u32>,
+    bpm: Option<f32>,
+    rating: Option<u8>,
+    play_count: u32,
+    /// UTC, as `YYYY-MM-DD HH:MM:SS`.
+    last_played_at: Option<String>,
+    /// Names of the playlists holding the track.
+    playlists: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ExportedPlaylist {
+    name: String,
+    /// Track paths in play order.
+    tracks: Vec<String>,
+}
+
+impl ExportedTrack {
+    fn new(track: TrackRecord, playlists: Vec<String>) -> Self {
+        Self {
+            path: track.path,
+            title: track.title,
+            artist: track.artist,
+            album: track.album,
+            album_artist: track.album_artist,
+            composer: track.composer,
+            genre: track.genre,
+            year: track.year,
+            track_number: track.track_number,
+            disc_number: track.disc_number,
+            duration_seconds: track.duration_seconds,
+            sample_rate: track.sample_rate,
+            bpm: track.bpm,
+            rating: track.rating,
+            play_count: track.play_count,
+            last_played_at: track.last_played_at,
+            playlists,
+        }
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<String>| value.unwrap_or_default();
+        vec![
+            self.path.clone(),
+            text(&self.title),
+            text(&self.artist),
+            text(&self.album),
+            text(&self.album_artist),
+            text(&self.composer),
+            text(&self.genre),
+            number(self.year.map(|v| v.to_string())),
+            number(self.track_number.map(|v| v.to_string())),
+            number(self.disc_number.map(|v| v.to_string())),
+            number(self.duration_seconds.map(|v| v.to_string())),
+            number(self.sample_rate.map(|v| v.to_string())),
+            number(self.bpm.map(|v| v.to_string())),
+            number(self.rating.map(|v| v.to_string())),
+            self.play_count.to_string(),
+            text(&self.last_played_at),
+            self.playlists.join("; "),
+        ]
+    }
+}
+
+/// Writes every library track with its tags, rating, play statistics and playlists to
+/// `dest`. JSON also lists the playlists with their entries in order; CSV has one row
+/// per track with the playlist names joined by `; `.
+pub fn export_library(
+    db: &DbManager,
+    dest: &Path,
+    format: LibraryExportFormat,
+) -> Result<ExportReport, String> {
+    let mut playlists = Vec::new();
+    let mut track_playlists = HashMap::<String, Vec<String>>::new();
+    for playlist in db.get_playlists()? {
+        let tracks = db.get_playlist_tracks(playlist.id)?;
+        for path in &tracks {
+            let names = track_playlists.entry(path.clone()).or_default();
+            if !names.contains(&playlist.name) {
+                names.push(playlist.name.clone());
+            }
+        }
+        playlists.push(ExportedPlaylist {
+            name: playlist.name,
+            tracks,
+        });
+    }
+    let tracks = db
+        .get_tracks()?
+        .into_iter()
+        .map(|track| {
+            let names = track_playlists.remove(&track.path).unwrap_or_default();
+            ExportedTrack::new(track, names)
+        })
+        .collect::<Vec<_>>();
+    let report = ExportReport {
+        tracks: tracks.len(),
+        playlists: playlists.len(),
+    };
+
+    let contents = match format {
+        LibraryExportFormat::Json => serde_json::to_string_pretty(&ExportedLibrary {
+            version: EXPORT_VERSION,
+            tracks,
+            playlists,
+        })
+        .map_err(|e| format!("Failed to serialize library export: {e}"))?,
+        LibraryExportFormat::Csv => {
+            let mut out = csv_line(CSV_COLUMNS.iter().map(|column| column.to_string()));
+            for track in &tracks {
+                out.push_str(&csv_line(track.csv_fields()));
+            }
+            out
+        }
+    };
+    fs::write(dest, contents).map_err(|e| format!("Failed to write {}: {e}", dest.display()))?;
+    Ok(report)
+}
+
+/// One CSV record, quoting fields that hold a comma, quote or line break.
+fn csv_line(fields: impl IntoIterator<Item = String>) -> String {
+    let mut line = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_library, LibraryExportFormat};
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn exports_tracks_with_ratings_plays_and_playlists() {
+        let dir = unique_temp_path("export");
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let db = DbManager::new(dir.join("library.db")).expect("db init");
+        db.initialize_playlist_schema().expect("schema");
+        for (path, title) in [("/m/a.flac", "Hello, \"World\""), ("/m/b.flac", "Plain")] {
+            db.save_track(&TrackInput {
+                path: path.to_string(),
+                title: Some(title.to_string()),
+                artist: Some("Band".to_string()),
+                album: None,
+                genre: None,
+                year: Some(2001),
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        }
+        db.set_track_rating("/m/a.flac", Some(4)).expect("rate");
+        db.record_track_play("/m/a.flac").expect("play");
+        let mix = db.create_playlist("Mix").expect("playlist");
+        db.set_playlist_tracks(mix, &["/m/b.flac".to_string(), "/m/a.flac".to_string()])
+            .expect("entries");
+
+        let json_path = dir.join("library.json");
+        let report =
+            export_library(&db, &json_path, LibraryExportFormat::Json).expect("json export");
+        assert_eq!((report.tracks, report.playlists), (2, 1));
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).expect("read json"))
+                .expect("valid json");
+        let a = json["tracks"]
+            .as_array()
+            .expect("tracks")
+            .iter()
+            .find(|track| track["path"] == "/m/a.flac")
+            .expect("track a");
+        assert_eq!(a["rating"], 4);
+        assert_eq!(a["play_count"], 1);
+        assert_eq!(a["playlists"], serde_json::json!(["Mix"]));
+        assert_eq!(
+            json["playlists"][0]["tracks"],
+            serde_json::json!(["/m/b.flac", "/m/a.flac"])
+        );
+
+        let csv_path = dir.join("library.csv");
+        export_library(&db, &csv_path, LibraryExportFormat::Csv).expect("csv export");
+        let csv = std::fs::read_to_string(&csv_path).expect("read csv");
+        let mut lines = csv.lines();
+        assert!(lines
+            .next()
+            .expect("header")
+            .starts_with("path,title,artist,"));
+        let row = lines
+            .find(|line| line.starts_with("/m/a.flac"))
+            .expect("row a");
+        assert!(row.starts_with("/m/a.flac,\"Hello, \"\"World\"\"\",Band,"));
+        assert!(row.contains(",4,1,"), "rating then play count: {row}");
+        assert!(row.ends_with(",Mix"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
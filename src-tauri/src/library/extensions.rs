@@ -0,0 +1,142 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use crate::db::manager::DbManager;
+
+/// Extensions handled out of the box by the bundled decoders (symphonia with the Opus,
+/// WavPack and APE additions in `audio::codecs`, plus the DSD reader).
+pub const DEFAULT_AUDIO_EXTENSIONS: &[&str] = &[
+    "flac", "mp3", "m4a", "m4b", "mp4", "aac", "ogg", "oga", "opus", "wav", "aif", "aiff", "aifc",
+    "caf", "wv", "ape", "dsf", "dff",
+];
+/// Settings key holding user-enabled extensions as a JSON array (e.g. `["mka","webm"]`).
+const EXTRA_EXTENSIONS_SETTING: &str = "extra_audio_extensions";
+
+/// Set of file extensions treated as audio by the scanner, watcher and file-open path.
+#[derive(Clone, Debug)]
+pub struct ExtensionRegistry {
+    extensions: BTreeSet<String>,
+}
+
+impl ExtensionRegistry {
+    pub fn with_extras(extras: &[String]) -> Self {
+        let mut extensions = DEFAULT_AUDIO_EXTENSIONS
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect::<BTreeSet<_>>();
+        extensions.extend(extras.iter().filter_map(|ext| normalize_extension(ext)));
+        Self { extensions }
+    }
+
+    pub fn is_supported_path(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.contains(&ext.to_ascii_lowercase()))
+    }
+
+    pub fn extensions(&self) -> Vec<String> {
+        self.extensions.iter().cloned().collect()
+    }
+}
+
+impl Default for ExtensionRegistry {
+    fn default() -> Self {
+        Self::with_extras(&[])
+    }
+}
+
+fn registry() -> &'static RwLock<ExtensionRegistry> {
+    static REGISTRY: OnceLock<RwLock<ExtensionRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(ExtensionRegistry::default()))
+}
+
+pub fn is_supported_audio_path(path: &Path) -> bool {
+    registry()
+        .read()
+        .map(|registry| registry.is_supported_path(path))
+        .unwrap_or(false)
+}
+
+pub fn supported_extensions() -> Vec<String> {
+    registry()
+        .read()
+        .map(|registry| registry.extensions())
+        .unwrap_or_default()
+}
+
+/// Applies the extra extensions persisted in settings. Called once at startup.
+pub fn load_from_settings(db: &DbManager) -> Result<(), String> {
+    let extras = match db.get_setting(EXTRA_EXTENSIONS_SETTING)? {
+        Some(json) => serde_json::from_str::<Vec<String>>(&json)
+            .map_err(|e| format!("Invalid {EXTRA_EXTENSIONS_SETTING} setting: {e}"))?,
+        None => Vec::new(),
+    };
+    replace_registry(ExtensionRegistry::with_extras(&extras))
+}
+
+/// Persists `extras` (on top of the defaults) and applies them immediately.
+/// Returns the full list of supported extensions.
+pub fn set_extra_extensions(db: &DbManager, extras: &[String]) -> Result<Vec<String>, String> {
+    let normalized = extras
+        .iter()
+        .filter_map(|ext| normalize_extension(ext))
+        .filter(|ext| !DEFAULT_AUDIO_EXTENSIONS.contains(&ext.as_str()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let json = serde_json::to_string(&normalized)
+        .map_err(|e| format!("Failed to serialize audio extensions: {e}"))?;
+    db.set_setting(EXTRA_EXTENSIONS_SETTING, &json)?;
+    let registry = ExtensionRegistry::with_extras(&normalized);
+    let extensions = registry.extensions();
+    replace_registry(registry)?;
+    Ok(extensions)
+}
+
+fn replace_registry(new_registry: ExtensionRegistry) -> Result<(), String> {
+    *registry()
+        .write()
+        .map_err(|_| "Extension registry lock poisoned".to_string())? = new_registry;
+    Ok(())
+}
+
+/// Accepts `".MKA"`, `"mka"` or `" webm "`; rejects empty or non-alphanumeric values.
+fn normalize_extension(value: &str) -> Option<String> {
+    let ext = value.trim().trim_start_matches('.').to_ascii_lowercase();
+    (!ext.is_empty() && ext.chars().all(|ch| ch.is_ascii_alphanumeric())).then_some(ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_extension, ExtensionRegistry};
+    use std::path::Path;
+
+    #[test]
+    fn defaults_are_case_insensitive() {
+        let registry = ExtensionRegistry::default();
+        assert!(registry.is_supported_path(Path::new("/music/a.FLAC")));
+        assert!(registry.is_supported_path(Path::new("/music/b.mp3")));
+        assert!(registry.is_supported_path(Path::new("/music/alac.m4a")));
+        assert!(registry.is_supported_path(Path::new("/music/studio.AIFF")));
+        assert!(registry.is_supported_path(Path::new("/music/take.caf")));
+        assert!(!registry.is_supported_path(Path::new("/music/c.mka")));
+        assert!(!registry.is_supported_path(Path::new("/music/no_extension")));
+    }
+
+    #[test]
+    fn extras_extend_the_defaults() {
+        let registry = ExtensionRegistry::with_extras(&[".MKA".to_string(), "webm".to_string()]);
+        assert!(registry.is_supported_path(Path::new("/music/live.mka")));
+        assert!(registry.is_supported_path(Path::new("/music/clip.webm")));
+        assert!(registry.is_supported_path(Path::new("/music/a.flac")));
+    }
+
+    #[test]
+    fn invalid_extensions_are_rejected() {
+        assert_eq!(normalize_extension(" .Opus "), Some("opus".to_string()));
+        assert_eq!(normalize_extension(""), None);
+        assert_eq!(normalize_extension("../x"), None);
+        assert_eq!(normalize_extension("a b"), None);
+    }
+}
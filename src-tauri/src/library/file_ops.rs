@@ -0,0 +1,355 @@
+use crate::audio::lyrics::find_lrc_for_track;
+use crate::db::manager::DbManager;
+use crate::library::paths::canonical_path;
+use crate::library::scanner::register_library_watch;
+use crate::library::{cue, prune, waveform_cache};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Outcome of one file in a batch copy/move; failures do not abort the batch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileOperationOutcome {
+    pub source: PathBuf,
+    pub destination: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileOperation {
+    Copy,
+    Move,
+}
+
+/// Opens the platform file manager with `path` selected (or its folder opened).
+pub fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    let path = canonical_path(path);
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(&path);
+        command
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = {
+        // xdg-open has no "select" mode, so open the containing folder instead.
+        let folder = if path.is_dir() {
+            path.clone()
+        } else {
+            path.parent().map(Path::to_path_buf).unwrap_or(path.clone())
+        };
+        let mut command = Command::new("xdg-open");
+        command.arg(folder);
+        command
+    };
+
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open file manager for {}: {e}", path.display()))
+}
+
+pub fn copy_files(paths: &[PathBuf], dest: &Path, db: &DbManager) -> Vec<FileOperationOutcome> {
+    transfer_files(paths, dest, db, FileOperation::Copy)
+}
+
+pub fn move_files(paths: &[PathBuf], dest: &Path, db: &DbManager) -> Vec<FileOperationOutcome> {
+    transfer_files(paths, dest, db, FileOperation::Move)
+}
+
+fn transfer_files(
+    paths: &[PathBuf],
+    dest: &Path,
+    db: &DbManager,
+    operation: FileOperation,
+) -> Vec<FileOperationOutcome> {
+    if !dest.is_dir() {
+        let error = format!("Destination {} is not a folder", dest.display());
+        return paths
+            .iter()
+            .map(|source| FileOperationOutcome {
+                source: source.clone(),
+                destination: None,
+                error: Some(error.clone()),
+            })
+            .collect();
+    }
+
+    let dest = canonical_path(dest);
+    let mut touched_library = false;
+    let outcomes = paths
+        .iter()
+        .map(|source| match transfer_file(source, &dest, db, operation) {
+            Ok((destination, in_library)) => {
+                touched_library |= in_library;
+                FileOperationOutcome {
+                    source: source.clone(),
+                    destination: Some(destination),
+                    error: None,
+                }
+            }
+            Err(err) => FileOperationOutcome {
+                source: source.clone(),
+                destination: None,
+                error: Some(err),
+            },
+        })
+        .collect();
+
+    // Library tracks moved or copied outside the watched roots must stay monitored.
+    if touched_library {
+        if let Err(err) = register_library_watch(&dest, db) {
+            eprintln!("Failed to watch {}: {err}", dest.display());
+        }
+    }
+    outcomes
+}
+
+/// Transfers one file (plus its `.lrc` sidecar) and updates the library row. The DB is
+/// updated before the file system so the watcher's remove/create events for the move
+/// find the row already re-pointed; it is rolled back if the file operation fails.
+fn transfer_file(
+    source: &Path,
+    dest_dir: &Path,
+    db: &DbManager,
+    operation: FileOperation,
+) -> Result<(PathBuf, bool), String> {
+    if !source.is_file() {
+        return Err(format!("{} is not a file", source.display()));
+    }
+    let source = canonical_path(source);
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", source.display()))?;
+    let destination = dest_dir.join(file_name);
+    if destination == source {
+        return Err(format!(
+            "{} is already in the destination",
+            source.display()
+        ));
+    }
+    if destination.exists() {
+        return Err(format!("{} already exists", destination.display()));
+    }
+
+    let source_str = source.to_string_lossy();
+    let destination_str = destination.to_string_lossy();
+    let in_library = match operation {
+        FileOperation::Copy => db.duplicate_track(&source_str, &destination_str)?,
+        FileOperation::Move => db.rename_track_path(&source_str, &destination_str)?,
+    };
+
+    let result = match operation {
+        FileOperation::Copy => fs::copy(&source, &destination).map(|_| ()),
+        FileOperation::Move => move_file(&source, &destination),
+    };
+    if let Err(err) = result {
+        if in_library {
+            let rollback = match operation {
+                FileOperation::Copy => db.delete_track(&destination_str),
+                FileOperation::Move => db
+                    .rename_track_path(&destination_str, &source_str)
+                    .map(|_| ()),
+            };
+            if let Err(rollback_err) = rollback {
+                eprintln!(
+                    "Failed to roll back library entry for {}: {rollback_err}",
+                    source.display()
+                );
+            }
+        }
+        return Err(format!(
+            "Failed to transfer {} to {}: {err}",
+            source.display(),
+            destination.display()
+        ));
+    }
+
+    if let Some(lyrics) = find_lrc_for_track(&source) {
+        let lyrics_destination = destination.with_extension("lrc");
+        if !lyrics_destination.exists() {
+            let lyrics_result = match operation {
+                FileOperation::Copy => fs::copy(&lyrics, &lyrics_destination).map(|_| ()),
+                FileOperation::Move => move_file(&lyrics, &lyrics_destination),
+            };
+            if let Err(err) = lyrics_result {
+                eprintln!("Failed to transfer lyrics {}: {err}", lyrics.display());
+            }
+        }
+    }
+
+    Ok((destination, in_library))
+}
+
+/// Deletes the file of `path`, to the platform trash when `to_recycle_bin` is set, with
+/// its library row (which drops it from search) and its spatial scene, plugin chain and
+/// DSP assignment, then its cached cover, lyrics and waveform. The rows are deleted
+/// first, in a transaction only committed once the file is gone, so a file that cannot
+/// be removed keeps them. Stems are cached by the separator, so the caller removes
+/// those. Cue sheet songs share their file with the rest of the sheet and are refused.
+pub fn delete_track_file(path: &Path, to_recycle_bin: bool, db: &DbManager) -> Result<(), String> {
+    if cue::split_virtual_path(path).is_some() {
+        return Err(format!(
+            "{} is a cue sheet song; delete the sheet's audio file instead",
+            path.display()
+        ));
+    }
+    if !path.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    let path = canonical_path(path);
+    // Keyed by the file's size and modification time, so found before it goes.
+    let waveform = waveform_cache::cached_file(&path);
+    db.delete_track_with(&path.to_string_lossy(), || {
+        if to_recycle_bin {
+            trash::delete(&path)
+                .map_err(|e| format!("Failed to move {} to the trash: {e}", path.display()))
+        } else {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {e}", path.display()))
+        }
+    })?;
+    for cached in prune::cached_files(&path).into_iter().chain(waveform) {
+        let _ = fs::remove_file(cached);
+    }
+    Ok(())
+}
+
+/// `rename` only works within one volume; fall back to copy + delete across drives.
+fn move_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, destination)?;
+    fs::remove_file(source).inspect_err(|_| {
+        let _ = fs::remove_file(destination);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_files, delete_track_file, move_files};
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = unique_temp_path(&format!("fileops-{name}"));
+        std::fs::create_dir_all(&dir).expect("temp dir should be created");
+        super::canonical_path(&dir)
+    }
+
+    fn library_with_track(root: &std::path::Path) -> (DbManager, PathBuf) {
+        let track = root.join("song.flac");
+        std::fs::write(&track, b"audio").expect("track should be written");
+        std::fs::write(root.join("song.lrc"), b"[00:01.00] hi").expect("lyrics");
+        let db = DbManager::new(root.join("library.db")).expect("db should initialize");
+        db.save_track(&TrackInput {
+            path: track.to_string_lossy().to_string(),
+            title: Some("Song".to_string()),
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("track should be saved");
+        db.save_track_bpm(&track.to_string_lossy(), Some(90.0))
+            .expect("bpm should be saved");
+        (db, track)
+    }
+
+    #[test]
+    fn move_updates_library_row_and_sidecar() {
+        let root = temp_dir("move-src");
+        let dest = temp_dir("move-dst");
+        let (db, track) = library_with_track(&root);
+
+        let outcomes = move_files(std::slice::from_ref(&track), &dest, &db);
+        assert_eq!(outcomes[0].error, None);
+        let moved = dest.join("song.flac");
+        assert_eq!(outcomes[0].destination.as_ref(), Some(&moved));
+        assert!(!track.exists() && moved.exists());
+        assert!(dest.join("song.lrc").exists());
+
+        let tracks = db.get_tracks().expect("tracks should load");
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].path, moved.to_string_lossy());
+        assert_eq!(tracks[0].bpm, Some(90.0));
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(dest);
+    }
+
+    #[test]
+    fn copy_keeps_source_and_refuses_to_overwrite() {
+        let root = temp_dir("copy-src");
+        let dest = temp_dir("copy-dst");
+        let (db, track) = library_with_track(&root);
+
+        assert_eq!(
+            copy_files(std::slice::from_ref(&track), &dest, &db)[0].error,
+            None
+        );
+        assert!(track.exists() && dest.join("song.flac").exists());
+        assert_eq!(db.get_tracks().expect("tracks should load").len(), 2);
+
+        let again = copy_files(std::slice::from_ref(&track), &dest, &db);
+        assert!(again[0].error.is_some());
+        assert_eq!(db.get_tracks().expect("tracks should load").len(), 2);
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(dest);
+    }
+
+    #[test]
+    fn deleting_a_file_removes_its_row_scene_and_caches() {
+        let root = temp_dir("delete");
+        let (db, track) = library_with_track(&root);
+        db.initialize_fts().expect("fts should initialize");
+        db.initialize_spatial_schema()
+            .expect("spatial schema should initialize");
+        let track_path = track.to_string_lossy().to_string();
+        db.save_spatial_scene(&track_path, "vocals", 1.0, 0.0, 0.0, true)
+            .expect("scene should be saved");
+        let lyrics = crate::audio::lyrics_downloader::cached_lyrics_path(&track);
+        std::fs::create_dir_all(lyrics.parent().expect("cache dir")).expect("cache dir");
+        std::fs::write(&lyrics, b"[00:01.00] hi").expect("cached lyrics");
+        crate::library::waveform_cache::store(&track, &[0.5; 4]).expect("waveform");
+        let waveform =
+            crate::library::waveform_cache::cached_file(&track).expect("cached waveform");
+
+        delete_track_file(&track, false, &db).expect("delete should work");
+        assert!(!track.exists());
+        assert!(
+            root.join("song.lrc").exists(),
+            "sidecar lyrics are the user's"
+        );
+        assert!(db.get_tracks().expect("tracks").is_empty());
+        assert!(db.fast_search("Song").expect("search").tracks.is_empty());
+        assert!(db
+            .load_spatial_scene(&track_path)
+            .expect("scene")
+            .is_empty());
+        assert!(!lyrics.exists() && !waveform.exists());
+        assert!(delete_track_file(&track, false, &db).is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::manager::{DbManager, TrackRecord};
+use crate::library::exclusions;
+use crate::library::extensions::is_supported_audio_path;
+use crate::library::paths::canonical_path;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FolderEntry {
+    pub name: String,
+    pub path: String,
+    /// Library tracks anywhere below the folder.
+    pub track_count: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct FolderFile {
+    pub name: String,
+    pub path: String,
+    /// The file's library row; `None` for files not scanned (yet) or split by a cue
+    /// sheet.
+    pub track: Option<TrackRecord>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FolderListing {
+    /// `None` for the top level, which lists the library roots as folders.
+    pub path: Option<String>,
+    /// `None` at a library root and at the top level.
+    pub parent: Option<String>,
+    pub folders: Vec<FolderEntry>,
+    pub files: Vec<FolderFile>,
+}
+
+/// Lists the subfolders and audio files of `path`, which must be a library root or a
+/// folder below one, or the library roots when `path` is `None`. Paths the scan
+/// exclusions skip are left out.
+pub fn browse_folder(db: &DbManager, path: Option<&Path>) -> Result<FolderListing, String> {
+    let root_rows = db.get_library_roots()?;
+    let Some(path) = path else {
+        let folders = root_rows
+            .into_iter()
+            .map(|root| FolderEntry {
+                name: root.path.clone(),
+                path: root.path,
+                track_count: root.track_count,
+            })
+            .collect();
+        return Ok(FolderListing {
+            path: None,
+            parent: None,
+            folders,
+            files: Vec::new(),
+        });
+    };
+
+    let roots = root_rows
+        .into_iter()
+        .map(|root| PathBuf::from(root.path))
+        .collect::<Vec<_>>();
+    let folder = canonical_path(path);
+    let root = roots
+        .iter()
+        .filter(|root| folder.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .ok_or_else(|| format!("{} is not inside a library root", folder.display()))?;
+    let entries = fs::read_dir(&folder)
+        .map_err(|e| format!("Failed to read folder {}: {e}", folder.display()))?;
+
+    let folder_path = folder.to_string_lossy();
+    let tracks = db
+        .get_tracks_in_folder(&folder_path)?
+        .into_iter()
+        .map(|track| (track.path.clone(), track))
+        .collect::<HashMap<_, _>>();
+    let folder_counts = db.count_tracks_by_subfolder(&folder_path)?;
+
+    let mut folders = Vec::new();
+    let mut files = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let entry_path = entry.path();
+        if exclusions::is_excluded_path(root, &entry_path) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry_path.is_dir() {
+            folders.push(FolderEntry {
+                track_count: folder_counts.get(&name).copied().unwrap_or(0),
+                path: entry_path.to_string_lossy().into_owned(),
+                name,
+            });
+        } else if is_supported_audio_path(&entry_path) {
+            let path = canonical_path(&entry_path).to_string_lossy().into_owned();
+            files.push(FolderFile {
+                track: tracks.get(&path).cloned(),
+                path,
+                name,
+            });
+        }
+    }
+    folders.sort_by_key(|folder| folder.name.to_lowercase());
+    files.sort_by_key(|file| file.name.to_lowercase());
+
+    let parent = if &folder == root {
+        None
+    } else {
+        folder
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned())
+    };
+    Ok(FolderListing {
+        path: Some(folder.to_string_lossy().into_owned()),
+        parent,
+        folders,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::browse_folder;
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::library::paths::canonical_path;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn lists_folders_and_files_inside_library_roots_only() {
+        let dir = unique_temp_path("folders");
+        let music = dir.join("music");
+        std::fs::create_dir_all(music.join("Band").join("Album")).expect("create dirs");
+        let db = DbManager::new(dir.join("library.db")).expect("db init");
+        db.initialize_library_root_schema().expect("schema");
+        let music = canonical_path(&music);
+        db.add_library_root(&music.to_string_lossy())
+            .expect("add root");
+        let song = music.join("Band").join("Album").join("01 Song.flac");
+        std::fs::write(&song, b"x").expect("write track");
+        std::fs::write(music.join("Band").join("notes.txt"), b"x").expect("write notes");
+        std::fs::write(music.join("Band").join("demo.mp3"), b"x").expect("write demo");
+        db.save_track(&TrackInput {
+            path: song.to_string_lossy().into_owned(),
+            title: Some("Song".to_string()),
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save track");
+
+        let top = browse_folder(&db, None).expect("roots");
+        assert_eq!(top.folders.len(), 1);
+        assert_eq!(top.folders[0].track_count, 1);
+
+        let root = browse_folder(&db, Some(&music)).expect("root");
+        assert_eq!(
+            (root.folders[0].name.as_str(), root.folders[0].track_count),
+            ("Band", 1),
+            "tracks in nested folders are counted"
+        );
+        let band = browse_folder(&db, Some(&music.join("Band"))).expect("band");
+        assert_eq!(
+            band.parent.as_deref(),
+            Some(music.to_string_lossy().as_ref())
+        );
+        assert_eq!(band.folders.len(), 1);
+        assert_eq!(
+            (band.folders[0].name.as_str(), band.folders[0].track_count),
+            ("Album", 1)
+        );
+        assert_eq!(band.files.len(), 1, "only audio files are listed");
+        assert!(band.files[0].track.is_none(), "the demo was never scanned");
+
+        let album = browse_folder(&db, Some(&music.join("Band").join("Album"))).expect("album");
+        let track = album.files[0]
+            .track
+            .as_ref()
+            .expect("joined with the library row");
+        assert_eq!(track.title.as_deref(), Some("Song"));
+        assert!(browse_folder(&db, Some(&music))
+            .expect("root")
+            .parent
+            .is_none());
+        assert!(browse_folder(&db, Some(&dir)).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
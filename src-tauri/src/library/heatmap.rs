@@ -0,0 +1,233 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::db::manager::DbManager;
+use crate::library::paths::canonical_path_string;
+
+/// Number of equal-width sections a track is split into, whatever its length.
+pub const HEATMAP_BUCKETS: usize = 100;
+const POSITION_POLL_INTERVAL_MS: u64 = 500;
+/// A position sample further than this from where steady playback would have taken it
+/// is a seek (or gap skip): the section jumped over was skipped, not played.
+const JUMP_TOLERANCE_SECONDS: f64 = 1.5;
+/// Shorter spans (scrubbing, a quick preview) are not counted.
+const MIN_SPAN_SECONDS: f64 = 1.0;
+
+/// How often each section of a track was listened to. Every contiguous listening span
+/// adds one play to each bucket it covers, so replayed sections heat up and skipped
+/// ones stay cold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayHeatmap {
+    counts: Vec<u16>,
+}
+
+impl Default for PlayHeatmap {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; HEATMAP_BUCKETS],
+        }
+    }
+}
+
+impl PlayHeatmap {
+    /// Decodes the stored form, two little-endian bytes per bucket. Anything else is
+    /// treated as no history.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.len() != HEATMAP_BUCKETS * 2 {
+            return Self::default();
+        }
+        Self {
+            counts: bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.counts
+            .iter()
+            .flat_map(|count| count.to_le_bytes())
+            .collect()
+    }
+
+    pub fn counts(&self) -> &[u16] {
+        &self.counts
+    }
+
+    pub fn record_span(&mut self, start_seconds: f64, end_seconds: f64, duration_seconds: f64) {
+        if !duration_seconds.is_finite()
+            || duration_seconds <= 0.0
+            || end_seconds - start_seconds < MIN_SPAN_SECONDS
+        {
+            return;
+        }
+        let scaled =
+            |seconds: f64| (seconds / duration_seconds).clamp(0.0, 1.0) * HEATMAP_BUCKETS as f64;
+        let first = (scaled(start_seconds) as usize).min(HEATMAP_BUCKETS - 1);
+        // A span ending exactly on a boundary does not reach into the next bucket.
+        let last = (scaled(end_seconds).ceil() as usize)
+            .saturating_sub(1)
+            .clamp(first, HEATMAP_BUCKETS - 1);
+        for count in &mut self.counts[first..=last] {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    /// Counts scaled so the most played bucket is 1.0 (all zero when never played).
+    pub fn normalized(&self) -> Vec<f32> {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        self.counts
+            .iter()
+            .map(|count| *count as f32 / max)
+            .collect()
+    }
+}
+
+/// Where playback is right now, as seen by the position monitor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionSample {
+    pub path: PathBuf,
+    pub position_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+/// A stretch of a track that was listened to without interruption.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayedSpan {
+    pub path: PathBuf,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+/// Turns periodic position samples into played spans: a span ends on pause, seek,
+/// gap skip or track change.
+#[derive(Debug, Default)]
+pub struct PositionTracker {
+    open: Option<PlayedSpan>,
+}
+
+impl PositionTracker {
+    /// Feeds the sample taken `elapsed_seconds` after the previous one (`None` while
+    /// paused or stopped) and returns the span that just ended, if any.
+    pub fn observe(
+        &mut self,
+        sample: Option<PositionSample>,
+        elapsed_seconds: f64,
+    ) -> Option<PlayedSpan> {
+        if let (Some(open), Some(sample)) = (self.open.as_mut(), sample.as_ref()) {
+            let expected = open.end_seconds + elapsed_seconds;
+            if open.path == sample.path
+                && (sample.position_seconds - expected).abs() <= JUMP_TOLERANCE_SECONDS
+            {
+                open.end_seconds = sample.position_seconds;
+                return None;
+            }
+        }
+        let ended = self.open.take();
+        self.open = sample.map(|sample| PlayedSpan {
+            path: sample.path,
+            start_seconds: sample.position_seconds,
+            end_seconds: sample.position_seconds,
+            duration_seconds: sample.duration_seconds,
+        });
+        ended
+    }
+}
+
+/// Adds a played span to its track's heatmap. Files outside the library are ignored.
+pub fn record_played_span(db: &DbManager, span: &PlayedSpan) -> Result<(), String> {
+    let path = canonical_path_string(&span.path.to_string_lossy());
+    let mut heatmap = load_heatmap(db, &path)?;
+    heatmap.record_span(span.start_seconds, span.end_seconds, span.duration_seconds);
+    db.save_play_heatmap(&path, &heatmap.to_bytes()).map(|_| ())
+}
+
+pub fn load_heatmap(db: &DbManager, path: &str) -> Result<PlayHeatmap, String> {
+    Ok(db
+        .get_play_heatmap(path)?
+        .map(|bytes| PlayHeatmap::from_bytes(&bytes))
+        .unwrap_or_default())
+}
+
+/// Polls `sample_position` twice a second for the life of the app and records every
+/// played span in the library.
+pub fn start_position_monitor(
+    db: DbManager,
+    sample_position: impl Fn() -> Option<PositionSample> + Send + 'static,
+) {
+    thread::spawn(move || {
+        let mut tracker = PositionTracker::default();
+        let mut last_poll = Instant::now();
+        loop {
+            thread::sleep(Duration::from_millis(POSITION_POLL_INTERVAL_MS));
+            let elapsed = last_poll.elapsed().as_secs_f64();
+            last_poll = Instant::now();
+            if let Some(span) = tracker.observe(sample_position(), elapsed) {
+                if let Err(err) = record_played_span(&db, &span) {
+                    eprintln!("Failed to record play heatmap: {err}");
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlayHeatmap, PositionSample, PositionTracker, HEATMAP_BUCKETS};
+    use std::path::PathBuf;
+
+    #[test]
+    fn spans_heat_the_buckets_they_cover() {
+        let mut heatmap = PlayHeatmap::default();
+        heatmap.record_span(0.0, 200.0, 200.0);
+        heatmap.record_span(50.0, 60.0, 200.0);
+        heatmap.record_span(10.0, 10.5, 200.0);
+        let counts = heatmap.counts();
+        assert_eq!(counts[0], 1);
+        assert_eq!(counts[24], 1);
+        assert_eq!(counts[25], 2);
+        assert_eq!(counts[29], 2);
+        assert_eq!(counts[30], 1);
+        assert_eq!(counts[HEATMAP_BUCKETS - 1], 1);
+        assert_eq!(heatmap.normalized()[27], 1.0);
+
+        let decoded = PlayHeatmap::from_bytes(&heatmap.to_bytes());
+        assert_eq!(decoded, heatmap);
+        assert_eq!(PlayHeatmap::from_bytes(&[1, 2, 3]), PlayHeatmap::default());
+    }
+
+    #[test]
+    fn tracker_splits_spans_on_seeks_pauses_and_track_changes() {
+        let sample = |path: &str, position_seconds: f64| {
+            Some(PositionSample {
+                path: PathBuf::from(path),
+                position_seconds,
+                duration_seconds: 300.0,
+            })
+        };
+        let mut tracker = PositionTracker::default();
+        assert_eq!(tracker.observe(sample("/a.flac", 0.0), 0.5), None);
+        assert_eq!(tracker.observe(sample("/a.flac", 0.5), 0.5), None);
+        assert_eq!(tracker.observe(sample("/a.flac", 1.0), 0.5), None);
+
+        // Seek forward: 1.0..120.0 was skipped.
+        let played = tracker
+            .observe(sample("/a.flac", 120.0), 0.5)
+            .expect("seek ends the span");
+        assert_eq!((played.start_seconds, played.end_seconds), (0.0, 1.0));
+
+        assert_eq!(tracker.observe(sample("/a.flac", 120.5), 0.5), None);
+        let played = tracker.observe(None, 0.5).expect("pause ends the span");
+        assert_eq!((played.start_seconds, played.end_seconds), (120.0, 120.5));
+        assert_eq!(tracker.observe(None, 0.5), None);
+
+        assert_eq!(tracker.observe(sample("/a.flac", 120.5), 3.0), None);
+        let played = tracker
+            .observe(sample("/b.flac", 0.2), 0.5)
+            .expect("track change ends the span");
+        assert_eq!(played.path, PathBuf::from("/a.flac"));
+    }
+}
@@ -0,0 +1,103 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::audio::engine::EndedPlay;
+use crate::db::manager::DbManager;
+
+const HISTORY_POLL_INTERVAL_MS: u64 = 1_000;
+/// Plays shorter than this (skipping through the queue, a quick preview) are not
+/// recorded.
+const MIN_LISTENED_SECONDS: f64 = 1.0;
+/// A play adds to the track's play count once half of it, or four minutes, was heard.
+const COUNTED_COMPLETION_PERCENT: f64 = 50.0;
+const COUNTED_LISTENED_SECONDS: f64 = 240.0;
+
+/// Share of the track heard in `play`, in percent; a replayed section can push the
+/// listening time past the duration, so it is capped at 100.
+fn completion_percent(play: &EndedPlay) -> f64 {
+    if play.duration_seconds <= 0.0 {
+        return if play.reached_end { 100.0 } else { 0.0 };
+    }
+    (play.listened_seconds / play.duration_seconds * 100.0).min(100.0)
+}
+
+fn record_play(db: &DbManager, play: &EndedPlay) -> Result<(), String> {
+    if play.listened_seconds < MIN_LISTENED_SECONDS {
+        return Ok(());
+    }
+    let path = play.path.to_string_lossy();
+    let completion = completion_percent(play);
+    db.add_play_history(&path, completion)?;
+    if completion >= COUNTED_COMPLETION_PERCENT || play.listened_seconds >= COUNTED_LISTENED_SECONDS
+    {
+        db.record_track_play(&path)?;
+    }
+    Ok(())
+}
+
+/// Collects the engine's ended plays every second for the life of the app and records
+/// them in the play history.
+pub fn start_history_recorder(
+    db: DbManager,
+    take_ended_plays: impl Fn() -> Vec<EndedPlay> + Send + 'static,
+) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(HISTORY_POLL_INTERVAL_MS));
+        for play in take_ended_plays() {
+            if let Err(err) = record_play(&db, &play) {
+                eprintln!("Failed to record play history: {err}");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completion_percent, record_play};
+    use crate::audio::engine::EndedPlay;
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::test_support::unique_temp_path;
+    use std::path::PathBuf;
+
+    #[test]
+    fn plays_are_recorded_with_their_completion() {
+        let play = |listened_seconds: f64, duration_seconds: f64, reached_end: bool| EndedPlay {
+            path: PathBuf::from("/music/song.flac"),
+            listened_seconds,
+            duration_seconds,
+            reached_end,
+        };
+        assert_eq!(completion_percent(&play(50.0, 200.0, false)), 25.0);
+        assert_eq!(completion_percent(&play(260.0, 200.0, true)), 100.0);
+        assert_eq!(completion_percent(&play(5.0, 0.0, true)), 100.0);
+
+        let db = DbManager::new(unique_temp_path("history-record.db")).expect("db init");
+        db.initialize_history_schema().expect("schema");
+        db.save_track(&TrackInput {
+            path: "/music/song.flac".to_string(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: Some(200.0),
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save track");
+        record_play(&db, &play(0.4, 200.0, false)).expect("record");
+        record_play(&db, &play(150.0, 200.0, false)).expect("record");
+        record_play(&db, &play(30.0, 200.0, false)).expect("record");
+        let history = db.get_play_history(10).expect("history");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].completion_percent, 75.0);
+        // Only the play past the halfway mark counts.
+        let tracks = db.get_tracks().expect("tracks");
+        assert_eq!(tracks[0].play_count, 1);
+    }
+}
@@ -0,0 +1,313 @@
+use plist::{Dictionary, Value};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::db::manager::DbManager;
+use crate::library::playlist_files::{resolve_entry, unused_playlist_name};
+use crate::library::scanner;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LibraryImport {
+    /// Tracks of the export matched to a file or library track.
+    pub matched: usize,
+    /// Matched files that were not in the library yet and were scanned into it.
+    pub added: usize,
+    pub playlists: usize,
+    /// Locations that matched neither a file on disk nor a library track.
+    pub missing: Vec<String>,
+}
+
+/// An entry of the export's `Tracks` dictionary.
+struct ExportedTrack {
+    location: String,
+    play_count: u32,
+    /// UTC, as `YYYY-MM-DD HH:MM:SS` like the library's own timestamps.
+    last_played_at: Option<String>,
+    rating: Option<u8>,
+}
+
+/// Imports an iTunes `Library.xml` (also written by MusicBee's iTunes-compatible
+/// export): matches its track locations to files, scanning those not yet in the
+/// library, merges their play counts, last plays and ratings, and recreates its user
+/// playlists, numbered when a name is taken. Locations from another machine are matched
+/// to the library track sharing the longest tail of folders, so import after scanning
+/// the music's new location.
+pub fn import_itunes_library(db: &DbManager, xml_path: &Path) -> Result<LibraryImport, String> {
+    let root = Value::from_file(xml_path)
+        .map_err(|e| format!("Failed to read {}: {e}", xml_path.display()))?;
+    let root = root
+        .as_dictionary()
+        .ok_or_else(|| format!("{} is not an iTunes library", xml_path.display()))?;
+    let exported = root
+        .get("Tracks")
+        .and_then(Value::as_dictionary)
+        .map(|tracks| {
+            tracks
+                .iter()
+                .filter_map(|(id, track)| Some((id.clone(), read_track(track.as_dictionary()?)?)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let base_dir = xml_path.parent().unwrap_or(Path::new(""));
+    let mut report = LibraryImport::default();
+    let mut paths = HashMap::<String, String>::new();
+    let mut new_files = Vec::new();
+    for (id, track) in &exported {
+        match resolve_entry(&track.location, base_dir, |name| {
+            db.get_track_paths_named(name)
+        })? {
+            Some(path) => {
+                if db.get_track(&path)?.is_none() && !new_files.contains(&PathBuf::from(&path)) {
+                    new_files.push(PathBuf::from(&path));
+                }
+                paths.insert(id.clone(), path);
+            }
+            None => report.missing.push(track.location.clone()),
+        }
+    }
+    scanner::index_files(&new_files, db);
+
+    for (id, track) in &exported {
+        let Some(path) = paths.get(id) else {
+            continue;
+        };
+        if db.merge_imported_stats(
+            path,
+            track.play_count,
+            track.last_played_at.as_deref(),
+            track.rating,
+        )? {
+            report.matched += 1;
+            if new_files.contains(&PathBuf::from(path)) {
+                report.added += 1;
+            }
+        }
+    }
+
+    for playlist in root
+        .get("Playlists")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_dictionary)
+        .filter(|playlist| is_user_playlist(playlist))
+    {
+        let Some(name) = playlist
+            .get("Name")
+            .and_then(Value::as_string)
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        else {
+            continue;
+        };
+        let tracks = playlist
+            .get("Playlist Items")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let id = item.as_dictionary()?.get("Track ID")?.as_signed_integer()?;
+                paths.get(&id.to_string()).cloned()
+            })
+            .collect::<Vec<_>>();
+        let id = db.create_playlist(&unused_playlist_name(db, name)?)?;
+        if let Err(err) = db.set_playlist_tracks(id, &tracks) {
+            let _ = db.delete_playlist(id);
+            return Err(err);
+        }
+        report.playlists += 1;
+    }
+    Ok(report)
+}
+
+fn read_track(track: &Dictionary) -> Option<ExportedTrack> {
+    // Streams and cloud-only tracks have no location, or a web one.
+    let location = track.get("Location")?.as_string()?;
+    if !location.to_ascii_lowercase().starts_with("file://") {
+        return None;
+    }
+    let computed = track
+        .get("Rating Computed")
+        .and_then(Value::as_boolean)
+        .unwrap_or(false);
+    let rating = track
+        .get("Rating")
+        .and_then(Value::as_signed_integer)
+        .filter(|_| !computed)
+        // 0 to 100, 20 per star.
+        .map(|rating| ((rating + 10) / 20).clamp(0, 5) as u8)
+        .filter(|stars| *stars > 0);
+    Some(ExportedTrack {
+        location: location.to_string(),
+        play_count: track
+            .get("Play Count")
+            .and_then(Value::as_signed_integer)
+            .map_or(0, |count| count.clamp(0, i64::from(u32::MAX)) as u32),
+        last_played_at: track
+            .get("Play Date UTC")
+            .and_then(Value::as_date)
+            .map(|date| {
+                // `2024-05-01T18:30:00Z`
+                date.to_xml_format()
+                    .replace('T', " ")
+                    .trim_end_matches('Z')
+                    .to_string()
+            }),
+        rating,
+    })
+}
+
+/// Whether a playlist was made by the user, rather than being the whole library, a
+/// built-in one such as Music or Podcasts, or a folder of playlists.
+fn is_user_playlist(playlist: &Dictionary) -> bool {
+    let flag = |key: &str| {
+        playlist
+            .get(key)
+            .and_then(Value::as_boolean)
+            .unwrap_or(false)
+    };
+    !flag("Master")
+        && !flag("Folder")
+        && playlist.get("Distinguished Kind").is_none()
+        && playlist
+            .get("Visible")
+            .and_then(Value::as_boolean)
+            .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::import_itunes_library;
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::library::paths::canonical_path_string;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn imports_stats_and_playlists_from_an_itunes_library() {
+        let dir = unique_temp_path("itunes");
+        let music = dir.join("Music").join("Band");
+        std::fs::create_dir_all(&music).expect("create dirs");
+        let db = DbManager::new(dir.join("library.db")).expect("db init");
+        db.initialize_playlist_schema().expect("schema");
+        // Already in the library under its new location; iTunes knew it elsewhere.
+        let moved = canonical_path_string(&music.join("Moved Song.mp3").to_string_lossy());
+        db.save_track(&TrackInput {
+            path: moved.clone(),
+            title: None,
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save track");
+        db.set_track_rating(&moved, Some(2)).expect("rate");
+        // On disk but not scanned yet.
+        let fresh = music.join("New Song.mp3");
+        std::fs::write(&fresh, b"not really audio").expect("write track");
+        let fresh_url = format!(
+            "file://localhost{}{}",
+            if cfg!(windows) { "/" } else { "" },
+            fresh
+                .to_string_lossy()
+                .replace('\\', "/")
+                .replace(' ', "%20")
+        );
+
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Tracks</key>
+    <dict>
+        <key>101</key>
+        <dict>
+            <key>Track ID</key><integer>101</integer>
+            <key>Rating</key><integer>100</integer>
+            <key>Play Count</key><integer>12</integer>
+            <key>Play Date UTC</key><date>2024-05-01T18:30:00Z</date>
+            <key>Location</key><string>file://localhost/Volumes/Old/Music/Band/Moved%20Song.mp3</string>
+        </dict>
+        <key>102</key>
+        <dict>
+            <key>Track ID</key><integer>102</integer>
+            <key>Rating</key><integer>80</integer>
+            <key>Play Count</key><integer>3</integer>
+            <key>Location</key><string>{fresh_url}</string>
+        </dict>
+        <key>103</key>
+        <dict>
+            <key>Track ID</key><integer>103</integer>
+            <key>Location</key><string>file://localhost/Volumes/Old/Elsewhere/Gone.m4a</string>
+        </dict>
+        <key>104</key>
+        <dict>
+            <key>Track ID</key><integer>104</integer>
+            <key>Location</key><string>https://example.com/stream</string>
+        </dict>
+    </dict>
+    <key>Playlists</key>
+    <array>
+        <dict>
+            <key>Name</key><string>Library</string>
+            <key>Master</key><true/>
+            <key>Playlist Items</key>
+            <array><dict><key>Track ID</key><integer>101</integer></dict></array>
+        </dict>
+        <dict>
+            <key>Name</key><string>Music</string>
+            <key>Distinguished Kind</key><integer>4</integer>
+        </dict>
+        <dict>
+            <key>Name</key><string>Road Trip</string>
+            <key>Playlist Items</key>
+            <array>
+                <dict><key>Track ID</key><integer>102</integer></dict>
+                <dict><key>Track ID</key><integer>103</integer></dict>
+                <dict><key>Track ID</key><integer>101</integer></dict>
+            </array>
+        </dict>
+    </array>
+</dict>
+</plist>"#
+        );
+        let xml_path = dir.join("Library.xml");
+        std::fs::write(&xml_path, xml).expect("write library");
+
+        let report = import_itunes_library(&db, &xml_path).expect("import");
+        assert_eq!((report.matched, report.added, report.playlists), (2, 1, 1));
+        assert_eq!(report.missing.len(), 1);
+        assert!(report.missing[0].ends_with("Gone.m4a"));
+
+        let tracks = db.get_tracks().expect("tracks");
+        let moved_row = tracks.iter().find(|t| t.path == moved).expect("moved");
+        assert_eq!(moved_row.play_count, 12);
+        assert_eq!(
+            moved_row.last_played_at.as_deref(),
+            Some("2024-05-01 18:30:00")
+        );
+        assert_eq!(moved_row.rating, Some(2), "the library's own rating wins");
+        let fresh = canonical_path_string(&fresh.to_string_lossy());
+        let fresh_row = tracks.iter().find(|t| t.path == fresh).expect("scanned");
+        assert_eq!((fresh_row.play_count, fresh_row.rating), (3, Some(4)));
+
+        let playlists = db.get_playlists().expect("playlists");
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(playlists[0].name, "Road Trip");
+        assert_eq!(
+            db.get_playlist_tracks(playlists[0].id).expect("entries"),
+            [fresh, moved]
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::audio::analyzer::AudioFingerprint;
+use crate::credentials;
+use crate::db::manager::{MusicBrainzIds, TrackInput};
+use crate::library::network_usage::{self, NetworkProvider};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const ACOUSTID_LOOKUP_URL: &str = "https://api.acoustid.org/v2/lookup";
+/// Keychain entry holding the AcoustID application key; without one, fingerprints are
+/// still computed and stored but never looked up.
+const ACOUSTID_CREDENTIAL: &str = "acoustid";
+/// AcoustID allows three requests per second per application.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(334);
+/// Matches scoring lower may be a different recording that merely sounds alike.
+const MIN_MATCH_SCORE: f64 = 0.9;
+
+/// Recording a fingerprint was matched to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AcoustIdMatch {
+    pub ids: MusicBrainzIds,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+/// Looks `fingerprint` up on AcoustID. Returns `None` without an AcoustID key, or when
+/// no recording matches confidently enough.
+pub fn lookup(
+    fingerprint: &AudioFingerprint,
+    track: &TrackInput,
+) -> Result<Option<AcoustIdMatch>, String> {
+    let Some(key) = credentials::load_credential(ACOUSTID_CREDENTIAL)? else {
+        return Ok(None);
+    };
+    if fingerprint.duration_seconds == 0 {
+        return Ok(None);
+    }
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent("PowerPlayer/0.1 (https://github.com/El-EnderJ/PowerPlayer)")
+        .build()
+        .map_err(|e| format!("Failed to build AcoustID HTTP client: {e}"))?;
+
+    let duration = fingerprint.duration_seconds.to_string();
+    wait_for_turn()?;
+    // Fingerprints run to a few kilobytes, too long for a query string.
+    let body = client
+        .post(ACOUSTID_LOOKUP_URL)
+        .form(&[
+            ("client", key.as_str()),
+            ("meta", "recordings releases"),
+            ("format", "json"),
+            ("duration", duration.as_str()),
+            ("fingerprint", fingerprint.fingerprint.as_str()),
+        ])
+        .send()
+        .and_then(|response| response.bytes())
+        .map_err(|e| format!("AcoustID request failed: {e}"))?;
+    network_usage::record(NetworkProvider::Metadata, body.len() as u64);
+    let response = serde_json::from_slice::<LookupResponse>(&body)
+        .map_err(|e| format!("Invalid AcoustID response: {e}"))?;
+    if let Some(error) = response.error {
+        return Err(format!("AcoustID lookup failed: {}", error.message));
+    }
+    Ok(best_match(response.results, track.album.as_deref()))
+}
+
+/// Copies the matched artist and title into `track` where it has none, or where they
+/// were only guessed from the file name. Returns whether anything changed.
+pub fn apply_match(track: &mut TrackInput, found: &AcoustIdMatch) -> bool {
+    let guessed = guessed_from_file_name(track);
+    let mut changed = false;
+    for (field, value) in [
+        (&mut track.title, &found.title),
+        (&mut track.artist, &found.artist),
+    ] {
+        let replace = guessed || field.as_deref().is_none_or(|v| v.trim().is_empty());
+        if replace && value.is_some() && field != value {
+            field.clone_from(value);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Whether the scanner fell back to the file name for the title, as `Title` or
+/// `Artist - Title`, because the file carries no tags of its own.
+fn guessed_from_file_name(track: &TrackInput) -> bool {
+    let Some(stem) = Path::new(&track.path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+    else {
+        return false;
+    };
+    match (track.artist.as_deref(), track.title.as_deref()) {
+        (_, None) => true,
+        (None, Some(title)) => title == stem,
+        (Some(artist), Some(title)) => title == stem || format!("{artist} - {title}") == stem,
+    }
+}
+
+/// Keeps lookups `REQUEST_INTERVAL` apart.
+fn wait_for_turn() -> Result<(), String> {
+    static LAST_REQUEST: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+    let mut last_request = LAST_REQUEST
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .map_err(|_| "AcoustID rate limiter lock poisoned".to_string())?;
+    if let Some(wait) = last_request.map(|last| REQUEST_INTERVAL.saturating_sub(last.elapsed())) {
+        std::thread::sleep(wait);
+    }
+    *last_request = Some(Instant::now());
+    Ok(())
+}
+
+/// The first recording with metadata of the best result scoring at least
+/// `MIN_MATCH_SCORE`. Its release is only kept when it is the track's album.
+fn best_match(results: Vec<LookupResult>, album: Option<&str>) -> Option<AcoustIdMatch> {
+    let recording = results
+        .into_iter()
+        .filter(|result| result.score >= MIN_MATCH_SCORE)
+        .max_by(|a, b| a.score.total_cmp(&b.score))?
+        .recordings
+        .into_iter()
+        .find(|recording| recording.title.is_some())?;
+    let artist = (!recording.artists.is_empty()).then(|| {
+        recording
+            .artists
+            .iter()
+            .map(|artist| format!("{}{}", artist.name, artist.joinphrase))
+            .collect::<String>()
+    });
+    let release_id = album.and_then(|album| {
+        recording
+            .releases
+            .iter()
+            .find(|release| {
+                release
+                    .title
+                    .as_deref()
+                    .is_some_and(|title| title.trim().eq_ignore_ascii_case(album.trim()))
+            })
+            .map(|release| release.id.clone())
+    });
+    Some(AcoustIdMatch {
+        ids: MusicBrainzIds {
+            recording_id: Some(recording.id),
+            release_id,
+            artist_id: recording.artists.first().map(|artist| artist.id.clone()),
+        },
+        title: recording.title,
+        artist,
+    })
+}
+
+#[derive(Deserialize)]
+struct LookupResponse {
+    #[serde(default)]
+    results: Vec<LookupResult>,
+    error: Option<LookupError>,
+}
+
+#[derive(Deserialize)]
+struct LookupError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct LookupResult {
+    score: f64,
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    id: String,
+    title: Option<String>,
+    #[serde(default)]
+    artists: Vec<Artist>,
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct Artist {
+    id: String,
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    id: String,
+    title: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_match, best_match, LookupResponse};
+    use crate::db::manager::TrackInput;
+
+    #[test]
+    fn picks_a_confident_match_and_replaces_file_name_guesses() {
+        let response = serde_json::from_str::<LookupResponse>(
+            r#"{"status": "ok", "results": [
+                {"id": "weak", "score": 0.5, "recordings": [{"id": "rec-weak", "title": "Other"}]},
+                {"id": "strong", "score": 0.97, "recordings": [
+                    {"id": "rec-bare"},
+                    {"id": "rec-1", "title": "Song",
+                     "artists": [{"id": "art-1", "name": "Band", "joinphrase": " feat. "},
+                                 {"id": "art-2", "name": "Guest"}],
+                     "releases": [{"id": "rel-best", "title": "Best Of"},
+                                  {"id": "rel-1", "title": "First Album"}]}
+                ]}
+            ]}"#,
+        )
+        .expect("parse response");
+        let found = best_match(response.results, Some("first album")).expect("match");
+        assert_eq!(found.ids.recording_id.as_deref(), Some("rec-1"));
+        assert_eq!(found.ids.release_id.as_deref(), Some("rel-1"));
+        assert_eq!(found.ids.artist_id.as_deref(), Some("art-1"));
+        assert_eq!(found.artist.as_deref(), Some("Band feat. Guest"));
+        assert!(best_match(Vec::new(), None).is_none());
+
+        let mut track = TrackInput {
+            path: "/music/track01.mp3".to_string(),
+            title: Some("track01".to_string()),
+            artist: None,
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        };
+        assert!(apply_match(&mut track, &found));
+        assert_eq!(
+            (track.artist.as_deref(), track.title.as_deref()),
+            (Some("Band feat. Guest"), Some("Song"))
+        );
+
+        let mut tagged = TrackInput {
+            path: "/music/track02.mp3".to_string(),
+            title: Some("Live Version".to_string()),
+            artist: None,
+            ..track
+        };
+        assert!(apply_match(&mut tagged, &found));
+        assert_eq!(
+            (tagged.artist.as_deref(), tagged.title.as_deref()),
+            (Some("Band feat. Guest"), Some("Live Version")),
+            "tagged titles are kept"
+        );
+        assert!(!apply_match(&mut tagged, &found));
+    }
+}
@@ -0,0 +1,273 @@
+use id3::frame::{Picture, PictureType};
+use id3::TagLike;
+use image::ImageFormat;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::db::manager::DbManager;
+
+/// Settings key for the opt-in "embed downloaded cover art into files" behaviour.
+const EMBED_FETCHED_ART_SETTING: &str = "embed_fetched_art";
+
+const FLAC_MARKER: &[u8; 4] = b"fLaC";
+pub(super) const FLAC_PICTURE_BLOCK: u8 = 6;
+const FLAC_LAST_BLOCK_FLAG: u8 = 0x80;
+/// Front cover, in both the ID3 APIC and FLAC PICTURE numbering.
+const FRONT_COVER: u32 = 3;
+
+pub fn embed_fetched_art_enabled(db: &DbManager) -> Result<bool, String> {
+    Ok(db
+        .get_setting(EMBED_FETCHED_ART_SETTING)?
+        .is_some_and(|value| value == "true"))
+}
+
+pub fn set_embed_fetched_art(db: &DbManager, enabled: bool) -> Result<(), String> {
+    db.set_setting(
+        EMBED_FETCHED_ART_SETTING,
+        if enabled { "true" } else { "false" },
+    )
+}
+
+/// Writes `image` into the file's tags as the front cover. Returns `false` without
+/// touching the file when it already has embedded art or its format is not writable
+/// (FLAC, MP3, WAV and AIFF are).
+pub fn embed_cover_art(path: &Path, image: &[u8]) -> Result<bool, String> {
+    let mime_type = cover_mime_type(image)?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "flac" => embed_flac_picture(path, image, mime_type),
+        "mp3" | "wav" | "aif" | "aiff" | "aifc" => embed_id3_picture(path, image, mime_type),
+        _ => Ok(false),
+    }
+}
+
+/// MIME type of JPEG or PNG cover art; other image formats are refused.
+pub(super) fn cover_mime_type(image: &[u8]) -> Result<&'static str, String> {
+    let format =
+        image::guess_format(image).map_err(|e| format!("Unrecognized cover art format: {e}"))?;
+    match format {
+        ImageFormat::Jpeg => Ok("image/jpeg"),
+        ImageFormat::Png => Ok("image/png"),
+        other => Err(format!("Unsupported cover art format: {other:?}")),
+    }
+}
+
+/// `id3` detects the container itself: a tag at the start of MP3 files, or in the
+/// `ID3 ` chunk of WAV and AIFF files.
+fn embed_id3_picture(path: &Path, image: &[u8], mime_type: &str) -> Result<bool, String> {
+    let mut tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(err) if matches!(err.kind, id3::ErrorKind::NoTag) => id3::Tag::new(),
+        // Never replace a tag we failed to parse: that would drop its other frames.
+        Err(err) => return Err(format!("Failed to read tags of {}: {err}", path.display())),
+    };
+    if tag.pictures().next().is_some() {
+        return Ok(false);
+    }
+
+    tag.add_frame(Picture {
+        mime_type: mime_type.to_string(),
+        picture_type: PictureType::CoverFront,
+        description: String::new(),
+        data: image.to_vec(),
+    });
+    tag.write_to_path(path, tag.version())
+        .map(|()| true)
+        .map_err(|e| format!("Failed to write cover art to {}: {e}", path.display()))
+}
+
+/// Inserts a PICTURE metadata block after the existing ones.
+fn embed_flac_picture(path: &Path, image: &[u8], mime_type: &str) -> Result<bool, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let (blocks, audio_offset) = flac_metadata_blocks(&data, path)?;
+    if blocks
+        .iter()
+        .any(|(block_type, _)| *block_type == FLAC_PICTURE_BLOCK)
+    {
+        return Ok(false);
+    }
+
+    let picture = flac_picture_block(image, mime_type)?;
+    let mut blocks = blocks
+        .into_iter()
+        .map(|(block_type, range)| (block_type, &data[range]))
+        .collect::<Vec<_>>();
+    blocks.push((FLAC_PICTURE_BLOCK, &picture));
+    write_flac_file(path, &blocks, &data[audio_offset..])
+        .map(|()| true)
+        .map_err(|e| format!("Failed to write cover art to {}: {e}", path.display()))
+}
+
+/// Type and byte range of a FLAC metadata block.
+pub(super) type FlacBlock = (u8, Range<usize>);
+
+/// The metadata blocks of a FLAC file, and the offset where its audio frames start.
+pub(super) fn flac_metadata_blocks(
+    data: &[u8],
+    path: &Path,
+) -> Result<(Vec<FlacBlock>, usize), String> {
+    if data.get(..4) != Some(FLAC_MARKER.as_slice()) {
+        return Err(format!("{} is not a FLAC file", path.display()));
+    }
+
+    let mut blocks = Vec::new();
+    let mut offset = 4;
+    loop {
+        let header = data
+            .get(offset..offset + 4)
+            .ok_or_else(|| format!("Truncated FLAC metadata in {}", path.display()))?;
+        let block_type = header[0] & !FLAC_LAST_BLOCK_FLAG;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let end = offset + 4 + length;
+        if end > data.len() {
+            return Err(format!("Truncated FLAC metadata in {}", path.display()));
+        }
+        blocks.push((block_type, offset + 4..end));
+        offset = end;
+        if header[0] & FLAC_LAST_BLOCK_FLAG != 0 {
+            return Ok((blocks, offset));
+        }
+    }
+}
+
+/// Writes a FLAC file from its metadata blocks, in order, and audio frames.
+pub(super) fn write_flac_file(
+    path: &Path,
+    blocks: &[(u8, &[u8])],
+    audio: &[u8],
+) -> std::io::Result<()> {
+    replace_file(path, |out| {
+        out.write_all(FLAC_MARKER)?;
+        for (index, (block_type, block)) in blocks.iter().enumerate() {
+            let last = index + 1 == blocks.len();
+            write_flac_block_header(out, *block_type, last, block.len())?;
+            out.write_all(block)?;
+        }
+        out.write_all(audio)
+    })
+}
+
+/// Rewrites `path` through `write`. The new contents go to a file next to the original
+/// that is renamed over it, so a failure never leaves the original truncated.
+pub(super) fn replace_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let extension = path
+        .extension()
+        .map(|ext| format!("{}.part", ext.to_string_lossy()))
+        .unwrap_or_else(|| "part".to_string());
+    let temp_path = path.with_extension(extension);
+    let result = File::create(&temp_path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        write(&mut out)?;
+        out.into_inner()?.sync_all()
+    });
+    let result = result.and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_flac_block_header(
+    out: &mut impl Write,
+    block_type: u8,
+    last: bool,
+    length: usize,
+) -> std::io::Result<()> {
+    let flag = if last { FLAC_LAST_BLOCK_FLAG } else { 0 };
+    let length = (length as u32).to_be_bytes();
+    out.write_all(&[block_type | flag, length[1], length[2], length[3]])
+}
+
+pub(super) fn flac_picture_block(image: &[u8], mime_type: &str) -> Result<Vec<u8>, String> {
+    let decoded =
+        image::load_from_memory(image).map_err(|e| format!("Failed to decode cover art: {e}"))?;
+    // Block lengths are 24-bit; leave room for the fixed fields.
+    if image.len() + mime_type.len() + 32 >= 1 << 24 {
+        return Err("Cover art is too large to embed in FLAC".to_string());
+    }
+    let mut block = Vec::with_capacity(image.len() + 64);
+    block.extend_from_slice(&FRONT_COVER.to_be_bytes());
+    block.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    block.extend_from_slice(mime_type.as_bytes());
+    block.extend_from_slice(&0_u32.to_be_bytes()); // empty description
+    block.extend_from_slice(&decoded.width().to_be_bytes());
+    block.extend_from_slice(&decoded.height().to_be_bytes());
+    block.extend_from_slice(&(decoded.color().bits_per_pixel() as u32).to_be_bytes());
+    block.extend_from_slice(&0_u32.to_be_bytes()); // not an indexed-colour image
+    block.extend_from_slice(&(image.len() as u32).to_be_bytes());
+    block.extend_from_slice(image);
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::embed_cover_art;
+    use crate::test_support::unique_temp_path;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = unique_temp_path(&format!("embed-{name}"));
+        std::fs::write(&path, bytes).expect("test file should be written");
+        path
+    }
+
+    fn png_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbImage::new(4, 3)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )
+            .expect("png should encode");
+        bytes
+    }
+
+    #[test]
+    fn flac_gets_a_picture_block_and_keeps_its_frames() {
+        let mut flac = b"fLaC".to_vec();
+        flac.extend_from_slice(&[0x80, 0, 0, 34]); // last block: STREAMINFO
+        flac.extend_from_slice(&[0x11; 34]);
+        flac.extend_from_slice(b"FRAMES");
+        let path = temp_file("song.flac", &flac);
+        let png = png_bytes();
+
+        assert!(embed_cover_art(&path, &png).expect("embed should succeed"));
+        let written = std::fs::read(&path).expect("flac should be readable");
+        assert_eq!(written[4], 0x00, "STREAMINFO is no longer the last block");
+        assert_eq!(written[42], 0x86, "PICTURE is the last block");
+        assert!(written.ends_with(b"FRAMES"));
+        assert!(written
+            .windows(png.len())
+            .any(|window| window == png.as_slice()));
+
+        assert!(!embed_cover_art(&path, &png).expect("second embed should be a no-op"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn mp3_gets_an_apic_frame_and_other_formats_are_skipped() {
+        let path = temp_file("song.mp3", &[0xFF, 0xFB, 0x90, 0x00]);
+        assert!(embed_cover_art(&path, &png_bytes()).expect("embed should succeed"));
+        let tag = id3::Tag::read_from_path(&path).expect("tag should be written");
+        let picture = tag.pictures().next().expect("picture should exist");
+        assert_eq!(picture.mime_type, "image/png");
+        assert!(!embed_cover_art(&path, &png_bytes()).expect("second embed is a no-op"));
+
+        let ogg = temp_file("song.ogg", b"OggS");
+        assert!(!embed_cover_art(&ogg, &png_bytes()).expect("ogg is skipped"));
+        assert!(embed_cover_art(&ogg, b"not an image").is_err());
+
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(ogg);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::library::art_cache;
-use reqwest::blocking::Client;
+use crate::library::network_usage::{self, NetworkProvider};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -15,17 +17,29 @@ pub fn find_local_cover(track_path: &Path) -> Option<PathBuf> {
         .find(|path| path.is_file())
 }
 
+/// Result of [`fetch_and_cache_art`].
+#[derive(Default)]
+pub struct FetchedArt {
+    /// Asset URL of the cached thumbnail.
+    pub art_url: Option<String>,
+    /// Full-size image when it was downloaded rather than found next to the track.
+    pub downloaded: Option<Vec<u8>>,
+}
+
 pub fn fetch_and_cache_art(
     track_path: &Path,
     artist: Option<&str>,
     title: Option<&str>,
-) -> Result<Option<String>, String> {
+) -> Result<FetchedArt, String> {
     if let Some(local_cover) = find_local_cover(track_path) {
-        return art_cache::cache_cover_file(track_path, &local_cover);
+        return Ok(FetchedArt {
+            art_url: art_cache::cache_cover_file(track_path, &local_cover)?,
+            downloaded: None,
+        });
     }
 
     let Some(title) = title.filter(|value| !value.trim().is_empty()) else {
-        return Ok(None);
+        return Ok(FetchedArt::default());
     };
 
     let client = Client::builder()
@@ -34,14 +48,15 @@ pub fn fetch_and_cache_art(
         .build()
         .map_err(|e| format!("Failed to build art HTTP client: {e}"))?;
 
-    if let Some(bytes) = fetch_from_itunes(&client, artist, title) {
-        return art_cache::cache_cover_bytes(track_path, &bytes);
-    }
-    if let Some(bytes) = fetch_from_musicbrainz(&client, artist, title) {
-        return art_cache::cache_cover_bytes(track_path, &bytes);
-    }
-
-    Ok(None)
+    let Some(bytes) = fetch_from_itunes(&client, artist, title)
+        .or_else(|| fetch_from_musicbrainz(&client, artist, title))
+    else {
+        return Ok(FetchedArt::default());
+    };
+    Ok(FetchedArt {
+        art_url: art_cache::cache_cover_bytes(track_path, &bytes)?,
+        downloaded: Some(bytes),
+    })
 }
 
 fn fetch_from_itunes(client: &Client, artist: Option<&str>, title: &str) -> Option<Vec<u8>> {
@@ -49,27 +64,16 @@ fn fetch_from_itunes(client: &Client, artist: Option<&str>, title: &str) -> Opti
         .filter(|value| !value.trim().is_empty())
         .map(|name| format!("{name} {title}"))
         .unwrap_or_else(|| title.to_string());
-    let response = client
-        .get(ITUNES_SEARCH_URL)
-        .query(&[
-            ("term", term),
-            ("entity", "song".to_string()),
-            ("limit", "1".to_string()),
-        ])
-        .send()
-        .ok()?;
-    let data: ItunesSearchResponse = response.json().ok()?;
+    let data: ItunesSearchResponse = download_json(client.get(ITUNES_SEARCH_URL).query(&[
+        ("term", term),
+        ("entity", "song".to_string()),
+        ("limit", "1".to_string()),
+    ]))?;
     let artwork_url = data
         .results
         .into_iter()
         .find_map(|item| item.artwork_url100.or(item.artwork_url60))?;
-    client
-        .get(artwork_url)
-        .send()
-        .ok()?
-        .bytes()
-        .ok()
-        .map(|b| b.to_vec())
+    download(client.get(artwork_url))
 }
 
 fn fetch_from_musicbrainz(client: &Client, artist: Option<&str>, title: &str) -> Option<Vec<u8>> {
@@ -77,16 +81,12 @@ fn fetch_from_musicbrainz(client: &Client, artist: Option<&str>, title: &str) ->
     if let Some(artist) = artist.filter(|value| !value.trim().is_empty()) {
         query.push_str(&format!(" AND artist:\"{artist}\""));
     }
-    let search = client
-        .get(MUSICBRAINZ_SEARCH_URL)
-        .query(&[
+    let data: MusicBrainzSearchResponse =
+        download_json(client.get(MUSICBRAINZ_SEARCH_URL).query(&[
             ("query", query),
             ("fmt", "json".to_string()),
             ("limit", "1".to_string()),
-        ])
-        .send()
-        .ok()?;
-    let data: MusicBrainzSearchResponse = search.json().ok()?;
+        ]))?;
     let release_id = data.recordings.into_iter().find_map(|recording| {
         recording
             .releases
@@ -94,13 +94,18 @@ fn fetch_from_musicbrainz(client: &Client, artist: Option<&str>, title: &str) ->
             .map(|release| release.id)
     })?;
     let cover_url = format!("https://coverartarchive.org/release/{release_id}/front-500");
-    client
-        .get(cover_url)
-        .send()
-        .ok()?
-        .bytes()
-        .ok()
-        .map(|b| b.to_vec())
+    download(client.get(cover_url))
+}
+
+/// Sends `request` and returns the body, counting it towards the art provider's usage.
+fn download(request: RequestBuilder) -> Option<Vec<u8>> {
+    let body = request.send().ok()?.bytes().ok()?;
+    network_usage::record(NetworkProvider::Art, body.len() as u64);
+    Some(body.to_vec())
+}
+
+fn download_json<T: DeserializeOwned>(request: RequestBuilder) -> Option<T> {
+    serde_json::from_slice(&download(request)?).ok()
 }
 
 #[derive(Deserialize)]
@@ -136,15 +141,11 @@ struct MusicBrainzRelease {
 #[cfg(test)]
 mod tests {
     use super::find_local_cover;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::test_support::unique_temp_path;
 
     #[test]
     fn detects_folder_cover_without_network() {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        let dir = std::env::temp_dir().join(format!("powerplayer-art-{nanos}"));
+        let dir = unique_temp_path("art");
         std::fs::create_dir_all(&dir).expect("test folder should exist");
         let track_path = dir.join("track.flac");
         std::fs::write(&track_path, b"audio").expect("dummy track should be created");
@@ -0,0 +1,254 @@
+use serde::Serialize;
+use std::path::Path;
+
+use super::tag_writer::{check_writable, write_tags, TagFields};
+use crate::db::manager::{DbManager, TrackRecord};
+use crate::library::scanner;
+
+/// One tag a batch edit changes, with the library's current value.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TagChange {
+    pub field: &'static str,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// What a batch edit would do to one file. `error` is set for files whose tags cannot
+/// be written.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchTagPreview {
+    pub path: String,
+    pub changes: Vec<TagChange>,
+    pub error: Option<String>,
+}
+
+/// Payload of the per-file progress events.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchTagProgress {
+    pub path: String,
+    pub done: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BatchTagSummary {
+    pub written: usize,
+    /// `(path, error)` of each file that was not written.
+    pub failed: Vec<(String, String)>,
+}
+
+/// The fields written to the `index`th of the selected files: the shared `fields`,
+/// numbered from 1 in selection order when `number_tracks` is set.
+fn fields_for(fields: &TagFields, index: usize, number_tracks: bool) -> TagFields {
+    let mut fields = fields.clone();
+    if number_tracks {
+        fields.track_number = u32::try_from(index + 1).ok();
+    }
+    fields
+}
+
+/// Dry run of `write_batch`: the tags each file would change, compared with the
+/// library's current values, without touching any file.
+pub fn preview_batch(
+    db: &DbManager,
+    paths: &[String],
+    fields: &TagFields,
+    number_tracks: bool,
+) -> Result<Vec<BatchTagPreview>, String> {
+    paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let fields = fields_for(fields, index, number_tracks);
+            let record = db.get_track(path)?;
+            let current = |get: fn(&TrackRecord) -> Option<String>| record.as_ref().and_then(get);
+            let candidates = [
+                (
+                    "title",
+                    fields.title.is_some(),
+                    current(|track| track.title.clone()),
+                    text(&fields.title),
+                ),
+                (
+                    "artist",
+                    fields.artist.is_some(),
+                    current(|track| track.artist.clone()),
+                    text(&fields.artist),
+                ),
+                (
+                    "album",
+                    fields.album.is_some(),
+                    current(|track| track.album.clone()),
+                    text(&fields.album),
+                ),
+                (
+                    "genre",
+                    fields.genre.is_some(),
+                    current(|track| track.genre.clone()),
+                    text(&fields.genre),
+                ),
+                (
+                    "year",
+                    fields.year.is_some(),
+                    current(|track| number(track.year)),
+                    number(fields.year),
+                ),
+                (
+                    "track_number",
+                    fields.track_number.is_some(),
+                    current(|track| number(track.track_number)),
+                    number(fields.track_number),
+                ),
+                // The current cover is a cached thumbnail, so a new image always counts
+                // as a change.
+                (
+                    "artwork",
+                    fields.artwork_path.is_some(),
+                    current(|track| track.art_url.clone()),
+                    text(&fields.artwork_path),
+                ),
+            ];
+            let changes = candidates
+                .into_iter()
+                .filter(|(field, written, from, to)| {
+                    *written && (from != to || *field == "artwork")
+                })
+                .map(|(field, _, from, to)| TagChange { field, from, to })
+                .collect();
+            Ok(BatchTagPreview {
+                path: path.clone(),
+                changes,
+                error: check_writable(Path::new(path)).err(),
+            })
+        })
+        .collect()
+}
+
+fn text(value: &Option<String>) -> Option<String> {
+    value.clone().filter(|value| !value.is_empty())
+}
+
+fn number(value: Option<u32>) -> Option<String> {
+    value
+        .filter(|value| *value > 0)
+        .map(|value| value.to_string())
+}
+
+/// Writes `fields` into each of `paths` in turn and re-reads it into the library,
+/// reporting every file to `on_progress`. A file that fails is skipped, not fatal.
+pub fn write_batch(
+    db: &DbManager,
+    paths: &[String],
+    fields: &TagFields,
+    number_tracks: bool,
+    mut on_progress: impl FnMut(BatchTagProgress),
+) -> BatchTagSummary {
+    let mut summary = BatchTagSummary::default();
+    for (index, path) in paths.iter().enumerate() {
+        let file = Path::new(path);
+        let result = write_tags(file, &fields_for(fields, index, number_tracks))
+            .and_then(|()| scanner::rescan_audio_file(file, db));
+        match &result {
+            Ok(()) => summary.written += 1,
+            Err(err) => summary.failed.push((path.clone(), err.clone())),
+        }
+        on_progress(BatchTagProgress {
+            path: path.clone(),
+            done: index + 1,
+            total: paths.len(),
+            error: result.err(),
+        });
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preview_batch, write_batch, TagChange};
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::library::metadata::tag_writer::TagFields;
+    use crate::library::paths::canonical_path_string;
+    use crate::test_support::unique_temp_path;
+    use id3::TagLike;
+
+    #[test]
+    fn batches_preview_then_write_shared_fields_and_numbers() {
+        let dir = unique_temp_path("batch-tags");
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let db = DbManager::new(dir.join("library.db")).expect("db init");
+        let mut paths = Vec::new();
+        for name in ["b.mp3", "a.mp3"] {
+            let path = dir.join(name);
+            std::fs::write(&path, [0xFF, 0xFB, 0x90, 0x00]).expect("write track");
+            let path = canonical_path_string(&path.to_string_lossy());
+            db.save_track(&TrackInput {
+                path: path.clone(),
+                title: Some(name.to_string()),
+                artist: None,
+                album: Some("Old".to_string()),
+                genre: None,
+                year: None,
+                track_number: Some(7),
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+            paths.push(path);
+        }
+        paths.push(dir.join("c.wma").to_string_lossy().into_owned());
+        let fields = TagFields {
+            album: Some("New".to_string()),
+            artist: Some("Band".to_string()),
+            ..TagFields::default()
+        };
+
+        let preview = preview_batch(&db, &paths, &fields, true).expect("preview");
+        assert_eq!(
+            preview[1].changes,
+            [
+                TagChange {
+                    field: "artist",
+                    from: None,
+                    to: Some("Band".to_string()),
+                },
+                TagChange {
+                    field: "album",
+                    from: Some("Old".to_string()),
+                    to: Some("New".to_string()),
+                },
+                TagChange {
+                    field: "track_number",
+                    from: Some("7".to_string()),
+                    to: Some("2".to_string()),
+                },
+            ]
+        );
+        assert!(preview[2].error.is_some(), "wma tags are not writable");
+        assert!(
+            id3::Tag::read_from_path(&paths[0]).is_err(),
+            "the preview leaves files alone"
+        );
+
+        let mut progress = Vec::new();
+        let summary = write_batch(&db, &paths, &fields, true, |event| progress.push(event));
+        assert_eq!((summary.written, summary.failed.len()), (2, 1));
+        assert_eq!(progress.len(), 3);
+        assert_eq!((progress[2].done, progress[2].total), (3, 3));
+        for (index, path) in paths[..2].iter().enumerate() {
+            let tag = id3::Tag::read_from_path(path).expect("tag should be written");
+            assert_eq!(tag.album(), Some("New"));
+            assert_eq!(tag.track(), Some(index as u32 + 1));
+        }
+        let tracks = db.get_tracks().expect("tracks");
+        assert!(tracks
+            .iter()
+            .all(|track| track.album.as_deref() == Some("New")));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
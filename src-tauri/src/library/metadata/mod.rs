@@ -1 +1,6 @@
+pub mod acoustid;
+pub mod art_embedder;
 pub mod art_fetcher;
+pub mod batch_tagger;
+pub mod musicbrainz;
+pub mod tag_writer;
@@ -0,0 +1,336 @@
+use crate::db::manager::{MusicBrainzIds, TrackInput};
+use crate::library::network_usage::{self, NetworkProvider};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const MUSICBRAINZ_RECORDING_URL: &str = "https://musicbrainz.org/ws/2/recording";
+/// MusicBrainz asks clients to stay under one request per second.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+/// Search results scoring lower are too loose to copy tags from.
+const MIN_SEARCH_SCORE: u32 = 90;
+
+/// Tags and identifiers of the recording a track was matched to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MusicBrainzMatch {
+    pub ids: MusicBrainzIds,
+    pub album: Option<String>,
+    pub year: Option<u32>,
+    pub track_number: Option<u32>,
+    pub genre: Option<String>,
+}
+
+/// Whether `track` has a title to search for and lacks any tag MusicBrainz can fill.
+pub fn needs_enrichment(track: &TrackInput) -> bool {
+    let blank = |value: &Option<String>| value.as_deref().is_none_or(|v| v.trim().is_empty());
+    !blank(&track.title)
+        && (blank(&track.album)
+            || blank(&track.genre)
+            || track.year.is_none()
+            || track.track_number.is_none())
+}
+
+/// Looks `track` up on MusicBrainz: directly by recording when `known` holds the IDs
+/// of an earlier match, otherwise by searching its title, artist and album.
+pub fn lookup(
+    track: &TrackInput,
+    known: Option<&MusicBrainzIds>,
+) -> Result<Option<MusicBrainzMatch>, String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(5))
+        .user_agent("PowerPlayer/0.1 (https://github.com/El-EnderJ/PowerPlayer)")
+        .build()
+        .map_err(|e| format!("Failed to build MusicBrainz HTTP client: {e}"))?;
+    let album = track
+        .album
+        .as_deref()
+        .filter(|value| !value.trim().is_empty());
+
+    if let Some(recording_id) = known.and_then(|ids| ids.recording_id.as_deref()) {
+        let url = format!("{MUSICBRAINZ_RECORDING_URL}/{recording_id}");
+        let body = get(
+            &client,
+            &url,
+            &[
+                ("inc", "releases+media+artist-credits+tags"),
+                ("fmt", "json"),
+            ],
+        )?;
+        let recording = serde_json::from_slice::<Recording>(&body)
+            .map_err(|e| format!("Invalid MusicBrainz recording {recording_id}: {e}"))?;
+        let release_id = known.and_then(|ids| ids.release_id.as_deref());
+        return Ok(Some(recording.to_match(release_id, album)));
+    }
+
+    let Some(title) = track
+        .title
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    let mut query = format!("recording:\"{}\"", escape_query(title));
+    if let Some(artist) = track
+        .artist
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        query.push_str(&format!(" AND artist:\"{}\"", escape_query(artist)));
+    }
+    if let Some(album) = album {
+        query.push_str(&format!(" AND release:\"{}\"", escape_query(album)));
+    }
+    let body = get(
+        &client,
+        MUSICBRAINZ_RECORDING_URL,
+        &[("query", &query), ("fmt", "json"), ("limit", "5")],
+    )?;
+    let response = serde_json::from_slice::<SearchResponse>(&body)
+        .map_err(|e| format!("Invalid MusicBrainz search response: {e}"))?;
+    Ok(best_match(response, album))
+}
+
+/// Copies the matched tags into the fields `track` leaves empty; tags already set are
+/// kept. Returns whether anything was filled in.
+pub fn fill_missing(track: &mut TrackInput, found: &MusicBrainzMatch) -> bool {
+    let mut changed = false;
+    for (field, value) in [
+        (&mut track.album, &found.album),
+        (&mut track.genre, &found.genre),
+    ] {
+        if field.as_deref().is_none_or(|v| v.trim().is_empty()) && value.is_some() {
+            field.clone_from(value);
+            changed = true;
+        }
+    }
+    for (field, value) in [
+        (&mut track.year, found.year),
+        (&mut track.track_number, found.track_number),
+    ] {
+        if field.is_none() && value.is_some() {
+            *field = value;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Sends a GET request, waiting first so requests stay `REQUEST_INTERVAL` apart.
+fn get(client: &Client, url: &str, query: &[(&str, &str)]) -> Result<Vec<u8>, String> {
+    static LAST_REQUEST: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+    let mut last_request = LAST_REQUEST
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .map_err(|_| "MusicBrainz rate limiter lock poisoned".to_string())?;
+    if let Some(wait) = last_request.map(|last| REQUEST_INTERVAL.saturating_sub(last.elapsed())) {
+        std::thread::sleep(wait);
+    }
+    *last_request = Some(Instant::now());
+    drop(last_request);
+
+    let response = client
+        .get(url)
+        .query(query)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("MusicBrainz request failed: {e}"))?;
+    let body = response
+        .bytes()
+        .map_err(|e| format!("Failed to read MusicBrainz response: {e}"))?;
+    network_usage::record(NetworkProvider::Metadata, body.len() as u64);
+    Ok(body.to_vec())
+}
+
+/// Escapes the characters Lucene treats specially inside a quoted phrase.
+fn escape_query(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn best_match(response: SearchResponse, album: Option<&str>) -> Option<MusicBrainzMatch> {
+    response
+        .recordings
+        .into_iter()
+        .find(|recording| recording.score.unwrap_or(0) >= MIN_SEARCH_SCORE)
+        .map(|recording| recording.to_match(None, album))
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    id: String,
+    score: Option<u32>,
+    #[serde(default, rename = "artist-credit")]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(rename = "first-release-date")]
+    first_release_date: Option<String>,
+    #[serde(default)]
+    releases: Vec<Release>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+impl Recording {
+    /// Tags of this recording on one of its releases: `release_id` when known, else
+    /// the release named `album`, else the first one listed.
+    fn to_match(&self, release_id: Option<&str>, album: Option<&str>) -> MusicBrainzMatch {
+        let release = release_id
+            .and_then(|id| self.releases.iter().find(|release| release.id == id))
+            .or_else(|| {
+                let album = album?;
+                self.releases.iter().find(|release| {
+                    release
+                        .title
+                        .as_deref()
+                        .is_some_and(|title| title.eq_ignore_ascii_case(album))
+                })
+            })
+            .or_else(|| self.releases.first());
+        let date = release
+            .and_then(|release| release.date.as_deref())
+            .filter(|date| !date.is_empty())
+            .or(self.first_release_date.as_deref());
+        MusicBrainzMatch {
+            ids: MusicBrainzIds {
+                recording_id: Some(self.id.clone()),
+                release_id: release.map(|release| release.id.clone()),
+                artist_id: self
+                    .artist_credit
+                    .first()
+                    .map(|credit| credit.artist.id.clone()),
+            },
+            album: release.and_then(|release| release.title.clone()),
+            year: date
+                .and_then(|date| date.get(..4))
+                .and_then(|year| year.parse().ok()),
+            track_number: release
+                .and_then(|release| release.media.first())
+                .and_then(|medium| medium.track.first())
+                .and_then(|track| track.number.as_deref())
+                .and_then(|number| number.parse().ok()),
+            genre: self
+                .tags
+                .iter()
+                .filter(|tag| tag.count > 0)
+                .max_by_key(|tag| tag.count)
+                .map(|tag| title_case(&tag.name)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ArtistCredit {
+    artist: Artist,
+}
+
+#[derive(Deserialize)]
+struct Artist {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    id: String,
+    title: Option<String>,
+    date: Option<String>,
+    #[serde(default)]
+    media: Vec<Medium>,
+}
+
+#[derive(Deserialize)]
+struct Medium {
+    /// Only the recording's own track; lookups call the list `tracks`.
+    #[serde(default, alias = "tracks")]
+    track: Vec<MediumTrack>,
+}
+
+#[derive(Deserialize)]
+struct MediumTrack {
+    number: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    #[serde(default)]
+    count: i64,
+    name: String,
+}
+
+/// MusicBrainz tags are lowercase (`"alternative rock"`); library genres are not.
+fn title_case(value: &str) -> String {
+    value
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best_match, fill_missing, needs_enrichment, SearchResponse};
+    use crate::db::manager::TrackInput;
+
+    #[test]
+    fn search_results_fill_only_missing_tags() {
+        let response: SearchResponse = serde_json::from_str(
+            r#"{"recordings": [
+                {"id": "weak", "score": 60, "title": "Song"},
+                {"id": "rec-1", "score": 100, "title": "Song",
+                 "artist-credit": [{"name": "Band", "artist": {"id": "artist-1"}}],
+                 "first-release-date": "1991-09-24",
+                 "releases": [
+                    {"id": "single", "title": "Song", "date": "1991-08-01",
+                     "media": [{"position": 1, "track": [{"number": "1"}]}]},
+                    {"id": "album", "title": "The Album", "date": "1991-09-24",
+                     "media": [{"position": 1, "track": [{"number": "4"}]}]}
+                 ],
+                 "tags": [{"count": 2, "name": "grunge"},
+                          {"count": 5, "name": "alternative rock"}]}
+            ]}"#,
+        )
+        .expect("parse search response");
+        let found = best_match(response, Some("the album")).expect("a strong match");
+        assert_eq!(found.ids.recording_id.as_deref(), Some("rec-1"));
+        assert_eq!(found.ids.release_id.as_deref(), Some("album"));
+        assert_eq!(found.ids.artist_id.as_deref(), Some("artist-1"));
+        assert_eq!(
+            (found.year, found.track_number, found.genre.as_deref()),
+            (Some(1991), Some(4), Some("Alternative Rock"))
+        );
+
+        let mut track = TrackInput {
+            path: "/music/song.mp3".to_string(),
+            title: Some("Song".to_string()),
+            artist: Some("Band".to_string()),
+            album: Some("the album".to_string()),
+            genre: None,
+            year: None,
+            track_number: Some(9),
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: None,
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        };
+        assert!(needs_enrichment(&track));
+        assert!(fill_missing(&mut track, &found));
+        assert_eq!(track.album.as_deref(), Some("the album"));
+        assert_eq!(track.genre.as_deref(), Some("Alternative Rock"));
+        assert_eq!((track.year, track.track_number), (Some(1991), Some(9)));
+        assert!(!needs_enrichment(&track));
+        assert!(!fill_missing(&mut track, &found));
+    }
+}
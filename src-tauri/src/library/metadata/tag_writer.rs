@@ -0,0 +1,785 @@
+use base64::Engine;
+use id3::frame::{Picture, PictureType, Timestamp};
+use id3::TagLike;
+use ogg::{PacketReader, PacketWriteEndInfo, PacketWriter};
+use serde::Deserialize;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use super::art_embedder::{
+    cover_mime_type, flac_metadata_blocks, flac_picture_block, replace_file, write_flac_file,
+    FLAC_PICTURE_BLOCK,
+};
+use crate::library::art_cache;
+
+const FLAC_VORBIS_COMMENT_BLOCK: u8 = 4;
+/// Ogg Vorbis and Opus cover art: a base64 FLAC PICTURE block.
+const VORBIS_PICTURE_KEY: &str = "METADATA_BLOCK_PICTURE";
+/// Well-known data types of MP4 `ilst` values.
+const MP4_IMPLICIT: u32 = 0;
+const MP4_UTF8: u32 = 1;
+const MP4_JPEG: u32 = 13;
+const MP4_PNG: u32 = 14;
+
+/// Tag values to write into a file. Fields left `None` keep their current value; an
+/// empty string, or 0 for numbers, removes the tag.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TagFields {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    pub track_number: Option<u32>,
+    /// Image file (JPEG or PNG) to embed as the only cover art.
+    pub artwork_path: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum TagFormat {
+    Id3,
+    Flac,
+    Mp4,
+    Ogg,
+}
+
+/// The tag format written for `path`, from its extension, or an error naming the
+/// unsupported extension.
+fn tag_format(path: &Path) -> Result<TagFormat, String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp3" | "wav" | "aif" | "aiff" | "aifc" => Ok(TagFormat::Id3),
+        "flac" => Ok(TagFormat::Flac),
+        "m4a" | "m4b" | "mp4" | "alac" => Ok(TagFormat::Mp4),
+        "ogg" | "oga" | "opus" => Ok(TagFormat::Ogg),
+        _ => Err(format!(
+            "Writing tags to .{extension} files is not supported: {}",
+            path.display()
+        )),
+    }
+}
+
+/// Checks that tags can be written to `path` without touching it.
+pub fn check_writable(path: &Path) -> Result<(), String> {
+    tag_format(path).map(|_| ())
+}
+
+enum ArtworkChange {
+    Keep,
+    Remove,
+    Replace {
+        image: Vec<u8>,
+        mime_type: &'static str,
+    },
+}
+
+/// Writes `fields` into the tags of `path`: ID3 for MP3, WAV and AIFF, Vorbis comments
+/// for FLAC, Ogg Vorbis and Opus, and `ilst` atoms for MP4/M4A. Other tags in the file
+/// are kept.
+pub fn write_tags(path: &Path, fields: &TagFields) -> Result<(), String> {
+    let format = tag_format(path)?;
+    let artwork = match fields.artwork_path.as_deref() {
+        None => ArtworkChange::Keep,
+        Some("") => ArtworkChange::Remove,
+        Some(art_path) => {
+            let image = fs::read(art_path)
+                .map_err(|e| format!("Failed to read cover art file {art_path}: {e}"))?;
+            let mime_type = cover_mime_type(&image)?;
+            ArtworkChange::Replace { image, mime_type }
+        }
+    };
+    match format {
+        TagFormat::Id3 => write_id3_tags(path, fields, &artwork)?,
+        TagFormat::Flac => write_flac_tags(path, fields, &artwork)?,
+        TagFormat::Mp4 => write_mp4_tags(path, fields, &artwork)?,
+        TagFormat::Ogg => write_ogg_tags(path, fields, &artwork)?,
+    }
+    // The scanner keeps the thumbnail it already cached for the file.
+    if !matches!(artwork, ArtworkChange::Keep) {
+        if let Some(thumbnail) = art_cache::cached_thumbnail(path) {
+            let _ = fs::remove_file(thumbnail);
+        }
+    }
+    Ok(())
+}
+
+fn write_id3_tags(path: &Path, fields: &TagFields, artwork: &ArtworkChange) -> Result<(), String> {
+    let mut tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(err) if matches!(err.kind, id3::ErrorKind::NoTag) => id3::Tag::new(),
+        // Never replace a tag we failed to parse: that would drop its other frames.
+        Err(err) => return Err(format!("Failed to read tags of {}: {err}", path.display())),
+    };
+
+    for (id, value) in [
+        ("TIT2", &fields.title),
+        ("TPE1", &fields.artist),
+        ("TALB", &fields.album),
+        ("TCON", &fields.genre),
+    ] {
+        if let Some(value) = value {
+            tag.remove(id);
+            if !value.is_empty() {
+                tag.set_text(id, value.as_str());
+            }
+        }
+    }
+    if let Some(year) = fields.year {
+        tag.remove_year();
+        tag.remove_date_recorded();
+        // ID3v2.4 replaced TYER with the recording date.
+        match year {
+            0 => {}
+            _ if tag.version() == id3::Version::Id3v24 => tag.set_date_recorded(Timestamp {
+                year: year as i32,
+                month: None,
+                day: None,
+                hour: None,
+                minute: None,
+                second: None,
+            }),
+            _ => tag.set_year(year as i32),
+        }
+    }
+    match fields.track_number {
+        Some(0) => tag.remove_track(),
+        Some(track) => tag.set_track(track),
+        None => {}
+    }
+    match artwork {
+        ArtworkChange::Keep => {}
+        ArtworkChange::Remove => tag.remove_all_pictures(),
+        ArtworkChange::Replace { image, mime_type } => {
+            tag.remove_all_pictures();
+            tag.add_frame(Picture {
+                mime_type: mime_type.to_string(),
+                picture_type: PictureType::CoverFront,
+                description: String::new(),
+                data: image.clone(),
+            });
+        }
+    }
+    tag.write_to_path(path, tag.version())
+        .map_err(|e| format!("Failed to write tags to {}: {e}", path.display()))
+}
+
+/// Replaces the fields in the VORBIS_COMMENT block, adding one after STREAMINFO when
+/// the file has none, and the PICTURE blocks when the artwork changes.
+fn write_flac_tags(path: &Path, fields: &TagFields, artwork: &ArtworkChange) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let (ranges, audio_offset) = flac_metadata_blocks(&data, path)?;
+    let mut blocks = ranges
+        .into_iter()
+        .map(|(block_type, range)| (block_type, data[range].to_vec()))
+        .collect::<Vec<_>>();
+
+    let existing = blocks
+        .iter()
+        .position(|(block_type, _)| *block_type == FLAC_VORBIS_COMMENT_BLOCK);
+    let (vendor, mut comments) = match existing {
+        Some(index) => parse_vorbis_comments(&blocks[index].1)
+            .ok_or_else(|| format!("Malformed Vorbis comments in {}", path.display()))?,
+        None => ("PowerPlayer".to_string(), Vec::new()),
+    };
+    set_vorbis_fields(&mut comments, fields);
+    let comment_block = vorbis_comment_block(&vendor, &comments);
+    match existing {
+        Some(index) => blocks[index].1 = comment_block,
+        None => blocks.insert(
+            1.min(blocks.len()),
+            (FLAC_VORBIS_COMMENT_BLOCK, comment_block),
+        ),
+    }
+
+    match artwork {
+        ArtworkChange::Keep => {}
+        ArtworkChange::Remove => blocks.retain(|(block_type, _)| *block_type != FLAC_PICTURE_BLOCK),
+        ArtworkChange::Replace { image, mime_type } => {
+            let picture = flac_picture_block(image, mime_type)?;
+            blocks.retain(|(block_type, _)| *block_type != FLAC_PICTURE_BLOCK);
+            blocks.push((FLAC_PICTURE_BLOCK, picture));
+        }
+    }
+
+    let blocks = blocks
+        .iter()
+        .map(|(block_type, block)| (*block_type, block.as_slice()))
+        .collect::<Vec<_>>();
+    write_flac_file(path, &blocks, &data[audio_offset..])
+        .map_err(|e| format!("Failed to write tags to {}: {e}", path.display()))
+}
+
+/// Rewrites the comment header of the first Ogg stream, a Vorbis or Opus one, keeping
+/// every other packet and its page layout. Cover art goes in a `METADATA_BLOCK_PICTURE`
+/// comment.
+fn write_ogg_tags(path: &Path, fields: &TagFields, artwork: &ArtworkChange) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let read_error =
+        |e: ogg::OggReadError| format!("Malformed Ogg stream in {}: {e}", path.display());
+    let mut reader = PacketReader::new(Cursor::new(&data));
+    let mut packets = Vec::new();
+    while let Some(packet) = reader.read_packet().map_err(read_error)? {
+        packets.push(packet);
+    }
+
+    let serial = packets
+        .first()
+        .map(|packet| packet.stream_serial())
+        .ok_or_else(|| format!("No Ogg stream in {}", path.display()))?;
+    // Vorbis headers end in a framing bit; Opus ones do not.
+    let (prefix, framing_bit): (&[u8], bool) = if packets[0].data.starts_with(b"\x01vorbis") {
+        (b"\x03vorbis", true)
+    } else if packets[0].data.starts_with(b"OpusHead") {
+        (b"OpusTags", false)
+    } else {
+        return Err(format!(
+            "Writing tags to Ogg streams other than Vorbis and Opus is not supported: {}",
+            path.display()
+        ));
+    };
+    let comment_index = packets
+        .iter()
+        .enumerate()
+        .filter(|(_, packet)| packet.stream_serial() == serial)
+        .nth(1)
+        .map(|(index, _)| index)
+        .filter(|index| packets[*index].data.starts_with(prefix))
+        .ok_or_else(|| format!("Missing Ogg comment header in {}", path.display()))?;
+    let (vendor, mut comments) =
+        parse_vorbis_comments(&packets[comment_index].data[prefix.len()..])
+            .ok_or_else(|| format!("Malformed Vorbis comments in {}", path.display()))?;
+    set_vorbis_fields(&mut comments, fields);
+    match artwork {
+        ArtworkChange::Keep => {}
+        ArtworkChange::Remove => {
+            comments.retain(|comment| !vorbis_key_is(comment, VORBIS_PICTURE_KEY))
+        }
+        ArtworkChange::Replace { image, mime_type } => {
+            let picture = flac_picture_block(image, mime_type)?;
+            comments.retain(|comment| !vorbis_key_is(comment, VORBIS_PICTURE_KEY));
+            comments.push(format!(
+                "{VORBIS_PICTURE_KEY}={}",
+                base64::engine::general_purpose::STANDARD.encode(picture)
+            ));
+        }
+    }
+    let mut header = prefix.to_vec();
+    header.extend(vorbis_comment_block(&vendor, &comments));
+    if framing_bit {
+        header.push(1);
+    }
+    packets[comment_index].data = header;
+
+    let mut writer = PacketWriter::new(Vec::with_capacity(data.len()));
+    for packet in &packets {
+        let end = if packet.last_in_stream() {
+            PacketWriteEndInfo::EndStream
+        } else if packet.last_in_page() {
+            PacketWriteEndInfo::EndPage
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer
+            .write_packet(
+                packet.data.as_slice(),
+                packet.stream_serial(),
+                end,
+                packet.absgp_page(),
+            )
+            .map_err(|e| format!("Failed to write tags to {}: {e}", path.display()))?;
+    }
+    let rewritten = writer.into_inner();
+    replace_file(path, |out| out.write_all(&rewritten))
+        .map_err(|e| format!("Failed to write tags to {}: {e}", path.display()))
+}
+
+/// Replaces the comments `fields` sets, shared by FLAC and Ogg files.
+fn set_vorbis_fields(comments: &mut Vec<String>, fields: &TagFields) {
+    let numbers = [
+        (&["DATE", "YEAR"][..], fields.year),
+        (&["TRACKNUMBER"][..], fields.track_number),
+    ];
+    let texts = [
+        ("TITLE", &fields.title),
+        ("ARTIST", &fields.artist),
+        ("ALBUM", &fields.album),
+        ("GENRE", &fields.genre),
+    ];
+    for (key, value) in texts {
+        if let Some(value) = value {
+            comments.retain(|comment| !vorbis_key_is(comment, key));
+            if !value.is_empty() {
+                comments.push(format!("{key}={value}"));
+            }
+        }
+    }
+    for (keys, value) in numbers {
+        if let Some(value) = value {
+            comments.retain(|comment| !keys.iter().any(|key| vorbis_key_is(comment, key)));
+            if value > 0 {
+                comments.push(format!("{}={value}", keys[0]));
+            }
+        }
+    }
+}
+
+fn vorbis_key_is(comment: &str, key: &str) -> bool {
+    comment
+        .split_once('=')
+        .is_some_and(|(name, _)| name.eq_ignore_ascii_case(key))
+}
+
+/// Vendor string and `KEY=value` comments of a VORBIS_COMMENT block.
+fn parse_vorbis_comments(block: &[u8]) -> Option<(String, Vec<String>)> {
+    fn read_u32(block: &[u8], offset: &mut usize) -> Option<u32> {
+        let value = u32::from_le_bytes(block.get(*offset..*offset + 4)?.try_into().ok()?);
+        *offset += 4;
+        Some(value)
+    }
+    fn read_string(block: &[u8], offset: &mut usize) -> Option<String> {
+        let length = read_u32(block, offset)? as usize;
+        let bytes = block.get(*offset..offset.checked_add(length)?)?;
+        *offset += length;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    let mut offset = 0;
+    let vendor = read_string(block, &mut offset)?;
+    let count = read_u32(block, &mut offset)?;
+    let comments = (0..count)
+        .map(|_| read_string(block, &mut offset))
+        .collect::<Option<Vec<_>>>()?;
+    Some((vendor, comments))
+}
+
+fn vorbis_comment_block(vendor: &str, comments: &[String]) -> Vec<u8> {
+    let mut block = vorbis_string(vendor);
+    block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        block.extend(vorbis_string(comment));
+    }
+    block
+}
+
+fn vorbis_string(text: &str) -> Vec<u8> {
+    let mut bytes = (text.len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(text.as_bytes());
+    bytes
+}
+
+/// One box of an MP4 file, as offsets into the slice it was parsed from.
+struct Mp4Box {
+    kind: [u8; 4],
+    start: usize,
+    body: usize,
+    end: usize,
+}
+
+/// Boxes laid out back to back in `data`. A size of 0 extends to the end.
+fn mp4_boxes(data: &[u8]) -> Option<Vec<Mp4Box>> {
+    let mut boxes = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let header = data.get(start..start + 8)?;
+        let kind = header[4..8].try_into().ok()?;
+        let (size, body) = match u32::from_be_bytes(header[..4].try_into().ok()?) {
+            0 => (data.len() - start, start + 8),
+            1 => {
+                let size = u64::from_be_bytes(data.get(start + 8..start + 16)?.try_into().ok()?);
+                (usize::try_from(size).ok()?, start + 16)
+            }
+            size => (size as usize, start + 8),
+        };
+        let end = start.checked_add(size)?;
+        if end > data.len() || end < body {
+            return None;
+        }
+        boxes.push(Mp4Box {
+            kind,
+            start,
+            body,
+            end,
+        });
+        start = end;
+    }
+    Some(boxes)
+}
+
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    out
+}
+
+/// Body of the first `kind` child in `body`.
+fn mp4_child<'a>(body: &'a [u8], kind: &[u8; 4]) -> Option<Option<&'a [u8]>> {
+    Some(
+        mp4_boxes(body)?
+            .into_iter()
+            .find(|child| &child.kind == kind)
+            .map(|child| &body[child.body..child.end]),
+    )
+}
+
+/// `body` with its first `kind` child replaced by one holding `child_body`, or with
+/// that child appended.
+fn with_mp4_child(body: &[u8], kind: &[u8; 4], child_body: &[u8]) -> Option<Vec<u8>> {
+    let child = mp4_box(kind, child_body);
+    let mut out = body.to_vec();
+    match mp4_boxes(body)?.into_iter().find(|b| &b.kind == kind) {
+        Some(existing) => drop(out.splice(existing.start..existing.end, child)),
+        None => out.extend(child),
+    }
+    Some(out)
+}
+
+/// Rewrites `moov/udta/meta/ilst`, creating the boxes that are missing. Chunk offsets
+/// into audio stored after the movie box move by the change in its size.
+fn write_mp4_tags(path: &Path, fields: &TagFields, artwork: &ArtworkChange) -> Result<(), String> {
+    let malformed = || format!("Malformed MP4 boxes in {}", path.display());
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let top = mp4_boxes(&data).ok_or_else(malformed)?;
+    let moov = top
+        .iter()
+        .find(|b| &b.kind == b"moov")
+        .ok_or_else(|| format!("{} has no MP4 movie box", path.display()))?;
+    let moov_body = &data[moov.body..moov.end];
+
+    let udta = mp4_child(moov_body, b"udta").ok_or_else(malformed)?;
+    let udta = udta.unwrap_or_default();
+    let meta = mp4_child(udta, b"meta")
+        .ok_or_else(malformed)?
+        .map_or_else(empty_mp4_meta, <[u8]>::to_vec);
+    // iTunes writes `meta` as a full box; QuickTime files can start it with `hdlr`.
+    let header_len = if meta.get(4..8) == Some(b"hdlr".as_slice()) {
+        0
+    } else {
+        4
+    };
+    let (meta_header, meta_children) = meta.split_at(header_len.min(meta.len()));
+    let ilst = mp4_child(meta_children, b"ilst")
+        .ok_or_else(malformed)?
+        .unwrap_or_default();
+    let ilst = updated_ilst(ilst, fields, artwork).ok_or_else(malformed)?;
+    let mut meta_body = meta_header.to_vec();
+    meta_body.extend(with_mp4_child(meta_children, b"ilst", &ilst).ok_or_else(malformed)?);
+    let udta = with_mp4_child(udta, b"meta", &meta_body).ok_or_else(malformed)?;
+    let mut moov_body = with_mp4_child(moov_body, b"udta", &udta).ok_or_else(malformed)?;
+
+    let delta = (moov_body.len() + 8) as i64 - (moov.end - moov.start) as i64;
+    if delta != 0 {
+        shift_chunk_offsets(&mut moov_body, moov.end as u64, delta).ok_or_else(malformed)?;
+    }
+    let moov_box = mp4_box(b"moov", &moov_body);
+    replace_file(path, |out| {
+        out.write_all(&data[..moov.start])?;
+        out.write_all(&moov_box)?;
+        out.write_all(&data[moov.end..])
+    })
+    .map_err(|e| format!("Failed to write tags to {}: {e}", path.display()))
+}
+
+/// A `meta` full box with the `mdir` handler iTunes metadata is written under.
+fn empty_mp4_meta() -> Vec<u8> {
+    let mut hdlr = vec![0; 8]; // version, flags and pre-defined
+    hdlr.extend_from_slice(b"mdirappl");
+    hdlr.extend_from_slice(&[0; 9]); // reserved, then an empty name
+    let mut meta = vec![0; 4];
+    meta.extend(mp4_box(b"hdlr", &hdlr));
+    meta
+}
+
+fn updated_ilst(ilst: &[u8], fields: &TagFields, artwork: &ArtworkChange) -> Option<Vec<u8>> {
+    let mut items = mp4_boxes(ilst)?
+        .into_iter()
+        .map(|item| (item.kind, ilst[item.start..item.end].to_vec()))
+        .collect::<Vec<_>>();
+
+    let texts = [
+        (b"\xa9nam", &fields.title),
+        (b"\xa9ART", &fields.artist),
+        (b"\xa9alb", &fields.album),
+    ];
+    for (kind, value) in texts {
+        if let Some(value) = value {
+            let item = (!value.is_empty()).then(|| ilst_item(kind, MP4_UTF8, value.as_bytes()));
+            set_ilst_item(&mut items, &[kind], item);
+        }
+    }
+    // `gnre` holds an ID3v1 genre number; the text genre replaces it.
+    if let Some(genre) = &fields.genre {
+        let item = (!genre.is_empty()).then(|| ilst_item(b"\xa9gen", MP4_UTF8, genre.as_bytes()));
+        set_ilst_item(&mut items, &[b"\xa9gen", b"gnre"], item);
+    }
+    if let Some(year) = fields.year {
+        let item = (year > 0).then(|| ilst_item(b"\xa9day", MP4_UTF8, year.to_string().as_bytes()));
+        set_ilst_item(&mut items, &[b"\xa9day"], item);
+    }
+    if let Some(track) = fields.track_number {
+        let total = items
+            .iter()
+            .find(|(kind, _)| kind == b"trkn")
+            .and_then(|(_, item)| item.get(28..30))
+            .map(|total| [total[0], total[1]])
+            .unwrap_or_default();
+        let item = u16::try_from(track)
+            .ok()
+            .filter(|track| *track > 0)
+            .map(|track| {
+                let [high, low] = track.to_be_bytes();
+                ilst_item(
+                    b"trkn",
+                    MP4_IMPLICIT,
+                    &[0, 0, high, low, total[0], total[1], 0, 0],
+                )
+            });
+        set_ilst_item(&mut items, &[b"trkn"], item);
+    }
+    match artwork {
+        ArtworkChange::Keep => {}
+        ArtworkChange::Remove => set_ilst_item(&mut items, &[b"covr"], None),
+        ArtworkChange::Replace { image, mime_type } => {
+            let data_type = if *mime_type == "image/png" {
+                MP4_PNG
+            } else {
+                MP4_JPEG
+            };
+            let item = ilst_item(b"covr", data_type, image);
+            set_ilst_item(&mut items, &[b"covr"], Some(item));
+        }
+    }
+    Some(items.into_iter().flat_map(|(_, item)| item).collect())
+}
+
+/// Replaces the items of `kinds` with `item`, which takes the first kind.
+fn set_ilst_item(items: &mut Vec<([u8; 4], Vec<u8>)>, kinds: &[&[u8; 4]], item: Option<Vec<u8>>) {
+    items.retain(|(kind, _)| !kinds.contains(&kind));
+    items.extend(item.map(|item| (*kinds[0], item)));
+}
+
+/// An `ilst` item holding one `data` box.
+fn ilst_item(kind: &[u8; 4], data_type: u32, value: &[u8]) -> Vec<u8> {
+    let mut data = data_type.to_be_bytes().to_vec();
+    data.extend_from_slice(&[0; 4]); // locale
+    data.extend_from_slice(value);
+    mp4_box(kind, &mp4_box(b"data", &data))
+}
+
+/// Adds `delta` to the chunk offsets from `after` on in the `stco` and `co64` tables
+/// under `body`.
+fn shift_chunk_offsets(body: &mut [u8], after: u64, delta: i64) -> Option<()> {
+    for child in mp4_boxes(body)? {
+        let child_body = &mut body[child.body..child.end];
+        match &child.kind {
+            b"trak" | b"mdia" | b"minf" | b"stbl" => shift_chunk_offsets(child_body, after, delta)?,
+            b"stco" | b"co64" => {
+                let width = if &child.kind == b"stco" { 4 } else { 8 };
+                let count = u32::from_be_bytes(child_body.get(4..8)?.try_into().ok()?) as usize;
+                let entries = child_body.get_mut(8..8 + count.checked_mul(width)?)?;
+                for entry in entries.chunks_exact_mut(width) {
+                    let offset = if width == 4 {
+                        u64::from(u32::from_be_bytes(entry.try_into().ok()?))
+                    } else {
+                        u64::from_be_bytes(entry.try_into().ok()?)
+                    };
+                    if offset < after {
+                        continue;
+                    }
+                    let offset = offset.checked_add_signed(delta)?;
+                    if width == 4 {
+                        entry.copy_from_slice(&u32::try_from(offset).ok()?.to_be_bytes());
+                    } else {
+                        entry.copy_from_slice(&offset.to_be_bytes());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mp4_boxes, mp4_child, parse_vorbis_comments, write_tags, TagFields};
+    use crate::audio::decoder::{decode_file, read_track_metadata};
+    use crate::audio::lossy_encoder::write_ogg_vorbis;
+    use crate::test_support::unique_temp_path;
+    use id3::TagLike;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = unique_temp_path(&format!("tags-{name}"));
+        std::fs::write(&path, bytes).expect("test file should be written");
+        path
+    }
+
+    fn fields() -> TagFields {
+        TagFields {
+            title: Some("Fixed Title".to_string()),
+            artist: Some("Band".to_string()),
+            genre: Some(String::new()),
+            year: Some(1999),
+            track_number: Some(3),
+            ..TagFields::default()
+        }
+    }
+
+    #[test]
+    fn id3_and_flac_tags_are_replaced_and_kept() {
+        let mp3 = temp_file("song.mp3", &[0xFF, 0xFB, 0x90, 0x00]);
+        let mut tag = id3::Tag::new();
+        tag.set_title("Wrong");
+        tag.set_album("Album");
+        tag.set_genre("Rock");
+        tag.write_to_path(&mp3, id3::Version::Id3v24)
+            .expect("tag should be written");
+        write_tags(&mp3, &fields()).expect("mp3 tags should be written");
+        let tag = id3::Tag::read_from_path(&mp3).expect("tag should be read");
+        assert_eq!(tag.title(), Some("Fixed Title"));
+        assert_eq!(tag.album(), Some("Album"), "untouched fields are kept");
+        assert_eq!(tag.genre(), None, "an empty value removes the tag");
+        assert_eq!(tag.date_recorded().map(|date| date.year), Some(1999));
+        assert_eq!(tag.track(), Some(3));
+
+        let mut flac = b"fLaC".to_vec();
+        flac.extend_from_slice(&[0x00, 0, 0, 34]); // STREAMINFO
+        flac.extend_from_slice(&[0x11; 34]);
+        let mut comments = 6_u32.to_le_bytes().to_vec();
+        comments.extend_from_slice(b"vendor");
+        comments.extend_from_slice(&2_u32.to_le_bytes());
+        for comment in ["title=Wrong", "ALBUM=Album"] {
+            comments.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            comments.extend_from_slice(comment.as_bytes());
+        }
+        flac.extend_from_slice(&[0x84, 0, 0, comments.len() as u8]);
+        flac.extend_from_slice(&comments);
+        flac.extend_from_slice(b"FRAMES");
+        let path = temp_file("song.flac", &flac);
+        write_tags(&path, &fields()).expect("flac tags should be written");
+        let written = std::fs::read(&path).expect("flac should be readable");
+        assert!(written.ends_with(b"FRAMES"));
+        let length = u32::from_be_bytes([0, written[43], written[44], written[45]]) as usize;
+        assert_eq!(written[42], 0x84, "the comments stay the last block");
+        let (vendor, comments) =
+            parse_vorbis_comments(&written[46..46 + length]).expect("comments should parse");
+        assert_eq!(vendor, "vendor");
+        assert_eq!(
+            comments,
+            [
+                "ALBUM=Album",
+                "TITLE=Fixed Title",
+                "ARTIST=Band",
+                "DATE=1999",
+                "TRACKNUMBER=3"
+            ]
+        );
+
+        assert!(write_tags(&temp_file("song.wma", b"\x30\x26"), &fields()).is_err());
+        let _ = std::fs::remove_file(mp3);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn ogg_vorbis_comment_header_is_rewritten() {
+        let samples = (0..8820)
+            .map(|n| (n as f32 * 0.03).sin() * 0.5)
+            .collect::<Vec<_>>();
+        let tags = [
+            ("ARTIST", "Wrong".to_string()),
+            ("ALBUM", "Album".to_string()),
+            ("GENRE", "Rock".to_string()),
+        ];
+        let path = unique_temp_path("tags-song.ogg");
+        write_ogg_vorbis(&path, &samples, 44_100, 2, &tags).expect("ogg should encode");
+        let before = decode_file(&path).expect("ogg should decode");
+
+        write_tags(&path, &fields()).expect("ogg tags should be written");
+        let after = decode_file(&path).expect("tagged ogg should decode");
+        let metadata = read_track_metadata(&path).expect("tagged ogg should probe");
+        let file = std::fs::File::open(&path).expect("tagged ogg should open");
+        let mut reader = ogg::PacketReader::new(file);
+        let header = (0..2)
+            .map(|_| reader.read_packet_expected().expect("header should read"))
+            .last()
+            .expect("comment header should exist");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(after.samples, before.samples, "the audio is untouched");
+        assert_eq!(metadata.artist.as_deref(), Some("Band"));
+        assert_eq!(metadata.genre, None);
+        assert!(header.data.starts_with(b"\x03vorbis") && header.data.ends_with(&[1]));
+        let (_, comments) =
+            parse_vorbis_comments(&header.data[7..]).expect("comments should parse");
+        assert_eq!(
+            comments,
+            [
+                "ALBUM=Album",
+                "TITLE=Fixed Title",
+                "ARTIST=Band",
+                "DATE=1999",
+                "TRACKNUMBER=3"
+            ]
+        );
+    }
+
+    #[test]
+    fn mp4_items_are_written_and_chunk_offsets_follow_the_movie_box() {
+        let boxed = |kind: &[u8], body: &[u8]| {
+            let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+            out.extend_from_slice(kind);
+            out.extend_from_slice(body);
+            out
+        };
+        let ftyp = boxed(b"ftyp", b"M4A \0\0\0\0");
+        let moov_len = |stco: u32| {
+            let mut table = vec![0; 4];
+            table.extend_from_slice(&1_u32.to_be_bytes());
+            table.extend_from_slice(&stco.to_be_bytes());
+            let stbl = boxed(b"stbl", &boxed(b"stco", &table));
+            boxed(
+                b"moov",
+                &boxed(b"trak", &boxed(b"mdia", &boxed(b"minf", &stbl))),
+            )
+        };
+        let audio_offset = (ftyp.len() + moov_len(0).len() + 8) as u32;
+        let mut file = ftyp.clone();
+        file.extend(moov_len(audio_offset));
+        file.extend(boxed(b"mdat", b"AUDIO"));
+        let path = temp_file("song.m4a", &file);
+
+        write_tags(&path, &fields()).expect("mp4 tags should be written");
+        let written = std::fs::read(&path).expect("m4a should be readable");
+        let top = mp4_boxes(&written).expect("boxes should parse");
+        let mdat = top.iter().find(|b| &b.kind == b"mdat").expect("mdat");
+        assert_eq!(&written[mdat.body..mdat.end], b"AUDIO");
+        let stco = written
+            .windows(4)
+            .position(|window| window == b"stco")
+            .expect("stco");
+        let offset = u32::from_be_bytes(written[stco + 12..stco + 16].try_into().unwrap());
+        assert_eq!(
+            offset as usize, mdat.body,
+            "chunk offset points at the audio"
+        );
+
+        let moov = top.iter().find(|b| &b.kind == b"moov").expect("moov");
+        let udta = mp4_child(&written[moov.body..moov.end], b"udta")
+            .flatten()
+            .expect("udta");
+        let meta = mp4_child(udta, b"meta").flatten().expect("meta");
+        let ilst = mp4_child(&meta[4..], b"ilst").flatten().expect("ilst");
+        let title = mp4_child(ilst, b"\xa9nam").flatten().expect("title");
+        assert_eq!(&title[16..], b"Fixed Title");
+        let track = mp4_child(ilst, b"trkn").flatten().expect("track");
+        assert_eq!(&track[16..], &[0, 0, 0, 3, 0, 0, 0, 0]);
+        assert!(mp4_child(ilst, b"\xa9gen").flatten().is_none());
+        let _ = std::fs::remove_file(path);
+    }
+}
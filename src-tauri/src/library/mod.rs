@@ -1,6 +1,28 @@
+pub mod album_experience;
 pub mod art_cache;
+pub mod artists;
+pub mod cue;
 pub mod enrichment_queue;
+pub mod exclusions;
+pub mod export;
+pub mod extensions;
+pub mod file_ops;
+pub mod folders;
+pub mod heatmap;
+pub mod history;
+pub mod itunes;
 pub mod metadata;
+pub mod network_usage;
+pub mod palette;
+pub mod paths;
+pub mod playlist_files;
+pub mod playlists;
+pub mod prune;
 pub mod queue;
+pub mod remote_cache;
+pub mod resume;
 pub mod scanner;
+pub mod stem_export;
+pub mod stem_queue;
 pub mod stems;
+pub mod waveform_cache;
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::db::manager::DbManager;
+
+/// Settings key holding the current month's usage as JSON.
+const NETWORK_USAGE_SETTING: &str = "network_usage";
+
+/// What a download was for. Every provider is always reported, even when unused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProvider {
+    Art,
+    Lyrics,
+    Streams,
+    Models,
+    AutoEq,
+    Metadata,
+}
+
+impl NetworkProvider {
+    pub const ALL: [Self; 6] = [
+        Self::Art,
+        Self::Lyrics,
+        Self::Streams,
+        Self::Models,
+        Self::AutoEq,
+        Self::Metadata,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    /// Response body bytes; headers and TLS overhead are not counted.
+    pub bytes: u64,
+    pub requests: u64,
+}
+
+/// Downloads of one calendar month (UTC, `"YYYY-MM"`); a new month starts from zero.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkUsage {
+    pub month: String,
+    pub providers: BTreeMap<NetworkProvider, ProviderUsage>,
+}
+
+impl NetworkUsage {
+    fn for_month(self, month: &str) -> Self {
+        if self.month == month {
+            return self;
+        }
+        Self {
+            month: month.to_string(),
+            providers: BTreeMap::new(),
+        }
+    }
+
+    pub fn provider(&self, provider: NetworkProvider) -> ProviderUsage {
+        self.providers.get(&provider).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheUsage {
+    pub name: String,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+fn attached_db() -> &'static OnceLock<DbManager> {
+    static DB: OnceLock<DbManager> = OnceLock::new();
+    &DB
+}
+
+/// Serializes the read-modify-write of the settings row across fetch threads.
+fn ledger_lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+/// Gives `record` somewhere to persist to. Called once at startup; downloads made
+/// before it (or in tests) are not counted.
+pub fn attach(db: DbManager) {
+    let _ = attached_db().set(db);
+}
+
+/// Counts one download of `bytes` for `provider` in the current month.
+pub fn record(provider: NetworkProvider, bytes: u64) {
+    let Some(db) = attached_db().get() else {
+        return;
+    };
+    if let Err(err) = record_in(db, provider, bytes, 1, &current_month()) {
+        eprintln!("Failed to record network usage: {err}");
+    }
+}
+
+/// Adds `bytes` to the current month without counting a request, for long-lived
+/// downloads (radio streams) that report their body in batches.
+pub fn record_bytes(provider: NetworkProvider, bytes: u64) {
+    let Some(db) = attached_db().get() else {
+        return;
+    };
+    if let Err(err) = record_in(db, provider, bytes, 0, &current_month()) {
+        eprintln!("Failed to record network usage: {err}");
+    }
+}
+
+/// Usage for the current month.
+pub fn usage(db: &DbManager) -> Result<NetworkUsage, String> {
+    Ok(stored_usage(db)?.for_month(&current_month()))
+}
+
+/// Size of everything below `dir`; a missing directory counts as empty.
+pub fn cache_usage(name: &str, dir: &Path) -> CacheUsage {
+    let (bytes, files) = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .fold((0, 0), |(bytes, files), metadata| {
+            (bytes + metadata.len(), files + 1)
+        });
+    CacheUsage {
+        name: name.to_string(),
+        bytes,
+        files,
+    }
+}
+
+fn record_in(
+    db: &DbManager,
+    provider: NetworkProvider,
+    bytes: u64,
+    requests: u64,
+    month: &str,
+) -> Result<(), String> {
+    let _guard = ledger_lock()
+        .lock()
+        .map_err(|_| "Network usage lock poisoned".to_string())?;
+    let mut usage = stored_usage(db)?.for_month(month);
+    let entry = usage.providers.entry(provider).or_default();
+    entry.bytes += bytes;
+    entry.requests += requests;
+    let json = serde_json::to_string(&usage)
+        .map_err(|e| format!("Failed to serialize network usage: {e}"))?;
+    db.set_setting(NETWORK_USAGE_SETTING, &json)
+}
+
+fn stored_usage(db: &DbManager) -> Result<NetworkUsage, String> {
+    match db.get_setting(NETWORK_USAGE_SETTING)? {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid {NETWORK_USAGE_SETTING} setting: {e}")),
+        None => Ok(NetworkUsage::default()),
+    }
+}
+
+fn current_month() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    month_key(seconds)
+}
+
+/// `"YYYY-MM"` (UTC) of a Unix timestamp, via Howard Hinnant's `civil_from_days`.
+fn month_key(unix_seconds: u64) -> String {
+    let days = (unix_seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{month_key, record_in, stored_usage, NetworkProvider};
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn month_keys_follow_the_calendar() {
+        assert_eq!(month_key(0), "1970-01");
+        assert_eq!(month_key(951_868_800), "2000-03");
+        assert_eq!(month_key(1_709_164_800), "2024-02");
+        assert_eq!(month_key(1_790_000_000), "2026-09");
+    }
+
+    #[test]
+    fn usage_accumulates_per_provider_and_resets_monthly() {
+        let db = DbManager::new(unique_temp_path("net.db")).expect("db should initialize");
+
+        record_in(&db, NetworkProvider::Art, 1_000, 1, "2026-09").expect("record");
+        record_in(&db, NetworkProvider::Art, 500, 0, "2026-09").expect("record");
+        record_in(&db, NetworkProvider::Lyrics, 80, 1, "2026-09").expect("record");
+        let september = stored_usage(&db).expect("usage");
+        assert_eq!(september.provider(NetworkProvider::Art).bytes, 1_500);
+        assert_eq!(september.provider(NetworkProvider::Art).requests, 1);
+        assert_eq!(september.provider(NetworkProvider::Models).bytes, 0);
+
+        record_in(&db, NetworkProvider::Lyrics, 20, 1, "2026-10").expect("record");
+        let october = stored_usage(&db).expect("usage");
+        assert_eq!(october.month, "2026-10");
+        assert_eq!(october.provider(NetworkProvider::Art).bytes, 0);
+        assert_eq!(october.provider(NetworkProvider::Lyrics).bytes, 20);
+    }
+}
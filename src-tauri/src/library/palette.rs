@@ -0,0 +1,77 @@
+use image::RgbImage;
+
+/// Cover art is shrunk to this size first; a palette needs no more detail.
+const SAMPLE_SIZE: u32 = 64;
+/// Colors closer than this (Euclidean RGB distance) count as the same swatch.
+const MIN_SWATCH_DISTANCE: u32 = 48;
+
+/// Dominant colors of an encoded image as `#rrggbb`, most common first.
+pub fn extract_palette(image_bytes: &[u8], max_colors: usize) -> Result<Vec<String>, String> {
+    let image = image::load_from_memory(image_bytes)
+        .map_err(|e| format!("Failed to decode cover art for palette: {e}"))?;
+    let sample = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgb8();
+    Ok(dominant_colors(&sample, max_colors)
+        .into_iter()
+        .map(|[r, g, b]| format!("#{r:02x}{g:02x}{b:02x}"))
+        .collect())
+}
+
+/// Buckets pixels into a 16x16x16 color cube and returns the average color of the
+/// fullest buckets, skipping ones too close to a swatch already picked.
+fn dominant_colors(image: &RgbImage, max_colors: usize) -> Vec<[u8; 3]> {
+    let mut buckets = vec![(0_u32, [0_u32; 3]); 16 * 16 * 16];
+    for pixel in image.pixels() {
+        let [r, g, b] = pixel.0;
+        let index = (r as usize >> 4) << 8 | (g as usize >> 4) << 4 | b as usize >> 4;
+        let (count, sums) = &mut buckets[index];
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip([r, g, b]) {
+            *sum += channel as u32;
+        }
+    }
+
+    let mut filled = buckets
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .collect::<Vec<_>>();
+    // Stable sort keeps equally common colors in cube order, so results are repeatable.
+    filled.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+
+    let mut palette: Vec<[u8; 3]> = Vec::with_capacity(max_colors);
+    for (count, sums) in filled {
+        if palette.len() >= max_colors {
+            break;
+        }
+        let color = sums.map(|sum| (sum / count) as u8);
+        let distinct = palette.iter().all(|picked| {
+            let distance_sq = picked
+                .iter()
+                .zip(color)
+                .map(|(a, b)| (*a as i32 - b as i32).pow(2) as u32)
+                .sum::<u32>();
+            distance_sq >= MIN_SWATCH_DISTANCE * MIN_SWATCH_DISTANCE
+        });
+        if distinct {
+            palette.push(color);
+        }
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dominant_colors;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn most_common_distinct_colors_come_first() {
+        let image = RgbImage::from_fn(8, 8, |x, y| match (x, y) {
+            (0..=1, _) => Rgb([10, 20, 200]),
+            // A near-duplicate of the red swatch must not take a palette slot.
+            (2, 0) => Rgb([230, 30, 0]),
+            _ => Rgb([250, 0, 0]),
+        });
+        assert_eq!(dominant_colors(&image, 4), vec![[250, 0, 0], [10, 20, 200]]);
+        assert_eq!(dominant_colors(&image, 1), vec![[250, 0, 0]]);
+    }
+}
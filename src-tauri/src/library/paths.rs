@@ -0,0 +1,91 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves `path` to the single spelling used as the library key.
+///
+/// Symlinks, `.`/`..` segments and (on Windows) case differences and mapped drives
+/// are resolved through the filesystem, so the same file always maps to the same
+/// row. Files that no longer exist (e.g. watcher remove events) fall back to the
+/// canonical parent directory joined with the original file name.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return strip_verbatim_prefix(canonical);
+    }
+    if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+        if let Ok(parent) = parent.canonicalize() {
+            return strip_verbatim_prefix(parent).join(name);
+        }
+    }
+    lexical_normalize(path)
+}
+
+pub fn canonical_path_string(path: &str) -> String {
+    canonical_path(Path::new(path))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Windows `canonicalize` returns `\\?\C:\...` and `\\?\UNC\server\share\...`;
+/// the library stores the conventional `C:\...` and `\\server\share\...` forms.
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    let raw = path.to_string_lossy();
+    if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{unc}"));
+    }
+    if let Some(local) = raw.strip_prefix(r"\\?\") {
+        return PathBuf::from(local);
+    }
+    path
+}
+
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push(component);
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_path, strip_verbatim_prefix};
+    use crate::test_support::unique_temp_path;
+    use std::path::PathBuf;
+
+    #[test]
+    fn different_spellings_resolve_to_same_path() {
+        let root = unique_temp_path("paths");
+        std::fs::create_dir_all(root.join("sub")).expect("dirs should be created");
+        let file = root.join("song.flac");
+        std::fs::write(&file, b"x").expect("file should be written");
+
+        let direct = canonical_path(&file);
+        let dotted = canonical_path(&root.join("sub").join("..").join(".").join("song.flac"));
+        assert_eq!(direct, dotted);
+
+        // Missing files keep a canonical parent.
+        let missing = canonical_path(&root.join("sub").join("..").join("gone.mp3"));
+        assert_eq!(missing, direct.with_file_name("gone.mp3"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn verbatim_prefixes_are_removed() {
+        assert_eq!(
+            strip_verbatim_prefix(PathBuf::from(r"\\?\C:\Music\a.flac")),
+            PathBuf::from(r"C:\Music\a.flac")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(PathBuf::from(r"\\?\UNC\nas\music\a.flac")),
+            PathBuf::from(r"\\nas\music\a.flac")
+        );
+    }
+}
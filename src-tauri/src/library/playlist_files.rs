@@ -0,0 +1,379 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::db::manager::{DbManager, TrackRecord};
+use crate::library::paths::canonical_path_string;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlaylistImport {
+    pub id: i64,
+    pub name: String,
+    pub imported: usize,
+    /// Entries that matched no file on disk or in the library, as written in the file.
+    pub missing: Vec<String>,
+}
+
+/// Reads an M3U, M3U8 or PLS file into a new playlist named `name`, or after the file
+/// when no name is given (numbered if that name is taken). Relative entries resolve
+/// against the file's folder; entries not found there, such as playlists written on
+/// another machine, are matched to the library track whose path ends with the same
+/// folders and file name. Web URLs are skipped.
+pub fn import_playlist(
+    db: &DbManager,
+    path: &Path,
+    name: Option<&str>,
+) -> Result<PlaylistImport, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    // `.m3u` files are often Latin-1; every byte sequence is valid Latin-1.
+    let text = String::from_utf8(bytes)
+        .unwrap_or_else(|err| err.into_bytes().iter().map(|b| char::from(*b)).collect());
+    // A UTF-8 byte order mark reads as `ï»¿` when the rest fell back to Latin-1.
+    let text = text
+        .trim_start_matches('\u{feff}')
+        .trim_start_matches("\u{ef}\u{bb}\u{bf}");
+    let is_pls = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pls"))
+        || text.trim_start().starts_with("[playlist]");
+    let entries = if is_pls {
+        parse_pls(text)
+    } else {
+        parse_m3u(text)
+    };
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut tracks = Vec::new();
+    let mut missing = Vec::new();
+    for entry in entries {
+        match resolve_entry(&entry, base_dir, |name| db.get_track_paths_named(name))? {
+            Some(track) => tracks.push(track),
+            None => missing.push(entry),
+        }
+    }
+
+    let name = match name.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => name.to_string(),
+        None => {
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "Imported playlist".to_string());
+            unused_playlist_name(db, &stem)?
+        }
+    };
+    let id = db.create_playlist(&name)?;
+    if let Err(err) = db.set_playlist_tracks(id, &tracks) {
+        let _ = db.delete_playlist(id);
+        return Err(err);
+    }
+    Ok(PlaylistImport {
+        id,
+        name,
+        imported: tracks.len(),
+        missing,
+    })
+}
+
+/// Writes `tracks` to `dest` as an extended M3U8 playlist with `#EXTINF` durations and
+/// `Artist - Title` lines from the library. Tracks under the playlist's folder are
+/// written relative to it, so the folder can be moved as a whole; others are absolute.
+pub fn export_m3u8(db: &DbManager, tracks: &[String], dest: &Path) -> Result<(), String> {
+    let base_dir = dest
+        .parent()
+        .map(|dir| PathBuf::from(canonical_path_string(&dir.to_string_lossy())));
+
+    let mut out = String::from("#EXTM3U\n");
+    for track in tracks {
+        if let Some(record) = db.get_track(track)? {
+            out.push_str(&extinf(&record));
+        }
+        let path = Path::new(track);
+        let written = base_dir
+            .as_deref()
+            .and_then(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(path);
+        out.push_str(&written.to_string_lossy());
+        out.push('\n');
+    }
+    fs::write(dest, out).map_err(|e| format!("Failed to write {}: {e}", dest.display()))
+}
+
+fn extinf(record: &TrackRecord) -> String {
+    let seconds = record
+        .duration_seconds
+        .map_or(-1, |seconds| seconds.round() as i64);
+    let title = record.title.clone().unwrap_or_else(|| {
+        Path::new(&record.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    match &record.artist {
+        Some(artist) => format!("#EXTINF:{seconds},{artist} - {title}\n"),
+        None => format!("#EXTINF:{seconds},{title}\n"),
+    }
+}
+
+/// Entries of an M3U/M3U8 file: every line that is not blank or a `#` directive. An
+/// `#EXTINF` with no entry after it describes nothing and is dropped with the other
+/// directives; line endings may be LF, CRLF or the bare CR of old Mac files.
+fn parse_m3u(text: &str) -> Vec<String> {
+    text.split(['\n', '\r'])
+        .map(|line| line.trim().trim_start_matches('\u{feff}'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Entries of a PLS file, in `FileN` order.
+fn parse_pls(text: &str) -> Vec<String> {
+    let mut entries = text
+        .split(['\n', '\r'])
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let index = key.trim().strip_prefix("File")?.parse::<u32>().ok()?;
+            Some((index, value.trim().to_string()))
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|(index, _)| *index);
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Library path for a playlist entry: the file it names, resolved against `base_dir`
+/// when relative, or else the library track sharing the longest tail of folders with it.
+/// `library_paths_named` looks up the library paths with a given file name.
+pub fn resolve_entry(
+    entry: &str,
+    base_dir: &Path,
+    library_paths_named: impl FnOnce(&str) -> Result<Vec<String>, String>,
+) -> Result<Option<String>, String> {
+    let lower = entry.to_ascii_lowercase();
+    let entry = if let Some(url) = lower.strip_prefix("file:") {
+        // `file:///...`, iTunes' `file://localhost/...` and the short `file:/...`.
+        let url = url
+            .strip_prefix("//localhost")
+            .or_else(|| url.strip_prefix("//"))
+            .unwrap_or(url);
+        percent_decode(&entry[entry.len() - url.len()..])
+    } else if lower.contains("://") {
+        return Ok(None);
+    } else {
+        entry.to_string()
+    };
+    // Playlists written on Windows use backslashes.
+    let entry = if cfg!(windows) {
+        entry
+    } else {
+        entry.replace('\\', "/")
+    };
+
+    let candidate = base_dir.join(&entry);
+    if candidate.is_file() {
+        return Ok(Some(canonical_path_string(&candidate.to_string_lossy())));
+    }
+    let wanted = tail_components(Path::new(&entry));
+    let Some(file_name) = wanted.last() else {
+        return Ok(None);
+    };
+    // Only tracks with the same file name can share a tail with the entry.
+    Ok(library_paths_named(file_name)?
+        .into_iter()
+        .filter_map(|track| {
+            let shared = tail_components(Path::new(&track))
+                .iter()
+                .rev()
+                .zip(wanted.iter().rev())
+                .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+                .count();
+            (shared > 0).then_some((shared, track))
+        })
+        .max_by_key(|(shared, _)| *shared)
+        .map(|(_, track)| track))
+}
+
+fn tail_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let decoded = String::from_utf8_lossy(&out).into_owned();
+    // `file:///C:/...` on Windows.
+    match decoded.strip_prefix('/') {
+        Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => rest.to_string(),
+        _ => decoded,
+    }
+}
+
+/// `base`, or the first of `base (2)`, `base (3)`, … that no playlist is named yet.
+pub fn unused_playlist_name(db: &DbManager, base: &str) -> Result<String, String> {
+    let taken = db
+        .get_playlists()?
+        .into_iter()
+        .map(|playlist| playlist.name.to_lowercase())
+        .collect::<Vec<_>>();
+    let mut name = base.to_string();
+    let mut number = 2;
+    while taken.contains(&name.to_lowercase()) {
+        name = format!("{base} ({number})");
+        number += 1;
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_m3u8, import_playlist, parse_m3u, resolve_entry};
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::library::paths::canonical_path_string;
+    use crate::test_support::unique_temp_path;
+    use proptest::prelude::*;
+    use std::path::Path;
+
+    #[test]
+    fn m3u_tolerates_bom_line_endings_and_dangling_extinf() {
+        let text = "\u{feff}#EXTM3U\r\n#EXTINF:12,No file\r\n#EXTINF:30,Song\r\n\
+                    Album/song.mp3\r  ../up.flac \rhttp://radio.example/live\n\n#EXTINF:-1,Last";
+        assert_eq!(
+            parse_m3u(text),
+            ["Album/song.mp3", "../up.flac", "http://radio.example/live"]
+        );
+        let library = ["/music/Album/song.mp3".to_string()];
+        assert_eq!(
+            resolve_entry("file:/elsewhere/Album/song.mp3", Path::new("/"), |_| Ok(
+                library.to_vec()
+            )),
+            Ok(Some(library[0].clone()))
+        );
+        assert_eq!(
+            resolve_entry("HTTPS://radio.example/live", Path::new("/"), |_| Ok(
+                library.to_vec()
+            )),
+            Ok(None)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn m3u_keeps_exactly_the_entry_lines(
+            lines in prop::collection::vec(
+                prop_oneof![
+                    "#EXT[A-Z]{0,6}(:-?[0-9]{0,3},[^\r\n]{0,12})?",
+                    "[^#\r\n\u{feff}]{0,24}",
+                    Just(String::new()),
+                ],
+                0..12,
+            ),
+            ending in prop_oneof![Just("\n"), Just("\r\n"), Just("\r")],
+        ) {
+            let expected = lines
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect::<Vec<_>>();
+            prop_assert_eq!(parse_m3u(&lines.join(ending)), expected);
+        }
+
+        #[test]
+        fn resolving_any_entry_never_panics(entry in "\\PC{0,40}") {
+            let library = ["/music/Band/Album/song.flac".to_string()];
+            let _ = resolve_entry(&entry, Path::new("/nonexistent"), |_| Ok(library.to_vec()));
+        }
+    }
+
+    #[test]
+    fn playlists_round_trip_through_m3u8_and_pls() {
+        let dir = unique_temp_path("playlist-files");
+        std::fs::create_dir_all(dir.join("Album")).expect("create dirs");
+        let db = DbManager::new(dir.join("library.db")).expect("db init");
+        db.initialize_playlist_schema().expect("schema");
+        let local = dir.join("Album").join("01 Intro.flac");
+        std::fs::write(&local, b"x").expect("write track");
+        let local = canonical_path_string(&local.to_string_lossy());
+        // A library track the playlist names by a path from another machine.
+        db.save_track(&TrackInput {
+            path: "/library/Band/Album/02 Song.flac".to_string(),
+            title: Some("Song".to_string()),
+            artist: Some("Band".to_string()),
+            album: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            composer: None,
+            duration_seconds: Some(183.4),
+            sample_rate: None,
+            art_url: None,
+            corrupted: false,
+        })
+        .expect("save track");
+
+        let m3u = dir.join("Mix.m3u");
+        std::fs::write(
+            &m3u,
+            "#EXTM3U\n#EXTINF:10,Intro\nAlbum/01 Intro.flac\n\
+             D:\\Music\\Band\\Album\\02 Song.flac\nhttp://radio.example/live\nmissing.mp3\n",
+        )
+        .expect("write m3u");
+        let import = import_playlist(&db, &m3u, None).expect("import m3u");
+        assert_eq!((import.name.as_str(), import.imported), ("Mix", 2));
+        assert_eq!(import.missing, ["http://radio.example/live", "missing.mp3"]);
+        let tracks = db.get_playlist_tracks(import.id).expect("tracks");
+        assert_eq!(
+            tracks,
+            [
+                local.clone(),
+                "/library/Band/Album/02 Song.flac".to_string()
+            ]
+        );
+
+        let pls = dir.join("Mix.pls");
+        std::fs::write(
+            &pls,
+            "[playlist]\nFile2=Album/01 Intro.flac\nFile1=02 Song.flac\nNumberOfEntries=2\n",
+        )
+        .expect("write pls");
+        let import = import_playlist(&db, &pls, None).expect("import pls");
+        assert_eq!(import.name, "Mix (2)");
+        assert_eq!(db.get_playlist_tracks(import.id).expect("tracks")[1], local);
+
+        let out = dir.join("out.m3u8");
+        export_m3u8(&db, &tracks, &out).expect("export");
+        let written = std::fs::read_to_string(&out).expect("read export");
+        let expected_local = std::path::Path::new("Album").join("01 Intro.flac");
+        assert_eq!(
+            written,
+            format!(
+                "#EXTM3U\n{}\n#EXTINF:183,Band - Song\n/library/Band/Album/02 Song.flac\n",
+                expected_local.display()
+            )
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::db::manager::DbManager;
+use crate::library::queue::PlaybackQueue;
+
+/// Inserts `paths` into playlist `id` before entry `position`, or at the end when it
+/// is `None` or past the end. Returns the updated entries.
+pub fn add_tracks(
+    db: &DbManager,
+    id: i64,
+    paths: Vec<String>,
+    position: Option<usize>,
+) -> Result<Vec<String>, String> {
+    db.edit_playlist_tracks(id, |tracks| {
+        let position = position.unwrap_or(tracks.len()).min(tracks.len());
+        tracks.splice(position..position, paths);
+        Ok(())
+    })
+}
+
+/// Removes the entries at `indices` (positions in the playlist, not paths, so one copy
+/// of a repeated track can go). Out-of-range indices are ignored.
+pub fn remove_tracks(db: &DbManager, id: i64, indices: &[usize]) -> Result<Vec<String>, String> {
+    db.edit_playlist_tracks(id, |tracks| {
+        *tracks = std::mem::take(tracks)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !indices.contains(index))
+            .map(|(_, path)| path)
+            .collect();
+        Ok(())
+    })
+}
+
+/// Moves the entry at `from` so it ends up at index `to`.
+pub fn move_track(db: &DbManager, id: i64, from: usize, to: usize) -> Result<Vec<String>, String> {
+    db.edit_playlist_tracks(id, |tracks| {
+        if from >= tracks.len() {
+            return Err(format!(
+                "Playlist {id} has no entry {from} ({} entries)",
+                tracks.len()
+            ));
+        }
+        let track = tracks.remove(from);
+        tracks.insert(to.min(tracks.len()), track);
+        Ok(())
+    })
+}
+
+/// Replaces the playback queue with playlist `id`, positioned on `start_index`. The
+/// shuffle setting is kept, as with `set_queue`. Returns the queued paths.
+pub fn load_into_queue(
+    db: &DbManager,
+    queue: &mut PlaybackQueue,
+    id: i64,
+    start_index: usize,
+) -> Result<Vec<String>, String> {
+    let tracks = db.get_playlist_tracks(id)?;
+    let shuffle = queue.is_shuffle_enabled();
+    queue.set_tracks(tracks.clone());
+    queue.jump_to(start_index);
+    queue.toggle_shuffle(shuffle);
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_tracks, load_into_queue, move_track, remove_tracks};
+    use crate::db::manager::DbManager;
+    use crate::library::queue::PlaybackQueue;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn playlist_entries_are_edited_by_position() {
+        let db = DbManager::new(unique_temp_path("playlist-edit.db")).expect("db init");
+        db.initialize_playlist_schema().expect("schema");
+        let id = db.create_playlist("Mix").expect("create");
+        let path = |name: &str| format!("/music/{name}.flac");
+
+        add_tracks(&db, id, vec![path("a"), path("c")], None).expect("add");
+        let tracks = add_tracks(&db, id, vec![path("b"), path("a")], Some(1)).expect("insert");
+        assert_eq!(tracks, [path("a"), path("b"), path("a"), path("c")]);
+
+        // Only the second copy of "a" goes.
+        let tracks = remove_tracks(&db, id, &[2, 9]).expect("remove");
+        assert_eq!(tracks, [path("a"), path("b"), path("c")]);
+        let tracks = move_track(&db, id, 2, 0).expect("move");
+        assert_eq!(tracks, [path("c"), path("a"), path("b")]);
+        assert!(move_track(&db, id, 3, 0).is_err());
+
+        let mut queue = PlaybackQueue::new();
+        load_into_queue(&db, &mut queue, id, 1).expect("load");
+        assert_eq!(queue.current_track(), Some(path("a").as_str()));
+        assert!(load_into_queue(&db, &mut queue, id + 1, 0).is_err());
+    }
+
+    #[test]
+    fn concurrent_adds_all_land() {
+        let db = DbManager::new(unique_temp_path("playlist-concurrent.db")).expect("db init");
+        db.initialize_playlist_schema().expect("schema");
+        let id = db.create_playlist("Mix").expect("create");
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let db = &db;
+                scope.spawn(move || {
+                    for track in 0..5 {
+                        let path = format!("/music/{thread}-{track}.flac");
+                        add_tracks(db, id, vec![path], None).expect("add");
+                    }
+                });
+            }
+        });
+        assert_eq!(db.get_playlist_tracks(id).expect("tracks").len(), 40);
+    }
+}
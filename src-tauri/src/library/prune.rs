@@ -0,0 +1,147 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use crate::audio::lyrics_downloader;
+use crate::db::manager::DbManager;
+use crate::library::paths::canonical_path_string;
+use crate::library::{art_cache, cue};
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PruneReport {
+    /// Library tracks whose file was checked.
+    pub checked: usize,
+    pub removed: usize,
+    pub removed_paths: Vec<String>,
+    /// Library roots that are unreachable right now, such as unplugged drives. Their
+    /// tracks are kept.
+    pub skipped_roots: Vec<String>,
+}
+
+/// Deletes the library rows whose files no longer exist, under `root` when given, with
+/// their per-track settings and the cover thumbnails and lyrics cached for them. Cue sheet songs go with their
+/// sheet.
+pub fn prune_missing_tracks(db: &DbManager, root: Option<&Path>) -> Result<PruneReport, String> {
+    let prefix = root.map(|root| {
+        let root = canonical_path_string(&root.to_string_lossy());
+        format!("{}{MAIN_SEPARATOR}", root.trim_end_matches(MAIN_SEPARATOR))
+    });
+    let unreachable = db
+        .get_library_roots()?
+        .into_iter()
+        .map(|root| root.path)
+        .filter(|root| !Path::new(root).is_dir())
+        .collect::<Vec<_>>();
+    let under = |path: &str, dir: &str| {
+        path.strip_prefix(dir.trim_end_matches(MAIN_SEPARATOR))
+            .is_some_and(|rest| rest.starts_with(MAIN_SEPARATOR))
+    };
+
+    let mut report = PruneReport::default();
+    for track in db.get_tracks()? {
+        if prefix
+            .as_deref()
+            .is_some_and(|prefix| !track.path.starts_with(prefix))
+        {
+            continue;
+        }
+        if let Some(root) = unreachable.iter().find(|root| under(&track.path, root)) {
+            if !report.skipped_roots.contains(root) {
+                report.skipped_roots.push(root.clone());
+            }
+            continue;
+        }
+        report.checked += 1;
+        let path = Path::new(&track.path);
+        let file =
+            cue::split_virtual_path(path).map_or_else(|| path.to_path_buf(), |(sheet, _)| sheet);
+        if file.exists() {
+            continue;
+        }
+        db.delete_track_and_settings(&track.path)?;
+        for cached in cached_files(path) {
+            let _ = fs::remove_file(cached);
+        }
+        report.removed_paths.push(track.path);
+    }
+    report.removed = report.removed_paths.len();
+    Ok(report)
+}
+
+/// Cache entries keyed by the track path alone. Waveforms are keyed by the file's
+/// size and modification time as well, so the cache's own pruning removes them.
+pub fn cached_files(track_path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![lyrics_downloader::cached_lyrics_path(track_path)];
+    files.extend(art_cache::cached_thumbnail(track_path));
+    files.retain(|file| file.is_file());
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prune_missing_tracks;
+    use crate::db::manager::{DbManager, TrackInput};
+    use crate::library::paths::canonical_path_string;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn prunes_missing_files_but_keeps_unreachable_roots() {
+        let dir = unique_temp_path("prune");
+        std::fs::create_dir_all(dir.join("music")).expect("create dir");
+        let db = DbManager::new(dir.join("library.db")).expect("db init");
+        db.initialize_library_root_schema().expect("schema");
+        db.initialize_spatial_schema().expect("spatial schema");
+        let music = canonical_path_string(&dir.join("music").to_string_lossy());
+        let unplugged = canonical_path_string(&dir.join("usb").to_string_lossy());
+        db.add_library_root(&music).expect("add root");
+        db.add_library_root(&unplugged).expect("add root");
+
+        let present = dir.join("music").join("present.flac");
+        std::fs::write(&present, b"x").expect("write track");
+        let present = canonical_path_string(&present.to_string_lossy());
+        let gone = format!("{music}{}gone.flac", std::path::MAIN_SEPARATOR);
+        let on_usb = format!("{unplugged}{}song.flac", std::path::MAIN_SEPARATOR);
+        for path in [&present, &gone, &on_usb] {
+            db.save_track(&TrackInput {
+                path: path.clone(),
+                title: None,
+                artist: None,
+                album: None,
+                genre: None,
+                year: None,
+                track_number: None,
+                disc_number: None,
+                album_artist: None,
+                composer: None,
+                duration_seconds: None,
+                sample_rate: None,
+                art_url: None,
+                corrupted: false,
+            })
+            .expect("save track");
+        }
+
+        db.save_spatial_scene(&gone, "vocals", 1.0, 0.0, 0.0, true)
+            .expect("save scene");
+
+        let report = prune_missing_tracks(&db, None).expect("prune");
+        assert_eq!((report.checked, report.removed), (2, 1));
+        assert_eq!(report.removed_paths, std::slice::from_ref(&gone));
+        assert_eq!(report.skipped_roots, [unplugged]);
+        let mut remaining = db
+            .get_tracks()
+            .expect("tracks")
+            .into_iter()
+            .map(|track| track.path)
+            .collect::<Vec<_>>();
+        remaining.sort();
+        let mut expected = vec![present, on_usb];
+        expected.sort();
+        assert_eq!(remaining, expected);
+        assert!(
+            db.load_spatial_scene(&gone).expect("scene").is_empty(),
+            "pruned tracks lose their settings"
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,188 @@
+use reqwest::blocking::Client;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::audio::stream::extension_for_content_type;
+use crate::library::art_cache::prune_flat_cache_dir;
+use crate::library::extensions::is_supported_audio_path;
+use crate::library::network_usage::{self, NetworkProvider};
+
+/// Remote tracks can be large, so only the most recently played few stay on disk.
+const REMOTE_CACHE_MAX_FILES: usize = 16;
+const DOWNLOAD_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// Applied to each body read by the blocking client, not to the whole download.
+const DOWNLOAD_READ_TIMEOUT_SECS: u64 = 30;
+/// Progress is reported at most once per this many bytes, plus once at the end.
+const PROGRESS_STEP_BYTES: u64 = 256 * 1024;
+
+/// Payload of the `remote-download-progress` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct DownloadProgress {
+    pub url: String,
+    pub downloaded_bytes: u64,
+    /// `None` when the server sends no `Content-Length`.
+    pub total_bytes: Option<u64>,
+}
+
+pub fn is_remote_url(path: &str) -> bool {
+    let lower = path.trim_start().to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir()
+        .join("powerplayer")
+        .join("remote_cache")
+}
+
+/// Downloads `url` into the remote cache, or reuses an earlier download of it, and
+/// returns the local file so it can be decoded like any library track.
+pub fn fetch(url: &str, mut on_progress: impl FnMut(DownloadProgress)) -> Result<PathBuf, String> {
+    let url = url.trim();
+    if !is_remote_url(url) {
+        return Err(format!("Unsupported remote URL: {url}"));
+    }
+    let dir = cache_dir();
+    let key = cache_key(url);
+    if let Some(cached) = find_cached(&dir, &key) {
+        return Ok(cached);
+    }
+
+    let mut response = download_client()?
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let extension = audio_extension(response.url().path(), content_type.as_deref())
+        .ok_or_else(|| format!("Remote file is not a supported audio type: {url}"))?;
+    let total_bytes = response.content_length();
+
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create remote cache {}: {e}", dir.display()))?;
+    let target = dir.join(format!("{key}.{extension}"));
+    // Written under a temporary name so an interrupted download is never mistaken for
+    // a cached track.
+    let partial = dir.join(format!("{key}.part"));
+    let result = download_to(&mut response, &partial, |downloaded_bytes| {
+        on_progress(DownloadProgress {
+            url: url.to_string(),
+            downloaded_bytes,
+            total_bytes,
+        })
+    });
+    let downloaded = match result {
+        Ok(downloaded) => downloaded,
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            return Err(format!("Failed to download {url}: {err}"));
+        }
+    };
+    network_usage::record(NetworkProvider::Streams, downloaded);
+    fs::rename(&partial, &target)
+        .map_err(|e| format!("Failed to store download {}: {e}", target.display()))?;
+    prune_flat_cache_dir(&dir, REMOTE_CACHE_MAX_FILES);
+    Ok(target)
+}
+
+/// HTTP client for large file downloads, with a timeout per read rather than per file.
+pub fn download_client() -> Result<Client, String> {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(DOWNLOAD_CONNECT_TIMEOUT_SECS))
+        .timeout(Duration::from_secs(DOWNLOAD_READ_TIMEOUT_SECS))
+        .user_agent("PowerPlayer/0.1")
+        .build()
+        .map_err(|e| format!("Failed to build download HTTP client: {e}"))
+}
+
+/// Copies `source` into a new file at `destination`, reporting the bytes written so far.
+pub fn download_to(
+    source: &mut impl Read,
+    destination: &Path,
+    mut on_progress: impl FnMut(u64),
+) -> Result<u64, String> {
+    let mut file = File::create(destination).map_err(|e| e.to_string())?;
+    let mut buffer = vec![0_u8; 64 * 1024];
+    let mut downloaded = 0_u64;
+    let mut last_reported = 0_u64;
+    on_progress(0);
+    loop {
+        let read = source.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+        downloaded += read as u64;
+        if downloaded - last_reported >= PROGRESS_STEP_BYTES {
+            last_reported = downloaded;
+            on_progress(downloaded);
+        }
+    }
+    file.flush().map_err(|e| e.to_string())?;
+    if last_reported != downloaded {
+        on_progress(downloaded);
+    }
+    Ok(downloaded)
+}
+
+fn cache_key(url: &str) -> String {
+    let mut hash = Sha256::new();
+    hash.update(url.as_bytes());
+    format!("{:x}", hash.finalize())
+}
+
+fn find_cached(dir: &Path, key: &str) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem().and_then(|stem| stem.to_str()) == Some(key)
+                && is_supported_audio_path(path)
+        })
+}
+
+/// Extension the decoder should probe with: the URL's own when it names a supported
+/// audio type, otherwise the one implied by the response content type.
+fn audio_extension(url_path: &str, content_type: Option<&str>) -> Option<String> {
+    let from_url = Path::new(url_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .filter(|extension| is_supported_audio_path(Path::new(&format!("x.{extension}"))));
+    from_url.or_else(|| {
+        content_type
+            .and_then(extension_for_content_type)
+            .map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{audio_extension, is_remote_url};
+
+    #[test]
+    fn picks_the_decoder_extension_from_url_or_content_type() {
+        assert!(is_remote_url("https://example.com/a.mp3"));
+        assert!(is_remote_url("HTTP://example.com/a.mp3"));
+        assert!(!is_remote_url("C:\\Music\\a.mp3"));
+
+        assert_eq!(
+            audio_extension("/shared/Song.FLAC", Some("application/octet-stream")),
+            Some("flac".to_string())
+        );
+        assert_eq!(
+            audio_extension("/download", Some("audio/mpeg; charset=binary")),
+            Some("mp3".to_string())
+        );
+        assert_eq!(audio_extension("/page.html", Some("text/html")), None);
+    }
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use crate::db::manager::DbManager;
+
+/// Files at least this long (or `.m4b` audiobooks, or files with chapters) are treated
+/// as long-form and resume where they stopped; music always starts from the top.
+const LONG_FORM_MIN_SECONDS: f64 = 20.0 * 60.0;
+/// Positions this close to the start are not worth a bookmark.
+const RESUME_MIN_SECONDS: f64 = 10.0;
+/// Stopping this close to the end counts as finished, so the bookmark is cleared.
+const RESUME_END_MARGIN_SECONDS: f64 = 30.0;
+
+pub fn is_long_form(path: &Path, duration_seconds: f64, chapter_count: usize) -> bool {
+    let audiobook = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("m4b"));
+    audiobook || chapter_count > 0 || duration_seconds >= LONG_FORM_MIN_SECONDS
+}
+
+/// Bookmark to store for `position_seconds`: `None` near either end of the track.
+fn bookmark_for(position_seconds: f64, duration_seconds: f64) -> Option<f64> {
+    (position_seconds.is_finite()
+        && position_seconds >= RESUME_MIN_SECONDS
+        && position_seconds < duration_seconds - RESUME_END_MARGIN_SECONDS)
+        .then_some(position_seconds)
+}
+
+/// Saves (or clears, near the start or end) the resume position of a long-form track.
+pub fn remember_position(
+    db: &DbManager,
+    path: &str,
+    position_seconds: f64,
+    duration_seconds: f64,
+) -> Result<(), String> {
+    db.save_resume_position(path, bookmark_for(position_seconds, duration_seconds))
+        .map(|_| ())
+}
+
+/// Stored resume position of `path`, if it still lies inside the track.
+pub fn saved_position(
+    db: &DbManager,
+    path: &str,
+    duration_seconds: f64,
+) -> Result<Option<f64>, String> {
+    Ok(db
+        .get_resume_position(path)?
+        .and_then(|seconds| bookmark_for(seconds, duration_seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bookmark_for, is_long_form};
+    use std::path::Path;
+
+    #[test]
+    fn long_form_detection() {
+        assert!(is_long_form(Path::new("/books/novel.M4B"), 60.0, 0));
+        assert!(is_long_form(Path::new("/talks/lecture.mp3"), 3_600.0, 0));
+        assert!(is_long_form(Path::new("/music/mix.m4a"), 300.0, 4));
+        assert!(!is_long_form(Path::new("/music/song.flac"), 240.0, 0));
+    }
+
+    #[test]
+    fn bookmarks_skip_the_edges_of_the_track() {
+        assert_eq!(bookmark_for(1_200.0, 3_600.0), Some(1_200.0));
+        assert_eq!(bookmark_for(5.0, 3_600.0), None);
+        assert_eq!(bookmark_for(3_590.0, 3_600.0), None);
+        assert_eq!(bookmark_for(f64::NAN, 3_600.0), None);
+    }
+}
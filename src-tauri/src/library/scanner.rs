@@ -1,64 +1,421 @@
+use crate::audio::codecs;
 use crate::audio::decoder::read_track_metadata;
-use crate::db::manager::{DbManager, TrackInput};
+use crate::db::album_store::VARIOUS_ARTISTS;
+use crate::db::manager::{DbManager, FileStamp, TrackInput, TrackRecord};
 use crate::library::art_cache;
+use crate::library::cue::{self, is_cue_path, read_cue_sheet};
 use crate::library::enrichment_queue;
+use crate::library::exclusions;
+use crate::library::extensions::is_supported_audio_path;
 use crate::library::metadata::art_fetcher;
+use crate::library::paths::canonical_path;
 use id3::TagLike;
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use rayon::prelude::*;
-use std::collections::HashSet;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use symphonia::core::{
     formats::FormatOptions,
     io::MediaSourceStream,
-    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value},
     probe::Hint,
 };
 use walkdir::WalkDir;
 
-pub fn scan_library_path(root: &Path, db: &DbManager) -> Result<usize, String> {
-    let files = collect_audio_files(root);
+/// Minimum time between two progress reports of a running scan.
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Quiet time after the last event for a path before the watcher handles it, so files
+/// still being copied are read once, complete.
+const WATCH_SETTLE_WINDOW: Duration = Duration::from_secs(2);
+/// How often the watcher looks for paths that went quiet.
+const WATCH_SETTLE_TICK: Duration = Duration::from_millis(500);
+/// How long the watchdog waits for a root's metadata before treating it as unreachable,
+/// so a hung network share cannot stall the checks of the other roots.
+const WATCHDOG_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Payload of the `scan-progress` events.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ScanProgress {
+    pub root: String,
+    /// Audio files and cue sheets found under the root.
+    pub discovered: usize,
+    pub processed: usize,
+    pub errors: usize,
+    pub current_path: Option<String>,
+    pub finished: bool,
+    /// Set once `cancel_scan` stopped the scan; files not processed yet are skipped.
+    pub cancelled: bool,
+}
+
+/// Cancellation flags of the scans in progress, by canonical root.
+fn running_scans() -> &'static Mutex<HashMap<PathBuf, Arc<AtomicBool>>> {
+    static SCANS: OnceLock<Mutex<HashMap<PathBuf, Arc<AtomicBool>>>> = OnceLock::new();
+    SCANS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Registers a scan of `root` for the guard's lifetime.
+struct RunningScan {
+    root: PathBuf,
+    cancel: Arc<AtomicBool>,
+}
+
+impl RunningScan {
+    fn start(root: &Path) -> Result<Self, String> {
+        let root = canonical_path(root);
+        let mut scans = running_scans()
+            .lock()
+            .map_err(|_| "Running scans lock poisoned".to_string())?;
+        if scans.contains_key(&root) {
+            return Err(format!("A scan of {} is already running", root.display()));
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        scans.insert(root.clone(), Arc::clone(&cancel));
+        Ok(Self { root, cancel })
+    }
+}
+
+impl Drop for RunningScan {
+    fn drop(&mut self) {
+        if let Ok(mut scans) = running_scans().lock() {
+            scans.remove(&self.root);
+        }
+    }
+}
+
+/// Stops the running scan of `root`, or every running scan when it is `None`. Returns
+/// how many scans were told to stop.
+pub fn cancel_scan(root: Option<&Path>) -> usize {
+    let Ok(scans) = running_scans().lock() else {
+        return 0;
+    };
+    let root = root.map(canonical_path);
+    let mut cancelled = 0;
+    for (scan_root, cancel) in scans.iter() {
+        if root.as_ref().is_none_or(|root| root == scan_root) {
+            cancel.store(true, Ordering::SeqCst);
+            cancelled += 1;
+        }
+    }
+    cancelled
+}
+
+/// Scans `root` into the library, reporting progress to `on_progress` once the files
+/// are discovered, at most every `SCAN_PROGRESS_INTERVAL` while they are processed,
+/// and when the scan finishes. Returns the number of tracks saved or kept, or an error
+/// when the root is already being scanned or the scan was cancelled.
+pub fn scan_library_path(
+    root: &Path,
+    db: &DbManager,
+    on_progress: impl Fn(ScanProgress) + Sync,
+) -> Result<usize, String> {
+    let scan = RunningScan::start(root)?;
+    let (cue_sheets, files): (Vec<_>, Vec<_>) =
+        collect_audio_files(root).into_iter().partition(|path| is_cue_path(path));
     let saved_count = AtomicUsize::new(0);
+    let stamps = db.get_file_stamps()?;
+    let progress = ScanReporter {
+        root: root.to_string_lossy().into_owned(),
+        discovered: cue_sheets.len() + files.len(),
+        processed: AtomicUsize::new(0),
+        errors: AtomicUsize::new(0),
+        last_report: Mutex::new(Instant::now()),
+        cancel: Arc::clone(&scan.cancel),
+        on_progress,
+    };
+    progress.report(None, false);
+
+    // Files split by a cue sheet are listed as its songs instead of as one long track.
+    let mut covered = HashSet::new();
+    for cue_path in &cue_sheets {
+        if progress.is_cancelled() {
+            break;
+        }
+        let result = index_cue_sheet(cue_path, db);
+        match &result {
+            Ok((sources, songs)) => {
+                covered.extend(sources.iter().cloned());
+                saved_count.fetch_add(*songs, Ordering::Relaxed);
+            }
+            Err(err) => eprintln!("Failed to index cue sheet {}: {err}", cue_path.display()),
+        }
+        progress.file_done(cue_path, result.is_err());
+    }
 
     files.par_iter().for_each(|path| {
-        let track = extract_track(path);
-        if track.corrupted {
-            eprintln!("Persisting track marked as corrupted: {}", track.path);
+        if progress.is_cancelled() {
+            return;
         }
-        match db.save_track(&track) {
-            Ok(_) => {
+        if covered.contains(path) {
+            progress.file_done(path, false);
+            return;
+        }
+        // Unchanged since the last scan: the row, its art and enrichment stand.
+        let unchanged = file_stamp(path)
+            .is_some_and(|stamp| stamps.get(path.to_string_lossy().as_ref()) == Some(&stamp));
+        let result = if unchanged {
+            Ok(true)
+        } else {
+            index_file(path, db)
+        };
+        match &result {
+            Ok(true) => {
                 saved_count.fetch_add(1, Ordering::Relaxed);
-                enrichment_queue::enqueue(track.clone(), db.clone());
             }
+            Ok(false) => {}
             Err(err) => {
-                eprintln!("Failed to persist track {}: {err}", track.path);
+                eprintln!("Failed to persist track {}: {err}", path.display());
             }
         }
+        progress.file_done(path, result.is_err());
     });
 
+    progress.report(None, true);
+    if progress.is_cancelled() {
+        return Err(format!("Scan of {} was cancelled", root.display()));
+    }
+    if let Err(err) = db.group_compilations() {
+        eprintln!("Failed to group compilations: {err}");
+    }
     Ok(saved_count.load(Ordering::Relaxed))
 }
 
+/// Counts the files a scan has processed and throttles its progress reports, which
+/// come from all of the scan's threads.
+struct ScanReporter<F> {
+    root: String,
+    discovered: usize,
+    processed: AtomicUsize,
+    errors: AtomicUsize,
+    last_report: Mutex<Instant>,
+    cancel: Arc<AtomicBool>,
+    on_progress: F,
+}
+
+impl<F: Fn(ScanProgress)> ScanReporter<F> {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    fn file_done(&self, path: &Path, failed: bool) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        // Another thread reporting right now covers this file too.
+        let Ok(mut last_report) = self.last_report.try_lock() else {
+            return;
+        };
+        if last_report.elapsed() < SCAN_PROGRESS_INTERVAL {
+            return;
+        }
+        *last_report = Instant::now();
+        drop(last_report);
+        self.report(Some(path), false);
+    }
+
+    fn report(&self, current_path: Option<&Path>, finished: bool) {
+        (self.on_progress)(ScanProgress {
+            root: self.root.clone(),
+            discovered: self.discovered,
+            processed: self.processed.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            current_path: current_path.map(|path| path.to_string_lossy().into_owned()),
+            finished,
+            cancelled: self.is_cancelled(),
+        });
+    }
+}
+
+/// Reads `path` into the library with the stamp it had before reading, so the next
+/// scan can skip it while it stays unchanged, and queues it for enrichment. Files below
+/// the duration or bitrate minimums of the scan exclusions are removed from the library
+/// instead; returns whether the file was saved.
+fn index_file(path: &Path, db: &DbManager) -> Result<bool, String> {
+    let Some((track, stamp)) = read_file(path) else {
+        db.delete_track(&path.to_string_lossy())?;
+        return Ok(false);
+    };
+    db.save_track(&track)?;
+    if let Some(stamp) = stamp {
+        db.save_file_stamp(&track.path, stamp)?;
+    }
+    enrichment_queue::enqueue(track, db.clone());
+    Ok(true)
+}
+
+/// Reads `path` again, such as after its tags were edited in another program, replacing
+/// its library row and cached cover. Returns the updated row, or `None` when the file
+/// now falls below the scan exclusions' minimums and was removed from the library.
+pub fn rescan_track(path: &Path, db: &DbManager) -> Result<Option<TrackRecord>, String> {
+    if !path.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    let path = canonical_path(path);
+    // The thumbnail is only written when missing, so a changed cover needs it gone.
+    if let Some(thumbnail) = art_cache::cached_thumbnail(&path) {
+        std::fs::remove_file(&thumbnail)
+            .map_err(|e| format!("Failed to remove cached art {}: {e}", thumbnail.display()))?;
+    }
+    if !index_file(&path, db)? {
+        return Ok(None);
+    }
+    if let Err(err) = db.group_compilations() {
+        eprintln!("Failed to group compilations: {err}");
+    }
+    db.get_track(&path.to_string_lossy())
+}
+
+/// Like `index_file` for several files: reads them in parallel and saves them in one
+/// transaction.
+pub fn index_files(files: &[PathBuf], db: &DbManager) {
+    let mut tracks = Vec::new();
+    for (path, read) in files
+        .par_iter()
+        .map(|path| (path, read_file(path)))
+        .collect::<Vec<_>>()
+    {
+        match read {
+            Some(read) => tracks.push(read),
+            None => {
+                if let Err(err) = db.delete_track(&path.to_string_lossy()) {
+                    eprintln!("Failed to delete excluded track {}: {err}", path.display());
+                }
+            }
+        }
+    }
+    if tracks.is_empty() {
+        return;
+    }
+    if let Err(err) = db.save_indexed_tracks(&tracks) {
+        eprintln!("Failed to persist {} watched tracks: {err}", tracks.len());
+        return;
+    }
+    if let Err(err) = db.group_compilations() {
+        eprintln!("Failed to group compilations: {err}");
+    }
+    for (track, _) in tracks {
+        enrichment_queue::enqueue(track, db.clone());
+    }
+}
+
+/// Metadata of `path` with the stamp the file had before it was read, or `None` when
+/// the file falls below the duration or bitrate minimums of the scan exclusions.
+fn read_file(path: &Path) -> Option<(TrackInput, Option<FileStamp>)> {
+    let stamp = file_stamp(path);
+    let track = extract_track(path);
+    if exclusions::is_excluded_audio(path, track.duration_seconds) {
+        return None;
+    }
+    if track.corrupted {
+        eprintln!("Persisting track marked as corrupted: {}", track.path);
+    }
+    Some((track, stamp))
+}
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(FileStamp {
+        modified_ms: i64::try_from(modified.as_millis()).ok()?,
+        size: i64::try_from(metadata.len()).ok()?,
+    })
+}
+
 fn collect_audio_files(root: &Path) -> Vec<PathBuf> {
-    WalkDir::new(root)
+    let root = canonical_path(root);
+    let mut files = WalkDir::new(&root)
         .into_iter()
+        // Excluded folders are not descended into.
+        .filter_entry(|entry| !exclusions::is_excluded_path(&root, entry.path()))
         .filter_map(Result::ok)
         .filter(|entry| entry.file_type().is_file())
         .map(|entry| entry.path().to_path_buf())
-        .filter(|path| {
-            path.extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| {
-                    matches!(
-                        ext.to_ascii_lowercase().as_str(),
-                        "flac" | "mp3" | "m4a" | "ogg" | "wav"
-                    )
-                })
-                .unwrap_or(false)
-        })
-        .collect()
+        .filter(|path| is_supported_audio_path(path) || is_cue_path(path))
+        .map(|path| canonical_path(&path))
+        .collect::<Vec<_>>();
+    // Symlinks inside the root can point at files that are also reached directly.
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Saves one virtual track per song of `cue_path` (see `library::cue`) and removes the
+/// whole-file rows of the audio files it splits, plus songs no longer in the sheet.
+/// Returns the audio files the sheet covers and the number of songs saved.
+fn index_cue_sheet(cue_path: &Path, db: &DbManager) -> Result<(Vec<PathBuf>, usize), String> {
+    let tracks = read_cue_sheet(cue_path)?;
+    let mut sources: HashMap<PathBuf, Option<TrackInput>> = HashMap::new();
+    let mut saved = Vec::new();
+
+    for cue_track in &tracks {
+        let source = canonical_path(&cue_track.file);
+        let file_track = sources.entry(source.clone()).or_insert_with(|| {
+            if source.exists() {
+                Some(extract_track(&source))
+            } else {
+                eprintln!(
+                    "Cue sheet {} references missing file {}",
+                    cue_path.display(),
+                    source.display()
+                );
+                None
+            }
+        });
+        let Some(file_track) = file_track else {
+            continue;
+        };
+
+        let end_seconds = cue_track
+            .end_seconds()
+            .or_else(|| file_track.duration_seconds.map(f64::from));
+        let track = TrackInput {
+            path: cue::virtual_track_path(cue_path, cue_track.number),
+            title: cue_track
+                .title
+                .clone()
+                .or_else(|| Some(format!("Track {:02}", cue_track.number))),
+            artist: cue_track
+                .performer
+                .clone()
+                .or_else(|| file_track.artist.clone()),
+            album: cue_track.album.clone().or_else(|| file_track.album.clone()),
+            genre: cue_track.genre.clone().or_else(|| file_track.genre.clone()),
+            year: file_track.year,
+            track_number: Some(cue_track.number),
+            disc_number: file_track.disc_number,
+            album_artist: file_track.album_artist.clone(),
+            composer: file_track.composer.clone(),
+            duration_seconds: end_seconds
+                .map(|end| (end - cue_track.start_seconds()).max(0.0) as f32),
+            sample_rate: file_track.sample_rate,
+            art_url: file_track.art_url.clone(),
+            corrupted: file_track.corrupted,
+        };
+        db.save_track(&track)?;
+        db.save_track_segment(
+            &track.path,
+            &source.to_string_lossy(),
+            cue_track.start_seconds(),
+            cue_track.end_seconds(),
+        )?;
+        saved.push(track.path.clone());
+        enrichment_queue::enqueue(track, db.clone());
+    }
+
+    db.delete_cue_tracks(&cue::virtual_track_prefix(cue_path), &saved)?;
+    let covered = sources
+        .into_iter()
+        .filter_map(|(source, track)| track.map(|_| source))
+        .collect::<Vec<_>>();
+    for source in &covered {
+        db.delete_track(&source.to_string_lossy())?;
+    }
+    Ok((covered, saved.len()))
 }
 
 pub fn register_library_watch(path: &Path, db: &DbManager) -> Result<(), String> {
@@ -68,64 +425,356 @@ pub fn register_library_watch(path: &Path, db: &DbManager) -> Result<(), String>
         .register(path, db)
 }
 
+/// Stops watching library root `path`. Returns `false` when it was not watched.
+pub fn unregister_library_watch(path: &Path) -> Result<bool, String> {
+    Ok(watcher_manager()
+        .lock()
+        .map_err(|_| "Library watcher lock poisoned".to_string())?
+        .roots
+        .remove(&canonical_path(path))
+        .is_some())
+}
+
+/// Watches a library root remembered from an earlier session. A root that cannot be
+/// watched now, such as a disconnected drive, is left to the watchdog to re-arm.
+pub fn restore_library_watch(path: &Path, db: &DbManager) -> Result<(), String> {
+    let mut manager = watcher_manager()
+        .lock()
+        .map_err(|_| "Library watcher lock poisoned".to_string())?;
+    if let Err(err) = manager.register(path, db) {
+        eprintln!("Library root {} is not watched yet: {err}", path.display());
+        manager.roots.insert(
+            canonical_path(path),
+            WatchedRoot {
+                watcher: None,
+                failed: Arc::new(AtomicBool::new(false)),
+                db: db.clone(),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Starts the background watchdog (once per process). Every
+/// `WATCHDOG_INTERVAL_SECS` it checks that each watched root is still reachable
+/// (reading its metadata only, never writing into it) and its watcher has not
+/// reported errors, re-arms the watchers of roots that came back or errored, and calls
+/// `on_degraded` with the roots that are currently not monitored whenever that
+/// set changes (an empty list means everything recovered).
+pub fn start_watcher_watchdog(on_degraded: impl Fn(Vec<String>) + Send + 'static) {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    if STARTED.set(()).is_err() {
+        return;
+    }
+    std::thread::spawn(move || {
+        let mut last_reported: Vec<PathBuf> = Vec::new();
+        loop {
+            std::thread::sleep(Duration::from_secs(WATCHDOG_INTERVAL_SECS));
+            let Ok(roots) = watcher_manager()
+                .lock()
+                .map(|manager| manager.roots.keys().cloned().collect::<Vec<_>>())
+            else {
+                break;
+            };
+            // Probe without the lock so roots can be added meanwhile.
+            let reachable = roots
+                .into_iter()
+                .filter(|root| root_reachable(root, WATCHDOG_PROBE_TIMEOUT))
+                .collect::<HashSet<_>>();
+            let Ok(mut manager) = watcher_manager().lock() else {
+                break;
+            };
+            let report = manager.check_health(|root| reachable.contains(root));
+            drop(manager);
+
+            // Events that happened while a watcher was dead were lost, so catch up.
+            for (root, db) in report.rearmed {
+                if let Err(err) = scan_library_path(&root, &db, |_| {}) {
+                    eprintln!("Failed to rescan re-armed root {}: {err}", root.display());
+                }
+            }
+            if report.degraded != last_reported {
+                on_degraded(
+                    report
+                        .degraded
+                        .iter()
+                        .map(|root| root.to_string_lossy().to_string())
+                        .collect(),
+                );
+                last_reported = report.degraded;
+            }
+        }
+    });
+}
+
 fn watcher_manager() -> &'static Mutex<LibraryWatcherManager> {
     static MANAGER: OnceLock<Mutex<LibraryWatcherManager>> = OnceLock::new();
     MANAGER.get_or_init(|| Mutex::new(LibraryWatcherManager::default()))
 }
 
+const WATCHDOG_INTERVAL_SECS: u64 = 30;
+
+struct WatchedRoot {
+    /// `None` while the root is unreachable or the watcher could not be re-created.
+    watcher: Option<RecommendedWatcher>,
+    /// Set by the notify callback when the backend reports an error.
+    failed: Arc<AtomicBool>,
+    db: DbManager,
+}
+
+/// Whether `root` is a folder that answers a metadata read within `timeout`. The read
+/// runs on its own thread, which is left behind when a share hangs.
+fn root_reachable(root: &Path, timeout: Duration) -> bool {
+    let (sender, receiver) = mpsc::channel();
+    let root = root.to_path_buf();
+    std::thread::spawn(move || {
+        let _ = sender.send(std::fs::metadata(&root).is_ok_and(|metadata| metadata.is_dir()));
+    });
+    receiver.recv_timeout(timeout).unwrap_or(false)
+}
+
+#[derive(Default)]
+struct WatchdogReport {
+    degraded: Vec<PathBuf>,
+    rearmed: Vec<(PathBuf, DbManager)>,
+}
+
 #[derive(Default)]
 struct LibraryWatcherManager {
-    watchers: Vec<RecommendedWatcher>,
-    watched_paths: HashSet<PathBuf>,
+    roots: HashMap<PathBuf, WatchedRoot>,
 }
 
 impl LibraryWatcherManager {
     fn register(&mut self, path: &Path, db: &DbManager) -> Result<(), String> {
-        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-        if self.watched_paths.contains(&canonical) {
+        let canonical = canonical_path(path);
+        if self
+            .roots
+            .get(&canonical)
+            .is_some_and(|root| root.watcher.is_some())
+        {
             return Ok(());
         }
 
-        let db = db.clone();
-        let mut watcher = RecommendedWatcher::new(
-            move |event: notify::Result<Event>| {
-                if let Ok(event) = event {
-                    handle_library_event(event, &db);
-                }
+        let failed = Arc::new(AtomicBool::new(false));
+        let watcher = create_watcher(&canonical, db, &failed)?;
+        self.roots.insert(
+            canonical,
+            WatchedRoot {
+                watcher: Some(watcher),
+                failed,
+                db: db.clone(),
             },
-            Config::default(),
-        )
-        .map_err(|e| format!("Failed to create library watcher: {e}"))?;
-        watcher
-            .watch(&canonical, RecursiveMode::Recursive)
-            .map_err(|e| format!("Failed to watch {}: {e}", canonical.display()))?;
-        self.watched_paths.insert(canonical);
-        self.watchers.push(watcher);
+        );
         Ok(())
     }
+
+    /// Re-arms the watchers of roots that are `reachable` again or reported an error,
+    /// and reports the roots that are not monitored.
+    fn check_health(&mut self, reachable: impl Fn(&Path) -> bool) -> WatchdogReport {
+        let mut report = WatchdogReport::default();
+        for (root, watched) in &mut self.roots {
+            if !reachable(root) {
+                // Drive disconnected or folder removed: release the stale handle so it
+                // is re-created from scratch once the root comes back.
+                watched.watcher = None;
+                report.degraded.push(root.clone());
+                continue;
+            }
+            let errored = watched.failed.swap(false, Ordering::SeqCst);
+            if watched.watcher.is_some() && !errored {
+                continue;
+            }
+
+            watched.watcher = None;
+            match create_watcher(root, &watched.db, &watched.failed) {
+                Ok(watcher) => {
+                    watched.watcher = Some(watcher);
+                    report.rearmed.push((root.clone(), watched.db.clone()));
+                }
+                Err(err) => {
+                    eprintln!("Failed to re-arm library watcher: {err}");
+                    report.degraded.push(root.clone());
+                }
+            }
+        }
+        report.degraded.sort();
+        report
+    }
 }
 
-fn handle_library_event(event: Event, db: &DbManager) {
-    for path in event.paths {
+fn create_watcher(
+    root: &Path,
+    db: &DbManager,
+    failed: &Arc<AtomicBool>,
+) -> Result<RecommendedWatcher, String> {
+    let (changes, receiver) = mpsc::channel::<PathBuf>();
+    let watched_root = root.to_path_buf();
+    let db = db.clone();
+    std::thread::spawn(move || settle_library_changes(&receiver, &watched_root, &db));
+    let failed = Arc::clone(failed);
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<Event>| match event {
+            Ok(event) => {
+                for path in event.paths {
+                    let _ = changes.send(path);
+                }
+            }
+            Err(err) => {
+                eprintln!("Library watcher error: {err}");
+                failed.store(true, Ordering::SeqCst);
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| format!("Failed to create library watcher: {e}"))?;
+    watcher
+        .watch(root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {e}", root.display()))?;
+    Ok(watcher)
+}
+
+/// Collects the paths a root's watcher reports and handles them in batches once they
+/// settle (see `PendingChanges`). Ends when the watcher is dropped.
+fn settle_library_changes(changes: &Receiver<PathBuf>, root: &Path, db: &DbManager) {
+    let mut pending = PendingChanges::default();
+    loop {
+        match changes.recv_timeout(WATCH_SETTLE_TICK) {
+            Ok(path) => pending.touch(path, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let settled = pending.take_settled(Instant::now());
+        if !settled.is_empty() {
+            handle_library_changes(settled, root, db);
+        }
+    }
+}
+
+/// Paths with watcher events not handled yet, coalesced per path.
+#[derive(Default)]
+struct PendingChanges {
+    /// Time of the last event and the file's stamp then, by path.
+    paths: HashMap<PathBuf, (Instant, Option<FileStamp>)>,
+}
+
+impl PendingChanges {
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        let stamp = file_stamp(&path);
+        self.paths.insert(path, (now, stamp));
+    }
+
+    /// Removes and returns the paths without events for `WATCH_SETTLE_WINDOW`. A file
+    /// whose size or modification time moved anyway is still being written and waits
+    /// for another window.
+    fn take_settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut settled = Vec::new();
+        self.paths.retain(|path, (last_event, stamp)| {
+            if now.duration_since(*last_event) < WATCH_SETTLE_WINDOW {
+                return true;
+            }
+            let current = file_stamp(path);
+            if current != *stamp {
+                *last_event = now;
+                *stamp = current;
+                return true;
+            }
+            settled.push(path.clone());
+            false
+        });
+        settled.sort();
+        settled
+    }
+}
+
+/// Applies settled watcher paths: cue sheets and the files they split are re-indexed
+/// through the sheets, removed files are deleted and the other audio files are indexed
+/// together.
+fn handle_library_changes(paths: Vec<PathBuf>, root: &Path, db: &DbManager) {
+    let mut files = Vec::new();
+    for path in paths {
+        if exclusions::is_excluded_path(root, &path) {
+            continue;
+        }
+        if is_cue_path(&path) {
+            handle_cue_sheet_event(&canonical_path(&path), db);
+            continue;
+        }
         if !is_supported_audio_path(&path) {
             continue;
         }
-        if path.exists() {
-            let track = extract_track(&path);
-            if let Err(err) = db.save_track(&track) {
-                eprintln!("Failed to persist watched track {}: {err}", track.path);
-            } else {
-                enrichment_queue::enqueue(track, db.clone());
+        let path = canonical_path(&path);
+        // Changes to a file split by a cue sheet re-index the sheet's songs instead.
+        let cue_sheets = cue_sheets_splitting(&path, db);
+        if !cue_sheets.is_empty() {
+            for cue_path in cue_sheets {
+                handle_cue_sheet_event(&cue_path, db);
             }
+        } else if path.exists() {
+            files.push(path);
         } else if let Err(err) = db.delete_track(path.to_string_lossy().as_ref()) {
             eprintln!("Failed to delete removed track {}: {err}", path.display());
         }
     }
+    index_files(&files, db);
+}
+
+/// Re-reads an audio file whose tags changed into the library, or re-indexes the cue
+/// sheets that split it into songs.
+pub fn rescan_audio_file(path: &Path, db: &DbManager) -> Result<(), String> {
+    let path = canonical_path(path);
+    let cue_sheets = cue_sheets_splitting(&path, db);
+    if !cue_sheets.is_empty() {
+        for cue_path in cue_sheets {
+            index_cue_sheet(&cue_path, db)?;
+        }
+        return Ok(());
+    }
+    index_file(&path, db).map(|_| ())
+}
+
+fn cue_sheets_splitting(path: &Path, db: &DbManager) -> HashSet<PathBuf> {
+    db.get_cue_track_paths(path.to_string_lossy().as_ref())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|track| cue::split_virtual_path(Path::new(track)))
+        .map(|(cue_path, _)| cue_path)
+        .collect()
+}
+
+fn handle_cue_sheet_event(cue_path: &Path, db: &DbManager) {
+    let result = if cue_path.exists() {
+        index_cue_sheet(cue_path, db).map(|_| ())
+    } else {
+        // Without the sheet, the files it split are listed as whole tracks again.
+        db.delete_cue_tracks(&cue::virtual_track_prefix(cue_path), &[])
+            .map(|sources| {
+                for source in sources.iter().map(Path::new).filter(|path| path.exists()) {
+                    if let Err(err) = index_file(source, db) {
+                        eprintln!("Failed to persist track {}: {err}", source.display());
+                    }
+                }
+            })
+    };
+    if let Err(err) = result {
+        eprintln!("Failed to update cue sheet {}: {err}", cue_path.display());
+    }
 }
 
 fn extract_track(path: &Path) -> TrackInput {
-    let (mut title, mut artist, mut album, duration_seconds, sample_rate) =
-        read_symphonia_metadata(path);
+    let SymphoniaMetadata {
+        mut title,
+        mut artist,
+        mut album,
+        mut album_artist,
+        mut composer,
+        mut duration_seconds,
+        mut sample_rate,
+        mut compilation,
+    } = read_symphonia_metadata(path);
+    let mut genre = None;
+    let mut year = None;
+    let mut track_number = None;
+    let mut disc_number = None;
     let mut corrupted = false;
     let mut art_url = None;
 
@@ -137,6 +786,14 @@ fn extract_track(path: &Path) -> TrackInput {
             if artist.is_none() {
                 artist = metadata.artist;
             }
+            // Formats symphonia cannot probe (DSD) only report these through the decoder.
+            album = album.or(metadata.album);
+            genre = metadata.genre;
+            year = metadata.year;
+            track_number = metadata.track_number;
+            disc_number = metadata.disc_number;
+            duration_seconds = duration_seconds.or(metadata.duration_seconds);
+            sample_rate = sample_rate.or(metadata.sample_rate);
             if let Some(cover_art) = metadata.cover_art {
                 art_url = art_cache::cache_cover_art(path, &cover_art).ok().flatten();
             }
@@ -157,6 +814,36 @@ fn extract_track(path: &Path) -> TrackInput {
         if album.is_none() {
             album = tag.album().map(ToOwned::to_owned);
         }
+        if genre.is_none() {
+            genre = tag.genre_parsed().map(|genre| genre.into_owned());
+        }
+        if year.is_none() {
+            year = tag.year().and_then(|year| u32::try_from(year).ok());
+        }
+        track_number = track_number.or(tag.track());
+        disc_number = disc_number.or(tag.disc());
+        if album_artist.is_none() {
+            album_artist = tag.album_artist().map(ToOwned::to_owned);
+        }
+        if composer.is_none() {
+            composer = tag
+                .get("TCOM")
+                .and_then(|frame| frame.content().text())
+                .map(ToOwned::to_owned);
+        }
+        compilation |= tag
+            .get("TCMP")
+            .and_then(|frame| frame.content().text())
+            .is_some_and(is_set_flag);
+    }
+    // Flagged compilations without an album artist would otherwise be split into one
+    // album per track artist.
+    if compilation
+        && album_artist
+            .as_deref()
+            .is_none_or(|name| name.trim().is_empty())
+    {
+        album_artist = Some(VARIOUS_ARTISTS.to_string());
     }
 
     if art_url.is_none() {
@@ -175,6 +862,12 @@ fn extract_track(path: &Path) -> TrackInput {
         }),
         artist,
         album,
+        genre,
+        year,
+        track_number,
+        disc_number,
+        album_artist,
+        composer,
         duration_seconds,
         sample_rate,
         art_url,
@@ -216,113 +909,119 @@ fn parse_artist_title_from_stem(stem: &str) -> Option<(String, String)> {
     Some((artist.to_string(), title.to_string()))
 }
 
-fn read_symphonia_metadata(
-    path: &Path,
-) -> (
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<f32>,
-    Option<u32>,
-) {
+/// Tags and stream details read through symphonia's probe.
+#[derive(Default)]
+struct SymphoniaMetadata {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    composer: Option<String>,
+    duration_seconds: Option<f32>,
+    sample_rate: Option<u32>,
+    /// Set by the MP4 `cpil` atom or a Vorbis `COMPILATION` comment.
+    compilation: bool,
+}
+
+fn read_symphonia_metadata(path: &Path) -> SymphoniaMetadata {
     let Ok(file) = std::fs::File::open(path) else {
-        return (None, None, None, None, None);
+        return SymphoniaMetadata::default();
     };
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
     let mut hint = Hint::new();
     if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
         hint.with_extension(ext);
     }
-    let Ok(mut probed) = symphonia::default::get_probe().format(
+    let Ok(mut probed) = codecs::get_probe().format(
         &hint,
         mss,
         &FormatOptions::default(),
         &MetadataOptions::default(),
     ) else {
-        return (None, None, None, None, None);
+        return SymphoniaMetadata::default();
     };
 
-    let mut title: Option<String> = None;
-    let mut artist: Option<String> = None;
-    let mut album: Option<String> = None;
+    let mut metadata = SymphoniaMetadata::default();
     if let Some(pre_metadata) = probed.metadata.get() {
         if let Some(revision) = pre_metadata.current() {
-            apply_revision_metadata(revision, &mut title, &mut artist, &mut album);
+            apply_revision_metadata(revision, &mut metadata);
         }
     }
 
     let format = &mut probed.format;
     if let Some(revision) = format.metadata().current() {
-        apply_revision_metadata(revision, &mut title, &mut artist, &mut album);
+        apply_revision_metadata(revision, &mut metadata);
+    }
+    // Files tagged with only an album artist are credited to it.
+    if metadata.artist.is_none() {
+        metadata.artist = metadata.album_artist.clone();
     }
 
-    let mut duration_seconds = None;
-    let sample_rate = format.default_track().and_then(|track| {
+    metadata.sample_rate = format.default_track().and_then(|track| {
         if let (Some(sample_rate), Some(n_frames)) =
             (track.codec_params.sample_rate, track.codec_params.n_frames)
         {
             if sample_rate > 0 {
-                duration_seconds = Some(n_frames as f32 / sample_rate as f32);
+                metadata.duration_seconds = Some(n_frames as f32 / sample_rate as f32);
             }
         }
         track.codec_params.sample_rate
     });
-
-    (title, artist, album, duration_seconds, sample_rate)
-}
-
-fn is_supported_audio_path(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| {
-            matches!(
-                ext.to_ascii_lowercase().as_str(),
-                "flac" | "mp3" | "m4a" | "ogg" | "wav"
-            )
-        })
-        .unwrap_or(false)
+    metadata
 }
 
-fn apply_revision_metadata(
-    revision: &MetadataRevision,
-    title: &mut Option<String>,
-    artist: &mut Option<String>,
-    album: &mut Option<String>,
-) {
+fn apply_revision_metadata(revision: &MetadataRevision, metadata: &mut SymphoniaMetadata) {
     for tag in revision.tags() {
-        if title.is_none() && matches!(tag.std_key, Some(StandardTagKey::TrackTitle)) {
-            *title = Some(tag.value.to_string());
-        }
-        if artist.is_none()
-            && matches!(
-                tag.std_key,
-                Some(
-                    StandardTagKey::Artist
-                        | StandardTagKey::AlbumArtist
-                        | StandardTagKey::Performer
-                )
-            )
+        // Symphonia maps the Vorbis comment under a misspelled key, so match it by name.
+        if tag.std_key == Some(StandardTagKey::Compilation)
+            || tag.key.eq_ignore_ascii_case("compilation")
         {
-            *artist = Some(tag.value.to_string());
+            metadata.compilation |= match &tag.value {
+                Value::Boolean(flag) => *flag,
+                Value::UnsignedInt(number) => *number != 0,
+                Value::SignedInt(number) => *number != 0,
+                Value::Binary(bytes) => bytes.iter().any(|byte| *byte != 0),
+                Value::String(text) => is_set_flag(text),
+                _ => false,
+            };
+            continue;
         }
-        if album.is_none() && matches!(tag.std_key, Some(StandardTagKey::Album)) {
-            *album = Some(tag.value.to_string());
+        let field = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => &mut metadata.title,
+            Some(StandardTagKey::Artist | StandardTagKey::Performer) => &mut metadata.artist,
+            Some(StandardTagKey::Album) => &mut metadata.album,
+            Some(StandardTagKey::AlbumArtist) => &mut metadata.album_artist,
+            Some(StandardTagKey::Composer) => &mut metadata.composer,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = Some(tag.value.to_string());
         }
     }
 }
 
+fn is_set_flag(text: &str) -> bool {
+    matches!(
+        text.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes"
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{extract_track, parse_artist_title_from_stem};
+    use super::{
+        cancel_scan, extract_track, handle_cue_sheet_event, index_cue_sheet,
+        parse_artist_title_from_stem, rescan_track, root_reachable, scan_library_path,
+        LibraryWatcherManager, PendingChanges, WATCHDOG_PROBE_TIMEOUT,
+    };
+    use crate::db::manager::DbManager;
+    use crate::test_support::unique_temp_path;
     use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
 
     fn unique_audio_path() -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        std::env::temp_dir().join(format!("powerplayer-corrupted-{nanos}.flac"))
+        unique_temp_path("corrupted.flac")
     }
 
     #[test]
@@ -336,6 +1035,93 @@ mod tests {
         let _ = std::fs::remove_file(path);
     }
 
+    #[test]
+    fn repeat_scans_skip_files_whose_stamp_is_unchanged() {
+        let dir = unique_temp_path("rescan");
+        std::fs::create_dir_all(&dir).expect("test dir should be created");
+        let audio = dir.join("Band - Song.flac");
+        std::fs::write(&audio, b"not-a-real-flac").expect("audio should be written");
+        let db = DbManager::new(dir.join("library.db")).expect("db should initialize");
+        let title = |db: &DbManager| db.get_tracks().expect("tracks should load").remove(0).title;
+
+        let events = Mutex::new(Vec::new());
+        let scanned = scan_library_path(&dir, &db, |progress| {
+            events.lock().expect("events lock").push(progress);
+        });
+        assert_eq!(scanned, Ok(1));
+        assert_eq!(title(&db), Some("Song".to_string()));
+        let events = events.into_inner().expect("events lock");
+        assert_eq!((events[0].discovered, events[0].processed), (1, 0));
+        let last = events.last().expect("final event");
+        assert!(last.finished);
+        assert_eq!((last.processed, last.errors), (1, 0));
+
+        let mut edited = extract_track(&super::canonical_path(&audio));
+        edited.title = Some("Edited in the library".to_string());
+        db.save_track(&edited).expect("track should save");
+
+        // Unchanged on disk: the library row is not re-read.
+        assert_eq!(scan_library_path(&dir, &db, |_| {}), Ok(1));
+        assert_eq!(title(&db), Some("Edited in the library".to_string()));
+
+        std::fs::write(&audio, b"still-not-a-real-flac").expect("audio should change");
+        assert_eq!(scan_library_path(&dir, &db, |_| {}), Ok(1));
+        assert_eq!(title(&db), Some("Song".to_string()));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rescanning_one_track_rereads_it_even_when_unchanged() {
+        let dir = unique_temp_path("rescan-one");
+        std::fs::create_dir_all(&dir).expect("test dir should be created");
+        let audio = dir.join("Band - Song.flac");
+        std::fs::write(&audio, b"not-a-real-flac").expect("audio should be written");
+        let db = DbManager::new(dir.join("library.db")).expect("db should initialize");
+        assert_eq!(scan_library_path(&dir, &db, |_| {}), Ok(1));
+        let mut edited = extract_track(&super::canonical_path(&audio));
+        edited.title = Some("Stale".to_string());
+        db.save_track(&edited).expect("track should save");
+
+        let track = rescan_track(&audio, &db)
+            .expect("rescan should work")
+            .expect("the track stays in the library");
+        assert_eq!(track.title.as_deref(), Some("Song"));
+        assert_eq!(track.artist.as_deref(), Some("Band"));
+        assert_eq!(db.get_tracks().expect("tracks").len(), 1);
+        assert!(rescan_track(&dir.join("gone.flac"), &db).is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn scans_can_be_cancelled_and_do_not_overlap() {
+        let dir = unique_temp_path("cancel-scan");
+        std::fs::create_dir_all(&dir).expect("test dir should be created");
+        std::fs::write(dir.join("song.flac"), b"not-a-real-flac").expect("audio should be written");
+        let db = DbManager::new(dir.join("library.db")).expect("db should initialize");
+
+        let last = Mutex::new(None);
+        let result = scan_library_path(&dir, &db, |progress| {
+            if progress.processed == 0 && !progress.finished {
+                assert!(scan_library_path(&dir, &db, |_| {}).is_err());
+                assert_eq!(cancel_scan(Some(&dir)), 1);
+            }
+            *last.lock().expect("progress lock") = Some(progress);
+        });
+        assert!(result.is_err());
+        let last = last.into_inner().expect("progress lock");
+        let last = last.expect("final event");
+        assert!(last.finished && last.cancelled);
+        assert_eq!(last.processed, 0);
+        assert!(db.get_tracks().expect("tracks should load").is_empty());
+
+        // The root is free again once the cancelled scan returned.
+        assert_eq!(cancel_scan(Some(&dir)), 0);
+        assert_eq!(scan_library_path(&dir, &db, |_| {}), Ok(1));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn filename_repair_extracts_artist_and_title() {
         let parsed = parse_artist_title_from_stem("Daft Punk - One More Time");
@@ -344,4 +1130,129 @@ mod tests {
             Some(("Daft Punk".to_string(), "One More Time".to_string()))
         );
     }
+
+    #[test]
+    fn watcher_events_wait_until_the_file_stops_growing() {
+        let path = unique_audio_path();
+        std::fs::write(&path, b"partial").expect("write file");
+        let start = Instant::now();
+        let mut pending = PendingChanges::default();
+        pending.touch(path.clone(), start);
+        pending.touch(path.clone(), start + Duration::from_millis(500));
+        let at = |secs| start + Duration::from_secs(secs);
+        assert!(pending.take_settled(at(2)).is_empty());
+
+        // Still being copied when the window ends: wait for another one.
+        std::fs::write(&path, b"partial and then the rest").expect("grow file");
+        assert!(pending.take_settled(at(3)).is_empty());
+        assert!(pending.take_settled(at(4)).is_empty());
+        assert_eq!(pending.take_settled(at(5)), std::slice::from_ref(&path));
+        assert!(pending.take_settled(at(9)).is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn watchdog_reports_missing_root_and_rearms_when_it_returns() {
+        let root = unique_temp_path("watch");
+        std::fs::create_dir_all(&root).expect("root should be created");
+        let db = DbManager::new(unique_temp_path("watch.db")).expect("db should initialize");
+
+        let mut manager = LibraryWatcherManager::default();
+        manager.register(&root, &db).expect("watch should register");
+        let canonical = super::canonical_path(&root);
+        let probe = |root: &std::path::Path| root_reachable(root, WATCHDOG_PROBE_TIMEOUT);
+        assert!(manager.check_health(probe).degraded.is_empty());
+
+        std::fs::remove_dir_all(&root).expect("root should be removed");
+        assert_eq!(
+            manager.check_health(probe).degraded,
+            vec![canonical.clone()]
+        );
+
+        std::fs::create_dir_all(&root).expect("root should come back");
+        let report = manager.check_health(probe);
+        assert!(report.degraded.is_empty());
+        assert_eq!(report.rearmed.len(), 1);
+        assert!(manager.roots[&canonical].watcher.is_some());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn watchdog_probes_roots_without_writing_to_them() {
+        let root = unique_temp_path("probe");
+        std::fs::create_dir_all(&root).expect("root should be created");
+        let db = DbManager::new(unique_temp_path("probe.db")).expect("db should initialize");
+        let mut manager = LibraryWatcherManager::default();
+        manager.register(&root, &db).expect("watch should register");
+        let probe = |root: &std::path::Path| root_reachable(root, WATCHDOG_PROBE_TIMEOUT);
+
+        let report = manager.check_health(probe);
+        assert!(report.degraded.is_empty() && report.rearmed.is_empty());
+        let entries = std::fs::read_dir(&root).expect("root should list").count();
+        assert_eq!(entries, 0, "nothing is written into the root");
+        assert!(!probe(&root.join("missing")));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn cue_sheet_songs_replace_the_whole_file_row() {
+        let dir = unique_temp_path("cue-scan");
+        std::fs::create_dir_all(&dir).expect("test dir should be created");
+        let dir = super::canonical_path(&dir);
+        let audio = dir.join("rip.flac");
+        std::fs::write(&audio, b"not-a-real-flac").expect("audio should be written");
+        let cue_path = dir.join("rip.cue");
+        std::fs::write(
+            &cue_path,
+            "PERFORMER \"Band\"\nTITLE \"Rip\"\nFILE \"rip.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"One\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Two\"\n    INDEX 01 01:00:00\n",
+        )
+        .expect("cue should be written");
+        let db = DbManager::new(dir.join("library.db")).expect("db should initialize");
+        db.save_track(&extract_track(&audio))
+            .expect("whole file should be saved");
+
+        let (covered, songs) = index_cue_sheet(&cue_path, &db).expect("sheet should index");
+        assert_eq!((covered, songs), (vec![audio.clone()], 2));
+        let tracks = db.get_tracks().expect("tracks should load");
+        let titles = tracks
+            .iter()
+            .map(|track| (track.path.clone(), track.title.clone(), track.album.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![
+                (
+                    format!("{}#01", cue_path.display()),
+                    Some("One".to_string()),
+                    Some("Rip".to_string())
+                ),
+                (
+                    format!("{}#02", cue_path.display()),
+                    Some("Two".to_string()),
+                    Some("Rip".to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            db.get_cue_track_paths(&audio.to_string_lossy())
+                .expect("cue tracks should load")
+                .len(),
+            2
+        );
+        assert_eq!(tracks[0].duration_seconds, Some(60.0));
+
+        std::fs::remove_file(&cue_path).expect("cue should be removed");
+        handle_cue_sheet_event(&cue_path, &db);
+        let paths = db
+            .get_tracks()
+            .expect("tracks should load")
+            .into_iter()
+            .map(|track| track.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![audio.to_string_lossy().to_string()]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -0,0 +1,186 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio::decoder::{decode_file, read_track_metadata};
+use crate::audio::flac_encoder::write_flac;
+use crate::audio::lossy_encoder::{write_mp3, write_ogg_vorbis};
+use crate::library::stems::{StemKind, StemPaths};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StemExportFormat {
+    Flac,
+    Mp3,
+    Ogg,
+}
+
+impl StemExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            StemExportFormat::Flac => "flac",
+            StemExportFormat::Mp3 => "mp3",
+            StemExportFormat::Ogg => "ogg",
+        }
+    }
+}
+
+/// Converts the cached stems of `track_path` into `dest_dir`, one file per stem named
+/// `Artist - Title (Vocals).flac` and so on, tagged with the track's artist, album and
+/// genre and a title naming the stem. FLAC is 24-bit, MP3 is LAME V2 and OGG is Vorbis
+/// quality 6. Nothing is written if any of the files exists already, and a failed
+/// export removes the files it wrote. Returns the files written.
+pub fn export_stems(
+    stems: &StemPaths,
+    track_path: &str,
+    dest_dir: &Path,
+    format: StemExportFormat,
+) -> Result<Vec<PathBuf>, String> {
+    if !dest_dir.is_dir() {
+        return Err(format!(
+            "Destination {} is not a folder",
+            dest_dir.display()
+        ));
+    }
+    let metadata = read_track_metadata(Path::new(track_path)).ok();
+    let field = |value: Option<&String>| value.filter(|value| !value.trim().is_empty()).cloned();
+    let title = metadata
+        .as_ref()
+        .and_then(|metadata| field(metadata.title.as_ref()))
+        .or_else(|| {
+            Path::new(track_path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Track".to_string());
+    let artist = metadata
+        .as_ref()
+        .and_then(|metadata| field(metadata.artist.as_ref()));
+    let album = metadata
+        .as_ref()
+        .and_then(|metadata| field(metadata.album.as_ref()));
+    let genre = metadata
+        .as_ref()
+        .and_then(|metadata| field(metadata.genre.as_ref()));
+
+    let sources: Vec<(StemKind, &Path)> = StemKind::all()
+        .iter()
+        .filter_map(|kind| stems.get(*kind).map(|path| (*kind, path)))
+        .collect();
+    let destinations: Vec<PathBuf> = sources
+        .iter()
+        .map(|(kind, _)| {
+            let label = stem_label(*kind);
+            let base = match &artist {
+                Some(artist) => format!("{artist} - {title} ({label})"),
+                None => format!("{title} ({label})"),
+            };
+            dest_dir.join(format!("{}.{}", file_name_safe(&base), format.extension()))
+        })
+        .collect();
+    if let Some(existing) = destinations.iter().find(|path| path.exists()) {
+        return Err(format!("{} already exists", existing.display()));
+    }
+
+    let mut written = Vec::new();
+    for ((kind, source), destination) in sources.into_iter().zip(destinations) {
+        let label = stem_label(kind);
+        let mut tags = vec![("TITLE", format!("{title} ({label})"))];
+        tags.extend(artist.clone().map(|artist| ("ARTIST", artist)));
+        tags.extend(album.clone().map(|album| ("ALBUM", album)));
+        tags.extend(genre.clone().map(|genre| ("GENRE", genre)));
+        if let Err(err) = export_stem(source, &destination, format, &tags) {
+            let _ = fs::remove_file(&destination);
+            for path in &written {
+                let _ = fs::remove_file(path);
+            }
+            return Err(err);
+        }
+        written.push(destination);
+    }
+    Ok(written)
+}
+
+fn export_stem(
+    source: &Path,
+    destination: &Path,
+    format: StemExportFormat,
+    tags: &[(&str, String)],
+) -> Result<(), String> {
+    let audio = decode_file(source)?;
+    let write = match format {
+        StemExportFormat::Flac => write_flac,
+        StemExportFormat::Mp3 => write_mp3,
+        StemExportFormat::Ogg => write_ogg_vorbis,
+    };
+    write(
+        destination,
+        &audio.samples,
+        audio.sample_rate,
+        audio.channels,
+        tags,
+    )
+}
+
+fn stem_label(kind: StemKind) -> String {
+    let name = kind.as_str();
+    name[..1].to_uppercase() + &name[1..]
+}
+
+/// Replaces characters Windows (the strictest file system) rejects in file names.
+fn file_name_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_stems, file_name_safe, StemExportFormat};
+    use crate::audio::decoder::decode_file;
+    use crate::audio::flac_encoder::write_flac;
+    use crate::library::stems::StemPaths;
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn stems_export_as_tagged_flac_files() {
+        let dir = unique_temp_path("stem-export-test");
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let stem = dir.join("stem.flac");
+        let samples = vec![0.25_f32, -0.25, 0.5, -0.5];
+        write_flac(&stem, &samples, 44_100, 2, &[]).expect("write stem");
+        let stems = StemPaths {
+            vocals: stem.clone(),
+            drums: stem.clone(),
+            bass: stem.clone(),
+            other: stem,
+            piano: None,
+            guitar: None,
+        };
+        let track = dir.join("My Song.flac");
+        let tags = [("ARTIST", "AC/DC".to_string())];
+        write_flac(&track, &samples, 44_100, 2, &tags).expect("write track");
+
+        let out = dir.join("out");
+        std::fs::create_dir_all(&out).expect("create out dir");
+        let track = track.to_string_lossy();
+        let written = export_stems(&stems, &track, &out, StemExportFormat::Flac).expect("export");
+        assert_eq!(written.len(), 4);
+        assert_eq!(
+            written[0].file_name().and_then(|name| name.to_str()),
+            Some("AC_DC - My Song (Vocals).flac")
+        );
+        let decoded = decode_file(&written[3]).expect("decode export");
+        assert_eq!(decoded.samples.len(), samples.len());
+
+        // A second export would overwrite, so it is refused.
+        assert!(export_stems(&stems, &track, &out, StemExportFormat::Flac).is_err());
+        assert_eq!(file_name_safe("a/b?. "), "a_b_");
+    }
+}
@@ -0,0 +1,308 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::library::stems::{StemAnalysisToken, StemSeparator};
+
+/// Separation is heavy on CPU (or GPU) and memory, so one track runs at a time unless
+/// the user asks for more.
+const DEFAULT_CONCURRENCY: usize = 1;
+const MAX_CONCURRENCY: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StemJobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of the per-track progress events.
+#[derive(Clone, Debug, Serialize)]
+pub struct StemJobEvent {
+    pub track_id: String,
+    pub status: StemJobStatus,
+    pub percent: f32,
+    pub stage: String,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StemQueueStatus {
+    pub paused: bool,
+    pub concurrency: usize,
+    pub pending: Vec<String>,
+    pub running: Vec<String>,
+    /// Tracks separated since the app started; `failed` counts those that were not.
+    pub completed: usize,
+    pub failed: usize,
+}
+
+#[derive(Default)]
+struct QueueState {
+    pending: VecDeque<String>,
+    running: Vec<String>,
+    paused: bool,
+    concurrency: usize,
+    completed: usize,
+    failed: usize,
+}
+
+struct Inner {
+    cache_dir: PathBuf,
+    state: Mutex<QueueState>,
+    on_event: Box<dyn Fn(StemJobEvent) + Send + Sync>,
+}
+
+/// Separates stems for many tracks in the background, so spatial mode starts instantly
+/// when they are played. Each job runs on its own thread with its own separator and
+/// registers a `StemAnalysisToken`, so `stems::cancel_analysis` stops it like any other
+/// analysis.
+pub struct StemQueue {
+    inner: Arc<Inner>,
+}
+
+impl StemQueue {
+    pub fn new(
+        cache_dir: impl Into<PathBuf>,
+        on_event: impl Fn(StemJobEvent) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cache_dir: cache_dir.into(),
+                state: Mutex::new(QueueState {
+                    concurrency: DEFAULT_CONCURRENCY,
+                    ..QueueState::default()
+                }),
+                on_event: Box::new(on_event),
+            }),
+        }
+    }
+
+    /// Adds `paths` to the end of the queue, skipping tracks already queued, running
+    /// or cached. Returns how many were added.
+    pub fn enqueue(&self, paths: Vec<String>) -> Result<usize, String> {
+        let separator = StemSeparator::new(&self.inner.cache_dir);
+        let added = {
+            let mut state = self.inner.lock()?;
+            let mut added = Vec::new();
+            for path in paths {
+                if path.is_empty()
+                    || state.pending.contains(&path)
+                    || state.running.contains(&path)
+                    || separator.is_cached(&path)
+                {
+                    continue;
+                }
+                state.pending.push_back(path.clone());
+                added.push(path);
+            }
+            added
+        };
+        for track_id in &added {
+            self.inner
+                .emit(track_id, StemJobStatus::Queued, 0.0, "Queued", None);
+        }
+        dispatch(&self.inner);
+        Ok(added.len())
+    }
+
+    /// Drops `track_path` from the queue if it has not started. Returns whether it was
+    /// queued.
+    pub fn remove(&self, track_path: &str) -> Result<bool, String> {
+        let removed = {
+            let mut state = self.inner.lock()?;
+            let before = state.pending.len();
+            state.pending.retain(|path| path != track_path);
+            state.pending.len() != before
+        };
+        if removed {
+            self.inner
+                .emit(track_path, StemJobStatus::Cancelled, 0.0, "Cancelled", None);
+        }
+        Ok(removed)
+    }
+
+    /// Stops starting new tracks; tracks already running finish.
+    pub fn pause(&self) -> Result<(), String> {
+        self.inner.lock()?.paused = true;
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<(), String> {
+        self.inner.lock()?.paused = false;
+        dispatch(&self.inner);
+        Ok(())
+    }
+
+    /// How many tracks are separated at once, 1 to 4. Lowering it lets running tracks
+    /// finish.
+    pub fn set_concurrency(&self, limit: usize) -> Result<(), String> {
+        self.inner.lock()?.concurrency = limit.clamp(1, MAX_CONCURRENCY);
+        dispatch(&self.inner);
+        Ok(())
+    }
+
+    pub fn status(&self) -> Result<StemQueueStatus, String> {
+        let state = self.inner.lock()?;
+        Ok(StemQueueStatus {
+            paused: state.paused,
+            concurrency: state.concurrency,
+            pending: state.pending.iter().cloned().collect(),
+            running: state.running.clone(),
+            completed: state.completed,
+            failed: state.failed,
+        })
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, QueueState>, String> {
+        self.state
+            .lock()
+            .map_err(|e| format!("Stem queue lock error: {e}"))
+    }
+
+    fn emit(
+        &self,
+        track_id: &str,
+        status: StemJobStatus,
+        percent: f32,
+        stage: &str,
+        error: Option<String>,
+    ) {
+        (self.on_event)(StemJobEvent {
+            track_id: track_id.to_string(),
+            status,
+            percent,
+            stage: stage.to_string(),
+            error,
+        });
+    }
+}
+
+/// Starts queued tracks until the concurrency limit is reached.
+fn dispatch(inner: &Arc<Inner>) {
+    let Ok(mut state) = inner.state.lock() else {
+        return;
+    };
+    while !state.paused && state.running.len() < state.concurrency {
+        let Some(track_path) = state.pending.pop_front() else {
+            break;
+        };
+        state.running.push(track_path.clone());
+        let inner = Arc::clone(inner);
+        thread::spawn(move || run_job(&inner, track_path));
+    }
+}
+
+fn run_job(inner: &Arc<Inner>, track_path: String) {
+    let token = StemAnalysisToken::register(&track_path);
+    inner.emit(
+        &track_path,
+        StemJobStatus::Running,
+        0.0,
+        "Starting...",
+        None,
+    );
+    let separator = StemSeparator::new(&inner.cache_dir);
+    let result = separator.analyze_spatial_stems(&track_path, &token, |progress| {
+        inner.emit(
+            &track_path,
+            StemJobStatus::Running,
+            progress.percent,
+            &progress.stage,
+            None,
+        );
+    });
+    let (status, error) = match result {
+        Ok(_) => (StemJobStatus::Done, None),
+        Err(_) if token.is_cancelled() => (StemJobStatus::Cancelled, None),
+        Err(err) => (StemJobStatus::Failed, Some(err)),
+    };
+    drop(token);
+
+    if let Ok(mut state) = inner.state.lock() {
+        state.running.retain(|path| *path != track_path);
+        if status == StemJobStatus::Failed {
+            state.failed += 1;
+        } else if status == StemJobStatus::Done {
+            state.completed += 1;
+        }
+    }
+    let stage = match status {
+        StemJobStatus::Done => "Complete",
+        StemJobStatus::Cancelled => "Cancelled",
+        _ => "Failed",
+    };
+    let percent = if status == StemJobStatus::Done {
+        1.0
+    } else {
+        0.0
+    };
+    inner.emit(&track_path, status, percent, stage, error);
+    dispatch(inner);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StemJobEvent, StemJobStatus, StemQueue};
+    use crate::test_support::unique_temp_path;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn paused_queue_holds_tracks_until_resumed() {
+        let cache_dir = unique_temp_path("stem-queue-test");
+        let events = Arc::new(Mutex::new(Vec::<StemJobEvent>::new()));
+        let sink = Arc::clone(&events);
+        let queue = StemQueue::new(&cache_dir, move |event| {
+            sink.lock().expect("events").push(event);
+        });
+
+        queue.pause().expect("pause");
+        queue.set_concurrency(9).expect("concurrency");
+        let tracks = vec!["/missing/a.flac".to_string(), "/missing/b.flac".to_string()];
+        assert_eq!(queue.enqueue(tracks.clone()).expect("enqueue"), 2);
+        assert_eq!(queue.enqueue(tracks).expect("enqueue again"), 0);
+        let status = queue.status().expect("status");
+        assert_eq!(status.concurrency, 4);
+        assert_eq!(status.pending.len(), 2);
+        assert!(status.running.is_empty());
+
+        assert!(queue.remove("/missing/b.flac").expect("remove"));
+        queue.resume().expect("resume");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let failed = |events: &[StemJobEvent]| {
+            events
+                .iter()
+                .any(|event| event.status == StemJobStatus::Failed)
+        };
+        while !failed(&events.lock().expect("events")) {
+            assert!(Instant::now() < deadline, "queued track never finished");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let status = queue.status().expect("status");
+        assert!(status.pending.is_empty() && status.running.is_empty());
+
+        let events = events.lock().expect("events");
+        let statuses = |track: &str| {
+            events
+                .iter()
+                .filter(|event| event.track_id == track)
+                .map(|event| event.status)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            statuses("/missing/b.flac"),
+            [StemJobStatus::Queued, StemJobStatus::Cancelled]
+        );
+        let a = statuses("/missing/a.flac");
+        assert_eq!(a.first(), Some(&StemJobStatus::Queued));
+        assert_eq!(a.last(), Some(&StemJobStatus::Failed));
+    }
+}
@@ -1,12 +1,29 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-
-/// The four stem types produced by the separation engine.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use ort::execution_providers::{
+    CUDAExecutionProvider, CoreMLExecutionProvider, DirectMLExecutionProvider,
+};
+use ort::session::Session;
+use ort::value::Tensor;
+
+use crate::audio::codecs;
+use crate::audio::decoder::{resample_hq, DecodedTrack};
+use crate::library::network_usage::{self, NetworkProvider};
+use crate::library::remote_cache::{self, DownloadProgress};
+
+/// The stem types produced by the separation engine. Piano and guitar only come from
+/// the six-stem model.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StemKind {
     Vocals,
     Drums,
     Bass,
     Other,
+    Piano,
+    Guitar,
 }
 
 impl StemKind {
@@ -16,26 +33,119 @@ impl StemKind {
             StemKind::Drums => "drums",
             StemKind::Bass => "bass",
             StemKind::Other => "other",
+            StemKind::Piano => "piano",
+            StemKind::Guitar => "guitar",
         }
     }
 
+    /// Every stem, in the order of the spatial room's `SOURCE_NAMES`.
     pub fn all() -> &'static [StemKind] {
         &[
             StemKind::Vocals,
             StemKind::Drums,
             StemKind::Bass,
             StemKind::Other,
+            StemKind::Piano,
+            StemKind::Guitar,
         ]
     }
 }
 
-/// Paths to the four cached stem WAV files for a given track.
+/// Stems every four- or six-stem separation has.
+const FOUR_STEMS: [StemKind; 4] = [
+    StemKind::Vocals,
+    StemKind::Drums,
+    StemKind::Bass,
+    StemKind::Other,
+];
+
+/// How finely a track is separated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SeparationMode {
+    /// Vocals, drums, bass and other.
+    #[default]
+    #[serde(rename = "four_stems")]
+    Four,
+    /// Vocals and everything else, from a smaller model; enough for karaoke and vocal
+    /// isolation.
+    #[serde(rename = "two_stems")]
+    Two,
+    /// The four stems with piano and guitar taken out of "other" (HT-Demucs 6s).
+    #[serde(rename = "six_stems")]
+    Six,
+}
+
+impl SeparationMode {
+    /// File names of the stems, without `.wav`.
+    fn stem_names(self) -> &'static [&'static str] {
+        match self {
+            SeparationMode::Four => &["vocals", "drums", "bass", "other"],
+            SeparationMode::Two => &["vocals", "instrumental"],
+            SeparationMode::Six => &["vocals", "drums", "bass", "other", "piano", "guitar"],
+        }
+    }
+
+    /// Index into `stem_names` of the stem that absorbs what the others miss.
+    fn residual_stem(self) -> usize {
+        match self {
+            SeparationMode::Four | SeparationMode::Six => 3,
+            SeparationMode::Two => 1,
+        }
+    }
+
+    /// Model inside the stem cache directory. It must take planar stereo
+    /// `[1, 2, frames]` at `MODEL_SAMPLE_RATE` and return `[1, stems, 2, frames]` in
+    /// `model_order`.
+    fn model_file(self) -> &'static str {
+        match self {
+            SeparationMode::Four => "demucs_4stems.onnx",
+            SeparationMode::Two => "vocals_2stems.onnx",
+            SeparationMode::Six => "htdemucs_6s.onnx",
+        }
+    }
+
+    /// Index into `stem_names` of each model output: Demucs emits drums, bass, other,
+    /// vocals (then guitar, piano for six stems); two-stem models vocals, then
+    /// accompaniment.
+    fn model_order(self) -> &'static [usize] {
+        match self {
+            SeparationMode::Four => &[1, 2, 3, 0],
+            SeparationMode::Two => &[0, 1],
+            SeparationMode::Six => &[1, 2, 3, 0, 5, 4],
+        }
+    }
+}
+
+/// Paths to the cached stem WAV files for a given track.
 #[derive(Clone, Debug)]
 pub struct StemPaths {
     pub vocals: PathBuf,
     pub drums: PathBuf,
     pub bass: PathBuf,
     pub other: PathBuf,
+    /// Only set for six-stem separations, whose "other" leaves them out.
+    pub piano: Option<PathBuf>,
+    pub guitar: Option<PathBuf>,
+}
+
+impl StemPaths {
+    pub fn get(&self, kind: StemKind) -> Option<&Path> {
+        match kind {
+            StemKind::Vocals => Some(&self.vocals),
+            StemKind::Drums => Some(&self.drums),
+            StemKind::Bass => Some(&self.bass),
+            StemKind::Other => Some(&self.other),
+            StemKind::Piano => self.piano.as_deref(),
+            StemKind::Guitar => self.guitar.as_deref(),
+        }
+    }
+}
+
+/// Paths to the cached stems of a two-stem split.
+#[derive(Clone, Debug)]
+pub struct VocalSplitPaths {
+    pub vocals: PathBuf,
+    pub instrumental: PathBuf,
 }
 
 /// Configuration for the stem separation engine.
@@ -47,6 +157,109 @@ pub struct StemSeparator {
 
 const MAX_STEM_CACHE_TRACKS: usize = 128;
 
+const MODEL_SAMPLE_RATE: u32 = 44_100;
+/// Frames per inference window (the 7.8 s segment HT-Demucs is trained on) and how many
+/// of them consecutive windows share.
+const MODEL_WINDOW_FRAMES: usize = 343_980;
+const MODEL_OVERLAP_FRAMES: usize = MODEL_WINDOW_FRAMES / 4;
+/// Folder inside a track's stem folder holding the windows separated so far.
+const CHECKPOINT_DIR_NAME: &str = "chunks";
+
+/// ONNX Runtime is loaded at run time rather than linked, so the app starts without it.
+const ONNX_RUNTIME_LIBRARY: &str = if cfg!(windows) {
+    "onnxruntime.dll"
+} else if cfg!(target_os = "macos") {
+    "libonnxruntime.dylib"
+} else {
+    "libonnxruntime.so"
+};
+
+/// Where separated stems are cached between sessions.
+pub fn default_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from(".cache"))
+        .join("powerplayer")
+        .join("stems")
+}
+
+/// Where the separation model of `mode` is read from and downloaded to.
+pub fn default_model_path(mode: SeparationMode) -> PathBuf {
+    default_cache_dir().join(mode.model_file())
+}
+
+const CANCELLED: &str = "Stem analysis was cancelled";
+
+/// Cancellation flags of the analyses running or waiting for the separator, by track.
+fn analyses() -> &'static Mutex<HashMap<String, Vec<Arc<AtomicBool>>>> {
+    static ANALYSES: OnceLock<Mutex<HashMap<String, Vec<Arc<AtomicBool>>>>> = OnceLock::new();
+    ANALYSES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Lets `cancel_analysis` stop one analysis of a track. Registered before waiting for
+/// the separator, so queued analyses can be cancelled too; dropping it unregisters.
+pub struct StemAnalysisToken {
+    track_path: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl StemAnalysisToken {
+    pub fn register(track_path: &str) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Ok(mut analyses) = analyses().lock() {
+            analyses
+                .entry(track_path.to_string())
+                .or_default()
+                .push(Arc::clone(&cancelled));
+        }
+        Self {
+            track_path: track_path.to_string(),
+            cancelled,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for StemAnalysisToken {
+    fn drop(&mut self) {
+        let Ok(mut analyses) = analyses().lock() else {
+            return;
+        };
+        if let Some(flags) = analyses.get_mut(&self.track_path) {
+            flags.retain(|flag| !Arc::ptr_eq(flag, &self.cancelled));
+            if flags.is_empty() {
+                analyses.remove(&self.track_path);
+            }
+        }
+    }
+}
+
+/// Cancels every analysis of `track_path` that is running or waiting. Each stops at its
+/// next step or window, removes what it wrote and fails with a cancelled error.
+/// Returns whether there was one.
+pub fn cancel_analysis(track_path: &str) -> bool {
+    let Ok(analyses) = analyses().lock() else {
+        return false;
+    };
+    let Some(flags) = analyses.get(track_path) else {
+        return false;
+    };
+    for flag in flags {
+        flag.store(true, Ordering::Relaxed);
+    }
+    true
+}
+
 /// Progress of an ongoing stem analysis.
 #[derive(Clone, Debug, serde::Serialize)]
 pub struct StemProgress {
@@ -67,6 +280,10 @@ impl StemSeparator {
         self.prefer_gpu = prefer;
     }
 
+    fn model_path(&self, mode: SeparationMode) -> PathBuf {
+        self.cache_dir.join(mode.model_file())
+    }
+
     /// Returns the cache directory for a given track (based on SHA-256 hash of path).
     fn track_cache_dir(&self, track_path: &str) -> PathBuf {
         use sha2::{Digest, Sha256};
@@ -76,6 +293,13 @@ impl StemSeparator {
         self.cache_dir.join(&hash[..16])
     }
 
+    fn vocal_split_dir(&self, track_path: &str) -> PathBuf {
+        let dir = self.track_cache_dir(track_path);
+        let mut name = dir.file_name().unwrap_or_default().to_os_string();
+        name.push("-2");
+        dir.with_file_name(name)
+    }
+
     /// Returns the expected stem file path inside the cache directory.
     fn stem_path(dir: &Path, kind: StemKind) -> PathBuf {
         dir.join(format!("{}.wav", kind.as_str()))
@@ -88,6 +312,8 @@ impl StemSeparator {
             drums: original.clone(),
             bass: original.clone(),
             other: original,
+            piano: None,
+            guitar: None,
         }
     }
 
@@ -125,18 +351,34 @@ impl StemSeparator {
     /// Check whether all four stems are already cached for this track.
     pub fn is_cached(&self, track_path: &str) -> bool {
         let dir = self.track_cache_dir(track_path);
-        StemKind::all()
+        FOUR_STEMS
             .iter()
             .all(|kind| Self::stem_path(&dir, *kind).exists())
     }
 
-    /// Get cached stem paths (returns None if not fully cached).
+    /// Delete every cached split of this track. Returns whether anything was removed.
+    pub fn remove_cached(&self, track_path: &str) -> bool {
+        [
+            self.track_cache_dir(track_path),
+            self.vocal_split_dir(track_path),
+        ]
+        .iter()
+        .filter(|dir| std::fs::remove_dir_all(dir).is_ok())
+        .count()
+            > 0
+    }
+
+    /// Get cached stem paths (returns None if not fully cached). Piano and guitar are
+    /// set when the track was separated into six stems.
     pub fn cached_paths(&self, track_path: &str) -> Option<StemPaths> {
         let dir = self.track_cache_dir(track_path);
         let vocals = Self::stem_path(&dir, StemKind::Vocals);
         let drums = Self::stem_path(&dir, StemKind::Drums);
         let bass = Self::stem_path(&dir, StemKind::Bass);
         let other = Self::stem_path(&dir, StemKind::Other);
+        let piano = Self::stem_path(&dir, StemKind::Piano);
+        let guitar = Self::stem_path(&dir, StemKind::Guitar);
+        let six_stems = piano.exists() && guitar.exists();
 
         if vocals.exists() && drums.exists() && bass.exists() && other.exists() {
             Some(StemPaths {
@@ -144,37 +386,96 @@ impl StemSeparator {
                 drums,
                 bass,
                 other,
+                piano: six_stems.then_some(piano),
+                guitar: six_stems.then_some(guitar),
             })
         } else {
             None
         }
     }
 
-    /// Analyze a track and produce 4 stems.
+    /// Get the cached two-stem split (returns None if not fully cached).
+    pub fn cached_vocal_split(&self, track_path: &str) -> Option<VocalSplitPaths> {
+        let dir = self.vocal_split_dir(track_path);
+        let paths = VocalSplitPaths {
+            vocals: dir.join("vocals.wav"),
+            instrumental: dir.join("instrumental.wav"),
+        };
+        (paths.vocals.exists() && paths.instrumental.exists()).then_some(paths)
+    }
+
+    /// The cached vocal stem of either mode, preferring the four-stem split.
+    pub fn cached_vocals(&self, track_path: &str) -> Option<PathBuf> {
+        self.cached_paths(track_path)
+            .map(|paths| paths.vocals)
+            .or_else(|| {
+                self.cached_vocal_split(track_path)
+                    .map(|paths| paths.vocals)
+            })
+    }
+
+    /// Analyze a track and produce 4 stems, or six when it was separated into six
+    /// before.
     ///
     /// **Step A**: If cached, return paths immediately.
     /// **Step B**: Load audio, split into chunks.
-    /// **Step C**: Run ONNX model (or fallback).
+    /// **Step C**: Run the ONNX model, or centre cancellation when no model or ONNX
+    /// Runtime is installed. Once the model runs, its errors are returned. Finished
+    /// model windows are checkpointed, so an analysis cut short by a crash or quit
+    /// resumes from the last one; a failed or cancelled one starts over.
     /// **Phase sync**: Ensure stems sum to original.
     ///
     /// The `progress_cb` is called with 0.0..1.0 and a stage description
-    /// so the UI can display progress.
+    /// so the UI can display progress. A cancelled `token` stops the analysis between
+    /// steps and model windows.
     pub fn analyze_spatial_stems(
         &self,
         track_path: &str,
+        token: &StemAnalysisToken,
+        progress_cb: impl Fn(StemProgress),
+    ) -> Result<StemPaths, String> {
+        self.analyze_stems(track_path, SeparationMode::Four, token, progress_cb)
+    }
+
+    /// Like `analyze_spatial_stems`, but with the six-stem model piano and guitar are
+    /// split out of "other". A four-stem cache is separated again once that model and
+    /// ONNX Runtime are installed; until then it is returned as it is, and fresh
+    /// analyses fall back to four stems.
+    pub fn analyze_six_stems(
+        &self,
+        track_path: &str,
+        token: &StemAnalysisToken,
         progress_cb: impl Fn(StemProgress),
     ) -> Result<StemPaths, String> {
+        self.analyze_stems(track_path, SeparationMode::Six, token, progress_cb)
+    }
+
+    fn analyze_stems(
+        &self,
+        track_path: &str,
+        mode: SeparationMode,
+        token: &StemAnalysisToken,
+        progress_cb: impl Fn(StemProgress),
+    ) -> Result<StemPaths, String> {
+        token.check()?;
         self.prune_stem_cache();
 
         // Step A: cache check
         let dir = self.track_cache_dir(track_path);
         if let Some(paths) = self.cached_paths(track_path) {
-            progress_cb(StemProgress {
-                track_id: track_path.to_string(),
-                percent: 1.0,
-                stage: "Cached".to_string(),
-            });
-            return Ok(paths);
+            let upgrade = mode == SeparationMode::Six
+                && paths.piano.is_none()
+                && self.model_path(mode).exists()
+                && onnx_runtime_library().is_some();
+            if !upgrade {
+                progress_cb(StemProgress {
+                    track_id: track_path.to_string(),
+                    percent: 1.0,
+                    stage: "Cached".to_string(),
+                });
+                return Ok(paths);
+            }
+            let _ = std::fs::remove_dir_all(&dir);
         }
 
         let existing_stems = StemKind::all()
@@ -191,9 +492,76 @@ impl StemSeparator {
             return Ok(Self::original_track_paths(track_path));
         }
 
-        std::fs::create_dir_all(&dir)
+        self.separate_into(&dir, track_path, mode, token, &progress_cb)?;
+        self.cached_paths(track_path)
+            .ok_or_else(|| format!("Stems of {track_path} were not written"))
+    }
+
+    /// Splits a track into vocals and instrumental only, the fast mode for karaoke and
+    /// vocal isolation. Cached apart from the four stems; leftovers of an incomplete
+    /// split are separated again.
+    pub fn analyze_vocal_split(
+        &self,
+        track_path: &str,
+        token: &StemAnalysisToken,
+        progress_cb: impl Fn(StemProgress),
+    ) -> Result<VocalSplitPaths, String> {
+        token.check()?;
+        self.prune_stem_cache();
+
+        let dir = self.vocal_split_dir(track_path);
+        if let Some(paths) = self.cached_vocal_split(track_path) {
+            progress_cb(StemProgress {
+                track_id: track_path.to_string(),
+                percent: 1.0,
+                stage: "Cached".to_string(),
+            });
+            return Ok(paths);
+        }
+        // Checkpoints of an interrupted split stay to be resumed.
+        for name in SeparationMode::Two.stem_names() {
+            let _ = std::fs::remove_file(dir.join(format!("{name}.wav")));
+        }
+        self.separate_into(&dir, track_path, SeparationMode::Two, token, &progress_cb)?;
+        Ok(VocalSplitPaths {
+            vocals: dir.join("vocals.wav"),
+            instrumental: dir.join("instrumental.wav"),
+        })
+    }
+
+    /// Steps B and C: writes the stems of `mode` for `track_path` into a new `dir`,
+    /// which is removed again if the run fails or is cancelled.
+    fn separate_into(
+        &self,
+        dir: &Path,
+        track_path: &str,
+        mode: SeparationMode,
+        token: &StemAnalysisToken,
+        progress_cb: &impl Fn(StemProgress),
+    ) -> Result<(), String> {
+        std::fs::create_dir_all(dir)
             .map_err(|e| format!("Failed to create stem cache dir: {e}"))?;
+        if let Err(err) = self.write_stems(dir, track_path, mode, token, progress_cb) {
+            // Stems left from a failed or cancelled run would read as an incomplete cache.
+            let _ = std::fs::remove_dir_all(dir);
+            return Err(err);
+        }
+        progress_cb(StemProgress {
+            track_id: track_path.to_string(),
+            percent: 1.0,
+            stage: "Complete".to_string(),
+        });
+        Ok(())
+    }
 
+    fn write_stems(
+        &self,
+        dir: &Path,
+        track_path: &str,
+        mode: SeparationMode,
+        token: &StemAnalysisToken,
+        progress_cb: &impl Fn(StemProgress),
+    ) -> Result<(), String> {
         progress_cb(StemProgress {
             track_id: track_path.to_string(),
             percent: 0.05,
@@ -201,7 +569,8 @@ impl StemSeparator {
         });
 
         // Step B: Load raw audio (stereo f32 PCM) via symphonia
-        let (samples, sample_rate, channels) = load_audio_f32(track_path)?;
+        let audio = load_audio_f32(track_path)?;
+        token.check()?;
 
         progress_cb(StemProgress {
             track_id: track_path.to_string(),
@@ -209,27 +578,46 @@ impl StemSeparator {
             stage: "Separating stems...".to_string(),
         });
 
-        // Step C: Attempt ONNX model, fallback to center-cancel
-        let stem_buffers = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            self.run_onnx_separation(&samples, sample_rate, channels, |p| {
-                progress_cb(StemProgress {
-                    track_id: track_path.to_string(),
-                    percent: 0.15 + p * 0.7,
-                    stage: "AI processing...".to_string(),
-                });
-            })
-        })) {
-            Ok(Ok(buffers)) => buffers,
-            Ok(Err(_)) | Err(_) => {
+        // Step C: ONNX model when installed, center-cancel otherwise
+        let checkpoints = dir.join(CHECKPOINT_DIR_NAME);
+        let separated = self.run_onnx_separation(&audio, mode, &checkpoints, token, |p| {
+            progress_cb(StemProgress {
+                track_id: track_path.to_string(),
+                percent: 0.15 + p * 0.7,
+                stage: "AI processing...".to_string(),
+            });
+        })?;
+        let stem_buffers = match separated {
+            Some(buffers) => buffers,
+            None => {
                 progress_cb(StemProgress {
                     track_id: track_path.to_string(),
                     percent: 0.2,
                     stage: "Fallback: center cancellation...".to_string(),
                 });
-                center_cancel_fallback(&samples, channels)?
+                let [vocals, drums, bass, other] =
+                    center_cancel_fallback(&audio.samples, audio.channels)?;
+                // Centre cancellation finds no piano or guitar, so six stems fall back to
+                // four.
+                match mode {
+                    SeparationMode::Four | SeparationMode::Six => {
+                        vec![vocals, drums, bass, other]
+                    }
+                    SeparationMode::Two => {
+                        let instrumental = drums
+                            .iter()
+                            .zip(&bass)
+                            .zip(&other)
+                            .map(|((drums, bass), other)| drums + bass + other)
+                            .collect();
+                        vec![vocals, instrumental]
+                    }
+                }
             }
         };
 
+        token.check()?;
+
         progress_cb(StemProgress {
             track_id: track_path.to_string(),
             percent: 0.9,
@@ -237,48 +625,327 @@ impl StemSeparator {
         });
 
         // Write stems as 32-bit float WAV
-        let kinds = StemKind::all();
-        for (i, kind) in kinds.iter().enumerate() {
-            let path = Self::stem_path(&dir, *kind);
-            write_wav_f32(&path, &stem_buffers[i], sample_rate, channels)?;
+        for (name, buffer) in mode.stem_names().iter().zip(&stem_buffers) {
+            token.check()?;
+            let path = dir.join(format!("{name}.wav"));
+            write_wav_f32(&path, buffer, audio.sample_rate, audio.channels)?;
         }
-
-        progress_cb(StemProgress {
-            track_id: track_path.to_string(),
-            percent: 1.0,
-            stage: "Complete".to_string(),
-        });
-
-        Ok(StemPaths {
-            vocals: Self::stem_path(&dir, StemKind::Vocals),
-            drums: Self::stem_path(&dir, StemKind::Drums),
-            bass: Self::stem_path(&dir, StemKind::Bass),
-            other: Self::stem_path(&dir, StemKind::Other),
-        })
+        let _ = std::fs::remove_dir_all(&checkpoints);
+        Ok(())
     }
 
-    /// Attempt to run ONNX-based stem separation.
+    /// Separates the track with the ONNX model, or returns `None` when the model or
+    /// ONNX Runtime is missing so the caller can fall back.
     ///
-    /// This is a structural placeholder: it defines the correct data flow
-    /// (chunk audio → build input tensor → run inference → reassemble)
-    /// but will return Err if no ONNX runtime is available, triggering
-    /// the center-cancellation fallback.
+    /// The track is reduced to its front pair at the model's rate, run through the
+    /// model in overlapping windows, and converted back to its own rate and channel
+    /// count. The residual stem ("other" or "instrumental") absorbs whatever the others
+    /// miss (including any channels past the front pair), so the stems always sum to
+    /// the original.
+    ///
+    /// Every finished window is stored in `checkpoint_dir`, so an analysis cut short by
+    /// a crash or quit picks up after the last one.
     fn run_onnx_separation(
         &self,
-        _samples: &[f32],
-        _sample_rate: u32,
-        _channels: u16,
-        _progress_cb: impl Fn(f32),
-    ) -> Result<[Vec<f32>; 4], String> {
-        // ONNX runtime integration point.
-        // When onnxruntime crate is added:
-        //   1. Load model from cache_dir / "spleeter_4stems.onnx"
-        //   2. SessionBuilder::new()?.with_execution_providers([CUDAExecutionProvider, CPUExecutionProvider])
-        //   3. Chunk input into ~10-20 second windows
-        //   4. Run each chunk, accumulate output tensors
-        //   5. Normalize and return 4 stem buffers
-        Err("ONNX runtime not available – using fallback".to_string())
+        audio: &DecodedTrack,
+        mode: SeparationMode,
+        checkpoint_dir: &Path,
+        token: &StemAnalysisToken,
+        progress_cb: impl Fn(f32),
+    ) -> Result<Option<Vec<Vec<f32>>>, String> {
+        let (samples, sample_rate) = (&audio.samples, audio.sample_rate);
+        let model = self.model_path(mode);
+        if !model.exists() {
+            return Ok(None);
+        }
+        let Some(runtime) = onnx_runtime_library() else {
+            return Ok(None);
+        };
+        let mut session = self.open_model_session(&runtime, &model)?;
+
+        let channels = usize::from(audio.channels.max(1));
+        let stereo: Vec<f32> = samples
+            .chunks_exact(channels)
+            .flat_map(|frame| [frame[0], frame[frame.len().min(2) - 1]])
+            .collect();
+        let input = resample_hq(&stereo, sample_rate, MODEL_SAMPLE_RATE, 2)?;
+        let order = mode.model_order();
+        let checkpoints = ChunkCheckpoints::open(
+            checkpoint_dir.to_path_buf(),
+            &checkpoint_key(&model, &input),
+        )?;
+        let separated = separate_in_windows(
+            &input,
+            order.len(),
+            MODEL_WINDOW_FRAMES,
+            MODEL_OVERLAP_FRAMES,
+            checkpoints.resuming(order.len() * 2 * MODEL_WINDOW_FRAMES, |window| {
+                token.check()?;
+                infer_window(&mut session, window)
+            }),
+            progress_cb,
+        )?;
+
+        let mut stems = vec![Vec::new(); order.len()];
+        for (source, index) in separated.iter().zip(order) {
+            let resampled = resample_hq(source, MODEL_SAMPLE_RATE, sample_rate, 2)?;
+            let mut stem = Vec::with_capacity(samples.len());
+            for frame in resampled.chunks_exact(2) {
+                if channels == 1 {
+                    stem.push(0.5 * (frame[0] + frame[1]));
+                } else {
+                    stem.extend_from_slice(frame);
+                    stem.resize(stem.len() + channels - 2, 0.0);
+                }
+            }
+            stem.resize(samples.len(), 0.0);
+            stems[*index] = stem;
+        }
+        let residual = mode.residual_stem();
+        for (i, sample) in samples.iter().enumerate() {
+            let sum: f32 = stems.iter().map(|stem| stem[i]).sum();
+            stems[residual][i] += sample - sum;
+        }
+        Ok(Some(stems))
+    }
+
+    fn open_model_session(&self, runtime: &Path, model: &Path) -> Result<Session, String> {
+        ort::init_from(runtime.to_string_lossy())
+            .commit()
+            .map_err(|e| format!("Failed to start ONNX Runtime: {e}"))?;
+        let mut builder =
+            Session::builder().map_err(|e| format!("Failed to create ONNX session: {e}"))?;
+        if self.prefer_gpu {
+            // Providers the runtime was not built with are skipped; the CPU always runs.
+            builder = builder
+                .with_execution_providers([
+                    CUDAExecutionProvider::default().build(),
+                    DirectMLExecutionProvider::default().build(),
+                    CoreMLExecutionProvider::default().build(),
+                ])
+                .map_err(|e| format!("Failed to set ONNX execution providers: {e}"))?;
+        }
+        builder
+            .commit_from_file(model)
+            .map_err(|e| format!("Failed to load stem model {}: {e}", model.display()))
+    }
+}
+
+/// Whether an ONNX Runtime library can be loaded for stem separation.
+pub fn onnx_runtime_available() -> bool {
+    onnx_runtime_library().is_some()
+}
+
+/// The ONNX Runtime library: `ORT_DYLIB_PATH` if set, otherwise the platform's library
+/// next to the executable. `ort` panics when it cannot load the library, and release
+/// builds abort on panic, so the library is test-loaded here first.
+fn onnx_runtime_library() -> Option<PathBuf> {
+    let path = match std::env::var_os("ORT_DYLIB_PATH") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => std::env::current_exe()
+            .ok()?
+            .parent()?
+            .join(ONNX_RUNTIME_LIBRARY),
+    };
+    // SAFETY: loading runs ONNX Runtime's initializers, which only set up its own state;
+    // nothing is called before the library is unloaded again.
+    unsafe { libloading::Library::new(&path) }.ok()?;
+    Some(path)
+}
+
+/// Downloads a separation model from `url` to `target`, replacing any earlier model
+/// only once the download is complete.
+pub fn download_model(
+    url: &str,
+    target: &Path,
+    mut on_progress: impl FnMut(DownloadProgress),
+) -> Result<(), String> {
+    let url = url.trim();
+    if !remote_cache::is_remote_url(url) {
+        return Err(format!("Unsupported model URL: {url}"));
+    }
+    let mut response = remote_cache::download_client()?
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    let total_bytes = response.content_length();
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create model dir {}: {e}", dir.display()))?;
+    }
+    let partial = target.with_extension("part");
+    let result = remote_cache::download_to(&mut response, &partial, |downloaded_bytes| {
+        on_progress(DownloadProgress {
+            url: url.to_string(),
+            downloaded_bytes,
+            total_bytes,
+        })
+    });
+    let downloaded = match result {
+        Ok(downloaded) => downloaded,
+        Err(err) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(format!("Failed to download {url}: {err}"));
+        }
+    };
+    network_usage::record(NetworkProvider::Models, downloaded);
+    std::fs::rename(&partial, target)
+        .map_err(|e| format!("Failed to store model {}: {e}", target.display()))
+}
+
+/// Runs `infer` over overlapping windows of interleaved stereo `input` and returns the
+/// interleaved stereo sources. `infer` gets one window as planar `[2, window]`
+/// samples, zero-padded past the end of the track, and returns planar
+/// `[sources, 2, window]`. Every frame is the weighted average of the windows covering it,
+/// with weights ramping across the overlap so the seams do not click.
+fn separate_in_windows(
+    input: &[f32],
+    source_count: usize,
+    window: usize,
+    overlap: usize,
+    mut infer: impl FnMut(Vec<f32>) -> Result<Vec<f32>, String>,
+    progress_cb: impl Fn(f32),
+) -> Result<Vec<Vec<f32>>, String> {
+    let frames = input.len() / 2;
+    let mut sources = vec![vec![0.0_f32; frames * 2]; source_count];
+    if frames == 0 {
+        return Ok(sources);
+    }
+    let overlap = overlap.min(window - 1);
+    let hop = window - overlap;
+    let weights: Vec<f32> = (0..window)
+        .map(|i| ((i.min(window - 1 - i) + 1) as f32 / (overlap + 1) as f32).min(1.0))
+        .collect();
+    let windows = frames.saturating_sub(overlap).div_ceil(hop).max(1);
+    let mut total_weight = vec![0.0_f32; frames];
+
+    for n in 0..windows {
+        let start = n * hop;
+        let len = window.min(frames - start);
+        let mut planar = vec![0.0_f32; 2 * window];
+        for i in 0..len {
+            planar[i] = input[(start + i) * 2];
+            planar[window + i] = input[(start + i) * 2 + 1];
+        }
+        let output = infer(planar)?;
+        if output.len() != source_count * 2 * window {
+            return Err(format!(
+                "Stem model returned {} samples for a window of {window} frames",
+                output.len()
+            ));
+        }
+        for i in 0..len {
+            let weight = weights[i];
+            total_weight[start + i] += weight;
+            for (s, source) in sources.iter_mut().enumerate() {
+                for c in 0..2 {
+                    source[(start + i) * 2 + c] += weight * output[(s * 2 + c) * window + i];
+                }
+            }
+        }
+        progress_cb((n + 1) as f32 / windows as f32);
+    }
+
+    for source in &mut sources {
+        for (frame, weight) in source.chunks_exact_mut(2).zip(&total_weight) {
+            frame[0] /= weight;
+            frame[1] /= weight;
+        }
+    }
+    Ok(sources)
+}
+
+/// Model outputs of finished windows, kept in the stem folder while a track is
+/// separated. They only count for the model and audio they were made from.
+struct ChunkCheckpoints {
+    dir: PathBuf,
+}
+
+impl ChunkCheckpoints {
+    /// Opens the checkpoints in `dir`, dropping any made under another `key`.
+    fn open(dir: PathBuf, key: &str) -> Result<Self, String> {
+        let key_path = dir.join("key");
+        if std::fs::read_to_string(&key_path).ok().as_deref() != Some(key) {
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create stem checkpoint dir: {e}"))?;
+            std::fs::write(&key_path, key)
+                .map_err(|e| format!("Failed to write stem checkpoint key: {e}"))?;
+        }
+        Ok(Self { dir })
     }
+
+    /// The stored output of window `index`, if it has `len` samples.
+    fn load(&self, index: usize, len: usize) -> Option<Vec<f32>> {
+        let bytes = std::fs::read(self.dir.join(format!("{index}.f32"))).ok()?;
+        (bytes.len() == len * 4).then(|| {
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect()
+        })
+    }
+
+    fn store(&self, index: usize, output: &[f32]) -> Result<(), String> {
+        let path = self.dir.join(format!("{index}.f32"));
+        let partial = path.with_extension("part");
+        let bytes: Vec<u8> = output.iter().flat_map(|s| s.to_le_bytes()).collect();
+        std::fs::write(&partial, bytes)
+            .and_then(|()| std::fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to store stem checkpoint: {e}"))
+    }
+
+    /// Wraps the `infer` of `separate_in_windows` so windows with a stored output of
+    /// `len` samples are not run again and new outputs are stored. Windows are numbered
+    /// in the order they are run.
+    fn resuming<'a>(
+        &'a self,
+        len: usize,
+        mut infer: impl FnMut(Vec<f32>) -> Result<Vec<f32>, String> + 'a,
+    ) -> impl FnMut(Vec<f32>) -> Result<Vec<f32>, String> + 'a {
+        let mut next = 0;
+        move |window| {
+            let index = next;
+            next += 1;
+            if let Some(output) = self.load(index, len) {
+                return Ok(output);
+            }
+            let output = infer(window)?;
+            self.store(index, &output)?;
+            Ok(output)
+        }
+    }
+}
+
+/// Identifies the model and the audio fed to it, so checkpoints of an earlier model or
+/// an edited file are not reused.
+fn checkpoint_key(model: &Path, input: &[f32]) -> String {
+    let model_len = model.metadata().map(|m| m.len()).unwrap_or(0);
+    // FNV-1a over the samples: cheap next to inference, and changes with the audio.
+    let hash = input
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, sample| {
+            (hash ^ u64::from(sample.to_bits())).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!(
+        "{} {model_len} {MODEL_WINDOW_FRAMES} {MODEL_OVERLAP_FRAMES} {} {hash:016x}",
+        model.file_name().unwrap_or_default().to_string_lossy(),
+        input.len()
+    )
+}
+
+fn infer_window(session: &mut Session, planar: Vec<f32>) -> Result<Vec<f32>, String> {
+    let frames = planar.len() / 2;
+    let input = Tensor::from_array(([1_usize, 2, frames], planar))
+        .map_err(|e| format!("Failed to build stem model input: {e}"))?;
+    let outputs = session
+        .run(ort::inputs![input])
+        .map_err(|e| format!("Stem model inference failed: {e}"))?;
+    let (_, separated) = outputs[0]
+        .try_extract_tensor::<f32>()
+        .map_err(|e| format!("Failed to read stem model output: {e}"))?;
+    Ok(separated.to_vec())
 }
 
 // ── Fallback: Center Cancellation / Side Extraction ────────────────────
@@ -352,7 +1019,7 @@ fn center_cancel_fallback(samples: &[f32], channels: u16) -> Result<[Vec<f32>; 4
 // ── Audio I/O helpers ──────────────────────────────────────────────────
 
 /// Load an audio file as interleaved f32 samples using symphonia.
-fn load_audio_f32(path: &str) -> Result<(Vec<f32>, u32, u16), String> {
+fn load_audio_f32(path: &str) -> Result<DecodedTrack, String> {
     use symphonia::core::audio::SampleBuffer;
     use symphonia::core::codecs::DecoderOptions;
     use symphonia::core::formats::FormatOptions;
@@ -369,7 +1036,7 @@ fn load_audio_f32(path: &str) -> Result<(Vec<f32>, u32, u16), String> {
         hint.with_extension(ext);
     }
 
-    let probed = symphonia::default::get_probe()
+    let probed = codecs::get_probe()
         .format(
             &hint,
             mss,
@@ -390,7 +1057,7 @@ fn load_audio_f32(path: &str) -> Result<(Vec<f32>, u32, u16), String> {
         .map(|ch| ch.count() as u16)
         .unwrap_or(2);
 
-    let mut decoder = symphonia::default::get_codecs()
+    let mut decoder = codecs::get_codecs()
         .make(&track.codec_params, &DecoderOptions::default())
         .map_err(|e| format!("Failed to create decoder: {e}"))?;
 
@@ -422,7 +1089,11 @@ fn load_audio_f32(path: &str) -> Result<(Vec<f32>, u32, u16), String> {
         all_samples.extend_from_slice(sample_buf.samples());
     }
 
-    Ok((all_samples, sample_rate, channels))
+    Ok(DecodedTrack {
+        sample_rate,
+        channels,
+        samples: all_samples,
+    })
 }
 
 /// Write interleaved f32 samples as a 32-bit float WAV file (minimal implementation).
@@ -472,14 +1143,10 @@ fn write_wav_f32(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::test_support::unique_temp_path;
 
     fn temp_cache_dir() -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should move forward")
-            .as_nanos();
-        let dir = std::env::temp_dir().join(format!("pp-stems-test-{nanos}"));
+        let dir = unique_temp_path("stems-test");
         std::fs::create_dir_all(&dir).expect("create temp dir");
         dir
     }
@@ -512,7 +1179,7 @@ mod tests {
         }
 
         let paths = sep
-            .analyze_spatial_stems(track, |_| {})
+            .analyze_spatial_stems(track, &StemAnalysisToken::register(track), |_| {})
             .expect("must use original track fallback");
         let original = PathBuf::from(track);
         assert_eq!(paths.vocals, original.clone());
@@ -521,6 +1188,98 @@ mod tests {
         assert_eq!(paths.other, original);
     }
 
+    #[test]
+    fn cancelled_analysis_leaves_no_stems_behind() {
+        let cache_dir = temp_cache_dir();
+        let track = cache_dir.join("track.wav");
+        let samples: Vec<f32> = (0..4_800).map(|i| (i as f32 * 0.05).sin()).collect();
+        write_wav_f32(&track, &samples, 48_000, 2).expect("write track");
+        let track = track.to_string_lossy().into_owned();
+        let sep = StemSeparator::new(&cache_dir);
+
+        let token = StemAnalysisToken::register(&track);
+        let result = sep.analyze_spatial_stems(&track, &token, |progress| {
+            if progress.stage.starts_with("Separating") {
+                assert!(cancel_analysis(&progress.track_id));
+            }
+        });
+        assert_eq!(result.unwrap_err(), CANCELLED);
+        assert!(!sep.track_cache_dir(&track).exists());
+        drop(token);
+        assert!(!cancel_analysis(&track));
+
+        let token = StemAnalysisToken::register(&track);
+        sep.analyze_spatial_stems(&track, &token, |_| {})
+            .expect("an uncancelled analysis completes");
+        assert!(sep.is_cached(&track));
+    }
+
+    #[test]
+    fn vocal_split_is_cached_apart_from_four_stems() {
+        let cache_dir = temp_cache_dir();
+        let track = cache_dir.join("track.wav");
+        let samples: Vec<f32> = (0..4_800).map(|i| (i as f32 * 0.05).sin()).collect();
+        write_wav_f32(&track, &samples, 48_000, 2).expect("write track");
+        let track = track.to_string_lossy().into_owned();
+        let sep = StemSeparator::new(&cache_dir);
+
+        let token = StemAnalysisToken::register(&track);
+        let split = sep
+            .analyze_vocal_split(&track, &token, |_| {})
+            .expect("two-stem split");
+        assert!(!sep.is_cached(&track));
+        assert_eq!(sep.cached_vocals(&track), Some(split.vocals.clone()));
+        let vocals = crate::audio::decoder::decode_file(&split.vocals).expect("vocals");
+        let instrumental =
+            crate::audio::decoder::decode_file(&split.instrumental).expect("instrumental");
+        for ((vocal, rest), sample) in vocals
+            .samples
+            .iter()
+            .zip(&instrumental.samples)
+            .zip(&samples)
+        {
+            assert!((vocal + rest - sample).abs() < 1e-5);
+        }
+
+        // Four stems, once separated, are preferred for karaoke.
+        let stems = sep
+            .analyze_spatial_stems(&track, &token, |_| {})
+            .expect("four stems");
+        assert_eq!(sep.cached_vocals(&track), Some(stems.vocals));
+        assert!(sep.cached_vocal_split(&track).is_some());
+    }
+
+    #[test]
+    fn six_stems_fall_back_to_four_without_their_model() {
+        let cache_dir = temp_cache_dir();
+        let track = cache_dir.join("track.wav");
+        let samples: Vec<f32> = (0..4_800).map(|i| (i as f32 * 0.05).sin()).collect();
+        write_wav_f32(&track, &samples, 48_000, 2).expect("write track");
+        let track = track.to_string_lossy().into_owned();
+        let sep = StemSeparator::new(&cache_dir);
+
+        let token = StemAnalysisToken::register(&track);
+        let stems = sep
+            .analyze_six_stems(&track, &token, |_| {})
+            .expect("four-stem fallback");
+        assert!(stems.piano.is_none() && stems.guitar.is_none());
+        assert!(sep.is_cached(&track));
+
+        // A six-stem cache also serves four-stem requests.
+        let dir = sep.track_cache_dir(&track);
+        for kind in [StemKind::Piano, StemKind::Guitar] {
+            write_wav_f32(&StemSeparator::stem_path(&dir, kind), &samples, 48_000, 2)
+                .expect("write stem");
+        }
+        let stems = sep
+            .analyze_spatial_stems(&track, &token, |_| {})
+            .expect("cached six stems");
+        assert_eq!(
+            stems.get(StemKind::Guitar),
+            Some(StemSeparator::stem_path(&dir, StemKind::Guitar).as_path())
+        );
+    }
+
     #[test]
     fn center_cancel_produces_four_stems() {
         // Create a simple stereo signal (L=1.0, R=0.5) × 100 frames
@@ -558,6 +1317,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn windowed_separation_overlap_adds_seamlessly() {
+        // A fake model that scales the window by 0.1, 0.2, 0.3 and 0.4 per source.
+        let frames = 1_000;
+        let input: Vec<f32> = (0..frames * 2).map(|i| (i as f32 * 0.37).sin()).collect();
+        let window = 256;
+        let calls = std::cell::Cell::new(0);
+        let last_progress = std::cell::Cell::new(0.0);
+        let sources = separate_in_windows(
+            &input,
+            4,
+            window,
+            64,
+            |planar| {
+                calls.set(calls.get() + 1);
+                Ok((0..4)
+                    .flat_map(|s| planar.iter().map(move |x| x * (s + 1) as f32 * 0.1))
+                    .collect())
+            },
+            |p| last_progress.set(p),
+        )
+        .expect("separation should succeed");
+        assert_eq!(calls.get(), 5);
+        assert_eq!(last_progress.get(), 1.0);
+        for (s, source) in sources.iter().enumerate() {
+            assert_eq!(source.len(), input.len());
+            for (out, x) in source.iter().zip(&input) {
+                assert!((out - x * (s + 1) as f32 * 0.1).abs() < 1e-5);
+            }
+        }
+
+        let wrong_shape = separate_in_windows(&input, 4, window, 64, Ok, |_| {});
+        assert!(wrong_shape.is_err());
+    }
+
+    #[test]
+    fn interrupted_windows_resume_from_checkpoints() {
+        let dir = temp_cache_dir().join(CHECKPOINT_DIR_NAME);
+        let input: Vec<f32> = (0..2_000).map(|i| (i as f32 * 0.37).sin()).collect();
+        let (window, overlap) = (256, 64);
+        let calls = std::cell::Cell::new(0);
+        let fake_model = |planar: Vec<f32>| {
+            calls.set(calls.get() + 1);
+            Ok::<_, String>([planar.clone(), planar].concat())
+        };
+
+        // The first run dies after three windows.
+        let checkpoints = ChunkCheckpoints::open(dir.clone(), "key").expect("open");
+        let crashed = separate_in_windows(
+            &input,
+            2,
+            window,
+            overlap,
+            checkpoints.resuming(4 * window, |planar| {
+                if calls.get() == 3 {
+                    return Err("crash".to_string());
+                }
+                fake_model(planar)
+            }),
+            |_| {},
+        );
+        assert!(crashed.is_err());
+
+        calls.set(0);
+        let checkpoints = ChunkCheckpoints::open(dir.clone(), "key").expect("reopen");
+        let resumed = separate_in_windows(
+            &input,
+            2,
+            window,
+            overlap,
+            checkpoints.resuming(4 * window, fake_model),
+            |_| {},
+        )
+        .expect("resume");
+        assert_eq!(calls.get(), 2);
+        for (out, x) in resumed[1].iter().zip(&input) {
+            assert!((out - x).abs() < 1e-5);
+        }
+
+        // Checkpoints of other audio are dropped.
+        calls.set(0);
+        let checkpoints = ChunkCheckpoints::open(dir, "other").expect("reopen");
+        separate_in_windows(
+            &input,
+            2,
+            window,
+            overlap,
+            checkpoints.resuming(4 * window, fake_model),
+            |_| {},
+        )
+        .expect("rerun");
+        assert_eq!(calls.get(), 5);
+    }
+
     #[test]
     fn write_and_verify_wav() {
         let dir = temp_cache_dir();
@@ -0,0 +1,107 @@
+use crate::audio::analyzer::{extract_waveform, WAVEFORM_CACHE_POINTS};
+use crate::db::manager::DbManager;
+use crate::library::art_cache::prune_flat_cache_dir;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+const WAVEFORM_CACHE_MAX_FILES: usize = 1024;
+
+/// Full-resolution envelope for `path`: from the `tracks` row for library tracks, from
+/// the file cache otherwise, decoding the file only on a miss.
+pub fn envelope(db: &DbManager, path: &str) -> Result<Vec<f32>, String> {
+    let cached = db
+        .get_waveform_data(path)?
+        .or_else(|| load(Path::new(path)));
+    if let Some(envelope) = cached.filter(|envelope| envelope.len() == WAVEFORM_CACHE_POINTS) {
+        return Ok(envelope);
+    }
+
+    let envelope = extract_waveform(Path::new(path), WAVEFORM_CACHE_POINTS)?;
+    if !db.save_waveform_data(path, &envelope)? {
+        if let Err(err) = store(Path::new(path), &envelope) {
+            eprintln!("Failed to cache waveform for {path}: {err}");
+        }
+    }
+    Ok(envelope)
+}
+
+/// Returns the cached envelope for `track_path`, if the file has not changed since it
+/// was stored. Used for files outside the library, whose waveform lives in `tracks`.
+pub fn load(track_path: &Path) -> Option<Vec<f32>> {
+    let cache_file = cache_file_path(track_path)?;
+    let json = fs::read_to_string(cache_file).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub fn store(track_path: &Path, envelope: &[f32]) -> Result<(), String> {
+    let Some(cache_file) = cache_file_path(track_path) else {
+        return Ok(());
+    };
+    if let Some(cache_dir) = cache_file.parent() {
+        prune_flat_cache_dir(cache_dir, WAVEFORM_CACHE_MAX_FILES);
+    }
+    let json = serde_json::to_string(envelope)
+        .map_err(|e| format!("Failed to serialize waveform cache: {e}"))?;
+    fs::write(&cache_file, json).map_err(|e| {
+        format!(
+            "Failed to write waveform cache {}: {e}",
+            cache_file.display()
+        )
+    })
+}
+
+/// Cache file holding the envelope of `track_path` as it is now, if one was stored.
+pub fn cached_file(track_path: &Path) -> Option<PathBuf> {
+    cache_file_path(track_path).filter(|file| file.is_file())
+}
+
+/// The key includes size and modification time so edited files are re-analysed.
+fn cache_file_path(track_path: &Path) -> Option<PathBuf> {
+    let metadata = fs::metadata(track_path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let mut hash = Sha256::new();
+    hash.update(track_path.to_string_lossy().as_bytes());
+    hash.update(metadata.len().to_le_bytes());
+    hash.update(modified.to_le_bytes());
+    let filename = format!("{:x}.json", hash.finalize());
+
+    let cache_dir = cache_dir();
+    let _ = fs::create_dir_all(&cache_dir);
+    Some(cache_dir.join(filename))
+}
+
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir()
+        .join("powerplayer")
+        .join("waveform_cache")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, store};
+    use crate::test_support::unique_temp_path;
+
+    #[test]
+    fn envelope_roundtrip_is_invalidated_by_file_changes() {
+        let track = unique_temp_path("waveform.wav");
+        std::fs::write(&track, b"original").expect("test file should be created");
+
+        assert_eq!(load(&track), None);
+        store(&track, &[0.25, 1.0]).expect("store should work");
+        assert_eq!(load(&track), Some(vec![0.25, 1.0]));
+
+        std::fs::write(&track, b"re-encoded with a different length").expect("rewrite");
+        assert_eq!(load(&track), None);
+
+        let _ = std::fs::remove_file(track);
+    }
+}
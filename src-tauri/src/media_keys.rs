@@ -0,0 +1,67 @@
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_global_shortcut::{Builder, Code, GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// A hardware media key, registered system-wide so it works while the window is
+/// unfocused or minimized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKey {
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+}
+
+impl MediaKey {
+    const ALL: [MediaKey; 4] = [
+        MediaKey::PlayPause,
+        MediaKey::Next,
+        MediaKey::Previous,
+        MediaKey::Stop,
+    ];
+
+    fn code(self) -> Code {
+        match self {
+            MediaKey::PlayPause => Code::MediaPlayPause,
+            MediaKey::Next => Code::MediaTrackNext,
+            MediaKey::Previous => Code::MediaTrackPrevious,
+            MediaKey::Stop => Code::MediaStop,
+        }
+    }
+
+    fn from_code(code: Code) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.code() == code)
+    }
+}
+
+/// Installs the global shortcut plugin and registers every media key with the OS.
+/// A key another application already holds is skipped rather than failing startup.
+pub fn register(
+    app: &AppHandle,
+    on_key: impl Fn(&AppHandle, MediaKey) + Send + Sync + 'static,
+) -> Result<(), String> {
+    app.plugin(
+        Builder::new()
+            .with_handler(move |app, shortcut, event| {
+                if event.state() != ShortcutState::Pressed {
+                    return;
+                }
+                if let Some(key) = MediaKey::from_code(shortcut.key) {
+                    on_key(app, key);
+                }
+            })
+            .build(),
+    )
+    .map_err(|e| format!("Failed to install the global shortcut plugin: {e}"))?;
+
+    for key in MediaKey::ALL {
+        if let Err(err) = app
+            .global_shortcut()
+            .register(Shortcut::new(None, key.code()))
+        {
+            eprintln!("Media key {key:?} is unavailable: {err}");
+        }
+    }
+    Ok(())
+}
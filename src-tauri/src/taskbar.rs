@@ -0,0 +1,322 @@
+//! Taskbar integration: playback progress on the taskbar button and, on Windows, a
+//! thumbnail toolbar (previous / play-pause / next) shown when hovering the button.
+
+use std::thread;
+use std::time::Duration;
+
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::WebviewWindow;
+
+#[cfg(target_os = "windows")]
+use crate::media_keys::MediaKey;
+
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where playback stands, as sampled for the taskbar.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TaskbarProgress {
+    /// Position through the track, 0 to 1.
+    pub fraction: f64,
+    pub playing: bool,
+}
+
+/// Mirrors `sample` (polled twice a second) onto the taskbar button of `window`; `None`
+/// clears the progress bar. On Windows the thumbnail toolbar's play/pause button follows
+/// the play state too.
+pub fn start_progress_monitor(
+    window: WebviewWindow,
+    sample: impl Fn() -> Option<TaskbarProgress> + Send + 'static,
+) {
+    thread::spawn(move || {
+        let mut shown = None;
+        loop {
+            let progress = sample();
+            let state = progress_bar_state(progress);
+            if shown != Some(state) {
+                let (status, percent) = state;
+                if let Err(err) = window.set_progress_bar(ProgressBarState {
+                    status: Some(status),
+                    progress: percent,
+                }) {
+                    eprintln!("Failed to update taskbar progress: {err}");
+                }
+                #[cfg(target_os = "windows")]
+                thumb_bar::set_playing(&window, progress.is_some_and(|p| p.playing));
+                shown = Some(state);
+            }
+            thread::sleep(PROGRESS_POLL_INTERVAL);
+        }
+    });
+}
+
+/// Taskbar status and whole percent for a playback sample. Paused tracks keep their
+/// progress in the paused (yellow) style.
+fn progress_bar_state(progress: Option<TaskbarProgress>) -> (ProgressBarStatus, Option<u64>) {
+    let Some(progress) = progress else {
+        return (ProgressBarStatus::None, None);
+    };
+    let percent = (progress.fraction.clamp(0.0, 1.0) * 100.0).round() as u64;
+    let status = if progress.playing {
+        ProgressBarStatus::Normal
+    } else {
+        ProgressBarStatus::Paused
+    };
+    (status, Some(percent))
+}
+
+/// Adds previous / play-pause / next buttons to the window's taskbar thumbnail; clicks
+/// are reported as the matching media key.
+#[cfg(target_os = "windows")]
+pub fn add_thumbnail_buttons(
+    window: &WebviewWindow,
+    on_click: impl Fn(MediaKey) + Send + Sync + 'static,
+) -> Result<(), String> {
+    thumb_bar::install(window, Box::new(on_click))
+}
+
+#[cfg(target_os = "windows")]
+mod thumb_bar {
+    use tauri::WebviewWindow;
+    use windows::core::w;
+    use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::Graphics::Gdi::{CreateBitmap, DeleteObject};
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+    use windows::Win32::UI::Shell::{
+        DefSubclassProc, ITaskbarList3, SetWindowSubclass, TaskbarList, THBF_ENABLED, THBN_CLICKED,
+        THB_FLAGS, THB_ICON, THB_TOOLTIP, THUMBBUTTON,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateIconIndirect, PostMessageW, RegisterWindowMessageW, HICON, ICONINFO, WM_APP,
+        WM_COMMAND,
+    };
+
+    use crate::media_keys::MediaKey;
+
+    const SUBCLASS_ID: usize = 0x5050;
+    /// Posted by `set_playing`, since the taskbar interface must be used on the UI thread.
+    const WM_SET_PLAYING: u32 = WM_APP + 0x50;
+    const BUTTON_PREVIOUS: u32 = 0;
+    const BUTTON_PLAY_PAUSE: u32 = 1;
+    const BUTTON_NEXT: u32 = 2;
+    const ICON_SIZE: usize = 16;
+
+    #[derive(Clone, Copy)]
+    enum Glyph {
+        Previous,
+        Play,
+        Pause,
+        Next,
+    }
+
+    /// Lives for the life of the window; its address is the subclass reference data.
+    struct ThumbBar {
+        taskbar: ITaskbarList3,
+        on_click: Box<dyn Fn(MediaKey) + Send + Sync>,
+        taskbar_created: u32,
+        icons: [HICON; 4],
+        playing: bool,
+    }
+
+    pub fn install(
+        window: &WebviewWindow,
+        on_click: Box<dyn Fn(MediaKey) + Send + Sync>,
+    ) -> Result<(), String> {
+        let hwnd = window_handle(window)?;
+        // SAFETY: called on the UI thread during setup, where COM is already initialized
+        // by the webview; the state is leaked so the subclass can use it until exit.
+        unsafe {
+            let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)
+                .map_err(|e| format!("Failed to create the taskbar interface: {e}"))?;
+            taskbar
+                .HrInit()
+                .map_err(|e| format!("Failed to initialize the taskbar interface: {e}"))?;
+            let icons = [
+                create_icon(Glyph::Previous)?,
+                create_icon(Glyph::Play)?,
+                create_icon(Glyph::Pause)?,
+                create_icon(Glyph::Next)?,
+            ];
+            let state = Box::into_raw(Box::new(ThumbBar {
+                taskbar,
+                on_click,
+                taskbar_created: RegisterWindowMessageW(w!("TaskbarButtonCreated")),
+                icons,
+                playing: false,
+            }));
+            if !SetWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID, state as usize).as_bool()
+            {
+                drop(Box::from_raw(state));
+                return Err("Failed to subclass the main window".to_string());
+            }
+            // The taskbar button usually exists already; if not, `TaskbarButtonCreated`
+            // adds the buttons once it does (and again after Explorer restarts).
+            let _ = add_buttons(&*state, hwnd);
+        }
+        Ok(())
+    }
+
+    /// Switches the middle button between play and pause.
+    pub fn set_playing(window: &WebviewWindow, playing: bool) {
+        if let Ok(hwnd) = window_handle(window) {
+            // SAFETY: posting a message has no memory-safety requirements.
+            let _ =
+                unsafe { PostMessageW(hwnd, WM_SET_PLAYING, WPARAM(playing as usize), LPARAM(0)) };
+        }
+    }
+
+    fn window_handle(window: &WebviewWindow) -> Result<HWND, String> {
+        let hwnd = window
+            .hwnd()
+            .map_err(|e| format!("Failed to read the window handle: {e}"))?;
+        Ok(HWND(hwnd.0 as _))
+    }
+
+    unsafe extern "system" fn subclass_proc(
+        hwnd: HWND,
+        message: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+        _subclass_id: usize,
+        reference: usize,
+    ) -> LRESULT {
+        let state = &mut *(reference as *mut ThumbBar);
+        if message == state.taskbar_created {
+            if let Err(err) = add_buttons(state, hwnd) {
+                eprintln!("Failed to add thumbnail toolbar buttons: {err}");
+            }
+        } else if message == WM_SET_PLAYING {
+            state.playing = wparam.0 != 0;
+            let _ = state
+                .taskbar
+                .ThumbBarUpdateButtons(hwnd, &[play_pause_button(state)]);
+            return LRESULT(0);
+        } else if message == WM_COMMAND && (wparam.0 >> 16) as u32 == THBN_CLICKED {
+            let key = match (wparam.0 & 0xFFFF) as u32 {
+                BUTTON_PREVIOUS => Some(MediaKey::Previous),
+                BUTTON_PLAY_PAUSE => Some(MediaKey::PlayPause),
+                BUTTON_NEXT => Some(MediaKey::Next),
+                _ => None,
+            };
+            if let Some(key) = key {
+                (state.on_click)(key);
+                return LRESULT(0);
+            }
+        }
+        DefSubclassProc(hwnd, message, wparam, lparam)
+    }
+
+    unsafe fn add_buttons(state: &ThumbBar, hwnd: HWND) -> windows::core::Result<()> {
+        state.taskbar.ThumbBarAddButtons(
+            hwnd,
+            &[
+                button(BUTTON_PREVIOUS, state.icons[0], "Previous"),
+                play_pause_button(state),
+                button(BUTTON_NEXT, state.icons[3], "Next"),
+            ],
+        )
+    }
+
+    fn play_pause_button(state: &ThumbBar) -> THUMBBUTTON {
+        if state.playing {
+            button(BUTTON_PLAY_PAUSE, state.icons[2], "Pause")
+        } else {
+            button(BUTTON_PLAY_PAUSE, state.icons[1], "Play")
+        }
+    }
+
+    fn button(id: u32, icon: HICON, tooltip: &str) -> THUMBBUTTON {
+        let mut tip = [0_u16; 260];
+        for (slot, unit) in tip.iter_mut().zip(tooltip.encode_utf16()) {
+            *slot = unit;
+        }
+        THUMBBUTTON {
+            dwMask: THB_ICON | THB_TOOLTIP | THB_FLAGS,
+            iId: id,
+            iBitmap: 0,
+            hIcon: icon,
+            szTip: tip,
+            dwFlags: THBF_ENABLED,
+        }
+    }
+
+    /// Draws a white transport glyph into a 16×16 32-bit icon.
+    unsafe fn create_icon(glyph: Glyph) -> Result<HICON, String> {
+        let mut pixels = [0_u32; ICON_SIZE * ICON_SIZE];
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                if covers(glyph, x as i32, y as i32) {
+                    pixels[y * ICON_SIZE + x] = 0xFFFF_FFFF;
+                }
+            }
+        }
+        let mask = [0_u8; ICON_SIZE * ICON_SIZE / 8];
+        let color = CreateBitmap(
+            ICON_SIZE as i32,
+            ICON_SIZE as i32,
+            1,
+            32,
+            Some(pixels.as_ptr().cast()),
+        );
+        let mask = CreateBitmap(
+            ICON_SIZE as i32,
+            ICON_SIZE as i32,
+            1,
+            1,
+            Some(mask.as_ptr().cast()),
+        );
+        let icon = CreateIconIndirect(&ICONINFO {
+            fIcon: true.into(),
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        });
+        // The icon keeps its own copies of the bitmaps.
+        let _ = DeleteObject(color);
+        let _ = DeleteObject(mask);
+        icon.map_err(|e| format!("Failed to create a thumbnail toolbar icon: {e}"))
+    }
+
+    /// Whether pixel (`x`, `y`) is part of `glyph`, drawn within a 3..13 box.
+    fn covers(glyph: Glyph, x: i32, y: i32) -> bool {
+        if !(3..13).contains(&y) {
+            return false;
+        }
+        // Half-height of a right-pointing triangle at column `x` starting at `left`.
+        let triangle = |left: i32, width: i32, flip: bool| {
+            let column = if flip { left + width - 1 - x } else { x - left };
+            (0..width).contains(&column) && (y - 8).abs() * width <= (width - column) * 5
+        };
+        match glyph {
+            Glyph::Play => triangle(4, 9, false),
+            Glyph::Pause => (4..7).contains(&x) || (9..12).contains(&x),
+            Glyph::Previous => (3..5).contains(&x) || triangle(5, 8, true),
+            Glyph::Next => (11..13).contains(&x) || triangle(3, 8, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{progress_bar_state, TaskbarProgress};
+    use tauri::window::ProgressBarStatus;
+
+    #[test]
+    fn maps_playback_to_taskbar_progress() {
+        assert_eq!(progress_bar_state(None), (ProgressBarStatus::None, None));
+        assert_eq!(
+            progress_bar_state(Some(TaskbarProgress {
+                fraction: 0.426,
+                playing: true,
+            })),
+            (ProgressBarStatus::Normal, Some(43))
+        );
+        assert_eq!(
+            progress_bar_state(Some(TaskbarProgress {
+                fraction: 1.7,
+                playing: false,
+            })),
+            (ProgressBarStatus::Paused, Some(100))
+        );
+    }
+}
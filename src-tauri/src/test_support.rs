@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A path in the temp directory that no other test uses, ending in `name` (which may
+/// carry an extension). Nothing is created there.
+pub fn unique_temp_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time should move forward")
+        .as_nanos();
+    let count = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("powerplayer-{nanos}-{count}-{name}"))
+}
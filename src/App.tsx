@@ -131,17 +131,18 @@ function App() {
     });
   }, [currentTime, duration, invokeSafe]);
   const handleSkipBack = useCallback(() => {
-    const prev = Math.max(0, currentTime - 10);
-    setCurrentTime(prev);
-    void invokeSafe("seek", { seconds: prev }).catch((error) => {
-      console.error("Failed to seek backward", error);
-    });
-  }, [currentTime, invokeSafe]);
+    void invokeSafe<{ position_seconds: number }>("instant_replay", { seconds: 10 })
+      .then(({ position_seconds }) => setCurrentTime(position_seconds))
+      .catch((error) => {
+        console.error("Failed to seek backward", error);
+      });
+  }, [invokeSafe]);
 
   const handleOpenTrack = useCallback(async () => {
     try {
+      const extensions = await invokeSafe<string[]>("get_supported_extensions");
       const selected = await open({
-        filters: [{ name: "Audio", extensions: ["flac", "mp3", "wav"] }],
+        filters: [{ name: "Audio", extensions }],
         multiple: false,
       });
       if (!selected || Array.isArray(selected)) return;
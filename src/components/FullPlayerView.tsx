@@ -124,7 +124,7 @@ function FullPlayerView({
 
     setWaveformData(null);
     setIsWaveformLoading(true);
-    void invokeSafe<number[]>("extract_waveform", { path: activeTrackPath, points: WAVEFORM_BARS })
+    void invokeSafe<number[]>("get_track_waveform", { path: activeTrackPath, points: WAVEFORM_BARS })
       .then((data) => {
         if (!cancelled && Array.isArray(data) && data.length) {
           // Defensive clamp in case stale/corrupt cache data falls outside normalized range.
@@ -13,6 +13,7 @@ interface LibraryTrack {
   sample_rate: number | null;
   art_url: string | null;
   corrupted: boolean;
+  bpm: number | null;
 }
 
 type Tab = "all" | "albums" | "artists" | "genres";